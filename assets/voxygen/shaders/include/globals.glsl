@@ -24,7 +24,8 @@ layout(std140, set = 0, binding = 0) uniform u_globals {
     // 1 - ThirdPerson
     uint cam_mode;
     float sprite_render_distance;
-    float globals_dummy; // Fix alignment.
+    // Floor of the terrain light underground, in the range 0 - 0.15
+    float cave_ambient_light;
 };
 
 // Specifies the pattern used in the player dithering
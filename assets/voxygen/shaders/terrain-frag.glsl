@@ -416,6 +416,10 @@ void main() {
     // vec3 col = srgb_to_linear(linear_to_srgb(f_col) + noise * 0.02);
     // vec3 col = /*srgb_to_linear*/(f_col + noise); // Small-scale noise
     // vec3 col = /*srgb_to_linear*/(f_col + hash(vec4(floor(f_pos * 3.0 - f_norm * 0.5), 0)) * 0.01); // Small-scale noise
+    // Keep the shapes of unlit caves visible
+    emitted_light = max(emitted_light, vec3(cave_ambient_light));
+    reflected_light = max(reflected_light, vec3(cave_ambient_light));
+    max_light = max(max_light, cave_ambient_light);
     vec3 surf_color = illuminate(max_light, view_dir, col * emitted_light, col * reflected_light);
 
     float f_select = (select_pos.w > 0 && select_pos.xyz == floor(f_pos - f_norm * 0.5)) ? 1.0 : 0.0;
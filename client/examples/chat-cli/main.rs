@@ -47,6 +47,7 @@ fn main() {
     let addr = ConnectionArgs::Tcp {
         prefer_ipv6: false,
         hostname: server_addr,
+        encryption: None,
     };
 
     // Create a client.
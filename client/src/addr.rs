@@ -1,3 +1,4 @@
+pub use network::{tls::CertFingerprint, NoiseKeys, NoisePublicKey};
 use std::net::SocketAddr;
 use tokio::net::lookup_host;
 use tracing::trace;
@@ -13,6 +14,18 @@ pub enum ConnectionArgs {
     Tcp {
         hostname: String,
         prefer_ipv6: bool,
+        /// Encrypts the channel, servers might require it. Pin the public key
+        /// the server admin published with [`NoiseKeys::with_remote_key`].
+        encryption: Option<NoiseKeys>,
+    },
+    ///hostname: (hostname|ip):[<port>]
+    TcpTls {
+        hostname: String,
+        prefer_ipv6: bool,
+        /// Trusts only the certificate with this fingerprint, for servers with
+        /// a self-signed certificate. Without it the certificate has to be
+        /// signed by a root of the system and issued for the hostname.
+        fingerprint: Option<CertFingerprint>,
     },
     Mpsc(u64),
 }
@@ -48,6 +61,20 @@ pub(crate) async fn resolve(
     }
 }
 
+/// Strips the port and the brackets around an ipv6 address, leaving the name
+/// a Tls certificate is issued for.
+pub(crate) fn server_name(address: &str) -> &str {
+    if let Some(bracketed) = address.strip_prefix('[') {
+        bracketed.split(']').next().unwrap_or(bracketed)
+    } else {
+        match address.rsplit_once(':') {
+            // more than one colon is an ipv6 address without port
+            Some((host, _)) if !host.contains(':') => host,
+            _ => address,
+        }
+    }
+}
+
 pub(crate) async fn try_connect<F>(
     network: &network::Network,
     address: &str,
@@ -99,6 +126,15 @@ mod tests {
         assert_eq!(args[0].port(), 666);
     }
 
+    #[test]
+    fn strip_server_name() {
+        assert_eq!(server_name("example.com"), "example.com");
+        assert_eq!(server_name("example.com:14004"), "example.com");
+        assert_eq!(server_name("127.0.0.1:776"), "127.0.0.1");
+        assert_eq!(server_name("::1"), "::1");
+        assert_eq!(server_name("[::1]:14004"), "::1");
+    }
+
     #[tokio::test]
     async fn resolve_ipv6() {
         let args = resolve("localhost", true).await.expect("resolve failed");
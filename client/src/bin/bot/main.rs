@@ -56,6 +56,7 @@ pub fn make_client(runtime: &Arc<Runtime>, server: &str) -> Client {
     let addr = ConnectionArgs::Tcp {
         prefer_ipv6: false,
         hostname: server.to_owned(),
+        encryption: None,
     };
     runtime
         .block_on(Client::new(addr, runtime_clone, &mut None))
@@ -0,0 +1,295 @@
+//! Spins up bots that connect like regular players, spawn a character and
+//! follow a [`Scenario`], to put a server under load. The bots only report
+//! their own connection stats, the load results come from the metrics endpoint
+//! of the server.
+
+mod scenario;
+
+use common::{
+    clock::Clock,
+    comp::{self, ControllerInputs, InputKind},
+    util::Dir,
+};
+use scenario::Scenario;
+use std::{
+    fmt,
+    path::PathBuf,
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
+use structopt::StructOpt;
+use tokio::runtime::Runtime;
+use tracing::{error, info};
+use vek::*;
+use veloren_client::{addr::ConnectionArgs, Client, Event};
+
+/// Bots head to the next waypoint once they are that close to the current one
+const WAYPOINT_RADIUS: f32 = 2.0;
+/// How often each bot logs its stats
+const STATS_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(StructOpt)]
+struct Opt {
+    /// RON file describing what the bots do
+    scenario: PathBuf,
+    /// Number of bots to spin up
+    #[structopt(short, long, default_value = "1")]
+    bots: u32,
+    /// Seconds between two bots connecting
+    #[structopt(short, long, default_value = "1.0")]
+    stagger: f32,
+    /// The bots are named after it, followed by their index
+    #[structopt(long, default_value = "loadtest")]
+    prefix: String,
+    /// Seconds after which the bots log out, they run until they are killed
+    /// otherwise
+    #[structopt(short, long)]
+    duration: Option<f32>,
+}
+
+#[derive(Default)]
+struct BotStats {
+    /// Time it took to connect and register
+    connected_after: Option<Duration>,
+    /// Time it took to get in game
+    spawned_after: Option<Duration>,
+    ticks: u64,
+    attacks: u32,
+    chat_messages: u32,
+    deaths: u32,
+    ping_ms: f64,
+    /// Why the bot stopped before the end of the test
+    error: Option<String>,
+}
+
+impl fmt::Display for BotStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let secs =
+            |d: Option<Duration>| d.map_or("-".to_owned(), |d| format!("{:.2}s", d.as_secs_f32()));
+        write!(
+            f,
+            "connected: {}, spawned: {}, ticks: {}, attacks: {}, chat messages: {}, deaths: {}, \
+             ping: {:.0}ms",
+            secs(self.connected_after),
+            secs(self.spawned_after),
+            self.ticks,
+            self.attacks,
+            self.chat_messages,
+            self.deaths,
+            self.ping_ms,
+        )?;
+        if let Some(error) = &self.error {
+            write!(f, ", stopped: {}", error)?;
+        }
+        Ok(())
+    }
+}
+
+fn main() {
+    let opt = Opt::from_args();
+    common_frontend::init_stdout(None);
+
+    let scenario = match Scenario::load(&opt.scenario) {
+        Ok(scenario) => Arc::new(scenario),
+        Err(e) => {
+            error!(?e, "Failed to load scenario {:?}", opt.scenario);
+            return;
+        },
+    };
+    let runtime = Arc::new(Runtime::new().unwrap());
+    let end = opt
+        .duration
+        .map(|duration| Instant::now() + Duration::from_secs_f32(duration));
+
+    let bots = (0..opt.bots)
+        .map(|index| {
+            if index > 0 {
+                thread::sleep(Duration::from_secs_f32(opt.stagger));
+            }
+            let name = format!("{}{}", opt.prefix, index);
+            let scenario = Arc::clone(&scenario);
+            let runtime = Arc::clone(&runtime);
+            let handle = thread::spawn({
+                let name = name.clone();
+                move || run_bot(name, &scenario, runtime, end)
+            });
+            (name, handle)
+        })
+        .collect::<Vec<_>>();
+    info!("All {} bots connecting", opt.bots);
+
+    for (name, handle) in bots {
+        match handle.join() {
+            Ok(stats) => println!("{}: {}", name, stats),
+            Err(_) => println!("{}: panicked", name),
+        }
+    }
+}
+
+fn run_bot(
+    name: String,
+    scenario: &Scenario,
+    runtime: Arc<Runtime>,
+    end: Option<Instant>,
+) -> BotStats {
+    let mut stats = BotStats::default();
+    if let Err(e) = play(&name, scenario, runtime, end, &mut stats) {
+        error!(?e, "Bot {} failed", name);
+        stats.error = Some(format!("{:?}", e));
+    }
+    stats
+}
+
+fn play(
+    name: &str,
+    scenario: &Scenario,
+    runtime: Arc<Runtime>,
+    end: Option<Instant>,
+    stats: &mut BotStats,
+) -> Result<(), veloren_client::Error> {
+    let start = Instant::now();
+    let addr = ConnectionArgs::Tcp {
+        prefer_ipv6: false,
+        hostname: scenario.server.clone(),
+        encryption: None,
+    };
+    let mut client = runtime.block_on(Client::new(addr, Arc::clone(&runtime), &mut None))?;
+    runtime.block_on(
+        client.register(name.to_owned(), scenario.password.clone(), |provider| {
+            scenario.trusted_auth_servers.iter().any(|p| p == provider)
+        }),
+    )?;
+    stats.connected_after = Some(start.elapsed());
+
+    let mut clock = Clock::new(Duration::from_secs_f64(1.0 / scenario.tps));
+
+    // Use the first character, create it if there is none
+    client.load_character_list();
+    while client.character_list().loading {
+        client.tick(ControllerInputs::default(), clock.dt(), |_| {})?;
+        client.cleanup();
+        clock.tick();
+    }
+    if client.character_list().characters.is_empty() {
+        client.create_character(
+            name.to_owned(),
+            Some("common.items.weapons.sword.starter".into()),
+            None,
+            body(),
+        );
+        client.load_character_list();
+        while client.character_list().loading || client.character_list().characters.is_empty() {
+            client.tick(ControllerInputs::default(), clock.dt(), |_| {})?;
+            client.cleanup();
+            clock.tick();
+        }
+    }
+    if let Some(id) = client.character_list().characters[0].character.id {
+        client.request_character(id);
+    }
+    while client.position().is_none() {
+        client.tick(ControllerInputs::default(), clock.dt(), |_| {})?;
+        client.cleanup();
+        clock.tick();
+    }
+    stats.spawned_after = Some(start.elapsed());
+    info!("Bot {} spawned", name);
+
+    let origin = client.position().unwrap_or_default().xy();
+    let mut waypoint = 0;
+    let mut next_attack = scenario.attack_interval;
+    let mut next_chat = scenario.chat_interval;
+    let mut attacking = false;
+    let mut was_dead = false;
+    let mut last_stats = Instant::now();
+    let in_game = Instant::now();
+    loop {
+        if end.map_or(false, |end| Instant::now() >= end) {
+            client.logout();
+            break;
+        }
+        let elapsed = in_game.elapsed().as_secs_f32();
+        let mut inputs = ControllerInputs::default();
+
+        let is_dead = client.is_dead();
+        if is_dead {
+            if !was_dead {
+                stats.deaths += 1;
+            }
+            client.respawn();
+        } else if let (Some(pos), Some(target)) = (client.position(), scenario.patrol.get(waypoint))
+        {
+            let to_target = origin + Vec2::from(*target) - pos.xy();
+            if to_target.magnitude_squared() < WAYPOINT_RADIUS.powi(2) {
+                waypoint = (waypoint + 1) % scenario.patrol.len();
+            } else {
+                inputs.move_dir = to_target.normalized();
+                inputs.look_dir = Dir::from_unnormalized(Vec3::from(to_target)).unwrap_or_default();
+            }
+        }
+        was_dead = is_dead;
+
+        // Attacks are held for a single tick
+        if attacking {
+            client.handle_input(InputKind::Primary, false, None, None);
+            attacking = false;
+        }
+        if let (Some(at), Some(interval)) = (next_attack, scenario.attack_interval) {
+            if elapsed >= at && !is_dead {
+                client.handle_input(InputKind::Primary, true, None, None);
+                attacking = true;
+                stats.attacks += 1;
+                next_attack = Some(at + interval);
+            }
+        }
+        if let (Some(at), Some(interval)) = (next_chat, scenario.chat_interval) {
+            if elapsed >= at {
+                if let Some(message) = scenario.chat_message(stats.chat_messages) {
+                    client.send_chat(message.clone());
+                    stats.chat_messages += 1;
+                }
+                next_chat = Some(at + interval);
+            }
+        }
+
+        for event in client.tick(inputs, clock.dt(), |_| {})? {
+            match event {
+                Event::Disconnect => {
+                    stats.error = Some("disconnected".to_owned());
+                    return Ok(());
+                },
+                Event::Kicked { reason, .. } => {
+                    stats.error = Some(format!("kicked: {}", reason));
+                    return Ok(());
+                },
+                _ => {},
+            }
+        }
+        client.cleanup();
+        stats.ticks += 1;
+        stats.ping_ms = client.get_ping_ms_rolling_avg();
+
+        if last_stats.elapsed() >= STATS_INTERVAL {
+            last_stats = Instant::now();
+            info!("Bot {}: {}", name, stats);
+        }
+        clock.tick();
+    }
+    Ok(())
+}
+
+fn body() -> comp::Body {
+    comp::body::humanoid::Body {
+        species: comp::body::humanoid::Species::Human,
+        body_type: comp::body::humanoid::BodyType::Female,
+        hair_style: 0,
+        beard: 0,
+        eyes: 0,
+        accessory: 0,
+        hair_color: 0,
+        skin: 0,
+        eye_color: 0,
+    }
+    .into()
+}
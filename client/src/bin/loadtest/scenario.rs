@@ -0,0 +1,63 @@
+use serde::Deserialize;
+use std::{error::Error, fs, path::Path};
+
+/// What the bots do once they are in game, loaded from a RON file:
+///
+/// ```ron
+/// (
+///     server: "localhost",
+///     patrol: [(0.0, 0.0), (30.0, 0.0), (30.0, 30.0), (0.0, 30.0)],
+///     attack_interval: Some(5.0),
+///     chat_interval: Some(60.0),
+///     chat_messages: ["Hello!", "Is anyone around?"],
+/// )
+/// ```
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct Scenario {
+    pub server: String,
+    /// Auth servers the bots log in with, leave empty for a server without
+    /// auth
+    pub trusted_auth_servers: Vec<String>,
+    /// Password of all the bots, only used with auth
+    pub password: String,
+    /// Waypoints the bots walk between in a loop, in blocks relative to where
+    /// they spawned
+    pub patrol: Vec<(f32, f32)>,
+    /// Seconds between two attacks, `None` for bots that never attack
+    pub attack_interval: Option<f32>,
+    /// Seconds between two chat messages, `None` for silent bots
+    pub chat_interval: Option<f32>,
+    /// Messages the bots say in turn
+    pub chat_messages: Vec<String>,
+    /// Ticks per second of each bot
+    pub tps: f64,
+}
+
+impl Default for Scenario {
+    fn default() -> Self {
+        Self {
+            server: "localhost".to_owned(),
+            trusted_auth_servers: Vec::new(),
+            password: String::new(),
+            patrol: Vec::new(),
+            attack_interval: None,
+            chat_interval: None,
+            chat_messages: Vec::new(),
+            tps: 30.0,
+        }
+    }
+}
+
+impl Scenario {
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let ron = fs::read_to_string(path)?;
+        Ok(ron::de::from_str(&ron)?)
+    }
+
+    /// Chat message number `n`, `None` if there are none to say
+    pub fn chat_message(&self, n: u32) -> Option<&String> {
+        (!self.chat_messages.is_empty())
+            .then(|| &self.chat_messages[n as usize % self.chat_messages.len()])
+    }
+}
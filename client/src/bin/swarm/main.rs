@@ -106,6 +106,7 @@ fn run_client(
     let addr = ConnectionArgs::Tcp {
         prefer_ipv6: false,
         hostname: "localhost".into(),
+        encryption: None,
     };
     let runtime_clone = Arc::clone(&runtime);
     let mut client = runtime
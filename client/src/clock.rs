@@ -0,0 +1,88 @@
+use common::resources::GameTime;
+
+/// Errors larger than this are corrected at once instead of slowly, in seconds
+const MAX_SLEW: f64 = 1.0;
+/// How fast small errors are corrected, in seconds per second
+const SLEW_RATE: f64 = 0.1;
+
+/// Estimate of the server's [`GameTime`], kept as an offset to the local time.
+///
+/// Samples from the server are late by half the round trip time, which
+/// changes all the time. Jumping to every new sample would make cooldowns
+/// flicker, so small errors are slewed away and the estimate never runs
+/// backwards.
+#[derive(Debug, Default)]
+pub struct ServerClock {
+    offset: Option<f64>,
+    target_offset: f64,
+}
+
+impl ServerClock {
+    /// Takes a sample of the server time that was received at `local_time`,
+    /// `rtt` is the current round trip time in seconds
+    pub fn sample(&mut self, server_time: GameTime, rtt: f64, local_time: f64) {
+        self.target_offset = server_time.0 + rtt / 2.0 - local_time;
+        match self.offset {
+            Some(offset) if (self.target_offset - offset).abs() <= MAX_SLEW => {},
+            _ => self.offset = Some(self.target_offset),
+        }
+    }
+
+    /// Moves the estimate towards the last sample, `dt` is the local time that
+    /// passed since the last call
+    pub fn advance(&mut self, dt: f64) {
+        if let Some(offset) = &mut self.offset {
+            let error = self.target_offset - *offset;
+            // Never slew back faster than time passes, so the clock stays monotonic
+            let max_step = (SLEW_RATE * dt).min(dt);
+            *offset += error.clamp(-max_step, max_step);
+        }
+    }
+
+    /// Server time at `local_time`, `None` until the first sample arrived
+    pub fn game_time(&self, local_time: f64) -> Option<GameTime> {
+        self.offset.map(|offset| GameTime(local_time + offset))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_sample_sets_the_time() {
+        let mut clock = ServerClock::default();
+        assert_eq!(clock.game_time(5.0), None);
+        clock.sample(GameTime(100.0), 0.2, 5.0);
+        assert_eq!(clock.game_time(5.0), Some(GameTime(100.1)));
+    }
+
+    #[test]
+    fn rtt_changes_are_slewed() {
+        let mut clock = ServerClock::default();
+        clock.sample(GameTime(100.0), 0.0, 0.0);
+
+        // The ping went up, the new sample claims the server is 0.5s ahead
+        clock.sample(GameTime(101.0), 1.0, 0.5);
+        let mut last = clock.game_time(0.5).unwrap().0;
+        for i in 1..=100 {
+            let dt = 0.1;
+            clock.advance(dt);
+            let now = clock.game_time(0.5 + i as f64 * dt).unwrap().0;
+            assert!(now >= last);
+            assert!(now - last <= dt * (1.0 + SLEW_RATE) + 1e-9);
+            last = now;
+        }
+        // After 10 seconds the error is gone
+        assert!((clock.game_time(10.5).unwrap().0 - 111.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn large_errors_are_corrected_at_once() {
+        let mut clock = ServerClock::default();
+        clock.sample(GameTime(100.0), 0.0, 0.0);
+        // Time was set back on the server
+        clock.sample(GameTime(10.0), 0.0, 1.0);
+        assert_eq!(clock.game_time(1.0), Some(GameTime(10.0)));
+    }
+}
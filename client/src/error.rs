@@ -26,6 +26,20 @@ pub enum Error {
     SpecsErr(SpecsError),
 }
 
+impl Error {
+    /// Whether the connection to the server dropped, as opposed to the server
+    /// refusing us or ending the session on purpose
+    pub fn is_connection_lost(&self) -> bool {
+        matches!(
+            self,
+            Self::NetworkErr(_)
+                | Self::ParticipantErr(_)
+                | Self::StreamErr(_)
+                | Self::ServerTimeout
+        )
+    }
+}
+
 impl From<SpecsError> for Error {
     fn from(err: SpecsError) -> Self { Self::SpecsErr(err) }
 }
@@ -3,6 +3,7 @@
 #![feature(label_break_value, option_zip)]
 
 pub mod addr;
+mod clock;
 pub mod cmd;
 pub mod error;
 
@@ -16,7 +17,7 @@ pub use specs::{
     Builder, DispatcherBuilder, Entity as EcsEntity, ReadStorage, World, WorldExt,
 };
 
-use crate::addr::ConnectionArgs;
+use crate::{addr::ConnectionArgs, clock::ServerClock};
 use byteorder::{ByteOrder, LittleEndian};
 use common::{
     character::{CharacterId, CharacterItem},
@@ -28,6 +29,7 @@ use common::{
         invite::{InviteKind, InviteResponse},
         skills::Skill,
         slot::{EquipSlot, InvSlotId, Slot},
+        social::{Guild, GuildRank},
         CharacterState, ChatMode, ControlAction, ControlEvent, Controller, ControllerInputs,
         GroupManip, InputKind, InventoryAction, InventoryEvent, InventoryUpdateEvent,
         MapMarkerChange, UtteranceKind,
@@ -38,7 +40,7 @@ use common::{
     mounting::Rider,
     outcome::Outcome,
     recipe::RecipeBook,
-    resources::{PlayerEntity, TimeOfDay},
+    resources::{GameTime, PlayerEntity, TimeOfDay},
     terrain::{
         block::Block, map::MapConfig, neighbors, BiomeKind, SitesKind, SpriteKind, TerrainChunk,
         TerrainChunkSize,
@@ -50,21 +52,21 @@ use common::{
 use common_base::{prof_span, span};
 use common_net::{
     msg::{
-        self, validate_chat_msg,
+        self, rules_hash, validate_chat_msg,
         world_msg::{EconomyInfo, PoiInfo, SiteId, SiteInfo},
         ChatMsgValidationError, ClientGeneral, ClientMsg, ClientRegister, ClientType,
-        DisconnectReason, InviteAnswer, Notification, PingMsg, PlayerInfo, PlayerListUpdate,
-        PresenceKind, RegisterError, ServerGeneral, ServerInit, ServerRegisterAnswer,
-        MAX_BYTES_CHAT_MSG,
+        ConnectionStats, DisconnectReason, FishingState, InviteAnswer, MessageBatch, Notification,
+        PingClock, PingMsg, PlayerInfo, PlayerListUpdate, PresenceKind, RegisterError,
+        ServerGeneral, ServerInit, ServerRegisterAnswer, WorldEventKind, MAX_BYTES_CHAT_MSG,
     },
-    sync::WorldSyncExt,
+    sync::{PhysicsBaselines, WorldSyncExt},
 };
 use common_state::State;
 use common_systems::add_local_systems;
 use comp::BuffKind;
 use hashbrown::{HashMap, HashSet};
 use image::DynamicImage;
-use network::{ConnectAddr, Network, Participant, Pid, Stream};
+use network::{ConnectAddr, Network, Participant, Pid, Stream, StreamError};
 use num::traits::FloatConst;
 use rayon::prelude::*;
 use specs::Component;
@@ -105,7 +107,12 @@ pub enum Event {
     Disconnect,
     DisconnectionNotification(u64),
     InventoryUpdated(InventoryUpdateEvent),
-    Kicked(String),
+    /// The server kicked us, the frontend should only log in again on its own
+    /// if `reconnect_allowed` is set
+    Kicked {
+        reason: String,
+        reconnect_allowed: bool,
+    },
     Notification(Notification),
     SetViewDistance(u32),
     Outcome(Outcome),
@@ -113,6 +120,15 @@ pub enum Event {
     CharacterEdited(CharacterId),
     CharacterError(String),
     MapMarker(comp::MapMarkerUpdate),
+    WorldEvent(WorldEventKind),
+}
+
+/// A friend of the player, friends are named by their player alias
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Friend {
+    /// `None` while they are offline
+    pub uid: Option<Uid>,
+    pub online: bool,
 }
 
 pub struct WorldData {
@@ -166,6 +182,10 @@ pub struct Client {
     world_data: WorldData,
     player_list: HashMap<Uid, PlayerInfo>,
     character_list: CharacterList,
+    /// Rules of the server the player still has to accept
+    rules: Option<String>,
+    /// Physics states of entities that physics updates are relative to
+    physics_baselines: PhysicsBaselines,
     sites: HashMap<SiteId, SiteInfoRich>,
     pois: Vec<PoiInfo>,
     pub chat_mode: ChatMode,
@@ -182,6 +202,8 @@ pub struct Client {
     pending_invites: HashSet<Uid>,
     // The pending trade the client is involved in, and it's id
     pending_trade: Option<(TradeId, PendingTrade, Option<SitePrices>)>,
+    guild: Option<Guild>,
+    friends: BTreeMap<String, Friend>,
 
     network: Option<Network>,
     participant: Option<Participant>,
@@ -197,6 +219,8 @@ pub struct Client {
     last_server_pong: f64,
     last_ping_delta: f64,
     ping_deltas: VecDeque<f64>,
+    ping_clock: PingClock,
+    connection_stats: Option<ConnectionStats>,
 
     tick: u64,
     state: State,
@@ -207,6 +231,16 @@ pub struct Client {
 
     pending_chunks: HashMap<Vec2<i32>, Instant>,
     target_time_of_day: Option<TimeOfDay>,
+    server_clock: ServerClock,
+    /// Progress and tension of the line while a fish is on it
+    fishing: Option<(f32, f32)>,
+    /// Progress and remaining fuel of the cooking pots nearby
+    cooking_pots: HashMap<Vec3<i32>, (f32, f32)>,
+    /// Speed modifiers of the animations of the entities nearby that don't
+    /// play at the normal speed
+    animation_speeds: HashMap<Uid, f32>,
+    /// Positions of the gravestones holding the items the player lost
+    gravestones: Vec<Vec3<f32>>,
 }
 
 /// Holds data related to the current players characters, as well as some
@@ -230,7 +264,11 @@ impl Client {
             ConnectionArgs::Tcp {
                 hostname,
                 prefer_ipv6,
-            } => addr::try_connect(&network, &hostname, prefer_ipv6, ConnectAddr::Tcp).await?,
+                encryption,
+            } => {
+                network.set_encryption(encryption);
+                addr::try_connect(&network, &hostname, prefer_ipv6, ConnectAddr::Tcp).await?
+            },
             ConnectionArgs::Quic {
                 hostname,
                 prefer_ipv6,
@@ -245,11 +283,27 @@ impl Client {
                 })
                 .await?
             },
+            ConnectionArgs::TcpTls {
+                hostname,
+                prefer_ipv6,
+                fingerprint,
+            } => {
+                let config = match fingerprint {
+                    Some(fingerprint) => network::tls::pinned_client_config(fingerprint),
+                    None => network::tls::native_roots_client_config(),
+                };
+                let server_name = addr::server_name(&hostname).to_string();
+                addr::try_connect(&network, &hostname, prefer_ipv6, |a| {
+                    ConnectAddr::TcpTls(a, Arc::clone(&config), server_name.clone())
+                })
+                .await?
+            },
             ConnectionArgs::Mpsc(id) => network.connect(ConnectAddr::Mpsc(id)).await?,
         };
 
         let stream = participant.opened().await?;
         let mut ping_stream = participant.opened().await?;
+        let ping_clock = PingClock::new();
         let mut register_stream = participant.opened().await?;
         let character_screen_stream = participant.opened().await?;
         let in_game_stream = participant.opened().await?;
@@ -272,7 +326,7 @@ impl Client {
         }
         debug!("Auth Server: {:?}", server_info.auth_provider);
 
-        ping_stream.send(PingMsg::Ping)?;
+        ping_stream.send(ping_clock.ping())?;
 
         // Wait for initial sync
         let mut ping_interval = tokio::time::interval(core::time::Duration::from_secs(1));
@@ -290,12 +344,13 @@ impl Client {
         ) = match loop {
             tokio::select! {
                 res = register_stream.recv() => break res?,
-                _ = ping_interval.tick() => ping_stream.send(PingMsg::Ping)?,
+                _ = ping_interval.tick() => ping_stream.send(ping_clock.ping())?,
             }
         } {
             ServerInit::GameSync {
                 entity_package,
                 time_of_day,
+                game_time,
                 max_group_size,
                 client_timeout,
                 world_map,
@@ -310,9 +365,13 @@ impl Client {
 
                 let entity = state.ecs_mut().apply_entity_package(entity_package);
                 *state.ecs_mut().write_resource() = time_of_day;
+                *state.ecs_mut().write_resource() = game_time;
                 *state.ecs_mut().write_resource() = PlayerEntity(Some(entity));
                 state.ecs_mut().insert(material_stats);
                 state.ecs_mut().insert(ability_map);
+                state
+                    .ecs_mut()
+                    .insert(comp::Thermals::new(world_map.thermals.clone()));
 
                 let map_size_lg = common::terrain::MapSizeLg::new(world_map.dimensions_lg)
                     .map_err(|_| {
@@ -337,7 +396,7 @@ impl Client {
                     |a: u8| (a as f32 / 255.0 * <f32 as FloatConst>::FRAC_PI_2()).tan();
                 let scale_height = |h: u8| h as f32 / 255.0 * max_height;
                 let scale_height_big = |h: u32| (h >> 3) as f32 / 8191.0 * max_height;
-                ping_stream.send(PingMsg::Ping)?;
+                ping_stream.send(ping_clock.ping())?;
 
                 debug!("Preparing image...");
                 let unzip_horizons = |(angles, heights): &(Vec<_>, Vec<_>)| {
@@ -366,7 +425,7 @@ impl Client {
                         && pos.x < map_size.x as i32
                         && pos.y < map_size.y as i32
                 };
-                ping_stream.send(PingMsg::Ping)?;
+                ping_stream.send(ping_clock.ping())?;
                 fn sample_pos(
                     map_config: &MapConfig,
                     pos: Vec2<i32>,
@@ -548,7 +607,7 @@ impl Client {
                             u32::from_le_bytes([r, g, b, a]);
                     },
                 );
-                ping_stream.send(PingMsg::Ping)?;
+                ping_stream.send(ping_clock.ping())?;
                 let make_raw = |rgb| -> Result<_, Error> {
                     let mut raw = vec![0u8; 4 * world_map_rgba.len()];
                     LittleEndian::write_u32_into(rgb, &mut raw);
@@ -564,7 +623,7 @@ impl Client {
                         .flipv(),
                     ))
                 };
-                ping_stream.send(PingMsg::Ping)?;
+                ping_stream.send(ping_clock.ping())?;
                 let lod_base = rgba;
                 let lod_alt = alt;
                 let world_map_rgb_img = make_raw(&world_map_rgba)?;
@@ -593,10 +652,14 @@ impl Client {
             },
             ServerInit::TooManyPlayers => Err(Error::TooManyPlayers),
         }?;
-        ping_stream.send(PingMsg::Ping)?;
+        ping_stream.send(ping_clock.ping())?;
 
         debug!("Initial sync done");
 
+        // The round trip time isn't known yet, it is corrected by later syncs
+        let mut server_clock = ServerClock::default();
+        server_clock.sample(*state.ecs().read_resource(), 0.0, state.get_time());
+
         Ok(Self {
             registered: false,
             presence: None,
@@ -610,11 +673,13 @@ impl Client {
             },
             player_list: HashMap::new(),
             character_list: CharacterList::default(),
+            rules: None,
+            physics_baselines: PhysicsBaselines::default(),
             sites: sites
-                .iter()
+                .into_iter()
                 .map(|s| {
                     (s.id, SiteInfoRich {
-                        site: s.clone(),
+                        site: s.sanitized(),
                         economy: None,
                     })
                 })
@@ -630,6 +695,8 @@ impl Client {
             group_members: HashMap::new(),
             pending_invites: HashSet::new(),
             pending_trade: None,
+            guild: None,
+            friends: BTreeMap::new(),
 
             network: Some(network),
             participant: Some(participant),
@@ -646,6 +713,8 @@ impl Client {
             last_server_pong: 0.0,
             last_ping_delta: 0.0,
             ping_deltas: VecDeque::new(),
+            ping_clock,
+            connection_stats: None,
 
             tick: 0,
             state,
@@ -654,6 +723,11 @@ impl Client {
 
             pending_chunks: HashMap::new(),
             target_time_of_day: None,
+            server_clock,
+            fishing: None,
+            cooking_pots: HashMap::new(),
+            animation_speeds: HashMap::new(),
+            gravestones: Vec::new(),
         })
     }
 
@@ -745,7 +819,8 @@ impl Client {
                     | ClientGeneral::EditCharacter { .. }
                     | ClientGeneral::DeleteCharacter(_)
                     | ClientGeneral::Character(_)
-                    | ClientGeneral::Spectate => &mut self.character_screen_stream,
+                    | ClientGeneral::Spectate
+                    | ClientGeneral::AcceptRules(_) => &mut self.character_screen_stream,
                     //Only in game
                     ClientGeneral::ControllerInputs(_)
                     | ClientGeneral::ControlEvent(_)
@@ -758,10 +833,26 @@ impl Client {
                     | ClientGeneral::UnlockSkill(_)
                     | ClientGeneral::RequestSiteInfo(_)
                     | ClientGeneral::UnlockSkillGroup(_)
+                    | ClientGeneral::RespecSkills
+                    | ClientGeneral::Sleep(_)
+                    | ClientGeneral::StartFishing
+                    | ClientGeneral::ReelIn
+                    | ClientGeneral::ReelFishingLine(_)
+                    | ClientGeneral::WaterCrop(_)
+                    | ClientGeneral::HarvestCrop(_)
+                    | ClientGeneral::LoadRecipeIntoPot(_, _)
+                    | ClientGeneral::TakeFromPot(_)
                     | ClientGeneral::RequestPlayerPhysics { .. }
                     | ClientGeneral::RequestLossyTerrainCompression { .. }
                     | ClientGeneral::AcknowledgePersistenceLoadError
-                    | ClientGeneral::UpdateMapMarker(_) => {
+                    | ClientGeneral::UpdateMapMarker(_)
+                    | ClientGeneral::CreateGuild { .. }
+                    | ClientGeneral::InviteToGuild(_)
+                    | ClientGeneral::KickFromGuild(_)
+                    | ClientGeneral::PromoteMember(_, _)
+                    | ClientGeneral::LeaveGuild
+                    | ClientGeneral::FriendAdd(_)
+                    | ClientGeneral::FriendRemove(_) => {
                         #[cfg(feature = "tracy")]
                         {
                             ingame = 1.0;
@@ -831,6 +922,15 @@ impl Client {
         self.send_msg(ClientGeneral::RequestCharacterList);
     }
 
+    /// Accepts the rules of the server and loads the character list, which
+    /// the server holds back until then
+    pub fn accept_rules(&mut self) {
+        if let Some(rules) = self.rules.take() {
+            self.send_msg(ClientGeneral::AcceptRules(rules_hash(&rules)));
+            self.load_character_list();
+        }
+    }
+
     /// New character creation
     pub fn create_character(
         &mut self,
@@ -984,6 +1084,9 @@ impl Client {
 
     pub fn character_list(&self) -> &CharacterList { &self.character_list }
 
+    /// Rules of the server, if the player has yet to accept them
+    pub fn rules(&self) -> Option<&str> { self.rules.as_deref() }
+
     pub fn server_info(&self) -> &ServerInfo { &self.server_info }
 
     pub fn world_data(&self) -> &WorldData { &self.world_data }
@@ -1098,6 +1201,84 @@ impl Client {
         self.send_msg(ClientGeneral::UnlockSkill(skill));
     }
 
+    /// Resets all skills of the character, see [`comp::SkillSet::respec_cost`]
+    /// for the coins this costs
+    pub fn respec_skills(&mut self) { self.send_msg(ClientGeneral::RespecSkills); }
+
+    /// Lies down in the bed at `pos`, the server refuses it during the day and
+    /// in combat
+    pub fn sleep(&mut self, pos: Vec3<i32>) { self.send_msg(ClientGeneral::Sleep(pos)); }
+
+    /// How far the screen should be faded out while the time of day catches up
+    /// with a large jump on the server, like when the night is skipped
+    pub fn time_skip_fade(&self) -> f32 {
+        // Time of day lag that fades the screen out completely, in seconds
+        const FADE_LAG: f64 = 3600.0;
+        self.target_time_of_day.map_or(0.0, |target| {
+            ((target.0 - self.state.get_time_of_day()) / FADE_LAG).clamp(0.0, 1.0) as f32
+        })
+    }
+
+    /// Casts the line of the wielded fishing rod, needs water in front of the
+    /// player
+    pub fn start_fishing(&mut self) { self.send_msg(ClientGeneral::StartFishing); }
+
+    /// Hooks the fish biting at the cast line, it has to be done right after
+    /// the bobber dips
+    pub fn reel_in(&mut self) { self.send_msg(ClientGeneral::ReelIn); }
+
+    /// Whether the line of a fishing rod is cast
+    pub fn is_fishing(&self) -> bool {
+        matches!(
+            self.state
+                .ecs()
+                .read_storage::<CharacterState>()
+                .get(self.entity()),
+            Some(CharacterState::Fishing(_))
+        )
+    }
+
+    /// Holding the reel key reels the fish in, but strains the line
+    pub fn reel_fishing_line(&mut self, reeling: bool) {
+        self.send_msg(ClientGeneral::ReelFishingLine(reeling));
+    }
+
+    pub fn water_crop(&mut self, pos: Vec3<i32>) { self.send_msg(ClientGeneral::WaterCrop(pos)); }
+
+    pub fn harvest_crop(&mut self, pos: Vec3<i32>) {
+        self.send_msg(ClientGeneral::HarvestCrop(pos));
+    }
+
+    /// Cooks the recipe in the pot at `pos`, the pot needs the ingredients and
+    /// some wood to burn
+    pub fn load_recipe_into_pot(&mut self, pos: Vec3<i32>, recipe: String) {
+        self.send_msg(ClientGeneral::LoadRecipeIntoPot(pos, recipe));
+    }
+
+    /// Takes the dishes cooked in the pot at `pos`, those that don't fit into
+    /// the inventory are dropped next to the pot
+    pub fn take_from_pot(&mut self, pos: Vec3<i32>) {
+        self.send_msg(ClientGeneral::TakeFromPot(pos));
+    }
+
+    /// Progress of the dish cooking in the pot at `pos` and the seconds of fuel
+    /// it has left, if the pot is nearby
+    pub fn cooking_pot(&self, pos: Vec3<i32>) -> Option<(f32, f32)> {
+        self.cooking_pots.get(&pos).copied()
+    }
+
+    /// How much faster than normal the animations of the entity play
+    pub fn animation_speed(&self, entity: Uid) -> f32 {
+        self.animation_speeds.get(&entity).copied().unwrap_or(1.0)
+    }
+
+    /// Positions of the gravestones holding the items the player lost
+    pub fn gravestones(&self) -> &[Vec3<f32>] { &self.gravestones }
+
+    /// Progress and tension of the line while a fish is on it, the fish is
+    /// caught at a progress of 1.0 and the line breaks at a tension of 1.0
+    pub fn fishing(&self) -> Option<(f32, f32)> { self.fishing }
+
     pub fn max_group_size(&self) -> u32 { self.max_group_size }
 
     pub fn invite(&self) -> Option<(Uid, std::time::Instant, std::time::Duration, InviteKind)> {
@@ -1118,6 +1299,39 @@ impl Client {
 
     pub fn is_trading(&self) -> bool { self.pending_trade.is_some() }
 
+    /// The guild of the current character, if it is part of one
+    pub fn guild(&self) -> Option<&Guild> { self.guild.as_ref() }
+
+    pub fn create_guild(&mut self, name: String, tag: String) {
+        self.send_msg(ClientGeneral::CreateGuild { name, tag });
+    }
+
+    pub fn invite_to_guild(&mut self, invitee: Uid) {
+        self.send_msg(ClientGeneral::InviteToGuild(invitee));
+    }
+
+    pub fn kick_from_guild(&mut self, member: CharacterId) {
+        self.send_msg(ClientGeneral::KickFromGuild(member));
+    }
+
+    pub fn promote_guild_member(&mut self, member: CharacterId, rank: GuildRank) {
+        self.send_msg(ClientGeneral::PromoteMember(member, rank));
+    }
+
+    pub fn leave_guild(&mut self) { self.send_msg(ClientGeneral::LeaveGuild); }
+
+    /// Friends of the player by name, along with whether they are online
+    pub fn friends(&self) -> &BTreeMap<String, Friend> { &self.friends }
+
+    /// Sends a friend request to the online player with this alias, or
+    /// accepts the one they sent
+    pub fn add_friend(&mut self, name: String) { self.send_msg(ClientGeneral::FriendAdd(name)); }
+
+    /// Removes a friend, or withdraws or declines a friend request
+    pub fn remove_friend(&mut self, name: String) {
+        self.send_msg(ClientGeneral::FriendRemove(name));
+    }
+
     pub fn send_invite(&mut self, invitee: Uid, kind: InviteKind) {
         self.send_msg(ClientGeneral::ControlEvent(ControlEvent::InitiateInvite(
             invitee, kind,
@@ -1291,6 +1505,10 @@ impl Client {
         select_pos: Option<Vec3<f32>>,
         target_entity: Option<EcsEntity>,
     ) {
+        if pressed && self.ability_on_cooldown(input) {
+            // The server would reject it anyway
+            return;
+        }
         if pressed {
             self.control_action(ControlAction::StartInput {
                 input,
@@ -1553,6 +1771,13 @@ impl Client {
             }
         }
 
+        // Keep the game time in line with the server, so that cooldowns are predicted
+        // correctly
+        self.server_clock.advance(dt.as_secs_f64());
+        if let Some(game_time) = self.server_clock.game_time(self.state.get_time()) {
+            *self.state.ecs_mut().write_resource() = game_time;
+        }
+
         // 4) Tick the client's LocalState
         self.state.tick(
             dt,
@@ -1573,7 +1798,7 @@ impl Client {
 
         // Send a ping to the server once every second
         if self.state.get_time() - self.last_server_ping > 1. {
-            self.send_msg_err(PingMsg::Ping)?;
+            self.send_msg_err(self.ping_clock.ping())?;
             self.last_server_ping = self.state.get_time();
         }
 
@@ -1723,9 +1948,15 @@ impl Client {
         match msg {
             ServerGeneral::Disconnect(reason) => match reason {
                 DisconnectReason::Shutdown => return Err(Error::ServerShutdown),
-                DisconnectReason::Kicked(reason) => {
+                DisconnectReason::Kicked {
+                    reason,
+                    reconnect_allowed,
+                } => {
                     debug!("sending ClientMsg::Terminate because we got kicked");
-                    frontend_events.push(Event::Kicked(reason));
+                    frontend_events.push(Event::Kicked {
+                        reason,
+                        reconnect_allowed,
+                    });
                     self.send_msg_err(ClientGeneral::Terminate)?;
                 },
             },
@@ -1861,6 +2092,11 @@ impl Client {
                 self.target_time_of_day = Some(time_of_day);
                 *self.state.ecs_mut().write_resource() = calendar;
             },
+            ServerGeneral::GameTime(game_time) => {
+                let rtt = self.get_ping_ms_rolling_avg() / 1000.0;
+                self.server_clock
+                    .sample(game_time, rtt, self.state.get_time());
+            },
             ServerGeneral::EntitySync(entity_sync_package) => {
                 self.state
                     .ecs_mut()
@@ -1871,6 +2107,12 @@ impl Client {
                     .ecs_mut()
                     .apply_comp_sync_package(comp_sync_package);
             },
+            ServerGeneral::EntityPhysicsDelta(package) => {
+                let comp_sync_package = self.physics_baselines.decode_package(package);
+                self.state
+                    .ecs_mut()
+                    .apply_comp_sync_package(comp_sync_package);
+            },
             ServerGeneral::CreateEntity(entity_package) => {
                 self.state.ecs_mut().apply_entity_package(entity_package);
             },
@@ -1884,6 +2126,24 @@ impl Client {
             ServerGeneral::Notification(n) => {
                 frontend_events.push(Event::Notification(n));
             },
+            ServerGeneral::FriendStatus { uid, name, online } => {
+                // Only friends that were offline come online, the ones that
+                // were just added or listed on login are not announced
+                if online
+                    && self
+                        .friends
+                        .get(&name)
+                        .map_or(false, |friend| !friend.online)
+                {
+                    frontend_events.push(Event::Notification(Notification::FriendOnline(
+                        name.clone(),
+                    )));
+                }
+                self.friends.insert(name, Friend { uid, online });
+            },
+            ServerGeneral::FriendRemoved(name) => {
+                self.friends.remove(&name);
+            },
             _ => unreachable!("Not a general msg"),
         }
         Ok(())
@@ -1973,6 +2233,25 @@ impl Client {
                     },
                 }
             },
+            ServerGeneral::GuildUpdate(guild) => {
+                // Kicked members get the guild without themselves in it
+                let is_member = matches!(
+                    self.presence,
+                    Some(PresenceKind::Character(id)) if guild.member(id).is_some()
+                );
+                self.guild = is_member.then(|| guild);
+            },
+            ServerGeneral::SkillSetDelta(diff) => {
+                let entity = self.entity();
+                if let Some(skill_set) = self
+                    .state
+                    .ecs_mut()
+                    .write_storage::<comp::SkillSet>()
+                    .get_mut(entity)
+                {
+                    skill_set.apply_diff(diff);
+                }
+            },
             ServerGeneral::Invite {
                 inviter,
                 timeout,
@@ -2005,6 +2284,7 @@ impl Client {
             // Cleanup for when the client goes back to the `presence = None`
             ServerGeneral::ExitInGameSuccess => {
                 self.presence = None;
+                self.guild = None;
                 self.clean_state();
             },
             ServerGeneral::InventoryUpdate(inventory, event) => {
@@ -2052,6 +2332,34 @@ impl Client {
                         impulse,
                     });
             },
+            ServerGeneral::FishingUpdate {
+                progress,
+                tension,
+                state,
+            } => {
+                self.fishing = match state {
+                    FishingState::Reeling => Some((progress, tension)),
+                    FishingState::Caught
+                    | FishingState::LineBroken
+                    | FishingState::Abandoned
+                    | FishingState::Missed => None,
+                };
+            },
+            ServerGeneral::CookingUpdate(pos, progress, fuel) => {
+                self.cooking_pots.insert(pos, (progress, fuel));
+            },
+            ServerGeneral::EntityAnimation {
+                entity,
+                speed_modifier,
+            } => {
+                if speed_modifier == 1.0 {
+                    self.animation_speeds.remove(&entity);
+                } else {
+                    self.animation_speeds.insert(entity, speed_modifier);
+                }
+            },
+            ServerGeneral::Gravestones(positions) => self.gravestones = positions,
+            ServerGeneral::WorldEvent(event) => frontend_events.push(Event::WorldEvent(event)),
             ServerGeneral::UpdatePendingTrade(id, trade, pricing) => {
                 tracing::trace!("UpdatePendingTrade {:?} {:?}", id, trade);
                 self.pending_trade = Some((id, trade, pricing));
@@ -2111,6 +2419,10 @@ impl Client {
                 warn!("CharacterActionError: {:?}.", error);
                 events.push(Event::CharacterError(error));
             },
+            ServerGeneral::CharacterCreationError(error) => {
+                warn!("CharacterCreationError: {:?}.", error);
+                events.push(Event::CharacterError(error.to_string()));
+            },
             ServerGeneral::CharacterDataLoadError(error) => {
                 trace!("Handling join error by server");
                 self.presence = None;
@@ -2123,6 +2435,10 @@ impl Client {
             ServerGeneral::CharacterEdited(character_id) => {
                 events.push(Event::CharacterEdited(character_id));
             },
+            ServerGeneral::Rules(rules) => {
+                self.rules = Some(rules);
+                self.character_list.loading = false;
+            },
             ServerGeneral::CharacterSuccess => {
                 debug!("client is now in ingame state on server");
                 if let Some(vd) = self.view_distance {
@@ -2137,12 +2453,14 @@ impl Client {
     fn handle_ping_msg(&mut self, msg: PingMsg) -> Result<(), Error> {
         prof_span!("handle_ping_msg");
         match msg {
-            PingMsg::Ping => {
-                self.send_msg_err(PingMsg::Pong)?;
+            PingMsg::Ping { sent_at_ns } => {
+                self.send_msg_err(self.ping_clock.pong(sent_at_ns))?;
             },
-            PingMsg::Pong => {
+            PingMsg::Pong { sent_at_ns, .. } => {
+                let rtt = self.ping_clock.rtt(sent_at_ns);
+                ConnectionStats::update(&mut self.connection_stats, rtt);
                 self.last_server_pong = self.state.get_time();
-                self.last_ping_delta = self.state.get_time() - self.last_server_ping;
+                self.last_ping_delta = rtt.as_secs_f64();
 
                 // Maintain the correct number of deltas for calculating the rolling average
                 // ping. The client sends a ping to the server every second so we should be
@@ -2163,9 +2481,12 @@ impl Client {
         loop {
             let cnt_start = cnt;
 
-            while let Some(msg) = self.general_stream.try_recv()? {
-                cnt += 1;
-                self.handle_server_msg(frontend_events, msg)?;
+            while let Some(batch) = self.general_stream.try_recv::<MessageBatch>()? {
+                for msg in batch.messages::<ServerGeneral>() {
+                    cnt += 1;
+                    let msg = msg.map_err(StreamError::Deserialize)?;
+                    self.handle_server_msg(frontend_events, msg)?;
+                }
             }
             while let Some(msg) = self.ping_stream.try_recv()? {
                 cnt += 1;
@@ -2175,13 +2496,16 @@ impl Client {
                 cnt += 1;
                 self.handle_server_character_screen_msg(frontend_events, msg)?;
             }
-            while let Some(msg) = self.in_game_stream.try_recv()? {
-                cnt += 1;
-                #[cfg(feature = "tracy")]
-                {
-                    ingame_cnt += 1;
+            while let Some(batch) = self.in_game_stream.try_recv::<MessageBatch>()? {
+                for msg in batch.messages::<ServerGeneral>() {
+                    cnt += 1;
+                    #[cfg(feature = "tracy")]
+                    {
+                        ingame_cnt += 1;
+                    }
+                    let msg = msg.map_err(StreamError::Deserialize)?;
+                    self.handle_server_in_game_msg(frontend_events, msg)?;
                 }
-                self.handle_server_in_game_msg(frontend_events, msg)?;
             }
             while let Some(msg) = self.terrain_stream.try_recv()? {
                 cnt += 1;
@@ -2256,8 +2580,42 @@ impl Client {
 
     pub fn get_tick(&self) -> u64 { self.tick }
 
+    /// Estimate of the server's current game time
+    pub fn game_time(&self) -> Option<GameTime> {
+        self.server_clock.game_time(self.state.get_time())
+    }
+
+    /// Whether the ability bound to `input` can't be used again yet
+    pub fn ability_on_cooldown(&self, input: InputKind) -> bool {
+        let ability_input = Option::<comp::AbilityInput>::from(input);
+        let (ability_input, game_time) = match ability_input.zip(self.game_time()) {
+            Some(x) => x,
+            None => return false,
+        };
+        let entity = self.entity();
+        let ecs = self.state.ecs();
+        let inventories = ecs.read_storage::<comp::Inventory>();
+        let skill_sets = ecs.read_storage::<comp::SkillSet>();
+        let cooldowns = ecs.read_storage::<comp::Cooldowns>();
+        ecs.read_storage::<comp::ActiveAbilities>()
+            .get(entity)
+            .and_then(|abilities| {
+                abilities.ability_id(
+                    ability_input,
+                    inventories.get(entity),
+                    skill_sets.get(entity),
+                )
+            })
+            .zip(cooldowns.get(entity))
+            .map_or(false, |(id, cooldowns)| !cooldowns.is_ready(id, game_time))
+    }
+
     pub fn get_ping_ms(&self) -> f64 { self.last_ping_delta * 1000.0 }
 
+    /// Round trip time and jitter of the pings to the server, `None` until the
+    /// first pong arrived
+    pub fn connection_stats(&self) -> Option<ConnectionStats> { self.connection_stats }
+
     pub fn get_ping_ms_rolling_avg(&self) -> f64 {
         let mut total_weight = 0.;
         let pings = self.ping_deltas.len() as f64;
@@ -2584,7 +2942,7 @@ impl Client {
 
         // Send a ping to the server once every second
         if self.state.get_time() - self.last_server_ping > 1. {
-            self.send_msg_err(PingMsg::Ping)?;
+            self.send_msg_err(self.ping_clock.ping())?;
             self.last_server_ping = self.state.get_time();
         }
 
@@ -2656,6 +3014,7 @@ mod tests {
             ConnectionArgs::Tcp {
                 hostname: "127.0.0.1:9000".to_owned(),
                 prefer_ipv6: false,
+                encryption: None,
             },
             runtime2,
             &mut None,
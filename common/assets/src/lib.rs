@@ -143,6 +143,37 @@ pub fn read_expect_dir<T: DirLoadable>(
     }
 }
 
+/// Resolves the ids of all assets matched by the given specifiers, a
+/// specifier ending in `.*` matches every asset of that directory
+/// recursively.
+///
+/// Directories that can't be read are returned as errors and skipped.
+pub fn bulk_ids<T: DirLoadable>(specifiers: &[String]) -> (Vec<String>, Vec<Error>) {
+    let mut ids = Vec::new();
+    let mut errors = Vec::new();
+    for specifier in specifiers {
+        if specifier.ends_with(".*") {
+            match load_dir::<T>(specifier, true) {
+                Ok(dir) => ids.extend(dir.ids().map(str::to_owned)),
+                Err(err) => errors.push(err),
+            }
+        } else {
+            ids.push(specifier.clone());
+        }
+    }
+    (ids, errors)
+}
+
+/// Loads all the given assets into the cache, so later loads of them don't
+/// have to touch the filesystem. `loaded` is called after each asset with
+/// the result of loading it, failing assets don't stop the others from
+/// loading.
+pub fn load_bulk<T: Compound>(ids: &[String], mut loaded: impl FnMut(&str, Result<(), Error>)) {
+    for id in ids {
+        loaded(id, T::load(id).map(|_| ()));
+    }
+}
+
 impl<T: Compound> AssetExt for T {
     fn load(specifier: &str) -> Result<AssetHandle<Self>, Error> { ASSETS.load(specifier) }
 
@@ -311,6 +342,23 @@ mod tests {
                 }
             });
     }
+
+    #[test]
+    fn bulk_ids_expand_directories() {
+        let (ids, errors) = crate::bulk_ids::<crate::DotVoxAsset>(&[
+            "voxygen.voxel.not_found".to_owned(),
+            "voxygen.voxel.sprite.*".to_owned(),
+            "voxygen.voxel.no_such_dir.*".to_owned(),
+        ]);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(ids[0], "voxygen.voxel.not_found");
+        assert!(ids.len() > 1);
+        assert!(
+            ids[1..]
+                .iter()
+                .all(|id| id.starts_with("voxygen.voxel.sprite."))
+        );
+    }
 }
 
 #[cfg(feature = "asset_tweak")]
@@ -0,0 +1,80 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use vek::*;
+use veloren_common::{
+    ray::RayCast,
+    terrain::{
+        block::{Block, BlockKind},
+        SpriteKind, TerrainChunk, TerrainChunkMeta,
+    },
+    vol::*,
+};
+
+const MIN_Z: i32 = 140;
+const GROUND_Z: i32 = 160;
+
+fn criterion_benchmark(c: &mut Criterion) {
+    // Setup: Create a chunk with flat ground and a few pillars on it.
+    let mut chunk = TerrainChunk::new(
+        MIN_Z,
+        Block::new(BlockKind::Rock, Rgb::zero()),
+        Block::air(SpriteKind::Empty),
+        TerrainChunkMeta::void(),
+    );
+    for pos in chunk.pos_iter(
+        Vec3::new(0, 0, MIN_Z),
+        Vec3::new(
+            TerrainChunk::RECT_SIZE.x as i32,
+            TerrainChunk::RECT_SIZE.y as i32,
+            GROUND_Z + 8,
+        ),
+    ) {
+        if pos.z < GROUND_Z || (pos.x % 7 == 0 && pos.y % 5 == 0) {
+            chunk
+                .set(pos, Block::new(BlockKind::Rock, Rgb::zero()))
+                .unwrap();
+        }
+    }
+
+    // Rays from above the ground in all directions, most of them hit the
+    // ground or a pillar
+    let from = Vec3::new(16.3, 16.7, GROUND_Z as f32 + 4.5);
+    let rays = (0..64)
+        .map(|i| {
+            let angle = i as f32 * 0.1;
+            let dir = Vec3::new(angle.cos(), angle.sin(), -0.3 + (i % 8) as f32 * 0.1);
+            (from, from + dir.normalized() * 30.0)
+        })
+        .collect::<Vec<_>>();
+
+    let mut c = c.benchmark_group("ray");
+
+    c.bench_function("step", |b| {
+        b.iter(|| {
+            for (from, to) in &rays {
+                black_box(
+                    chunk
+                        .ray(*from, *to)
+                        .until(|b| b.is_filled())
+                        .ignore_error()
+                        .cast(),
+                );
+            }
+        })
+    });
+
+    c.bench_function("dda", |b| {
+        b.iter(|| {
+            for (from, to) in &rays {
+                black_box(
+                    RayCast::between(&chunk, *from, *to)
+                        .until(|b| b.is_filled())
+                        .cast(),
+                );
+            }
+        })
+    });
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,132 @@
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+/// Bytes a batch grows to at most, unless a single message is bigger
+pub const DEFAULT_MAX_BATCH_BYTES: usize = 64 * 1024;
+
+/// Messages of one stream sent together as a single network message, so that
+/// the many small messages of a server tick don't cost a write each.
+///
+/// Starts with the number of messages as a little endian `u16`, followed by
+/// the bincode encoded messages. They are read back in the order they were
+/// pushed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MessageBatch(Vec<u8>);
+
+impl Default for MessageBatch {
+    fn default() -> Self { Self(vec![0; 2]) }
+}
+
+impl MessageBatch {
+    pub fn new() -> Self { Self::default() }
+
+    /// Encodes a message, so that it can be pushed to the batches of multiple
+    /// clients without being serialized again
+    pub fn encode<M: Serialize>(msg: &M) -> Vec<u8> {
+        // Only fails for types serde can't represent, which messages aren't
+        bincode::serialize(msg).unwrap()
+    }
+
+    /// Number of messages in the batch, a received batch too short to hold
+    /// the count is empty
+    pub fn len(&self) -> usize {
+        self.0
+            .get(..2)
+            .map_or(0, |count| u16::from_le_bytes([count[0], count[1]]) as usize)
+    }
+
+    pub fn is_empty(&self) -> bool { self.len() == 0 }
+
+    /// Size of the batch in bytes
+    pub fn byte_len(&self) -> usize { self.0.len() }
+
+    /// Appends a message from [`MessageBatch::encode`]. Returns false without
+    /// appending if the count of the batch would overflow.
+    #[must_use]
+    pub fn push_encoded(&mut self, encoded: &[u8]) -> bool {
+        match u16::try_from(self.len() + 1) {
+            Ok(len) => {
+                self.0[..2].copy_from_slice(&len.to_le_bytes());
+                self.0.extend_from_slice(encoded);
+                true
+            },
+            Err(_) => false,
+        }
+    }
+
+    /// Decodes the messages, stops at the first one that is invalid
+    pub fn messages<M: DeserializeOwned>(
+        &self,
+    ) -> impl Iterator<Item = Result<M, bincode::Error>> + '_ {
+        let mut data = self.0.get(2..).unwrap_or_default();
+        let mut failed = false;
+        (0..self.len()).map_while(move |_| {
+            if failed {
+                return None;
+            }
+            let msg = bincode::deserialize_from(&mut data);
+            failed = msg.is_err();
+            Some(msg)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_keep_their_order() {
+        let mut batch = MessageBatch::new();
+        assert!(batch.is_empty());
+        for msg in ["a", "bc", "", "def"] {
+            assert!(batch.push_encoded(&MessageBatch::encode(&msg)));
+        }
+        assert_eq!(batch.len(), 4);
+        assert_eq!(
+            batch
+                .messages::<String>()
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap(),
+            ["a", "bc", "", "def"]
+        );
+
+        // Goes through the network as a message of its own
+        let batch: MessageBatch =
+            bincode::deserialize(&bincode::serialize(&batch).unwrap()).unwrap();
+        assert_eq!(batch.messages::<String>().count(), 4);
+    }
+
+    #[test]
+    fn count_is_limited() {
+        let mut batch = MessageBatch::new();
+        let encoded = MessageBatch::encode(&());
+        for _ in 0..u16::MAX {
+            assert!(batch.push_encoded(&encoded));
+        }
+        assert!(!batch.push_encoded(&encoded));
+        assert_eq!(batch.len(), u16::MAX as usize);
+    }
+
+    #[test]
+    fn invalid_batch_stops() {
+        let mut batch = MessageBatch::new();
+        assert!(batch.push_encoded(&MessageBatch::encode(&7u8)));
+        assert!(batch.push_encoded(&MessageBatch::encode(&8u8)));
+        // Claims more messages than it holds
+        batch.0[0] = 3;
+        let msgs = batch.messages::<u8>().collect::<Vec<_>>();
+        assert_eq!(msgs.len(), 3);
+        assert!(matches!(msgs[..2], [Ok(7), Ok(8)]));
+        assert!(msgs[2].is_err());
+    }
+
+    #[test]
+    fn short_batch_is_empty() {
+        for bytes in [vec![], vec![1]] {
+            let batch: MessageBatch =
+                bincode::deserialize(&bincode::serialize(&bytes).unwrap()).unwrap();
+            assert!(batch.is_empty());
+            assert_eq!(batch.messages::<u8>().count(), 0);
+        }
+    }
+}
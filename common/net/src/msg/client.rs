@@ -2,8 +2,9 @@ use super::{world_msg::SiteId, PingMsg};
 use common::{
     character::CharacterId,
     comp,
-    comp::{Skill, SkillGroupKind},
+    comp::{social::GuildRank, Skill, SkillGroupKind},
     terrain::block::Block,
+    uid::Uid,
 };
 use serde::{Deserialize, Serialize};
 use vek::*;
@@ -62,6 +63,11 @@ pub enum ClientGeneral {
     },
     Character(CharacterId),
     Spectate,
+    /// Accepts the rules of the server, with the [`rules_hash`] of the text
+    /// that was shown
+    ///
+    /// [`rules_hash`]: super::rules_hash
+    AcceptRules(u64),
     //Only in game
     ControllerInputs(Box<comp::ControllerInputs>),
     ControlEvent(comp::ControlEvent),
@@ -77,8 +83,42 @@ pub enum ClientGeneral {
     },
     UnlockSkill(Skill),
     UnlockSkillGroup(SkillGroupKind),
+    /// Resets all skills, paid for with coins
+    RespecSkills,
+    /// Lies down in the bed at the given position, only allowed at night
+    Sleep(Vec3<i32>),
+    /// Casts the line of the equipped fishing rod into nearby water
+    StartFishing,
+    /// Reels in the line to hook the fish that bites, brings in nothing if no
+    /// fish is biting
+    ReelIn,
+    /// Whether the player holds the key to reel in the line
+    ReelFishingLine(bool),
+    /// Waters the crop at the given position, it only grows for a day after
+    WaterCrop(Vec3<i32>),
+    /// Harvests the ripe crop at the given position
+    HarvestCrop(Vec3<i32>),
+    /// Loads the recipe with the given id into the cooking pot at the given
+    /// position, the ingredients are taken from the inventory
+    LoadRecipeIntoPot(Vec3<i32>, String),
+    /// Takes the cooked dishes out of the cooking pot at the given position
+    TakeFromPot(Vec3<i32>),
     RequestSiteInfo(SiteId),
     UpdateMapMarker(comp::MapMarkerChange),
+    CreateGuild {
+        name: String,
+        tag: String,
+    },
+    InviteToGuild(Uid),
+    KickFromGuild(CharacterId),
+    PromoteMember(CharacterId, GuildRank),
+    LeaveGuild,
+    /// Sends a friend request to the online player with this alias, or
+    /// accepts the one they sent
+    FriendAdd(String),
+    /// Removes the friend with this alias, or withdraws or declines a friend
+    /// request
+    FriendRemove(String),
     //Only in Game, via terrain stream
     TerrainChunkRequest {
         key: Vec2<i32>,
@@ -112,7 +152,8 @@ impl ClientMsg {
                         ClientGeneral::RequestCharacterList
                         | ClientGeneral::CreateCharacter { .. }
                         | ClientGeneral::EditCharacter { .. }
-                        | ClientGeneral::DeleteCharacter(_) => {
+                        | ClientGeneral::DeleteCharacter(_)
+                        | ClientGeneral::AcceptRules(_) => {
                             c_type != ClientType::ChatOnly && presence.is_none()
                         },
                         ClientGeneral::Character(_) | ClientGeneral::Spectate => {
@@ -131,10 +172,26 @@ impl ClientMsg {
                         | ClientGeneral::UnlockSkill(_)
                         | ClientGeneral::RequestSiteInfo(_)
                         | ClientGeneral::UnlockSkillGroup(_)
+                        | ClientGeneral::RespecSkills
+                        | ClientGeneral::Sleep(_)
+                        | ClientGeneral::StartFishing
+                        | ClientGeneral::ReelIn
+                        | ClientGeneral::ReelFishingLine(_)
+                        | ClientGeneral::WaterCrop(_)
+                        | ClientGeneral::HarvestCrop(_)
+                        | ClientGeneral::LoadRecipeIntoPot(_, _)
+                        | ClientGeneral::TakeFromPot(_)
                         | ClientGeneral::RequestPlayerPhysics { .. }
                         | ClientGeneral::RequestLossyTerrainCompression { .. }
                         | ClientGeneral::AcknowledgePersistenceLoadError
-                        | ClientGeneral::UpdateMapMarker(_) => {
+                        | ClientGeneral::UpdateMapMarker(_)
+                        | ClientGeneral::CreateGuild { .. }
+                        | ClientGeneral::InviteToGuild(_)
+                        | ClientGeneral::KickFromGuild(_)
+                        | ClientGeneral::PromoteMember(_, _)
+                        | ClientGeneral::LeaveGuild
+                        | ClientGeneral::FriendAdd(_)
+                        | ClientGeneral::FriendRemove(_) => {
                             c_type == ClientType::Game && presence.is_some()
                         },
                         //Always possible
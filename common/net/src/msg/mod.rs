@@ -1,21 +1,25 @@
+pub mod batch;
 pub mod client;
 pub mod compression;
 pub mod ecs_packet;
+pub mod ping;
 pub mod server;
 pub mod world_msg;
 
 // Reexports
 pub use self::{
+    batch::{MessageBatch, DEFAULT_MAX_BATCH_BYTES},
     client::{ClientGeneral, ClientMsg, ClientRegister, ClientType},
     compression::{
         CompressedData, GridLtrPacking, PackingFormula, QuadPngEncoding, TriPngEncoding,
         VoxelImageEncoding, WidePacking, WireChonk,
     },
     ecs_packet::EcsCompPacket,
+    ping::{ConnectionStats, PingClock, PingMsg},
     server::{
-        CharacterInfo, DisconnectReason, InviteAnswer, Notification, PlayerInfo, PlayerListUpdate,
-        RegisterError, SerializedTerrainChunk, ServerGeneral, ServerInfo, ServerInit, ServerMsg,
-        ServerRegisterAnswer,
+        CharacterInfo, DisconnectReason, FishingState, InviteAnswer, Notification, PlayerInfo,
+        PlayerListUpdate, RegisterError, SerializedTerrainChunk, ServerGeneral, ServerInfo,
+        ServerInit, ServerMsg, ServerRegisterAnswer, WorldEventKind,
     },
     world_msg::WorldMapMsg,
 };
@@ -36,12 +40,6 @@ impl PresenceKind {
     pub fn controlling_char(&self) -> bool { matches!(self, Self::Character(_) | Self::Possessor) }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
-pub enum PingMsg {
-    Ping,
-    Pong,
-}
-
 pub const MAX_BYTES_CHAT_MSG: usize = 256;
 
 pub enum ChatMsgValidationError {
@@ -56,3 +54,13 @@ pub fn validate_chat_msg(msg: &str) -> Result<(), ChatMsgValidationError> {
         Err(ChatMsgValidationError::TooLong)
     }
 }
+
+/// Hash of the rules of a server, which clients send back to accept them.
+///
+/// This is FNV-1a rather than the std hasher, as it must neither change
+/// between releases nor between the client and the server.
+pub fn rules_hash(rules: &str) -> u64 {
+    rules.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
@@ -0,0 +1,146 @@
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// Timestamps are nanoseconds of the monotonic [`PingClock`] of the side that
+/// wrote them, so they only mean something to that side
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum PingMsg {
+    Ping {
+        sent_at_ns: u64,
+    },
+    /// Answer to a `Ping`, `sent_at_ns` is echoed unchanged
+    Pong {
+        sent_at_ns: u64,
+        echoed_at_ns: u64,
+    },
+}
+
+/// Monotonic clock the timestamps of [`PingMsg`]s are taken from
+#[derive(Debug, Clone, Copy)]
+pub struct PingClock {
+    epoch: Instant,
+}
+
+impl Default for PingClock {
+    fn default() -> Self {
+        Self {
+            epoch: Instant::now(),
+        }
+    }
+}
+
+impl PingClock {
+    pub fn new() -> Self { Self::default() }
+
+    /// Nanoseconds passed since the clock was created
+    pub fn now_ns(&self) -> u64 { self.epoch.elapsed().as_nanos() as u64 }
+
+    pub fn ping(&self) -> PingMsg {
+        PingMsg::Ping {
+            sent_at_ns: self.now_ns(),
+        }
+    }
+
+    /// Answer to a `Ping` of the remote side
+    pub fn pong(&self, sent_at_ns: u64) -> PingMsg {
+        PingMsg::Pong {
+            sent_at_ns,
+            echoed_at_ns: self.now_ns(),
+        }
+    }
+
+    /// Round trip time of a `Ping` that was sent by this clock, taken when its
+    /// `Pong` arrives
+    pub fn rtt(&self, sent_at_ns: u64) -> Duration {
+        Duration::from_nanos(self.now_ns().saturating_sub(sent_at_ns))
+    }
+}
+
+/// Smoothed round trip time of the `Ping`s to the remote side and its jitter,
+/// the mean deviation of the samples from it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ConnectionStats {
+    pub rtt_ms: f32,
+    pub jitter_ms: f32,
+}
+
+/// Weight of a new sample in the moving averages, the same as TCP uses
+const RTT_WEIGHT: f32 = 1.0 / 8.0;
+const JITTER_WEIGHT: f32 = 1.0 / 4.0;
+
+impl ConnectionStats {
+    /// Stats of the first sample
+    pub fn new(rtt: Duration) -> Self {
+        let rtt_ms = rtt.as_secs_f32() * 1000.0;
+        Self {
+            rtt_ms,
+            jitter_ms: rtt_ms / 2.0,
+        }
+    }
+
+    /// Adds a sample to the exponentially weighted moving averages
+    pub fn sample(&mut self, rtt: Duration) {
+        let rtt_ms = rtt.as_secs_f32() * 1000.0;
+        self.jitter_ms += ((self.rtt_ms - rtt_ms).abs() - self.jitter_ms) * JITTER_WEIGHT;
+        self.rtt_ms += (rtt_ms - self.rtt_ms) * RTT_WEIGHT;
+    }
+
+    /// Adds a sample to `stats`, or starts them with it
+    pub fn update(stats: &mut Option<Self>, rtt: Duration) {
+        match stats {
+            Some(stats) => stats.sample(rtt),
+            None => *stats = Some(Self::new(rtt)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pong_echoes_ping() {
+        let local = PingClock::new();
+        let remote = PingClock::new();
+        let sent_at_ns = match local.ping() {
+            PingMsg::Ping { sent_at_ns } => sent_at_ns,
+            PingMsg::Pong { .. } => unreachable!(),
+        };
+        match remote.pong(sent_at_ns) {
+            PingMsg::Pong {
+                sent_at_ns: echoed, ..
+            } => assert_eq!(echoed, sent_at_ns),
+            PingMsg::Ping { .. } => unreachable!(),
+        }
+        assert!(local.rtt(sent_at_ns) < Duration::from_secs(1));
+        // A timestamp from the future doesn't underflow
+        assert_eq!(local.rtt(u64::MAX), Duration::ZERO);
+    }
+
+    #[test]
+    fn stats_follow_samples() {
+        let mut stats = None;
+        ConnectionStats::update(&mut stats, Duration::from_millis(100));
+        assert_eq!(
+            stats,
+            Some(ConnectionStats {
+                rtt_ms: 100.0,
+                jitter_ms: 50.0
+            })
+        );
+
+        // A steady connection settles without jitter
+        for _ in 0..100 {
+            ConnectionStats::update(&mut stats, Duration::from_millis(40));
+        }
+        let steady = stats.unwrap();
+        assert!((steady.rtt_ms - 40.0).abs() < 0.1);
+        assert!(steady.jitter_ms < 0.1);
+
+        // A single spike moves the average by an eighth only
+        ConnectionStats::update(&mut stats, Duration::from_millis(120));
+        let spiked = stats.unwrap();
+        assert!((spiked.rtt_ms - 50.0).abs() < 0.1);
+        assert!((spiked.jitter_ms - 20.0).abs() < 0.1);
+    }
+}
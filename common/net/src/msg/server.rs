@@ -9,7 +9,7 @@ use common::{
     comp::{self, invite::InviteKind, item::MaterialStatManifest},
     outcome::Outcome,
     recipe::RecipeBook,
-    resources::TimeOfDay,
+    resources::{GameTime, TimeOfDay},
     terrain::{Block, TerrainChunk, TerrainChunkMeta, TerrainChunkSize},
     trade::{PendingTrade, SitePrices, TradeId, TradeResult},
     uid::Uid,
@@ -56,6 +56,7 @@ pub enum ServerInit {
     GameSync {
         entity_package: sync::EntityPackage<EcsCompPacket>,
         time_of_day: TimeOfDay,
+        game_time: GameTime,
         max_group_size: u32,
         client_timeout: Duration,
         world_map: crate::msg::world_msg::WorldMapMsg,
@@ -132,12 +133,26 @@ pub enum ServerGeneral {
     CharacterListUpdate(Vec<CharacterItem>),
     /// An error occurred while creating or deleting a character
     CharacterActionError(String),
+    /// The name or body sent to create or edit a character were rejected
+    CharacterCreationError(character::CreationError),
     /// A new character was created
     CharacterCreated(character::CharacterId),
     CharacterEdited(character::CharacterId),
     CharacterSuccess,
+    /// Rules of the server, which have to be accepted with
+    /// [`ClientGeneral::AcceptRules`] before a character can be selected
+    ///
+    /// [`ClientGeneral::AcceptRules`]: super::ClientGeneral::AcceptRules
+    Rules(String),
     //Ingame related
     GroupUpdate(comp::group::ChangeNotification<sync::Uid>),
+    /// The guild of the client's character changed, also sent to members that
+    /// were kicked so they know they are no longer part of it
+    GuildUpdate(comp::social::Guild),
+    /// Changes that turn the spectator view of the skill set of the client's
+    /// entity, which is synced with [`ServerGeneral::CompSync`], into the full
+    /// one. Sent on the same stream, right after it.
+    SkillSetDelta(comp::skillset::SkillSetDiff),
     /// Indicate to the client that they are invited to join a group
     Invite {
         inviter: sync::Uid,
@@ -164,6 +179,27 @@ pub enum ServerGeneral {
     SetViewDistance(u32),
     Outcomes(Vec<Outcome>),
     Knockback(Vec3<f32>),
+    /// State of the fish on the line of the player, sent every tick while
+    /// fishing and once more when it's over
+    FishingUpdate {
+        progress: f32,
+        tension: f32,
+        state: FishingState,
+    },
+    /// Progress and remaining fuel of the cooking pot at a position, sent
+    /// regularly to the clients nearby
+    CookingUpdate(Vec3<i32>, f32, f32),
+    /// How much faster than normal the animations of an entity play, sent to
+    /// the clients nearby whenever it changes
+    EntityAnimation {
+        entity: Uid,
+        speed_modifier: f32,
+    },
+    /// Positions of the gravestones holding the items the player lost, sent
+    /// whenever they change
+    Gravestones(Vec<Vec3<f32>>),
+    /// Something happened that every player in game is told about
+    WorldEvent(WorldEventKind),
     // Ingame related AND terrain stream
     TerrainChunkUpdate {
         key: Vec2<i32>,
@@ -178,13 +214,28 @@ pub enum ServerGeneral {
     ChatMode(comp::ChatMode),
     SetPlayerEntity(Uid),
     TimeOfDay(TimeOfDay, Calendar),
+    /// Time the server has been running for, lets the client line up
+    /// cooldowns with its own clock
+    GameTime(GameTime),
     EntitySync(sync::EntitySyncPackage),
     CompSync(sync::CompSyncPackage<EcsCompPacket>),
+    /// Physics of entities, as differences to the last state sent where
+    /// that is smaller
+    EntityPhysicsDelta(sync::PhysicsDeltaPackage),
     CreateEntity(sync::EntityPackage<EcsCompPacket>),
     DeleteEntity(Uid),
     Disconnect(DisconnectReason),
     /// Send a popup notification such as "Waypoint Saved"
     Notification(Notification),
+    /// Online status of a friend, sent for all friends on login and whenever
+    /// one of them logs in or out. `uid` is `None` while they are offline.
+    FriendStatus {
+        uid: Option<Uid>,
+        name: String,
+        online: bool,
+    },
+    /// The friend with this name is no longer a friend
+    FriendRemoved(String),
     UpdatePendingTrade(TradeId, PendingTrade, Option<SitePrices>),
     FinishedTrade(TradeResult),
     /// Economic information about sites
@@ -237,17 +288,50 @@ pub enum InviteAnswer {
     TimedOut,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FishingState {
+    /// A fish is on the line and being reeled in
+    Reeling,
+    Caught,
+    LineBroken,
+    /// The player put the fishing rod away
+    Abandoned,
+    /// The line was reeled in too early or too late and came back empty
+    Missed,
+}
+
+impl FishingState {
+    /// Reeling in only makes progress while the tension of the line stays
+    /// below this, the line breaks at a tension of 1.0
+    pub const MAX_REEL_TENSION: f32 = 0.8;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WorldEventKind {
+    /// A boss got hurt enough to enter its next phase
+    BossPhaseChange { boss_uid: Uid, new_phase: u8 },
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Notification {
     WaypointSaved,
+    /// A catch was landed with the fishing rod, the name and amount of the
+    /// item
+    Caught(String, u32),
+    /// A friend with this name came online, raised by the client itself
+    FriendOnline(String),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum DisconnectReason {
     /// Server shut down
     Shutdown,
-    /// Client was kicked
-    Kicked(String),
+    /// Client was kicked, `reason` is shown to the player. Clients don't try
+    /// to get back in on their own unless `reconnect_allowed` is set.
+    Kicked {
+        reason: String,
+        reconnect_allowed: bool,
+    },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -278,8 +362,10 @@ impl ServerMsg {
                         ServerGeneral::CharacterDataLoadError(_)
                         | ServerGeneral::CharacterListUpdate(_)
                         | ServerGeneral::CharacterActionError(_)
+                        | ServerGeneral::CharacterCreationError(_)
                         | ServerGeneral::CharacterEdited(_)
-                        | ServerGeneral::CharacterCreated(_) => {
+                        | ServerGeneral::CharacterCreated(_)
+                        | ServerGeneral::Rules(_) => {
                             c_type != ClientType::ChatOnly && presence.is_none()
                         },
                         ServerGeneral::CharacterSuccess => {
@@ -287,6 +373,8 @@ impl ServerMsg {
                         },
                         //Ingame related
                         ServerGeneral::GroupUpdate(_)
+                        | ServerGeneral::GuildUpdate(_)
+                        | ServerGeneral::SkillSetDelta(_)
                         | ServerGeneral::Invite { .. }
                         | ServerGeneral::InvitePending(_)
                         | ServerGeneral::InviteComplete { .. }
@@ -297,6 +385,11 @@ impl ServerMsg {
                         | ServerGeneral::SetViewDistance(_)
                         | ServerGeneral::Outcomes(_)
                         | ServerGeneral::Knockback(_)
+                        | ServerGeneral::FishingUpdate { .. }
+                        | ServerGeneral::CookingUpdate(..)
+                        | ServerGeneral::EntityAnimation { .. }
+                        | ServerGeneral::Gravestones(_)
+                        | ServerGeneral::WorldEvent(_)
                         | ServerGeneral::UpdatePendingTrade(_, _, _)
                         | ServerGeneral::FinishedTrade(_)
                         | ServerGeneral::SiteEconomy(_)
@@ -309,12 +402,16 @@ impl ServerMsg {
                         | ServerGeneral::ChatMode(_)
                         | ServerGeneral::SetPlayerEntity(_)
                         | ServerGeneral::TimeOfDay(_, _)
+                        | ServerGeneral::GameTime(_)
                         | ServerGeneral::EntitySync(_)
                         | ServerGeneral::CompSync(_)
+                        | ServerGeneral::EntityPhysicsDelta(_)
                         | ServerGeneral::CreateEntity(_)
                         | ServerGeneral::DeleteEntity(_)
                         | ServerGeneral::Disconnect(_)
-                        | ServerGeneral::Notification(_) => true,
+                        | ServerGeneral::Notification(_)
+                        | ServerGeneral::FriendStatus { .. }
+                        | ServerGeneral::FriendRemoved(_) => true,
                     }
             },
             ServerMsg::Ping(_) => true,
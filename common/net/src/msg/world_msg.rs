@@ -1,4 +1,4 @@
-use common::{grid::Grid, trade::Good};
+use common::{comp::ThermalColumn, grid::Grid, trade::Good};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use vek::*;
@@ -124,6 +124,9 @@ pub struct WorldMapMsg {
     pub horizons: [(Vec<u8>, Vec<u8>); 2],
     pub sites: Vec<SiteInfo>,
     pub pois: Vec<PoiInfo>,
+    /// Rising air gliders can gain altitude in, the client needs them to
+    /// predict the flight of its own character.
+    pub thermals: Vec<ThermalColumn>,
 }
 
 pub type SiteId = common::trade::SiteId;
@@ -136,15 +139,47 @@ pub struct SiteInfo {
     pub name: Option<String>,
 }
 
+impl SiteInfo {
+    /// Longer names are cut, they would overflow map labels and the compass
+    pub const MAX_NAME_LEN: usize = 32;
+
+    /// Site names are chosen by the server, don't trust them to be displayable.
+    /// Strips control characters, collapses whitespace and caps the length.
+    #[must_use]
+    pub fn sanitized(mut self) -> Self {
+        self.name = self.name.and_then(|name| {
+            let name = name
+                .split(|c: char| c.is_whitespace() || c.is_control())
+                .filter(|word| !word.is_empty())
+                .collect::<Vec<_>>()
+                .join(" ");
+            let name = name
+                .chars()
+                .take(Self::MAX_NAME_LEN)
+                .collect::<String>()
+                .trim_end()
+                .to_string();
+            (!name.is_empty()).then(|| name)
+        });
+        self
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[repr(u8)]
 pub enum SiteKind {
     Town,
-    Dungeon { difficulty: u32 },
+    Dungeon {
+        difficulty: u32,
+    },
     Castle,
     Cave,
     Tree,
     Gnarling,
+    /// A kind added by a newer server, only unit variants can be mapped to
+    /// this, so new kinds must not carry data
+    #[serde(other)]
+    Unknown,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -3,6 +3,7 @@
 pub mod interpolation;
 mod net_sync;
 mod packet;
+mod physics;
 mod sync_ext;
 mod track;
 
@@ -14,5 +15,8 @@ pub use packet::{
     handle_remove, CompPacket, CompSyncPackage, EntityPackage, EntitySyncPackage,
     InterpolatableComponent,
 };
+pub use physics::{
+    PhysicsBaselines, PhysicsDelta, PhysicsDeltaPackage, PhysicsSnapshot, PhysicsUpdate,
+};
 pub use sync_ext::WorldSyncExt;
 pub use track::UpdateTracker;
@@ -31,6 +31,20 @@ where
     /// components for the client's own entity.
     const SYNC_FROM: SyncFrom;
 
+    /// Set if [`NetSync::clone_for_spectator`] hides anything. Only applies to
+    /// components synced from any entity, the client's own entity then gets
+    /// the full component in addition.
+    const SPECTATOR_VIEW: bool = false;
+
+    /// Set if the client's own entity doesn't get the full component, but
+    /// only what differs from the spectator view. The server sends that on
+    /// its own, as it depends on the component.
+    const SPECTATOR_DELTA: bool = false;
+
+    /// The copy of this component that is synced to clients not controlling
+    /// the entity, see [`NetSync::SPECTATOR_VIEW`].
+    fn clone_for_spectator(&self) -> Self { self.clone() }
+
     // sync::handle_modify(comp, entity, world)
 
     /// Allows making modifications before the synced component is inserted on
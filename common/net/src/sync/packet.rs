@@ -168,12 +168,15 @@ impl<P: CompPacket> CompSyncPackage<P> {
             .push((uid.into(), CompUpdateKind::Removed(PhantomData::<C>.into())));
     }
 
+    /// `clone_comp` creates the copy of each updated component that is added
+    /// to this package.
     pub fn add_component_updates<'a, C: Component + Clone + Send + Sync>(
         &mut self,
         uids: &ReadStorage<'a, Uid>,
         tracker: &UpdateTracker<C>,
         storage: &ReadStorage<'a, C>,
         filter: impl Join + Copy,
+        clone_comp: impl Fn(&C) -> C,
     ) where
         P: From<C>,
         C: TryFrom<P>,
@@ -181,7 +184,7 @@ impl<P: CompPacket> CompSyncPackage<P> {
         P::Phantom: TryInto<PhantomData<C>>,
         C::Storage: specs::storage::Tracked,
     {
-        tracker.get_updates_for(uids, storage, filter, &mut self.comp_updates);
+        tracker.get_updates_for(uids, storage, filter, clone_comp, &mut self.comp_updates);
     }
 
     /// If there was an update to the component `C` on the provided entity this
@@ -0,0 +1,301 @@
+//! Delta encoding of physics updates
+//!
+//! The server remembers the physics state it sent last for each entity, the
+//! baseline, separately for every client. Later updates are sent as fixed point
+//! differences to the baseline when those are smaller than the full state.
+//! Physics updates go through a reliable and ordered stream, so a client has
+//! the same baseline as the server as soon as the update setting it is sent.
+use super::{CompPacket, CompSyncPackage};
+use common::{
+    comp::{Ori, Pos, Vel},
+    uid::Uid,
+};
+use hashbrown::HashMap;
+use serde::{Deserialize, Serialize};
+use vek::*;
+
+/// Resolution of position differences, in blocks
+const POS_STEP: f32 = 1.0 / 256.0;
+/// Resolution of velocity differences, in blocks per second
+const VEL_STEP: f32 = 1.0 / 16.0;
+/// Resolution of differences of the orientation quaternion components
+const ORI_STEP: f32 = 1.0 / 256.0;
+
+/// Physics state of an entity
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PhysicsSnapshot {
+    pub pos: Vec3<f32>,
+    pub vel: Option<Vec3<f32>>,
+    /// Components of the orientation quaternion, only normalized when turned
+    /// back into an [`Ori`] so both sides add up deltas the same way
+    pub ori: Option<Vec4<f32>>,
+}
+
+impl PhysicsSnapshot {
+    pub fn new(pos: Pos, vel: Option<Vel>, ori: Option<Ori>) -> Self {
+        Self {
+            pos: pos.0,
+            vel: vel.map(|vel| vel.0),
+            ori: ori.map(|ori| ori.to_quat().into_vec4()),
+        }
+    }
+
+    /// Size in bytes when serialized with bincode
+    fn encoded_len(&self) -> usize {
+        12 + option_len(self.vel.is_some(), 12) + option_len(self.ori.is_some(), 16)
+    }
+}
+
+/// Difference between two [`PhysicsSnapshot`]s in fixed point
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PhysicsDelta {
+    pub pos: Vec3<i16>,
+    pub vel: Option<Vec3<i8>>,
+    pub ori: Option<Vec4<i8>>,
+}
+
+impl PhysicsDelta {
+    /// `None` if the difference is too big to be represented or a component
+    /// was added or removed
+    fn between(from: &PhysicsSnapshot, to: &PhysicsSnapshot) -> Option<Self> {
+        Some(Self {
+            pos: quantize_vec3(to.pos - from.pos, POS_STEP)?,
+            vel: match (from.vel, to.vel) {
+                (Some(from), Some(to)) => Some(quantize_vec3(to - from, VEL_STEP)?),
+                (None, None) => None,
+                _ => return None,
+            },
+            ori: match (from.ori, to.ori) {
+                (Some(from), Some(to)) => {
+                    let delta = (to - from).map(|diff| quantize(diff, ORI_STEP));
+                    Some(Vec4::new(delta.x?, delta.y?, delta.z?, delta.w?))
+                },
+                (None, None) => None,
+                _ => return None,
+            },
+        })
+    }
+
+    /// `None` if the delta doesn't have the same components as `from`
+    fn apply(&self, from: &PhysicsSnapshot) -> Option<PhysicsSnapshot> {
+        Some(PhysicsSnapshot {
+            pos: from.pos + self.pos.map(|d| f32::from(d) * POS_STEP),
+            vel: match (from.vel, self.vel) {
+                (Some(vel), Some(d)) => Some(vel + d.map(|d| f32::from(d) * VEL_STEP)),
+                (None, None) => None,
+                _ => return None,
+            },
+            ori: match (from.ori, self.ori) {
+                (Some(ori), Some(d)) => Some(ori + d.map(|d| f32::from(d) * ORI_STEP)),
+                (None, None) => None,
+                _ => return None,
+            },
+        })
+    }
+
+    /// Size in bytes when serialized with bincode
+    fn encoded_len(&self) -> usize {
+        6 + option_len(self.vel.is_some(), 3) + option_len(self.ori.is_some(), 4)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum PhysicsUpdate {
+    Full(PhysicsSnapshot),
+    /// Relative to the baseline of the entity
+    Delta(PhysicsDelta),
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct PhysicsDeltaPackage {
+    pub updates: Vec<(Uid, PhysicsUpdate)>,
+    /// Entities the baselines were dropped for, their next update is a full
+    /// one
+    pub forgotten: Vec<Uid>,
+}
+
+impl PhysicsDeltaPackage {
+    pub fn is_empty(&self) -> bool { self.updates.is_empty() && self.forgotten.is_empty() }
+}
+
+/// The last physics state sent for each entity. The server encodes updates
+/// against it for one client, that client decodes them against its own copy.
+#[derive(Debug, Default)]
+pub struct PhysicsBaselines {
+    /// Baselines along with whether they were used since the last
+    /// [`PhysicsBaselines::take_unused`]
+    baselines: HashMap<Uid, (PhysicsSnapshot, bool)>,
+}
+
+impl PhysicsBaselines {
+    /// Encodes the state of an entity as a delta if it has a baseline and the
+    /// delta is smaller, the state the client decodes is the new baseline
+    pub fn encode(&mut self, uid: Uid, snapshot: PhysicsSnapshot) -> PhysicsUpdate {
+        let delta = self
+            .baselines
+            .get(&uid)
+            .and_then(|(baseline, _)| {
+                let delta = PhysicsDelta::between(baseline, &snapshot)?;
+                Some((delta, delta.apply(baseline)?))
+            })
+            .filter(|(delta, _)| delta.encoded_len() < snapshot.encoded_len());
+        let (update, baseline) = match delta {
+            Some((delta, baseline)) => (PhysicsUpdate::Delta(delta), baseline),
+            None => (PhysicsUpdate::Full(snapshot), snapshot),
+        };
+        self.baselines.insert(uid, (baseline, true));
+        update
+    }
+
+    /// Keeps the baseline of an entity that is still visible but wasn't
+    /// updated
+    pub fn keep(&mut self, uid: Uid) {
+        if let Some((_, used)) = self.baselines.get_mut(&uid) {
+            *used = true;
+        }
+    }
+
+    /// Drops the baselines that weren't encoded against or kept since the last
+    /// call, their entities are the ones that went out of sight
+    pub fn take_unused(&mut self) -> Vec<Uid> {
+        let mut unused = Vec::new();
+        self.baselines.retain(|uid, (_, used)| {
+            if !*used {
+                unused.push(*uid);
+            }
+            std::mem::replace(used, false)
+        });
+        unused
+    }
+
+    /// Decodes the state of an entity, `None` if a delta doesn't fit the
+    /// baseline
+    pub fn decode(&mut self, uid: Uid, update: PhysicsUpdate) -> Option<PhysicsSnapshot> {
+        let snapshot = match update {
+            PhysicsUpdate::Full(snapshot) => snapshot,
+            PhysicsUpdate::Delta(delta) => delta.apply(&self.baselines.get(&uid)?.0)?,
+        };
+        self.baselines.insert(uid, (snapshot, true));
+        Some(snapshot)
+    }
+
+    /// Decodes a package into the component updates it stands for
+    pub fn decode_package<P>(&mut self, package: PhysicsDeltaPackage) -> CompSyncPackage<P>
+    where
+        P: CompPacket + From<Pos> + From<Vel> + From<Ori>,
+    {
+        for uid in package.forgotten {
+            self.baselines.remove(&uid);
+        }
+        let mut comp_sync_package = CompSyncPackage::new();
+        for (uid, update) in package.updates {
+            if let Some(snapshot) = self.decode(uid, update) {
+                comp_sync_package.comp_modified(uid, Pos(snapshot.pos));
+                if let Some(vel) = snapshot.vel {
+                    comp_sync_package.comp_modified(uid, Vel(vel));
+                }
+                if let Some(ori) = snapshot.ori {
+                    let quat = Quaternion::from_vec4(ori);
+                    comp_sync_package.comp_modified(uid, Ori::new(quat.normalized()));
+                }
+            } else {
+                tracing::warn!(?uid, "Received a physics delta without a baseline");
+            }
+        }
+        comp_sync_package
+    }
+}
+
+fn option_len(is_some: bool, len: usize) -> usize { 1 + if is_some { len } else { 0 } }
+
+fn quantize<T: TryFrom<i32>>(diff: f32, step: f32) -> Option<T> {
+    let steps = (diff / step).round();
+    (steps.abs() <= i32::MAX as f32)
+        .then(|| T::try_from(steps as i32).ok())
+        .flatten()
+}
+
+fn quantize_vec3<T: TryFrom<i32>>(diff: Vec3<f32>, step: f32) -> Option<Vec3<T>> {
+    let diff = diff.map(|diff| quantize(diff, step));
+    Some(Vec3::new(diff.x?, diff.y?, diff.z?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::msg::EcsCompPacket;
+
+    fn snapshot(pos: Vec3<f32>, vel: Vec3<f32>) -> PhysicsSnapshot {
+        PhysicsSnapshot {
+            pos,
+            vel: Some(vel),
+            ori: Some(Vec4::new(0.0, 0.0, 0.6, 0.8)),
+        }
+    }
+
+    #[test]
+    fn first_update_is_full() {
+        let mut server = PhysicsBaselines::default();
+        let state = snapshot(Vec3::new(100.0, 200.0, 50.0), Vec3::zero());
+        assert_eq!(server.encode(Uid(1), state), PhysicsUpdate::Full(state));
+        assert!(matches!(
+            server.encode(Uid(1), state),
+            PhysicsUpdate::Delta(_)
+        ));
+        // A baseline doesn't help other entities
+        assert_eq!(server.encode(Uid(2), state), PhysicsUpdate::Full(state));
+    }
+
+    #[test]
+    fn decoded_state_follows_encoded_state() {
+        let mut server = PhysicsBaselines::default();
+        let mut client = PhysicsBaselines::default();
+        let mut pos = Vec3::new(1000.0, 2000.0, 300.0);
+        let mut vel = Vec3::new(3.0, -2.0, 0.5);
+        for i in 0..500 {
+            pos += vel * 0.033;
+            vel += Vec3::new(0.01, 0.02, -0.3);
+            // A teleport can't be expressed as a delta
+            if i == 250 {
+                pos.x += 1000.0;
+            }
+            let state = snapshot(pos, vel);
+            let update = server.encode(Uid(7), state);
+            assert_eq!(matches!(update, PhysicsUpdate::Full(_)), i == 0 || i == 250);
+            let decoded = client.decode(Uid(7), update).unwrap();
+            // Rounding errors don't add up since deltas are taken against
+            // the decoded state
+            assert!(decoded.pos.distance(pos) <= POS_STEP);
+            assert!(decoded.vel.unwrap().distance(vel) <= VEL_STEP);
+        }
+    }
+
+    #[test]
+    fn unused_baselines_are_forgotten() {
+        let mut server = PhysicsBaselines::default();
+        let mut client = PhysicsBaselines::default();
+        let state = snapshot(Vec3::zero(), Vec3::zero());
+        for uid in [Uid(1), Uid(2)] {
+            client.decode(uid, server.encode(uid, state));
+        }
+        assert!(server.take_unused().is_empty());
+
+        // Only the first entity is still in sight
+        server.keep(Uid(1));
+        let forgotten = server.take_unused();
+        assert_eq!(forgotten, vec![Uid(2)]);
+        let _: CompSyncPackage<EcsCompPacket> = client.decode_package(PhysicsDeltaPackage {
+            updates: Vec::new(),
+            forgotten,
+        });
+
+        let delta = PhysicsUpdate::Delta(PhysicsDelta {
+            pos: Vec3::zero(),
+            vel: Some(Vec3::zero()),
+            ori: Some(Vec4::zero()),
+        });
+        assert_eq!(client.decode(Uid(1), delta), Some(state));
+        assert_eq!(client.decode(Uid(2), delta), None);
+        assert_eq!(server.encode(Uid(2), state), PhysicsUpdate::Full(state));
+    }
+}
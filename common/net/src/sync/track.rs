@@ -89,6 +89,7 @@ impl<C: Component + Clone + Send + Sync> UpdateTracker<C> {
         uids: &specs::ReadStorage<'a, Uid>,
         storage: &specs::ReadStorage<'a, C>,
         entity_filter: impl Join + Copy,
+        clone_comp: impl Fn(&C) -> C,
         buf: &mut Vec<(u64, CompUpdateKind<P>)>,
     ) where
         P: CompPacket,
@@ -102,7 +103,7 @@ impl<C: Component + Clone + Send + Sync> UpdateTracker<C> {
         for (uid, comp, _, _) in (uids, storage, &self.inserted, entity_filter).join() {
             buf.push((
                 (*uid).into(),
-                CompUpdateKind::Inserted(P::from(comp.clone())),
+                CompUpdateKind::Inserted(P::from(clone_comp(comp))),
             ));
         }
 
@@ -110,7 +111,7 @@ impl<C: Component + Clone + Send + Sync> UpdateTracker<C> {
         for (uid, comp, _, _) in (uids, storage, &self.modified, entity_filter).join() {
             buf.push((
                 (*uid).into(),
-                CompUpdateKind::Modified(P::from(comp.clone())),
+                CompUpdateKind::Modified(P::from(clone_comp(comp))),
             ));
         }
 
@@ -28,6 +28,8 @@ macro_rules! synced_components {
             health: Health,
             poise: Poise,
             light_emitter: LightEmitter,
+            audio_emitter: AudioEmitter,
+            gravestone: Gravestone,
             item: Item,
             scale: Scale,
             group: Group,
@@ -35,6 +37,7 @@ macro_rules! synced_components {
             is_rider: IsRider,
             mass: Mass,
             density: Density,
+            terminal_velocity: TerminalVelocity,
             collider: Collider,
             sticky: Sticky,
             immovable: Immovable,
@@ -60,6 +63,7 @@ macro_rules! synced_components {
             // Synced to the client only for its own entity
 
             combo: Combo,
+            cooldowns: Cooldowns,
             active_abilities: ActiveAbilities,
             can_build: CanBuild,
         }
@@ -153,6 +157,14 @@ impl NetSync for LightEmitter {
     const SYNC_FROM: SyncFrom = SyncFrom::AnyEntity;
 }
 
+impl NetSync for AudioEmitter {
+    const SYNC_FROM: SyncFrom = SyncFrom::AnyEntity;
+}
+
+impl NetSync for Gravestone {
+    const SYNC_FROM: SyncFrom = SyncFrom::AnyEntity;
+}
+
 impl NetSync for Item {
     const SYNC_FROM: SyncFrom = SyncFrom::AnyEntity;
 }
@@ -181,6 +193,10 @@ impl NetSync for Density {
     const SYNC_FROM: SyncFrom = SyncFrom::AnyEntity;
 }
 
+impl NetSync for TerminalVelocity {
+    const SYNC_FROM: SyncFrom = SyncFrom::AnyEntity;
+}
+
 impl NetSync for Collider {
     const SYNC_FROM: SyncFrom = SyncFrom::AnyEntity;
 }
@@ -218,7 +234,12 @@ impl NetSync for Inventory {
 }
 
 impl NetSync for SkillSet {
+    // See `ServerGeneral::SkillSetDelta`
+    const SPECTATOR_DELTA: bool = true;
+    const SPECTATOR_VIEW: bool = true;
     const SYNC_FROM: SyncFrom = SyncFrom::AnyEntity;
+
+    fn clone_for_spectator(&self) -> Self { SkillSet::clone_for_spectator(self) }
 }
 
 // These are synced only from the client's own  entity.
@@ -227,6 +248,10 @@ impl NetSync for Combo {
     const SYNC_FROM: SyncFrom = SyncFrom::ClientEntity;
 }
 
+impl NetSync for Cooldowns {
+    const SYNC_FROM: SyncFrom = SyncFrom::ClientEntity;
+}
+
 impl NetSync for ActiveAbilities {
     const SYNC_FROM: SyncFrom = SyncFrom::ClientEntity;
 }
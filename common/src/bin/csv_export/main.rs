@@ -43,6 +43,8 @@ fn armor_stats() -> Result<(), Box<dyn Error>> {
         "Energy Reward",
         "Crit Power",
         "Stealth",
+        "Fire Resistance",
+        "Poison Resistance",
         "Description",
     ])?;
 
@@ -70,6 +72,8 @@ fn armor_stats() -> Result<(), Box<dyn Error>> {
                 let energy_reward = armor.energy_reward().unwrap_or(0.0).to_string();
                 let crit_power = armor.crit_power().unwrap_or(0.0).to_string();
                 let stealth = armor.stealth().unwrap_or(0.0).to_string();
+                let fire_resistance = armor.fire_resistance().unwrap_or(0.0).to_string();
+                let poison_resistance = armor.poison_resistance().unwrap_or(0.0).to_string();
 
                 wtr.write_record(&[
                     item.item_definition_id(),
@@ -82,6 +86,8 @@ fn armor_stats() -> Result<(), Box<dyn Error>> {
                     &energy_reward,
                     &crit_power,
                     &stealth,
+                    &fire_resistance,
+                    &poison_resistance,
                     item.description(),
                 ])?;
             },
@@ -169,6 +175,7 @@ fn get_tool_kind(kind: &ToolKind) -> String {
         ToolKind::Blowgun => "Blowgun".to_string(),
         ToolKind::Debug => "Debug".to_string(),
         ToolKind::Farming => "Farming".to_string(),
+        ToolKind::Fishing => "Fishing".to_string(),
         ToolKind::Pick => "Pick".to_string(),
         ToolKind::Natural => "Natural".to_string(),
         ToolKind::Empty => "Empty".to_string(),
@@ -171,6 +171,17 @@ fn armor_stats() -> Result<(), Box<dyn Error>> {
                                 None
                             };
 
+                            // Resistance columns are optional to still accept older exports
+                            let resistance = |header: &str| {
+                                headers
+                                    .get(header)
+                                    .and_then(|i| record.get(*i))
+                                    .map(|raw| raw.parse().unwrap())
+                                    .filter(|value: &f32| *value != 0.0)
+                            };
+                            let fire_resistance = resistance("Fire Resistance");
+                            let poison_resistance = resistance("Poison Resistance");
+
                             let kind = armor.kind.clone();
                             let armor_stats = comp::item::armor::Stats::new(
                                 protection,
@@ -179,6 +190,8 @@ fn armor_stats() -> Result<(), Box<dyn Error>> {
                                 energy_reward,
                                 crit_power,
                                 stealth,
+                                fire_resistance,
+                                poison_resistance,
                             );
                             let armor = comp::item::armor::Armor::new(kind, armor_stats);
                             let quality = if let Some(quality_raw) = record.get(headers["Quality"])
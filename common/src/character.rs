@@ -8,6 +8,113 @@ pub const MAX_CHARACTERS_PER_PLAYER: usize = 8;
 pub type CharacterId = i64;
 
 pub const MAX_NAME_LENGTH: usize = 20;
+/// Punctuation allowed in character names, in addition to letters, digits and
+/// single spaces
+pub const NAME_PUNCTUATION: &[char] = &['-', '\'', '.', '_'];
+
+/// Why a character can't be created with a certain name and body. Shared
+/// between the client, to show errors before submitting, and the server, which
+/// has the final say.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CreationError {
+    NameEmpty,
+    /// Length of the name in characters
+    NameTooLong(usize),
+    NameSurroundingSpaces,
+    NameRepeatedSpaces,
+    NameInvalidCharacter(char),
+    /// Only humanoid characters can be created
+    NotHumanoid,
+    /// The named body attribute is outside of the range of its species
+    BodyOutOfRange(String),
+}
+
+/// Checks that `name` and `body` are allowed for a new character. This
+/// doesn't check that the name is unique or free of banned words, that is done
+/// by the server.
+pub fn validate(name: &str, body: &comp::Body) -> Result<(), CreationError> {
+    validate_name(name)?;
+    validate_body(body)
+}
+
+pub fn validate_name(name: &str) -> Result<(), CreationError> {
+    if name.is_empty() {
+        return Err(CreationError::NameEmpty);
+    }
+    let len = name.chars().count();
+    if len > MAX_NAME_LENGTH {
+        return Err(CreationError::NameTooLong(len));
+    }
+    if name.starts_with(' ') || name.ends_with(' ') {
+        return Err(CreationError::NameSurroundingSpaces);
+    }
+    if name.contains("  ") {
+        return Err(CreationError::NameRepeatedSpaces);
+    }
+    match name
+        .chars()
+        .find(|c| !(c.is_alphanumeric() || *c == ' ' || NAME_PUNCTUATION.contains(c)))
+    {
+        Some(c) => Err(CreationError::NameInvalidCharacter(c)),
+        None => Ok(()),
+    }
+}
+
+pub fn validate_body(body: &comp::Body) -> Result<(), CreationError> {
+    let body = match body {
+        comp::Body::Humanoid(body) => body,
+        _ => return Err(CreationError::NotHumanoid),
+    };
+    let species = body.species;
+    let body_type = body.body_type;
+    let ranges = [
+        (
+            "hair_style",
+            body.hair_style,
+            species.num_hair_styles(body_type),
+        ),
+        ("beard", body.beard, species.num_beards(body_type)),
+        ("eyes", body.eyes, species.num_eyes(body_type)),
+        (
+            "accessory",
+            body.accessory,
+            species.num_accessories(body_type),
+        ),
+        ("hair_color", body.hair_color, species.num_hair_colors()),
+        ("skin", body.skin, species.num_skin_colors()),
+        ("eye_color", body.eye_color, species.num_eye_colors()),
+    ];
+    match ranges.iter().find(|(_, value, num)| value >= num) {
+        Some((attribute, _, _)) => Err(CreationError::BodyOutOfRange(attribute.to_string())),
+        None => Ok(()),
+    }
+}
+
+impl core::fmt::Display for CreationError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            CreationError::NameEmpty => write!(f, "Character name is empty"),
+            CreationError::NameTooLong(len) => write!(
+                f,
+                "Character name is too long ({} of at most {} characters)",
+                len, MAX_NAME_LENGTH
+            ),
+            CreationError::NameSurroundingSpaces => {
+                write!(f, "Character name starts or ends with a space")
+            },
+            CreationError::NameRepeatedSpaces => {
+                write!(f, "Character name contains repeated spaces")
+            },
+            CreationError::NameInvalidCharacter(c) => {
+                write!(f, "Character name contains the invalid character {:?}", c)
+            },
+            CreationError::NotHumanoid => write!(f, "Only humanoid characters can be created"),
+            CreationError::BodyOutOfRange(attribute) => {
+                write!(f, "Invalid Body, {} is out of range", attribute)
+            },
+        }
+    }
+}
 
 /// The minimum character data we need to create a new character on the server.
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
@@ -24,3 +131,74 @@ pub struct CharacterItem {
     pub body: comp::Body,
     pub inventory: Inventory,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::comp::humanoid;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    #[test]
+    fn names() {
+        assert_eq!(validate_name("Grimnir"), Ok(()));
+        assert_eq!(validate_name("Jo-Ann O'Neil"), Ok(()));
+        assert_eq!(validate_name("Zoë 2"), Ok(()));
+        assert_eq!(validate_name(""), Err(CreationError::NameEmpty));
+        assert_eq!(
+            validate_name(" Grimnir"),
+            Err(CreationError::NameSurroundingSpaces)
+        );
+        assert_eq!(
+            validate_name("Grim  nir"),
+            Err(CreationError::NameRepeatedSpaces)
+        );
+        assert_eq!(
+            validate_name("Grim\nnir"),
+            Err(CreationError::NameInvalidCharacter('\n'))
+        );
+        assert_eq!(
+            validate_name("Thisnameiswaytoolongforus"),
+            Err(CreationError::NameTooLong(25))
+        );
+    }
+
+    #[test]
+    fn bodies() {
+        let mut body = humanoid::Body::random();
+        assert_eq!(validate_body(&comp::Body::Humanoid(body)), Ok(()));
+        body.skin = u8::MAX;
+        assert_eq!(
+            validate_body(&comp::Body::Humanoid(body)),
+            Err(CreationError::BodyOutOfRange("skin".to_string()))
+        );
+        assert_eq!(
+            validate_body(&comp::Body::Ship(comp::ship::Body::DefaultAirship)),
+            Err(CreationError::NotHumanoid)
+        );
+    }
+
+    #[test]
+    fn random_unicode_names() {
+        let mut rng = StdRng::seed_from_u64(1337);
+        for _ in 0..10_000 {
+            let len = rng.gen_range(0..30);
+            let name = (0..len)
+                .map(|_| {
+                    // mostly ascii, so that valid names are generated as well
+                    if rng.gen_bool(0.8) {
+                        rng.gen_range(' '..='~')
+                    } else {
+                        rng.gen::<char>()
+                    }
+                })
+                .collect::<String>();
+            let result = validate_name(&name);
+            // decisions are stable
+            assert_eq!(result, validate_name(&name));
+            if result.is_ok() {
+                assert!(name.chars().count() <= MAX_NAME_LENGTH);
+                assert_eq!(name.trim(), name);
+            }
+        }
+    }
+}
@@ -49,11 +49,13 @@ pub enum ChatCommand {
     Ban,
     BattleMode,
     BattleModeForce,
+    Boss,
     Build,
     BuildAreaAdd,
     BuildAreaList,
     BuildAreaRemove,
     Campfire,
+    CombatLog,
     DebugColumn,
     DisconnectAllPlayers,
     DropAll,
@@ -356,6 +358,15 @@ impl ChatCommand {
                 "Change your battle mode flag without any checks",
                 Some(Admin),
             ),
+            ChatCommand::Boss => cmd(
+                vec![
+                    Enum("entity", ENTITIES.clone(), Required),
+                    Float("first phase", 0.66, Optional),
+                    Float("second phase", 0.33, Optional),
+                ],
+                "Spawn a boss that enters its next phases at the given health fractions",
+                Some(Admin),
+            ),
             ChatCommand::Build => cmd(vec![], "Toggles build mode on and off", None),
             ChatCommand::BuildAreaAdd => cmd(
                 vec![
@@ -377,6 +388,19 @@ impl ChatCommand {
                 Some(Admin),
             ),
             ChatCommand::Campfire => cmd(vec![], "Spawns a campfire", Some(Admin)),
+            ChatCommand::CombatLog => cmd(
+                vec![
+                    Integer("uid", 0, Required),
+                    Integer("last_n", 10, Optional),
+                    Enum(
+                        "format",
+                        vec!["text".to_owned(), "csv".to_owned()],
+                        Optional,
+                    ),
+                ],
+                "Shows the last damage dealt or received by the entity with the given uid",
+                Some(Admin),
+            ),
             ChatCommand::DebugColumn => cmd(
                 vec![Integer("x", 15000, Required), Integer("y", 15000, Required)],
                 "Prints some debug information about a column",
@@ -684,11 +708,13 @@ impl ChatCommand {
             ChatCommand::Ban => "ban",
             ChatCommand::BattleMode => "battlemode",
             ChatCommand::BattleModeForce => "battlemode_force",
+            ChatCommand::Boss => "boss",
             ChatCommand::Build => "build",
             ChatCommand::BuildAreaAdd => "build_area_add",
             ChatCommand::BuildAreaList => "build_area_list",
             ChatCommand::BuildAreaRemove => "build_area_remove",
             ChatCommand::Campfire => "campfire",
+            ChatCommand::CombatLog => "combat_log",
             ChatCommand::DebugColumn => "debug_column",
             ChatCommand::DisconnectAllPlayers => "disconnect_all_players",
             ChatCommand::DropAll => "dropall",
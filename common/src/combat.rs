@@ -4,7 +4,7 @@ use crate::{
     comp::{
         inventory::{
             item::{
-                armor::Protection,
+                armor::{Armor, Protection},
                 tool::{self, Tool, ToolKind},
                 Item, ItemDesc, ItemKind, MaterialStatManifest,
             },
@@ -21,10 +21,15 @@ use crate::{
     util::Dir,
 };
 
+#[cfg(not(target_arch = "wasm32"))]
+use hashbrown::HashMap;
+#[cfg(not(target_arch = "wasm32"))]
+use lazy_static::lazy_static;
 #[cfg(not(target_arch = "wasm32"))]
 use rand::{thread_rng, Rng};
 
 use serde::{Deserialize, Serialize};
+use std::ops::Add;
 
 use crate::{comp::Group, resources::Time};
 #[cfg(not(target_arch = "wasm32"))]
@@ -58,6 +63,7 @@ pub struct AttackerInfo<'a> {
     pub energy: Option<&'a Energy>,
     pub combo: Option<&'a Combo>,
     pub inventory: Option<&'a Inventory>,
+    pub skill_set: Option<&'a SkillSet>,
 }
 
 #[cfg(not(target_arch = "wasm32"))]
@@ -132,17 +138,15 @@ impl Attack {
 
     pub fn effects(&self) -> impl Iterator<Item = &AttackEffect> { self.effects.iter() }
 
-    pub fn compute_damage_reduction(
+    /// Returns the damage reduction from the target blocking the attack
+    pub fn compute_block_reduction(
         target: &TargetInfo,
         source: AttackSource,
         dir: Dir,
-        damage: Damage,
         mut emit: impl FnMut(ServerEvent),
         mut emit_outcome: impl FnMut(Outcome),
     ) -> f32 {
-        let damage_reduction =
-            Damage::compute_damage_reduction(Some(damage), target.inventory, target.stats);
-        let block_reduction = match source {
+        match source {
             AttackSource::Melee => {
                 if let (Some(CharacterState::BasicBlock(data)), Some(ori)) =
                     (target.char_state, target.ori)
@@ -172,8 +176,7 @@ impl Attack {
                 }
             },
             _ => 0.0,
-        };
-        1.0 - (1.0 - damage_reduction) * (1.0 - block_reduction)
+        }
     }
 
     pub fn apply_attack(
@@ -210,6 +213,15 @@ impl Attack {
                 && (target_dodging || !may_harm)
         };
         let is_crit = thread_rng().gen::<f32>() < self.crit_chance;
+        // Abilities don't know which hand they were used with, assume the main hand
+        let tool = attacker
+            .and_then(|a| a.inventory)
+            .and_then(|inv| get_weapons(inv).0);
+        let formula = DAMAGE_FORMULAS.get(tool);
+        let defense = DefenseContext {
+            inventory: target.inventory,
+            stats: target.stats,
+        };
         let mut is_applied = false;
         let mut accumulated_damage = 0.0;
         for damage in self
@@ -219,25 +231,53 @@ impl Attack {
             .filter(|d| !avoid_damage(d))
         {
             is_applied = true;
-            let damage_reduction = Attack::compute_damage_reduction(
+            let armor_reduction = Damage::compute_damage_reduction(
+                Some(damage.damage),
+                target.inventory,
+                target.stats,
+            );
+            let block_reduction = Attack::compute_block_reduction(
                 &target,
                 attack_source,
                 dir,
-                damage.damage,
                 &mut emit,
                 &mut emit_outcome,
             );
-            let change = damage.damage.calculate_health_change(
-                damage_reduction,
+            let damage_reduction = 1.0 - (1.0 - armor_reduction) * (1.0 - block_reduction);
+            // The formula already accounts for armor and the strength modifier
+            let value = formula.compute(
+                &AttackContext {
+                    damage: damage.damage,
+                    strength_modifier,
+                    skill_set: attacker.and_then(|a| a.skill_set),
+                },
+                &defense,
+            );
+            let change = Damage {
+                value,
+                ..damage.damage
+            }
+            .calculate_health_change(
+                block_reduction,
                 attacker.map(|x| x.into()),
                 is_crit,
                 self.crit_multiplier,
-                strength_modifier,
+                1.0,
                 time,
             );
             let applied_damage = -change.amount;
             accumulated_damage += applied_damage;
-            emit_outcome(Outcome::Damage { pos: target.pos });
+            let by = attacker.map(|a| a.uid);
+            emit_outcome(Outcome::Damage {
+                pos: target.pos,
+                by,
+            });
+            if is_crit {
+                emit_outcome(Outcome::CriticalHit {
+                    pos: target.pos,
+                    by,
+                });
+            }
             if change.amount.abs() > Health::HEALTH_EPSILON {
                 emit(ServerEvent::HealthChange {
                     entity: target.entity,
@@ -255,7 +295,9 @@ impl Attack {
                                     amount: -(energy_change - target_energy.current()),
                                     by: attacker.map(|x| x.into()),
                                     cause: Some(damage.damage.source),
+                                    kind: Some(damage.damage.kind),
                                     time,
+                                    crit: false,
                                 };
                                 emit(ServerEvent::HealthChange {
                                     entity: target.entity,
@@ -292,8 +334,12 @@ impl Attack {
                         }
                     },
                     // Piercing damage ignores some penetration, and is handled when damage
-                    // reduction is computed Energy is a placeholder damage type
-                    DamageKind::Piercing | DamageKind::Energy => {},
+                    // reduction is computed Energy is a placeholder damage type. Fire and poison
+                    // resistances are also part of the damage reduction
+                    DamageKind::Piercing
+                    | DamageKind::Energy
+                    | DamageKind::Fire
+                    | DamageKind::Poison => {},
                 }
                 for effect in damage.effects.iter() {
                     match effect {
@@ -335,7 +381,9 @@ impl Attack {
                                     amount: applied_damage * l,
                                     by: attacker.map(|a| a.into()),
                                     cause: None,
+                                    kind: None,
                                     time,
+                                    crit: false,
                                 };
                                 if change.amount.abs() > Health::HEALTH_EPSILON {
                                     emit(ServerEvent::HealthChange {
@@ -367,7 +415,9 @@ impl Attack {
                                 amount: *h * strength_modifier,
                                 by: attacker.map(|a| a.into()),
                                 cause: None,
+                                kind: None,
                                 time,
+                                crit: false,
                             };
                             if change.amount.abs() > Health::HEALTH_EPSILON {
                                 emit(ServerEvent::HealthChange {
@@ -478,7 +528,9 @@ impl Attack {
                                 amount: accumulated_damage * l,
                                 by: attacker.map(|a| a.into()),
                                 cause: None,
+                                kind: None,
                                 time,
+                                crit: false,
                             };
                             if change.amount.abs() > Health::HEALTH_EPSILON {
                                 emit(ServerEvent::HealthChange {
@@ -510,7 +562,9 @@ impl Attack {
                             amount: h * strength_modifier,
                             by: attacker.map(|a| a.into()),
                             cause: None,
+                            kind: None,
                             time,
+                            crit: false,
                         };
                         if change.amount.abs() > Health::HEALTH_EPSILON {
                             emit(ServerEvent::HealthChange {
@@ -658,6 +712,18 @@ pub enum CombatEffect {
     Combo(i32),
 }
 
+#[cfg(not(target_arch = "wasm32"))]
+impl CombatEffect {
+    /// Kind of the damage over time dealt by this effect, if it applies such a
+    /// buff
+    pub fn damage_kind(&self) -> Option<DamageKind> {
+        match self {
+            CombatEffect::Buff(buff) => buff.kind.damage_kind(),
+            _ => None,
+        }
+    }
+}
+
 #[cfg(not(target_arch = "wasm32"))]
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum CombatRequirement {
@@ -738,6 +804,70 @@ pub enum DamageKind {
     /// Catch all for remaining damage kinds (TODO: differentiate further with
     /// staff/sceptre reworks
     Energy,
+    /// Additionally reduced by fire resistance
+    Fire,
+    /// Additionally reduced by poison resistance
+    Poison,
+}
+
+/// Highest fraction of damage that resistances can ignore
+pub const MAX_RESISTANCE: f32 = 0.8;
+
+/// Resistances against elemental damage kinds, summed over armor and buffs.
+/// Every point is the fraction of damage of that kind which is ignored, on top
+/// of the reduction from protection.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Resistances {
+    pub fire: f32,
+    pub poison: f32,
+}
+
+impl Resistances {
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn from_armor(armor: &Armor) -> Self {
+        Self {
+            fire: armor.fire_resistance().unwrap_or(0.0),
+            poison: armor.poison_resistance().unwrap_or(0.0),
+        }
+    }
+
+    /// Summed resistance against `kind`, physical kinds are only reduced by
+    /// protection
+    pub fn get(&self, kind: DamageKind) -> f32 {
+        match kind {
+            DamageKind::Fire => self.fire,
+            DamageKind::Poison => self.poison,
+            DamageKind::Piercing
+            | DamageKind::Slashing
+            | DamageKind::Crushing
+            | DamageKind::Energy => 0.0,
+        }
+    }
+
+    pub fn add(&mut self, kind: DamageKind, value: f32) {
+        match kind {
+            DamageKind::Fire => self.fire += value,
+            DamageKind::Poison => self.poison += value,
+            DamageKind::Piercing
+            | DamageKind::Slashing
+            | DamageKind::Crushing
+            | DamageKind::Energy => {},
+        }
+    }
+
+    /// Fraction of damage of `kind` which is ignored
+    pub fn reduction(&self, kind: DamageKind) -> f32 { self.get(kind).clamp(0.0, MAX_RESISTANCE) }
+}
+
+impl Add for Resistances {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            fire: self.fire + other.fire,
+            poison: self.poison + other.poison,
+        }
+    }
 }
 
 const PIERCING_PENETRATION_FRACTION: f32 = 1.0;
@@ -788,7 +918,10 @@ impl Damage {
         } else {
             0.0
         };
-        1.0 - (1.0 - inventory_dr) * (1.0 - stats_dr)
+        let resistance = damage.map_or(0.0, |damage| {
+            compute_resistances(inventory, stats).reduction(damage.kind)
+        });
+        1.0 - (1.0 - inventory_dr) * (1.0 - stats_dr) * (1.0 - resistance)
     }
 
     pub fn calculate_health_change(
@@ -821,7 +954,9 @@ impl Damage {
                     amount: -damage,
                     by: damage_contributor,
                     cause: Some(self.source),
+                    kind: Some(self.kind),
                     time,
+                    crit: is_crit,
                 }
             },
             DamageSource::Falling => {
@@ -833,14 +968,18 @@ impl Damage {
                     amount: -damage,
                     by: None,
                     cause: Some(self.source),
+                    kind: Some(self.kind),
                     time,
+                    crit: false,
                 }
             },
             DamageSource::Buff(_) | DamageSource::Other => HealthChange {
                 amount: -damage,
                 by: None,
                 cause: Some(self.source),
+                kind: Some(self.kind),
                 time,
+                crit: false,
             },
         }
     }
@@ -851,6 +990,131 @@ impl Damage {
     }
 }
 
+/// Everything about the attacker a [`DamageFormula`] may depend on
+#[cfg(not(target_arch = "wasm32"))]
+pub struct AttackContext<'a> {
+    /// Damage of the attack, already modified by the ability and its skills
+    pub damage: Damage,
+    pub strength_modifier: f32,
+    pub skill_set: Option<&'a SkillSet>,
+}
+
+/// Everything about the defender a [`DamageFormula`] may depend on
+#[cfg(not(target_arch = "wasm32"))]
+pub struct DefenseContext<'a> {
+    pub inventory: Option<&'a Inventory>,
+    pub stats: Option<&'a Stats>,
+}
+
+/// Computes the damage an attack deals after armor, but before crits and
+/// blocking are applied.
+#[cfg(not(target_arch = "wasm32"))]
+pub trait DamageFormula: Send + Sync {
+    fn compute(&self, attacker: &AttackContext, defender: &DefenseContext) -> f32;
+}
+
+/// Used for tools without a formula of their own and when no tool is equipped
+#[cfg(not(target_arch = "wasm32"))]
+pub struct BaseDamageFormula;
+
+#[cfg(not(target_arch = "wasm32"))]
+impl DamageFormula for BaseDamageFormula {
+    fn compute(&self, attacker: &AttackContext, defender: &DefenseContext) -> f32 {
+        let armor_reduction = Damage::compute_damage_reduction(
+            Some(attacker.damage),
+            defender.inventory,
+            defender.stats,
+        );
+        attacker.damage.value * attacker.strength_modifier * (1.0 - armor_reduction)
+    }
+}
+
+/// Multiplier for the skill points earned in the tree of `tool`
+#[cfg(not(target_arch = "wasm32"))]
+fn weapon_mastery(attacker: &AttackContext, tool: ToolKind, per_sp: f32) -> f32 {
+    let earned_sp = attacker
+        .skill_set
+        .map_or(0, |s| s.earned_sp(SkillGroupKind::Weapon(tool)));
+    1.0 + earned_sp as f32 * per_sp
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub struct SwordDamageFormula;
+
+#[cfg(not(target_arch = "wasm32"))]
+impl DamageFormula for SwordDamageFormula {
+    fn compute(&self, attacker: &AttackContext, defender: &DefenseContext) -> f32 {
+        BaseDamageFormula.compute(attacker, defender)
+            * weapon_mastery(attacker, ToolKind::Sword, 0.005)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub struct AxeDamageFormula;
+
+#[cfg(not(target_arch = "wasm32"))]
+impl DamageFormula for AxeDamageFormula {
+    fn compute(&self, attacker: &AttackContext, defender: &DefenseContext) -> f32 {
+        // Axes hit hard but rely on their raw power, mastery matters less
+        BaseDamageFormula.compute(attacker, defender)
+            * weapon_mastery(attacker, ToolKind::Axe, 0.003)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub struct BowDamageFormula;
+
+#[cfg(not(target_arch = "wasm32"))]
+impl DamageFormula for BowDamageFormula {
+    fn compute(&self, attacker: &AttackContext, defender: &DefenseContext) -> f32 {
+        // Piercing damage already ignores part of the armor, see
+        // `Damage::compute_damage_reduction`
+        BaseDamageFormula.compute(attacker, defender)
+            * weapon_mastery(attacker, ToolKind::Bow, 0.004)
+    }
+}
+
+/// Maps each [`ToolKind`] to the [`DamageFormula`] used for its attacks
+#[cfg(not(target_arch = "wasm32"))]
+pub struct DamageFormulaRegistry {
+    formulas: HashMap<ToolKind, Box<dyn DamageFormula>>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl DamageFormulaRegistry {
+    /// A registry where every tool uses the [`BaseDamageFormula`]
+    pub fn empty() -> Self {
+        Self {
+            formulas: HashMap::new(),
+        }
+    }
+
+    pub fn register(&mut self, tool: ToolKind, formula: impl DamageFormula + 'static) {
+        self.formulas.insert(tool, Box::new(formula));
+    }
+
+    pub fn get(&self, tool: Option<ToolKind>) -> &dyn DamageFormula {
+        tool.and_then(|tool| self.formulas.get(&tool))
+            .map_or(&BaseDamageFormula as &dyn DamageFormula, |f| f.as_ref())
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Default for DamageFormulaRegistry {
+    fn default() -> Self {
+        let mut registry = Self::empty();
+        registry.register(ToolKind::Sword, SwordDamageFormula);
+        registry.register(ToolKind::Axe, AxeDamageFormula);
+        registry.register(ToolKind::Bow, BowDamageFormula);
+        registry
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+lazy_static! {
+    pub static ref DAMAGE_FORMULAS: DamageFormulaRegistry = DamageFormulaRegistry::default();
+}
+
 #[cfg(not(target_arch = "wasm32"))]
 #[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Knockback {
@@ -1183,6 +1447,23 @@ pub fn compute_stealth_coefficient(inventory: Option<&Inventory>) -> f32 {
     })
 }
 
+/// Computes the resistances against each damage kind from armor and buffs
+#[cfg(not(target_arch = "wasm32"))]
+pub fn compute_resistances(inventory: Option<&Inventory>, stats: Option<&Stats>) -> Resistances {
+    let armor = inventory.map_or_else(Resistances::default, |inv| {
+        inv.equipped_items()
+            .filter_map(|item| {
+                if let ItemKind::Armor(armor) = &item.kind() {
+                    Some(Resistances::from_armor(armor))
+                } else {
+                    None
+                }
+            })
+            .fold(Resistances::default(), Add::add)
+    });
+    armor + stats.map_or_else(Resistances::default, |stats| stats.resistances)
+}
+
 /// Computes the total protection provided from armor. Is used to determine the
 /// damage reduction applied to damage received by an entity None indicates that
 /// the armor equipped makes the entity invulnerable
@@ -1204,3 +1485,147 @@ pub fn compute_protection(inventory: Option<&Inventory>) -> Option<f32> {
             .sum::<Option<f32>>()
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::comp::inventory::{
+        item::armor::{self, ArmorKind},
+        loadout_builder::LoadoutBuilder,
+        test_helpers::get_test_chest,
+    };
+
+    fn fire_armor(kind: ArmorKind, fire_resistance: f32) -> Armor {
+        Armor::new(
+            kind,
+            armor::Stats::new(
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                Some(fire_resistance),
+                None,
+            ),
+        )
+    }
+
+    #[test]
+    fn resistances_stack_additively() {
+        let chest = fire_armor(ArmorKind::Chest("Test".to_string()), 0.3);
+        let hat = fire_armor(ArmorKind::Head("Test".to_string()), 0.3);
+        let total = Resistances::from_armor(&chest) + Resistances::from_armor(&hat);
+        assert!((total.reduction(DamageKind::Fire) - 0.6).abs() < f32::EPSILON);
+        assert_eq!(total.reduction(DamageKind::Poison), 0.0);
+        assert_eq!(total.reduction(DamageKind::Slashing), 0.0);
+
+        let total = total + Resistances::from_armor(&chest);
+        assert_eq!(total.reduction(DamageKind::Fire), MAX_RESISTANCE);
+    }
+
+    fn attack(kind: DamageKind) -> AttackContext<'static> {
+        AttackContext {
+            damage: Damage {
+                source: DamageSource::Melee,
+                kind,
+                value: 10.0,
+            },
+            strength_modifier: 1.0,
+            skill_set: None,
+        }
+    }
+
+    fn wearing(protection: Protection) -> Inventory {
+        Inventory::new_with_loadout(
+            LoadoutBuilder::empty()
+                .chest(Some(get_test_chest(protection)))
+                .build(),
+        )
+    }
+
+    fn formula_damage(attack: &AttackContext, inventory: &Inventory, stats: &Stats) -> f32 {
+        BaseDamageFormula.compute(attack, &DefenseContext {
+            inventory: Some(inventory),
+            stats: Some(stats),
+        })
+    }
+
+    #[test]
+    fn damage_formula_without_armor() {
+        let stats = Stats::empty();
+        let attack = attack(DamageKind::Slashing);
+        assert_eq!(
+            formula_damage(&attack, &Inventory::new_empty(), &stats),
+            10.0
+        );
+        assert_eq!(
+            formula_damage(&attack, &wearing(Protection::Normal(0.0)), &stats),
+            10.0
+        );
+        // Without skills the weapon formulas don't change anything either
+        let inventory = Inventory::new_empty();
+        let defender = DefenseContext {
+            inventory: Some(&inventory),
+            stats: Some(&stats),
+        };
+        assert_eq!(SwordDamageFormula.compute(&attack, &defender), 10.0);
+    }
+
+    #[test]
+    fn damage_formula_negative_armor() {
+        let stats = Stats::empty();
+        let attack = attack(DamageKind::Slashing);
+        // -20 / (60 + 20) = -25% damage reduction
+        let damage = formula_damage(&attack, &wearing(Protection::Normal(-20.0)), &stats);
+        assert!((damage - 12.5).abs() < 0.001, "{}", damage);
+        // Piercing can't penetrate negative armor any further
+        let damage = formula_damage(
+            &self::attack(DamageKind::Piercing),
+            &wearing(Protection::Normal(-20.0)),
+            &stats,
+        );
+        assert!((damage - 12.5).abs() < 0.001, "{}", damage);
+    }
+
+    #[test]
+    fn damage_formula_cap() {
+        let mut stats = Stats::empty();
+        let attack = attack(DamageKind::Fire);
+        // Armor never blocks all damage, unless it's invincible
+        let damage = formula_damage(&attack, &wearing(Protection::Normal(1.0e6)), &stats);
+        assert!(damage > 0.0 && damage < 0.001, "{}", damage);
+        assert_eq!(
+            formula_damage(&attack, &wearing(Protection::Invincible), &stats),
+            0.0
+        );
+        // Resistances are capped
+        stats.resistances.add(DamageKind::Fire, 5.0);
+        let damage = formula_damage(&attack, &Inventory::new_empty(), &stats);
+        assert!(
+            (damage - 10.0 * (1.0 - MAX_RESISTANCE)).abs() < 0.001,
+            "{}",
+            damage
+        );
+    }
+
+    #[test]
+    fn resistance_reduces_matching_damage() {
+        let mut stats = Stats::empty();
+        stats.resistances.add(DamageKind::Fire, 0.5);
+        let damage = |kind| Damage {
+            source: DamageSource::Projectile,
+            kind,
+            value: 10.0,
+        };
+        let fire =
+            Damage::compute_damage_reduction(Some(damage(DamageKind::Fire)), None, Some(&stats));
+        let physical = Damage::compute_damage_reduction(
+            Some(damage(DamageKind::Crushing)),
+            None,
+            Some(&stats),
+        );
+        assert!((fire - 0.5).abs() < f32::EPSILON);
+        assert_eq!(physical, 0.0);
+    }
+}
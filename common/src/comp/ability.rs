@@ -130,6 +130,17 @@ impl ActiveAbilities {
         }
     }
 
+    /// Id of the ability bound to an input, cooldowns are tracked by it
+    pub fn ability_id<'a>(
+        &self,
+        input: AbilityInput,
+        inventory: Option<&'a Inventory>,
+        skill_set: Option<&SkillSet>,
+    ) -> Option<&'a str> {
+        self.get_ability(input, inventory, skill_set)
+            .ability_id(inventory)
+    }
+
     /// Returns the CharacterAbility from an ability input, and also whether the
     /// ability was from a weapon wielded in the offhand
     pub fn activate_ability(
@@ -225,6 +236,7 @@ impl ActiveAbilities {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum AbilityInput {
     Primary,
     Secondary,
@@ -402,7 +414,8 @@ impl From<&CharacterState> for CharacterAbilityType {
             | CharacterState::SpriteSummon(_)
             | CharacterState::UseItem(_)
             | CharacterState::SpriteInteract(_)
-            | CharacterState::Wallrun(_) => Self::Other,
+            | CharacterState::Wallrun(_)
+            | CharacterState::Fishing(_) => Self::Other,
         }
     }
 }
@@ -1171,11 +1184,12 @@ impl CharacterAbility {
                 ref mut is_interruptible,
                 ref mut speed_increase,
                 ref mut max_speed_increase,
-                ref stage_data,
+                ref mut stage_data,
                 ref mut max_energy_gain,
                 ref mut scales_from_combo,
                 ..
             } => {
+                let modifiers = SKILL_MODIFIERS.sword_tree.combo;
                 *is_interruptible = skillset.has_skill(Sword(InterruptingAttacks));
 
                 if skillset.has_skill(Sword(TsCombo)) {
@@ -1186,6 +1200,13 @@ impl CharacterAbility {
                 } else {
                     *speed_increase = 0.0;
                     *max_speed_increase = 0.0;
+                    stage_data.truncate(modifiers.untrained_stages);
+                }
+                if let Ok(level) = skillset.skill_level(Sword(TsSpeed)) {
+                    let combo_window = modifiers.combo_window.powi(level.into());
+                    for stage in stage_data.iter_mut() {
+                        stage.combo_window *= combo_window;
+                    }
                 }
 
                 let energy_level = skillset.skill_level(Sword(TsRegen)).unwrap_or(0);
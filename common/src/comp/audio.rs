@@ -0,0 +1,45 @@
+use serde::{Deserialize, Serialize};
+use specs::{Component, DerefFlaggedStorage};
+use specs_idvs::IdvStorage;
+
+/// The looping sounds an [`AudioEmitter`] can play, the client decides which
+/// sound files belong to each kind
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum AudioEmitterKind {
+    Campfire,
+    Waterfall,
+}
+
+/// Plays a looping sound at the position of the entity for as long as the
+/// component is attached
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AudioEmitter {
+    pub kind: AudioEmitterKind,
+    pub volume: f32,
+    /// Distance in blocks at which the sound has faded out completely
+    pub range: f32,
+}
+
+impl AudioEmitter {
+    pub fn new(kind: AudioEmitterKind) -> Self {
+        Self {
+            kind,
+            volume: 1.0,
+            range: 40.0,
+        }
+    }
+
+    /// Volume heard at `dist` blocks from the emitter, fading out linearly
+    /// until `range`
+    pub fn volume_at(&self, dist: f32) -> f32 {
+        if self.range > 0.0 {
+            self.volume * (1.0 - dist / self.range).clamp(0.0, 1.0)
+        } else {
+            0.0
+        }
+    }
+}
+
+impl Component for AudioEmitter {
+    type Storage = DerefFlaggedStorage<Self, IdvStorage<Self>>;
+}
@@ -98,6 +98,7 @@ make_case_elim!(
         GnarlingTotemRed = 83,
         GnarlingTotemGreen = 84,
         GnarlingTotemWhite = 85,
+        FishingBobber = 86,
     }
 );
 
@@ -108,7 +109,7 @@ impl Body {
     }
 }
 
-pub const ALL_OBJECTS: [Body; 86] = [
+pub const ALL_OBJECTS: [Body; 87] = [
     Body::Arrow,
     Body::Bomb,
     Body::Scarecrow,
@@ -195,6 +196,7 @@ pub const ALL_OBJECTS: [Body; 86] = [
     Body::GnarlingTotemRed,
     Body::GnarlingTotemWhite,
     Body::GnarlingTotemGreen,
+    Body::FishingBobber,
 ];
 
 impl From<Body> for super::Body {
@@ -290,6 +292,7 @@ impl Body {
             Body::GnarlingTotemRed => "gnarling_totem_red",
             Body::GnarlingTotemGreen => "gnarling_totem_green",
             Body::GnarlingTotemWhite => "gnarling_totem_white",
+            Body::FishingBobber => "fishing_bobber",
         }
     }
 
@@ -315,6 +318,7 @@ impl Body {
             Body::Scarecrow => 900.0,
             Body::TrainingDummy => 2000.0,
             Body::Snowball => 0.9 * WATER_DENSITY,
+            Body::FishingBobber => 0.5 * WATER_DENSITY,
             // let them sink
             _ => 1.1 * WATER_DENSITY,
         };
@@ -400,6 +404,7 @@ impl Body {
             Body::Hive => 2.0,
             Body::Coconut => 2.0,
             Body::GnarlingTotemRed | Body::GnarlingTotemGreen | Body::GnarlingTotemWhite => 100.0,
+            Body::FishingBobber => 0.05,
         };
 
         Mass(m)
@@ -420,6 +425,7 @@ impl Body {
             Body::GnarlingTotemRed | Body::GnarlingTotemGreen | Body::GnarlingTotemWhite => {
                 Vec3::new(0.8, 0.8, 1.4)
             },
+            Body::FishingBobber => Vec3::new(0.3, 0.3, 0.5),
             // FIXME: this *must* be exhaustive match
             _ => Vec3::broadcast(0.5),
         }
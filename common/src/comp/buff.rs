@@ -1,5 +1,5 @@
 #![allow(clippy::nonstandard_macro_braces)] //tmp as of false positive !?
-use crate::uid::Uid;
+use crate::{combat::DamageKind, uid::Uid};
 use core::{cmp::Ordering, time::Duration};
 #[cfg(not(target_arch = "wasm32"))]
 use hashbrown::HashMap;
@@ -111,6 +111,16 @@ impl BuffKind {
 
     /// Checks if buff should queue
     pub fn queues(self) -> bool { matches!(self, BuffKind::Saturation) }
+
+    /// Kind of the damage dealt over time by this buff, which decides the
+    /// resistance that reduces it
+    pub fn damage_kind(self) -> Option<DamageKind> {
+        match self {
+            BuffKind::Burning => Some(DamageKind::Fire),
+            BuffKind::Poisoned => Some(DamageKind::Poison),
+            _ => None,
+        }
+    }
 }
 
 // Struct used to store data relevant to a buff
@@ -165,6 +175,9 @@ pub enum BuffEffect {
     MaxEnergyModifier { value: f32, kind: ModifierKind },
     /// Reduces damage after armor is accounted for by this fraction
     DamageReduction(f32),
+    /// Adds to the resistance against a damage kind, see
+    /// [`Resistances`](crate::combat::Resistances)
+    Resistance(DamageKind, f32),
     /// Gradually changes an entities max health over time
     MaxHealthChangeOverTime {
         rate: f32,
@@ -180,6 +193,20 @@ pub enum BuffEffect {
     GroundFriction(f32),
 }
 
+/// Over time effects are applied in steps, once the accumulated change reaches
+/// a second worth of `rate` or `max_step`, whichever is smaller, and when the
+/// buff runs out
+#[cfg(not(target_arch = "wasm32"))]
+pub fn over_time_step_due(
+    accumulated: f32,
+    rate: f32,
+    max_step: f32,
+    remaining: Option<Duration>,
+) -> bool {
+    accumulated.abs() > rate.abs().min(max_step)
+        || remaining.map_or(false, |dur| dur == Duration::default())
+}
+
 /// Actual de/buff.
 /// Buff can timeout after some time if `time` is Some. If `time` is None,
 /// Buff will last indefinitely, until removed manually (by some action, like
@@ -345,7 +372,10 @@ impl Buff {
                 data.duration,
             ),
             BuffKind::Wet => (
-                vec![BuffEffect::GroundFriction(1.0 - nn_scaling(data.strength))],
+                vec![
+                    BuffEffect::GroundFriction(1.0 - nn_scaling(data.strength)),
+                    BuffEffect::Resistance(DamageKind::Fire, nn_scaling(data.strength) * 0.5),
+                ],
                 data.duration,
             ),
             BuffKind::Ensnared => (
@@ -522,3 +552,48 @@ pub type BuffId = u64;
 impl Component for Buffs {
     type Storage = DerefFlaggedStorage<Self, IdvStorage<Self>>;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Counts how often an over time effect is applied within `secs` at 60
+    /// ticks per second
+    fn steps(rate: f32, max_step: f32, secs: u32) -> u32 {
+        let dt = 1.0 / 60.0;
+        let mut accumulated = 0.0;
+        let mut steps = 0;
+        for _ in 0..secs * 60 {
+            accumulated += rate * dt;
+            if over_time_step_due(accumulated, rate, max_step, Some(Duration::from_secs(1))) {
+                accumulated = 0.0;
+                steps += 1;
+            }
+        }
+        steps
+    }
+
+    #[test]
+    fn over_time_cadence() {
+        // Slow effects apply a bit less than once a second, as the accumulated
+        // change has to exceed the rate
+        assert_eq!(steps(-0.5, 1.0, 10), 9);
+        // Fast effects apply once they exceed `max_step`, here every 7 ticks
+        assert_eq!(steps(-10.0, 1.0, 10), 85);
+        // Running out flushes whatever accumulated
+        assert!(over_time_step_due(
+            -0.1,
+            -5.0,
+            1.0,
+            Some(Duration::default())
+        ));
+        assert!(!over_time_step_due(-0.1, -5.0, 1.0, None));
+    }
+
+    #[test]
+    fn over_time_damage_kinds() {
+        assert_eq!(BuffKind::Burning.damage_kind(), Some(DamageKind::Fire));
+        assert_eq!(BuffKind::Poisoned.damage_kind(), Some(DamageKind::Poison));
+        assert_eq!(BuffKind::Bleeding.damage_kind(), None);
+    }
+}
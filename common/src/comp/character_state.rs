@@ -25,6 +25,8 @@ pub struct StateUpdate {
     pub density: Density,
     pub energy: Energy,
     pub swap_equipped_weapons: bool,
+    /// Id of the ability that was just used, if it has a cooldown
+    pub started_cooldown: Option<String>,
     pub should_strafe: bool,
     pub queued_inputs: BTreeMap<InputKind, InputAttr>,
     pub removed_inputs: Vec<InputKind>,
@@ -54,6 +56,7 @@ impl From<&JoinData<'_>> for StateUpdate {
             density: *data.density,
             energy: *data.energy,
             swap_equipped_weapons: false,
+            started_cooldown: None,
             should_strafe: data.inputs.strafing,
             character: data.character.clone(),
             queued_inputs: BTreeMap::new(),
@@ -123,6 +126,8 @@ pub enum CharacterState {
     SpriteInteract(sprite_interact::Data),
     /// Runs on the wall
     Wallrun(wallrun::Data),
+    /// Waits for a fish to bite, with the line of a fishing rod cast
+    Fishing(fishing::Data),
 }
 
 impl CharacterState {
@@ -130,6 +135,7 @@ impl CharacterState {
         matches!(
             self,
             CharacterState::Wielding(_)
+                | CharacterState::Fishing(_)
                 | CharacterState::BasicMelee(_)
                 | CharacterState::BasicRanged(_)
                 | CharacterState::DashMelee(_)
@@ -329,6 +335,7 @@ impl CharacterState {
             CharacterState::SpriteSummon(data) => data.behavior(j, output_events),
             CharacterState::UseItem(data) => data.behavior(j, output_events),
             CharacterState::SpriteInteract(data) => data.behavior(j, output_events),
+            CharacterState::Fishing(data) => data.behavior(j, output_events),
         }
     }
 
@@ -375,6 +382,7 @@ impl CharacterState {
             CharacterState::SpriteSummon(data) => data.handle_event(j, output_events, action),
             CharacterState::UseItem(data) => data.handle_event(j, output_events, action),
             CharacterState::SpriteInteract(data) => data.handle_event(j, output_events, action),
+            CharacterState::Fishing(data) => data.handle_event(j, output_events, action),
         }
     }
 }
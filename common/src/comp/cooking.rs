@@ -0,0 +1,217 @@
+use crate::{
+    comp::{
+        item::{tool::AbilityMap, MaterialStatManifest},
+        Item,
+    },
+    recipe::{Recipe, RecipeInput},
+};
+use serde::{Deserialize, Serialize};
+use specs::Component;
+use specs_idvs::IdvStorage;
+use std::sync::Arc;
+
+/// Key of a recipe in the recipe book
+pub type RecipeId = String;
+
+/// Seconds of cooking a dish needs
+pub const COOKING_DURATION: f32 = 20.0;
+/// Seconds a pot keeps burning on one log of wood
+pub const FUEL_PER_LOG: f32 = 60.0;
+/// Item burnt to refuel a pot
+pub const FUEL_ITEM: &str = "common.items.log.wood";
+
+/// State of a cooking pot, it cooks the loaded recipe from the ingredients put
+/// into it as long as it has fuel left
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct CookingPot {
+    /// Ingredients and cooked dishes in the pot
+    pub contents: Vec<Item>,
+    /// Seconds the pot can still cook for
+    pub fuel_remaining: f32,
+    pub recipe: Option<RecipeId>,
+    /// Fraction of the current dish that was cooked, reset once it's done
+    pub progress: f32,
+}
+
+impl CookingPot {
+    pub fn refuel(&mut self, seconds: f32) { self.fuel_remaining += seconds; }
+
+    /// Whether the pot holds enough ingredients to cook `recipe` once. Inputs
+    /// that aren't consumed, like tools, aren't put into the pot.
+    pub fn has_ingredients(&self, recipe: &Recipe) -> bool {
+        recipe
+            .inputs()
+            .filter(|(_, needed)| *needed > 0)
+            .all(|(input, needed)| self.amount_of(input) >= needed)
+    }
+
+    /// Takes the cooked dishes out of the pot, that is everything that isn't
+    /// an ingredient of `recipe`. Without a recipe the whole pot is emptied.
+    pub fn take_dishes(&mut self, recipe: Option<&Recipe>) -> Vec<Item> {
+        let is_ingredient = |item: &Item| {
+            recipe.map_or(false, |recipe| {
+                recipe
+                    .inputs()
+                    .any(|(input, needed)| needed > 0 && item.matches_recipe_input(input))
+            })
+        };
+        let (ingredients, dishes): (Vec<_>, Vec<_>) =
+            self.contents.drain(..).partition(is_ingredient);
+        self.contents = ingredients;
+        dishes
+    }
+
+    fn amount_of(&self, input: &RecipeInput) -> u32 {
+        self.contents
+            .iter()
+            .filter(|item| item.matches_recipe_input(input))
+            .map(|item| item.amount())
+            .sum()
+    }
+
+    /// Cooks for `dt` seconds, `recipe` is the loaded one and `None` if it
+    /// doesn't exist. Returns whether a dish was finished.
+    pub fn tick(
+        &mut self,
+        dt: f32,
+        recipe: Option<&Recipe>,
+        ability_map: &AbilityMap,
+        msm: &MaterialStatManifest,
+    ) -> bool {
+        let recipe = match recipe.filter(|recipe| self.has_ingredients(recipe)) {
+            Some(recipe) => recipe,
+            None => {
+                self.progress = 0.0;
+                return false;
+            },
+        };
+        if self.fuel_remaining <= 0.0 {
+            return false;
+        }
+
+        let cooked = dt.min(self.fuel_remaining);
+        self.fuel_remaining -= cooked;
+        self.progress += cooked / COOKING_DURATION;
+        if self.progress < 1.0 {
+            return false;
+        }
+
+        for (input, needed) in recipe.inputs() {
+            self.consume(input, needed);
+        }
+        let (item_def, amount) = &recipe.output;
+        let mut dish = Item::new_from_item_def(Arc::clone(item_def), &[], ability_map, msm);
+        if dish.is_stackable() {
+            let _ = dish.set_amount(*amount);
+            self.contents.push(dish);
+        } else {
+            for _ in 1..*amount {
+                self.contents.push(dish.duplicate(ability_map, msm));
+            }
+            self.contents.push(dish);
+        }
+        self.progress = 0.0;
+        true
+    }
+
+    /// Removes `needed` of the items matching `input` from the contents
+    fn consume(&mut self, input: &RecipeInput, mut needed: u32) {
+        let mut i = 0;
+        while needed > 0 && i < self.contents.len() {
+            let item = &mut self.contents[i];
+            if item.matches_recipe_input(input) {
+                let amount = item.amount();
+                if amount <= needed {
+                    needed -= amount;
+                    self.contents.remove(i);
+                    continue;
+                }
+                let _ = item.decrease_amount(needed);
+                needed = 0;
+            }
+            i += 1;
+        }
+    }
+}
+
+impl Component for CookingPot {
+    type Storage = IdvStorage<Self>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recipe::default_recipe_book;
+
+    fn fish(amount: u32) -> Item {
+        let mut fish = Item::new_from_asset_expect("common.items.food.meat.fish_raw");
+        fish.set_amount(amount).unwrap();
+        fish
+    }
+
+    fn cook(pot: &mut CookingPot, recipe: Option<&Recipe>, seconds: f32) -> u32 {
+        let (ability_map, msm) = (AbilityMap::default(), MaterialStatManifest::default());
+        (0..(seconds * 10.0) as u32)
+            .filter(|_| pot.tick(0.1, recipe, &ability_map, &msm))
+            .count() as u32
+    }
+
+    #[test]
+    fn cooks_only_with_fuel_and_ingredients() {
+        let book = default_recipe_book().read();
+        let recipe = book.get("fish_cooked");
+        assert!(recipe.is_some());
+
+        let mut pot = CookingPot::default();
+        pot.refuel(FUEL_PER_LOG);
+        assert_eq!(cook(&mut pot, recipe, COOKING_DURATION * 2.0), 0);
+        assert_eq!(pot.fuel_remaining, FUEL_PER_LOG);
+
+        pot.contents.push(fish(1));
+        assert_eq!(cook(&mut pot, None, COOKING_DURATION * 2.0), 0);
+
+        let mut pot = CookingPot {
+            contents: vec![fish(1)],
+            ..Default::default()
+        };
+        assert_eq!(cook(&mut pot, recipe, COOKING_DURATION * 2.0), 0);
+        assert_eq!(pot.progress, 0.0);
+    }
+
+    #[test]
+    fn finished_dish_replaces_ingredients() {
+        let book = default_recipe_book().read();
+        let recipe = book.get("fish_cooked");
+        let mut pot = CookingPot {
+            contents: vec![fish(3)],
+            ..Default::default()
+        };
+        pot.refuel(COOKING_DURATION * 2.5);
+
+        assert_eq!(cook(&mut pot, recipe, COOKING_DURATION * 3.0), 2);
+        assert_eq!(pot.fuel_remaining, 0.0);
+        assert!(pot.progress > 0.4 && pot.progress < 0.6);
+
+        let ids = pot
+            .contents
+            .iter()
+            .map(|item| (item.item_definition_id(), item.amount()))
+            .collect::<Vec<_>>();
+        assert_eq!(ids, [
+            ("common.items.food.meat.fish_raw", 1),
+            ("common.items.food.meat.fish_cooked", 1),
+            ("common.items.food.meat.fish_cooked", 1),
+        ]);
+
+        let dishes = pot.take_dishes(recipe);
+        assert_eq!(dishes.len(), 2);
+        assert!(
+            dishes
+                .iter()
+                .all(|item| item.item_definition_id() == "common.items.food.meat.fish_cooked")
+        );
+        assert_eq!(pot.contents.len(), 1);
+        assert_eq!(pot.take_dishes(None).len(), 1);
+        assert!(pot.contents.is_empty());
+    }
+}
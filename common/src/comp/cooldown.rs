@@ -0,0 +1,110 @@
+use crate::{
+    assets::{self, Asset, AssetExt, AssetHandle},
+    resources::GameTime,
+};
+use hashbrown::HashMap;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use specs::{Component, DerefFlaggedStorage};
+use specs_idvs::IdvStorage;
+
+/// Cooldowns of abilities in seconds, by ability id. Abilities that aren't
+/// listed can be used again right away.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct CooldownManifest(HashMap<String, f64>);
+
+impl Asset for CooldownManifest {
+    type Loader = assets::RonLoader;
+
+    const EXTENSION: &'static str = "ron";
+}
+
+impl CooldownManifest {
+    pub fn cooldown(&self, ability_id: &str) -> Option<f64> { self.0.get(ability_id).copied() }
+}
+
+lazy_static! {
+    pub static ref COOLDOWN_MANIFEST: AssetHandle<CooldownManifest> =
+        CooldownManifest::load_expect("common.abilities.cooldowns");
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Cooldown {
+    pub started_at: GameTime,
+    pub ready_at: GameTime,
+}
+
+impl Cooldown {
+    /// Part of the cooldown that is still left, from 1.0 right after the
+    /// ability was used down to 0.0 once it's ready
+    pub fn remaining_fraction(&self, now: GameTime) -> f32 {
+        let duration = self.ready_at.0 - self.started_at.0;
+        if duration <= 0.0 {
+            0.0
+        } else {
+            ((self.ready_at.0 - now.0) / duration).clamp(0.0, 1.0) as f32
+        }
+    }
+}
+
+/// Abilities of an entity that can't be used again yet, by ability id. The
+/// server starts the cooldowns, clients only read them to show when
+/// abilities will be ready and to avoid requesting abilities that would be
+/// rejected anyway.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Cooldowns {
+    cooldowns: HashMap<String, Cooldown>,
+}
+
+impl Cooldowns {
+    pub fn start(&mut self, ability_id: &str, now: GameTime, duration: f64) {
+        self.cooldowns.insert(ability_id.to_owned(), Cooldown {
+            started_at: now,
+            ready_at: GameTime(now.0 + duration),
+        });
+    }
+
+    pub fn get(&self, ability_id: &str, now: GameTime) -> Option<&Cooldown> {
+        self.cooldowns
+            .get(ability_id)
+            .filter(|cooldown| cooldown.ready_at.0 > now.0)
+    }
+
+    pub fn is_ready(&self, ability_id: &str, now: GameTime) -> bool {
+        self.get(ability_id, now).is_none()
+    }
+
+    /// Forgets cooldowns that ran out, returns whether there were any
+    pub fn remove_expired(&mut self, now: GameTime) -> bool {
+        let len = self.cooldowns.len();
+        self.cooldowns
+            .retain(|_, cooldown| cooldown.ready_at.0 > now.0);
+        self.cooldowns.len() != len
+    }
+}
+
+impl Component for Cooldowns {
+    type Storage = DerefFlaggedStorage<Self, IdvStorage<Self>>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cooldowns_run_out() {
+        let mut cooldowns = Cooldowns::default();
+        cooldowns.start("spin", GameTime(10.0), 4.0);
+        assert!(!cooldowns.is_ready("spin", GameTime(10.0)));
+        assert!(cooldowns.is_ready("leap", GameTime(10.0)));
+
+        let cooldown = cooldowns.get("spin", GameTime(11.0)).unwrap();
+        assert_eq!(cooldown.remaining_fraction(GameTime(11.0)), 0.75);
+        assert_eq!(cooldown.remaining_fraction(GameTime(20.0)), 0.0);
+
+        assert!(cooldowns.is_ready("spin", GameTime(14.0)));
+        assert!(!cooldowns.remove_expired(GameTime(13.0)));
+        assert!(cooldowns.remove_expired(GameTime(14.0)));
+        assert_eq!(cooldowns, Cooldowns::default());
+    }
+}
@@ -0,0 +1,165 @@
+use crate::{resources::Time, terrain::SpriteKind};
+use serde::{Deserialize, Serialize};
+use specs::Component;
+use specs_idvs::IdvStorage;
+
+/// How long a crop keeps growing after it was watered, in seconds
+pub const WATERED_DURATION: f64 = 24.0 * 3600.0;
+/// Server ticks a crop with a fertility of 1.0 needs to grow by one stage, 10
+/// minutes at 30 ticks per second
+pub const TICKS_PER_STAGE: u32 = 18_000;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum CropKind {
+    Carrot,
+    Tomato,
+    Cabbage,
+    Flax,
+}
+
+impl CropKind {
+    /// The crop growing in a sprite, `None` if it's not a crop
+    pub fn from_sprite(sprite: SpriteKind) -> Option<Self> {
+        match sprite {
+            SpriteKind::Carrot => Some(CropKind::Carrot),
+            SpriteKind::Tomato => Some(CropKind::Tomato),
+            SpriteKind::Cabbage => Some(CropKind::Cabbage),
+            SpriteKind::Flax => Some(CropKind::Flax),
+            _ => None,
+        }
+    }
+
+    pub fn max_stages(self) -> u8 {
+        match self {
+            CropKind::Carrot | CropKind::Flax => 3,
+            CropKind::Tomato | CropKind::Cabbage => 4,
+        }
+    }
+
+    /// Item and amount of it a ripe crop yields
+    pub fn harvest(self) -> (&'static str, u32) {
+        match self {
+            CropKind::Carrot => ("common.items.food.carrot", 3),
+            CropKind::Tomato => ("common.items.food.tomato", 2),
+            CropKind::Cabbage => ("common.items.food.lettuce", 1),
+            CropKind::Flax => ("common.items.flowers.wild_flax", 2),
+        }
+    }
+}
+
+/// A crop that is tended to by players. It only grows while it was watered
+/// recently and can be harvested once it reached its last stage.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FarmPlot {
+    pub crop: CropKind,
+    pub growth_stage: u8,
+    pub max_stages: u8,
+    pub last_watered: Option<Time>,
+    /// Crops on fertile ground grow faster, must be positive
+    pub fertility: f32,
+    /// Ticks the crop grew since it reached the current stage
+    growth_ticks: u32,
+}
+
+impl FarmPlot {
+    pub fn new(crop: CropKind) -> Self {
+        Self {
+            crop,
+            growth_stage: 0,
+            max_stages: crop.max_stages(),
+            last_watered: None,
+            fertility: 1.0,
+            growth_ticks: 0,
+        }
+    }
+
+    pub fn water(&mut self, time: Time) { self.last_watered = Some(time); }
+
+    pub fn is_watered(&self, time: Time) -> bool {
+        self.last_watered
+            .map_or(false, |watered| time.0 - watered.0 < WATERED_DURATION)
+    }
+
+    pub fn is_harvestable(&self) -> bool { self.growth_stage >= self.max_stages }
+
+    /// Grows the crop by one server tick, returns whether it reached the next
+    /// stage
+    pub fn tick(&mut self, time: Time) -> bool {
+        if self.is_harvestable() || !self.is_watered(time) {
+            return false;
+        }
+        self.growth_ticks += 1;
+        let ticks_needed = (TICKS_PER_STAGE as f32 / self.fertility.max(0.01)).ceil() as u32;
+        if self.growth_ticks >= ticks_needed {
+            self.growth_ticks = 0;
+            self.growth_stage += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Resets a ripe crop and returns the item and amount it yields
+    pub fn harvest(&mut self) -> Option<(&'static str, u32)> {
+        if self.is_harvestable() {
+            self.growth_stage = 0;
+            self.growth_ticks = 0;
+            Some(self.crop.harvest())
+        } else {
+            None
+        }
+    }
+}
+
+impl Component for FarmPlot {
+    type Storage = IdvStorage<Self>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grow(plot: &mut FarmPlot, time: Time, ticks: u32) -> u32 {
+        (0..ticks).filter(|_| plot.tick(time)).count() as u32
+    }
+
+    #[test]
+    fn only_watered_crops_grow() {
+        let mut plot = FarmPlot::new(CropKind::Carrot);
+        assert_eq!(grow(&mut plot, Time(0.0), TICKS_PER_STAGE), 0);
+
+        plot.water(Time(0.0));
+        assert_eq!(grow(&mut plot, Time(10.0), TICKS_PER_STAGE), 1);
+        assert_eq!(plot.growth_stage, 1);
+
+        // Dried out a day later
+        assert_eq!(grow(&mut plot, Time(WATERED_DURATION), TICKS_PER_STAGE), 0);
+    }
+
+    #[test]
+    fn fertility_speeds_up_growth() {
+        let mut plot = FarmPlot::new(CropKind::Tomato);
+        plot.fertility = 2.0;
+        plot.water(Time(0.0));
+        assert_eq!(grow(&mut plot, Time(0.0), TICKS_PER_STAGE), 2);
+
+        let mut plot = FarmPlot::new(CropKind::Tomato);
+        plot.fertility = 0.5;
+        plot.water(Time(0.0));
+        assert_eq!(grow(&mut plot, Time(0.0), TICKS_PER_STAGE * 2 - 1), 0);
+        assert_eq!(grow(&mut plot, Time(0.0), 1), 1);
+    }
+
+    #[test]
+    fn harvest_resets_ripe_crops() {
+        let mut plot = FarmPlot::new(CropKind::Flax);
+        plot.water(Time(0.0));
+        assert_eq!(plot.harvest(), None);
+
+        assert_eq!(grow(&mut plot, Time(0.0), TICKS_PER_STAGE * 10), 3);
+        assert!(plot.is_harvestable());
+        assert_eq!(plot.harvest(), Some(CropKind::Flax.harvest()));
+        assert_eq!(plot.growth_stage, 0);
+        assert!(!plot.is_harvestable());
+    }
+}
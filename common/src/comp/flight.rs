@@ -0,0 +1,156 @@
+use hashbrown::HashMap;
+use serde::{Deserialize, Serialize};
+use specs::Component;
+use specs_idvs::IdvStorage;
+use vek::*;
+
+/// Glide ratio of a glider with wings of aspect ratio 1, see
+/// [`GlideState::new`]
+const BASE_GLIDE_RATIO: f32 = 4.0;
+/// Altitude over which the air gets thinner by a factor of e, lowering the
+/// glide ratio of gliders flying that high
+const THIN_AIR_HEIGHT: f32 = 4000.0;
+/// Log base 2 of the size of the cells [`Thermals`] are indexed by
+const THERMAL_CELL_SIZE_LG: u32 = 6;
+
+/// Flight characteristics of an entity, only present while it is gliding
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct GlideState {
+    /// Distance flown forward per distance sunk, in thick air
+    pub glide_ratio: f32,
+    pub aspect_ratio: f32,
+}
+
+impl GlideState {
+    /// Longer, narrower wings glide further
+    pub fn new(aspect_ratio: f32) -> Self {
+        let aspect_ratio = aspect_ratio.max(1.0);
+        Self {
+            glide_ratio: BASE_GLIDE_RATIO * aspect_ratio.sqrt(),
+            aspect_ratio,
+        }
+    }
+
+    /// Glide ratio at `altitude` in blocks, which drops as the air thins out
+    pub fn glide_ratio_at(&self, altitude: f32) -> f32 {
+        self.glide_ratio * (-altitude.max(0.0) / THIN_AIR_HEIGHT).exp()
+    }
+
+    /// Aspect ratio of wings that glide as well in thick air as these do at
+    /// `altitude`. Lift and drag of the wings come from the fluid dynamics, so
+    /// thin air is applied to them as a shorter wingspan.
+    pub fn aspect_ratio_at(&self, altitude: f32) -> f32 {
+        // The glide ratio grows with the square root of the aspect ratio
+        self.aspect_ratio * (self.glide_ratio_at(altitude) / self.glide_ratio).powi(2)
+    }
+}
+
+impl Component for GlideState {
+    type Storage = IdvStorage<Self>;
+}
+
+/// Rising column of warm air, gliders inside it gain altitude
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ThermalColumn {
+    pub center: Vec2<f32>,
+    pub radius: f32,
+    /// Speed the air rises at in the center of the column, fading out towards
+    /// its edge
+    pub updraft: f32,
+}
+
+impl ThermalColumn {
+    pub fn updraft_at(&self, pos: Vec2<f32>) -> f32 {
+        let dist = self.center.distance(pos);
+        self.updraft * (1.0 - dist / self.radius).max(0.0)
+    }
+}
+
+/// Thermal columns of the world, sent along with the world map. They are
+/// indexed by the cells they overlap, so that looking up the updraft at a
+/// position only checks the columns nearby.
+#[derive(Clone, Debug, Default)]
+pub struct Thermals {
+    columns: Vec<ThermalColumn>,
+    cells: HashMap<Vec2<i32>, Vec<usize>>,
+}
+
+impl Thermals {
+    pub fn new(columns: Vec<ThermalColumn>) -> Self {
+        let mut cells = HashMap::<_, Vec<_>>::new();
+        for (i, column) in columns.iter().enumerate() {
+            let min = Self::cell(column.center - column.radius);
+            let max = Self::cell(column.center + column.radius);
+            for x in min.x..=max.x {
+                for y in min.y..=max.y {
+                    cells.entry(Vec2::new(x, y)).or_default().push(i);
+                }
+            }
+        }
+        Self { columns, cells }
+    }
+
+    fn cell(pos: Vec2<f32>) -> Vec2<i32> { pos.map(|e| (e.floor() as i32) >> THERMAL_CELL_SIZE_LG) }
+
+    /// Upward speed of the air at `pos`, from the strongest column it is in
+    pub fn updraft_at(&self, pos: Vec2<f32>) -> f32 {
+        self.cells
+            .get(&Self::cell(pos))
+            .into_iter()
+            .flatten()
+            .map(|i| self.columns[*i].updraft_at(pos))
+            .fold(0.0, f32::max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn thin_air_shortens_wings() {
+        let glide = GlideState::new(4.0);
+        assert!(glide.glide_ratio_at(2000.0) < glide.glide_ratio_at(0.0));
+        assert_eq!(glide.aspect_ratio_at(0.0), 4.0);
+        let thin = GlideState::new(glide.aspect_ratio_at(2000.0));
+        assert!((thin.glide_ratio - glide.glide_ratio_at(2000.0)).abs() < 1e-4);
+        assert!(thin.aspect_ratio > 1.0);
+    }
+
+    #[test]
+    fn thermal_updraft_fades_out() {
+        let thermals = Thermals::new(vec![
+            ThermalColumn {
+                center: Vec2::zero(),
+                radius: 10.0,
+                updraft: 4.0,
+            },
+            ThermalColumn {
+                center: Vec2::new(15.0, 0.0),
+                radius: 10.0,
+                updraft: 2.0,
+            },
+        ]);
+        assert_eq!(thermals.updraft_at(Vec2::zero()), 4.0);
+        assert_eq!(thermals.updraft_at(Vec2::new(5.0, 0.0)), 2.0);
+        assert_eq!(thermals.updraft_at(Vec2::new(10.0, 0.0)), 1.0);
+        assert_eq!(thermals.updraft_at(Vec2::new(0.0, 30.0)), 0.0);
+    }
+
+    #[test]
+    fn thermals_are_found_across_cells() {
+        let size = (1 << THERMAL_CELL_SIZE_LG) as f32;
+        let column = ThermalColumn {
+            center: Vec2::new(size - 1.0, -1.0),
+            radius: size * 1.5,
+            updraft: 3.0,
+        };
+        let thermals = Thermals::new(vec![column]);
+        for x in -3..4 {
+            for y in -3..4 {
+                let pos = Vec2::new(x as f32, y as f32) * size * 0.5 + 0.25;
+                assert_eq!(thermals.updraft_at(pos), column.updraft_at(pos));
+            }
+        }
+    }
+}
@@ -1,6 +1,11 @@
 #[cfg(not(target_arch = "wasm32"))]
-use super::body::{object, Body};
+use super::{
+    body::{object, Body},
+    Mass,
+};
 use super::{Density, Ori, Vel};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::{consts::GRAVITY, resources::DeltaTime};
 use crate::{
     consts::{AIR_DENSITY, LAVA_DENSITY, WATER_DENSITY},
     util::{Dir, Plane, Projection},
@@ -323,6 +328,89 @@ impl Body {
     }
 }
 
+/// The density of the fluid as a function of submersion ratio in given fluid
+/// where it is assumed that any unsubmersed part is is air.
+// TODO: Better suited partial submersion curve?
+pub fn fluid_density(height: f32, fluid: &Fluid) -> Density {
+    // If depth is less than our height (partial submersion), remove
+    // fluid density based on the ratio of displacement to full volume.
+    let immersion = fluid
+        .depth()
+        .map_or(1.0, |depth| (depth / height).clamp(0.0, 1.0));
+
+    Density(fluid.density().0 * immersion + AIR_DENSITY * (1.0 - immersion))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn integrate_forces(
+    dt: &DeltaTime,
+    mut vel: Vel,
+    (body, wings): (&Body, Option<&Wings>),
+    density: &Density,
+    mass: &Mass,
+    fluid: &Fluid,
+    gravity: f32,
+) -> Vel {
+    let dim = body.dimensions();
+    let height = dim.z;
+    let rel_flow = fluid.relative_flow(&vel);
+    let fluid_density = fluid_density(height, fluid);
+    debug_assert!(mass.0 > 0.0);
+    debug_assert!(density.0 > 0.0);
+
+    // Aerodynamic/hydrodynamic forces
+    if !rel_flow.0.is_approx_zero() {
+        debug_assert!(!rel_flow.0.map(|a| a.is_nan()).reduce_or());
+        let impulse = dt.0 * body.aerodynamic_forces(&rel_flow, fluid_density.0, wings);
+        debug_assert!(!impulse.map(|a| a.is_nan()).reduce_or());
+        if !impulse.is_approx_zero() {
+            let new_v = vel.0 + impulse / mass.0;
+            // If the new velocity is in the opposite direction, it's because the forces
+            // involved are too high for the current tick to handle. We deal with this by
+            // removing the component of our velocity vector along the direction of force.
+            // This way we can only ever lose velocity and will never experience a reverse
+            // in direction from events such as falling into water at high velocities.
+            if new_v.dot(vel.0) < 0.0 {
+                // Multiply by a factor to prevent full stop,
+                // as this can cause things to get stuck in high-density medium
+                vel.0 -= vel.0.projected(&impulse) * 0.9;
+            } else {
+                vel.0 = new_v;
+            }
+        };
+        debug_assert!(!vel.0.map(|a| a.is_nan()).reduce_or());
+    };
+
+    // Hydrostatic/aerostatic forces
+    // modify gravity to account for the effective density as a result of buoyancy
+    let down_force = dt.0 * gravity * (density.0 - fluid_density.0) / density.0;
+    vel.0.z -= down_force;
+
+    vel
+}
+
+/// Velocity after a physics tick of an entity in `fluid`, or without any
+/// forces but gravity before its surroundings are known. This is what the
+/// physics system applies, it's shared so that predictions of a flight path
+/// match it.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn integrate_vel(
+    dt: &DeltaTime,
+    mut vel: Vel,
+    body_wings: (&Body, Option<&Wings>),
+    density: &Density,
+    mass: &Mass,
+    fluid: Option<&Fluid>,
+) -> Vel {
+    match fluid {
+        None => {
+            vel.0.z -= dt.0 * GRAVITY;
+            vel
+        },
+        Some(fluid) => integrate_forces(dt, vel, body_wings, density, mass, fluid, GRAVITY),
+    }
+}
+
 /// Geometric angle of attack
 ///
 /// # Note
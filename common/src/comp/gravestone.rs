@@ -0,0 +1,54 @@
+use crate::{character::CharacterId, resources::Time};
+use serde::{Deserialize, Serialize};
+use specs::{Component, DerefFlaggedStorage};
+use specs_idvs::IdvStorage;
+
+/// Marks a container left where a player died, holding the items they lost.
+/// The items are kept in the `Inventory` of the same entity.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Gravestone {
+    pub owner: CharacterId,
+    /// Name of the character the gravestone belongs to, shown when looting it
+    pub owner_name: String,
+    /// Until then, only the owner can loot the gravestone
+    pub public_at: Time,
+    /// The gravestone despawns at that time, along with the items left in it
+    pub despawn_at: Time,
+}
+
+impl Gravestone {
+    /// Whether the character `looter` may take items out of the gravestone,
+    /// `None` for entities that aren't characters
+    pub fn can_loot(&self, looter: Option<CharacterId>, time: Time) -> bool {
+        looter == Some(self.owner) || time.0 >= self.public_at.0
+    }
+
+    pub fn is_expired(&self, time: Time) -> bool { time.0 >= self.despawn_at.0 }
+}
+
+impl Component for Gravestone {
+    type Storage = DerefFlaggedStorage<Self, IdvStorage<Self>>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_owner_loots_during_grace_period() {
+        let gravestone = Gravestone {
+            owner: 1,
+            owner_name: "Owner".to_owned(),
+            public_at: Time(10.0),
+            despawn_at: Time(20.0),
+        };
+
+        assert!(gravestone.can_loot(Some(1), Time(0.0)));
+        assert!(!gravestone.can_loot(Some(2), Time(0.0)));
+        assert!(!gravestone.can_loot(None, Time(9.0)));
+        assert!(gravestone.can_loot(Some(2), Time(10.0)));
+        assert!(gravestone.can_loot(None, Time(15.0)));
+        assert!(!gravestone.is_expired(Time(15.0)));
+        assert!(gravestone.is_expired(Time(20.0)));
+    }
+}
@@ -5,7 +5,10 @@ use hashbrown::HashMap;
 use serde::{Deserialize, Serialize};
 use std::convert::TryFrom;
 
-use crate::{combat::DamageContributor, resources::Time};
+use crate::{
+    combat::{DamageContributor, DamageKind},
+    resources::Time,
+};
 #[cfg(not(target_arch = "wasm32"))]
 use specs::{Component, DerefFlaggedStorage};
 #[cfg(not(target_arch = "wasm32"))]
@@ -22,8 +25,12 @@ pub struct HealthChange {
     pub by: Option<DamageContributor>,
     /// The category of action that resulted in the health change
     pub cause: Option<DamageSource>,
+    /// The kind of damage dealt, `None` for healing
+    pub kind: Option<DamageKind>,
     /// The time that the health change occurred at
     pub time: Time,
+    /// Whether the health change was a critical hit
+    pub crit: bool,
 }
 
 impl HealthChange {
@@ -132,13 +139,26 @@ impl Health {
                 amount: 0.0,
                 by: None,
                 cause: None,
+                kind: None,
                 time: Time(0.0),
+                crit: false,
             },
             is_dead: false,
             damage_contributors: HashMap::new(),
         }
     }
 
+    /// Scales the base health by `factor`, for entities that are weaker than
+    /// their body would usually be
+    #[cfg(not(target_arch = "wasm32"))]
+    #[must_use]
+    pub fn scaled(mut self, factor: f32) -> Self {
+        self.base_max = ((self.base_max as f32 * factor) as u32).max(Self::SCALING_FACTOR_INT);
+        self.maximum = self.base_max;
+        self.current = self.base_max;
+        self
+    }
+
     // TODO: Delete this once stat points will be a thing
     #[cfg(not(target_arch = "wasm32"))]
     pub fn update_max_hp(&mut self, body: comp::Body, level: u16) {
@@ -210,7 +230,9 @@ impl Health {
                 amount: 0.0,
                 by: None,
                 cause: None,
+                kind: None,
                 time: Time(0.0),
+                crit: false,
             },
             is_dead: false,
             damage_contributors: HashMap::new(),
@@ -244,6 +266,8 @@ mod tests {
             time: Time(123.0),
             by: Some(damage_contrib),
             cause: None,
+            kind: None,
+            crit: false,
         };
 
         health.change_by(health_change);
@@ -269,6 +293,8 @@ mod tests {
             time: Time(123.0),
             by: Some(damage_contrib),
             cause: None,
+            kind: None,
+            crit: false,
         };
 
         health.change_by(health_change);
@@ -288,6 +314,8 @@ mod tests {
             time: Time(123.0),
             by: Some(damage_contrib),
             cause: None,
+            kind: None,
+            crit: false,
         };
         health.change_by(health_change);
         health.change_by(health_change);
@@ -313,6 +341,8 @@ mod tests {
             time: Time(10.0),
             by: Some(damage_contrib1),
             cause: None,
+            kind: None,
+            crit: false,
         };
         health.change_by(health_change);
 
@@ -322,6 +352,8 @@ mod tests {
             time: Time(100.0),
             by: Some(damage_contrib2),
             cause: None,
+            kind: None,
+            crit: false,
         };
         health.change_by(health_change);
 
@@ -335,6 +367,8 @@ mod tests {
             time: Time(620.0),
             by: Some(damage_contrib2),
             cause: None,
+            kind: None,
+            crit: false,
         };
         health.change_by(health_change);
 
@@ -46,6 +46,12 @@ pub struct Stats {
     /// Stealth is summed along with the base stealth bonus (2.0), and then
     /// the agent's perception distance is divided by this value
     stealth: Option<f32>,
+    /// Resistances are summed with those of other armor and buffs, the
+    /// total fraction of fire damage ignored is capped at
+    /// `combat::MAX_RESISTANCE`
+    fire_resistance: Option<f32>,
+    /// Same as fire resistance, but for poison damage
+    poison_resistance: Option<f32>,
 }
 
 impl Stats {
@@ -58,6 +64,8 @@ impl Stats {
         energy_reward: Option<f32>,
         crit_power: Option<f32>,
         stealth: Option<f32>,
+        fire_resistance: Option<f32>,
+        poison_resistance: Option<f32>,
     ) -> Self {
         Self {
             protection,
@@ -66,6 +74,8 @@ impl Stats {
             energy_reward,
             crit_power,
             stealth,
+            fire_resistance,
+            poison_resistance,
         }
     }
 
@@ -80,6 +90,10 @@ impl Stats {
     pub fn crit_power(&self) -> Option<f32> { self.crit_power }
 
     pub fn stealth(&self) -> Option<f32> { self.stealth }
+
+    pub fn fire_resistance(&self) -> Option<f32> { self.fire_resistance }
+
+    pub fn poison_resistance(&self) -> Option<f32> { self.poison_resistance }
 }
 
 impl Sub<Stats> for Stats {
@@ -99,6 +113,14 @@ impl Sub<Stats> for Stats {
                 .map(|(a, b)| a - b),
             crit_power: self.crit_power.zip(other.crit_power).map(|(a, b)| a - b),
             stealth: self.stealth.zip(other.stealth).map(|(a, b)| a - b),
+            fire_resistance: self
+                .fire_resistance
+                .zip(other.fire_resistance)
+                .map(|(a, b)| a - b),
+            poison_resistance: self
+                .poison_resistance
+                .zip(other.poison_resistance)
+                .map(|(a, b)| a - b),
         }
     }
 }
@@ -159,6 +181,10 @@ impl Armor {
 
     pub fn stealth(&self) -> Option<f32> { self.stats.stealth }
 
+    pub fn fire_resistance(&self) -> Option<f32> { self.stats.fire_resistance }
+
+    pub fn poison_resistance(&self) -> Option<f32> { self.stats.poison_resistance }
+
     #[cfg(test)]
     pub fn test_armor(
         kind: ArmorKind,
@@ -174,6 +200,8 @@ impl Armor {
                 energy_reward: None,
                 crit_power: None,
                 stealth: None,
+                fire_resistance: None,
+                poison_resistance: None,
             },
         }
     }
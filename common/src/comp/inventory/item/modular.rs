@@ -49,6 +49,7 @@ impl TagExampleInfo for ModularComponentTag {
                 ToolKind::Shield => "shield damage component",
                 ToolKind::Debug => "debug damage component",
                 ToolKind::Farming => "farming damage component",
+                ToolKind::Fishing => "fishing damage component",
                 ToolKind::Pick => "pickaxe head",
                 ToolKind::Natural => "natural damage component",
                 ToolKind::Empty => "empty damage component",
@@ -68,6 +69,7 @@ impl TagExampleInfo for ModularComponentTag {
                 ToolKind::Natural => "natural held component",
                 ToolKind::Debug => "debug held component",
                 ToolKind::Farming => "farming held component",
+                ToolKind::Fishing => "fishing held component",
                 ToolKind::Pick => "pickaxe handle",
                 ToolKind::Empty => "empty held component",
             },
@@ -90,6 +92,7 @@ impl TagExampleInfo for ModularComponentTag {
                 ToolKind::Natural => "common.items.tag_examples.modular.damage.natural",
                 ToolKind::Debug => "common.items.tag_examples.modular.damage.debug",
                 ToolKind::Farming => "common.items.tag_examples.modular.damage.farming",
+                ToolKind::Fishing => "common.items.tag_examples.modular.damage.fishing",
                 ToolKind::Pick => "common.items.tag_examples.modular.damage.pick",
                 ToolKind::Empty => "common.items.tag_examples.modular.damage.empty",
             },
@@ -107,6 +110,7 @@ impl TagExampleInfo for ModularComponentTag {
                 ToolKind::Natural => "common.items.tag_examples.modular.held.natural",
                 ToolKind::Debug => "common.items.tag_examples.modular.held.debug",
                 ToolKind::Farming => "common.items.tag_examples.modular.held.farming",
+                ToolKind::Fishing => "common.items.tag_examples.modular.held.fishing",
                 ToolKind::Pick => "common.items.tag_examples.modular.held.pick",
                 ToolKind::Empty => "common.items.tag_examples.modular.held.empty",
             },
@@ -29,6 +29,7 @@ pub enum ToolKind {
     // tools
     Debug,
     Farming,
+    Fishing,
     Pick,
     // npcs
     /// Intended for invisible weapons (e.g. a creature using its claws or
@@ -54,6 +55,7 @@ impl ToolKind {
             ToolKind::Natural => "natural",
             ToolKind::Debug => "debug",
             ToolKind::Farming => "farming",
+            ToolKind::Fishing => "fishing",
             ToolKind::Pick => "pickaxe",
             ToolKind::Empty => "empty",
         }
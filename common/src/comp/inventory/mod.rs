@@ -25,7 +25,7 @@ pub mod loadout;
 pub mod loadout_builder;
 pub mod slot;
 #[cfg(test)] mod test;
-#[cfg(test)] mod test_helpers;
+#[cfg(test)] pub(crate) mod test_helpers;
 pub mod trade_pricing;
 
 pub type InvSlot = Option<Item>;
@@ -90,6 +90,14 @@ impl Inventory {
         }
     }
 
+    /// Inventory of a container, which has `capacity` slots and no loadout
+    pub fn new_container(capacity: usize) -> Inventory {
+        Inventory {
+            slots: vec![None; capacity],
+            ..Self::new_empty()
+        }
+    }
+
     /// Total number of slots in in the inventory.
     pub fn capacity(&self) -> usize { self.slots().count() }
 
@@ -473,6 +481,31 @@ impl Inventory {
             .sum()
     }
 
+    /// Removes `amount` items of the given kind, taken from as many stacks as
+    /// needed. Nothing is removed if the inventory holds fewer than that.
+    pub fn remove_item_amount(&mut self, item_def: &ItemDef, amount: u32) -> bool {
+        if self.item_count(item_def) < u64::from(amount) {
+            return false;
+        }
+        let mut remaining = amount;
+        for slot in self.slots_mut() {
+            if remaining == 0 {
+                break;
+            }
+            if let Some(item) = slot.as_mut().filter(|item| item.is_same_item_def(item_def)) {
+                if item.amount() > remaining {
+                    // Can't fail, the stack holds more than is taken out of it
+                    let _ = item.decrease_amount(remaining);
+                    remaining = 0;
+                } else {
+                    remaining -= item.amount();
+                    *slot = None;
+                }
+            }
+        }
+        true
+    }
+
     /// Adds a new item to the first empty slot of the inventory. Returns the
     /// item again in an Err if no free slot was found, otherwise returns a
     /// reference to the item.
@@ -1,9 +1,13 @@
 use super::*;
-use crate::comp::{
-    inventory::{slot::ArmorSlot, test_helpers::get_test_bag},
-    Item,
+use crate::{
+    assets::AssetExt,
+    comp::{
+        inventory::{slot::ArmorSlot, test_helpers::get_test_bag},
+        Item,
+    },
 };
 use lazy_static::lazy_static;
+use std::sync::Arc;
 lazy_static! {
     static ref TEST_ITEMS: Vec<Item> = vec![Item::new_from_asset_expect(
         "common.items.debug.admin_stick"
@@ -498,3 +502,25 @@ fn fill_inv_slots(inv: &mut Inventory, items: u16) {
         inv.push(boots.duplicate(ability_map, msm)).unwrap();
     }
 }
+
+#[test]
+fn remove_item_amount_takes_from_multiple_stacks() {
+    let coins = |amount| {
+        let mut coins = Item::new_from_asset_expect("common.items.utility.coins");
+        coins.set_amount(amount).unwrap();
+        Some(coins)
+    };
+    let mut inv = Inventory {
+        next_sort_order: InventorySortOrder::Name,
+        slots: vec![coins(30), None, coins(25)],
+        loadout: LoadoutBuilder::empty().build(),
+    };
+    let coin_def = Arc::<ItemDef>::load_expect_cloned("common.items.utility.coins");
+
+    assert!(inv.remove_item_amount(&coin_def, 40));
+    assert_eq!(inv.item_count(&coin_def), 15);
+    assert!(inv.slots[0].is_none());
+    // Nothing is taken if there aren't enough
+    assert!(!inv.remove_item_amount(&coin_def, 20));
+    assert_eq!(inv.item_count(&coin_def), 15);
+}
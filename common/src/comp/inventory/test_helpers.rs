@@ -29,3 +29,24 @@ pub(super) fn get_test_bag(slots: u16) -> Item {
         &MaterialStatManifest::default(),
     )
 }
+
+pub(crate) fn get_test_chest(protection: Protection) -> Item {
+    let item_def = ItemDef::new_test(
+        "common.items.testing.test_chest".to_string(),
+        ItemKind::Armor(armor::Armor::test_armor(
+            ArmorKind::Chest("Test Chest".to_string()),
+            protection,
+            Protection::Normal(0.0),
+        )),
+        Quality::Common,
+        Vec::new(),
+        0,
+    );
+
+    Item::new_from_item_def(
+        Arc::new(item_def),
+        &[],
+        &AbilityMap::default(),
+        &MaterialStatManifest::default(),
+    )
+}
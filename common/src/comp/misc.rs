@@ -13,6 +13,10 @@ pub enum Object {
         owner: Option<Uid>,
         reagent: Reagent,
     },
+    /// The bobber of a cast fishing line, removed once the line is reeled in
+    Bobber {
+        owner: Uid,
+    },
 }
 
 impl Component for Object {
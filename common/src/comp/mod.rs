@@ -4,6 +4,7 @@ pub mod ability;
 #[cfg(not(target_arch = "wasm32"))] pub mod agent;
 #[cfg(not(target_arch = "wasm32"))]
 pub mod anchor;
+#[cfg(not(target_arch = "wasm32"))] pub mod audio;
 #[cfg(not(target_arch = "wasm32"))] pub mod aura;
 #[cfg(not(target_arch = "wasm32"))] pub mod beam;
 #[cfg(not(target_arch = "wasm32"))] pub mod body;
@@ -16,10 +17,20 @@ pub mod compass;
 #[cfg(not(target_arch = "wasm32"))]
 pub mod controller;
 #[cfg(not(target_arch = "wasm32"))]
+pub mod cooking;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod cooldown;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod dialogue;
 #[cfg(not(target_arch = "wasm32"))] mod energy;
 #[cfg(not(target_arch = "wasm32"))]
+pub mod farming;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod flight;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod fluid_dynamics;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod gravestone;
 #[cfg(not(target_arch = "wasm32"))] pub mod group;
 mod health;
 #[cfg(not(target_arch = "wasm32"))] mod inputs;
@@ -42,6 +53,8 @@ pub mod projectile;
 pub mod shockwave;
 #[cfg(not(target_arch = "wasm32"))]
 pub mod skillset;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod social;
 #[cfg(not(target_arch = "wasm32"))] mod stats;
 #[cfg(not(target_arch = "wasm32"))]
 pub mod visual;
@@ -56,6 +69,7 @@ pub use self::{
     admin::{Admin, AdminRole},
     agent::{Agent, Alignment, Behavior, BehaviorCapability, BehaviorState, PidController},
     anchor::Anchor,
+    audio::{AudioEmitter, AudioEmitterKind},
     aura::{Aura, AuraChange, AuraKind, Auras},
     beam::{Beam, BeamSegment},
     body::{
@@ -76,8 +90,13 @@ pub use self::{
         Climb, ControlAction, ControlEvent, Controller, ControllerInputs, GroupManip, InputAttr,
         InputKind, InventoryAction, InventoryEvent, InventoryManip, UtteranceKind,
     },
+    cooking::{CookingPot, RecipeId},
+    cooldown::Cooldowns,
     energy::Energy,
+    farming::{CropKind, FarmPlot},
+    flight::{GlideState, ThermalColumn, Thermals},
     fluid_dynamics::Fluid,
+    gravestone::Gravestone,
     group::Group,
     inputs::CanBuild,
     inventory::{
@@ -97,7 +116,7 @@ pub use self::{
     pet::Pet,
     phys::{
         Collider, Density, ForceUpdate, Immovable, Mass, PhysicsState, Pos, PosVelOriDefer,
-        PreviousPhysCache, Scale, Sticky, Vel,
+        PreviousPhysCache, Scale, Sticky, TerminalVelocity, Vel,
     },
     player::DisconnectReason,
     player::{AliasError, Player, MAX_ALIAS_LEN},
@@ -105,6 +105,35 @@ impl Component for Density {
     type Storage = DerefFlaggedStorage<Self, IdvStorage<Self>>;
 }
 
+/// Highest speed an entity can fall at in m/s, so falling down deep shafts
+/// doesn't accelerate it without bound
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TerminalVelocity(pub f32);
+
+impl TerminalVelocity {
+    /// Cap while gliding, the glider slows the fall down a lot more than the
+    /// drag alone would
+    pub const GLIDING: f32 = 8.0;
+
+    /// Clamps the downward part of `vel` to the cap
+    pub fn apply(&self, vel: &mut Vel, gliding: bool) {
+        let cap = if gliding {
+            self.0.min(Self::GLIDING)
+        } else {
+            self.0
+        };
+        vel.0.z = vel.0.z.max(-cap);
+    }
+}
+
+impl Default for TerminalVelocity {
+    fn default() -> Self { Self(30.0) }
+}
+
+impl Component for TerminalVelocity {
+    type Storage = DerefFlaggedStorage<Self, IdvStorage<Self>>;
+}
+
 // Collider
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum Collider {
@@ -219,3 +248,34 @@ pub struct ForceUpdate;
 impl Component for ForceUpdate {
     type Storage = NullStorage<Self>;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consts::GRAVITY;
+
+    fn free_fall(terminal_velocity: TerminalVelocity, gliding: bool) -> Vel {
+        let dt = 1.0 / 30.0;
+        let mut vel = Vel(Vec3::zero());
+        for _ in 0..300 {
+            vel.0.z -= dt * GRAVITY;
+            terminal_velocity.apply(&mut vel, gliding);
+        }
+        vel
+    }
+
+    #[test]
+    fn free_fall_is_capped() {
+        // 10 seconds of free fall would reach 250 m/s without the cap
+        assert_eq!(free_fall(TerminalVelocity::default(), false).0.z, -30.0);
+        assert_eq!(
+            free_fall(TerminalVelocity::default(), true).0.z,
+            -TerminalVelocity::GLIDING
+        );
+
+        // Rising isn't affected
+        let mut vel = Vel(Vec3::new(50.0, 0.0, 50.0));
+        TerminalVelocity::default().apply(&mut vel, false);
+        assert_eq!(vel.0, Vec3::new(50.0, 0.0, 50.0));
+    }
+}
@@ -155,6 +155,9 @@ impl Poise {
     /// The amount poise is scaled by within this module
     const SCALING_FACTOR_FLOAT: f32 = 256.;
     const SCALING_FACTOR_INT: u32 = Self::SCALING_FACTOR_FLOAT as u32;
+    /// Knockbacks changing the velocity of an entity at half of its poise by
+    /// more than this many m/s stagger it
+    const STAGGER_SPEED: f32 = 8.0;
 
     /// Returns the current value of poise casted to a float
     pub fn current(&self) -> f32 { self.current as f32 / Self::SCALING_FACTOR_FLOAT }
@@ -214,6 +217,25 @@ impl Poise {
     /// change
     pub fn knockback(&self) -> Dir { self.last_change }
 
+    /// Returns the stunned state and its duration if a knockback that changed
+    /// the velocity of the entity by `speed` staggers it. The more poise is
+    /// left the harder the entity is to stagger, entities that are already
+    /// stunned or rolling aren't affected at all.
+    pub fn knockback_stagger(
+        &self,
+        char_state: &CharacterState,
+        speed: f32,
+    ) -> Option<(CharacterState, f64)> {
+        let threshold = Self::STAGGER_SPEED * (0.5 + self.fraction());
+        if char_state.is_stunned() || char_state.is_dodge() || speed <= threshold {
+            None
+        } else {
+            PoiseState::Interrupted
+                .poise_effect(char_state.is_wield())
+                .0
+        }
+    }
+
     /// Defines the poise states based on current poise value
     pub fn poise_state(&self) -> PoiseState {
         match self.current() {
@@ -259,3 +281,81 @@ impl Poise {
 impl Component for Poise {
     type Storage = DerefFlaggedStorage<Self, IdvStorage<Self>>;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        assets::AssetExt,
+        comp::{humanoid, CharacterAbility, InputKind},
+        states::{
+            charged_melee,
+            utils::{AbilityInfo, StageSection},
+        },
+    };
+
+    fn charging_hammer() -> CharacterState {
+        let ability = CharacterAbility::load_expect_cloned("common.abilities.hammer.charged");
+        let melee_constructor = match ability {
+            CharacterAbility::ChargedMelee {
+                melee_constructor, ..
+            } => melee_constructor,
+            _ => panic!("Hammer charge is no charged melee"),
+        };
+        CharacterState::ChargedMelee(charged_melee::Data {
+            static_data: charged_melee::StaticData {
+                energy_drain: 30.0,
+                energy_cost: 1.0,
+                charge_duration: Duration::from_secs_f32(1.2),
+                swing_duration: Duration::from_secs_f32(0.12),
+                hit_timing: 0.2,
+                recover_duration: Duration::from_secs_f32(0.3),
+                melee_constructor,
+                ability_info: AbilityInfo {
+                    tool: None,
+                    hand: None,
+                    input: InputKind::Primary,
+                    input_attr: None,
+                },
+                specifier: None,
+            },
+            stage_section: StageSection::Charge,
+            timer: Duration::from_secs_f32(0.5),
+            exhausted: false,
+            charge_amount: 0.4,
+        })
+    }
+
+    #[test]
+    fn strong_knockback_staggers_charging_attack() {
+        let poise = Poise::new(comp::Body::Humanoid(humanoid::Body::random()));
+        let charging = charging_hammer();
+
+        assert!(poise.knockback_stagger(&charging, 2.0).is_none());
+        let (stunned, duration) = poise
+            .knockback_stagger(&charging, 20.0)
+            .expect("Strong knockbacks stagger");
+        assert!(stunned.is_stunned());
+        assert!(duration > 0.0);
+
+        // Already staggered entities aren't staggered again
+        assert!(poise.knockback_stagger(&stunned, 20.0).is_none());
+    }
+
+    #[test]
+    fn stagger_threshold_grows_with_poise() {
+        let mut poise = Poise::new(comp::Body::Humanoid(humanoid::Body::random()));
+        let charging = charging_hammer();
+        let speed = Poise::STAGGER_SPEED;
+        assert!(poise.knockback_stagger(&charging, speed).is_none());
+
+        poise.change(PoiseChange {
+            amount: -poise.current(),
+            impulse: Vec3::zero(),
+            by: None,
+            cause: None,
+            time: Time(0.0),
+        });
+        assert!(poise.knockback_stagger(&charging, speed).is_some());
+    }
+}
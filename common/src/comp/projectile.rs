@@ -165,7 +165,7 @@ impl ProjectileConstructor {
                 let damage = AttackDamage::new(
                     Damage {
                         source: DamageSource::Explosion,
-                        kind: DamageKind::Energy,
+                        kind: DamageKind::Fire,
                         value: damage,
                     },
                     Some(GroupTarget::OutOfGroup),
@@ -246,7 +246,7 @@ impl ProjectileConstructor {
                 let damage = AttackDamage::new(
                     Damage {
                         source: DamageSource::Explosion,
-                        kind: DamageKind::Energy,
+                        kind: DamageKind::Poison,
                         value: damage,
                     },
                     Some(GroupTarget::OutOfGroup),
@@ -420,7 +420,7 @@ impl ProjectileConstructor {
                 let damage = AttackDamage::new(
                     Damage {
                         source: DamageSource::Explosion,
-                        kind: DamageKind::Energy,
+                        kind: DamageKind::Fire,
                         value: damage,
                     },
                     Some(GroupTarget::OutOfGroup),
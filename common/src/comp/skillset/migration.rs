@@ -0,0 +1,217 @@
+//! Loading of skill sets that were saved by older versions of the game.
+//!
+//! Skills are read as plain JSON first, so skills that were renamed since can
+//! be mapped to their new name and skills that no longer exist can be dropped
+//! without failing the whole load. The renames are listed in the
+//! `common.skill_trees.skill_migrations` asset.
+
+use super::{skills::Skill, SkillGroup, SkillGroupKind, SkillSet};
+use crate::assets::{self, Asset, AssetExt};
+use hashbrown::HashMap;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use tracing::warn;
+
+/// Version of the format written by [`serialize_skill_set`]
+pub const SKILL_SET_VERSION: u32 = 2;
+
+/// Skills and skill groups that were renamed or removed, by the version that
+/// changed them. Keys are written like in the skill tree assets, e.g.
+/// `"Sword(TsDamage)"`, renaming to `None` removes the skill.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct SkillMigrations(pub HashMap<u32, HashMap<String, Option<String>>>);
+
+impl Asset for SkillMigrations {
+    type Loader = assets::RonLoader;
+
+    const EXTENSION: &'static str = "ron";
+}
+
+#[derive(Debug, PartialEq)]
+pub enum MigrationError {
+    /// The data doesn't match the format of any version
+    UnknownFormat,
+    /// The data was saved by a newer version of the game
+    UnsupportedVersion(u32),
+}
+
+/// Skill group as read from any version, before its skills are migrated
+struct RawSkillGroup {
+    kind: Value,
+    earned_exp: u32,
+    /// Skills in the order they were unlocked in
+    skills: Vec<Value>,
+}
+
+/// Format from before skill sets were versioned, with the skill groups laid
+/// out like [`SkillGroup`]
+#[derive(Deserialize)]
+struct SkillSetV1 {
+    skill_groups: Vec<SkillGroupV1>,
+}
+
+#[derive(Deserialize)]
+struct SkillGroupV1 {
+    skill_group_kind: Value,
+    earned_exp: u32,
+    ordered_skills: Vec<Value>,
+}
+
+impl SkillSetV1 {
+    fn parse(raw: &[u8]) -> Option<(u32, Vec<RawSkillGroup>)> {
+        let skill_set = serde_json::from_slice::<Self>(raw).ok()?;
+        let skill_groups = skill_set
+            .skill_groups
+            .into_iter()
+            .map(|group| RawSkillGroup {
+                kind: group.skill_group_kind,
+                earned_exp: group.earned_exp,
+                skills: group.ordered_skills,
+            })
+            .collect();
+        Some((1, skill_groups))
+    }
+}
+
+/// Current format, leaves out everything that is derived from the experience
+#[derive(Serialize, Deserialize)]
+struct SkillSetV2 {
+    version: u32,
+    skill_groups: Vec<SkillGroupV2>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SkillGroupV2 {
+    kind: Value,
+    earned_exp: u32,
+    skills: Vec<Value>,
+}
+
+impl SkillSetV2 {
+    fn parse(raw: &[u8]) -> Option<(u32, Vec<RawSkillGroup>)> {
+        let skill_set = serde_json::from_slice::<Self>(raw).ok()?;
+        let skill_groups = skill_set
+            .skill_groups
+            .into_iter()
+            .map(|group| RawSkillGroup {
+                kind: group.kind,
+                earned_exp: group.earned_exp,
+                skills: group.skills,
+            })
+            .collect();
+        Some((skill_set.version, skill_groups))
+    }
+}
+
+/// Deserializers of all versions, tried in order
+const FORMATS: [fn(&[u8]) -> Option<(u32, Vec<RawSkillGroup>)>; 2] =
+    [SkillSetV2::parse, SkillSetV1::parse];
+
+impl SkillMigrations {
+    /// Loads a skill set saved by any version up to the current one. Skills
+    /// that don't exist anymore are dropped, their skill points can be spent
+    /// again.
+    pub fn migrate_skill_set(&self, raw: &[u8]) -> Result<SkillSet, MigrationError> {
+        let (version, raw_groups) = FORMATS
+            .iter()
+            .find_map(|parse| parse(raw))
+            .ok_or(MigrationError::UnknownFormat)?;
+        if version > SKILL_SET_VERSION {
+            return Err(MigrationError::UnsupportedVersion(version));
+        }
+
+        let mut skill_groups = HashMap::new();
+        let mut all_skills = HashMap::new();
+        for raw_group in raw_groups {
+            let kind = match self.migrate::<SkillGroupKind>(&raw_group.kind, version) {
+                Some(kind) => kind,
+                None => continue,
+            };
+            let mut skill_group = SkillGroup::new(kind);
+            skill_group.add_experience(raw_group.earned_exp);
+            let skills = raw_group
+                .skills
+                .iter()
+                .filter_map(|skill| self.migrate::<Skill>(skill, version))
+                .collect();
+            skill_groups.insert(kind, skill_group);
+            all_skills.insert(kind, Ok(skills));
+        }
+        Ok(SkillSet::load_from_database(skill_groups, all_skills))
+    }
+
+    /// Applies the renames of all versions after `version` to a skill or skill
+    /// group, `None` if it was removed or is unknown
+    fn migrate<T: DeserializeOwned>(&self, value: &Value, version: u32) -> Option<T> {
+        let mut key = match key_of(value) {
+            Some(key) => key,
+            None => {
+                warn!(?value, "Dropping malformed skill from saved skill set");
+                return None;
+            },
+        };
+        for renames in (version + 1..=SKILL_SET_VERSION).filter_map(|v| self.0.get(&v)) {
+            if let Some(renamed) = renames.get(&key) {
+                key = renamed.clone()?;
+            }
+        }
+        match serde_json::from_value(value_of(&key)) {
+            Ok(migrated) => Some(migrated),
+            Err(_) => {
+                warn!(?key, "Dropping unknown skill from saved skill set");
+                None
+            },
+        }
+    }
+}
+
+/// Loads a skill set saved by any version up to the current one, see
+/// [`SkillMigrations::migrate_skill_set`]
+pub fn migrate_skill_set(raw: &[u8]) -> Result<SkillSet, MigrationError> {
+    SkillMigrations::load_expect("common.skill_trees.skill_migrations")
+        .read()
+        .migrate_skill_set(raw)
+}
+
+/// Serializes the skill groups and skills of a skill set in the current format
+pub fn serialize_skill_set(skill_set: &SkillSet) -> Vec<u8> {
+    let skill_groups = skill_set
+        .skill_groups()
+        .map(|group| SkillGroupV2 {
+            kind: serde_json::to_value(group.skill_group_kind).unwrap_or_default(),
+            earned_exp: group.earned_exp,
+            skills: group
+                .ordered_skills
+                .iter()
+                .filter_map(|skill| serde_json::to_value(skill).ok())
+                .collect(),
+        })
+        .collect();
+    serde_json::to_vec(&SkillSetV2 {
+        version: SKILL_SET_VERSION,
+        skill_groups,
+    })
+    .unwrap_or_default()
+}
+
+/// Writes a skill like the skill tree assets do, e.g. `Sword(TsDamage)`
+fn key_of(value: &Value) -> Option<String> {
+    match value {
+        Value::String(variant) => Some(variant.clone()),
+        Value::Object(map) if map.len() == 1 => {
+            let (variant, inner) = map.iter().next()?;
+            Some(format!("{}({})", variant, key_of(inner)?))
+        },
+        _ => None,
+    }
+}
+
+fn value_of(key: &str) -> Value {
+    match key.split_once('(') {
+        Some((variant, inner)) => {
+            let inner = inner.strip_suffix(')').unwrap_or(inner);
+            Value::Object(std::iter::once((variant.to_owned(), value_of(inner))).collect())
+        },
+        None => Value::String(key.to_owned()),
+    }
+}
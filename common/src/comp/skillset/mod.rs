@@ -1,8 +1,9 @@
 use crate::{
-    assets::{self, Asset, AssetExt},
+    assets::{self, Asset, AssetExt, AssetHandle},
     comp::{
         item::tool::ToolKind,
         skills::{GeneralSkill, Skill},
+        Stats,
     },
 };
 use hashbrown::HashMap;
@@ -12,8 +13,9 @@ use sha2::{Digest, Sha256};
 use specs::{Component, DerefFlaggedStorage};
 use specs_idvs::IdvStorage;
 use std::{collections::BTreeSet, hash::Hash};
-use tracing::{trace, warn};
+use tracing::warn;
 
+pub mod migration;
 pub mod skills;
 
 #[cfg(test)] mod test;
@@ -56,7 +58,84 @@ impl Asset for SkillPrerequisitesMap {
     const EXTENSION: &'static str = "ron";
 }
 
+/// Passive bonus granted while both skills of a pair are unlocked, on top of
+/// the effects of the skills themselves
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum SynergyEffect {
+    /// Multiplies the maximum health
+    MaxHealth(f32),
+    /// Multiplies the maximum energy
+    MaxEnergy(f32),
+    /// Multiplies the movement speed
+    MoveSpeed(f32),
+    /// Multiplies the attack speed
+    AttackSpeed(f32),
+}
+
+impl SynergyEffect {
+    /// Applies the bonus to the temporary modifiers of `stats`, which have to
+    /// be reset before as they aren't persisted
+    pub fn apply(self, stats: &mut Stats) {
+        match self {
+            SynergyEffect::MaxHealth(mult) => stats.max_health_modifiers.mult_mod *= mult,
+            SynergyEffect::MaxEnergy(mult) => stats.max_energy_modifiers.mult_mod *= mult,
+            SynergyEffect::MoveSpeed(mult) => stats.move_speed_modifier *= mult,
+            SynergyEffect::AttackSpeed(mult) => stats.attack_speed_modifier *= mult,
+        }
+    }
+}
+
+/// Contains the synergy granted by each pair of skills, the order of the
+/// skills in a pair doesn't matter.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SkillSynergyMap(HashMap<(Skill, Skill), SynergyEffect>);
+
+impl Asset for SkillSynergyMap {
+    type Loader = assets::RonLoader;
+
+    const EXTENSION: &'static str = "ron";
+}
+
+/// Parameters of the logistic curve that gives the experience needed for each
+/// skill point, see [`SkillXpCurveParams::skill_point_cost`]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SkillXpCurveParams {
+    /// Costs are rounded down to multiples of this
+    pub exp_increment: f32,
+    /// Cost of the first skill point
+    pub starting_exp: f32,
+    /// Cost that later skill points approach
+    pub exp_ceiling: f32,
+    /// How fast the costs approach the ceiling
+    pub scaling_factor: f32,
+    /// Base of the exponential, `e` gives the usual logistic curve
+    pub a: f32,
+}
+
+impl Asset for SkillXpCurveParams {
+    type Loader = assets::RonLoader;
+
+    const EXTENSION: &'static str = "ron";
+}
+
+impl SkillXpCurveParams {
+    /// Experience needed to earn a skill point when `level` skill points were
+    /// already earned in the skill group
+    pub fn skill_point_cost(&self, level: u16) -> u32 {
+        (self.exp_increment
+            * (self.exp_ceiling
+                / self.exp_increment
+                / (1.0
+                    + self.a.powf(-self.scaling_factor * level as f32)
+                        * (self.exp_ceiling / self.starting_exp - 1.0)))
+                .floor()) as u32
+    }
+}
+
 lazy_static! {
+    // The handle is read on every use, so costs follow hot reloads of the curve
+    pub static ref SKILL_XP_CURVE: AssetHandle<SkillXpCurveParams> =
+        SkillXpCurveParams::load_expect("common.skill_trees.skill_xp_curve");
     // Determines the skills that comprise each skill group.
     //
     // This data is used to determine which of a player's skill groups a
@@ -100,6 +179,12 @@ lazy_static! {
             "common.skill_trees.skill_prerequisites",
         ).0
     };
+    // Loads the synergies between pairs of skills
+    pub static ref SKILL_SYNERGIES: HashMap<(Skill, Skill), SynergyEffect> = {
+        SkillSynergyMap::load_expect_cloned(
+            "common.skill_trees.skill_synergies",
+        ).0
+    };
     pub static ref SKILL_GROUP_HASHES: HashMap<SkillGroupKind, Vec<u8>> = {
         let map = SkillTreeMap::load_expect_cloned(
             "common.skill_trees.skills_skill-groups_manifest",
@@ -121,6 +206,8 @@ lazy_static! {
 pub enum SkillGroupKind {
     General,
     Weapon(ToolKind),
+    Magic,
+    Crafting,
 }
 
 impl SkillGroupKind {
@@ -128,17 +215,18 @@ impl SkillGroupKind {
     /// Changing this is forward compatible with persistence and will
     /// automatically force a respec for skill group kinds that are affected.
     pub fn skill_point_cost(self, level: u16) -> u32 {
-        const EXP_INCREMENT: f32 = 10.0;
-        const STARTING_EXP: f32 = 70.0;
-        const EXP_CEILING: f32 = 1000.0;
-        const SCALING_FACTOR: f32 = 0.125;
-        (EXP_INCREMENT
-            * (EXP_CEILING
-                / EXP_INCREMENT
-                / (1.0
-                    + std::f32::consts::E.powf(-SCALING_FACTOR * level as f32)
-                        * (EXP_CEILING / STARTING_EXP - 1.0)))
-                .floor()) as u32
+        SKILL_XP_CURVE.read().skill_point_cost(level)
+    }
+
+    /// Multiplier for the experience earned by a character of the given
+    /// level, lets high level characters catch up in skill groups they
+    /// neglected so far.
+    pub fn xp_gain_multiplier(self, level: u16) -> f32 {
+        match level {
+            0..=9 => 1.0,
+            10..=19 => 1.5,
+            _ => 2.0,
+        }
     }
 
     /// Gets the total amount of skill points that can be spent in a particular
@@ -248,6 +336,35 @@ impl Component for SkillSet {
     type Storage = DerefFlaggedStorage<Self, IdvStorage<Self>>;
 }
 
+/// The skill groups and skills of a [`SkillSet`] at some point, used to undo
+/// a batch of unlocks if one of them fails. See [`SkillSet::snapshot`].
+#[derive(Clone, Debug)]
+pub struct SkillSetSnapshot {
+    skill_groups: HashMap<SkillGroupKind, SkillGroup>,
+    skills: HashMap<Skill, u16>,
+}
+
+/// The changes between two [`SkillSet`]s, see [`SkillSet::diff`]. Only
+/// contains what differs, so it's usually a lot smaller than the skill set.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SkillSetDiff {
+    /// Skills that were unlocked or changed their level
+    pub added: Vec<(Skill, u16)>,
+    pub removed: Vec<Skill>,
+    /// Skill groups that were unlocked or whose exp or skill points changed
+    pub changed_groups: Vec<SkillGroup>,
+    pub removed_groups: Vec<SkillGroupKind>,
+}
+
+impl SkillSetDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.changed_groups.is_empty()
+            && self.removed_groups.is_empty()
+    }
+}
+
 impl Default for SkillSet {
     /// Instantiate a new skill set with the default skill groups with no
     /// unlocked skills in them - used when adding a skill set to a new
@@ -271,6 +388,9 @@ impl Default for SkillSet {
 }
 
 impl SkillSet {
+    /// Coins a full respec costs per skill point earned across all groups
+    pub const RESPEC_COST_PER_SP: u32 = 10;
+
     pub fn initial_skills() -> HashMap<Skill, u16> {
         let mut skills = HashMap::new();
         skills.insert(Skill::UnlockGroup(SkillGroupKind::General), 1);
@@ -307,7 +427,7 @@ impl SkillSet {
             if let Some(skills_result) = all_skills.remove(&skill_group_kind) {
                 match skills_result {
                     Ok(skills) => {
-                        let backup_skillset = skillset.clone();
+                        let snapshot = skillset.snapshot();
                         // Iterate over all skills and make sure that unlocking them is successful.
                         // If any fail, fall back to skillset before
                         // unlocking any to allow a full respec
@@ -315,7 +435,7 @@ impl SkillSet {
                             .iter()
                             .all(|skill| skillset.unlock_skill(*skill).is_ok())
                         {
-                            skillset = backup_skillset;
+                            skillset.restore(snapshot);
                             // If unlocking failed, set persistence_load_error
                             skillset.persistence_load_error =
                                 Some(SkillsPersistenceError::SkillsUnlockFailed)
@@ -382,6 +502,22 @@ impl SkillSet {
         }
     }
 
+    /// Level of the character, the most skill points earned in any of its skill
+    /// groups
+    pub fn level(&self) -> u16 {
+        self.skill_groups
+            .values()
+            .map(|s_g| s_g.earned_sp)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Multiplier applied to experience earned in a skill group, see
+    /// [`SkillGroupKind::xp_gain_multiplier`]
+    pub fn xp_gain_multiplier(&self, skill_group_kind: SkillGroupKind) -> f32 {
+        skill_group_kind.xp_gain_multiplier(self.level())
+    }
+
     /// Gets the available experience for a particular skill group
     pub fn available_experience(&self, skill_group: SkillGroupKind) -> u32 {
         self.skill_group(skill_group)
@@ -421,6 +557,22 @@ impl SkillSet {
         self.skill_group(skill_group).map_or(0, |s_g| s_g.earned_sp)
     }
 
+    /// Skill points earned across all skill groups
+    pub fn total_earned_sp(&self) -> u16 {
+        self.skill_groups
+            .values()
+            .fold(0, |total, s_g| total.saturating_add(s_g.earned_sp))
+    }
+
+    /// How far the experience of a skill group is towards its next skill
+    /// point, in `0.0..=1.0`. 0.0 if the skill group isn't unlocked.
+    pub fn exp_fraction_to_next_sp(&self, skill_group: SkillGroupKind) -> f32 {
+        self.skill_group(skill_group).map_or(0.0, |s_g| {
+            let cost = skill_group.skill_point_cost(s_g.earned_sp).max(1);
+            (s_g.available_exp as f32 / cost as f32).min(1.0)
+        })
+    }
+
     /// Checks that the skill set contains all prerequisite skills of the
     /// required level for a particular skill
     pub fn prerequisites_met(&self, skill: Skill) -> bool {
@@ -461,7 +613,8 @@ impl SkillSet {
     }
 
     /// Unlocks a skill for a player, assuming they have the relevant skill
-    /// group unlocked and available SP in that skill group.
+    /// group unlocked and available SP in that skill group. The error tells
+    /// which of those conditions wasn't met.
     pub fn unlock_skill(&mut self, skill: Skill) -> Result<(), SkillUnlockError> {
         if let Some(skill_group_kind) = skill.skill_group_kind() {
             let next_level = self.next_skill_level(skill);
@@ -491,19 +644,15 @@ impl SkillSet {
                             self.skills.insert(skill, next_level);
                             Ok(())
                         } else {
-                            trace!("Tried to unlock skill for skill group with insufficient SP");
                             Err(SkillUnlockError::InsufficientSP)
                         }
                     } else {
-                        trace!("Tried to unlock skill without meeting prerequisite skills");
                         Err(SkillUnlockError::MissingPrerequisites)
                     }
                 } else {
-                    trace!("Tried to unlock skill for a skill group that player does not have");
                     Err(SkillUnlockError::UnavailableSkillGroup)
                 }
             } else {
-                trace!("Tried to unlock skill the player already has");
                 Err(SkillUnlockError::SkillAlreadyUnlocked)
             }
         } else {
@@ -515,6 +664,24 @@ impl SkillSet {
         }
     }
 
+    /// Unlocks all given skills, prerequisites before the skills that depend
+    /// on them, regardless of the order they are given in. Every skill is
+    /// attempted even if an earlier one failed. A skill listed multiple times
+    /// is unlocked once per listing to raise its level.
+    pub fn bulk_unlock(&mut self, skills: impl IntoIterator<Item = Skill>) -> BulkUnlockResult {
+        let mut depths = HashMap::new();
+        let mut skills = skills
+            .into_iter()
+            .map(|skill| (prerequisite_depth(skill, &mut depths), skill))
+            .collect::<Vec<_>>();
+        // Stable, so levels of the same skill stay in the given order
+        skills.sort_by_key(|(depth, _)| *depth);
+        skills
+            .into_iter()
+            .map(|(_, skill)| (skill, self.unlock_skill(skill)))
+            .collect()
+    }
+
     /// Checks if the player has available SP to spend
     pub fn has_available_sp(&self) -> bool {
         self.skill_groups.iter().any(|(kind, sg)| {
@@ -524,6 +691,15 @@ impl SkillSet {
         })
     }
 
+    /// Number of skill points that can still be spent, over the skill groups
+    /// counted by [`Self::has_available_sp`]
+    pub fn unspent_sp(&self) -> u16 {
+        self.skill_groups
+            .iter()
+            .filter(|(kind, sg)| (sg.earned_sp - sg.available_sp) < kind.total_skill_point_cost())
+            .fold(0, |sum, (_, sg)| sum.saturating_add(sg.available_sp))
+    }
+
     /// Checks if the skill is at max level in a skill set
     pub fn is_at_max_level(&self, skill: Skill) -> bool {
         if let Ok(level) = self.skill_level(skill) {
@@ -544,14 +720,155 @@ impl SkillSet {
             Err(SkillError::MissingSkill)
         }
     }
+
+    /// Returns the synergies of all skill pairs that are both unlocked
+    pub fn get_active_synergies(&self) -> Vec<SynergyEffect> {
+        SKILL_SYNERGIES
+            .iter()
+            .filter(|((a, b), _)| self.has_skill(*a) && self.has_skill(*b))
+            .map(|(_, effect)| *effect)
+            .collect()
+    }
+
+    /// Saves the skill groups and skills, which can be brought back with
+    /// [`SkillSet::restore`]. `modify_health` and `modify_energy` aren't part
+    /// of the snapshot, they only cause the stats to be recalculated.
+    pub fn snapshot(&self) -> SkillSetSnapshot {
+        SkillSetSnapshot {
+            skill_groups: self.skill_groups.clone(),
+            skills: self.skills.clone(),
+        }
+    }
+
+    /// Replaces the skill groups and skills with the ones from `snapshot`
+    pub fn restore(&mut self, snapshot: SkillSetSnapshot) {
+        self.skill_groups = snapshot.skill_groups;
+        self.skills = snapshot.skills;
+    }
+
+    /// Whether any skill point was spent, i.e. whether
+    /// [`SkillSet::full_respec`] would change anything
+    pub fn can_respec(&self) -> bool {
+        self.skills != SkillSet::initial_skills()
+            || self
+                .skill_groups
+                .values()
+                .any(|group| group.available_sp != group.earned_sp)
+    }
+
+    /// Coins it costs to respec, scales with the skill points earned so far
+    pub fn respec_cost(&self) -> u32 {
+        let earned_sp = self
+            .skill_groups
+            .values()
+            .map(|group| u32::from(group.earned_sp))
+            .sum::<u32>();
+        earned_sp.saturating_mul(Self::RESPEC_COST_PER_SP)
+    }
+
+    /// Removes all unlocked skills and refunds the skill points spent on them.
+    /// Skill groups keep their experience and skill points, weapon groups
+    /// have to be unlocked again before their skill points can be spent.
+    pub fn full_respec(&mut self) {
+        // Max health and energy have to be recalculated without the increases
+        self.modify_health |= self.has_skill(Skill::General(GeneralSkill::HealthIncrease));
+        self.modify_energy |= self.has_skill(Skill::General(GeneralSkill::EnergyIncrease));
+        self.skills = SkillSet::initial_skills();
+        for group in self.skill_groups.values_mut() {
+            group.available_sp = group.earned_sp;
+            group.ordered_skills.clear();
+        }
+    }
+
+    /// Returns the changes that turn `old` into `new`, which can be applied
+    /// with [`SkillSet::apply_diff`]. `modify_health`, `modify_energy` and
+    /// `persistence_load_error` aren't part of the diff.
+    pub fn diff(old: &SkillSet, new: &SkillSet) -> SkillSetDiff {
+        SkillSetDiff {
+            added: new
+                .skills
+                .iter()
+                .filter(|(skill, level)| old.skills.get(skill) != Some(level))
+                .map(|(skill, level)| (*skill, *level))
+                .collect(),
+            removed: old
+                .skills
+                .keys()
+                .filter(|skill| !new.skills.contains_key(skill))
+                .copied()
+                .collect(),
+            changed_groups: new
+                .skill_groups
+                .values()
+                .filter(|group| old.skill_groups.get(&group.skill_group_kind) != Some(group))
+                .cloned()
+                .collect(),
+            removed_groups: old
+                .skill_groups
+                .keys()
+                .filter(|kind| !new.skill_groups.contains_key(kind))
+                .copied()
+                .collect(),
+        }
+    }
+
+    /// Applies the changes created by [`SkillSet::diff`]
+    pub fn apply_diff(&mut self, diff: SkillSetDiff) {
+        for skill in diff.removed {
+            self.skills.remove(&skill);
+        }
+        self.skills.extend(diff.added);
+        for kind in diff.removed_groups {
+            self.skill_groups.remove(&kind);
+        }
+        self.skill_groups.extend(
+            diff.changed_groups
+                .into_iter()
+                .map(|group| (group.skill_group_kind, group)),
+        );
+    }
+
+    /// Copy of this skill set as seen by other players, which keeps the
+    /// unlocked skills and their levels but hides the exp and unspent skill
+    /// points of every skill group.
+    pub fn clone_for_spectator(&self) -> Self {
+        let mut skill_set = self.clone();
+        for skill_group in skill_set.skill_groups.values_mut() {
+            skill_group.available_exp = 0;
+            skill_group.earned_exp = 0;
+            skill_group.available_sp = 0;
+        }
+        skill_set
+    }
 }
 
+/// Length of the longest chain of prerequisites leading to `skill`, skills
+/// without prerequisites have a depth of 0
+fn prerequisite_depth(skill: Skill, depths: &mut HashMap<Skill, usize>) -> usize {
+    if let Some(depth) = depths.get(&skill) {
+        return *depth;
+    }
+    // Inserted before recursing so a cyclic definition can't recurse forever
+    depths.insert(skill, 0);
+    let depth = skill
+        .prerequisite_skills()
+        .map(|(prerequisite, _)| prerequisite_depth(prerequisite, depths) + 1)
+        .max()
+        .unwrap_or(0);
+    depths.insert(skill, depth);
+    depth
+}
+
+/// The outcome of every skill passed to [`SkillSet::bulk_unlock`], in the
+/// order they were unlocked in
+pub type BulkUnlockResult = Vec<(Skill, Result<(), SkillUnlockError>)>;
+
 #[derive(Debug)]
 pub enum SkillError {
     MissingSkill,
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub enum SkillUnlockError {
     InsufficientSP,
     MissingPrerequisites,
@@ -1,6 +1,7 @@
 use crate::comp::skillset::{
     SkillGroupKind, SKILL_GROUP_LOOKUP, SKILL_MAX_LEVEL, SKILL_PREREQUISITES,
 };
+use hashbrown::HashMap;
 use serde::{Deserialize, Serialize};
 
 /// Represents a skill that a player can unlock, that either grants them some
@@ -22,6 +23,8 @@ pub enum Skill {
     Climb(ClimbSkill),
     Swim(SwimSkill),
     Pick(MiningSkill),
+    Magic(MagicSkill),
+    Crafting(CraftingSkill),
     UnlockGroup(SkillGroupKind),
 }
 
@@ -183,12 +186,31 @@ pub enum MiningSkill {
     GemGain,
 }
 
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, Serialize, Deserialize, Ord, PartialOrd)]
+pub enum MagicSkill {
+    EnergyRegen,
+    SpellDamage,
+    SpellCost,
+    UnlockWard,
+    WardStrength,
+    WardDuration,
+}
+
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, Serialize, Deserialize, Ord, PartialOrd)]
+pub enum CraftingSkill {
+    Speed,
+    MaterialSaving,
+    SalvageYield,
+    UnlockMasterwork,
+    MasterworkChance,
+}
+
 impl Skill {
     /// Returns a vec of prerequisite skills (it should only be necessary to
     /// note direct prerequisites)
     /// Automatically filters itself from the skills returned
-    /// Is unable to detect cyclic dependencies, so ensure that there are no
-    /// cycles if you modify the prerequisite map.
+    /// Is unable to detect cyclic dependencies, those are caught by
+    /// [`validate_skill_prerequisites`] in the tests.
     pub fn prerequisite_skills(&self) -> impl Iterator<Item = (Skill, u16)> + '_ {
         SKILL_PREREQUISITES
             .get(self)
@@ -215,6 +237,69 @@ impl Skill {
     }
 }
 
+/// Checks that no skill requires itself through its prerequisites. Returns the
+/// path of every cycle found otherwise, e.g. `[a, b, a]` if `a` requires `b`
+/// and `b` requires `a`. A skill listing itself is ignored, the same as in
+/// [`Skill::prerequisite_skills`].
+pub fn validate_skill_prerequisites(
+    map: &HashMap<Skill, HashMap<Skill, u16>>,
+) -> Result<(), Vec<Vec<Skill>>> {
+    #[derive(Clone, Copy, PartialEq)]
+    enum Visit {
+        InProgress,
+        Done,
+    }
+
+    fn visit(
+        skill: Skill,
+        map: &HashMap<Skill, HashMap<Skill, u16>>,
+        visits: &mut HashMap<Skill, Visit>,
+        path: &mut Vec<Skill>,
+        cycles: &mut Vec<Vec<Skill>>,
+    ) {
+        match visits.get(&skill) {
+            Some(Visit::Done) => return,
+            Some(Visit::InProgress) => {
+                let start = path.iter().position(|s| *s == skill).unwrap_or(0);
+                let mut cycle = path[start..].to_vec();
+                cycle.push(skill);
+                cycles.push(cycle);
+                return;
+            },
+            None => {},
+        }
+        visits.insert(skill, Visit::InProgress);
+        path.push(skill);
+        let mut prereqs = map
+            .get(&skill)
+            .into_iter()
+            .flat_map(|prereqs| prereqs.keys().copied())
+            .filter(|prereq| *prereq != skill)
+            .collect::<Vec<_>>();
+        // Sorted so the reported cycles don't depend on the hash order
+        prereqs.sort();
+        for prereq in prereqs {
+            visit(prereq, map, visits, path, cycles);
+        }
+        path.pop();
+        visits.insert(skill, Visit::Done);
+    }
+
+    let mut skills = map.keys().copied().collect::<Vec<_>>();
+    skills.sort();
+    let mut visits = HashMap::new();
+    let mut cycles = Vec::new();
+    for skill in skills {
+        visit(skill, map, &mut visits, &mut Vec::new(), &mut cycles);
+    }
+
+    if cycles.is_empty() {
+        Ok(())
+    } else {
+        Err(cycles)
+    }
+}
+
 /// Tree of modifiers that represent how stats are
 /// changed per each skill level.
 ///
@@ -253,10 +338,17 @@ impl SkillTreeModifiers {
 }
 
 pub struct SwordTreeModifiers {
+    pub combo: SwordComboModifiers,
     pub dash: SwordDashModifiers,
     pub spin: SwordSpinModifiers,
 }
 
+pub struct SwordComboModifiers {
+    /// Number of combo stages available without TsCombo
+    pub untrained_stages: usize,
+    pub combo_window: f32,
+}
+
 pub struct SwordDashModifiers {
     pub energy_cost: f32,
     pub energy_drain: f32,
@@ -275,6 +367,10 @@ pub struct SwordSpinModifiers {
 impl SwordTreeModifiers {
     const fn get() -> Self {
         Self {
+            combo: SwordComboModifiers {
+                untrained_stages: 2,
+                combo_window: 1.25,
+            },
             dash: SwordDashModifiers {
                 energy_cost: 0.9,
                 energy_drain: 0.9,
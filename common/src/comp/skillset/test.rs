@@ -1,5 +1,16 @@
 use super::*;
-use crate::comp::{skillset::SkillPrerequisitesMap, Skill};
+use crate::comp::{
+    melee::{MeleeConstructor, MeleeConstructorKind},
+    skills::{
+        validate_skill_prerequisites, AxeSkill, HammerSkill, MiningSkill, SwordSkill,
+        SKILL_MODIFIERS,
+    },
+    skillset::{
+        migration::{migrate_skill_set, serialize_skill_set, MigrationError, SkillMigrations},
+        SkillPrerequisitesMap,
+    },
+    CharacterAbility, Skill,
+};
 use hashbrown::HashMap;
 
 // Unneeded cfg(test) here keeps rust-analyzer happy
@@ -26,3 +37,415 @@ fn check_cyclic_skill_deps() {
 
     assert!(!is_cyclic_undirected(&graph));
 }
+
+#[test]
+fn skill_prerequisites_are_acyclic() {
+    let skill_prereqs =
+        SkillPrerequisitesMap::load_expect_cloned("common.skill_trees.skill_prerequisites").0;
+    assert_eq!(validate_skill_prerequisites(&skill_prereqs), Ok(()));
+}
+
+#[test]
+fn validator_reports_cycles() {
+    let [a, b, c] = [
+        Skill::Pick(MiningSkill::Speed),
+        Skill::Pick(MiningSkill::OreGain),
+        Skill::Pick(MiningSkill::GemGain),
+    ];
+    let mut map = HashMap::new();
+    map.insert(a, [(b, 1), (a, 1)].into_iter().collect::<HashMap<_, _>>());
+    map.insert(c, [(a, 1)].into_iter().collect::<HashMap<_, _>>());
+    assert_eq!(validate_skill_prerequisites(&map), Ok(()));
+
+    map.insert(b, [(c, 1)].into_iter().collect::<HashMap<_, _>>());
+    assert_eq!(
+        validate_skill_prerequisites(&map),
+        Err(vec![vec![a, b, c, a]])
+    );
+}
+
+#[test]
+fn spectator_skill_set_hides_exp() {
+    let mut skill_set = SkillSet::default();
+    let pick = SkillGroupKind::Weapon(ToolKind::Pick);
+    skill_set.add_skill_points(pick, 2);
+    skill_set.add_experience(pick, 10);
+    skill_set
+        .unlock_skill(Skill::Pick(MiningSkill::Speed))
+        .unwrap();
+
+    let spectator = skill_set.clone_for_spectator();
+    assert_eq!(spectator.available_experience(pick), 0);
+    assert_eq!(spectator.available_sp(pick), 0);
+    assert_eq!(spectator.earned_sp(pick), skill_set.earned_sp(pick));
+    assert!(spectator.skill_groups().all(|group| group.earned_exp == 0));
+    assert_eq!(
+        spectator.skill_level(Skill::Pick(MiningSkill::Speed)).ok(),
+        skill_set.skill_level(Skill::Pick(MiningSkill::Speed)).ok()
+    );
+}
+
+#[test]
+fn snapshot_restore_round_trip() {
+    let mut skill_set = SkillSet::default();
+    let pick = SkillGroupKind::Weapon(ToolKind::Pick);
+    skill_set.add_skill_points(pick, 1);
+    let before = skill_set.clone();
+
+    let snapshot = skill_set.snapshot();
+    skill_set
+        .unlock_skill(Skill::Pick(MiningSkill::Speed))
+        .unwrap();
+    assert_eq!(
+        skill_set.skill_level(Skill::Pick(MiningSkill::Speed)).ok(),
+        Some(1)
+    );
+    assert_eq!(skill_set.available_sp(pick), 0);
+
+    skill_set.restore(snapshot);
+    assert_eq!(skill_set, before);
+}
+
+#[test]
+fn snapshot_rolls_back_failed_batch() {
+    let mut skill_set = SkillSet::default();
+    let pick = SkillGroupKind::Weapon(ToolKind::Pick);
+    skill_set.add_skill_points(pick, 1);
+    let before = skill_set.clone();
+
+    let snapshot = skill_set.snapshot();
+    // the second unlock fails, there are no skill points left
+    let result = [
+        Skill::Pick(MiningSkill::Speed),
+        Skill::Pick(MiningSkill::Speed),
+    ]
+    .iter()
+    .try_for_each(|skill| skill_set.unlock_skill(*skill));
+    assert!(matches!(result, Err(SkillUnlockError::InsufficientSP)));
+    skill_set.restore(snapshot);
+    assert_eq!(skill_set, before);
+    assert!(!skill_set.has_skill(Skill::Pick(MiningSkill::Speed)));
+}
+
+#[test]
+fn bulk_unlock_orders_prerequisites_first() {
+    let mut skill_set = SkillSet::default();
+    let sword = SkillGroupKind::Weapon(ToolKind::Sword);
+    skill_set.unlock_skill_group(sword);
+    skill_set.add_skill_points(sword, 3);
+
+    let results = skill_set.bulk_unlock([
+        Skill::Sword(SwordSkill::SDamage),
+        Skill::Sword(SwordSkill::SDamage),
+        Skill::Sword(SwordSkill::UnlockSpin),
+        Skill::Axe(AxeSkill::UnlockLeap),
+    ]);
+    assert_eq!(results, vec![
+        (Skill::Sword(SwordSkill::UnlockSpin), Ok(())),
+        (
+            Skill::Axe(AxeSkill::UnlockLeap),
+            Err(SkillUnlockError::UnavailableSkillGroup)
+        ),
+        (Skill::Sword(SwordSkill::SDamage), Ok(())),
+        // Level 2 costs 2 SP, only 1 is left
+        (
+            Skill::Sword(SwordSkill::SDamage),
+            Err(SkillUnlockError::InsufficientSP)
+        ),
+    ]);
+    assert_eq!(
+        skill_set
+            .skill_level(Skill::Sword(SwordSkill::SDamage))
+            .ok(),
+        Some(1)
+    );
+}
+
+#[test]
+fn bulk_unlock_reports_missing_prerequisites() {
+    let mut skill_set = SkillSet::default();
+    let sword = SkillGroupKind::Weapon(ToolKind::Sword);
+    skill_set.unlock_skill_group(sword);
+    skill_set.add_skill_points(sword, 1);
+
+    let results = skill_set.bulk_unlock([Skill::Sword(SwordSkill::SDamage)]);
+    assert_eq!(results, vec![(
+        Skill::Sword(SwordSkill::SDamage),
+        Err(SkillUnlockError::MissingPrerequisites)
+    )]);
+    assert_eq!(skill_set.available_sp(sword), 1);
+}
+
+#[test]
+fn diff_round_trip() {
+    let old = SkillSet::default();
+    let mut new = old.clone();
+    let pick = SkillGroupKind::Weapon(ToolKind::Pick);
+    new.add_skill_points(pick, 2);
+    new.unlock_skill(Skill::Pick(MiningSkill::Speed)).unwrap();
+
+    let diff = SkillSet::diff(&old, &new);
+    assert_eq!(diff.added, vec![(Skill::Pick(MiningSkill::Speed), 1)]);
+    assert_eq!(diff.changed_groups.len(), 1);
+    assert!(diff.removed.is_empty() && diff.removed_groups.is_empty());
+    let mut applied = old.clone();
+    applied.apply_diff(diff);
+    assert_eq!(applied, new);
+
+    // Going back removes the skill again
+    let mut reverted = new.clone();
+    reverted.apply_diff(SkillSet::diff(&new, &old));
+    assert_eq!(reverted, old);
+}
+
+#[test]
+fn diff_of_equal_skill_sets_is_empty() {
+    let skill_set = SkillSet::default();
+    assert!(SkillSet::diff(&skill_set, &skill_set.clone()).is_empty());
+}
+
+#[test]
+fn full_respec_refunds_skill_points() {
+    let mut skill_set = SkillSet::default();
+    let pick = SkillGroupKind::Weapon(ToolKind::Pick);
+    skill_set.add_skill_points(pick, 3);
+    skill_set
+        .unlock_skill(Skill::Pick(MiningSkill::Speed))
+        .unwrap();
+    skill_set
+        .unlock_skill(Skill::Pick(MiningSkill::Speed))
+        .unwrap();
+    assert_eq!(skill_set.available_sp(pick), 0);
+    assert_eq!(skill_set.respec_cost(), 3 * SkillSet::RESPEC_COST_PER_SP);
+    assert!(skill_set.can_respec());
+
+    skill_set.full_respec();
+    assert!(!skill_set.has_skill(Skill::Pick(MiningSkill::Speed)));
+    assert_eq!(skill_set.available_sp(pick), 3);
+    assert_eq!(skill_set.earned_sp(pick), 3);
+    assert!(!skill_set.can_respec());
+    // The cost doesn't change by respeccing
+    assert_eq!(skill_set.respec_cost(), 3 * SkillSet::RESPEC_COST_PER_SP);
+}
+
+#[test]
+fn full_respec_is_idempotent() {
+    let mut skill_set = SkillSet::default();
+    skill_set.full_respec();
+    assert_eq!(skill_set, SkillSet::default());
+
+    skill_set.add_skill_points(SkillGroupKind::Weapon(ToolKind::Pick), 1);
+    skill_set
+        .unlock_skill(Skill::Pick(MiningSkill::Speed))
+        .unwrap();
+    skill_set.full_respec();
+    let respecced = skill_set.clone();
+    skill_set.full_respec();
+    assert_eq!(skill_set, respecced);
+}
+
+#[test]
+fn xp_gain_multiplier_grows_with_level() {
+    let general = SkillGroupKind::General;
+    assert_eq!(general.xp_gain_multiplier(0), 1.0);
+    assert_eq!(general.xp_gain_multiplier(9), 1.0);
+    assert_eq!(general.xp_gain_multiplier(10), 1.5);
+    assert_eq!(general.xp_gain_multiplier(19), 1.5);
+    assert_eq!(general.xp_gain_multiplier(20), 2.0);
+
+    // The level of the character applies to all of its skill groups
+    let mut skill_set = SkillSet::default();
+    let pick = SkillGroupKind::Weapon(ToolKind::Pick);
+    skill_set.add_skill_points(pick, 12);
+    assert_eq!(skill_set.level(), 12);
+    assert_eq!(skill_set.xp_gain_multiplier(pick), 1.5);
+    assert_eq!(skill_set.xp_gain_multiplier(general), 1.5);
+}
+
+#[test]
+fn migration_round_trips_skill_sets() {
+    let mut skill_set = SkillSet::default();
+    let pick = SkillGroupKind::Weapon(ToolKind::Pick);
+    skill_set.add_skill_points(pick, 3);
+    skill_set
+        .unlock_skill(Skill::Pick(MiningSkill::Speed))
+        .unwrap();
+    skill_set
+        .unlock_skill(Skill::Pick(MiningSkill::Speed))
+        .unwrap();
+
+    let migrated = migrate_skill_set(&serialize_skill_set(&skill_set)).unwrap();
+    assert_eq!(
+        migrated.skill_level(Skill::Pick(MiningSkill::Speed)).ok(),
+        Some(2)
+    );
+    assert_eq!(migrated.earned_sp(pick), 3);
+    assert_eq!(migrated.available_sp(pick), 0);
+    assert!(migrated.persistence_load_error.is_none());
+}
+
+#[test]
+fn migration_renames_and_drops_old_skills() {
+    let pick = SkillGroupKind::Weapon(ToolKind::Pick);
+    let exp = pick.skill_point_cost(0) + pick.skill_point_cost(1);
+    // A skill set saved before versioning, with a renamed and a removed skill
+    let raw = format!(
+        r#"{{"skill_groups": [{{
+            "skill_group_kind": {{"Weapon": "Pick"}},
+            "available_exp": 0,
+            "earned_exp": {},
+            "available_sp": 0,
+            "earned_sp": 2,
+            "ordered_skills": [{{"Pick": "Haste"}}, {{"Pick": "Removed"}}]
+        }}]}}"#,
+        exp
+    );
+    let mut renames = HashMap::new();
+    renames.insert("Pick(Haste)".to_string(), Some("Pick(Speed)".to_string()));
+    let mut migrations = SkillMigrations::default();
+    migrations.0.insert(2, renames);
+
+    let migrated = migrations.migrate_skill_set(raw.as_bytes()).unwrap();
+    assert!(migrated.has_skill(Skill::Pick(MiningSkill::Speed)));
+    // The points spent on the removed skill are refunded
+    assert_eq!(migrated.available_sp(pick), 1);
+    assert!(migrated.persistence_load_error.is_none());
+}
+
+#[test]
+fn migration_rejects_unknown_formats() {
+    assert_eq!(
+        migrate_skill_set(b"[1, 2, 3]").unwrap_err(),
+        MigrationError::UnknownFormat
+    );
+    assert_eq!(
+        migrate_skill_set(br#"{"version": 99, "skill_groups": []}"#).unwrap_err(),
+        MigrationError::UnsupportedVersion(99)
+    );
+}
+
+#[test]
+fn skill_xp_curve_asset() {
+    let pick = SkillGroupKind::Weapon(ToolKind::Pick);
+    let curve = SkillXpCurveParams::load_expect_cloned("common.skill_trees.skill_xp_curve");
+    assert_eq!(pick.skill_point_cost(0), curve.skill_point_cost(0));
+    assert!(curve.skill_point_cost(0) <= 70);
+    assert!(curve.skill_point_cost(100) <= 1000);
+    assert!(curve.skill_point_cost(10) > curve.skill_point_cost(0));
+
+    let flat = SkillXpCurveParams {
+        exp_ceiling: curve.starting_exp,
+        ..curve
+    };
+    assert_eq!(flat.skill_point_cost(0), flat.skill_point_cost(50));
+}
+
+#[test]
+fn planned_skill_groups_have_skills() {
+    for group in [SkillGroupKind::Magic, SkillGroupKind::Crafting] {
+        let skills = &SKILL_GROUP_DEFS[&group].skills;
+        assert!(skills.len() >= 5);
+        assert!(skills.iter().all(|s| s.skill_group_kind() == Some(group)));
+        assert!(group.total_skill_point_cost() > 0);
+    }
+}
+
+#[test]
+fn exp_progress_queries() {
+    let mut skillset = SkillSet::default();
+    let general = SkillGroupKind::General;
+    let sword = SkillGroupKind::Weapon(ToolKind::Sword);
+    assert_eq!(skillset.exp_fraction_to_next_sp(sword), 0.0);
+    assert_eq!(skillset.exp_fraction_to_next_sp(general), 0.0);
+
+    let cost = general.skill_point_cost(0);
+    skillset.add_experience(general, cost / 2);
+    let fraction = skillset.exp_fraction_to_next_sp(general);
+    assert!((fraction - (cost / 2) as f32 / cost as f32).abs() < 1e-6);
+
+    // Earning the skill point starts the next one from the leftover experience
+    skillset.add_experience(general, cost - cost / 2);
+    assert_eq!(skillset.earned_sp(general), 1);
+    assert_eq!(skillset.exp_fraction_to_next_sp(general), 0.0);
+
+    let earned_before = skillset.total_earned_sp();
+    skillset.add_skill_points(sword, 3);
+    assert_eq!(skillset.total_earned_sp(), earned_before);
+    skillset.unlock_skill_group(sword);
+    skillset.add_skill_points(sword, 3);
+    assert_eq!(skillset.total_earned_sp(), earned_before + 3);
+}
+
+#[test]
+fn hammer_knockback_scales_with_skill_level() {
+    let hammer = SkillGroupKind::Weapon(ToolKind::Hammer);
+    let charged = CharacterAbility::load_expect_cloned("common.abilities.hammer.charged");
+    let scaled_knockback = |skillset: &SkillSet| match charged
+        .clone()
+        .adjusted_by_skills(skillset, Some(ToolKind::Hammer))
+    {
+        CharacterAbility::ChargedMelee {
+            melee_constructor:
+                MeleeConstructor {
+                    scaled: Some(MeleeConstructorKind::Bash { knockback, .. }),
+                    ..
+                },
+            ..
+        } => knockback,
+        _ => panic!("Hammer charge lost its scaled bash"),
+    };
+
+    let mut skillset = SkillSet::default();
+    skillset.unlock_skill_group(hammer);
+    skillset.add_skill_points(hammer, 50);
+    let modifier = SKILL_MODIFIERS.hammer_tree.charged.scaled_knockback;
+    let base = scaled_knockback(&skillset);
+    for level in 1..=Skill::Hammer(HammerSkill::CKnockback).max_level() {
+        skillset
+            .unlock_skill(Skill::Hammer(HammerSkill::CKnockback))
+            .unwrap();
+        let expected = base * modifier.powi(level.into());
+        assert!((scaled_knockback(&skillset) - expected).abs() < 1e-3);
+    }
+}
+
+#[test]
+fn synergies_need_both_skills() {
+    let mut skill_set = SkillSet::default();
+    let synergy = SKILL_SYNERGIES[&(
+        Skill::General(GeneralSkill::HealthIncrease),
+        Skill::General(GeneralSkill::EnergyIncrease),
+    )];
+    skill_set.add_skill_points(SkillGroupKind::General, 2);
+    skill_set
+        .unlock_skill(Skill::General(GeneralSkill::HealthIncrease))
+        .unwrap();
+    assert!(!skill_set.get_active_synergies().contains(&synergy));
+
+    skill_set
+        .unlock_skill(Skill::General(GeneralSkill::EnergyIncrease))
+        .unwrap();
+    assert!(skill_set.get_active_synergies().contains(&synergy));
+
+    let mut stats = Stats::new("Test".to_owned());
+    synergy.apply(&mut stats);
+    assert!(stats.max_health_modifiers.mult_mod > 1.0);
+}
+
+#[test]
+fn synergies_pair_distinct_skills_of_trees() {
+    for (a, b) in SKILL_SYNERGIES.keys() {
+        assert_ne!(a, b);
+        assert!(
+            SKILL_GROUP_LOOKUP.contains_key(a),
+            "{:?} is in no skill tree",
+            a
+        );
+        assert!(
+            SKILL_GROUP_LOOKUP.contains_key(b),
+            "{:?} is in no skill tree",
+            b
+        );
+        assert!(!SKILL_SYNERGIES.contains_key(&(*b, *a)));
+    }
+}
@@ -0,0 +1,281 @@
+use crate::{character::CharacterId, comp::Item, uid::Uid};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+pub type GuildId = u64;
+
+/// Ranks are ordered, a higher rank has all the permissions of the lower ones
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum GuildRank {
+    Member,
+    /// Can invite new members and kick members
+    Officer,
+    /// Can also promote and demote members, there is exactly one leader
+    Leader,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GuildMember {
+    /// Guild membership belongs to a character, unlike `Uid`s this stays the
+    /// same across sessions
+    pub character_id: CharacterId,
+    pub rank: GuildRank,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Guild {
+    pub id: GuildId,
+    pub name: String,
+    /// Short abbreviation of the name, e.g. shown next to member names
+    pub tag: String,
+    pub members: Vec<GuildMember>,
+    pub bank: Vec<Item>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GuildError {
+    InvalidName,
+    InvalidTag,
+    NameTaken,
+    NotACharacter,
+    AlreadyInGuild,
+    NotInGuild,
+    InsufficientRank,
+}
+
+impl fmt::Display for GuildError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let msg = match self {
+            GuildError::InvalidName => "Guild names must be 3 to 24 characters long",
+            GuildError::InvalidTag => "Guild tags must be 2 to 5 letters or digits",
+            GuildError::NameTaken => "A guild with this name or tag already exists",
+            GuildError::NotACharacter => "Only player characters can be part of a guild",
+            GuildError::AlreadyInGuild => "Already a member of a guild",
+            GuildError::NotInGuild => "Not a member of this guild",
+            GuildError::InsufficientRank => "Your guild rank doesn't allow this",
+        };
+        f.write_str(msg)
+    }
+}
+
+impl std::error::Error for GuildError {}
+
+/// Requests of a guild member to change their guild
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GuildManip {
+    Create { name: String, tag: String },
+    Invite(Uid),
+    Kick(CharacterId),
+    Promote(CharacterId, GuildRank),
+    Leave,
+}
+
+/// Requests of a player to change their friends list. Friends are named by
+/// their player alias, since they don't need to be online to be removed.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FriendManip {
+    /// Sends a friend request to an online player, or accepts the one they
+    /// sent
+    Add(String),
+    /// Removes a friend, or withdraws or declines a request
+    Remove(String),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FriendError {
+    NotOnline,
+    Yourself,
+    AlreadyFriends,
+    AlreadyRequested,
+    NotFriends,
+    NoRequest,
+}
+
+impl fmt::Display for FriendError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let msg = match self {
+            FriendError::NotOnline => "There is no player with this name online",
+            FriendError::Yourself => "You can't be friends with yourself",
+            FriendError::AlreadyFriends => "You are already friends",
+            FriendError::AlreadyRequested => "You already sent a friend request to this player",
+            FriendError::NotFriends => "There is no friend or friend request with this name",
+            FriendError::NoRequest => "The friend request was withdrawn",
+        };
+        f.write_str(msg)
+    }
+}
+
+impl std::error::Error for FriendError {}
+
+impl Guild {
+    pub const MAX_NAME_LEN: usize = 24;
+    pub const MAX_TAG_LEN: usize = 5;
+    pub const MIN_NAME_LEN: usize = 3;
+    pub const MIN_TAG_LEN: usize = 2;
+
+    /// Creates a guild with `leader` as its only member
+    pub fn new(
+        id: GuildId,
+        name: String,
+        tag: String,
+        leader: CharacterId,
+    ) -> Result<Self, GuildError> {
+        let name_len = name.trim().chars().count();
+        if name_len < Self::MIN_NAME_LEN || name_len > Self::MAX_NAME_LEN {
+            return Err(GuildError::InvalidName);
+        }
+        let tag_len = tag.chars().count();
+        if tag_len < Self::MIN_TAG_LEN
+            || tag_len > Self::MAX_TAG_LEN
+            || !tag.chars().all(char::is_alphanumeric)
+        {
+            return Err(GuildError::InvalidTag);
+        }
+        Ok(Self {
+            id,
+            name: name.trim().to_owned(),
+            tag,
+            members: vec![GuildMember {
+                character_id: leader,
+                rank: GuildRank::Leader,
+            }],
+            bank: Vec::new(),
+        })
+    }
+
+    pub fn member(&self, character_id: CharacterId) -> Option<&GuildMember> {
+        self.members.iter().find(|m| m.character_id == character_id)
+    }
+
+    pub fn rank(&self, character_id: CharacterId) -> Option<GuildRank> {
+        self.member(character_id).map(|m| m.rank)
+    }
+
+    pub fn can_invite(&self, inviter: CharacterId) -> bool {
+        self.rank(inviter) >= Some(GuildRank::Officer)
+    }
+
+    pub fn add_member(&mut self, character_id: CharacterId) -> Result<(), GuildError> {
+        if self.member(character_id).is_some() {
+            return Err(GuildError::AlreadyInGuild);
+        }
+        self.members.push(GuildMember {
+            character_id,
+            rank: GuildRank::Member,
+        });
+        Ok(())
+    }
+
+    /// Removes `target` from the guild. Members can only be kicked by someone
+    /// of a higher rank, the leader can't be kicked.
+    pub fn kick(&mut self, by: CharacterId, target: CharacterId) -> Result<(), GuildError> {
+        let by_rank = self.rank(by).ok_or(GuildError::NotInGuild)?;
+        let target_rank = self.rank(target).ok_or(GuildError::NotInGuild)?;
+        if by_rank < GuildRank::Officer || target_rank >= by_rank {
+            return Err(GuildError::InsufficientRank);
+        }
+        self.members.retain(|m| m.character_id != target);
+        Ok(())
+    }
+
+    /// Removes the character from the guild. A leaving leader hands
+    /// leadership to the highest ranked member that joined first.
+    pub fn leave(&mut self, character_id: CharacterId) -> Result<(), GuildError> {
+        let rank = self.rank(character_id).ok_or(GuildError::NotInGuild)?;
+        self.members.retain(|m| m.character_id != character_id);
+        if rank == GuildRank::Leader {
+            // `max_by_key` returns the last of equal members
+            if let Some(successor) = self.members.iter_mut().rev().max_by_key(|m| m.rank) {
+                successor.rank = GuildRank::Leader;
+            }
+        }
+        Ok(())
+    }
+
+    /// Changes the rank of `target`, only the leader can do this. Promoting
+    /// someone to leader hands over leadership, the old leader becomes an
+    /// officer.
+    pub fn promote(
+        &mut self,
+        by: CharacterId,
+        target: CharacterId,
+        rank: GuildRank,
+    ) -> Result<(), GuildError> {
+        if self.rank(by).ok_or(GuildError::NotInGuild)? != GuildRank::Leader || by == target {
+            return Err(GuildError::InsufficientRank);
+        }
+        if self.member(target).is_none() {
+            return Err(GuildError::NotInGuild);
+        }
+        for member in self.members.iter_mut() {
+            if member.character_id == target {
+                member.rank = rank;
+            } else if member.character_id == by && rank == GuildRank::Leader {
+                member.rank = GuildRank::Officer;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn guild() -> Guild {
+        let mut guild = Guild::new(1, "Dome Keepers".to_owned(), "DK".to_owned(), 10).unwrap();
+        guild.add_member(11).unwrap();
+        guild.add_member(12).unwrap();
+        guild
+    }
+
+    #[test]
+    fn validates_name_and_tag() {
+        assert_eq!(
+            Guild::new(1, "ab".to_owned(), "DK".to_owned(), 1).err(),
+            Some(GuildError::InvalidName)
+        );
+        assert_eq!(
+            Guild::new(1, "Dome Keepers".to_owned(), "D K".to_owned(), 1).err(),
+            Some(GuildError::InvalidTag)
+        );
+    }
+
+    #[test]
+    fn kicking_needs_higher_rank() {
+        let mut guild = guild();
+        assert_eq!(guild.kick(11, 12), Err(GuildError::InsufficientRank));
+        guild.promote(10, 11, GuildRank::Officer).unwrap();
+        assert_eq!(guild.kick(11, 10), Err(GuildError::InsufficientRank));
+        assert_eq!(guild.kick(11, 12), Ok(()));
+        assert!(guild.member(12).is_none());
+    }
+
+    #[test]
+    fn promoting_to_leader_hands_over_leadership() {
+        let mut guild = guild();
+        assert_eq!(
+            guild.promote(11, 12, GuildRank::Officer),
+            Err(GuildError::InsufficientRank)
+        );
+        guild.promote(10, 11, GuildRank::Leader).unwrap();
+        assert_eq!(guild.rank(11), Some(GuildRank::Leader));
+        assert_eq!(guild.rank(10), Some(GuildRank::Officer));
+    }
+
+    #[test]
+    fn leaving_leader_hands_over_leadership() {
+        let mut guild = guild();
+        guild.add_member(13).unwrap();
+        guild.promote(10, 13, GuildRank::Officer).unwrap();
+        assert_eq!(guild.leave(10), Ok(()));
+        assert!(guild.member(10).is_none());
+        assert_eq!(guild.rank(13), Some(GuildRank::Leader));
+        assert_eq!(guild.leave(13), Ok(()));
+        assert_eq!(guild.rank(11), Some(GuildRank::Leader));
+        assert_eq!(guild.leave(13), Err(GuildError::NotInGuild));
+        assert_eq!(guild.leave(11), Ok(()));
+        assert_eq!(guild.leave(12), Ok(()));
+        assert!(guild.members.is_empty());
+    }
+}
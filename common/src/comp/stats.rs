@@ -1,3 +1,4 @@
+use crate::combat::Resistances;
 use serde::{Deserialize, Serialize};
 use specs::{Component, DerefFlaggedStorage};
 use specs_idvs::IdvStorage;
@@ -50,6 +51,8 @@ impl Error for StatChangeError {}
 pub struct Stats {
     pub name: String,
     pub damage_reduction: f32,
+    /// Resistances granted by buffs, armor is accounted for separately
+    pub resistances: Resistances,
     pub max_health_modifiers: StatsModifier,
     pub move_speed_modifier: f32,
     pub attack_speed_modifier: f32,
@@ -62,6 +65,7 @@ impl Stats {
         Self {
             name,
             damage_reduction: 0.0,
+            resistances: Resistances::default(),
             max_health_modifiers: StatsModifier::default(),
             move_speed_modifier: 1.0,
             attack_speed_modifier: 1.0,
@@ -77,6 +81,7 @@ impl Stats {
     /// Resets temporary modifiers to default values
     pub fn reset_temp_modifiers(&mut self) {
         self.damage_reduction = 0.0;
+        self.resistances = Resistances::default();
         self.max_health_modifiers = StatsModifier::default();
         self.move_speed_modifier = 1.0;
         self.attack_speed_modifier = 1.0;
@@ -62,6 +62,40 @@ pub enum ServerEvent {
     },
     InventoryManip(EcsEntity, comp::InventoryManip),
     GroupManip(EcsEntity, comp::GroupManip),
+    GuildManip(EcsEntity, comp::social::GuildManip),
+    FriendManip(EcsEntity, comp::social::FriendManip),
+    RespecSkills(EcsEntity),
+    Sleep {
+        entity: EcsEntity,
+        bed: Vec3<i32>,
+    },
+    StartFishing(EcsEntity),
+    ReelIn(EcsEntity),
+    ReelFishingLine {
+        entity: EcsEntity,
+        reeling: bool,
+    },
+    CreateItemDrop {
+        pos: Pos,
+        item: comp::Item,
+    },
+    WaterCrop {
+        entity: EcsEntity,
+        pos: Vec3<i32>,
+    },
+    HarvestCrop {
+        entity: EcsEntity,
+        pos: Vec3<i32>,
+    },
+    LoadRecipeIntoPot {
+        entity: EcsEntity,
+        pos: Vec3<i32>,
+        recipe: String,
+    },
+    TakeFromPot {
+        entity: EcsEntity,
+        pos: Vec3<i32>,
+    },
     Respawn(EcsEntity),
     Shoot {
         entity: EcsEntity,
@@ -167,6 +201,11 @@ pub enum ServerEvent {
         entity: EcsEntity,
         change: i32,
     },
+    /// An ability with a cooldown was used
+    StartCooldown {
+        entity: EcsEntity,
+        ability_id: String,
+    },
     Parry {
         entity: EcsEntity,
         energy_cost: f32,
@@ -74,9 +74,13 @@ pub mod spiral;
 pub mod states;
 #[cfg(not(target_arch = "wasm32"))] pub mod store;
 #[cfg(not(target_arch = "wasm32"))]
+pub mod talent;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod terrain;
 #[cfg(not(target_arch = "wasm32"))] pub mod time;
 #[cfg(not(target_arch = "wasm32"))] pub mod trade;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod trajectory;
 #[cfg(not(target_arch = "wasm32"))] pub mod typed;
 pub mod uid;
 #[cfg(not(target_arch = "wasm32"))] pub mod util;
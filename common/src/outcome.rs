@@ -60,6 +60,12 @@ pub enum Outcome {
     },
     Damage {
         pos: Vec3<f32>,
+        by: Option<Uid>,
+    },
+    /// A hit that dealt critical damage, emitted along with `Damage`
+    CriticalHit {
+        pos: Vec3<f32>,
+        by: Option<Uid>,
     },
     Death {
         pos: Vec3<f32>,
@@ -76,6 +82,12 @@ pub enum Outcome {
     GroundSlam {
         pos: Vec3<f32>,
     },
+    /// An entity landed hard enough to take fall damage
+    FallDamage {
+        pos: Vec3<f32>,
+        uid: Uid,
+        damage: f32,
+    },
     Utterance {
         pos: Vec3<f32>,
         body: comp::Body,
@@ -85,6 +97,13 @@ pub enum Outcome {
         pos: Vec3<f32>,
         wielded: bool,
     },
+    /// Something broke the surface of water, like a bobber landing or a fish
+    /// biting at it
+    Splash {
+        pos: Vec3<f32>,
+        /// 1.0 for a fish biting
+        power: f32,
+    },
 }
 
 impl Outcome {
@@ -97,12 +116,15 @@ impl Outcome {
             | Outcome::SkillPointGain { pos, .. }
             | Outcome::SummonedCreature { pos, .. }
             | Outcome::Damage { pos, .. }
+            | Outcome::CriticalHit { pos, .. }
             | Outcome::Death { pos, .. }
             | Outcome::Block { pos, .. }
             | Outcome::PoiseChange { pos, .. }
             | Outcome::GroundSlam { pos }
+            | Outcome::FallDamage { pos, .. }
             | Outcome::Utterance { pos, .. }
-            | Outcome::Glider { pos, .. } => Some(*pos),
+            | Outcome::Glider { pos, .. }
+            | Outcome::Splash { pos, .. } => Some(*pos),
             Outcome::BreakBlock { pos, .. } => Some(pos.map(|e| e as f32 + 0.5)),
             Outcome::ExpChange { .. } | Outcome::ComboChange { .. } => None,
         }
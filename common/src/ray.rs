@@ -1,4 +1,4 @@
-use crate::vol::ReadVol;
+use crate::{terrain::Block, util::SpatialGrid, vol::ReadVol};
 use vek::*;
 
 pub trait RayForEach<V> = FnMut(&V, Vec3<i32>);
@@ -109,3 +109,416 @@ where
         (dist, Ok(None))
     }
 }
+
+/// What stopped a [`RayCast`]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum RayTarget<E> {
+    Block(Vec3<i32>),
+    Entity(E),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RayHit<E> {
+    pub target: RayTarget<E>,
+    /// Where the ray entered the block or entity
+    pub pos: Vec3<f32>,
+    /// Normal of the face the ray entered through, zero if the ray started
+    /// inside
+    pub normal: Vec3<f32>,
+    /// Distance from the start of the ray to `pos`
+    pub dist: f32,
+}
+
+/// Exact voxel raycast, visiting every voxel the ray passes through in order
+/// (Amanatides & Woo). Unlike [`Ray`] it can't step over the corner of a
+/// voxel, and voxels outside of the volume never stop it.
+///
+/// Entities are tested against their bounding boxes and the closest of the
+/// hit block and entities is returned. The candidates are usually taken from
+/// a [`SpatialGrid`], see [`RayCast::spatial_grid`].
+///
+/// [`SpatialGrid`]: crate::util::SpatialGrid
+pub struct RayCast<'a, V: ReadVol, F, E = ()> {
+    vol: &'a V,
+    from: Vec3<f32>,
+    dir: Vec3<f32>,
+    max_dist: f32,
+    hit: F,
+    entities: Vec<(E, Aabb<f32>)>,
+    ignore: Option<E>,
+}
+
+impl<'a, V: ReadVol> RayCast<'a, V, fn(&V::Vox) -> bool> {
+    /// Casts from `from` in direction `dir` for at most `max_dist` blocks. By
+    /// default any voxel stops the ray, see [`RayCast::until`].
+    pub fn new(vol: &'a V, from: Vec3<f32>, dir: Vec3<f32>, max_dist: f32) -> Self {
+        Self {
+            vol,
+            from,
+            dir: dir.try_normalized().unwrap_or_else(Vec3::zero),
+            max_dist,
+            hit: |_| true,
+            entities: Vec::new(),
+            ignore: None,
+        }
+    }
+
+    /// Casts from `from` to `to`
+    pub fn between(vol: &'a V, from: Vec3<f32>, to: Vec3<f32>) -> Self {
+        Self::new(vol, from, to - from, from.distance(to))
+    }
+}
+
+impl<'a, V, F, E> RayCast<'a, V, F, E>
+where
+    V: ReadVol,
+    F: FnMut(&V::Vox) -> bool,
+    E: PartialEq,
+{
+    /// Voxels for which `hit` returns true stop the ray
+    pub fn until<H: FnMut(&V::Vox) -> bool>(self, hit: H) -> RayCast<'a, V, H, E> {
+        RayCast {
+            vol: self.vol,
+            from: self.from,
+            dir: self.dir,
+            max_dist: self.max_dist,
+            hit,
+            entities: self.entities,
+            ignore: self.ignore,
+        }
+    }
+
+    #[must_use]
+    pub fn max_dist(mut self, max_dist: f32) -> Self {
+        self.max_dist = max_dist;
+        self
+    }
+
+    /// Entities the ray can hit, with their bounding boxes
+    pub fn entities<T: PartialEq>(
+        self,
+        entities: impl IntoIterator<Item = (T, Aabb<f32>)>,
+    ) -> RayCast<'a, V, F, T> {
+        RayCast {
+            vol: self.vol,
+            from: self.from,
+            dir: self.dir,
+            max_dist: self.max_dist,
+            hit: self.hit,
+            entities: entities.into_iter().collect(),
+            ignore: None,
+        }
+    }
+
+    /// Takes the entities near the ray from `grid`, `aabb` gives the bounding
+    /// box of an entity or `None` for entities the ray should pass through
+    pub fn spatial_grid(
+        self,
+        grid: &SpatialGrid,
+        mut aabb: impl FnMut(specs::Entity) -> Option<Aabb<f32>>,
+    ) -> RayCast<'a, V, F, specs::Entity> {
+        let entities = grid
+            .in_aabr(self.aabr())
+            .filter_map(|entity| Some((entity, aabb(entity)?)))
+            .collect::<Vec<_>>();
+        self.entities(entities)
+    }
+
+    /// The ray passes through this entity, usually the one casting it
+    #[must_use]
+    pub fn ignore_entity(mut self, entity: E) -> Self {
+        self.ignore = Some(entity);
+        self
+    }
+
+    /// Horizontal area the ray passes over
+    pub fn aabr(&self) -> Aabr<i32> {
+        let to = self.from + self.dir * self.max_dist;
+        Aabr {
+            min: Vec2::from(self.from.map2(to, f32::min)).map(|e: f32| e.floor() as i32),
+            max: Vec2::from(self.from.map2(to, f32::max)).map(|e: f32| e.ceil() as i32),
+        }
+    }
+
+    /// Returns the closest block or entity hit within the maximum distance
+    pub fn cast(mut self) -> Option<RayHit<E>> {
+        let mut nearest = None::<RayHit<E>>;
+        for (entity, aabb) in std::mem::take(&mut self.entities) {
+            if self.ignore.as_ref() == Some(&entity) {
+                continue;
+            }
+            if let Some((dist, normal)) = ray_aabb(self.from, self.dir, aabb) {
+                if dist <= self.max_dist && nearest.as_ref().map_or(true, |n| dist < n.dist) {
+                    nearest = Some(RayHit {
+                        target: RayTarget::Entity(entity),
+                        pos: self.from + self.dir * dist,
+                        normal,
+                        dist,
+                    });
+                }
+            }
+        }
+
+        // The blocks only need to be walked up to the closest entity
+        let max_dist = nearest.as_ref().map_or(self.max_dist, |n| n.dist);
+        self.walk(max_dist)
+            .map(|(block, normal, dist)| RayHit {
+                target: RayTarget::Block(block),
+                pos: self.from + self.dir * dist,
+                normal,
+                dist,
+            })
+            .or(nearest)
+    }
+
+    /// Returns the first voxel `hit` accepts with the normal of the face the
+    /// ray entered it through and the distance to it
+    fn walk(&mut self, max_dist: f32) -> Option<(Vec3<i32>, Vec3<f32>, f32)> {
+        let mut pos = self.from.map(|e| e.floor() as i32);
+        let step = self.dir.map(|e| {
+            if e > 0.0 {
+                1
+            } else if e < 0.0 {
+                -1
+            } else {
+                0
+            }
+        });
+        // Distance along the ray to cross a whole voxel on each axis
+        let delta = self.dir.map(|e| 1.0 / e.abs());
+        // Distance along the ray to the next voxel boundary on each axis
+        let mut next = Vec3::new(0usize, 1, 2).map(|i| {
+            if step[i] == 0 {
+                f32::INFINITY
+            } else {
+                let boundary = pos[i] + (step[i] + 1) / 2;
+                (boundary as f32 - self.from[i]) / self.dir[i]
+            }
+        });
+        let mut normal = Vec3::zero();
+        let mut dist = 0.0;
+
+        while dist <= max_dist {
+            if matches!(self.vol.get(pos), Ok(vox) if (self.hit)(vox)) {
+                return Some((pos, normal, dist));
+            }
+
+            let axis = if next.x < next.y {
+                if next.x < next.z { 0 } else { 2 }
+            } else if next.y < next.z {
+                1
+            } else {
+                2
+            };
+            dist = next[axis];
+            pos[axis] += step[axis];
+            next[axis] += delta[axis];
+            normal = Vec3::zero();
+            normal[axis] = -step[axis] as f32;
+        }
+
+        None
+    }
+}
+
+impl<'a, V, F, E> RayCast<'a, V, F, E>
+where
+    V: ReadVol<Vox = Block>,
+    F: FnMut(&Block) -> bool,
+    E: PartialEq,
+{
+    /// Water and other liquids never stop the ray, whatever the predicate says
+    pub fn ignore_fluid(self) -> RayCast<'a, V, impl FnMut(&Block) -> bool, E> {
+        let mut hit = self.hit;
+        RayCast {
+            vol: self.vol,
+            from: self.from,
+            dir: self.dir,
+            max_dist: self.max_dist,
+            hit: move |block: &Block| !block.is_liquid() && hit(block),
+            entities: self.entities,
+            ignore: self.ignore,
+        }
+    }
+}
+
+/// Distance along the ray to where it enters `aabb` and the normal of the
+/// face it enters through (slab method)
+fn ray_aabb(from: Vec3<f32>, dir: Vec3<f32>, aabb: Aabb<f32>) -> Option<(f32, Vec3<f32>)> {
+    let mut near = 0.0f32;
+    let mut far = f32::INFINITY;
+    let mut normal = Vec3::zero();
+    for i in 0..3 {
+        if dir[i] == 0.0 {
+            if from[i] < aabb.min[i] || from[i] > aabb.max[i] {
+                return None;
+            }
+        } else {
+            let (enter, exit, face) = if dir[i] > 0.0 {
+                (aabb.min[i], aabb.max[i], -1.0)
+            } else {
+                (aabb.max[i], aabb.min[i], 1.0)
+            };
+            let enter = (enter - from[i]) / dir[i];
+            if enter > near {
+                near = enter;
+                normal = Vec3::zero();
+                normal[i] = face;
+            }
+            far = far.min((exit - from[i]) / dir[i]);
+            if near > far {
+                return None;
+            }
+        }
+    }
+    Some((near, normal))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        terrain::{BlockKind, SpriteKind},
+        vol::WriteVol,
+        volumes::dyna::Dyna,
+    };
+    use rand::{prelude::*, rngs::StdRng};
+
+    const SIZE: u32 = 12;
+
+    fn rock() -> Block { Block::new(BlockKind::Rock, Rgb::zero()) }
+
+    fn random_vol(rng: &mut impl Rng) -> Dyna<Block, ()> {
+        Dyna::from_fn(Vec3::broadcast(SIZE), (), |_| {
+            if rng.gen_bool(0.03) {
+                rock()
+            } else {
+                Block::air(SpriteKind::Empty)
+            }
+        })
+    }
+
+    /// Steps along the ray in tiny steps, the result is exact up to `STEP`
+    fn reference(
+        vol: &Dyna<Block, ()>,
+        from: Vec3<f32>,
+        dir: Vec3<f32>,
+        max_dist: f32,
+    ) -> Option<(Vec3<i32>, f32)> {
+        const STEP: f32 = 0.0005;
+        (0..)
+            .map(|i| i as f32 * STEP)
+            .take_while(|dist| *dist <= max_dist)
+            .map(|dist| ((from + dir * dist).map(|e| e.floor() as i32), dist))
+            .find(|(pos, _)| vol.get(*pos).map_or(false, |b| b.is_filled()))
+    }
+
+    #[test]
+    fn matches_stepping_reference() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..500 {
+            let vol = random_vol(&mut rng);
+            let from = Vec3::<f32>::zero().map(|_| rng.gen_range(0.0..SIZE as f32));
+            let mut dir = Vec3::<f32>::zero().map(|_| rng.gen_range(-1.0..1.0));
+            // Rays along the axes and planes are the usual edge cases
+            if rng.gen_bool(0.2) {
+                dir[rng.gen_range(0..3)] = 0.0;
+            }
+            let dir = dir.normalized();
+
+            let hit = RayCast::new(&vol, from, dir, 15.0)
+                .until(|b| b.is_filled())
+                .cast();
+            match (hit, reference(&vol, from, dir, 15.0)) {
+                (Some(hit), Some((pos, dist))) => {
+                    assert!((hit.dist - dist).abs() < 0.002, "{:?} {:?}", hit, dist);
+                    // Grazing a corner may touch a different block at the same distance
+                    assert!(hit.target == RayTarget::Block(pos) || (hit.dist - dist).abs() < 0.001);
+                    assert!((hit.pos - (from + dir * hit.dist)).magnitude() < 0.001);
+                },
+                (None, None) => {},
+                (hit, reference) => {
+                    // Only a hit right at the maximum distance can be missed by either
+                    let dist = hit.map(|h| h.dist).or(reference.map(|r| r.1)).unwrap();
+                    assert!((dist - 15.0).abs() < 0.01, "{:?} {:?}", hit, reference);
+                },
+            }
+        }
+    }
+
+    #[test]
+    fn reports_face_normal() {
+        let mut vol =
+            Dyna::<_, ()>::filled(Vec3::broadcast(SIZE), Block::air(SpriteKind::Empty), ());
+        vol.set(Vec3::new(5, 5, 5), rock()).unwrap();
+        let hit = RayCast::between(&vol, Vec3::new(5.5, 5.5, 0.5), Vec3::new(5.5, 5.5, 11.0))
+            .until(|b| b.is_filled())
+            .cast()
+            .unwrap();
+        assert_eq!(hit.target, RayTarget::Block(Vec3::new(5, 5, 5)));
+        assert_eq!(hit.normal, -Vec3::unit_z());
+        assert!((hit.dist - 4.5).abs() < 0.0001);
+        // Starting inside the block hits it right away
+        let hit = RayCast::new(&vol, Vec3::broadcast(5.5), Vec3::unit_x(), 10.0)
+            .until(|b| b.is_filled())
+            .cast()
+            .unwrap();
+        assert_eq!((hit.normal, hit.dist), (Vec3::zero(), 0.0));
+        // Too far away
+        assert!(
+            RayCast::new(&vol, Vec3::new(0.5, 5.5, 5.5), Vec3::unit_x(), 4.0)
+                .until(|b| b.is_filled())
+                .cast()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn ignores_fluid() {
+        let mut vol =
+            Dyna::<_, ()>::filled(Vec3::broadcast(SIZE), Block::air(SpriteKind::Empty), ());
+        vol.set(
+            Vec3::new(3, 0, 0),
+            Block::new(BlockKind::Water, Rgb::zero()),
+        )
+        .unwrap();
+        vol.set(Vec3::new(6, 0, 0), rock()).unwrap();
+        let cast = || RayCast::new(&vol, Vec3::broadcast(0.5), Vec3::unit_x(), 10.0);
+        let hit = cast().until(|b| !b.is_air()).cast().unwrap();
+        assert_eq!(hit.target, RayTarget::Block(Vec3::new(3, 0, 0)));
+        let hit = cast().until(|b| !b.is_air()).ignore_fluid().cast().unwrap();
+        assert_eq!(hit.target, RayTarget::Block(Vec3::new(6, 0, 0)));
+    }
+
+    #[test]
+    fn nearest_of_block_and_entities() {
+        let mut vol =
+            Dyna::<_, ()>::filled(Vec3::broadcast(SIZE), Block::air(SpriteKind::Empty), ());
+        vol.set(Vec3::new(6, 0, 0), rock()).unwrap();
+        let aabb = |x: f32| Aabb {
+            min: Vec3::new(x, 0.0, 0.0),
+            max: Vec3::new(x + 1.0, 1.0, 1.0),
+        };
+        let cast = || {
+            RayCast::new(&vol, Vec3::broadcast(0.5), Vec3::unit_x(), 10.0).until(|b| b.is_filled())
+        };
+
+        let hit = cast()
+            .entities([(1, aabb(4.0)), (2, aabb(2.0))])
+            .cast()
+            .unwrap();
+        assert_eq!(hit.target, RayTarget::Entity(2));
+        assert_eq!(hit.normal, -Vec3::unit_x());
+        assert!((hit.dist - 1.5).abs() < 0.0001);
+        // The caster doesn't block its own ray, even though the ray starts in it
+        let hit = cast()
+            .entities([(0, aabb(0.0)), (1, aabb(4.0))])
+            .ignore_entity(0)
+            .cast()
+            .unwrap();
+        assert_eq!(hit.target, RayTarget::Entity(1));
+        // Entities behind the block are hidden
+        let hit = cast().entities([(1, aabb(8.0))]).cast().unwrap();
+        assert_eq!(hit.target, RayTarget::Block(Vec3::new(6, 0, 0)));
+    }
+}
@@ -112,7 +112,11 @@ impl CharacterBehavior for Data {
                     let mut damage = AttackDamage::new(
                         Damage {
                             source: DamageSource::Energy,
-                            kind: DamageKind::Energy,
+                            kind: self
+                                .static_data
+                                .damage_effect
+                                .and_then(|effect| effect.damage_kind())
+                                .unwrap_or(DamageKind::Energy),
                             value: self.static_data.damage,
                         },
                         Some(GroupTarget::OutOfGroup),
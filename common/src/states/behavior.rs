@@ -1,11 +1,11 @@
 use crate::{
     comp::{
         self, character_state::OutputEvents, item::MaterialStatManifest, ActiveAbilities, Beam,
-        Body, CharacterState, Combo, ControlAction, Controller, ControllerInputs, Density, Energy,
-        Health, InputAttr, InputKind, Inventory, InventoryAction, Mass, Melee, Ori, PhysicsState,
-        Pos, SkillSet, StateUpdate, Stats, Vel,
+        Body, CharacterState, Combo, ControlAction, Controller, ControllerInputs, Cooldowns,
+        Density, Energy, Health, InputAttr, InputKind, Inventory, InventoryAction, Mass, Melee,
+        Ori, PhysicsState, Pos, SkillSet, StateUpdate, Stats, Vel,
     },
-    resources::DeltaTime,
+    resources::{DeltaTime, GameTime},
     terrain::TerrainGrid,
     uid::Uid,
 };
@@ -125,10 +125,12 @@ pub struct JoinData<'a> {
     pub stats: &'a Stats,
     pub skill_set: &'a SkillSet,
     pub active_abilities: Option<&'a ActiveAbilities>,
+    pub cooldowns: Option<&'a Cooldowns>,
     pub msm: &'a MaterialStatManifest,
     pub combo: Option<&'a Combo>,
     pub alignment: Option<&'a comp::Alignment>,
     pub terrain: &'a TerrainGrid,
+    pub game_time: &'a GameTime,
 }
 
 pub struct JoinStruct<'a> {
@@ -151,9 +153,11 @@ pub struct JoinStruct<'a> {
     pub stat: &'a Stats,
     pub skill_set: &'a SkillSet,
     pub active_abilities: Option<&'a ActiveAbilities>,
+    pub cooldowns: Option<&'a Cooldowns>,
     pub combo: Option<&'a Combo>,
     pub alignment: Option<&'a comp::Alignment>,
     pub terrain: &'a TerrainGrid,
+    pub game_time: &'a GameTime,
 }
 
 impl<'a> JoinData<'a> {
@@ -189,6 +193,8 @@ impl<'a> JoinData<'a> {
             alignment: j.alignment,
             terrain: j.terrain,
             active_abilities: j.active_abilities,
+            cooldowns: j.cooldowns,
+            game_time: j.game_time,
         }
     }
 }
@@ -69,6 +69,12 @@ impl Data {
             0.0
         }
     }
+
+    /// Speed the projectile would be shot with at the current charge
+    pub fn projectile_speed(&self) -> f32 {
+        self.static_data.initial_projectile_speed
+            + self.charge_frac() * self.static_data.scaled_projectile_speed
+    }
 }
 
 impl CharacterBehavior for Data {
@@ -127,8 +133,7 @@ impl CharacterBehavior for Data {
                         body: self.static_data.projectile_body,
                         projectile,
                         light: self.static_data.projectile_light,
-                        speed: self.static_data.initial_projectile_speed
-                            + charge_frac * self.static_data.scaled_projectile_speed,
+                        speed: self.projectile_speed(),
                         object: None,
                     });
 
@@ -42,6 +42,10 @@ pub struct Stage<T> {
     pub hit_timing: f32,
     /// Initial recover duration of stage (how long until character exits state)
     pub base_recover_duration: T,
+    /// How long after recovering an attack input still continues the combo,
+    /// once this passes the combo resets to the first stage
+    #[serde(default)]
+    pub combo_window: T,
     /// How much forward movement there is in the swing portion of the stage
     pub forward_movement: f32,
     /// What kind of damage this stage of the attack does
@@ -65,6 +69,7 @@ impl Stage<f32> {
             hit_timing: self.hit_timing,
             base_swing_duration: Duration::from_secs_f32(self.base_swing_duration),
             base_recover_duration: Duration::from_secs_f32(self.base_recover_duration),
+            combo_window: Duration::from_secs_f32(self.combo_window),
             forward_movement: self.forward_movement,
             damage_kind: self.damage_kind,
             damage_effect: self.damage_effect,
@@ -95,6 +100,7 @@ impl Stage<f32> {
             base_swing_duration: self.base_swing_duration / stats.speed,
             hit_timing: self.hit_timing,
             base_recover_duration: self.base_recover_duration / stats.speed,
+            combo_window: self.combo_window,
             forward_movement: self.forward_movement,
             damage_kind: self.damage_kind,
             damage_effect: self.damage_effect,
@@ -156,6 +162,45 @@ pub struct Data {
     pub stage_section: StageSection,
 }
 
+/// How a combo continues once a stage finished recovering
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ComboStep {
+    /// The attack input was pressed in time, continues with the given stage
+    NextStage(u32),
+    /// Still within the combo window, waiting for the attack input
+    Await,
+    /// The combo window was missed
+    Reset,
+}
+
+impl Data {
+    // Index should be `self.stage - 1`, however in cases of client-server desync
+    // this can cause panics. This ensures that `self.stage - 1` is valid, and if it
+    // isn't, index of 0 is used, which is always safe.
+    fn stage_index(&self) -> usize {
+        self.static_data
+            .stage_data
+            .get(self.stage as usize - 1)
+            .map_or(0, |_| self.stage as usize - 1)
+    }
+
+    /// The stage after the current one, wraps around after the last stage
+    pub fn next_stage(&self) -> u32 { (self.stage % self.static_data.num_stages) + 1 }
+
+    /// Decides how the combo continues after the recover duration of the
+    /// current stage passed
+    pub fn after_recover(&self, input_pressed: bool) -> ComboStep {
+        let stage = &self.static_data.stage_data[self.stage_index()];
+        if input_pressed {
+            ComboStep::NextStage(self.next_stage())
+        } else if self.timer < stage.base_recover_duration + stage.combo_window {
+            ComboStep::Await
+        } else {
+            ComboStep::Reset
+        }
+    }
+}
+
 impl CharacterBehavior for Data {
     fn behavior(&self, data: &JoinData, output_events: &mut OutputEvents) -> StateUpdate {
         let mut update = StateUpdate::from(data);
@@ -164,14 +209,7 @@ impl CharacterBehavior for Data {
 
         handle_move(data, &mut update, 0.4);
 
-        // Index should be `self.stage - 1`, however in cases of client-server desync
-        // this can cause panics. This ensures that `self.stage - 1` is valid, and if it
-        // isn't, index of 0 is used, which is always safe.
-        let stage_index = self
-            .static_data
-            .stage_data
-            .get(self.stage as usize - 1)
-            .map_or(0, |_| self.stage as usize - 1);
+        let stage_index = self.stage_index();
 
         let speed_modifer = 1.0
             + self.static_data.max_speed_increase
@@ -343,11 +381,22 @@ impl CharacterBehavior for Data {
                     });
                 } else {
                     // Done
-                    if input_is_pressed(data, self.static_data.ability_info.input) {
-                        reset_state(self, data, output_events, &mut update);
-                    } else {
-                        update.character =
-                            CharacterState::Wielding(wielding::Data { is_sneaking: false });
+                    let input_pressed = input_is_pressed(data, self.static_data.ability_info.input);
+                    match self.after_recover(input_pressed) {
+                        ComboStep::NextStage(stage) => {
+                            reset_state(self, data, output_events, &mut update, stage);
+                        },
+                        ComboStep::Await => {
+                            update.character = CharacterState::ComboMelee(Data {
+                                static_data: self.static_data.clone(),
+                                timer: tick_attack_or_default(data, self.timer, None),
+                                ..*self
+                            });
+                        },
+                        ComboStep::Reset => {
+                            update.character =
+                                CharacterState::Wielding(wielding::Data { is_sneaking: false });
+                        },
                     }
                 }
             },
@@ -373,6 +422,7 @@ fn reset_state(
     join: &JoinData,
     output_events: &mut OutputEvents,
     update: &mut StateUpdate,
+    stage: u32,
 ) {
     handle_input(
         join,
@@ -382,6 +432,150 @@ fn reset_state(
     );
 
     if let CharacterState::ComboMelee(c) = &mut update.character {
-        c.stage = (data.stage % data.static_data.num_stages) + 1;
+        c.stage = stage;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        assets::AssetExt,
+        comp::{
+            skills::{Skill, SwordSkill},
+            CharacterAbility, InputKind, SkillGroupKind, SkillSet,
+        },
+    };
+
+    fn stage(stage: u32) -> Stage<Duration> {
+        Stage {
+            stage,
+            base_damage: 10.0,
+            damage_increase: 1.0,
+            base_poise_damage: 0.0,
+            poise_damage_increase: 0.0,
+            knockback: 0.0,
+            range: 4.0,
+            angle: 30.0,
+            base_buildup_duration: Duration::from_millis(100),
+            base_swing_duration: Duration::from_millis(100),
+            hit_timing: 0.5,
+            base_recover_duration: Duration::from_millis(150),
+            combo_window: Duration::from_millis(250),
+            forward_movement: 0.0,
+            damage_kind: DamageKind::Slashing,
+            damage_effect: None,
+        }
+    }
+
+    fn recovering(stage: u32) -> Data {
+        Data {
+            static_data: StaticData {
+                num_stages: 3,
+                stage_data: (1..=3).map(self::stage).collect(),
+                initial_energy_gain: 0.0,
+                max_energy_gain: 20.0,
+                energy_increase: 2.5,
+                speed_increase: 0.9,
+                max_speed_increase: 0.8,
+                scales_from_combo: 2,
+                is_interruptible: true,
+                ori_modifier: 1.0,
+                ability_info: AbilityInfo {
+                    tool: Some(ToolKind::Sword),
+                    hand: None,
+                    input: InputKind::Primary,
+                    input_attr: None,
+                },
+            },
+            exhausted: true,
+            stage,
+            timer: Duration::default(),
+            stage_section: StageSection::Recover,
+        }
+    }
+
+    /// Steps through the recover section in ticks of 50ms, with the attack
+    /// input only being pressed on tick `press_at`
+    fn recover(mut data: Data, press_at: Option<u32>) -> (ComboStep, u32) {
+        let recover_duration =
+            data.static_data.stage_data[data.stage_index()].base_recover_duration;
+        for tick in 0.. {
+            if data.timer >= recover_duration {
+                match data.after_recover(press_at == Some(tick)) {
+                    ComboStep::Await => {},
+                    step => return (step, tick),
+                }
+            }
+            data.timer += Duration::from_millis(50);
+        }
+        unreachable!()
+    }
+
+    #[test]
+    fn input_within_window_advances_stage() {
+        assert_eq!(
+            recover(recovering(1), Some(5)),
+            (ComboStep::NextStage(2), 5)
+        );
+        assert_eq!(
+            recover(recovering(2), Some(3)),
+            (ComboStep::NextStage(3), 3)
+        );
+        // The last stage starts over
+        assert_eq!(
+            recover(recovering(3), Some(7)),
+            (ComboStep::NextStage(1), 7)
+        );
+    }
+
+    #[test]
+    fn missing_window_resets_combo() {
+        // Recovering takes 3 ticks, the window closes after another 5
+        assert_eq!(recover(recovering(1), None), (ComboStep::Reset, 8));
+        assert_eq!(recover(recovering(2), Some(9)), (ComboStep::Reset, 8));
+        // Pressing before the stage recovered doesn't count
+        assert_eq!(recover(recovering(1), Some(1)), (ComboStep::Reset, 8));
+    }
+
+    #[test]
+    fn sword_skills_modify_combo() {
+        let ability = CharacterAbility::load_expect_cloned("common.abilities.sword.triplestrike");
+        let mut skill_set = SkillSet::default();
+        let sword = SkillGroupKind::Weapon(ToolKind::Sword);
+        skill_set.unlock_skill_group(sword);
+        skill_set.add_skill_points(sword, 10);
+
+        let untrained = ability
+            .clone()
+            .adjusted_by_skills(&skill_set, Some(ToolKind::Sword));
+        assert!(matches!(untrained, CharacterAbility::ComboMelee {
+            ref stage_data,
+            scales_from_combo: 0,
+            ..
+        } if stage_data.len() == 2));
+
+        for skill in [
+            SwordSkill::TsCombo,
+            SwordSkill::TsDamage,
+            SwordSkill::TsSpeed,
+            SwordSkill::TsSpeed,
+        ] {
+            skill_set.unlock_skill(Skill::Sword(skill)).unwrap();
+        }
+        let trained = ability.adjusted_by_skills(&skill_set, Some(ToolKind::Sword));
+        match trained {
+            CharacterAbility::ComboMelee {
+                stage_data,
+                scales_from_combo,
+                ..
+            } => {
+                assert_eq!(stage_data.len(), 3);
+                assert_eq!(scales_from_combo, 1);
+                // Two levels of TsSpeed lengthen the window of the first stage
+                assert!((stage_data[0].combo_window - 0.25 * 1.25 * 1.25).abs() < 0.001);
+            },
+            _ => panic!("sword triple strike should be a combo"),
+        }
     }
 }
@@ -0,0 +1,54 @@
+use super::utils::*;
+use crate::{
+    comp::{character_state::OutputEvents, CharacterState, InventoryAction, StateUpdate},
+    states::{
+        behavior::{CharacterBehavior, JoinData},
+        idle, wielding,
+    },
+    util::Dir,
+};
+use serde::{Deserialize, Serialize};
+use vek::*;
+
+/// The line of a fishing rod is in the water. The server puts the character
+/// into this state once the bobber landed and takes them out of it when the
+/// line is reeled in.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Data {
+    /// Where the bobber landed
+    pub bobber: Vec3<f32>,
+}
+
+impl CharacterBehavior for Data {
+    fn behavior(&self, data: &JoinData, _: &mut OutputEvents) -> StateUpdate {
+        let mut update = StateUpdate::from(data);
+
+        let to_bobber = Dir::from_unnormalized((self.bobber - data.pos.0).with_z(0.0));
+        handle_orientation(data, &mut update, 1.0, to_bobber);
+
+        // Walking away or falling lets go of the line
+        if data.physics.on_ground.is_none() || data.inputs.move_dir.magnitude_squared() > 0.0 {
+            update.character = CharacterState::Wielding(wielding::Data { is_sneaking: false });
+        }
+
+        update
+    }
+
+    fn manipulate_loadout(
+        &self,
+        data: &JoinData,
+        output_events: &mut OutputEvents,
+        inv_action: InventoryAction,
+    ) -> StateUpdate {
+        let mut update = StateUpdate::from(data);
+        update.character = CharacterState::Idle(idle::Data { is_sneaking: false });
+        handle_manipulate_loadout(data, output_events, &mut update, inv_action);
+        update
+    }
+
+    fn unwield(&self, data: &JoinData, _: &mut OutputEvents) -> StateUpdate {
+        let mut update = StateUpdate::from(data);
+        update.character = CharacterState::Idle(idle::Data { is_sneaking: false });
+        update
+    }
+}
@@ -14,6 +14,7 @@ pub mod combo_melee;
 pub mod dance;
 pub mod dash_melee;
 pub mod equipping;
+pub mod fishing;
 pub mod glide;
 pub mod glide_wield;
 pub mod idle;
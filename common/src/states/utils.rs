@@ -4,6 +4,7 @@ use crate::{
     comp::{
         arthropod, biped_large, biped_small,
         character_state::OutputEvents,
+        cooldown::COOLDOWN_MANIFEST,
         inventory::slot::{EquipSlot, Slot},
         item::{Hands, ItemKind, Tool, ToolKind},
         quadruped_low, quadruped_medium, quadruped_small,
@@ -884,6 +885,16 @@ pub fn handle_jump(
 
 fn handle_ability(data: &JoinData<'_>, update: &mut StateUpdate, input: InputKind) {
     if let Some(ability_input) = input.into() {
+        // Only abilities listed in the cooldown manifest have a cooldown
+        let cooldown_id = data
+            .active_abilities
+            .and_then(|a| a.ability_id(ability_input, data.inventory, Some(data.skill_set)))
+            .filter(|id| COOLDOWN_MANIFEST.read().cooldown(id).is_some());
+        if let (Some(id), Some(cooldowns)) = (cooldown_id, data.cooldowns) {
+            if !cooldowns.is_ready(id, *data.game_time) {
+                return;
+            }
+        }
         if let Some((ability, from_offhand)) = data
             .active_abilities
             .and_then(|a| {
@@ -901,6 +912,7 @@ fn handle_ability(data: &JoinData<'_>, update: &mut StateUpdate, input: InputKin
                 AbilityInfo::from_input(data, from_offhand, input),
                 data,
             ));
+            update.started_cooldown = cooldown_id.map(str::to_owned);
         }
     }
 }
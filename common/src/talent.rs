@@ -0,0 +1,174 @@
+//! Talents are permanent choices a character makes as it levels up. Unlike
+//! skills they can't be refunded, and picking one can rule out others.
+
+use crate::assets::{self, AssetExt, AssetHandle};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct TalentId(pub String);
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Talent {
+    pub id: TalentId,
+    /// Character level from which the talent can be picked
+    pub required_level: u16,
+    /// Talents that can't be picked along with this one. Incompatibility goes
+    /// both ways, so it only needs to be listed on one of the two talents.
+    #[serde(default)]
+    pub incompatible_with: Vec<TalentId>,
+}
+
+/// Every talent a character can pick, see [`default_talents`]
+#[derive(Clone, Debug, Deserialize)]
+#[serde(transparent)]
+pub struct Talents(pub Vec<Talent>);
+
+impl assets::Asset for Talents {
+    type Loader = assets::RonLoader;
+
+    const EXTENSION: &'static str = "ron";
+}
+
+impl Talents {
+    pub fn get(&self, id: &TalentId) -> Option<&Talent> {
+        self.0.iter().find(|talent| &talent.id == id)
+    }
+
+    /// Whether `a` and `b` can't be picked together
+    pub fn incompatible(&self, a: &TalentId, b: &TalentId) -> bool {
+        let lists = |x: &TalentId, y: &TalentId| {
+            self.get(x)
+                .map_or(false, |talent| talent.incompatible_with.contains(y))
+        };
+        lists(a, b) || lists(b, a)
+    }
+}
+
+pub fn default_talents() -> AssetHandle<Talents> { Talents::load_expect("common.talents") }
+
+#[derive(Debug, PartialEq)]
+pub enum TalentError {
+    UnknownTalent,
+    AlreadyUnlocked,
+    LevelTooLow {
+        required_level: u16,
+    },
+    /// The unlocked talents that rule out the requested one
+    IncompatibleTalent(Vec<TalentId>),
+}
+
+/// Talents picked by a character
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TalentTree {
+    pub unlocked: Vec<TalentId>,
+}
+
+impl TalentTree {
+    pub fn has(&self, talent: &TalentId) -> bool { self.unlocked.contains(talent) }
+
+    /// Unlocks `talent` out of the [`default_talents`] for a character at
+    /// `character_level`
+    pub fn unlock(&mut self, talent: TalentId, character_level: u16) -> Result<(), TalentError> {
+        self.unlock_from(&default_talents().read(), talent, character_level)
+    }
+
+    /// Unlocks `talent` out of the given talents for a character at
+    /// `character_level`
+    pub fn unlock_from(
+        &mut self,
+        talents: &Talents,
+        talent: TalentId,
+        character_level: u16,
+    ) -> Result<(), TalentError> {
+        let required_level = talents
+            .get(&talent)
+            .ok_or(TalentError::UnknownTalent)?
+            .required_level;
+        if self.has(&talent) {
+            return Err(TalentError::AlreadyUnlocked);
+        }
+        if character_level < required_level {
+            return Err(TalentError::LevelTooLow { required_level });
+        }
+        let conflicts = self
+            .unlocked
+            .iter()
+            .filter(|unlocked| talents.incompatible(&talent, unlocked))
+            .cloned()
+            .collect::<Vec<_>>();
+        if !conflicts.is_empty() {
+            return Err(TalentError::IncompatibleTalent(conflicts));
+        }
+        self.unlocked.push(talent);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn id(name: &str) -> TalentId { TalentId(name.to_owned()) }
+
+    fn talents() -> Talents {
+        let talent = |name, required_level, incompatible_with: &[&str]| Talent {
+            id: id(name),
+            required_level,
+            incompatible_with: incompatible_with.iter().map(|name| id(name)).collect(),
+        };
+        Talents(vec![
+            talent("sprinter", 5, &[]),
+            talent("juggernaut", 10, &["acrobat"]),
+            talent("acrobat", 10, &[]),
+            talent("hermit", 15, &["juggernaut", "acrobat"]),
+        ])
+    }
+
+    #[test]
+    fn level_requirement() {
+        let talents = talents();
+        let mut tree = TalentTree::default();
+        assert_eq!(
+            tree.unlock_from(&talents, id("juggernaut"), 9),
+            Err(TalentError::LevelTooLow { required_level: 10 })
+        );
+        assert_eq!(tree.unlock_from(&talents, id("juggernaut"), 10), Ok(()));
+        assert_eq!(
+            tree.unlock_from(&talents, id("juggernaut"), 20),
+            Err(TalentError::AlreadyUnlocked)
+        );
+        assert_eq!(
+            tree.unlock_from(&talents, id("nonexistent"), 20),
+            Err(TalentError::UnknownTalent)
+        );
+    }
+
+    #[test]
+    fn incompatible_talents_are_listed() {
+        let talents = talents();
+        let mut tree = TalentTree::default();
+        tree.unlock_from(&talents, id("sprinter"), 20).unwrap();
+        tree.unlock_from(&talents, id("acrobat"), 20).unwrap();
+        // Listed on the other talent only
+        assert_eq!(
+            tree.unlock_from(&talents, id("juggernaut"), 20),
+            Err(TalentError::IncompatibleTalent(vec![id("acrobat")]))
+        );
+        assert_eq!(
+            tree.unlock_from(&talents, id("hermit"), 20),
+            Err(TalentError::IncompatibleTalent(vec![id("acrobat")]))
+        );
+        assert_eq!(tree.unlocked, [id("sprinter"), id("acrobat")]);
+    }
+
+    #[test]
+    fn default_talents_load() {
+        let talents = default_talents().read();
+        for talent in &talents.0 {
+            for other in &talent.incompatible_with {
+                assert!(talents.get(other).is_some(), "{:?} doesn't exist", other);
+            }
+        }
+    }
+}
@@ -0,0 +1,198 @@
+//! Simplified hydraulic erosion, used to smooth out the harsh edges of freshly
+//! generated terrain
+
+use super::{Block, TerrainChunk, TerrainChunkSize};
+use crate::vol::{ReadVol, RectVolSize, WriteVol};
+use std::cmp::Ordering;
+use vek::*;
+
+/// Water every column receives per iteration
+const RAIN: f32 = 1.0;
+/// Fraction of the flowing water that evaporates per iteration
+const EVAPORATION: f32 = 0.5;
+/// Sediment a unit of water can carry at a velocity of 1
+const CAPACITY: f32 = 0.5;
+/// Fraction of the free capacity that is eroded per iteration
+const EROSION: f32 = 0.3;
+/// Fraction of the excess sediment that is deposited per iteration
+const DEPOSITION: f32 = 0.3;
+
+const SIZE: usize = TerrainChunkSize::RECT_SIZE.x as usize;
+
+pub struct ErosionSimulator;
+
+impl ErosionSimulator {
+    /// Runs `iterations` passes of hydraulic erosion over the surface of
+    /// `chunk`. Every pass rains onto each column, the water flows down the
+    /// steepest slope and erodes material proportional to its velocity, or
+    /// deposits what it carries once it slows down.
+    ///
+    /// Only natural, dry terrain is changed. The outermost columns are left
+    /// untouched so that chunks still line up with their neighbours, and
+    /// chunks containing a settlement are skipped entirely.
+    pub fn simulate(chunk: &mut TerrainChunk, iterations: u32) {
+        if iterations == 0 || chunk.meta().contains_settlement() {
+            return;
+        }
+
+        let surface = Self::surface(chunk);
+        let fixed = surface
+            .iter()
+            .enumerate()
+            .map(|(i, z)| {
+                let (x, y) = (i % SIZE, i / SIZE);
+                x == 0 || y == 0 || x == SIZE - 1 || y == SIZE - 1 || z.is_none()
+            })
+            .collect::<Vec<_>>();
+        let mut height = surface
+            .iter()
+            .map(|z| z.map_or(0.0, |z| (z + 1) as f32))
+            .collect::<Vec<_>>();
+        let mut water = vec![0.0; SIZE * SIZE];
+        let mut sediment = vec![0.0; SIZE * SIZE];
+
+        for _ in 0..iterations {
+            let mut delta = vec![0.0; SIZE * SIZE];
+            let mut next_water = vec![0.0; SIZE * SIZE];
+            let mut next_sediment = vec![0.0; SIZE * SIZE];
+            for i in (0..SIZE * SIZE).filter(|i| !fixed[*i]) {
+                let water = water[i] + RAIN;
+                let mut sediment = sediment[i];
+                match Self::downhill(&height, i) {
+                    Some((j, slope)) => {
+                        let capacity = CAPACITY * slope.sqrt() * water;
+                        let change = if sediment > capacity {
+                            (sediment - capacity) * DEPOSITION
+                        } else {
+                            // Never dig deeper than the column we flow into
+                            -((capacity - sediment) * EROSION).min(slope * 0.5)
+                        };
+                        delta[i] += change;
+                        sediment -= change;
+                        next_water[j] += water * (1.0 - EVAPORATION);
+                        next_sediment[j] += sediment;
+                    },
+                    // Water collects in sinks and drops everything it carries
+                    None => delta[i] += sediment,
+                }
+            }
+            height.iter_mut().zip(delta).for_each(|(h, d)| *h += d);
+            water = next_water;
+            sediment = next_sediment;
+        }
+
+        for (i, z) in surface.into_iter().enumerate() {
+            if let (Some(z), false) = (z, fixed[i]) {
+                let pos = Vec3::new((i % SIZE) as i32, (i / SIZE) as i32, z + 1);
+                Self::set_height(chunk, pos, (height[i] + sediment[i]).round() as i32);
+            }
+        }
+    }
+
+    /// Returns the z of the topmost filled block of every column, if the
+    /// column can be eroded
+    fn surface(chunk: &TerrainChunk) -> Vec<Option<i32>> {
+        (0..SIZE * SIZE)
+            .map(|i| {
+                let pos = Vec3::new((i % SIZE) as i32, (i / SIZE) as i32, 0);
+                let z = (chunk.get_min_z() - 1..chunk.get_max_z())
+                    .rev()
+                    .find(|z| chunk.get(pos.with_z(*z)).map_or(false, |b| b.is_filled()))?;
+                let top = chunk.get(pos.with_z(z)).ok()?;
+                let above = chunk.get(pos.with_z(z + 1)).ok()?;
+                (top.is_terrain() && above.is_air()).then(|| z)
+            })
+            .collect()
+    }
+
+    /// The lower neighbour water at column `i` flows to, along with the
+    /// height difference
+    fn downhill(height: &[f32], i: usize) -> Option<(usize, f32)> {
+        let (x, y) = (i % SIZE, i / SIZE);
+        [
+            (x > 0).then(|| i - 1),
+            (x + 1 < SIZE).then(|| i + 1),
+            (y > 0).then(|| i - SIZE),
+            (y + 1 < SIZE).then(|| i + SIZE),
+        ]
+        .into_iter()
+        .flatten()
+        .map(|j| (j, height[i] - height[j]))
+        .filter(|(_, slope)| *slope > 0.0)
+        .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(Ordering::Equal))
+    }
+
+    /// Moves the top of the column at `top` to `new_top`, sprites standing on
+    /// the surface are moved along with it
+    fn set_height(chunk: &mut TerrainChunk, top: Vec3<i32>, new_top: i32) {
+        let new_top = new_top.max(chunk.get_min_z());
+        if new_top == top.z {
+            return;
+        }
+        let cover = chunk.get(top).map_or_else(|_| Block::empty(), |b| *b);
+        let fill = if new_top < top.z {
+            Block::empty()
+        } else {
+            chunk
+                .get(top - Vec3::unit_z())
+                .map_or_else(|_| Block::empty(), |b| *b)
+        };
+        for z in new_top.min(top.z)..new_top.max(top.z) + 1 {
+            let _ = chunk.set(top.with_z(z), fill);
+        }
+        let _ = chunk.set(top.with_z(new_top), cover);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::terrain::{BlockKind, TerrainChunkMeta};
+
+    /// Flat rock chunk whose topmost blocks are at z = 9
+    fn flat_chunk() -> TerrainChunk {
+        TerrainChunk::new(
+            10,
+            Block::new(BlockKind::Rock, Rgb::broadcast(100)),
+            Block::empty(),
+            TerrainChunkMeta::void(),
+        )
+    }
+
+    #[test]
+    fn flat_terrain_is_unchanged() {
+        let mut chunk = flat_chunk();
+        ErosionSimulator::simulate(&mut chunk, 50);
+        assert!(
+            ErosionSimulator::surface(&chunk)
+                .into_iter()
+                .all(|z| z == Some(9))
+        );
+    }
+
+    #[test]
+    fn peaks_are_worn_down() {
+        let mut chunk = flat_chunk();
+        let grass = Block::new(BlockKind::Grass, Rgb::new(50, 150, 50));
+        for x in 12..20 {
+            for y in 12..20 {
+                for z in 10..30 {
+                    chunk.set(Vec3::new(x, y, z), grass).unwrap();
+                }
+            }
+        }
+        ErosionSimulator::simulate(&mut chunk, 50);
+
+        let surface = ErosionSimulator::surface(&chunk);
+        let peak = surface.iter().flatten().max().copied().unwrap();
+        assert!(peak < 29, "peak wasn't eroded");
+        assert!(peak > 9, "peak was flattened entirely");
+        // Material is carried into the valley
+        assert!(surface.iter().filter(|z| **z > Some(9)).count() > 64);
+        // The borders still line up with the neighbouring chunks
+        for i in 0..SIZE {
+            assert_eq!(surface[i], Some(9));
+            assert_eq!(surface[i * SIZE], Some(9));
+        }
+    }
+}
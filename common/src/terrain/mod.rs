@@ -1,6 +1,7 @@
 pub mod biome;
 pub mod block;
 pub mod chonk;
+pub mod erosion;
 pub mod map;
 pub mod site;
 pub mod sprite;
@@ -0,0 +1,181 @@
+//! Prediction of the flight path of projectiles
+use crate::{
+    comp::{
+        fluid_dynamics::{self, Fluid},
+        Body, Density, Mass, Vel,
+    },
+    resources::DeltaTime,
+};
+use vek::*;
+
+/// Length of a server tick, the step flight paths are predicted with
+pub const TICK_DT: f32 = 1.0 / 30.0;
+
+/// Flight of a projectile, stepped the same way the physics system moves it
+/// while it doesn't collide with anything
+#[derive(Copy, Clone, Debug)]
+pub struct Trajectory {
+    pub pos: Vec3<f32>,
+    pub vel: Vel,
+    body: Body,
+    mass: Mass,
+    density: Density,
+    fluid: Option<Fluid>,
+}
+
+impl Trajectory {
+    /// Projectile of `body` that was just launched, the physics system only
+    /// learns which fluid it is in after its first tick
+    pub fn new(pos: Vec3<f32>, vel: Vec3<f32>, body: Body) -> Self {
+        Self {
+            pos,
+            vel: Vel(vel),
+            body,
+            mass: body.mass(),
+            density: body.density(),
+            fluid: None,
+        }
+    }
+
+    /// Advances the projectile by a tick of length `dt`
+    pub fn step(&mut self, dt: f32) {
+        // Forces are integrated over at most 0.1s, the movement isn't clamped
+        self.vel = fluid_dynamics::integrate_vel(
+            &DeltaTime(dt.min(0.1)),
+            self.vel,
+            (&self.body, None),
+            &self.density,
+            &self.mass,
+            self.fluid.as_ref(),
+        );
+        self.pos += self.vel.0 * dt;
+        // Liquids are ignored, projectiles are hardly ever shot through them
+        if self.fluid.is_none() {
+            self.fluid = Some(Fluid::Air {
+                elevation: self.pos.z,
+                vel: Vel::default(),
+            });
+        }
+    }
+
+    /// Positions after each of the following ticks of length `dt`
+    pub fn path(mut self, dt: f32) -> impl Iterator<Item = Vec3<f32>> {
+        std::iter::repeat_with(move || {
+            self.step(dt);
+            self.pos
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        comp::{body::object, PhysicsState},
+        consts::GRAVITY,
+    };
+
+    fn arrow(vel: Vec3<f32>) -> Trajectory {
+        Trajectory::new(Vec3::zero(), vel, Body::Object(object::Body::Arrow))
+    }
+
+    #[test]
+    fn first_tick_only_has_gravity() {
+        let mut trajectory = arrow(Vec3::new(40.0, 0.0, 10.0));
+        trajectory.step(TICK_DT);
+        assert_eq!(
+            trajectory.vel.0,
+            Vec3::new(40.0, 0.0, 10.0 - GRAVITY * TICK_DT)
+        );
+        assert_eq!(trajectory.pos, trajectory.vel.0 * TICK_DT);
+
+        // Long ticks only integrate the forces over 0.1s
+        let mut trajectory = arrow(Vec3::new(40.0, 0.0, 10.0));
+        trajectory.step(0.5);
+        assert_eq!(trajectory.vel.0.z, 10.0 - GRAVITY * 0.1);
+        assert_eq!(trajectory.pos, trajectory.vel.0 * 0.5);
+    }
+
+    #[test]
+    fn path_follows_the_ticks() {
+        let mut trajectory = arrow(Vec3::new(40.0, 0.0, 10.0));
+        let path = trajectory.path(TICK_DT).take(60).collect::<Vec<_>>();
+        for pos in &path {
+            trajectory.step(TICK_DT);
+            assert_eq!(trajectory.pos, *pos);
+        }
+        assert!(path.windows(2).all(|w| w[1].x > w[0].x));
+        // The arrow rises for less than half a second and falls below its
+        // start within two
+        let top = path.iter().map(|pos| pos.z).fold(f32::MIN, f32::max);
+        assert!(top > 1.0 && top < 10.0 * 10.0 / (2.0 * GRAVITY) + 0.5);
+        assert!(path[59].z < 0.0);
+    }
+
+    /// A tick of the physics system for a projectile that doesn't hit anything
+    fn physics_tick(
+        pos: &mut Vec3<f32>,
+        vel: &mut Vel,
+        state: &mut PhysicsState,
+        body: &Body,
+        dt: f32,
+    ) {
+        *vel = fluid_dynamics::integrate_vel(
+            &DeltaTime(dt.min(0.1)),
+            *vel,
+            (body, None),
+            &body.density(),
+            &body.mass(),
+            state.in_fluid.as_ref(),
+        );
+        // Point colliders move along the ray to their target
+        let pos_delta = vel.0 * dt;
+        *pos += pos_delta.try_normalized().unwrap_or_else(Vec3::zero) * pos_delta.magnitude();
+        state.in_fluid = match state.in_fluid {
+            Some(Fluid::Liquid { .. }) | None => Some(Fluid::Air {
+                elevation: pos.z,
+                vel: Vel::default(),
+            }),
+            fluid => fluid,
+        };
+    }
+
+    #[test]
+    fn path_matches_the_physics_system() {
+        let body = Body::Object(object::Body::Arrow);
+        for dt in [TICK_DT, 0.25] {
+            for speed in [0.0, 5.0, 20.0, 60.0, 120.0] {
+                for pitch in [-80.0f32, -30.0, 0.0, 10.0, 45.0, 89.0] {
+                    for yaw in [0.0f32, 135.0] {
+                        let (pitch, yaw) = (pitch.to_radians(), yaw.to_radians());
+                        let launch = Vec3::new(
+                            pitch.cos() * yaw.cos(),
+                            pitch.cos() * yaw.sin(),
+                            pitch.sin(),
+                        ) * speed;
+                        let start = Vec3::new(10.0, -20.0, 300.0);
+                        let (mut pos, mut vel) = (start, Vel(launch));
+                        let mut state = PhysicsState::default();
+                        let path = Trajectory::new(start, launch, body).path(dt);
+                        for (tick, predicted) in path.take(120).enumerate() {
+                            physics_tick(&mut pos, &mut vel, &mut state, &body, dt);
+                            let tolerance = 1e-4 * (pos - start).magnitude().max(1.0);
+                            assert!(
+                                predicted.distance(pos) < tolerance,
+                                "dt {}, speed {}, pitch {}, yaw {}, tick {}: predicted {:?}, \
+                                 moved to {:?}",
+                                dt,
+                                speed,
+                                pitch.to_degrees(),
+                                yaw.to_degrees(),
+                                tick,
+                                predicted,
+                                pos
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -13,8 +13,8 @@ use common::{
     outcome::Outcome,
     region::RegionMap,
     resources::{
-        DeltaTime, EntitiesDiedLastTick, GameMode, PlayerEntity, PlayerPhysicsSettings, Time,
-        TimeOfDay,
+        DeltaTime, EntitiesDiedLastTick, GameMode, GameTime, PlayerEntity, PlayerPhysicsSettings,
+        Time, TimeOfDay,
     },
     slowjob::SlowJobPool,
     terrain::{Block, TerrainChunk, TerrainGrid},
@@ -137,16 +137,21 @@ impl State {
         ecs.register::<comp::Auras>();
         ecs.register::<comp::Energy>();
         ecs.register::<comp::Combo>();
+        ecs.register::<comp::Cooldowns>();
         ecs.register::<comp::Health>();
         ecs.register::<comp::Poise>();
         ecs.register::<comp::CanBuild>();
         ecs.register::<comp::LightEmitter>();
+        ecs.register::<comp::AudioEmitter>();
+        ecs.register::<comp::Gravestone>();
         ecs.register::<comp::Item>();
         ecs.register::<comp::Scale>();
         ecs.register::<Is<Mount>>();
         ecs.register::<Is<Rider>>();
         ecs.register::<comp::Mass>();
         ecs.register::<comp::Density>();
+        ecs.register::<comp::GlideState>();
+        ecs.register::<comp::TerminalVelocity>();
         ecs.register::<comp::Collider>();
         ecs.register::<comp::Sticky>();
         ecs.register::<comp::Immovable>();
@@ -196,6 +201,8 @@ impl State {
         ecs.register::<comp::Projectile>();
         ecs.register::<comp::Melee>();
         ecs.register::<comp::ItemDrop>();
+        ecs.register::<comp::FarmPlot>();
+        ecs.register::<comp::CookingPot>();
         ecs.register::<comp::ChatMode>();
         ecs.register::<comp::Faction>();
         ecs.register::<comp::invite::Invite>();
@@ -205,6 +212,7 @@ impl State {
         // Register synced resources used by the ECS.
         ecs.insert(TimeOfDay(0.0));
         ecs.insert(Calendar::default());
+        ecs.insert(GameTime(0.0));
 
         // Register unsynced resources used by the ECS.
         ecs.insert(Time(0.0));
@@ -237,6 +245,7 @@ impl State {
         ecs.insert(PhysicsMetrics::default());
         ecs.insert(Trades::default());
         ecs.insert(PlayerPhysicsSettings::default());
+        ecs.insert(comp::Thermals::default());
 
         // Load plugins from asset directory
         #[cfg(feature = "plugins")]
@@ -494,6 +503,7 @@ impl State {
         // Change the time accordingly.
         self.ecs.write_resource::<TimeOfDay>().0 += dt.as_secs_f64() * DAY_CYCLE_FACTOR;
         self.ecs.write_resource::<Time>().0 += dt.as_secs_f64();
+        self.ecs.write_resource::<GameTime>().0 += dt.as_secs_f64();
 
         // Update delta time.
         // Beyond a delta time of MAX_DELTA_TIME, start lagging to avoid skipping
@@ -541,7 +551,7 @@ impl State {
                 },
                 LocalEvent::ApplyImpulse { entity, impulse } => {
                     if let Some(vel) = velocities.get_mut(entity) {
-                        vel.0 = impulse;
+                        vel.0 += impulse;
                     }
                 },
                 LocalEvent::Boost {
@@ -3,7 +3,7 @@ use common::{
     comp::{
         agent::{Sound, SoundKind},
         Alignment, Beam, BeamSegment, Body, CharacterState, Combo, Energy, Group, Health,
-        Inventory, Ori, Player, Pos, Scale, Stats,
+        Inventory, Ori, Player, Pos, Scale, SkillSet, Stats,
     },
     event::{EventBus, ServerEvent},
     outcome::Outcome,
@@ -41,6 +41,7 @@ pub struct ReadData<'a> {
     bodies: ReadStorage<'a, Body>,
     healths: ReadStorage<'a, Health>,
     inventories: ReadStorage<'a, Inventory>,
+    skill_sets: ReadStorage<'a, SkillSet>,
     groups: ReadStorage<'a, Group>,
     energies: ReadStorage<'a, Energy>,
     stats: ReadStorage<'a, Stats>,
@@ -213,6 +214,7 @@ impl<'a> System<'a> for Sys {
                                         energy: read_data.energies.get(entity),
                                         combo: read_data.combos.get(entity),
                                         inventory: read_data.inventories.get(entity),
+                                        skill_set: read_data.skill_sets.get(entity),
                                     }
                                 });
 
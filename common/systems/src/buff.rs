@@ -1,14 +1,14 @@
 use common::{
-    combat::DamageContributor,
+    combat::{compute_resistances, DamageContributor},
     comp::{
         body::{object, Body},
         buff::{
-            Buff, BuffCategory, BuffChange, BuffData, BuffEffect, BuffId, BuffKind, BuffSource,
-            Buffs,
+            over_time_step_due, Buff, BuffCategory, BuffChange, BuffData, BuffEffect, BuffId,
+            BuffKind, BuffSource, Buffs,
         },
         fluid_dynamics::{Fluid, LiquidKind},
         Energy, Group, Health, HealthChange, Inventory, LightEmitter, ModifierKind, PhysicsState,
-        Stats,
+        SkillSet, Stats,
     },
     event::{EventBus, ServerEvent},
     resources::{DeltaTime, Time},
@@ -34,6 +34,7 @@ pub struct ReadData<'a> {
     energies: ReadStorage<'a, Energy>,
     physics_states: ReadStorage<'a, PhysicsState>,
     groups: ReadStorage<'a, Group>,
+    skill_sets: ReadStorage<'a, SkillSet>,
     uid_allocator: Read<'a, UidAllocator>,
     time: Read<'a, Time>,
 }
@@ -180,9 +181,20 @@ impl<'a> System<'a> for Sys {
                 }
             }
 
+            // Resistances granted by buffs are recomputed below, so the over time damage
+            // of this tick uses the ones of the last tick
+            let resistances = compute_resistances(read_data.inventories.get(entity), Some(&stat));
+
             // Call to reset stats to base values
             stat.reset_temp_modifiers();
 
+            // Synergies of unlocked skills are passive, so they are applied like buffs
+            if let Some(skill_set) = read_data.skill_sets.get(entity) {
+                for synergy in skill_set.get_active_synergies() {
+                    synergy.apply(&mut *stat);
+                }
+            }
+
             // Iterator over the lists of buffs by kind
             let buff_comp = &mut *buff_comp;
             for buff_ids in buff_comp.kinds.values() {
@@ -206,18 +218,22 @@ impl<'a> System<'a> for Sys {
                                 *accumulated += *rate * dt;
                                 // Apply health change only once per second, per health, or
                                 // when a buff is removed
-                                if accumulated.abs() > rate.abs().min(1.0)
-                                    || buff.time.map_or(false, |dur| dur == Duration::default())
-                                {
-                                    let (cause, by) = if *accumulated < 0.0 {
-                                        (Some(DamageSource::Buff(buff.kind)), buff_owner)
+                                if over_time_step_due(*accumulated, *rate, 1.0, buff.time) {
+                                    let (cause, by, damage_kind) = if *accumulated < 0.0 {
+                                        (
+                                            Some(DamageSource::Buff(buff.kind)),
+                                            buff_owner,
+                                            buff.kind.damage_kind(),
+                                        )
                                     } else {
-                                        (None, None)
+                                        (None, None, None)
                                     };
+                                    let resistance =
+                                        damage_kind.map_or(0.0, |kind| resistances.reduction(kind));
                                     let amount = match *kind {
                                         ModifierKind::Additive => *accumulated,
                                         ModifierKind::Fractional => health.maximum() * *accumulated,
-                                    };
+                                    } * (1.0 - resistance);
                                     let damage_contributor = by.and_then(|uid| {
                                         read_data.uid_allocator.retrieve_entity_internal(uid.0).map(
                                             |entity| {
@@ -234,7 +250,9 @@ impl<'a> System<'a> for Sys {
                                             amount,
                                             by: damage_contributor,
                                             cause,
+                                            kind: damage_kind,
                                             time: *read_data.time,
+                                            crit: false,
                                         },
                                     });
                                     *accumulated = 0.0;
@@ -248,15 +266,19 @@ impl<'a> System<'a> for Sys {
                                 *accumulated += *rate * dt;
                                 // Apply energy change only once per second, per energy, or
                                 // when a buff is removed
-                                if accumulated.abs() > rate.abs().min(10.0)
-                                    || buff.time.map_or(false, |dur| dur == Duration::default())
-                                {
+                                if over_time_step_due(*accumulated, *rate, 10.0, buff.time) {
+                                    let resistance = match buff.kind.damage_kind() {
+                                        Some(kind) if *accumulated < 0.0 => {
+                                            resistances.reduction(kind)
+                                        },
+                                        _ => 0.0,
+                                    };
                                     let amount = match *kind {
                                         ModifierKind::Additive => *accumulated,
                                         ModifierKind::Fractional => {
                                             energy.maximum() as f32 * *accumulated
                                         },
-                                    };
+                                    } * (1.0 - resistance);
                                     server_emitter.emit(ServerEvent::EnergyChange {
                                         entity,
                                         change: amount,
@@ -283,6 +305,9 @@ impl<'a> System<'a> for Sys {
                             BuffEffect::DamageReduction(dr) => {
                                 stat.damage_reduction = stat.damage_reduction.max(*dr).min(1.0);
                             },
+                            BuffEffect::Resistance(kind, value) => {
+                                stat.resistances.add(*kind, *value);
+                            },
                             BuffEffect::MaxHealthChangeOverTime {
                                 rate,
                                 kind,
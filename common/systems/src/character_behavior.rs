@@ -6,15 +6,15 @@ use specs::{
 use common::{
     comp::{
         self, character_state::OutputEvents, inventory::item::MaterialStatManifest,
-        ActiveAbilities, Beam, Body, CharacterState, Combo, Controller, Density, Energy, Health,
-        Inventory, InventoryManip, Mass, Melee, Ori, PhysicsState, Poise, Pos, SkillSet,
-        StateUpdate, Stats, Vel,
+        ActiveAbilities, Beam, Body, CharacterState, Combo, Controller, Cooldowns, Density, Energy,
+        GlideState, Health, Inventory, InventoryManip, Mass, Melee, Ori, PhysicsState, Poise, Pos,
+        SkillSet, StateUpdate, Stats, Vel,
     },
     event::{EventBus, LocalEvent, ServerEvent},
     link::Is,
     mounting::Rider,
     outcome::Outcome,
-    resources::{DeltaTime, Time},
+    resources::{DeltaTime, GameTime, Time},
     states::{
         behavior::{JoinData, JoinStruct},
         idle,
@@ -31,6 +31,7 @@ pub struct ReadData<'a> {
     local_bus: Read<'a, EventBus<LocalEvent>>,
     dt: Read<'a, DeltaTime>,
     time: Read<'a, Time>,
+    game_time: Read<'a, GameTime>,
     lazy_update: Read<'a, LazyUpdate>,
     healths: ReadStorage<'a, Health>,
     bodies: ReadStorage<'a, Body>,
@@ -43,6 +44,7 @@ pub struct ReadData<'a> {
     stats: ReadStorage<'a, Stats>,
     skill_sets: ReadStorage<'a, SkillSet>,
     active_abilities: ReadStorage<'a, ActiveAbilities>,
+    cooldowns: ReadStorage<'a, Cooldowns>,
     msm: Read<'a, MaterialStatManifest>,
     combos: ReadStorage<'a, Combo>,
     alignments: ReadStorage<'a, comp::Alignment>,
@@ -67,6 +69,7 @@ impl<'a> System<'a> for Sys {
         WriteStorage<'a, Energy>,
         WriteStorage<'a, Controller>,
         WriteStorage<'a, Poise>,
+        WriteStorage<'a, GlideState>,
         Write<'a, Vec<Outcome>>,
     );
 
@@ -86,6 +89,7 @@ impl<'a> System<'a> for Sys {
             mut energies,
             mut controllers,
             mut poises,
+            mut glide_states,
             mut outcomes,
         ): Self::SystemData,
     ) {
@@ -191,9 +195,11 @@ impl<'a> System<'a> for Sys {
                 stat,
                 skill_set,
                 active_abilities,
+                cooldowns: read_data.cooldowns.get(entity),
                 combo,
                 alignment: read_data.alignments.get(entity),
                 terrain: &read_data.terrain,
+                game_time: &read_data.game_time,
             };
 
             for action in actions {
@@ -226,6 +232,21 @@ impl<'a> System<'a> for Sys {
             Self::publish_state_update(&mut join_struct, state_update, &mut output_events);
         }
 
+        // Physics picks up the flight characteristics of gliding entities
+        for (entity, char_state) in (&read_data.entities, &character_states).join() {
+            match char_state {
+                CharacterState::Glide(data) => {
+                    if !glide_states.contains(entity) {
+                        let glide_state = GlideState::new(data.aspect_ratio);
+                        let _ = glide_states.insert(entity, glide_state);
+                    }
+                },
+                _ => {
+                    glide_states.remove(entity);
+                },
+            }
+        }
+
         local_emitter.append_vec(local_events);
         server_emitter.append_vec(server_events);
     }
@@ -271,5 +292,11 @@ impl Sys {
                 InventoryManip::SwapEquippedWeapons,
             ));
         }
+        if let Some(ability_id) = state_update.started_cooldown {
+            output_events.emit_server(ServerEvent::StartCooldown {
+                entity: join.entity,
+                ability_id,
+            });
+        }
     }
 }
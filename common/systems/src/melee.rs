@@ -3,7 +3,7 @@ use common::{
     comp::{
         agent::{Sound, SoundKind},
         Alignment, Body, CharacterState, Combo, Energy, Group, Health, Inventory, Melee, Ori,
-        Player, Pos, Scale, Stats,
+        Player, Pos, Scale, SkillSet, Stats,
     },
     event::{EventBus, ServerEvent},
     outcome::Outcome,
@@ -33,6 +33,7 @@ pub struct ReadData<'a> {
     healths: ReadStorage<'a, Health>,
     energies: ReadStorage<'a, Energy>,
     inventories: ReadStorage<'a, Inventory>,
+    skill_sets: ReadStorage<'a, SkillSet>,
     groups: ReadStorage<'a, Group>,
     char_states: ReadStorage<'a, CharacterState>,
     server_bus: Read<'a, EventBus<ServerEvent>>,
@@ -158,6 +159,7 @@ impl<'a> System<'a> for Sys {
                         energy: read_data.energies.get(attacker),
                         combo: read_data.combos.get(attacker),
                         inventory: read_data.inventories.get(attacker),
+                        skill_set: read_data.skill_sets.get(attacker),
                     });
 
                     let target_info = TargetInfo {
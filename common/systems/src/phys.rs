@@ -1,15 +1,17 @@
 use common::{
     comp::{
         body::ship::figuredata::{VoxelCollider, VOXEL_COLLIDER_MANIFEST},
-        fluid_dynamics::{Fluid, LiquidKind, Wings},
-        Body, CharacterState, Collider, Density, Immovable, Mass, Ori, PhysicsState, Pos,
-        PosVelOriDefer, PreviousPhysCache, Projectile, Scale, Stats, Sticky, Vel,
+        fluid_dynamics::{self, Fluid, LiquidKind, Wings},
+        Body, CharacterState, Collider, Density, GlideState, Immovable, Mass, Ori, PhysicsState,
+        Pos, PosVelOriDefer, PreviousPhysCache, Projectile, Scale, Stats, Sticky, TerminalVelocity,
+        Thermals, Vel,
     },
-    consts::{AIR_DENSITY, FRIC_GROUND, GRAVITY},
+    consts::FRIC_GROUND,
     event::{EventBus, ServerEvent},
     link::Is,
     mounting::Rider,
     outcome::Outcome,
+    ray::{RayCast, RayHit, RayTarget},
     resources::DeltaTime,
     states,
     terrain::{Block, TerrainGrid},
@@ -28,66 +30,6 @@ use specs::{
 use std::ops::Range;
 use vek::*;
 
-/// The density of the fluid as a function of submersion ratio in given fluid
-/// where it is assumed that any unsubmersed part is is air.
-// TODO: Better suited partial submersion curve?
-fn fluid_density(height: f32, fluid: &Fluid) -> Density {
-    // If depth is less than our height (partial submersion), remove
-    // fluid density based on the ratio of displacement to full volume.
-    let immersion = fluid
-        .depth()
-        .map_or(1.0, |depth| (depth / height).clamp(0.0, 1.0));
-
-    Density(fluid.density().0 * immersion + AIR_DENSITY * (1.0 - immersion))
-}
-
-fn integrate_forces(
-    dt: &DeltaTime,
-    mut vel: Vel,
-    (body, wings): (&Body, Option<&Wings>),
-    density: &Density,
-    mass: &Mass,
-    fluid: &Fluid,
-    gravity: f32,
-) -> Vel {
-    let dim = body.dimensions();
-    let height = dim.z;
-    let rel_flow = fluid.relative_flow(&vel);
-    let fluid_density = fluid_density(height, fluid);
-    debug_assert!(mass.0 > 0.0);
-    debug_assert!(density.0 > 0.0);
-
-    // Aerodynamic/hydrodynamic forces
-    if !rel_flow.0.is_approx_zero() {
-        debug_assert!(!rel_flow.0.map(|a| a.is_nan()).reduce_or());
-        let impulse = dt.0 * body.aerodynamic_forces(&rel_flow, fluid_density.0, wings);
-        debug_assert!(!impulse.map(|a| a.is_nan()).reduce_or());
-        if !impulse.is_approx_zero() {
-            let new_v = vel.0 + impulse / mass.0;
-            // If the new velocity is in the opposite direction, it's because the forces
-            // involved are too high for the current tick to handle. We deal with this by
-            // removing the component of our velocity vector along the direction of force.
-            // This way we can only ever lose velocity and will never experience a reverse
-            // in direction from events such as falling into water at high velocities.
-            if new_v.dot(vel.0) < 0.0 {
-                // Multiply by a factor to prevent full stop,
-                // as this can cause things to get stuck in high-density medium
-                vel.0 -= vel.0.projected(&impulse) * 0.9;
-            } else {
-                vel.0 = new_v;
-            }
-        };
-        debug_assert!(!vel.0.map(|a| a.is_nan()).reduce_or());
-    };
-
-    // Hydrostatic/aerostatic forces
-    // modify gravity to account for the effective density as a result of buoyancy
-    let down_force = dt.0 * gravity * (density.0 - fluid_density.0) / density.0;
-    vel.0.z -= down_force;
-
-    vel
-}
-
 fn calc_z_limit(char_state_maybe: Option<&CharacterState>, collider: &Collider) -> (f32, f32) {
     let modifier = if char_state_maybe.map_or(false, |c_s| c_s.is_dodge() || c_s.is_glide()) {
         0.5
@@ -119,7 +61,10 @@ pub struct PhysicsRead<'a> {
     bodies: ReadStorage<'a, Body>,
     character_states: ReadStorage<'a, CharacterState>,
     densities: ReadStorage<'a, Density>,
+    terminal_velocities: ReadStorage<'a, TerminalVelocity>,
     stats: ReadStorage<'a, Stats>,
+    glide_states: ReadStorage<'a, GlideState>,
+    thermals: Read<'a, Thermals>,
 }
 
 #[derive(SystemData)]
@@ -609,6 +554,8 @@ impl<'a> PhysicsData<'a> {
             &write.physics_states,
             &read.masses,
             &read.densities,
+            read.terminal_velocities.maybe(),
+            read.glide_states.maybe(),
             !&read.is_ridings,
         )
             .par_join()
@@ -627,6 +574,8 @@ impl<'a> PhysicsData<'a> {
                     physics_state,
                     mass,
                     density,
+                    terminal_velocity,
+                    glide_state,
                     _,
                 )| {
                     let in_loaded_chunk = read
@@ -645,36 +594,51 @@ impl<'a> PhysicsData<'a> {
                         // to lag (as observed in the 0.9 release party).
                         let dt = DeltaTime(read.dt.0.min(0.1));
 
-                        match physics_state.in_fluid {
-                            None => {
-                                vel.0.z -= dt.0 * GRAVITY;
-                            },
-                            Some(fluid) => {
-                                let wings = match character_state {
-                                    Some(&CharacterState::Glide(states::glide::Data {
-                                        aspect_ratio,
-                                        planform_area,
-                                        ori,
-                                        ..
-                                    })) => Some(Wings {
-                                        aspect_ratio,
-                                        planform_area,
-                                        ori,
-                                    }),
+                        let wings = match character_state {
+                            Some(&CharacterState::Glide(states::glide::Data {
+                                aspect_ratio,
+                                planform_area,
+                                ori,
+                                ..
+                            })) => Some(Wings {
+                                // The air thins out with altitude
+                                aspect_ratio: glide_state.map_or(aspect_ratio, |glide_state| {
+                                    glide_state.aspect_ratio_at(pos.0.z)
+                                }),
+                                planform_area,
+                                ori,
+                            }),
+
+                            _ => None,
+                        };
+                        // Gliders ride the air rising in thermals, the updraft is part of
+                        // the flow their wings generate lift from
+                        let thermal_air = match (&wings, &physics_state.in_fluid) {
+                            (
+                                Some(_),
+                                Some(Fluid::Air {
+                                    vel: air_vel,
+                                    elevation,
+                                }),
+                            ) => Some(Fluid::Air {
+                                vel: Vel(air_vel.0
+                                    + Vec3::unit_z() * read.thermals.updraft_at(pos.0.xy())),
+                                elevation: *elevation,
+                            }),
+                            _ => None,
+                        };
+                        *vel = fluid_dynamics::integrate_vel(
+                            &dt,
+                            *vel,
+                            (body, wings.as_ref()),
+                            density,
+                            mass,
+                            thermal_air.as_ref().or(physics_state.in_fluid.as_ref()),
+                        );
 
-                                    _ => None,
-                                };
-                                vel.0 = integrate_forces(
-                                    &dt,
-                                    *vel,
-                                    (body, wings.as_ref()),
-                                    density,
-                                    mass,
-                                    &fluid,
-                                    GRAVITY,
-                                )
-                                .0
-                            },
+                        if let Some(terminal_velocity) = terminal_velocity {
+                            let gliding = character_state.map_or(false, |cs| cs.is_glide());
+                            terminal_velocity.apply(vel, gliding);
                         }
                     }
                 },
@@ -902,14 +866,17 @@ impl<'a> PhysicsData<'a> {
                             {
                                 (0.0, Some(block))
                             } else {
-                                let (dist, block) = read
-                                    .terrain
-                                    .ray(pos.0, pos.0 + pos_delta)
-                                    .until(|block: &Block| block.is_solid())
-                                    .ignore_error()
-                                    .cast();
-                                // Can't fail since we do ignore_error above
-                                (dist, block.unwrap())
+                                match RayCast::between(&*read.terrain, pos.0, pos.0 + pos_delta)
+                                    .until(Block::is_solid)
+                                    .cast()
+                                {
+                                    Some(RayHit {
+                                        target: RayTarget::Block(block_pos),
+                                        dist,
+                                        ..
+                                    }) => (dist, read.terrain.get(block_pos).ok()),
+                                    _ => (pos_delta.magnitude(), None),
+                                }
                             };
 
                             pos.0 += pos_delta.try_normalized().unwrap_or_else(Vec3::zero) * dist;
@@ -3,7 +3,7 @@ use common::{
     comp::{
         agent::{Sound, SoundKind},
         projectile, Alignment, Body, CharacterState, Combo, Energy, Group, Health, Inventory, Ori,
-        PhysicsState, Player, Pos, Projectile, Stats, Vel,
+        PhysicsState, Player, Pos, Projectile, SkillSet, Stats, Vel,
     },
     event::{Emitter, EventBus, ServerEvent},
     outcome::Outcome,
@@ -35,6 +35,7 @@ pub struct ReadData<'a> {
     physics_states: ReadStorage<'a, PhysicsState>,
     velocities: ReadStorage<'a, Vel>,
     inventories: ReadStorage<'a, Inventory>,
+    skill_sets: ReadStorage<'a, SkillSet>,
     groups: ReadStorage<'a, Group>,
     energies: ReadStorage<'a, Energy>,
     stats: ReadStorage<'a, Stats>,
@@ -268,6 +269,7 @@ fn dispatch_hit(
                         energy: read_data.energies.get(entity),
                         combo: read_data.combos.get(entity),
                         inventory: read_data.inventories.get(entity),
+                        skill_set: read_data.skill_sets.get(entity),
                     });
 
             let target_info = TargetInfo {
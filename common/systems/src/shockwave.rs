@@ -3,7 +3,7 @@ use common::{
     comp::{
         agent::{Sound, SoundKind},
         Alignment, Body, CharacterState, Combo, Energy, Group, Health, Inventory, Ori,
-        PhysicsState, Player, Pos, Scale, Shockwave, ShockwaveHitEntities, Stats,
+        PhysicsState, Player, Pos, Scale, Shockwave, ShockwaveHitEntities, SkillSet, Stats,
     },
     event::{EventBus, ServerEvent},
     outcome::Outcome,
@@ -36,6 +36,7 @@ pub struct ReadData<'a> {
     bodies: ReadStorage<'a, Body>,
     healths: ReadStorage<'a, Health>,
     inventories: ReadStorage<'a, Inventory>,
+    skill_sets: ReadStorage<'a, SkillSet>,
     groups: ReadStorage<'a, Group>,
     physics_states: ReadStorage<'a, PhysicsState>,
     energies: ReadStorage<'a, Energy>,
@@ -196,6 +197,7 @@ impl<'a> System<'a> for Sys {
                                 energy: read_data.energies.get(entity),
                                 combo: read_data.combos.get(entity),
                                 inventory: read_data.inventories.get(entity),
+                                skill_set: read_data.skill_sets.get(entity),
                             });
 
                     let target_info = TargetInfo {
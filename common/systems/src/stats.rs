@@ -145,6 +145,7 @@ impl<'a> System<'a> for Sys {
                 CharacterState::Idle { .. }
                 | CharacterState::Talk { .. }
                 | CharacterState::Sit { .. }
+                | CharacterState::Fishing { .. }
                 | CharacterState::Dance { .. }
                 | CharacterState::Glide { .. }
                 | CharacterState::GlideWield { .. }
@@ -5,10 +5,11 @@ use criterion::{criterion_group, criterion_main, Criterion, Throughput};
 use std::{sync::Arc, time::Duration};
 use tokio::runtime::Runtime;
 use veloren_network_protocol::{
-    InitProtocol, MpscMsg, MpscRecvProtocol, MpscSendProtocol, Pid, Promises, ProtocolError,
-    ProtocolEvent, ProtocolMetricCache, ProtocolMetrics, QuicDataFormat, QuicRecvProtocol,
-    QuicSendProtocol, RecvProtocol, SendProtocol, Sid, TcpRecvProtocol, TcpSendProtocol,
-    UnreliableDrain, UnreliableSink, _internal::OTFrame,
+    InitProtocol, KeepAlive, MpscMsg, MpscRecvProtocol, MpscSendProtocol, Pid, Promises,
+    ProtocolError, ProtocolEvent, ProtocolMetricCache, ProtocolMetrics, QuicDataFormat,
+    QuicRecvProtocol, QuicSendProtocol, RecvProtocol, SendProtocol, Sid, TcpRecvProtocol,
+    TcpSendProtocol, UnreliableDrain, UnreliableSink, VersionPolicy, _internal::OTFrame,
+    DEFAULT_STREAM_WINDOW,
 };
 
 fn frame_serialize(frame: OTFrame, buffer: &mut BytesMut) { frame.write_bytes(buffer); }
@@ -22,11 +23,15 @@ where
     let [mut p1, mut p2] = p;
     tokio::join!(
         async {
-            p1.initialize(true, Pid::fake(2), 1337).await.unwrap();
+            p1.initialize(true, Pid::fake(2), 1337, VersionPolicy::default())
+                .await
+                .unwrap();
             p1
         },
         async {
-            p2.initialize(false, Pid::fake(3), 42).await.unwrap();
+            p2.initialize(false, Pid::fake(3), 42, VersionPolicy::default())
+                .await
+                .unwrap();
             p2
         }
     );
@@ -41,6 +46,7 @@ async fn send_msg<T: SendProtocol>(mut s: T, data: Bytes, cnt: usize) {
         prio: 0,
         promises: Promises::ORDERED,
         guaranteed_bandwidth: 100_000,
+        window: DEFAULT_STREAM_WINDOW,
     })
     .await
     .unwrap();
@@ -143,6 +149,14 @@ fn criterion_tcp(c: &mut Criterion) {
                 |(b, p)| send_and_recv_msg(p, b, 1_000_000),
             )
         });
+    c.throughput(Throughput::Elements(100000))
+        .bench_function("100000_msg_100B", |b| {
+            let buf = Bytes::from(&[7u8; 100][..]);
+            b.to_async(rt()).iter_with_setup(
+                || (buf.clone(), utils::tcp_bound(10000, None)),
+                |(b, p)| send_and_recv_msg(p, b, 100_000),
+            )
+        });
     c.finish();
 }
 
@@ -229,11 +243,11 @@ mod utils {
         });
         [
             (
-                TcpSendProtocol::new(TcpDrain { sender: s1 }, m.clone()),
+                TcpSendProtocol::new(TcpDrain { sender: s1 }, m.clone(), KeepAlive::default()),
                 TcpRecvProtocol::new(TcpSink { receiver: r2 }, m.clone()),
             ),
             (
-                TcpSendProtocol::new(TcpDrain { sender: s2 }, m.clone()),
+                TcpSendProtocol::new(TcpDrain { sender: s2 }, m.clone(), KeepAlive::default()),
                 TcpRecvProtocol::new(TcpSink { receiver: r1 }, m),
             ),
         ]
@@ -6,6 +6,26 @@ pub enum InitProtocolError {
     Closed,
     WrongMagicNumber([u8; 7]),
     WrongVersion([u32; 3]),
+    /// The listening side rejected our version, only the connecting side can
+    /// get this
+    VersionMismatch {
+        server_version: [u32; 3],
+        min_client: [u32; 3],
+        /// Our own version that got rejected
+        client_version: [u32; 3],
+    },
+    /// One side requires an encrypted channel, the other one doesn't
+    EncryptionMismatch {
+        local: bool,
+        remote: bool,
+    },
+    /// The Noise handshake of an encrypted channel failed
+    EncryptionFailed,
+    /// The remote side doesn't own the key pinned with
+    /// [`NoiseKeys::with_remote_key`]
+    ///
+    /// [`NoiseKeys::with_remote_key`]: crate::NoiseKeys::with_remote_key
+    UntrustedKey,
 }
 
 /// When you return closed you must stay closed!
@@ -18,6 +38,9 @@ pub enum ProtocolError {
     /// the underlying I/O connection is still valid, but the remote side
     /// send WRONG (e.g. Invalid, or wrong order) data on the protocol layer.
     Violated,
+    /// The remote side send a frame with an id we don't know. Frames have
+    /// no common length field, so the rest of the stream can't be read anymore
+    UnknownFrame(u8),
 }
 
 impl From<ProtocolError> for InitProtocolError {
@@ -25,7 +48,7 @@ impl From<ProtocolError> for InitProtocolError {
         match err {
             ProtocolError::Closed => InitProtocolError::Closed,
             // not possible as the Init has raw access to the I/O
-            ProtocolError::Violated => InitProtocolError::Closed,
+            ProtocolError::Violated | ProtocolError::UnknownFrame(_) => InitProtocolError::Closed,
         }
     }
 }
@@ -46,6 +69,29 @@ impl core::fmt::Display for InitProtocolError {
                 &r,
                 &crate::types::VELOREN_NETWORK_VERSION
             ),
+            InitProtocolError::VersionMismatch {
+                server_version,
+                min_client,
+                client_version,
+            } => write!(
+                f,
+                "Network version '{:?}' was rejected, remote side is on '{:?}' and requires at \
+                 least '{:?}'",
+                client_version, server_version, min_client
+            ),
+            InitProtocolError::EncryptionMismatch { local: true, .. } => write!(
+                f,
+                "Encryption is required, but the remote side wants an unencrypted channel"
+            ),
+            InitProtocolError::EncryptionMismatch { .. } => write!(
+                f,
+                "Remote side requires encryption, but it is disabled on this side"
+            ),
+            InitProtocolError::EncryptionFailed => write!(f, "Encryption handshake failed"),
+            InitProtocolError::UntrustedKey => write!(
+                f,
+                "Remote side doesn't have the pinned key, someone might pose as it"
+            ),
         }
     }
 }
@@ -55,6 +101,7 @@ impl core::fmt::Display for ProtocolError {
         match self {
             ProtocolError::Closed => write!(f, "Channel closed"),
             ProtocolError::Violated => write!(f, "Channel protocol violated"),
+            ProtocolError::UnknownFrame(id) => write!(f, "Unknown frame id {}", id),
         }
     }
 }
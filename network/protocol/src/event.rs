@@ -17,10 +17,19 @@ pub enum ProtocolEvent {
         prio: Prio,
         promises: Promises,
         guaranteed_bandwidth: Bandwidth,
+        /// bytes the opening side can receive before sending a `WindowUpdate`,
+        /// the same window is used for the other direction
+        window: u64,
     },
     CloseStream {
         sid: Sid,
     },
+    /// the receiving application consumed `bytes` of the stream, they can be
+    /// sent again
+    WindowUpdate {
+        sid: Sid,
+        bytes: u64,
+    },
     Message {
         data: Bytes,
         sid: Sid,
@@ -36,13 +45,19 @@ impl ProtocolEvent {
                 prio,
                 promises,
                 guaranteed_bandwidth,
+                window,
             } => OTFrame::OpenStream {
                 sid: *sid,
                 prio: *prio,
                 promises: *promises,
                 guaranteed_bandwidth: *guaranteed_bandwidth,
+                window: *window,
             },
             ProtocolEvent::CloseStream { sid } => OTFrame::CloseStream { sid: *sid },
+            ProtocolEvent::WindowUpdate { sid, bytes } => OTFrame::WindowUpdate {
+                sid: *sid,
+                bytes: *bytes,
+            },
             ProtocolEvent::Message { .. } => {
                 unimplemented!("Event::Message to OTFrame IS NOT supported")
             },
@@ -61,6 +76,17 @@ mod tests {
             ProtocolEvent::CloseStream { sid: Sid::new(42) }.to_frame(),
             OTFrame::CloseStream { sid: Sid::new(42) }
         );
+        assert_eq!(
+            ProtocolEvent::WindowUpdate {
+                sid: Sid::new(42),
+                bytes: 1337,
+            }
+            .to_frame(),
+            OTFrame::WindowUpdate {
+                sid: Sid::new(42),
+                bytes: 1337,
+            }
+        );
     }
 
     #[test]
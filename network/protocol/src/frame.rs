@@ -1,5 +1,9 @@
-use crate::types::{Bandwidth, Mid, Pid, Prio, Promises, Sid};
+use crate::{
+    error::ProtocolError,
+    types::{Bandwidth, Mid, Pid, Prio, Promises, Sid},
+};
 use bytes::{Buf, BufMut, Bytes, BytesMut};
+use tracing::info;
 
 // const FRAME_RESERVED_1: u8 = 0;
 const FRAME_HANDSHAKE: u8 = 1;
@@ -10,8 +14,59 @@ const FRAME_CLOSE_STREAM: u8 = 5;
 const FRAME_DATA_HEADER: u8 = 6;
 const FRAME_DATA: u8 = 7;
 const FRAME_RAW: u8 = 8;
-//const FRAME_RESERVED_2: u8 = 10;
-//const FRAME_RESERVED_3: u8 = 13;
+const FRAME_PING: u8 = 9;
+const FRAME_PONG: u8 = 10;
+const FRAME_VERSION_MISMATCH: u8 = 11;
+const FRAME_WINDOW_UPDATE: u8 = 12;
+const FRAME_NOISE: u8 = 13;
+
+/// Frame names indexed by their id, used for logging and as metric labels
+pub(crate) const FRAME_NAMES: [&str; 14] = [
+    "reserved",
+    "handshake",
+    "init",
+    "shutdown",
+    "open_stream",
+    "close_stream",
+    "data_header",
+    "data",
+    "raw",
+    "ping",
+    "pong",
+    "version_mismatch",
+    "window_update",
+    "noise",
+];
+
+/// Set in the flags of a `Handshake` if the sender wants the channel encrypted
+const HANDSHAKE_ENCRYPTED: u8 = 0b0000_0001;
+
+/// Number of bytes following an unknown frame id that get logged
+const UNKNOWN_FRAME_LOG_BYTES: usize = 16;
+
+/// Logs the unknown frame id at the start of `bytes` and what follows it, the
+/// stream can't be read past it
+fn unknown_frame(bytes: &[u8]) -> ProtocolError {
+    let id = bytes[0];
+    let next_bytes = &bytes[1..bytes.len().min(UNKNOWN_FRAME_LOG_BYTES + 1)];
+    info!(
+        ?id,
+        ?next_bytes,
+        "protocol violation by remote side: unknown frame id"
+    );
+    ProtocolError::UnknownFrame(id)
+}
+
+/// Sees every frame a [`TcpSendProtocol`] sends or a [`TcpRecvProtocol`]
+/// receives after the handshake, in the unencrypted byte layout of the wire.
+/// Meant for debugging, e.g. to log the frames of a channel.
+///
+/// [`TcpSendProtocol`]: crate::TcpSendProtocol
+/// [`TcpRecvProtocol`]: crate::TcpRecvProtocol
+pub trait FrameObserver: std::fmt::Debug + Send + Sync {
+    fn frame_sent(&self, frame: &[u8]);
+    fn frame_received(&self, frame: &[u8]);
+}
 
 /// Used for Communication between Channel <----(TCP/UDP)----> Channel
 #[derive(Debug, PartialEq, Clone)]
@@ -19,14 +74,28 @@ pub enum InitFrame {
     Handshake {
         magic_number: [u8; 7],
         version: [u32; 3],
+        /// The sender wants all further frames encrypted, see [`noise`]. The
+        /// channel is only set up if both sides agree on this.
+        ///
+        /// [`noise`]: crate::noise
+        encrypted: bool,
     },
     Init {
         pid: Pid,
         secret: u128,
     },
+    /// Send instead of a `Handshake` to a remote with an incompatible version,
+    /// the connection is closed afterwards
+    VersionMismatch {
+        server_version: [u32; 3],
+        min_client: [u32; 3],
+    },
     /// WARNING: sending RAW is only for debug purposes and will drop the
     /// connection
-    Raw(Vec<u8>),
+    Raw(Bytes),
+    /// A message of the Noise handshake, exchanged after the `Handshake` of
+    /// an encrypted channel
+    Noise(Bytes),
 }
 
 /// Used for OUT TCP Communication between Channel --(TCP)--> Channel
@@ -34,15 +103,32 @@ pub enum InitFrame {
 pub enum OTFrame {
     Shutdown, /* Shutdown this channel gracefully, if all channels are shutdown (gracefully),
                * Participant is deleted */
+    /// send regularly, so the remote can tell an idle channel from a dead one.
+    /// `timestamp` is in nanoseconds on the senders clock, the remote echoes
+    /// it in a `Pong` to measure the round trip time.
+    Ping {
+        timestamp: u64,
+    },
+    /// answers a `Ping`, `delay` is the time in nanoseconds the `Ping` waited
+    /// for the `Pong` to be send, it's not part of the round trip time
+    Pong {
+        timestamp: u64,
+        delay: u64,
+    },
     OpenStream {
         sid: Sid,
         prio: Prio,
         promises: Promises,
         guaranteed_bandwidth: Bandwidth,
+        window: u64,
     },
     CloseStream {
         sid: Sid,
     },
+    WindowUpdate {
+        sid: Sid,
+        bytes: u64,
+    },
     DataHeader {
         mid: Mid,
         sid: Sid,
@@ -59,32 +145,70 @@ pub enum OTFrame {
 pub enum ITFrame {
     Shutdown, /* Shutdown this channel gracefully, if all channels are shutdown (gracefully),
                * Participant is deleted */
+    /// send regularly, so the remote can tell an idle channel from a dead one.
+    /// `timestamp` is in nanoseconds on the senders clock, the remote echoes
+    /// it in a `Pong` to measure the round trip time.
+    Ping {
+        timestamp: u64,
+    },
+    /// answers a `Ping`, `delay` is the time in nanoseconds the `Ping` waited
+    /// for the `Pong` to be send, it's not part of the round trip time
+    Pong {
+        timestamp: u64,
+        delay: u64,
+    },
     OpenStream {
         sid: Sid,
         prio: Prio,
         promises: Promises,
         guaranteed_bandwidth: Bandwidth,
+        window: u64,
     },
     CloseStream {
         sid: Sid,
     },
+    WindowUpdate {
+        sid: Sid,
+        bytes: u64,
+    },
     DataHeader {
         mid: Mid,
         sid: Sid,
         length: u64,
     },
+    /// slice of the read buffer, not copied
     Data {
         mid: Mid,
-        data: BytesMut,
+        data: Bytes,
     },
 }
 
 impl InitFrame {
     // Size WITHOUT the 1rst indicating byte
-    pub(crate) const HANDSHAKE_CNS: usize = 19;
+    pub(crate) const HANDSHAKE_CNS: usize = 20;
     pub(crate) const INIT_CNS: usize = 32;
+    /// const part of the NOISE frame, actual size is variable
+    pub(crate) const NOISE_CNS: usize = 2;
+    /// Longest payload of a NOISE frame, handshake messages are much shorter
+    pub(crate) const NOISE_MAX_SIZE: usize = 1024;
     /// const part of the RAW frame, actual size is variable
     pub(crate) const RAW_CNS: usize = 2;
+    /// Longest payload of a RAW frame, they only carry short debug messages
+    pub(crate) const RAW_MAX_SIZE: usize = 1024;
+    pub(crate) const VERSION_MISMATCH_CNS: usize = 24;
+
+    /// short name used as metrics label
+    pub fn id(&self) -> u8 {
+        match self {
+            InitFrame::Handshake { .. } => FRAME_HANDSHAKE,
+            InitFrame::Init { .. } => FRAME_INIT,
+            InitFrame::VersionMismatch { .. } => FRAME_VERSION_MISMATCH,
+            InitFrame::Raw(_) => FRAME_RAW,
+            InitFrame::Noise(_) => FRAME_NOISE,
+        }
+    }
+
+    pub fn name(&self) -> &'static str { FRAME_NAMES[self.id() as usize] }
 
     //provide an appropriate buffer size. > 1500
     pub(crate) fn write_bytes(self, bytes: &mut BytesMut) {
@@ -92,35 +216,53 @@ impl InitFrame {
             InitFrame::Handshake {
                 magic_number,
                 version,
+                encrypted,
             } => {
                 bytes.put_u8(FRAME_HANDSHAKE);
                 bytes.put_slice(&magic_number);
                 bytes.put_u32_le(version[0]);
                 bytes.put_u32_le(version[1]);
                 bytes.put_u32_le(version[2]);
+                bytes.put_u8(if encrypted { HANDSHAKE_ENCRYPTED } else { 0 });
             },
             InitFrame::Init { pid, secret } => {
                 bytes.put_u8(FRAME_INIT);
                 pid.to_bytes(bytes);
                 bytes.put_u128_le(secret);
             },
+            InitFrame::VersionMismatch {
+                server_version,
+                min_client,
+            } => {
+                bytes.put_u8(FRAME_VERSION_MISMATCH);
+                for v in server_version.iter().chain(min_client.iter()) {
+                    bytes.put_u32_le(*v);
+                }
+            },
             InitFrame::Raw(data) => {
                 bytes.put_u8(FRAME_RAW);
                 bytes.put_u16_le(data.len() as u16);
                 bytes.put_slice(&data);
             },
+            InitFrame::Noise(data) => {
+                bytes.put_u8(FRAME_NOISE);
+                bytes.put_u16_le(data.len() as u16);
+                bytes.put_slice(&data);
+            },
         }
     }
 
-    pub(crate) fn read_frame(bytes: &mut BytesMut) -> Option<Self> {
+    /// Err => cannot recover
+    /// Ok(None) => waiting for more data
+    pub(crate) fn read_frame(bytes: &mut BytesMut) -> Result<Option<Self>, ProtocolError> {
         let frame_no = match bytes.get(0) {
             Some(&f) => f,
-            None => return None,
+            None => return Ok(None),
         };
         let frame = match frame_no {
             FRAME_HANDSHAKE => {
                 if bytes.len() < Self::HANDSHAKE_CNS + 1 {
-                    return None;
+                    return Ok(None);
                 }
                 bytes.advance(1);
                 let mut magic_number_bytes = bytes.copy_to_bytes(7);
@@ -129,11 +271,12 @@ impl InitFrame {
                 InitFrame::Handshake {
                     magic_number,
                     version: [bytes.get_u32_le(), bytes.get_u32_le(), bytes.get_u32_le()],
+                    encrypted: bytes.get_u8() & HANDSHAKE_ENCRYPTED != 0,
                 }
             },
             FRAME_INIT => {
                 if bytes.len() < Self::INIT_CNS + 1 {
-                    return None;
+                    return Ok(None);
                 }
                 bytes.advance(1);
                 InitFrame::Init {
@@ -141,21 +284,54 @@ impl InitFrame {
                     secret: bytes.get_u128_le(),
                 }
             },
+            FRAME_VERSION_MISMATCH => {
+                if bytes.len() < Self::VERSION_MISMATCH_CNS + 1 {
+                    return Ok(None);
+                }
+                bytes.advance(1);
+                InitFrame::VersionMismatch {
+                    server_version: [bytes.get_u32_le(), bytes.get_u32_le(), bytes.get_u32_le()],
+                    min_client: [bytes.get_u32_le(), bytes.get_u32_le(), bytes.get_u32_le()],
+                }
+            },
             FRAME_RAW => {
                 if bytes.len() < Self::RAW_CNS + 1 {
-                    return None;
+                    return Ok(None);
                 }
-                bytes.advance(1);
-                let length = bytes.get_u16_le() as usize;
+                let length = u16::from_le_bytes([bytes[1], bytes[2]]) as usize;
+                if length > Self::RAW_MAX_SIZE {
+                    info!(
+                        ?length,
+                        "protocol violation by remote side: RAW frame too long"
+                    );
+                    return Err(ProtocolError::Violated);
+                }
+                bytes.advance(Self::RAW_CNS + 1);
                 // lower length is allowed
                 let max_length = length.min(bytes.len());
-                let mut data = vec![0; max_length];
-                data.copy_from_slice(&bytes[..max_length]);
-                InitFrame::Raw(data)
+                InitFrame::Raw(bytes.split_to(max_length).freeze())
             },
-            _ => InitFrame::Raw(bytes.to_vec()),
+            FRAME_NOISE => {
+                if bytes.len() < Self::NOISE_CNS + 1 {
+                    return Ok(None);
+                }
+                let length = u16::from_le_bytes([bytes[1], bytes[2]]) as usize;
+                if length > Self::NOISE_MAX_SIZE {
+                    info!(
+                        ?length,
+                        "protocol violation by remote side: NOISE frame too long"
+                    );
+                    return Err(ProtocolError::Violated);
+                }
+                if bytes.len() < Self::NOISE_CNS + 1 + length {
+                    return Ok(None);
+                }
+                bytes.advance(Self::NOISE_CNS + 1);
+                InitFrame::Noise(bytes.split_to(length).freeze())
+            },
+            _ => return Err(unknown_frame(bytes)),
         };
-        Some(frame)
+        Ok(Some(frame))
     }
 }
 
@@ -163,32 +339,72 @@ pub(crate) const TCP_CLOSE_STREAM_CNS: usize = 8;
 /// const part of the DATA frame, actual size is variable
 pub(crate) const TCP_DATA_CNS: usize = 10;
 pub(crate) const TCP_DATA_HEADER_CNS: usize = 24;
-pub(crate) const TCP_OPEN_STREAM_CNS: usize = 18;
+pub(crate) const TCP_OPEN_STREAM_CNS: usize = 26;
+pub(crate) const TCP_WINDOW_UPDATE_CNS: usize = 16;
 // Size WITHOUT the 1rst indicating byte
 pub(crate) const TCP_SHUTDOWN_CNS: usize = 0;
+// Size WITHOUT the 1rst indicating byte
+pub(crate) const TCP_PING_CNS: usize = 8;
+// Size WITHOUT the 1rst indicating byte
+pub(crate) const TCP_PONG_CNS: usize = 16;
 
 impl OTFrame {
+    /// short name used as metrics label
+    pub fn id(&self) -> u8 {
+        match self {
+            Self::Shutdown => FRAME_SHUTDOWN,
+            Self::Ping { .. } => FRAME_PING,
+            Self::Pong { .. } => FRAME_PONG,
+            Self::OpenStream { .. } => FRAME_OPEN_STREAM,
+            Self::CloseStream { .. } => FRAME_CLOSE_STREAM,
+            Self::WindowUpdate { .. } => FRAME_WINDOW_UPDATE,
+            Self::DataHeader { .. } => FRAME_DATA_HEADER,
+            Self::Data { .. } => FRAME_DATA,
+        }
+    }
+
+    pub fn name(&self) -> &'static str { FRAME_NAMES[self.id() as usize] }
+
     pub fn write_bytes(self, bytes: &mut BytesMut) {
         match self {
             Self::Shutdown => {
                 bytes.put_u8(FRAME_SHUTDOWN);
             },
+            Self::Ping { timestamp } => {
+                bytes.put_u8(FRAME_PING);
+                bytes.put_u64_le(timestamp);
+            },
+            Self::Pong { timestamp, delay } => {
+                bytes.put_u8(FRAME_PONG);
+                bytes.put_u64_le(timestamp);
+                bytes.put_u64_le(delay);
+            },
             Self::OpenStream {
                 sid,
                 prio,
                 promises,
                 guaranteed_bandwidth,
+                window,
             } => {
                 bytes.put_u8(FRAME_OPEN_STREAM);
                 sid.to_bytes(bytes);
                 bytes.put_u8(prio);
                 bytes.put_u8(promises.to_le_bytes()[0]);
                 bytes.put_u64_le(guaranteed_bandwidth);
+                bytes.put_u64_le(window);
             },
             Self::CloseStream { sid } => {
                 bytes.put_u8(FRAME_CLOSE_STREAM);
                 sid.to_bytes(bytes);
             },
+            Self::WindowUpdate {
+                sid,
+                bytes: consumed,
+            } => {
+                bytes.put_u8(FRAME_WINDOW_UPDATE);
+                sid.to_bytes(bytes);
+                bytes.put_u64_le(consumed);
+            },
             Self::DataHeader { mid, sid, length } => {
                 bytes.put_u8(FRAME_DATA_HEADER);
                 bytes.put_u64_le(mid);
@@ -206,17 +422,36 @@ impl OTFrame {
 }
 
 impl ITFrame {
+    /// short name used as metrics label, same as [`OTFrame::name`]
+    pub fn id(&self) -> u8 {
+        match self {
+            Self::Shutdown => FRAME_SHUTDOWN,
+            Self::Ping { .. } => FRAME_PING,
+            Self::Pong { .. } => FRAME_PONG,
+            Self::OpenStream { .. } => FRAME_OPEN_STREAM,
+            Self::CloseStream { .. } => FRAME_CLOSE_STREAM,
+            Self::WindowUpdate { .. } => FRAME_WINDOW_UPDATE,
+            Self::DataHeader { .. } => FRAME_DATA_HEADER,
+            Self::Data { .. } => FRAME_DATA,
+        }
+    }
+
+    pub fn name(&self) -> &'static str { FRAME_NAMES[self.id() as usize] }
+
     /// Err => cannot recover
     /// Ok(None) => waiting for more data
-    pub(crate) fn read_frame(bytes: &mut BytesMut) -> Result<Option<Self>, ()> {
+    pub fn read_frame(bytes: &mut BytesMut) -> Result<Option<Self>, ProtocolError> {
         let frame_no = match bytes.first() {
             Some(&f) => f,
             None => return Ok(None),
         };
         let size = match frame_no {
             FRAME_SHUTDOWN => TCP_SHUTDOWN_CNS,
+            FRAME_PING => TCP_PING_CNS,
+            FRAME_PONG => TCP_PONG_CNS,
             FRAME_OPEN_STREAM => TCP_OPEN_STREAM_CNS,
             FRAME_CLOSE_STREAM => TCP_CLOSE_STREAM_CNS,
+            FRAME_WINDOW_UPDATE => TCP_WINDOW_UPDATE_CNS,
             FRAME_DATA_HEADER => TCP_DATA_HEADER_CNS,
             FRAME_DATA => {
                 if bytes.len() < 9 + 1 + 1 {
@@ -224,7 +459,7 @@ impl ITFrame {
                 }
                 u16::from_le_bytes([bytes[8 + 1], bytes[9 + 1]]) as usize + TCP_DATA_CNS
             },
-            _ => return Err(()),
+            _ => return Err(unknown_frame(bytes)),
         };
 
         if bytes.len() < size + 1 {
@@ -236,6 +471,21 @@ impl ITFrame {
                 let _ = bytes.split_to(size + 1);
                 Self::Shutdown
             },
+            FRAME_PING => {
+                let mut bytes = bytes.split_to(size + 1);
+                bytes.advance(1);
+                Self::Ping {
+                    timestamp: bytes.get_u64_le(),
+                }
+            },
+            FRAME_PONG => {
+                let mut bytes = bytes.split_to(size + 1);
+                bytes.advance(1);
+                Self::Pong {
+                    timestamp: bytes.get_u64_le(),
+                    delay: bytes.get_u64_le(),
+                }
+            },
             FRAME_OPEN_STREAM => {
                 let mut bytes = bytes.split_to(size + 1);
                 bytes.advance(1);
@@ -244,6 +494,7 @@ impl ITFrame {
                     prio: bytes.get_u8(),
                     promises: Promises::from_bits_truncate(bytes.get_u8()),
                     guaranteed_bandwidth: bytes.get_u64_le(),
+                    window: bytes.get_u64_le(),
                 }
             },
             FRAME_CLOSE_STREAM => {
@@ -253,6 +504,14 @@ impl ITFrame {
                     sid: Sid::from_bytes(&mut bytes),
                 }
             },
+            FRAME_WINDOW_UPDATE => {
+                let mut bytes = bytes.split_to(size + 1);
+                bytes.advance(1);
+                Self::WindowUpdate {
+                    sid: Sid::from_bytes(&mut bytes),
+                    bytes: bytes.get_u64_le(),
+                }
+            },
             FRAME_DATA_HEADER => {
                 let mut bytes = bytes.split_to(size + 1);
                 bytes.advance(1);
@@ -267,7 +526,7 @@ impl ITFrame {
                 let mid = bytes.get_u64_le();
                 let length = bytes.get_u16_le();
                 debug_assert_eq!(length as usize, size - TCP_DATA_CNS);
-                let data = bytes.split_to(length as usize);
+                let data = bytes.split_to(length as usize).freeze();
                 Self::Data { mid, data }
             },
             _ => unreachable!("Frame::to_frame should be handled before!"),
@@ -276,23 +535,60 @@ impl ITFrame {
     }
 }
 
+/// The frame as the remote sent it
+impl From<ITFrame> for OTFrame {
+    fn from(frame: ITFrame) -> Self {
+        match frame {
+            ITFrame::Shutdown => Self::Shutdown,
+            ITFrame::Ping { timestamp } => Self::Ping { timestamp },
+            ITFrame::Pong { timestamp, delay } => Self::Pong { timestamp, delay },
+            ITFrame::OpenStream {
+                sid,
+                prio,
+                promises,
+                guaranteed_bandwidth,
+                window,
+            } => Self::OpenStream {
+                sid,
+                prio,
+                promises,
+                guaranteed_bandwidth,
+                window,
+            },
+            ITFrame::CloseStream { sid } => Self::CloseStream { sid },
+            ITFrame::WindowUpdate { sid, bytes } => Self::WindowUpdate { sid, bytes },
+            ITFrame::DataHeader { mid, sid, length } => Self::DataHeader { mid, sid, length },
+            ITFrame::Data { mid, data } => Self::Data { mid, data },
+        }
+    }
+}
+
 #[allow(unused_variables)]
 impl PartialEq<ITFrame> for OTFrame {
     fn eq(&self, other: &ITFrame) -> bool {
         match self {
             Self::Shutdown => matches!(other, ITFrame::Shutdown),
+            Self::Ping { timestamp } => matches!(other, ITFrame::Ping { timestamp }),
+            Self::Pong { timestamp, delay } => {
+                matches!(other, ITFrame::Pong { timestamp, delay })
+            },
             Self::OpenStream {
                 sid,
                 prio,
                 promises,
                 guaranteed_bandwidth,
+                window,
             } => matches!(other, ITFrame::OpenStream {
                 sid,
                 prio,
                 promises,
                 guaranteed_bandwidth,
+                window,
             }),
             Self::CloseStream { sid } => matches!(other, ITFrame::CloseStream { sid }),
+            Self::WindowUpdate { sid, bytes } => {
+                matches!(other, ITFrame::WindowUpdate { sid, bytes })
+            },
             Self::DataHeader { mid, sid, length } => {
                 matches!(other, ITFrame::DataHeader { mid, sid, length })
             },
@@ -311,12 +607,18 @@ mod tests {
             InitFrame::Handshake {
                 magic_number: VELOREN_MAGIC_NUMBER,
                 version: VELOREN_NETWORK_VERSION,
+                encrypted: false,
             },
             InitFrame::Init {
                 pid: Pid::fake(0),
                 secret: 0u128,
             },
-            InitFrame::Raw(vec![1, 2, 3]),
+            InitFrame::VersionMismatch {
+                server_version: [0, 8, 1],
+                min_client: [0, 8, 0],
+            },
+            InitFrame::Raw(Bytes::from_static(&[1, 2, 3])),
+            InitFrame::Noise(Bytes::from_static(&[4, 5, 6])),
         ]
     }
 
@@ -327,6 +629,7 @@ mod tests {
                 prio: 14,
                 promises: Promises::GUARANTEED_DELIVERY,
                 guaranteed_bandwidth: 1_000_000,
+                window: 4_000_000,
             },
             OTFrame::DataHeader {
                 sid: Sid::new(1337),
@@ -341,6 +644,15 @@ mod tests {
                 mid: 0,
                 data: Bytes::from(&[42u8; 16][..]),
             },
+            OTFrame::Ping { timestamp: 1337 },
+            OTFrame::Pong {
+                timestamp: 1337,
+                delay: 42,
+            },
+            OTFrame::WindowUpdate {
+                sid: Sid::new(1337),
+                bytes: 36,
+            },
             OTFrame::CloseStream {
                 sid: Sid::new(1337),
             },
@@ -358,7 +670,7 @@ mod tests {
 
         for frame in get_initframes() {
             println!("initframe: {:?}", &frame);
-            assert_eq!(Some(frame.clone()), dupl(frame));
+            assert_eq!(Ok(Some(frame.clone())), dupl(frame));
         }
     }
 
@@ -381,7 +693,7 @@ mod tests {
         // compare
         for (f, fd) in frames.drain(..).zip(framesd.drain(..)) {
             println!("initframe: {:?}", &f);
-            assert_eq!(Some(f), fd);
+            assert_eq!(Ok(Some(f)), fd);
         }
     }
 
@@ -435,6 +747,82 @@ mod tests {
         assert_eq!(frame1, frame2.expect("ERR").expect("NONE"));
     }
 
+    #[test]
+    fn initframe_boundary_values() {
+        let frames = vec![
+            InitFrame::Handshake {
+                magic_number: [u8::MAX; 7],
+                version: [u32::MAX; 3],
+                encrypted: true,
+            },
+            InitFrame::Init {
+                pid: Pid::fake(7),
+                secret: u128::MAX,
+            },
+            InitFrame::VersionMismatch {
+                server_version: [u32::MAX; 3],
+                min_client: [0; 3],
+            },
+            InitFrame::Raw(Bytes::new()),
+            InitFrame::Raw(Bytes::from(vec![255u8; InitFrame::RAW_MAX_SIZE])),
+            InitFrame::Noise(Bytes::new()),
+            InitFrame::Noise(Bytes::from(vec![255u8; InitFrame::NOISE_MAX_SIZE])),
+        ];
+        for frame in frames {
+            let mut buffer = BytesMut::new();
+            frame.clone().write_bytes(&mut buffer);
+            assert_eq!(Ok(Some(frame)), InitFrame::read_frame(&mut buffer));
+            // Reading consumes exactly what was written
+            assert!(buffer.is_empty());
+        }
+    }
+
+    #[test]
+    fn frame_boundary_values() {
+        let sid = Sid::new(u64::MAX);
+        let frames = vec![
+            OTFrame::OpenStream {
+                sid,
+                prio: Prio::MAX,
+                promises: Promises::all(),
+                guaranteed_bandwidth: Bandwidth::MAX,
+                window: u64::MAX,
+            },
+            OTFrame::OpenStream {
+                sid: Sid::new(0),
+                prio: 0,
+                promises: Promises::empty(),
+                guaranteed_bandwidth: 0,
+                window: 0,
+            },
+            OTFrame::WindowUpdate {
+                sid,
+                bytes: u64::MAX,
+            },
+            OTFrame::DataHeader {
+                mid: Mid::MAX,
+                sid,
+                length: u64::MAX,
+            },
+            OTFrame::Data {
+                mid: Mid::MAX,
+                data: Bytes::new(),
+            },
+            OTFrame::Data {
+                mid: 0,
+                data: Bytes::from(vec![255u8; u16::MAX as usize]),
+            },
+            OTFrame::CloseStream { sid },
+        ];
+        for frame in frames {
+            let mut buffer = BytesMut::new();
+            frame.clone().write_bytes(&mut buffer);
+            assert_eq!(frame, ITFrame::read_frame(&mut buffer).unwrap().unwrap());
+            // Reading consumes exactly what was written
+            assert!(buffer.is_empty());
+        }
+    }
+
     #[test]
     fn initframe_too_short_buffer() {
         let mut buffer = BytesMut::with_capacity(10);
@@ -442,6 +830,7 @@ mod tests {
         let frame1 = InitFrame::Handshake {
             magic_number: VELOREN_MAGIC_NUMBER,
             version: VELOREN_NETWORK_VERSION,
+            encrypted: false,
         };
         InitFrame::write_bytes(frame1, &mut buffer);
     }
@@ -453,11 +842,12 @@ mod tests {
         let frame1 = InitFrame::Handshake {
             magic_number: VELOREN_MAGIC_NUMBER,
             version: VELOREN_NETWORK_VERSION,
+            encrypted: false,
         };
         let _ = InitFrame::write_bytes(frame1, &mut buffer);
         buffer.truncate(6); // simulate partial retrieve
         let frame1d = InitFrame::read_frame(&mut buffer);
-        assert_eq!(frame1d, None);
+        assert_eq!(frame1d, Ok(None));
     }
 
     #[test]
@@ -465,7 +855,37 @@ mod tests {
         let mut buffer = BytesMut::from(&b"dtrgwcser"[..]);
         assert_eq!(
             InitFrame::read_frame(&mut buffer),
-            Some(InitFrame::Raw(b"dtrgwcser".to_vec()))
+            Err(ProtocolError::UnknownFrame(b'd'))
+        );
+    }
+
+    #[test]
+    fn initframe_raw_is_consumed() {
+        let mut buffer = BytesMut::with_capacity(50);
+
+        let frame1 = InitFrame::Raw(Bytes::from_static(b"foobar"));
+        let frame2 = InitFrame::Init {
+            pid: Pid::fake(1),
+            secret: 42u128,
+        };
+        InitFrame::write_bytes(frame1.clone(), &mut buffer);
+        InitFrame::write_bytes(frame2.clone(), &mut buffer);
+        assert_eq!(InitFrame::read_frame(&mut buffer), Ok(Some(frame1)));
+        assert_eq!(InitFrame::read_frame(&mut buffer), Ok(Some(frame2)));
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn initframe_raw_too_long() {
+        let mut buffer = BytesMut::with_capacity(50);
+
+        let frame1 = InitFrame::Raw(Bytes::from_static(b"foobar"));
+        InitFrame::write_bytes(frame1, &mut buffer);
+        buffer[1] = 0xFF;
+        buffer[2] = 0xFF;
+        assert_eq!(
+            InitFrame::read_frame(&mut buffer),
+            Err(ProtocolError::Violated)
         );
     }
 
@@ -473,23 +893,23 @@ mod tests {
     fn initframe_attack_too_much_length() {
         let mut buffer = BytesMut::with_capacity(50);
 
-        let frame1 = InitFrame::Raw(b"foobar".to_vec());
+        let frame1 = InitFrame::Raw(Bytes::from_static(b"foobar"));
         let _ = InitFrame::write_bytes(frame1.clone(), &mut buffer);
         buffer[1] = 255;
         let framed = InitFrame::read_frame(&mut buffer);
-        assert_eq!(framed, Some(frame1));
+        assert_eq!(framed, Ok(Some(frame1)));
     }
 
     #[test]
     fn initframe_attack_too_low_length() {
         let mut buffer = BytesMut::with_capacity(50);
 
-        let frame1 = InitFrame::Raw(b"foobar".to_vec());
+        let frame1 = InitFrame::Raw(Bytes::from_static(b"foobar"));
         let _ = InitFrame::write_bytes(frame1, &mut buffer);
         buffer[1] = 3;
         let framed = InitFrame::read_frame(&mut buffer);
         // we accept a different frame here, as it's RAW and debug only!
-        assert_eq!(framed, Some(InitFrame::Raw(b"foo".to_vec())));
+        assert_eq!(framed, Ok(Some(InitFrame::Raw(Bytes::from_static(b"foo")))));
     }
 
     #[test]
@@ -501,6 +921,7 @@ mod tests {
             promises: Promises::ENCRYPTED,
             prio: 88,
             guaranteed_bandwidth: 1_000_000,
+            window: 4_000_000,
         };
         OTFrame::write_bytes(frame1, &mut buffer);
     }
@@ -514,6 +935,7 @@ mod tests {
             promises: Promises::ENCRYPTED,
             prio: 88,
             guaranteed_bandwidth: 1_000_000,
+            window: 4_000_000,
         };
         OTFrame::write_bytes(frame1, &mut buffer);
         buffer.truncate(6); // simulate partial retrieve
@@ -524,7 +946,10 @@ mod tests {
     #[test]
     fn frame_rubish() {
         let mut buffer = BytesMut::from(&b"dtrgwcser"[..]);
-        assert_eq!(ITFrame::read_frame(&mut buffer), Err(()));
+        assert_eq!(
+            ITFrame::read_frame(&mut buffer),
+            Err(ProtocolError::UnknownFrame(b'd'))
+        );
     }
 
     #[test]
@@ -558,11 +983,11 @@ mod tests {
             framed,
             Ok(Some(ITFrame::Data {
                 mid: 7u64,
-                data: BytesMut::from(&b"foo"[..]),
+                data: Bytes::from_static(b"foo"),
             }))
         );
         //next = Invalid => Empty
         let framed = ITFrame::read_frame(&mut buffer);
-        assert_eq!(framed, Err(()));
+        assert_eq!(framed, Err(ProtocolError::UnknownFrame(b'b')));
     }
 }
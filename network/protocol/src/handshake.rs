@@ -1,13 +1,15 @@
 use crate::{
     error::{InitProtocolError, ProtocolError},
     frame::InitFrame,
+    noise::{ChannelCipher, NoiseHandshake, NoiseKeys},
     types::{
-        Pid, Sid, STREAM_ID_OFFSET1, STREAM_ID_OFFSET2, VELOREN_MAGIC_NUMBER,
+        Pid, Sid, VersionPolicy, STREAM_ID_OFFSET1, STREAM_ID_OFFSET2, VELOREN_MAGIC_NUMBER,
         VELOREN_NETWORK_VERSION,
     },
     InitProtocol,
 };
 use async_trait::async_trait;
+use bytes::Bytes;
 use tracing::{debug, error, info, trace};
 
 /// Implement this for auto Handshake with [`ReliableSink`].
@@ -22,6 +24,16 @@ use tracing::{debug, error, info, trace};
 #[async_trait]
 pub trait ReliableDrain {
     async fn send(&mut self, frame: InitFrame) -> Result<(), ProtocolError>;
+
+    /// Keys to encrypt the channel with if this side requires it. The
+    /// handshake fails if the remote side doesn't agree.
+    fn encryption(&self) -> Option<&NoiseKeys> { None }
+
+    /// Encrypts all frames sent after this with `cipher`, called once the
+    /// Noise handshake is done. Fails if the protocol can't encrypt.
+    fn start_encryption(&mut self, _cipher: ChannelCipher) -> Result<(), ProtocolError> {
+        Err(ProtocolError::Closed)
+    }
 }
 
 /// Implement this for auto Handshake with [`ReliableDrain`]. See
@@ -31,6 +43,12 @@ pub trait ReliableDrain {
 #[async_trait]
 pub trait ReliableSink {
     async fn recv(&mut self) -> Result<InitFrame, ProtocolError>;
+
+    /// Decrypts all frames received after this with `cipher`, see
+    /// [`ReliableDrain::start_encryption`].
+    fn start_encryption(&mut self, _cipher: ChannelCipher) -> Result<(), ProtocolError> {
+        Err(ProtocolError::Closed)
+    }
 }
 
 #[async_trait]
@@ -44,26 +62,27 @@ where
         initializer: bool,
         local_pid: Pid,
         local_secret: u128,
+        version_policy: VersionPolicy,
     ) -> Result<(Pid, Sid, u128), InitProtocolError> {
         #[cfg(debug_assertions)]
         const WRONG_NUMBER: &str = "Handshake does not contain the magic number required by \
                                     veloren server.\nWe are not sure if you are a valid veloren \
                                     client.\nClosing the connection";
-        #[cfg(debug_assertions)]
-        const WRONG_VERSION: &str = "Handshake does contain a correct magic number, but invalid \
-                                     version.\nWe don't know how to communicate with \
-                                     you.\nClosing the connection";
         const ERR_S: &str = "Got A Raw Message, these are usually Debug Messages indicating that \
                              something went wrong on network layer and connection will be closed";
 
         let drain = &mut self.0;
         let sink = &mut self.1;
+        let encrypted = drain.encryption().is_some();
 
-        if initializer {
+        // the connecting side opens, so a listener can tell transports apart by
+        // the first bytes it receives
+        if !initializer {
             drain
                 .send(InitFrame::Handshake {
                     magic_number: VELOREN_MAGIC_NUMBER,
                     version: VELOREN_NETWORK_VERSION,
+                    encrypted,
                 })
                 .await?;
         }
@@ -72,53 +91,73 @@ where
             InitFrame::Handshake {
                 magic_number,
                 version,
+                encrypted: remote_encrypted,
             } => {
                 trace!(?magic_number, ?version, "Recv handshake");
                 if magic_number != VELOREN_MAGIC_NUMBER {
                     error!(?magic_number, "Connection with invalid magic_number");
                     #[cfg(debug_assertions)]
                     drain
-                        .send(InitFrame::Raw(WRONG_NUMBER.as_bytes().to_vec()))
+                        .send(InitFrame::Raw(Bytes::from_static(WRONG_NUMBER.as_bytes())))
                         .await?;
                     Err(InitProtocolError::WrongMagicNumber(magic_number))
-                } else if version[0] != VELOREN_NETWORK_VERSION[0]
-                    || version[1] != VELOREN_NETWORK_VERSION[1]
+                } else if initializer
+                    && !version_policy.is_compatible(VELOREN_NETWORK_VERSION, version)
                 {
+                    // only the listening side decides about compatibility, the connecting
+                    // side learns about it via the reply
                     error!(?version, "Connection with wrong network version");
-                    #[cfg(debug_assertions)]
                     drain
-                        .send(InitFrame::Raw(
-                            format!(
-                                "{} Our Version: {:?}\nYour Version: {:?}\nClosing the connection",
-                                WRONG_VERSION, VELOREN_NETWORK_VERSION, version,
-                            )
-                            .as_bytes()
-                            .to_vec(),
-                        ))
+                        .send(InitFrame::VersionMismatch {
+                            server_version: VELOREN_NETWORK_VERSION,
+                            min_client: version_policy.min_compatible(VELOREN_NETWORK_VERSION),
+                        })
                         .await?;
                     Err(InitProtocolError::WrongVersion(version))
                 } else {
-                    trace!("Handshake Frame completed");
                     if initializer {
-                        drain
-                            .send(InitFrame::Init {
-                                pid: local_pid,
-                                secret: local_secret,
-                            })
-                            .await?;
-                    } else {
+                        // also sent on an encryption mismatch, so the remote knows why it fails
                         drain
                             .send(InitFrame::Handshake {
                                 magic_number: VELOREN_MAGIC_NUMBER,
                                 version: VELOREN_NETWORK_VERSION,
+                                encrypted,
                             })
                             .await?;
                     }
-                    Ok(())
+                    if remote_encrypted != encrypted {
+                        error!(
+                            ?encrypted,
+                            ?remote_encrypted,
+                            "Connection with different encryption"
+                        );
+                        Err(InitProtocolError::EncryptionMismatch {
+                            local: encrypted,
+                            remote: remote_encrypted,
+                        })
+                    } else {
+                        trace!("Handshake Frame completed");
+                        Ok(())
+                    }
                 }
             },
+            InitFrame::VersionMismatch {
+                server_version,
+                min_client,
+            } => {
+                error!(
+                    ?server_version,
+                    ?min_client,
+                    "Remote rejected our network version"
+                );
+                Err(InitProtocolError::VersionMismatch {
+                    server_version,
+                    min_client,
+                    client_version: VELOREN_NETWORK_VERSION,
+                })
+            },
             InitFrame::Raw(bytes) => {
-                match std::str::from_utf8(bytes.as_slice()) {
+                match std::str::from_utf8(&bytes) {
                     Ok(string) => error!(?string, ERR_S),
                     _ => error!(?bytes, ERR_S),
                 }
@@ -130,6 +169,21 @@ where
             },
         }?;
 
+        if encrypted {
+            let cipher = noise_handshake(initializer, drain, sink).await?;
+            drain.start_encryption(cipher.clone())?;
+            sink.start_encryption(cipher)?;
+            trace!("Channel is now encrypted");
+        }
+        if initializer {
+            drain
+                .send(InitFrame::Init {
+                    pid: local_pid,
+                    secret: local_secret,
+                })
+                .await?;
+        }
+
         match sink.recv().await? {
             InitFrame::Init { pid, secret } => {
                 debug!(?pid, "Participant send their ID");
@@ -148,7 +202,7 @@ where
                 Ok((pid, stream_id_offset, secret))
             },
             InitFrame::Raw(bytes) => {
-                match std::str::from_utf8(bytes.as_slice()) {
+                match std::str::from_utf8(&bytes) {
                     Ok(string) => error!(?string, ERR_S),
                     _ => error!(?bytes, ERR_S),
                 }
@@ -162,6 +216,35 @@ where
     }
 }
 
+/// Exchanges the `Noise` frames of the handshake
+async fn noise_handshake<D, S>(
+    initializer: bool,
+    drain: &mut D,
+    sink: &mut S,
+) -> Result<ChannelCipher, InitProtocolError>
+where
+    D: ReliableDrain + Send,
+    S: ReliableSink + Send,
+{
+    let keys = drain.encryption().cloned().unwrap_or_default();
+    let mut handshake = NoiseHandshake::new(initializer, &keys)?;
+    while !handshake.is_finished() {
+        if handshake.is_my_turn() {
+            let message = handshake.write()?;
+            drain.send(InitFrame::Noise(message)).await?;
+        } else {
+            match sink.recv().await? {
+                InitFrame::Noise(message) => handshake.read(&message)?,
+                _ => {
+                    info!("Noise handshake failed");
+                    return Err(InitProtocolError::Closed);
+                },
+            }
+        }
+    }
+    handshake.finish()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -170,7 +253,10 @@ mod tests {
     #[tokio::test]
     async fn handshake_drop_start() {
         let [mut p1, p2] = ac_bound(10, None);
-        let r1 = tokio::spawn(async move { p1.initialize(true, Pid::fake(2), 1337).await });
+        let r1 = tokio::spawn(async move {
+            p1.initialize(true, Pid::fake(2), 1337, VersionPolicy::default())
+                .await
+        });
         let r2 = tokio::spawn(async move {
             let _ = &p2;
             let _ = p2;
@@ -182,12 +268,15 @@ mod tests {
     #[tokio::test]
     async fn handshake_wrong_magic_number() {
         let [mut p1, mut p2] = ac_bound(10, None);
-        let r1 = tokio::spawn(async move { p1.initialize(true, Pid::fake(2), 1337).await });
+        let r1 = tokio::spawn(async move {
+            p1.initialize(true, Pid::fake(2), 1337, VersionPolicy::default())
+                .await
+        });
         let r2 = tokio::spawn(async move {
-            let _ = p2.1.recv().await?;
             p2.0.send(InitFrame::Handshake {
                 magic_number: *b"woopsie",
                 version: VELOREN_NETWORK_VERSION,
+                encrypted: false,
             })
             .await?;
             let _ = p2.1.recv().await?;
@@ -204,12 +293,15 @@ mod tests {
     #[tokio::test]
     async fn handshake_wrong_version() {
         let [mut p1, mut p2] = ac_bound(10, None);
-        let r1 = tokio::spawn(async move { p1.initialize(true, Pid::fake(2), 1337).await });
+        let r1 = tokio::spawn(async move {
+            p1.initialize(true, Pid::fake(2), 1337, VersionPolicy::default())
+                .await
+        });
         let r2 = tokio::spawn(async move {
-            let _ = p2.1.recv().await?;
             p2.0.send(InitFrame::Handshake {
                 magic_number: VELOREN_MAGIC_NUMBER,
                 version: [0, 1, 2],
+                encrypted: false,
             })
             .await?;
             let _ = p2.1.recv().await?;
@@ -222,18 +314,204 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn handshake_unexpected_raw() {
+    async fn handshake_outdated_client() {
+        let [mut p1, mut p2] = ac_bound(10, None);
+        let r1 = tokio::spawn(async move {
+            p1.0.send(InitFrame::Handshake {
+                magic_number: VELOREN_MAGIC_NUMBER,
+                version: [0, 1, 2],
+                encrypted: false,
+            })
+            .await?;
+            p1.1.recv().await.map_err(InitProtocolError::from)
+        });
+        let r2 = tokio::spawn(async move {
+            p2.initialize(true, Pid::fake(3), 42, VersionPolicy::default())
+                .await
+        });
+        let (r1, r2) = tokio::join!(r1, r2);
+        assert_eq!(
+            r1.unwrap(),
+            Ok(InitFrame::VersionMismatch {
+                server_version: VELOREN_NETWORK_VERSION,
+                min_client: [VELOREN_NETWORK_VERSION[0], VELOREN_NETWORK_VERSION[1], 0],
+            })
+        );
+        assert_eq!(r2.unwrap(), Err(InitProtocolError::WrongVersion([0, 1, 2])));
+    }
+
+    #[tokio::test]
+    async fn handshake_exact_version_policy() {
+        let patched = [
+            VELOREN_NETWORK_VERSION[0],
+            VELOREN_NETWORK_VERSION[1],
+            VELOREN_NETWORK_VERSION[2] + 1,
+        ];
+        assert!(VersionPolicy::MajorMinor.is_compatible(VELOREN_NETWORK_VERSION, patched));
+        let [mut p1, mut p2] = ac_bound(10, None);
+        let r1 = tokio::spawn(async move {
+            p1.0.send(InitFrame::Handshake {
+                magic_number: VELOREN_MAGIC_NUMBER,
+                version: patched,
+                encrypted: false,
+            })
+            .await?;
+            p1.1.recv().await.map_err(InitProtocolError::from)
+        });
+        let r2 = tokio::spawn(async move {
+            p2.initialize(true, Pid::fake(3), 42, VersionPolicy::Exact)
+                .await
+        });
+        let (r1, r2) = tokio::join!(r1, r2);
+        assert_eq!(
+            r1.unwrap(),
+            Ok(InitFrame::VersionMismatch {
+                server_version: VELOREN_NETWORK_VERSION,
+                min_client: VELOREN_NETWORK_VERSION,
+            })
+        );
+        assert_eq!(r2.unwrap(), Err(InitProtocolError::WrongVersion(patched)));
+
+        // the same remote is accepted by a listener that only checks major and minor
+        let [mut p1, mut p2] = ac_bound(10, None);
+        let r1 = tokio::spawn(async move {
+            p1.0.send(InitFrame::Handshake {
+                magic_number: VELOREN_MAGIC_NUMBER,
+                version: patched,
+                encrypted: false,
+            })
+            .await?;
+            let _ = p1.1.recv().await?;
+            let _ = p1.1.recv().await?;
+            p1.0.send(InitFrame::Init {
+                pid: Pid::fake(2),
+                secret: 1337,
+            })
+            .await?;
+            Result::<(), InitProtocolError>::Ok(())
+        });
+        let r2 = tokio::spawn(async move {
+            p2.initialize(true, Pid::fake(3), 42, VersionPolicy::MajorMinor)
+                .await
+        });
+        let (r1, r2) = tokio::join!(r1, r2);
+        assert_eq!(r1.unwrap(), Ok(()));
+        assert_eq!(r2.unwrap(), Ok((Pid::fake(2), STREAM_ID_OFFSET1, 1337)));
+    }
+
+    #[tokio::test]
+    async fn handshake_encrypted_remote() {
+        let [mut p1, mut p2] = ac_bound(10, None);
+        let r1 = tokio::spawn(async move {
+            p1.initialize(true, Pid::fake(2), 1337, VersionPolicy::default())
+                .await
+        });
+        let r2 = tokio::spawn(async move {
+            p2.0.send(InitFrame::Handshake {
+                magic_number: VELOREN_MAGIC_NUMBER,
+                version: VELOREN_NETWORK_VERSION,
+                encrypted: true,
+            })
+            .await?;
+            let _ = p2.1.recv().await?;
+            let _ = p2.1.recv().await?; //this should be closed now
+            Ok(())
+        });
+        let (r1, r2) = tokio::join!(r1, r2);
+        assert_eq!(
+            r1.unwrap(),
+            Err(InitProtocolError::EncryptionMismatch {
+                local: false,
+                remote: true,
+            })
+        );
+        assert_eq!(r2.unwrap(), Err(InitProtocolError::Closed));
+    }
+
+    #[tokio::test]
+    async fn handshake_rejected_by_server() {
         let [mut p1, mut p2] = ac_bound(10, None);
-        let r1 = tokio::spawn(async move { p1.initialize(true, Pid::fake(2), 1337).await });
+        let r1 = tokio::spawn(async move {
+            p1.initialize(false, Pid::fake(2), 1337, VersionPolicy::default())
+                .await
+        });
+        let r2 = tokio::spawn(async move {
+            let _ = p2.1.recv().await?;
+            p2.0.send(InitFrame::VersionMismatch {
+                server_version: [0, 9, 1],
+                min_client: [0, 9, 0],
+            })
+            .await?;
+            Result::<(), InitProtocolError>::Ok(())
+        });
+        let (r1, r2) = tokio::join!(r1, r2);
+        assert_eq!(
+            r1.unwrap(),
+            Err(InitProtocolError::VersionMismatch {
+                server_version: [0, 9, 1],
+                min_client: [0, 9, 0],
+                client_version: VELOREN_NETWORK_VERSION,
+            })
+        );
+        assert_eq!(r2.unwrap(), Ok(()));
+    }
+
+    #[tokio::test]
+    async fn handshake_client_leaves_version_check_to_server() {
+        let patched = [
+            VELOREN_NETWORK_VERSION[0],
+            VELOREN_NETWORK_VERSION[1],
+            VELOREN_NETWORK_VERSION[2] + 1,
+        ];
+        let [mut p1, mut p2] = ac_bound(10, None);
+        let r1 = tokio::spawn(async move {
+            p1.initialize(false, Pid::fake(2), 1337, VersionPolicy::Exact)
+                .await
+        });
         let r2 = tokio::spawn(async move {
             let _ = p2.1.recv().await?;
+            p2.0.send(InitFrame::Handshake {
+                magic_number: VELOREN_MAGIC_NUMBER,
+                version: patched,
+                encrypted: false,
+            })
+            .await?;
+            p2.0.send(InitFrame::Init {
+                pid: Pid::fake(3),
+                secret: 42,
+            })
+            .await?;
+            p2.1.recv().await.map_err(InitProtocolError::from)
+        });
+        let (r1, r2) = tokio::join!(r1, r2);
+        assert_eq!(r1.unwrap(), Ok((Pid::fake(3), STREAM_ID_OFFSET2, 42)));
+        assert_eq!(
+            r2.unwrap(),
+            Ok(InitFrame::Init {
+                pid: Pid::fake(2),
+                secret: 1337,
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn handshake_unexpected_raw() {
+        let [mut p1, mut p2] = ac_bound(10, None);
+        let r1 = tokio::spawn(async move {
+            p1.initialize(true, Pid::fake(2), 1337, VersionPolicy::default())
+                .await
+        });
+        let r2 = tokio::spawn(async move {
             p2.0.send(InitFrame::Handshake {
                 magic_number: VELOREN_MAGIC_NUMBER,
                 version: VELOREN_NETWORK_VERSION,
+                encrypted: false,
             })
             .await?;
             let _ = p2.1.recv().await?;
-            p2.0.send(InitFrame::Raw(b"Hello World".to_vec())).await?;
+            let _ = p2.1.recv().await?;
+            p2.0.send(InitFrame::Raw(Bytes::from_static(b"Hello World")))
+                .await?;
             Result::<(), InitProtocolError>::Ok(())
         });
         let (r1, r2) = tokio::join!(r1, r2);
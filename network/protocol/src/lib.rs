@@ -15,8 +15,7 @@
 //!  - TCP
 //!  - MPSC
 //!  - QUIC
-//!
-//! eventually a pure UDP implementation will follow
+//!  - UDP
 //!
 //! warning: don't mix protocol, using the TCP variant for actual UDP socket
 //! will result in dropped data  using UDP with a TCP socket will be a waste of
@@ -57,26 +56,37 @@ mod handshake;
 mod message;
 mod metrics;
 mod mpsc;
+mod noise;
 mod prio;
 mod quic;
+mod rtt;
+#[cfg(test)] mod sim;
 mod tcp;
 mod types;
+mod udp;
 mod util;
 
 pub use error::{InitProtocolError, ProtocolError};
 pub use event::ProtocolEvent;
-pub use metrics::ProtocolMetricCache;
+pub use frame::FrameObserver;
 #[cfg(feature = "metrics")]
 pub use metrics::ProtocolMetrics;
+pub use metrics::{ProtocolMetricCache, StreamObserver};
 pub use mpsc::{MpscMsg, MpscRecvProtocol, MpscSendProtocol};
+pub use noise::{ChannelCipher, NoiseKeys, NoisePublicKey, ParseKeyError};
 pub use quic::{QuicDataFormat, QuicDataFormatStream, QuicRecvProtocol, QuicSendProtocol};
+pub use rtt::{ChannelRtt, RttEstimator};
 pub use tcp::{TcpRecvProtocol, TcpSendProtocol};
-pub use types::{Bandwidth, Cid, Pid, Prio, Promises, Sid, HIGHEST_PRIO, VELOREN_NETWORK_VERSION};
+pub use types::{
+    Bandwidth, Cid, KeepAlive, Pid, Prio, Promises, Sid, VersionPolicy, DEFAULT_STREAM_WINDOW,
+    HIGHEST_PRIO, VELOREN_NETWORK_VERSION,
+};
+pub use udp::{is_udp_handshake, UdpAcks, UdpRecvProtocol, UdpSendProtocol};
 
 ///use at own risk, might change any time, for internal benchmarks
 pub mod _internal {
     pub use crate::{
-        frame::{ITFrame, OTFrame},
+        frame::{ITFrame, InitFrame, OTFrame},
         util::SortedVec,
     };
 }
@@ -84,6 +94,9 @@ pub mod _internal {
 use async_trait::async_trait;
 
 /// Handshake: Used to connect 2 Channels.
+///
+/// The connecting side (`initializer: false`) sends the first frame, the
+/// listening side (`initializer: true`) validates and answers it.
 #[async_trait]
 pub trait InitProtocol {
     async fn initialize(
@@ -91,6 +104,7 @@ pub trait InitProtocol {
         initializer: bool,
         local_pid: Pid,
         secret: u128,
+        version_policy: VersionPolicy,
     ) -> Result<(Pid, Sid, u128), InitProtocolError>;
 }
 
@@ -24,9 +24,14 @@ pub(crate) struct OTMessage {
     start: u64, /* remove */
 }
 
+/// Contains a incoming message for all protocols. The fragments are slices
+/// of the read buffer, a message that arrives in one fragment is handed out as
+/// is, bigger ones are copied into a single allocation.
 #[derive(Debug)]
 pub(crate) struct ITMessage {
-    pub data: BytesMut,
+    data: BytesMut,
+    /// kept as is until a second fragment arrives
+    first: Option<Bytes>,
     pub sid: Sid,
     pub length: u64,
 }
@@ -77,6 +82,9 @@ impl OTMessage {
         }
     }
 
+    /// whether the header was sent already
+    pub(crate) fn is_started(&self) -> bool { self.send_header }
+
     pub(crate) fn get_sid_len(&self) -> (Sid, u64) { (self.sid, self.original_length) }
 }
 
@@ -87,7 +95,37 @@ impl ITMessage {
         Self {
             sid,
             length,
-            data: BytesMut::with_capacity((length as usize).min(ALLOC_BLOCK /* anti-ddos */)),
+            data: BytesMut::new(),
+            first: None,
+        }
+    }
+
+    /// bytes received so far
+    pub(crate) fn len(&self) -> usize {
+        self.first
+            .as_ref()
+            .map_or(self.data.len(), |first| first.len())
+    }
+
+    pub(crate) fn is_complete(&self) -> bool { self.len() == self.length as usize }
+
+    pub(crate) fn push(&mut self, fragment: Bytes) {
+        if self.first.is_none() && self.data.is_empty() {
+            self.first = Some(fragment);
+            return;
+        }
+        if let Some(first) = self.first.take() {
+            self.data
+                .reserve((self.length as usize).min(ALLOC_BLOCK /* anti-ddos */));
+            self.data.extend_from_slice(&first);
+        }
+        self.data.extend_from_slice(&fragment);
+    }
+
+    pub(crate) fn into_data(self) -> Bytes {
+        match self.first {
+            Some(first) => first,
+            None => self.data.freeze(),
         }
     }
 }
@@ -184,6 +222,39 @@ impl OUMessage {
         }
     }
 
+    /// whether the header was sent already
+    pub(crate) fn is_started(&self) -> bool { self.send_header }
+
     pub(crate) fn get_sid_len(&self) -> (Sid, u64) { (self.sid, self.buffer.data.len() as u64) }
 }
 */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn single_fragment_is_not_copied() {
+        let fragment = Bytes::from(vec![1u8; 100]);
+        let mut msg = ITMessage::new(Sid::new(1), 100, &mut BytesMut::new());
+        msg.push(fragment.clone());
+        assert!(msg.is_complete());
+        let data = msg.into_data();
+        assert_eq!(data.as_ptr(), fragment.as_ptr());
+    }
+
+    #[test]
+    fn fragments_are_assembled() {
+        let mut msg = ITMessage::new(Sid::new(1), 3000, &mut BytesMut::new());
+        for (i, len) in [1400, 1400, 200].iter().enumerate() {
+            assert!(!msg.is_complete());
+            msg.push(Bytes::from(vec![i as u8; *len]));
+        }
+        assert!(msg.is_complete());
+        let data = msg.into_data();
+        assert_eq!(data.len(), 3000);
+        assert_eq!(data[1399], 0);
+        assert_eq!(data[1400], 1);
+        assert_eq!(data[2999], 2);
+    }
+}
@@ -1,3 +1,5 @@
+#[cfg(feature = "metrics")]
+use crate::frame::FRAME_NAMES;
 use crate::types::Sid;
 #[cfg(feature = "metrics")]
 use prometheus::{
@@ -6,7 +8,18 @@ use prometheus::{
 };
 #[cfg(feature = "metrics")]
 use std::collections::HashMap;
-use std::{error::Error, sync::Arc};
+use std::{error::Error, sync::Arc, time::Duration};
+
+/// Gets told about the messages of every stream of a channel, as the protocol
+/// handles their data header and data frames. Allows counting the traffic of
+/// single streams instead of whole channels, see
+/// [`ProtocolMetricCache::set_stream_observer`].
+pub trait StreamObserver: std::fmt::Debug + Send + Sync {
+    /// A message of `bytes` was queued to be sent
+    fn message_sent(&self, sid: Sid, bytes: u64);
+    /// A message of `bytes` was received completely
+    fn message_received(&self, sid: Sid, bytes: u64);
+}
 
 #[allow(dead_code)]
 pub enum RemoveReason {
@@ -56,8 +69,27 @@ pub struct ProtocolMetrics {
     rdata_frames_t: IntCounterVec,
     /// data frames bytes send by prio by CHANNEL,
     rdata_frames_b: IntCounterVec,
-    /// ping per CHANNEL //TODO: implement
+    /// smoothed round trip time in microseconds per CHANNEL
     ping: IntGaugeVec,
+
+    // based on CHANNEL/PROTOCOL/DIRECTION/FRAME
+    /// frames send or received total, by FRAME type,
+    frames_t: IntCounterVec,
+    /// frame bytes send or received throughput, including frame header, by
+    /// FRAME type,
+    frames_b: IntCounterVec,
+    /// received frames or datagrams that were dropped because they could not
+    /// be parsed, by CHANNEL,
+    malformed_frames: IntCounterVec,
+    /// received datagrams that were dropped because their checksum didn't
+    /// match, by CHANNEL,
+    corrupted_datagrams: IntCounterVec,
+    /// received datagrams that were dropped because they were already
+    /// received, by CHANNEL,
+    duplicated_datagrams: IntCounterVec,
+    /// received frames that were dropped because the receiver couldn't buffer
+    /// them, by CHANNEL,
+    dropped_frames: IntCounterVec,
 }
 
 /// Cache for [`ProtocolMetrics`], more optimized and cleared up after channel
@@ -68,14 +100,21 @@ pub struct ProtocolMetrics {
 #[derive(Debug, Clone)]
 pub struct ProtocolMetricCache {
     cid: String,
+    protocol: &'static str,
     m: Arc<ProtocolMetrics>,
     cache: HashMap<Sid, CacheLine>,
+    /// send and recv [`FrameLine`]s, indexed by frame id
+    frames: [[Option<FrameLine>; FRAME_NAMES.len()]; 2],
     sdata_frames_t: GenericCounter<AtomicU64>,
     sdata_frames_b: GenericCounter<AtomicU64>,
     rdata_frames_t: GenericCounter<AtomicU64>,
     rdata_frames_b: GenericCounter<AtomicU64>,
-    #[allow(dead_code)]
+    malformed_frames: GenericCounter<AtomicU64>,
+    corrupted_datagrams: GenericCounter<AtomicU64>,
+    duplicated_datagrams: GenericCounter<AtomicU64>,
+    dropped_frames: GenericCounter<AtomicU64>,
     ping: GenericGauge<AtomicI64>,
+    streams: Option<Arc<dyn StreamObserver>>,
 }
 
 #[cfg(not(feature = "metrics"))]
@@ -174,7 +213,56 @@ impl ProtocolMetrics {
             ),
             &["channel"],
         )?;
-        let ping = IntGaugeVec::new(Opts::new("ping", "Ping per channel"), &["channel"])?;
+        let ping = IntGaugeVec::new(
+            Opts::new(
+                "ping",
+                "Smoothed round trip time per channel in microseconds",
+            ),
+            &["channel"],
+        )?;
+        let frames_t = IntCounterVec::new(
+            Opts::new(
+                "frames_total",
+                "Number of frames send/received per channel, protocol and frame type",
+            ),
+            &["channel", "protocol", "direction", "frame"],
+        )?;
+        let frames_b = IntCounterVec::new(
+            Opts::new(
+                "frames_throughput",
+                "Number of frame bytes, including the frame header, send/received per channel, \
+                 protocol and frame type",
+            ),
+            &["channel", "protocol", "direction", "frame"],
+        )?;
+        let malformed_frames = IntCounterVec::new(
+            Opts::new(
+                "malformed_frames_total",
+                "Number of received frames or datagrams dropped because they could not be parsed",
+            ),
+            &["channel"],
+        )?;
+        let corrupted_datagrams = IntCounterVec::new(
+            Opts::new(
+                "corrupted_datagrams_total",
+                "Number of received datagrams dropped because their checksum didn't match",
+            ),
+            &["channel"],
+        )?;
+        let duplicated_datagrams = IntCounterVec::new(
+            Opts::new(
+                "duplicated_datagrams_total",
+                "Number of received datagrams dropped because they were already received",
+            ),
+            &["channel"],
+        )?;
+        let dropped_frames = IntCounterVec::new(
+            Opts::new(
+                "dropped_frames_total",
+                "Number of received frames dropped because the receiver couldn't buffer them",
+            ),
+            &["channel"],
+        )?;
 
         Ok(Self {
             smsg_it,
@@ -190,6 +278,12 @@ impl ProtocolMetrics {
             rdata_frames_t,
             rdata_frames_b,
             ping,
+            frames_t,
+            frames_b,
+            malformed_frames,
+            corrupted_datagrams,
+            duplicated_datagrams,
+            dropped_frames,
         })
     }
 
@@ -207,6 +301,12 @@ impl ProtocolMetrics {
         registry.register(Box::new(self.rdata_frames_t.clone()))?;
         registry.register(Box::new(self.rdata_frames_b.clone()))?;
         registry.register(Box::new(self.ping.clone()))?;
+        registry.register(Box::new(self.frames_t.clone()))?;
+        registry.register(Box::new(self.frames_b.clone()))?;
+        registry.register(Box::new(self.malformed_frames.clone()))?;
+        registry.register(Box::new(self.corrupted_datagrams.clone()))?;
+        registry.register(Box::new(self.duplicated_datagrams.clone()))?;
+        registry.register(Box::new(self.dropped_frames.clone()))?;
         Ok(())
     }
 }
@@ -227,6 +327,20 @@ pub(crate) struct CacheLine {
     pub rmsg_ob: [GenericCounter<AtomicU64>; 2],
 }
 
+#[cfg(feature = "metrics")]
+#[derive(Debug, Clone)]
+pub(crate) struct FrameLine {
+    pub t: GenericCounter<AtomicU64>,
+    pub b: GenericCounter<AtomicU64>,
+}
+
+#[cfg(feature = "metrics")]
+const SEND: usize = 0;
+#[cfg(feature = "metrics")]
+const RECV: usize = 1;
+#[cfg(feature = "metrics")]
+const DIRECTIONS: [&str; 2] = ["send", "recv"];
+
 #[cfg(feature = "metrics")]
 impl ProtocolMetricCache {
     pub fn new(channel_key: &str, metrics: Arc<ProtocolMetrics>) -> Self {
@@ -235,19 +349,73 @@ impl ProtocolMetricCache {
         let sdata_frames_b = metrics.sdata_frames_b.with_label_values(&[&cid]);
         let rdata_frames_t = metrics.rdata_frames_t.with_label_values(&[&cid]);
         let rdata_frames_b = metrics.rdata_frames_b.with_label_values(&[&cid]);
+        let malformed_frames = metrics.malformed_frames.with_label_values(&[&cid]);
+        let corrupted_datagrams = metrics.corrupted_datagrams.with_label_values(&[&cid]);
+        let duplicated_datagrams = metrics.duplicated_datagrams.with_label_values(&[&cid]);
+        let dropped_frames = metrics.dropped_frames.with_label_values(&[&cid]);
         let ping = metrics.ping.with_label_values(&[&cid]);
         Self {
             cid,
+            protocol: "unknown",
             m: metrics,
             cache: HashMap::new(),
+            frames: Default::default(),
             sdata_frames_t,
             sdata_frames_b,
             rdata_frames_t,
             rdata_frames_b,
+            malformed_frames,
+            corrupted_datagrams,
+            duplicated_datagrams,
+            dropped_frames,
             ping,
+            streams: None,
+        }
+    }
+
+    /// Reports the messages of every stream to `observer` as well
+    pub fn set_stream_observer(&mut self, observer: Arc<dyn StreamObserver>) {
+        self.streams = Some(observer);
+    }
+
+    pub(crate) fn message_sent(&self, sid: Sid, bytes: u64) {
+        if let Some(streams) = &self.streams {
+            streams.message_sent(sid, bytes);
         }
     }
 
+    pub(crate) fn message_received(&self, sid: Sid, bytes: u64) {
+        if let Some(streams) = &self.streams {
+            streams.message_received(sid, bytes);
+        }
+    }
+
+    /// Sets the `protocol` label of the frame metrics, e.g. "tcp". Needs to be
+    /// called before any frame is send or received.
+    #[must_use]
+    pub fn with_protocol(mut self, protocol: &'static str) -> Self {
+        self.protocol = protocol;
+        self
+    }
+
+    fn frame_line(&mut self, direction: usize, frame: u8) -> &FrameLine {
+        let cid = &self.cid;
+        let protocol = self.protocol;
+        let m = &self.m;
+        self.frames[direction][frame as usize].get_or_insert_with(|| {
+            let labels = [
+                cid.as_str(),
+                protocol,
+                DIRECTIONS[direction],
+                FRAME_NAMES[frame as usize],
+            ];
+            FrameLine {
+                t: m.frames_t.with_label_values(&labels),
+                b: m.frames_b.with_label_values(&labels),
+            }
+        })
+    }
+
     pub(crate) fn init_sid(&mut self, sid: Sid) -> &CacheLine {
         let cid = &self.cid;
         let m = &self.m;
@@ -281,6 +449,7 @@ impl ProtocolMetricCache {
     }
 
     pub(crate) fn smsg_ib(&mut self, sid: Sid, bytes: u64) {
+        self.message_sent(sid, bytes);
         let line = self.init_sid(sid);
         line.smsg_it.inc();
         line.smsg_ib.inc_by(bytes);
@@ -297,6 +466,18 @@ impl ProtocolMetricCache {
         self.sdata_frames_b.inc_by(bytes);
     }
 
+    pub(crate) fn sframes_b(&mut self, frame: u8, bytes: u64) {
+        let line = self.frame_line(SEND, frame);
+        line.t.inc();
+        line.b.inc_by(bytes);
+    }
+
+    pub(crate) fn rframes_b(&mut self, frame: u8, bytes: u64) {
+        let line = self.frame_line(RECV, frame);
+        line.t.inc();
+        line.b.inc_by(bytes);
+    }
+
     pub(crate) fn rmsg_ib(&mut self, sid: Sid, bytes: u64) {
         let line = self.init_sid(sid);
         line.rmsg_it.inc();
@@ -304,6 +485,9 @@ impl ProtocolMetricCache {
     }
 
     pub(crate) fn rmsg_ob(&mut self, sid: Sid, reason: RemoveReason, bytes: u64) {
+        if let RemoveReason::Finished = reason {
+            self.message_received(sid, bytes);
+        }
         let line = self.init_sid(sid);
         line.rmsg_ot[reason.i()].inc();
         line.rmsg_ob[reason.i()].inc_by(bytes);
@@ -314,6 +498,16 @@ impl ProtocolMetricCache {
         self.rdata_frames_b.inc_by(bytes);
     }
 
+    pub(crate) fn malformed_frame(&mut self) { self.malformed_frames.inc(); }
+
+    pub(crate) fn corrupted_datagram(&mut self) { self.corrupted_datagrams.inc(); }
+
+    pub(crate) fn duplicated_datagram(&mut self) { self.duplicated_datagrams.inc(); }
+
+    pub(crate) fn dropped_frame(&mut self) { self.dropped_frames.inc(); }
+
+    pub(crate) fn ping(&mut self, rtt: Duration) { self.ping.set(rtt.as_micros() as i64); }
+
     #[cfg(test)]
     pub(crate) fn assert_msg(&mut self, sid: Sid, cnt: u64, reason: RemoveReason) {
         let line = self.init_sid(sid);
@@ -343,6 +537,27 @@ impl ProtocolMetricCache {
         assert_eq!(self.sdata_frames_b.get(), bytes);
         assert_eq!(self.rdata_frames_b.get(), bytes);
     }
+
+    #[cfg(test)]
+    pub(crate) fn assert_frames(&mut self, frame: &'static str, cnt: u64, bytes: u64) {
+        let frame = FRAME_NAMES.iter().position(|n| *n == frame).unwrap() as u8;
+        let line = self.frame_line(SEND, frame);
+        assert_eq!((line.t.get(), line.b.get()), (cnt, bytes));
+        let line = self.frame_line(RECV, frame);
+        assert_eq!((line.t.get(), line.b.get()), (cnt, bytes));
+    }
+
+    #[cfg(test)]
+    pub(crate) fn malformed_frames(&self) -> u64 { self.malformed_frames.get() }
+
+    #[cfg(test)]
+    pub(crate) fn corrupted_datagrams(&self) -> u64 { self.corrupted_datagrams.get() }
+
+    #[cfg(test)]
+    pub(crate) fn duplicated_datagrams(&self) -> u64 { self.duplicated_datagrams.get() }
+
+    #[cfg(test)]
+    pub(crate) fn dropped_frames(&self) -> u64 { self.dropped_frames.get() }
 }
 
 #[cfg(feature = "metrics")]
@@ -367,11 +582,29 @@ impl Drop for ProtocolMetricCache {
             let _ = m.rmsg_ob.remove_label_values(&[cid, &s, finished]);
             let _ = m.rmsg_ob.remove_label_values(&[cid, &s, dropped]);
         }
+        for (direction, lines) in self.frames.iter_mut().enumerate() {
+            for (frame, line) in lines.iter_mut().enumerate() {
+                if line.take().is_some() {
+                    let labels = [
+                        cid.as_str(),
+                        self.protocol,
+                        DIRECTIONS[direction],
+                        FRAME_NAMES[frame],
+                    ];
+                    let _ = m.frames_t.remove_label_values(&labels);
+                    let _ = m.frames_b.remove_label_values(&labels);
+                }
+            }
+        }
         let _ = m.ping.remove_label_values(&[cid]);
         let _ = m.sdata_frames_t.remove_label_values(&[cid]);
         let _ = m.sdata_frames_b.remove_label_values(&[cid]);
         let _ = m.rdata_frames_t.remove_label_values(&[cid]);
         let _ = m.rdata_frames_b.remove_label_values(&[cid]);
+        let _ = m.malformed_frames.remove_label_values(&[cid]);
+        let _ = m.corrupted_datagrams.remove_label_values(&[cid]);
+        let _ = m.duplicated_datagrams.remove_label_values(&[cid]);
+        let _ = m.dropped_frames.remove_label_values(&[cid]);
     }
 }
 
@@ -387,17 +620,36 @@ impl std::fmt::Debug for ProtocolMetrics {
 impl ProtocolMetricCache {
     pub fn new(_channel_key: &str, _metrics: Arc<ProtocolMetrics>) -> Self { Self {} }
 
+    #[must_use]
+    pub fn with_protocol(self, _protocol: &'static str) -> Self { self }
+
+    pub fn set_stream_observer(&mut self, _observer: Arc<dyn StreamObserver>) {}
+
     pub(crate) fn smsg_ib(&mut self, _sid: Sid, _b: u64) {}
 
     pub(crate) fn smsg_ob(&mut self, _sid: Sid, _reason: RemoveReason, _b: u64) {}
 
     pub(crate) fn sdata_frames_b(&mut self, _cnt: u64, _b: u64) {}
 
+    pub(crate) fn sframes_b(&mut self, _frame: u8, _b: u64) {}
+
+    pub(crate) fn rframes_b(&mut self, _frame: u8, _b: u64) {}
+
     pub(crate) fn rmsg_ib(&mut self, _sid: Sid, _b: u64) {}
 
     pub(crate) fn rmsg_ob(&mut self, _sid: Sid, _reason: RemoveReason, _b: u64) {}
 
     pub(crate) fn rdata_frames_b(&mut self, _b: u64) {}
+
+    pub(crate) fn malformed_frame(&mut self) {}
+
+    pub(crate) fn corrupted_datagram(&mut self) {}
+
+    pub(crate) fn duplicated_datagram(&mut self) {}
+
+    pub(crate) fn dropped_frame(&mut self) {}
+
+    pub(crate) fn ping(&mut self, _rtt: Duration) {}
 }
 
 #[cfg(not(feature = "metrics"))]
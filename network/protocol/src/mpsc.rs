@@ -5,12 +5,15 @@ use crate::{
     event::ProtocolEvent,
     frame::InitFrame,
     handshake::{ReliableDrain, ReliableSink},
-    metrics::ProtocolMetricCache,
+    metrics::{ProtocolMetricCache, StreamObserver},
     types::{Bandwidth, Promises},
     RecvProtocol, SendProtocol, UnreliableDrain, UnreliableSink,
 };
 use async_trait::async_trait;
-use std::time::{Duration, Instant};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
 #[cfg(feature = "trace_pedantic")]
 use tracing::trace;
 
@@ -68,6 +71,12 @@ where
             | Promises::COMPRESSED
             | Promises::ENCRYPTED /*assume a direct mpsc connection is secure*/
     }
+
+    /// Reports the messages of every stream to `observer`, see
+    /// [`StreamObserver`]
+    pub fn set_stream_observer(&mut self, observer: Arc<dyn StreamObserver>) {
+        self.metrics.set_stream_observer(observer);
+    }
 }
 
 impl<S> MpscRecvProtocol<S>
@@ -75,6 +84,12 @@ where
     S: UnreliableSink<DataFormat = MpscMsg>,
 {
     pub fn new(sink: S, metrics: ProtocolMetricCache) -> Self { Self { sink, metrics } }
+
+    /// Reports the messages of every stream to `observer`, see
+    /// [`StreamObserver`]
+    pub fn set_stream_observer(&mut self, observer: Arc<dyn StreamObserver>) {
+        self.metrics.set_stream_observer(observer);
+    }
 }
 
 #[async_trait]
@@ -96,6 +111,7 @@ where
                 let (bytes, line) = {
                     let sid = *_sid;
                     let bytes = _data.len() as u64;
+                    self.metrics.message_sent(sid, bytes);
                     let line = self.metrics.init_sid(sid);
                     line.smsg_it.inc();
                     line.smsg_ib.inc_by(bytes);
@@ -134,6 +150,7 @@ where
                     if let ProtocolEvent::Message { data, sid } = &e {
                         let sid = *sid;
                         let bytes = data.len() as u64;
+                        self.metrics.message_received(sid, bytes);
                         let line = self.metrics.init_sid(sid);
                         line.rmsg_it.inc();
                         line.rmsg_ib.inc_by(bytes);
@@ -236,15 +253,21 @@ pub mod test_utils {
 mod tests {
     use crate::{
         mpsc::test_utils::*,
-        types::{Pid, STREAM_ID_OFFSET1, STREAM_ID_OFFSET2},
+        types::{Pid, VersionPolicy, STREAM_ID_OFFSET1, STREAM_ID_OFFSET2},
         InitProtocol,
     };
 
     #[tokio::test]
     async fn handshake_all_good() {
         let [mut p1, mut p2] = ac_bound(10, None);
-        let r1 = tokio::spawn(async move { p1.initialize(true, Pid::fake(2), 1337).await });
-        let r2 = tokio::spawn(async move { p2.initialize(false, Pid::fake(3), 42).await });
+        let r1 = tokio::spawn(async move {
+            p1.initialize(true, Pid::fake(2), 1337, VersionPolicy::default())
+                .await
+        });
+        let r2 = tokio::spawn(async move {
+            p2.initialize(false, Pid::fake(3), 42, VersionPolicy::default())
+                .await
+        });
         let (r1, r2) = tokio::join!(r1, r2);
         assert_eq!(r1.unwrap(), Ok((Pid::fake(3), STREAM_ID_OFFSET1, 42)));
         assert_eq!(r2.unwrap(), Ok((Pid::fake(2), STREAM_ID_OFFSET2, 1337)));
@@ -0,0 +1,315 @@
+//! Encryption of channels
+//!
+//! If both sides set the `encrypted` flag of their `Handshake`, a Noise XX
+//! handshake is done right after it. Its messages are sent as `Noise` frames,
+//! afterwards both sides have a [`ChannelCipher`] with a key for each direction
+//! of the channel. All further frames, starting with the `Init`, are encrypted
+//! and authenticated with it by the protocol.
+//!
+//! By default each side generates a new static key for every channel, so the
+//! handshake protects against eavesdropping and tampering, but doesn't
+//! authenticate the remote side. A server sets a static private key with
+//! [`NoiseKeys::with_private_key`] and publishes its [`NoisePublicKey`],
+//! clients pin it with [`NoiseKeys::with_remote_key`] so that nobody in the
+//! middle can pose as the server.
+use crate::error::InitProtocolError;
+use bytes::{Bytes, BytesMut};
+use snow::{
+    params::{DHChoice, NoiseParams},
+    resolvers::{CryptoResolver, DefaultResolver},
+    HandshakeState, StatelessTransportState,
+};
+use std::{fmt, str::FromStr, sync::Arc};
+use tracing::info;
+
+const NOISE_PARAMS: &str = "Noise_XX_25519_ChaChaPoly_BLAKE2s";
+/// Bytes the ciphertext is longer than the plaintext
+pub const TAG_LEN: usize = 16;
+/// Longest message of the Noise protocol
+const MAX_MESSAGE: usize = 65535;
+/// Longest plaintext that can be encrypted at once
+pub const MAX_PLAINTEXT: usize = MAX_MESSAGE - TAG_LEN;
+/// Longest handshake message, the messages of the XX pattern are shorter
+const MAX_HANDSHAKE_MESSAGE: usize = 256;
+/// Length of the private and public Curve25519 keys
+pub const KEY_LEN: usize = 32;
+
+/// Static public key of one side of encrypted channels
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct NoisePublicKey(pub [u8; KEY_LEN]);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParseKeyError;
+
+/// Lowercase hex, the format accepted by [`FromStr`]
+impl fmt::Display for NoisePublicKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.iter().try_for_each(|b| write!(f, "{:02x}", b))
+    }
+}
+
+impl FromStr for NoisePublicKey {
+    type Err = ParseKeyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hex = s.trim();
+        if hex.len() != KEY_LEN * 2 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(ParseKeyError);
+        }
+        let mut key = [0u8; KEY_LEN];
+        for (i, byte) in key.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).map_err(|_| ParseKeyError)?;
+        }
+        Ok(Self(key))
+    }
+}
+
+impl fmt::Display for ParseKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "expected a public key of {} hex digits", KEY_LEN * 2)
+    }
+}
+
+impl std::error::Error for ParseKeyError {}
+
+/// Static keys one side uses in the handshake of its encrypted channels
+#[derive(Clone, Default)]
+pub struct NoiseKeys {
+    private: Option<[u8; KEY_LEN]>,
+    remote: Option<NoisePublicKey>,
+}
+
+impl fmt::Debug for NoiseKeys {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NoiseKeys")
+            .field("public", &self.public_key())
+            .field("remote", &self.remote)
+            .finish()
+    }
+}
+
+impl NoiseKeys {
+    /// Generates a new private key, to be stored and passed to
+    /// [`with_private_key`] on every start.
+    ///
+    /// [`with_private_key`]: Self::with_private_key
+    pub fn generate_private_key() -> [u8; KEY_LEN] {
+        let mut dh = curve25519();
+        dh.generate(&mut *DefaultResolver.resolve_rng().expect("default rng"));
+        let mut private = [0u8; KEY_LEN];
+        private.copy_from_slice(dh.privkey());
+        private
+    }
+
+    /// Uses `private` as static key of every channel instead of a new one, so
+    /// that remotes can pin its [`public_key`].
+    ///
+    /// [`public_key`]: Self::public_key
+    #[must_use]
+    pub fn with_private_key(mut self, private: [u8; KEY_LEN]) -> Self {
+        self.private = Some(private);
+        self
+    }
+
+    /// Only accepts remotes that prove to own the private key of `remote`, the
+    /// handshake fails with [`InitProtocolError::UntrustedKey`] otherwise.
+    #[must_use]
+    pub fn with_remote_key(mut self, remote: NoisePublicKey) -> Self {
+        self.remote = Some(remote);
+        self
+    }
+
+    /// Public key of the private key set with [`with_private_key`], there is
+    /// none to publish without it.
+    ///
+    /// [`with_private_key`]: Self::with_private_key
+    pub fn public_key(&self) -> Option<NoisePublicKey> {
+        self.private.map(|private| {
+            let mut dh = curve25519();
+            dh.set(&private);
+            let mut public = [0u8; KEY_LEN];
+            public.copy_from_slice(dh.pubkey());
+            NoisePublicKey(public)
+        })
+    }
+}
+
+fn curve25519() -> Box<dyn snow::types::Dh> {
+    DefaultResolver
+        .resolve_dh(&DHChoice::Curve25519)
+        .expect("default resolver supports Curve25519")
+}
+
+/// Keys of an encrypted channel, shared by both halves of it.
+///
+/// There is no internal nonce counter, every encrypted message needs a nonce
+/// that is never used again in the same direction.
+#[derive(Clone)]
+pub struct ChannelCipher(Arc<StatelessTransportState>);
+
+impl core::fmt::Debug for ChannelCipher {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("ChannelCipher")
+            .field("initiator", &self.0.is_initiator())
+            .finish_non_exhaustive()
+    }
+}
+
+impl ChannelCipher {
+    /// Static public key the remote side used in the handshake
+    pub fn remote_static(&self) -> Option<&[u8]> { self.0.get_remote_static() }
+
+    /// Appends the ciphertext of `plaintext` to `out`.
+    ///
+    /// # Panics
+    /// if `plaintext` is longer than [`MAX_PLAINTEXT`]
+    pub fn encrypt(&self, nonce: u64, plaintext: &[u8], out: &mut BytesMut) {
+        assert!(plaintext.len() <= MAX_PLAINTEXT, "plaintext too long");
+        let start = out.len();
+        out.resize(start + plaintext.len() + TAG_LEN, 0);
+        self.0
+            .write_message(nonce, plaintext, &mut out[start..])
+            .expect("output has room for the ciphertext");
+    }
+
+    /// Appends the plaintext of `ciphertext` to `out`, returns false if it
+    /// was tampered with or `nonce` isn't the one it was encrypted with
+    pub fn decrypt(&self, nonce: u64, ciphertext: &[u8], out: &mut BytesMut) -> bool {
+        if ciphertext.len() < TAG_LEN || ciphertext.len() > MAX_MESSAGE {
+            return false;
+        }
+        let start = out.len();
+        out.resize(start + ciphertext.len() - TAG_LEN, 0);
+        let ok = self
+            .0
+            .read_message(nonce, ciphertext, &mut out[start..])
+            .is_ok();
+        if !ok {
+            out.truncate(start);
+        }
+        ok
+    }
+}
+
+/// State of the Noise XX handshake of a channel
+pub(crate) struct NoiseHandshake {
+    state: HandshakeState,
+    remote: Option<NoisePublicKey>,
+}
+
+fn failed(e: snow::Error) -> InitProtocolError {
+    info!(?e, "Noise handshake failed");
+    InitProtocolError::EncryptionFailed
+}
+
+impl NoiseHandshake {
+    pub(crate) fn new(initiator: bool, keys: &NoiseKeys) -> Result<Self, InitProtocolError> {
+        let params: NoiseParams = NOISE_PARAMS.parse().map_err(failed)?;
+        let private = keys.private.unwrap_or_else(NoiseKeys::generate_private_key);
+        let builder = snow::Builder::new(params).local_private_key(&private);
+        let state = if initiator {
+            builder.build_initiator()
+        } else {
+            builder.build_responder()
+        };
+        state.map_err(failed).map(|state| Self {
+            state,
+            remote: keys.remote,
+        })
+    }
+
+    pub(crate) fn is_finished(&self) -> bool { self.state.is_handshake_finished() }
+
+    /// Whether the next message is written by us or read from the remote
+    pub(crate) fn is_my_turn(&self) -> bool { self.state.is_my_turn() }
+
+    pub(crate) fn write(&mut self) -> Result<Bytes, InitProtocolError> {
+        let mut message = vec![0; MAX_HANDSHAKE_MESSAGE];
+        let len = self
+            .state
+            .write_message(&[], &mut message)
+            .map_err(failed)?;
+        message.truncate(len);
+        Ok(Bytes::from(message))
+    }
+
+    /// Fails as soon as the remote revealed a static key that isn't the pinned
+    /// one
+    pub(crate) fn read(&mut self, message: &[u8]) -> Result<(), InitProtocolError> {
+        let mut payload = vec![0; message.len()];
+        self.state
+            .read_message(message, &mut payload)
+            .map_err(failed)?;
+        match (self.remote, self.state.get_remote_static()) {
+            (Some(pinned), Some(remote)) if pinned.0[..] != *remote => {
+                info!(%pinned, "Remote static key doesn't match the pinned one");
+                Err(InitProtocolError::UntrustedKey)
+            },
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn finish(self) -> Result<ChannelCipher, InitProtocolError> {
+        self.state
+            .into_stateless_transport_mode()
+            .map(|state| ChannelCipher(Arc::new(state)))
+            .map_err(failed)
+    }
+}
+
+/// Runs a handshake in memory, for protocols that can't do it on their own
+#[cfg(test)]
+pub(crate) fn cipher_pair() -> (ChannelCipher, ChannelCipher) {
+    let mut initiator = NoiseHandshake::new(true, &NoiseKeys::default()).unwrap();
+    let mut responder = NoiseHandshake::new(false, &NoiseKeys::default()).unwrap();
+    while !initiator.is_finished() || !responder.is_finished() {
+        let (from, to) = if initiator.is_my_turn() {
+            (&mut initiator, &mut responder)
+        } else {
+            (&mut responder, &mut initiator)
+        };
+        let message = from.write().unwrap();
+        to.read(&message).unwrap();
+    }
+    (initiator.finish().unwrap(), responder.finish().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ciphers_match() {
+        let (a, b) = cipher_pair();
+        assert!(a.remote_static().is_some());
+        let mut ciphertext = BytesMut::new();
+        a.encrypt(7, b"veloren", &mut ciphertext);
+        assert_eq!(ciphertext.len(), 7 + TAG_LEN);
+        let mut plaintext = BytesMut::new();
+        assert!(b.decrypt(7, &ciphertext, &mut plaintext));
+        assert_eq!(&plaintext[..], b"veloren");
+
+        // wrong nonce, wrong direction and tampered ciphertext
+        plaintext.clear();
+        assert!(!b.decrypt(8, &ciphertext, &mut plaintext));
+        assert!(!a.decrypt(7, &ciphertext, &mut plaintext));
+        ciphertext[0] ^= 1;
+        assert!(!b.decrypt(7, &ciphertext, &mut plaintext));
+        assert!(plaintext.is_empty());
+    }
+
+    #[test]
+    fn public_key_roundtrip() {
+        assert_eq!(NoiseKeys::default().public_key(), None);
+        let keys = NoiseKeys::default().with_private_key(NoiseKeys::generate_private_key());
+        let public = keys.public_key().unwrap();
+        let hex = public.to_string();
+        assert_eq!(hex.len(), KEY_LEN * 2);
+        assert_eq!(hex.parse(), Ok(public));
+        assert_eq!(hex[2..].parse::<NoisePublicKey>(), Err(ParseKeyError));
+        assert_eq!(
+            "zz".repeat(KEY_LEN).parse::<NoisePublicKey>(),
+            Err(ParseKeyError)
+        );
+    }
+}
@@ -10,6 +10,21 @@ use std::{
     time::Duration,
 };
 
+/// Bytes of a stream the remote application didn't consume yet
+#[derive(Debug)]
+struct FlowWindow {
+    size: u64,
+    in_flight: u64,
+}
+
+impl FlowWindow {
+    /// A message bigger than the whole window is sent once nothing else is in
+    /// flight, so it can't block the stream forever
+    fn allows(&self, length: u64) -> bool {
+        self.in_flight == 0 || self.in_flight + length <= self.size
+    }
+}
+
 #[derive(Debug)]
 struct StreamInfo {
     pub(crate) guaranteed_bandwidth: Bandwidth,
@@ -17,12 +32,36 @@ struct StreamInfo {
     #[allow(dead_code)]
     pub(crate) promises: Promises,
     pub(crate) messages: VecDeque<OTMessage>,
+    /// number of grabs in a row this stream had messages but got no bandwidth
+    pub(crate) starved_grabs: u8,
+    /// `None` for streams that aren't flow controlled
+    window: Option<FlowWindow>,
+}
+
+impl StreamInfo {
+    /// whether the next frame of this stream may be sent
+    fn can_send(&self) -> bool {
+        match (self.messages.front(), &self.window) {
+            (None, _) => false,
+            (Some(msg), Some(window)) if !msg.is_started() => window.allows(msg.get_sid_len().1),
+            (Some(_), _) => true,
+        }
+    }
 }
 
+/// A stream with queued messages that got no bandwidth for this many grabs in
+/// a row may send a single frame, so low prios never fully starve.
+const STARVATION_GRABS: u8 = 8;
+
 /// Responsible for queueing messages.
 /// every stream has a guaranteed bandwidth and a prio 0-7.
 /// when `n` Bytes are available in the buffer, first the guaranteed bandwidth
-/// is used. Then remaining bandwidth is used to fill up the prios.
+/// is used. Then remaining bandwidth is used to fill up the prios, starting
+/// with the highest prio (0). Streams starving for [`STARVATION_GRABS`] grabs
+/// are allowed to send a frame anyway.
+/// Messages of streams with [`Promises::GUARANTEED_DELIVERY`] are only started
+/// while they fit into the window the remote advertised, streams with an
+/// exhausted window are skipped until a window update arrives.
 #[derive(Debug)]
 pub(crate) struct PrioManager {
     streams: HashMap<Sid, StreamInfo>,
@@ -45,15 +84,39 @@ impl PrioManager {
         prio: Prio,
         promises: Promises,
         guaranteed_bandwidth: Bandwidth,
+        window: u64,
     ) {
+        let window = promises
+            .contains(Promises::GUARANTEED_DELIVERY)
+            .then(|| FlowWindow {
+                size: window,
+                in_flight: 0,
+            });
         self.streams.insert(sid, StreamInfo {
             guaranteed_bandwidth,
             prio,
             promises,
             messages: VecDeque::new(),
+            starved_grabs: 0,
+            window,
         });
     }
 
+    /// the remote consumed `bytes` of the stream
+    pub fn window_update(&mut self, sid: Sid, bytes: u64) {
+        if let Some(window) = self.streams.get_mut(&sid).and_then(|s| s.window.as_mut()) {
+            window.in_flight = window.in_flight.saturating_sub(bytes);
+        }
+    }
+
+    /// the remote closed the stream and won't send window updates anymore,
+    /// queued messages are sent regardless so the stream can be closed
+    pub fn release_window(&mut self, sid: Sid) {
+        if let Some(stream) = self.streams.get_mut(&sid) {
+            stream.window = None;
+        }
+    }
+
     pub fn try_close_stream(&mut self, sid: Sid) -> bool {
         if let Some(si) = self.streams.get(&sid) {
             if si.messages.is_empty() {
@@ -66,6 +129,15 @@ impl PrioManager {
 
     pub fn is_empty(&self) -> bool { self.streams.is_empty() }
 
+    /// streams with messages that weren't completely grabbed yet
+    pub fn queued_streams(&self) -> Vec<Sid> {
+        self.streams
+            .iter()
+            .filter(|(_, si)| !si.messages.is_empty())
+            .map(|(sid, _)| *sid)
+            .collect()
+    }
+
     pub fn add(&mut self, buffer: Bytes, mid: Mid, sid: Sid) {
         self.streams
             .get_mut(&sid)
@@ -86,8 +158,19 @@ impl PrioManager {
 
         let mut process_stream =
             |sid: &Sid, stream: &mut StreamInfo, mut bandwidth: i64, cur_bytes: &mut u64| {
+                if bandwidth <= 0 || !stream.can_send() {
+                    return;
+                }
+                stream.starved_grabs = 0;
                 let mut finished = None;
                 'outer: for (i, msg) in stream.messages.iter_mut().enumerate() {
+                    if let (false, Some(window)) = (msg.is_started(), &mut stream.window) {
+                        let (_, length) = msg.get_sid_len();
+                        if !window.allows(length) {
+                            break;
+                        }
+                        window.in_flight += length;
+                    }
                     while let Some(frame) = msg.next() {
                         let b = if let OTFrame::Data { data, .. } = &frame {
                             crate::frame::TCP_DATA_CNS + 1 + data.len()
@@ -113,26 +196,158 @@ impl PrioManager {
 
         // Add guaranteed bandwidth
         for (sid, stream) in self.streams.iter_mut() {
-            prios[stream.prio as usize] += 1;
+            if stream.can_send() {
+                stream.starved_grabs = stream.starved_grabs.saturating_add(1);
+                prios[stream.prio as usize] += 1;
+            }
             let stream_byte_cnt = (stream.guaranteed_bandwidth as f64 * dt.as_secs_f64()) as u64;
             process_stream(sid, stream, stream_byte_cnt as i64, &mut cur_bytes);
         }
 
-        if cur_bytes < total_bytes {
-            // Add optional bandwidth
-            for prio in 0..=HIGHEST_PRIO {
-                if prios[prio as usize] == 0 {
+        // Add optional bandwidth
+        for prio in 0..=HIGHEST_PRIO {
+            if cur_bytes >= total_bytes {
+                break;
+            }
+            if prios[prio as usize] == 0 {
+                continue;
+            }
+            let per_stream_bytes = ((total_bytes - cur_bytes) / prios[prio as usize]) as i64;
+            for (sid, stream) in self.streams.iter_mut() {
+                if stream.prio != prio {
                     continue;
                 }
-                let per_stream_bytes = ((total_bytes - cur_bytes) / prios[prio as usize]) as i64;
-                for (sid, stream) in self.streams.iter_mut() {
-                    if stream.prio != prio {
-                        continue;
-                    }
-                    process_stream(sid, stream, per_stream_bytes, &mut cur_bytes);
-                }
+                process_stream(sid, stream, per_stream_bytes, &mut cur_bytes);
+            }
+        }
+
+        // Don't let streams starve
+        for (sid, stream) in self.streams.iter_mut() {
+            if stream.starved_grabs >= STARVATION_GRABS {
+                process_stream(
+                    sid,
+                    stream,
+                    OTMessage::FRAME_DATA_SIZE as i64,
+                    &mut cur_bytes,
+                );
             }
         }
         (frames, cur_bytes)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{metrics::ProtocolMetrics, types::DEFAULT_STREAM_WINDOW};
+    use std::sync::Arc;
+
+    fn mgr() -> PrioManager {
+        PrioManager::new(ProtocolMetricCache::new(
+            "prio",
+            Arc::new(ProtocolMetrics::new().unwrap()),
+        ))
+    }
+
+    fn data_bytes(frames: &[(Sid, OTFrame)], sid: Sid) -> usize {
+        frames
+            .iter()
+            .filter(|(s, _)| *s == sid)
+            .map(|(_, f)| match f {
+                OTFrame::Data { data, .. } => data.len(),
+                _ => 0,
+            })
+            .sum()
+    }
+
+    #[test]
+    fn high_prio_first() {
+        let mut mgr = mgr();
+        let (bulk, ping) = (Sid::new(1), Sid::new(2));
+        mgr.open_stream(bulk, 6, Promises::ORDERED, 0, DEFAULT_STREAM_WINDOW);
+        mgr.open_stream(ping, 1, Promises::ORDERED, 0, DEFAULT_STREAM_WINDOW);
+        for mid in 0..10 {
+            mgr.add(Bytes::from(vec![0u8; 100_000]), mid, bulk);
+        }
+        mgr.add(Bytes::from(vec![1u8; 500]), 10, ping);
+        let (frames, _) = mgr.grab(20_000, Duration::from_secs(1));
+        // the ping is send completely before the bulk stream gets anything
+        let first_bulk = frames.iter().position(|(s, _)| *s == bulk).unwrap();
+        assert!(frames[..first_bulk].iter().all(|(s, _)| *s == ping));
+        assert_eq!(data_bytes(&frames, ping), 500);
+        assert!(data_bytes(&frames, bulk) < 20_000);
+    }
+
+    #[test]
+    fn no_underflow_when_budget_is_exceeded() {
+        let mut mgr = mgr();
+        mgr.open_stream(Sid::new(1), 0, Promises::ORDERED, 0, DEFAULT_STREAM_WINDOW);
+        mgr.open_stream(Sid::new(2), 3, Promises::ORDERED, 0, DEFAULT_STREAM_WINDOW);
+        mgr.add(Bytes::from(vec![0u8; 10_000]), 0, Sid::new(1));
+        mgr.add(Bytes::from(vec![0u8; 10_000]), 1, Sid::new(2));
+        // prio 0 overshoots the 100 bytes, prio 3 must not get anything
+        let (frames, bytes) = mgr.grab(100, Duration::from_secs(1));
+        assert!(bytes > 100);
+        assert_eq!(data_bytes(&frames, Sid::new(2)), 0);
+    }
+
+    #[test]
+    fn low_prio_never_starves() {
+        let mut mgr = mgr();
+        let (bulk, low) = (Sid::new(1), Sid::new(2));
+        mgr.open_stream(bulk, 0, Promises::ORDERED, 0, DEFAULT_STREAM_WINDOW);
+        mgr.open_stream(low, 7, Promises::ORDERED, 0, DEFAULT_STREAM_WINDOW);
+        mgr.add(Bytes::from(vec![0u8; 10_000_000]), 0, bulk);
+        mgr.add(Bytes::from(vec![1u8; 100]), 1, low);
+        let mut grabs = 0;
+        loop {
+            grabs += 1;
+            let (frames, _) = mgr.grab(1_400, Duration::from_secs(1));
+            if data_bytes(&frames, low) == 100 {
+                break;
+            }
+            assert!(grabs <= STARVATION_GRABS as usize);
+        }
+    }
+
+    #[test]
+    fn window_caps_unconsumed_bytes() {
+        let mut mgr = mgr();
+        let sid = Sid::new(1);
+        mgr.open_stream(sid, 0, Promises::GUARANTEED_DELIVERY, 0, 10_000);
+        for mid in 0..100 {
+            mgr.add(Bytes::from(vec![0u8; 1_000]), mid, sid);
+        }
+        // the remote never consumes anything
+        let mut sent = 0;
+        for _ in 0..20 {
+            let (frames, _) = mgr.grab(1_000_000, Duration::from_secs(1));
+            sent += data_bytes(&frames, sid);
+        }
+        assert_eq!(sent, 10_000);
+
+        mgr.window_update(sid, 2_500);
+        let (frames, _) = mgr.grab(1_000_000, Duration::from_secs(1));
+        assert_eq!(data_bytes(&frames, sid), 2_000);
+
+        // once the remote closed the stream the rest is flushed regardless
+        mgr.release_window(sid);
+        let (frames, _) = mgr.grab(1_000_000, Duration::from_secs(1));
+        assert_eq!(data_bytes(&frames, sid), 88_000);
+        assert!(mgr.try_close_stream(sid));
+    }
+
+    #[test]
+    fn message_bigger_than_window_is_sent_alone() {
+        let mut mgr = mgr();
+        let sid = Sid::new(1);
+        mgr.open_stream(sid, 0, Promises::GUARANTEED_DELIVERY, 0, 1_000);
+        mgr.add(Bytes::from(vec![0u8; 5_000]), 0, sid);
+        mgr.add(Bytes::from(vec![0u8; 100]), 1, sid);
+        let (frames, _) = mgr.grab(1_000_000, Duration::from_secs(1));
+        assert_eq!(data_bytes(&frames, sid), 5_000);
+        mgr.window_update(sid, 5_000);
+        let (frames, _) = mgr.grab(1_000_000, Duration::from_secs(1));
+        assert_eq!(data_bytes(&frames, sid), 100);
+    }
+}
@@ -4,8 +4,9 @@ use crate::{
     frame::{ITFrame, InitFrame, OTFrame},
     handshake::{ReliableDrain, ReliableSink},
     message::{ITMessage, ALLOC_BLOCK},
-    metrics::{ProtocolMetricCache, RemoveReason},
+    metrics::{ProtocolMetricCache, RemoveReason, StreamObserver},
     prio::PrioManager,
+    tcp::write_frame,
     types::{Bandwidth, Mid, Promises, Sid},
     util::SortedVec,
     RecvProtocol, SendProtocol, UnreliableDrain, UnreliableSink,
@@ -13,12 +14,15 @@ use crate::{
 use async_trait::async_trait;
 use bytes::BytesMut;
 use hashbrown::HashMap;
-use std::time::{Duration, Instant};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
 use tracing::info;
 #[cfg(feature = "trace_pedantic")]
 use tracing::trace;
 
-#[derive(PartialEq)]
+#[derive(Debug, PartialEq)]
 pub enum QuicDataFormatStream {
     Main,
     Reliable(Sid),
@@ -119,6 +123,10 @@ where
         }
     }
 
+    /// Streams that still have messages queued, they are lost along with the
+    /// channel
+    pub fn queued_streams(&self) -> Vec<Sid> { self.store.queued_streams() }
+
     /// returns all promises that this Protocol can take care of
     /// If you open a Stream anyway, unsupported promises are ignored.
     pub fn supported_promises() -> Promises {
@@ -128,6 +136,12 @@ where
             | Promises::COMPRESSED
             | Promises::ENCRYPTED
     }
+
+    /// Reports the messages of every stream to `observer`, see
+    /// [`StreamObserver`]
+    pub fn set_stream_observer(&mut self, observer: Arc<dyn StreamObserver>) {
+        self.metrics.set_stream_observer(observer);
+    }
 }
 
 impl<S> QuicRecvProtocol<S>
@@ -174,6 +188,12 @@ where
         }
         Ok(chunk.stream)
     }
+
+    /// Reports the messages of every stream to `observer`, see
+    /// [`StreamObserver`]
+    pub fn set_stream_observer(&mut self, observer: Arc<dyn StreamObserver>) {
+        self.metrics.set_stream_observer(observer);
+    }
 }
 
 #[async_trait]
@@ -188,14 +208,17 @@ where
                 prio,
                 promises,
                 guaranteed_bandwidth,
+                window,
             } => {
                 self.store
-                    .open_stream(sid, prio, promises, guaranteed_bandwidth);
+                    .open_stream(sid, prio, promises, guaranteed_bandwidth, window);
                 if is_reliable(&promises) {
                     self.reliable_buffers.insert(sid, BytesMut::new());
                 }
             },
+            ProtocolEvent::WindowUpdate { sid, bytes } => self.store.window_update(sid, bytes),
             ProtocolEvent::CloseStream { sid } => {
+                self.store.release_window(sid);
                 if !self.store.try_close_stream(sid) {
                     #[cfg(feature = "trace_pedantic")]
                     trace!(?sid, "hold back notify close stream");
@@ -215,9 +238,10 @@ where
                 prio,
                 promises,
                 guaranteed_bandwidth,
+                window,
             } => {
                 self.store
-                    .open_stream(sid, prio, promises, guaranteed_bandwidth);
+                    .open_stream(sid, prio, promises, guaranteed_bandwidth, window);
                 if is_reliable(&promises) {
                     self.reliable_buffers.insert(sid, BytesMut::new());
                     //Send a empty message to notify local drain of stream
@@ -225,7 +249,13 @@ where
                         .send(QuicDataFormat::with_reliable(&mut BytesMut::new(), sid))
                         .await?;
                 }
-                event.to_frame().write_bytes(&mut self.main_buffer);
+                write_frame(event.to_frame(), &mut self.main_buffer, &mut self.metrics);
+                self.drain
+                    .send(QuicDataFormat::with_main(&mut self.main_buffer))
+                    .await?;
+            },
+            ProtocolEvent::WindowUpdate { .. } => {
+                write_frame(event.to_frame(), &mut self.main_buffer, &mut self.metrics);
                 self.drain
                     .send(QuicDataFormat::with_main(&mut self.main_buffer))
                     .await?;
@@ -233,7 +263,7 @@ where
             ProtocolEvent::CloseStream { sid } => {
                 if self.store.try_close_stream(sid) {
                     let _ = self.reliable_buffers.delete(&sid); //delete if it was reliable
-                    event.to_frame().write_bytes(&mut self.main_buffer);
+                    write_frame(event.to_frame(), &mut self.main_buffer, &mut self.metrics);
                     self.drain
                         .send(QuicDataFormat::with_main(&mut self.main_buffer))
                         .await?;
@@ -245,7 +275,7 @@ where
             },
             ProtocolEvent::Shutdown => {
                 if self.store.is_empty() {
-                    event.to_frame().write_bytes(&mut self.main_buffer);
+                    write_frame(event.to_frame(), &mut self.main_buffer, &mut self.metrics);
                     self.drain
                         .send(QuicDataFormat::with_main(&mut self.main_buffer))
                         .await?;
@@ -279,11 +309,12 @@ where
                 data_frames += 1;
             }
             match self.reliable_buffers.get_mut(&sid) {
-                Some(buffer) => frame.write_bytes(buffer),
+                Some(buffer) => write_frame(frame, buffer, &mut self.metrics),
                 None => {
-                    self.drain
-                        .send(QuicDataFormat::with_unreliable(frame))
-                        .await?
+                    let id = frame.id();
+                    let data = QuicDataFormat::with_unreliable(frame);
+                    self.metrics.sframes_b(id, data.data.len() as u64);
+                    self.drain.send(data).await?
                 },
             }
         }
@@ -302,7 +333,11 @@ where
             if self.store.try_close_stream(sid) {
                 #[cfg(feature = "trace_pedantic")]
                 trace!(?sid, "close stream, as it's now empty");
-                OTFrame::CloseStream { sid }.write_bytes(&mut self.main_buffer);
+                write_frame(
+                    OTFrame::CloseStream { sid },
+                    &mut self.main_buffer,
+                    &mut self.metrics,
+                );
                 self.drain
                     .send(QuicDataFormat::with_main(&mut self.main_buffer))
                     .await?;
@@ -328,7 +363,11 @@ where
         if self.pending_shutdown && self.store.is_empty() {
             #[cfg(feature = "trace_pedantic")]
             trace!("shutdown, as it's now empty");
-            OTFrame::Shutdown {}.write_bytes(&mut self.main_buffer);
+            write_frame(
+                OTFrame::Shutdown {},
+                &mut self.main_buffer,
+                &mut self.metrics,
+            );
             self.drain
                 .send(QuicDataFormat::with_main(&mut self.main_buffer))
                 .await?;
@@ -345,10 +384,13 @@ where
 {
     async fn recv(&mut self) -> Result<ProtocolEvent, ProtocolError> {
         'outer: loop {
+            let before = self.main_buffer.len();
             match ITFrame::read_frame(&mut self.main_buffer) {
                 Ok(Some(frame)) => {
                     #[cfg(feature = "trace_pedantic")]
                     trace!(?frame, "recv");
+                    let len = before - self.main_buffer.len();
+                    self.metrics.rframes_b(frame.id(), len as u64);
                     match frame {
                         ITFrame::Shutdown => break 'outer Ok(ProtocolEvent::Shutdown),
                         ITFrame::OpenStream {
@@ -356,6 +398,7 @@ where
                             prio,
                             promises,
                             guaranteed_bandwidth,
+                            window,
                         } => {
                             if is_reliable(&promises) {
                                 self.reliable_buffers.insert(sid, BytesMut::new());
@@ -365,8 +408,12 @@ where
                                 prio: prio.min(crate::types::HIGHEST_PRIO),
                                 promises,
                                 guaranteed_bandwidth,
+                                window,
                             });
                         },
+                        ITFrame::WindowUpdate { sid, bytes } => {
+                            break 'outer Ok(ProtocolEvent::WindowUpdate { sid, bytes });
+                        },
                         ITFrame::CloseStream { sid } => {
                             //FIXME: defer close!
                             //let _ = self.reliable_buffers.delete(sid); // if it was reliable
@@ -376,7 +423,7 @@ where
                     };
                 },
                 Ok(None) => {},
-                Err(()) => return Err(ProtocolError::Violated),
+                Err(e) => return Err(e),
             }
 
             // try to order pending
@@ -419,10 +466,13 @@ where
 
             for (buffer, reliable) in iter {
                 loop {
+                    let before = buffer.len();
                     match ITFrame::read_frame(buffer) {
                         Ok(Some(frame)) => {
                             #[cfg(feature = "trace_pedantic")]
                             trace!(?frame, "recv");
+                            let len = before - buffer.len();
+                            self.metrics.rframes_b(frame.id(), len as u64);
                             match frame {
                                 ITFrame::DataHeader { sid, mid, length } => {
                                     let m = ITMessage::new(sid, length, &mut self.itmsg_allocator);
@@ -447,8 +497,8 @@ where
                                             }
                                         },
                                     };
-                                    m.data.extend_from_slice(&data);
-                                    if m.data.len() == m.length as usize {
+                                    m.push(data);
+                                    if m.is_complete() {
                                         // finished, yay
                                         let m = self
                                             .incoming
@@ -457,11 +507,11 @@ where
                                         self.metrics.rmsg_ob(
                                             m.sid,
                                             RemoveReason::Finished,
-                                            m.data.len() as u64,
+                                            m.len() as u64,
                                         );
                                         break 'outer Ok(ProtocolEvent::Message {
                                             sid: m.sid,
-                                            data: m.data.freeze(),
+                                            data: m.into_data(),
                                         });
                                     }
                                 },
@@ -469,7 +519,7 @@ where
                             };
                         },
                         Ok(None) => break, //inner => read more data
-                        Err(()) => return Err(ProtocolError::Violated),
+                        Err(e) => return Err(e),
                     }
                 }
             }
@@ -486,7 +536,9 @@ where
 {
     async fn send(&mut self, frame: InitFrame) -> Result<(), ProtocolError> {
         self.main_buffer.reserve(500);
+        let id = frame.id();
         frame.write_bytes(&mut self.main_buffer);
+        self.metrics.sframes_b(id, self.main_buffer.len() as u64);
         self.drain
             .send(QuicDataFormat::with_main(&mut self.main_buffer))
             .await
@@ -499,14 +551,20 @@ where
     S: UnreliableSink<DataFormat = QuicDataFormat>,
 {
     async fn recv(&mut self) -> Result<InitFrame, ProtocolError> {
-        while self.main_buffer.len() < 100 {
-            if self.recv_into_stream().await? == QuicDataFormatStream::Main {
-                if let Some(frame) = InitFrame::read_frame(&mut self.main_buffer) {
-                    return Ok(frame);
-                }
+        // the remote might have sent multiple frames at once, which are all in
+        // the buffer already
+        loop {
+            let before = self.main_buffer.len();
+            if let Some(frame) = InitFrame::read_frame(&mut self.main_buffer)? {
+                let len = before - self.main_buffer.len();
+                self.metrics.rframes_b(frame.id(), len as u64);
+                return Ok(frame);
+            }
+            if self.main_buffer.len() >= 100 {
+                return Err(ProtocolError::Violated);
             }
+            while self.recv_into_stream().await? != QuicDataFormatStream::Main {}
         }
-        Err(ProtocolError::Violated)
     }
 }
 
@@ -599,8 +657,11 @@ mod tests {
         error::ProtocolError,
         frame::OTFrame,
         metrics::{ProtocolMetricCache, ProtocolMetrics, RemoveReason},
-        quic::{test_utils::*, QuicDataFormat},
-        types::{Pid, Promises, Sid, STREAM_ID_OFFSET1, STREAM_ID_OFFSET2},
+        quic::{test_utils::*, QuicDataFormat, QuicDataFormatStream, QuicSendProtocol},
+        types::{
+            Pid, Promises, Sid, VersionPolicy, DEFAULT_STREAM_WINDOW, STREAM_ID_OFFSET1,
+            STREAM_ID_OFFSET2,
+        },
         InitProtocol, ProtocolEvent, RecvProtocol, SendProtocol,
     };
     use bytes::{Bytes, BytesMut};
@@ -609,8 +670,14 @@ mod tests {
     #[tokio::test]
     async fn handshake_all_good() {
         let [mut p1, mut p2] = quic_bound(10, 0.5, None);
-        let r1 = tokio::spawn(async move { p1.initialize(true, Pid::fake(2), 1337).await });
-        let r2 = tokio::spawn(async move { p2.initialize(false, Pid::fake(3), 42).await });
+        let r1 = tokio::spawn(async move {
+            p1.initialize(true, Pid::fake(2), 1337, VersionPolicy::default())
+                .await
+        });
+        let r2 = tokio::spawn(async move {
+            p2.initialize(false, Pid::fake(3), 42, VersionPolicy::default())
+                .await
+        });
         let (r1, r2) = tokio::join!(r1, r2);
         assert_eq!(r1.unwrap(), Ok((Pid::fake(3), STREAM_ID_OFFSET1, 42)));
         assert_eq!(r2.unwrap(), Ok((Pid::fake(2), STREAM_ID_OFFSET2, 1337)));
@@ -625,6 +692,7 @@ mod tests {
             prio: 0u8,
             promises: Promises::ORDERED,
             guaranteed_bandwidth: 1_000_000,
+            window: DEFAULT_STREAM_WINDOW,
         };
         s.send(event.clone()).await.unwrap();
         let e = r.recv().await.unwrap();
@@ -640,6 +708,7 @@ mod tests {
             prio: 3u8,
             promises: Promises::ORDERED,
             guaranteed_bandwidth: 1_000_000,
+            window: DEFAULT_STREAM_WINDOW,
         };
         s.send(event).await.unwrap();
         let _ = r.recv().await.unwrap();
@@ -674,6 +743,7 @@ mod tests {
             prio: 5u8,
             promises: Promises::COMPRESSED | Promises::ORDERED,
             guaranteed_bandwidth: 1_000_000,
+            window: DEFAULT_STREAM_WINDOW,
         };
         s.send(event).await.unwrap();
         let _ = r.recv().await.unwrap();
@@ -701,6 +771,7 @@ mod tests {
             prio: 5u8,
             promises: Promises::COMPRESSED | Promises::ORDERED,
             guaranteed_bandwidth: 0,
+            window: DEFAULT_STREAM_WINDOW,
         };
         s.send(event).await.unwrap();
         let _ = r.recv().await.unwrap();
@@ -729,6 +800,7 @@ mod tests {
             prio: 5u8,
             promises: Promises::COMPRESSED | Promises::ORDERED,
             guaranteed_bandwidth: 0,
+            window: DEFAULT_STREAM_WINDOW,
         };
         s.send(event).await.unwrap();
         let _ = r.recv().await.unwrap();
@@ -761,6 +833,7 @@ mod tests {
             prio: 5u8,
             promises: Promises::COMPRESSED | Promises::ORDERED,
             guaranteed_bandwidth: 0,
+            window: DEFAULT_STREAM_WINDOW,
         };
         s.send(event).await.unwrap();
         let event = ProtocolEvent::Message {
@@ -801,6 +874,7 @@ mod tests {
             prio: 5u8,
             promises: Promises::COMPRESSED | Promises::ORDERED,
             guaranteed_bandwidth: 1_000_000,
+            window: DEFAULT_STREAM_WINDOW,
         }
         .write_bytes(&mut bytes);
         s.send(QuicDataFormat::with_main(&mut bytes)).await.unwrap();
@@ -855,6 +929,7 @@ mod tests {
             prio: 5u8,
             promises: Promises::COMPRESSED,
             guaranteed_bandwidth: 1_000_000,
+            window: DEFAULT_STREAM_WINDOW,
         }
         .write_bytes(&mut bytes);
         s.send(QuicDataFormat::with_main(&mut bytes)).await.unwrap();
@@ -879,6 +954,7 @@ mod tests {
             prio: 3u8,
             promises: Promises::ORDERED,
             guaranteed_bandwidth: 1_000_000,
+            window: DEFAULT_STREAM_WINDOW,
         };
         p1.0.send(event).await.unwrap();
         let _ = p2.1.recv().await.unwrap();
@@ -902,6 +978,7 @@ mod tests {
             prio: 3u8,
             promises: Promises::ORDERED,
             guaranteed_bandwidth: 1_000_000,
+            window: DEFAULT_STREAM_WINDOW,
         };
         p1.0.send(event).await.unwrap();
         let e = p2.1.recv().await.unwrap();
@@ -916,6 +993,48 @@ mod tests {
         assert_eq!(event, e);
     }
 
+    #[tokio::test]
+    async fn reliable_streams_use_own_quic_stream() {
+        // every reliable Sid must be mapped to its own quic stream, so a big message on
+        // one stream can't block another one
+        let (sender, receiver) = async_channel::bounded(100);
+        let metrics = ProtocolMetricCache::new("quic", Arc::new(ProtocolMetrics::new().unwrap()));
+        let mut s = QuicSendProtocol::new(
+            QuicDrain {
+                sender,
+                drop_ratio: 0.0,
+            },
+            metrics,
+        );
+        for sid in [Sid::new(1), Sid::new(5)] {
+            let event = ProtocolEvent::OpenStream {
+                sid,
+                prio: 3u8,
+                promises: Promises::ORDERED | Promises::GUARANTEED_DELIVERY,
+                guaranteed_bandwidth: 1_000_000,
+                window: DEFAULT_STREAM_WINDOW,
+            };
+            s.send(event).await.unwrap();
+            let event = ProtocolEvent::Message {
+                sid,
+                data: Bytes::from(&[188u8; 600][..]),
+            };
+            s.send(event).await.unwrap();
+        }
+        s.flush(1_000_000, Duration::from_secs(1)).await.unwrap();
+        drop(s);
+        let mut data_streams = vec![];
+        while let Ok(data) = receiver.recv().await {
+            if !data.data.is_empty() && data.stream != QuicDataFormatStream::Main {
+                data_streams.push(data.stream);
+            }
+        }
+        assert_eq!(data_streams, vec![
+            QuicDataFormatStream::Reliable(Sid::new(1)),
+            QuicDataFormatStream::Reliable(Sid::new(5)),
+        ]);
+    }
+
     #[tokio::test]
     async fn unrealiable_test() {
         const MIN_CHECK: usize = 10;
@@ -934,6 +1053,7 @@ mod tests {
             prio: 3u8,
             promises: Promises::empty(), /* on purpose! */
             guaranteed_bandwidth: 1_000_000,
+            window: DEFAULT_STREAM_WINDOW,
         };
         p1.0.send(event).await.unwrap();
         let e = p2.1.recv().await.unwrap();
@@ -0,0 +1,158 @@
+//! Round trip time of a channel
+//!
+//! The [`SendProtocol`] regularly sends a `Ping` with a timestamp of its own
+//! clock, the remote echoes it in a `Pong` together with the time the `Ping`
+//! waited to be answered. The round trip time is smoothed like TCP does it,
+//! see [RFC 6298](https://datatracker.ietf.org/doc/html/rfc6298).
+//!
+//! [`SendProtocol`]: crate::SendProtocol
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// Smoothed round trip time and its variation, following RFC 6298
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RttEstimator {
+    srtt: Option<Duration>,
+    rttvar: Duration,
+}
+
+impl RttEstimator {
+    pub fn sample(&mut self, rtt: Duration) {
+        match self.srtt {
+            None => {
+                self.srtt = Some(rtt);
+                self.rttvar = rtt / 2;
+            },
+            Some(srtt) => {
+                let diff = if srtt > rtt { srtt - rtt } else { rtt - srtt };
+                self.rttvar = self.rttvar * 3 / 4 + diff / 4;
+                self.srtt = Some(srtt * 7 / 8 + rtt / 8);
+            },
+        }
+    }
+
+    /// `None` until the first sample arrived
+    pub fn srtt(&self) -> Option<Duration> { self.srtt }
+
+    pub fn rttvar(&self) -> Duration { self.rttvar }
+}
+
+#[derive(Debug, Default)]
+struct RttState {
+    estimator: RttEstimator,
+    /// timestamp of the last `Ping` received and when it arrived, waiting to
+    /// be answered
+    echo: Option<(u64, Instant)>,
+}
+
+/// Round trip time shared between the [`SendProtocol`] and [`RecvProtocol`]
+/// of a channel. The receiving half takes samples from `Pong`s and queues
+/// the answers to `Ping`s, which the sending half sends on the next `flush`.
+/// Both halves need a clone of the same `ChannelRtt` for this to work.
+///
+/// [`SendProtocol`]: crate::SendProtocol
+/// [`RecvProtocol`]: crate::RecvProtocol
+#[derive(Debug, Clone)]
+pub struct ChannelRtt {
+    /// timestamps in `Ping`s are the nanoseconds passed since then
+    epoch: Instant,
+    state: Arc<Mutex<RttState>>,
+}
+
+impl Default for ChannelRtt {
+    fn default() -> Self {
+        Self {
+            epoch: Instant::now(),
+            state: Arc::default(),
+        }
+    }
+}
+
+impl ChannelRtt {
+    pub fn new() -> Self { Self::default() }
+
+    /// Smoothed round trip time, `None` until the first `Pong` arrived
+    pub fn rtt(&self) -> Option<Duration> { self.state.lock().unwrap().estimator.srtt() }
+
+    /// Timestamp to send in a `Ping`
+    pub(crate) fn timestamp(&self) -> u64 { self.epoch.elapsed().as_nanos() as u64 }
+
+    /// Remembers a `Ping` to be answered, only the latest one is answered
+    pub(crate) fn ping_received(&self, timestamp: u64) {
+        self.state.lock().unwrap().echo = Some((timestamp, Instant::now()));
+    }
+
+    /// Timestamp and delay to send in a `Pong`, if a `Ping` is waiting
+    pub(crate) fn take_echo(&self) -> Option<(u64, u64)> {
+        let (timestamp, received) = self.state.lock().unwrap().echo.take()?;
+        Some((timestamp, received.elapsed().as_nanos() as u64))
+    }
+
+    /// Takes a sample from a `Pong`, returns the new round trip time. `Pong`s
+    /// that can't be answers to our `Ping`s are ignored.
+    pub(crate) fn pong_received(&self, timestamp: u64, delay: u64) -> Option<Duration> {
+        let rtt = self
+            .timestamp()
+            .checked_sub(timestamp)?
+            .saturating_sub(delay)
+            // a round trip below a microsecond is a measuring error
+            .max(1_000);
+        let mut state = self.state.lock().unwrap();
+        state.estimator.sample(Duration::from_nanos(rtt));
+        state.estimator.srtt()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_sample_sets_srtt() {
+        let mut rtt = RttEstimator::default();
+        assert_eq!(rtt.srtt(), None);
+        rtt.sample(Duration::from_millis(100));
+        assert_eq!(rtt.srtt(), Some(Duration::from_millis(100)));
+        assert_eq!(rtt.rttvar(), Duration::from_millis(50));
+    }
+
+    #[test]
+    fn samples_are_smoothed() {
+        let mut rtt = RttEstimator::default();
+        rtt.sample(Duration::from_millis(100));
+        rtt.sample(Duration::from_millis(180));
+        assert_eq!(rtt.srtt(), Some(Duration::from_millis(110)));
+        assert_eq!(rtt.rttvar(), Duration::from_micros(57_500));
+        for _ in 0..100 {
+            rtt.sample(Duration::from_millis(20));
+        }
+        let srtt = rtt.srtt().unwrap();
+        assert!(srtt >= Duration::from_millis(20) && srtt < Duration::from_millis(21));
+        assert!(rtt.rttvar() < Duration::from_millis(1));
+    }
+
+    #[test]
+    fn pong_delay_is_not_counted() {
+        let rtt = ChannelRtt::new();
+        let timestamp = rtt.timestamp();
+        std::thread::sleep(Duration::from_millis(20));
+        let delay = Duration::from_millis(15).as_nanos() as u64;
+        let srtt = rtt.pong_received(timestamp, delay).unwrap();
+        assert!(srtt >= Duration::from_millis(5) && srtt < Duration::from_millis(15));
+        // a timestamp from the future can't be ours
+        assert_eq!(rtt.pong_received(u64::MAX, 0), None);
+        assert_eq!(rtt.rtt(), Some(srtt));
+    }
+
+    #[test]
+    fn latest_ping_is_answered_once() {
+        let rtt = ChannelRtt::new();
+        assert_eq!(rtt.take_echo(), None);
+        rtt.ping_received(1);
+        rtt.ping_received(2);
+        assert!(matches!(rtt.take_echo(), Some((2, _))));
+        assert_eq!(rtt.take_echo(), None);
+    }
+}
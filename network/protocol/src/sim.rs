@@ -0,0 +1,410 @@
+//! Simulated network links for tests, loopback channels deliver instantly and
+//! never lose anything, which hides most timing related bugs.
+//!
+//! A link is one-way and sits between a [`SimDrain`] and a [`SimSink`]. It
+//! delays every packet by a configurable latency plus jitter, limits the
+//! throughput and loses packets. Reliable links (emulating TCP) never drop or
+//! reorder, a lost packet is retransmitted after a round trip instead.
+//! Unreliable links (emulating UDP) drop lost packets and may reorder them.
+//!
+//! Everything is seeded, so combined with `#[tokio::test(start_paused =
+//! true)]` a test behaves the same on every run.
+use crate::{
+    error::ProtocolError,
+    metrics::{ProtocolMetricCache, ProtocolMetrics},
+    rtt::ChannelRtt,
+    tcp::{TcpRecvProtocol, TcpSendProtocol},
+    types::KeepAlive,
+    udp::{UdpAcks, UdpRecvProtocol, UdpSendProtocol},
+    UnreliableDrain, UnreliableSink,
+};
+use async_trait::async_trait;
+use bytes::BytesMut;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::{cmp::Reverse, collections::BinaryHeap, sync::Arc, time::Duration};
+use tokio::{
+    sync::mpsc,
+    time::{sleep_until, Instant},
+};
+
+#[derive(Debug, Clone, Copy)]
+pub struct SimConfig {
+    /// one-way latency
+    pub latency: Duration,
+    /// every packet is delayed by an additional, uniformly distributed,
+    /// `0..jitter`
+    pub jitter: Duration,
+    /// probability `0.0..=1.0` of a packet getting lost
+    pub loss: f64,
+    /// bytes per second, `None` for unlimited
+    pub bandwidth: Option<u64>,
+    /// reliable links don't drop or reorder packets, see module docs
+    pub reliable: bool,
+    pub seed: u64,
+}
+
+impl SimConfig {
+    /// a link without any delay or loss
+    pub fn perfect() -> Self {
+        Self {
+            latency: Duration::ZERO,
+            jitter: Duration::ZERO,
+            loss: 0.0,
+            bandwidth: None,
+            reliable: true,
+            seed: 0,
+        }
+    }
+
+    pub fn with_latency(mut self, latency: Duration, jitter: Duration) -> Self {
+        self.latency = latency;
+        self.jitter = jitter;
+        self
+    }
+
+    pub fn with_loss(mut self, loss: f64) -> Self {
+        self.loss = loss;
+        self
+    }
+
+    pub fn with_bandwidth(mut self, bytes_per_sec: u64) -> Self {
+        self.bandwidth = Some(bytes_per_sec);
+        self
+    }
+
+    pub fn unreliable(mut self) -> Self {
+        self.reliable = false;
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+}
+
+#[derive(Debug)]
+struct InFlight {
+    deliver_at: Instant,
+    /// keeps packets with the same deliver_at in send order
+    seq: u64,
+    data: BytesMut,
+}
+
+impl PartialEq for InFlight {
+    fn eq(&self, other: &Self) -> bool {
+        (self.deliver_at, self.seq) == (other.deliver_at, other.seq)
+    }
+}
+
+impl Eq for InFlight {}
+
+impl PartialOrd for InFlight {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> { Some(self.cmp(other)) }
+}
+
+impl Ord for InFlight {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (self.deliver_at, self.seq).cmp(&(other.deliver_at, other.seq))
+    }
+}
+
+#[derive(Debug)]
+pub struct SimDrain {
+    config: SimConfig,
+    rng: StdRng,
+    sender: mpsc::UnboundedSender<InFlight>,
+    seq: u64,
+    /// when the link has sent all bytes passed to it so far
+    link_free_at: Instant,
+    /// reliable links deliver in order, never earlier than the previous packet
+    last_deliver_at: Instant,
+    /// packets that are lost regardless of the configured loss
+    lose_next: u64,
+    pub lost: u64,
+}
+
+#[derive(Debug)]
+pub struct SimSink {
+    receiver: mpsc::UnboundedReceiver<InFlight>,
+    in_flight: BinaryHeap<Reverse<InFlight>>,
+}
+
+/// creates a one-way link
+pub fn sim_link(config: SimConfig) -> (SimDrain, SimSink) {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    let now = Instant::now();
+    (
+        SimDrain {
+            config,
+            rng: StdRng::seed_from_u64(config.seed),
+            sender,
+            seq: 0,
+            link_free_at: now,
+            last_deliver_at: now,
+            lose_next: 0,
+            lost: 0,
+        },
+        SimSink {
+            receiver,
+            in_flight: BinaryHeap::new(),
+        },
+    )
+}
+
+/// emulate Tcp protocol over simulated links, `config` is used for both
+/// directions, the seed of the second one is increased by 1
+pub fn sim_tcp_bound(
+    config: SimConfig,
+    metrics: Option<ProtocolMetricCache>,
+) -> [(TcpSendProtocol<SimDrain>, TcpRecvProtocol<SimSink>); 2] {
+    let (d1, s1) = sim_link(config);
+    let (d2, s2) = sim_link(config.with_seed(config.seed.wrapping_add(1)));
+    let m = metrics.unwrap_or_else(|| {
+        ProtocolMetricCache::new("sim", Arc::new(ProtocolMetrics::new().unwrap()))
+    });
+    let (rtt1, rtt2) = (ChannelRtt::new(), ChannelRtt::new());
+    [
+        (
+            TcpSendProtocol::new(d1, m.clone(), KeepAlive::default()).with_rtt(rtt1.clone()),
+            TcpRecvProtocol::new(s2, m.clone()).with_rtt(rtt1),
+        ),
+        (
+            TcpSendProtocol::new(d2, m.clone(), KeepAlive::default()).with_rtt(rtt2.clone()),
+            TcpRecvProtocol::new(s1, m).with_rtt(rtt2),
+        ),
+    ]
+}
+
+/// emulate Udp protocol over simulated links, see [`sim_tcp_bound`], use an
+/// [`SimConfig::unreliable`] config
+pub fn sim_udp_bound(
+    config: SimConfig,
+    metrics: Option<ProtocolMetricCache>,
+) -> [(UdpSendProtocol<SimDrain>, UdpRecvProtocol<SimSink>); 2] {
+    let (d1, s1) = sim_link(config);
+    let (d2, s2) = sim_link(config.with_seed(config.seed.wrapping_add(1)));
+    let m = metrics.unwrap_or_else(|| {
+        ProtocolMetricCache::new("sim", Arc::new(ProtocolMetrics::new().unwrap()))
+    });
+    let (a1, a2) = (UdpAcks::default(), UdpAcks::default());
+    let (rtt1, rtt2) = (ChannelRtt::new(), ChannelRtt::new());
+    [
+        (
+            UdpSendProtocol::new(d1, a1.clone(), m.clone()).with_rtt(rtt1.clone()),
+            UdpRecvProtocol::new(s2, a1, m.clone()).with_rtt(rtt1),
+        ),
+        (
+            UdpSendProtocol::new(d2, a2.clone(), m.clone()).with_rtt(rtt2.clone()),
+            UdpRecvProtocol::new(s1, a2, m).with_rtt(rtt2),
+        ),
+    ]
+}
+
+impl SimDrain {
+    /// Loses the next `packets` packets, on top of the configured loss, so
+    /// that a test doesn't depend on the seed to lose a certain packet
+    pub fn lose_next(&mut self, packets: u64) { self.lose_next += packets; }
+
+    fn delay(&mut self) -> Duration {
+        let jitter = if self.config.jitter.is_zero() {
+            Duration::ZERO
+        } else {
+            self.config.jitter.mul_f64(self.rng.gen::<f64>())
+        };
+        self.config.latency + jitter
+    }
+}
+
+#[async_trait]
+impl UnreliableDrain for SimDrain {
+    type DataFormat = BytesMut;
+
+    async fn send(&mut self, data: Self::DataFormat) -> Result<(), ProtocolError> {
+        let now = Instant::now();
+        if let Some(bandwidth) = self.config.bandwidth {
+            let transfer = Duration::from_secs_f64(data.len() as f64 / bandwidth as f64);
+            self.link_free_at = self.link_free_at.max(now) + transfer;
+        } else {
+            self.link_free_at = now;
+        }
+        let mut deliver_at = self.link_free_at + self.delay();
+        let mut lost = self.lose_next > 0;
+        self.lose_next = self.lose_next.saturating_sub(1);
+        while lost || self.rng.gen_bool(self.config.loss) {
+            lost = false;
+            self.lost += 1;
+            if !self.config.reliable {
+                return Ok(());
+            }
+            // the sender notices the missing ack and retransmits
+            deliver_at += self.config.latency * 2 + self.delay();
+        }
+        if self.config.reliable {
+            deliver_at = deliver_at.max(self.last_deliver_at);
+            self.last_deliver_at = deliver_at;
+        }
+        self.seq += 1;
+        self.sender
+            .send(InFlight {
+                deliver_at,
+                seq: self.seq,
+                data,
+            })
+            .map_err(|_| ProtocolError::Closed)
+    }
+}
+
+#[async_trait]
+impl UnreliableSink for SimSink {
+    type DataFormat = BytesMut;
+
+    async fn recv(&mut self) -> Result<Self::DataFormat, ProtocolError> {
+        loop {
+            while let Ok(packet) = self.receiver.try_recv() {
+                self.in_flight.push(Reverse(packet));
+            }
+            let next = match self.in_flight.peek() {
+                Some(Reverse(packet)) if packet.deliver_at <= Instant::now() => {
+                    return Ok(self.in_flight.pop().unwrap().0.data);
+                },
+                Some(Reverse(packet)) => Some(packet.deliver_at),
+                None => None,
+            };
+            // wait for the next packet to arrive, or a new one to be sent
+            match next {
+                Some(deliver_at) => tokio::select! {
+                    _ = sleep_until(deliver_at) => {},
+                    packet = self.receiver.recv() => match packet {
+                        Some(packet) => self.in_flight.push(Reverse(packet)),
+                        None => sleep_until(deliver_at).await,
+                    },
+                },
+                None => match self.receiver.recv().await {
+                    Some(packet) => self.in_flight.push(Reverse(packet)),
+                    None => return Err(ProtocolError::Closed),
+                },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        types::{Pid, Promises, Sid, VersionPolicy, DEFAULT_STREAM_WINDOW},
+        InitProtocol, ProtocolEvent, RecvProtocol, SendProtocol,
+    };
+    use bytes::Bytes;
+
+    fn bad_link() -> SimConfig {
+        SimConfig::perfect()
+            .with_latency(Duration::from_millis(100), Duration::from_millis(20))
+            .with_loss(0.02)
+            .with_seed(42)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn latency_is_applied() {
+        let (mut d, mut s) =
+            sim_link(SimConfig::perfect().with_latency(Duration::from_millis(100), Duration::ZERO));
+        let start = Instant::now();
+        d.send(BytesMut::from(&b"hello"[..])).await.unwrap();
+        assert_eq!(&s.recv().await.unwrap()[..], b"hello");
+        assert_eq!(start.elapsed(), Duration::from_millis(100));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn bandwidth_is_applied() {
+        let (mut d, mut s) = sim_link(SimConfig::perfect().with_bandwidth(1_000));
+        let start = Instant::now();
+        for _ in 0..4 {
+            d.send(BytesMut::from(&[0u8; 500][..])).await.unwrap();
+        }
+        for _ in 0..4 {
+            s.recv().await.unwrap();
+        }
+        assert_eq!(start.elapsed(), Duration::from_secs(2));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn unreliable_loses_and_reorders() {
+        let (mut d, mut s) = sim_link(bad_link().unreliable());
+        for i in 0..1000u16 {
+            d.send(BytesMut::from(&i.to_le_bytes()[..])).await.unwrap();
+        }
+        let lost = d.lost;
+        drop(d);
+        let mut received = Vec::new();
+        while let Ok(data) = s.recv().await {
+            received.push(u16::from_le_bytes([data[0], data[1]]));
+        }
+        assert_eq!(received.len() as u64 + lost, 1000);
+        assert!((5..=40).contains(&lost), "lost {} packets", lost);
+        assert!(received.windows(2).any(|w| w[0] > w[1]));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn reliable_keeps_order() {
+        let (mut d, mut s) = sim_link(bad_link());
+        for i in 0..1000u16 {
+            d.send(BytesMut::from(&i.to_le_bytes()[..])).await.unwrap();
+        }
+        assert!(d.lost > 0);
+        for i in 0..1000u16 {
+            let data = s.recv().await.unwrap();
+            assert_eq!(u16::from_le_bytes([data[0], data[1]]), i);
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn tcp_over_bad_link() {
+        let [mut p1, mut p2] = sim_tcp_bound(bad_link(), None);
+        let start = Instant::now();
+        let r1 = tokio::spawn(async move {
+            p1.initialize(true, Pid::fake(2), 1337, VersionPolicy::default())
+                .await
+                .unwrap();
+            p1
+        });
+        let r2 = tokio::spawn(async move {
+            p2.initialize(false, Pid::fake(3), 42, VersionPolicy::default())
+                .await
+                .unwrap();
+            p2
+        });
+        let (p1, p2) = tokio::join!(r1, r2);
+        // handshake needs at least 1.5 round trips
+        assert!(start.elapsed() >= Duration::from_millis(300));
+        let (mut s, mut r) = (p1.unwrap().0, p2.unwrap().1);
+
+        let sid = Sid::new(1);
+        s.send(ProtocolEvent::OpenStream {
+            sid,
+            prio: 5u8,
+            promises: Promises::ORDERED,
+            guaranteed_bandwidth: 0,
+            window: DEFAULT_STREAM_WINDOW,
+        })
+        .await
+        .unwrap();
+        let msgs = (0..20u8)
+            .map(|i| ProtocolEvent::Message {
+                sid,
+                data: Bytes::from(vec![i; 10_000]),
+            })
+            .collect::<Vec<_>>();
+        for msg in &msgs {
+            s.send(msg.clone()).await.unwrap();
+        }
+        s.flush(1_000_000, Duration::from_secs(1)).await.unwrap();
+        assert!(matches!(
+            r.recv().await,
+            Ok(ProtocolEvent::OpenStream { .. })
+        ));
+        for msg in msgs {
+            assert_eq!(r.recv().await.unwrap(), msg);
+        }
+    }
+}
@@ -1,18 +1,23 @@
 use crate::{
     error::ProtocolError,
     event::ProtocolEvent,
-    frame::{ITFrame, InitFrame, OTFrame},
+    frame::{FrameObserver, ITFrame, InitFrame, OTFrame},
     handshake::{ReliableDrain, ReliableSink},
     message::{ITMessage, ALLOC_BLOCK},
-    metrics::{ProtocolMetricCache, RemoveReason},
+    metrics::{ProtocolMetricCache, RemoveReason, StreamObserver},
+    noise::{ChannelCipher, NoiseKeys, MAX_PLAINTEXT, TAG_LEN},
     prio::PrioManager,
-    types::{Bandwidth, Mid, Promises, Sid},
+    rtt::ChannelRtt,
+    types::{Bandwidth, KeepAlive, Mid, Promises, Sid},
     RecvProtocol, SendProtocol, UnreliableDrain, UnreliableSink,
 };
 use async_trait::async_trait;
-use bytes::BytesMut;
+use bytes::{Buf, BufMut, BytesMut};
 use hashbrown::HashMap;
-use std::time::{Duration, Instant};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
 use tracing::info;
 #[cfg(feature = "trace_pedantic")]
 use tracing::trace;
@@ -32,9 +37,14 @@ where
     notify_closing_streams: Vec<Sid>,
     pending_shutdown: bool,
     drain: D,
-    #[allow(dead_code)]
-    last: Instant,
+    /// last time a `Ping` was flushed to the `drain`
+    last_ping: Option<Instant>,
+    keepalive: KeepAlive,
+    rtt: ChannelRtt,
     metrics: ProtocolMetricCache,
+    encryption: Option<NoiseKeys>,
+    cipher: Option<RecordCipher>,
+    frames: Option<Arc<dyn FrameObserver>>,
 }
 
 /// TCP implementation of [`RecvProtocol`]
@@ -49,14 +59,91 @@ where
     itmsg_allocator: BytesMut,
     incoming: HashMap<Mid, ITMessage>,
     sink: S,
+    rtt: ChannelRtt,
     metrics: ProtocolMetricCache,
+    cipher: Option<RecordCipher>,
+    frames: Option<Arc<dyn FrameObserver>>,
+}
+
+/// frames are collected until this many bytes are buffered before they are
+/// passed to the drain, so small frames don't need a write each
+const WRITE_BATCH_SIZE: usize = 16_384;
+
+/// size of the length in front of every encrypted record
+const RECORD_HEADER_SIZE: usize = 2;
+
+/// Encryption of the byte stream of an encrypted channel. It is split into
+/// records of a `u16` length followed by the ciphertext, the records of each
+/// direction are numbered and the number is used as nonce.
+#[derive(Debug)]
+struct RecordCipher {
+    cipher: ChannelCipher,
+    nonce: u64,
+    /// received ciphertext of a record that isn't complete yet
+    pending: BytesMut,
+}
+
+impl RecordCipher {
+    fn new(cipher: ChannelCipher) -> Self {
+        Self {
+            cipher,
+            nonce: 0,
+            pending: BytesMut::new(),
+        }
+    }
+
+    fn seal(&mut self, mut plaintext: &[u8]) -> BytesMut {
+        let records = (plaintext.len() + MAX_PLAINTEXT - 1) / MAX_PLAINTEXT;
+        let mut records =
+            BytesMut::with_capacity(plaintext.len() + records * (RECORD_HEADER_SIZE + TAG_LEN));
+        while !plaintext.is_empty() {
+            let (record, rest) = plaintext.split_at(plaintext.len().min(MAX_PLAINTEXT));
+            records.put_u16_le((record.len() + TAG_LEN) as u16);
+            self.cipher.encrypt(self.nonce, record, &mut records);
+            self.nonce += 1;
+            plaintext = rest;
+        }
+        records
+    }
+
+    /// Decrypts all records that are complete with `chunk` to `out`, a record
+    /// that was tampered with closes the channel
+    fn open(&mut self, chunk: BytesMut, out: &mut BytesMut) -> Result<(), ProtocolError> {
+        self.pending.unsplit(chunk);
+        while self.pending.len() >= RECORD_HEADER_SIZE {
+            let length = u16::from_le_bytes([self.pending[0], self.pending[1]]) as usize;
+            if self.pending.len() < RECORD_HEADER_SIZE + length {
+                break;
+            }
+            self.pending.advance(RECORD_HEADER_SIZE);
+            let record = self.pending.split_to(length);
+            if !self.cipher.decrypt(self.nonce, &record, out) {
+                info!("protocol violation by remote side: record can't be decrypted");
+                return Err(ProtocolError::Violated);
+            }
+            self.nonce += 1;
+        }
+        Ok(())
+    }
+}
+
+/// writes `frame` to `buffer` and counts it in the frame metrics
+pub(crate) fn write_frame(
+    frame: OTFrame,
+    buffer: &mut BytesMut,
+    metrics: &mut ProtocolMetricCache,
+) {
+    let before = buffer.len();
+    let id = frame.id();
+    frame.write_bytes(buffer);
+    metrics.sframes_b(id, (buffer.len() - before) as u64);
 }
 
 impl<D> TcpSendProtocol<D>
 where
     D: UnreliableDrain<DataFormat = BytesMut>,
 {
-    pub fn new(drain: D, metrics: ProtocolMetricCache) -> Self {
+    pub fn new(drain: D, metrics: ProtocolMetricCache, keepalive: KeepAlive) -> Self {
         Self {
             buffer: BytesMut::new(),
             store: PrioManager::new(metrics.clone()),
@@ -65,11 +152,58 @@ where
             notify_closing_streams: vec![],
             pending_shutdown: false,
             drain,
-            last: Instant::now(),
+            last_ping: None,
+            keepalive,
+            rtt: ChannelRtt::new(),
             metrics,
+            encryption: None,
+            cipher: None,
+            frames: None,
+        }
+    }
+
+    /// Requires the channel to be encrypted, the handshake fails if the remote
+    /// side doesn't encrypt as well. `keys` authenticate us or the remote,
+    /// see [`NoiseKeys`] and [`ChannelCipher`].
+    #[must_use]
+    pub fn with_encryption(mut self, keys: NoiseKeys) -> Self {
+        self.encryption = Some(keys);
+        self
+    }
+
+    /// writes `frame` to the buffer and shows it to the [`FrameObserver`]
+    fn push_frame(&mut self, frame: OTFrame) {
+        let before = self.buffer.len();
+        write_frame(frame, &mut self.buffer, &mut self.metrics);
+        if let Some(frames) = &self.frames {
+            frames.frame_sent(&self.buffer[before..]);
         }
     }
 
+    /// passes `data` to the drain, encrypted once the channel is
+    async fn write(&mut self, data: BytesMut) -> Result<(), ProtocolError> {
+        let data = match &mut self.cipher {
+            Some(cipher) => cipher.seal(&data),
+            None => data,
+        };
+        self.drain.send(data).await
+    }
+
+    /// Shares the round trip time with the [`TcpRecvProtocol`] of the same
+    /// channel, which is needed to answer pings and measure it
+    #[must_use]
+    pub fn with_rtt(mut self, rtt: ChannelRtt) -> Self {
+        self.rtt = rtt;
+        self
+    }
+
+    /// Smoothed round trip time of the channel, see [`ChannelRtt`]
+    pub fn rtt(&self) -> Option<Duration> { self.rtt.rtt() }
+
+    /// Streams that still have messages queued, they are lost along with the
+    /// channel
+    pub fn queued_streams(&self) -> Vec<Sid> { self.store.queued_streams() }
+
     /// returns all promises that this Protocol can take care of
     /// If you open a Stream anyway, unsupported promises are ignored.
     pub fn supported_promises() -> Promises {
@@ -78,6 +212,18 @@ where
             | Promises::GUARANTEED_DELIVERY
             | Promises::COMPRESSED
     }
+
+    /// Reports the messages of every stream to `observer`, see
+    /// [`StreamObserver`]
+    pub fn set_stream_observer(&mut self, observer: Arc<dyn StreamObserver>) {
+        self.metrics.set_stream_observer(observer);
+    }
+
+    /// Shows every frame sent after the handshake to `observer`, see
+    /// [`FrameObserver`]
+    pub fn set_frame_observer(&mut self, observer: Arc<dyn FrameObserver>) {
+        self.frames = Some(observer);
+    }
 }
 
 impl<S> TcpRecvProtocol<S>
@@ -90,9 +236,50 @@ where
             itmsg_allocator: BytesMut::with_capacity(ALLOC_BLOCK),
             incoming: HashMap::new(),
             sink,
+            rtt: ChannelRtt::new(),
             metrics,
+            cipher: None,
+            frames: None,
         }
     }
+
+    /// Shares the round trip time with the [`TcpSendProtocol`] of the same
+    /// channel, which is needed to answer pings and measure it
+    #[must_use]
+    pub fn with_rtt(mut self, rtt: ChannelRtt) -> Self {
+        self.rtt = rtt;
+        self
+    }
+
+    /// Smoothed round trip time of the channel, see [`ChannelRtt`]
+    pub fn rtt(&self) -> Option<Duration> { self.rtt.rtt() }
+
+    /// reads the next chunk from the sink to `buffer`, decrypted once the
+    /// channel is encrypted
+    async fn read(&mut self) -> Result<(), ProtocolError> {
+        let chunk = self.sink.recv().await?;
+        match &mut self.cipher {
+            Some(cipher) => cipher.open(chunk, &mut self.buffer),
+            None => {
+                // chunks are consecutive slices of the read buffer of the sink, so this
+                // usually doesn't copy
+                self.buffer.unsplit(chunk);
+                Ok(())
+            },
+        }
+    }
+
+    /// Reports the messages of every stream to `observer`, see
+    /// [`StreamObserver`]
+    pub fn set_stream_observer(&mut self, observer: Arc<dyn StreamObserver>) {
+        self.metrics.set_stream_observer(observer);
+    }
+
+    /// Shows every frame received after the handshake to `observer`, see
+    /// [`FrameObserver`]
+    pub fn set_frame_observer(&mut self, observer: Arc<dyn FrameObserver>) {
+        self.frames = Some(observer);
+    }
 }
 
 #[async_trait]
@@ -107,11 +294,14 @@ where
                 prio,
                 promises,
                 guaranteed_bandwidth,
+                window,
             } => {
                 self.store
-                    .open_stream(sid, prio, promises, guaranteed_bandwidth);
+                    .open_stream(sid, prio, promises, guaranteed_bandwidth, window);
             },
+            ProtocolEvent::WindowUpdate { sid, bytes } => self.store.window_update(sid, bytes),
             ProtocolEvent::CloseStream { sid } => {
+                self.store.release_window(sid);
                 if !self.store.try_close_stream(sid) {
                     #[cfg(feature = "trace_pedantic")]
                     trace!(?sid, "hold back notify close stream");
@@ -131,16 +321,23 @@ where
                 prio,
                 promises,
                 guaranteed_bandwidth,
+                window,
             } => {
                 self.store
-                    .open_stream(sid, prio, promises, guaranteed_bandwidth);
-                event.to_frame().write_bytes(&mut self.buffer);
-                self.drain.send(self.buffer.split()).await?;
+                    .open_stream(sid, prio, promises, guaranteed_bandwidth, window);
+                self.push_frame(event.to_frame());
+                let data = self.buffer.split();
+                self.write(data).await?;
+            },
+            // goes out with the next flush
+            ProtocolEvent::WindowUpdate { .. } => {
+                self.push_frame(event.to_frame());
             },
             ProtocolEvent::CloseStream { sid } => {
                 if self.store.try_close_stream(sid) {
-                    event.to_frame().write_bytes(&mut self.buffer);
-                    self.drain.send(self.buffer.split()).await?;
+                    self.push_frame(event.to_frame());
+                    let data = self.buffer.split();
+                    self.write(data).await?;
                 } else {
                     #[cfg(feature = "trace_pedantic")]
                     trace!(?sid, "hold back close stream");
@@ -149,8 +346,9 @@ where
             },
             ProtocolEvent::Shutdown => {
                 if self.store.is_empty() {
-                    event.to_frame().write_bytes(&mut self.buffer);
-                    self.drain.send(self.buffer.split()).await?;
+                    self.push_frame(event.to_frame());
+                    let data = self.buffer.split();
+                    self.write(data).await?;
                 } else {
                     #[cfg(feature = "trace_pedantic")]
                     trace!("hold back shutdown");
@@ -172,7 +370,8 @@ where
         dt: Duration,
     ) -> Result</* actual */ Bandwidth, ProtocolError> {
         let (frames, total_bytes) = self.store.grab(bandwidth, dt);
-        self.buffer.reserve(total_bytes as usize);
+        self.buffer
+            .reserve((total_bytes as usize).min(WRITE_BATCH_SIZE));
         let mut data_frames = 0;
         let mut data_bandwidth = 0;
         for (_, frame) in frames {
@@ -180,25 +379,25 @@ where
                 data_bandwidth += data.len();
                 data_frames += 1;
             }
-            frame.write_bytes(&mut self.buffer);
+            self.push_frame(frame);
+            if self.buffer.len() >= WRITE_BATCH_SIZE {
+                let data = self.buffer.split();
+                self.write(data).await?;
+            }
         }
-        self.drain.send(self.buffer.split()).await?;
         self.metrics
             .sdata_frames_b(data_frames, data_bandwidth as u64);
 
-        let mut finished_streams = vec![];
-        for (i, &sid) in self.closing_streams.iter().enumerate() {
+        // closing streams and the shutdown go out with the last data frames
+        for sid in std::mem::take(&mut self.closing_streams) {
             if self.store.try_close_stream(sid) {
                 #[cfg(feature = "trace_pedantic")]
                 trace!(?sid, "close stream, as it's now empty");
-                OTFrame::CloseStream { sid }.write_bytes(&mut self.buffer);
-                self.drain.send(self.buffer.split()).await?;
-                finished_streams.push(i);
+                self.push_frame(OTFrame::CloseStream { sid });
+            } else {
+                self.closing_streams.push(sid);
             }
         }
-        for i in finished_streams.iter().rev() {
-            self.closing_streams.remove(*i);
-        }
 
         let mut finished_streams = vec![];
         for (i, sid) in self.notify_closing_streams.iter().enumerate() {
@@ -215,10 +414,26 @@ where
         if self.pending_shutdown && self.store.is_empty() {
             #[cfg(feature = "trace_pedantic")]
             trace!("shutdown, as it's now empty");
-            OTFrame::Shutdown {}.write_bytes(&mut self.buffer);
-            self.drain.send(self.buffer.split()).await?;
+            self.push_frame(OTFrame::Shutdown {});
             self.pending_shutdown = false;
         }
+
+        // pings go out with the last data frames, if there are any
+        if let Some((timestamp, delay)) = self.rtt.take_echo() {
+            self.push_frame(OTFrame::Pong { timestamp, delay });
+        }
+        if self
+            .last_ping
+            .map_or(true, |last| last.elapsed() >= self.keepalive.interval)
+        {
+            let timestamp = self.rtt.timestamp();
+            self.push_frame(OTFrame::Ping { timestamp });
+            self.last_ping = Some(Instant::now());
+        }
+        if !self.buffer.is_empty() {
+            let data = self.buffer.split();
+            self.write(data).await?;
+        }
         Ok(data_bandwidth as u64)
     }
 }
@@ -231,28 +446,48 @@ where
     async fn recv(&mut self) -> Result<ProtocolEvent, ProtocolError> {
         'outer: loop {
             loop {
+                let before = self.buffer.len();
                 match ITFrame::read_frame(&mut self.buffer) {
                     Ok(Some(frame)) => {
                         #[cfg(feature = "trace_pedantic")]
                         trace!(?frame, "recv");
+                        self.metrics
+                            .rframes_b(frame.id(), (before - self.buffer.len()) as u64);
+                        if let Some(frames) = &self.frames {
+                            // the frame is encoded again, the buffer it was read from is gone
+                            let mut bytes = BytesMut::new();
+                            OTFrame::from(frame.clone()).write_bytes(&mut bytes);
+                            frames.frame_received(&bytes);
+                        }
                         match frame {
                             ITFrame::Shutdown => break 'outer Ok(ProtocolEvent::Shutdown),
+                            ITFrame::Ping { timestamp } => self.rtt.ping_received(timestamp),
+                            ITFrame::Pong { timestamp, delay } => {
+                                if let Some(rtt) = self.rtt.pong_received(timestamp, delay) {
+                                    self.metrics.ping(rtt);
+                                }
+                            },
                             ITFrame::OpenStream {
                                 sid,
                                 prio,
                                 promises,
                                 guaranteed_bandwidth,
+                                window,
                             } => {
                                 break 'outer Ok(ProtocolEvent::OpenStream {
                                     sid,
                                     prio: prio.min(crate::types::HIGHEST_PRIO),
                                     promises,
                                     guaranteed_bandwidth,
+                                    window,
                                 });
                             },
                             ITFrame::CloseStream { sid } => {
                                 break 'outer Ok(ProtocolEvent::CloseStream { sid });
                             },
+                            ITFrame::WindowUpdate { sid, bytes } => {
+                                break 'outer Ok(ProtocolEvent::WindowUpdate { sid, bytes });
+                            },
                             ITFrame::DataHeader { sid, mid, length } => {
                                 let m = ITMessage::new(sid, length, &mut self.itmsg_allocator);
                                 self.metrics.rmsg_ib(sid, length);
@@ -271,33 +506,28 @@ where
                                         break 'outer Err(ProtocolError::Violated);
                                     },
                                 };
-                                m.data.extend_from_slice(&data);
-                                if m.data.len() == m.length as usize {
+                                m.push(data);
+                                if m.is_complete() {
                                     // finished, yay
                                     let m = self.incoming.remove(&mid).unwrap();
                                     self.metrics.rmsg_ob(
                                         m.sid,
                                         RemoveReason::Finished,
-                                        m.data.len() as u64,
+                                        m.len() as u64,
                                     );
                                     break 'outer Ok(ProtocolEvent::Message {
                                         sid: m.sid,
-                                        data: m.data.freeze(),
+                                        data: m.into_data(),
                                     });
                                 }
                             },
                         };
                     },
                     Ok(None) => break, //inner => read more data
-                    Err(()) => return Err(ProtocolError::Violated),
+                    Err(e) => return Err(e),
                 }
             }
-            let chunk = self.sink.recv().await?;
-            if self.buffer.is_empty() {
-                self.buffer = chunk;
-            } else {
-                self.buffer.extend_from_slice(&chunk);
-            }
+            self.read().await?;
         }
     }
 }
@@ -309,8 +539,17 @@ where
 {
     async fn send(&mut self, frame: InitFrame) -> Result<(), ProtocolError> {
         let mut buffer = BytesMut::with_capacity(500);
+        let id = frame.id();
         frame.write_bytes(&mut buffer);
-        self.drain.send(buffer).await
+        self.metrics.sframes_b(id, buffer.len() as u64);
+        self.write(buffer).await
+    }
+
+    fn encryption(&self) -> Option<&NoiseKeys> { self.encryption.as_ref() }
+
+    fn start_encryption(&mut self, cipher: ChannelCipher) -> Result<(), ProtocolError> {
+        self.cipher = Some(RecordCipher::new(cipher));
+        Ok(())
     }
 }
 
@@ -320,14 +559,26 @@ where
     S: UnreliableSink<DataFormat = BytesMut>,
 {
     async fn recv(&mut self) -> Result<InitFrame, ProtocolError> {
-        while self.buffer.len() < 100 {
-            let chunk = self.sink.recv().await?;
-            self.buffer.extend_from_slice(&chunk);
-            if let Some(frame) = InitFrame::read_frame(&mut self.buffer) {
+        // the remote might have sent more than one frame in a chunk, all init frames
+        // are limited in size
+        loop {
+            let before = self.buffer.len();
+            if let Some(frame) = InitFrame::read_frame(&mut self.buffer)? {
+                self.metrics
+                    .rframes_b(frame.id(), (before - self.buffer.len()) as u64);
                 return Ok(frame);
             }
+            self.read().await?;
         }
-        Err(ProtocolError::Violated)
+    }
+
+    fn start_encryption(&mut self, cipher: ChannelCipher) -> Result<(), ProtocolError> {
+        let mut cipher = RecordCipher::new(cipher);
+        // everything received after the handshake is encrypted already
+        let received = self.buffer.split();
+        cipher.open(received, &mut self.buffer)?;
+        self.cipher = Some(cipher);
+        Ok(())
     }
 }
 
@@ -357,14 +608,17 @@ mod test_utils {
         let m = metrics.unwrap_or_else(|| {
             ProtocolMetricCache::new("tcp", Arc::new(ProtocolMetrics::new().unwrap()))
         });
+        let (rtt1, rtt2) = (ChannelRtt::new(), ChannelRtt::new());
         [
             (
-                TcpSendProtocol::new(TcpDrain { sender: s1 }, m.clone()),
-                TcpRecvProtocol::new(TcpSink { receiver: r2 }, m.clone()),
+                TcpSendProtocol::new(TcpDrain { sender: s1 }, m.clone(), KeepAlive::default())
+                    .with_rtt(rtt1.clone()),
+                TcpRecvProtocol::new(TcpSink { receiver: r2 }, m.clone()).with_rtt(rtt1),
             ),
             (
-                TcpSendProtocol::new(TcpDrain { sender: s2 }, m.clone()),
-                TcpRecvProtocol::new(TcpSink { receiver: r1 }, m),
+                TcpSendProtocol::new(TcpDrain { sender: s2 }, m.clone(), KeepAlive::default())
+                    .with_rtt(rtt2.clone()),
+                TcpRecvProtocol::new(TcpSink { receiver: r1 }, m).with_rtt(rtt2),
             ),
         ]
     }
@@ -398,20 +652,59 @@ mod test_utils {
 mod tests {
     use crate::{
         error::ProtocolError,
-        frame::OTFrame,
-        metrics::{ProtocolMetricCache, ProtocolMetrics, RemoveReason},
-        tcp::test_utils::*,
-        types::{Pid, Promises, Sid, STREAM_ID_OFFSET1, STREAM_ID_OFFSET2},
-        InitProtocol, ProtocolEvent, RecvProtocol, SendProtocol,
+        frame::{FrameObserver, ITFrame, OTFrame},
+        metrics::{ProtocolMetricCache, ProtocolMetrics, RemoveReason, StreamObserver},
+        noise::{NoiseKeys, NoisePublicKey, KEY_LEN},
+        tcp::{test_utils::*, TcpRecvProtocol, TcpSendProtocol},
+        types::{
+            KeepAlive, Pid, Promises, Sid, VersionPolicy, DEFAULT_STREAM_WINDOW, STREAM_ID_OFFSET1,
+            STREAM_ID_OFFSET2,
+        },
+        InitProtocol, InitProtocolError, ProtocolEvent, RecvProtocol, SendProtocol,
     };
     use bytes::{Bytes, BytesMut};
-    use std::{sync::Arc, time::Duration};
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    /// Remembers the messages it was told about, `true` for the sent ones
+    #[derive(Debug, Default)]
+    struct RecordingObserver(Mutex<Vec<(bool, Sid, u64)>>);
+
+    impl StreamObserver for RecordingObserver {
+        fn message_sent(&self, sid: Sid, bytes: u64) {
+            self.0.lock().unwrap().push((true, sid, bytes));
+        }
+
+        fn message_received(&self, sid: Sid, bytes: u64) {
+            self.0.lock().unwrap().push((false, sid, bytes));
+        }
+    }
+
+    /// Remembers the frames it was shown, `true` for the sent ones
+    #[derive(Debug, Default)]
+    struct RecordingFrames(Mutex<Vec<(bool, Vec<u8>)>>);
+
+    impl FrameObserver for RecordingFrames {
+        fn frame_sent(&self, frame: &[u8]) { self.0.lock().unwrap().push((true, frame.to_vec())); }
+
+        fn frame_received(&self, frame: &[u8]) {
+            self.0.lock().unwrap().push((false, frame.to_vec()));
+        }
+    }
 
     #[tokio::test]
     async fn handshake_all_good() {
         let [mut p1, mut p2] = tcp_bound(10, None);
-        let r1 = tokio::spawn(async move { p1.initialize(true, Pid::fake(2), 1337).await });
-        let r2 = tokio::spawn(async move { p2.initialize(false, Pid::fake(3), 42).await });
+        let r1 = tokio::spawn(async move {
+            p1.initialize(true, Pid::fake(2), 1337, VersionPolicy::default())
+                .await
+        });
+        let r2 = tokio::spawn(async move {
+            p2.initialize(false, Pid::fake(3), 42, VersionPolicy::default())
+                .await
+        });
         let (r1, r2) = tokio::join!(r1, r2);
         assert_eq!(r1.unwrap(), Ok((Pid::fake(3), STREAM_ID_OFFSET1, 42)));
         assert_eq!(r2.unwrap(), Ok((Pid::fake(2), STREAM_ID_OFFSET2, 1337)));
@@ -426,6 +719,7 @@ mod tests {
             prio: 0u8,
             promises: Promises::ORDERED,
             guaranteed_bandwidth: 1_000_000,
+            window: DEFAULT_STREAM_WINDOW,
         };
         s.send(event.clone()).await.unwrap();
         let e = r.recv().await.unwrap();
@@ -441,6 +735,7 @@ mod tests {
             prio: 3u8,
             promises: Promises::ORDERED,
             guaranteed_bandwidth: 1_000_000,
+            window: DEFAULT_STREAM_WINDOW,
         };
         s.send(event).await.unwrap();
         let _ = r.recv().await.unwrap();
@@ -463,6 +758,100 @@ mod tests {
         assert_eq!(event, e)
     }
 
+    #[tokio::test]
+    async fn stream_observer_sees_messages() {
+        let sid = Sid::new(1);
+        let [p1, p2] = tcp_bound(10, None);
+        let (mut s, mut r) = (p1.0, p2.1);
+        let (sent, received) = (
+            Arc::new(RecordingObserver::default()),
+            Arc::new(RecordingObserver::default()),
+        );
+        s.set_stream_observer(Arc::clone(&sent) as Arc<dyn StreamObserver>);
+        r.set_stream_observer(Arc::clone(&received) as Arc<dyn StreamObserver>);
+        let event = ProtocolEvent::OpenStream {
+            sid,
+            prio: 3u8,
+            promises: Promises::ORDERED,
+            guaranteed_bandwidth: 1_000_000,
+            window: DEFAULT_STREAM_WINDOW,
+        };
+        s.send(event).await.unwrap();
+        let _ = r.recv().await.unwrap();
+        // split into multiple data frames
+        for len in [30, 3000] {
+            let data = Bytes::from(vec![7u8; len]);
+            s.send(ProtocolEvent::Message { sid, data }).await.unwrap();
+        }
+        s.flush(1_000_000, Duration::from_secs(1)).await.unwrap();
+        let _ = r.recv().await.unwrap();
+        assert_eq!(*received.0.lock().unwrap(), [(false, sid, 30)]);
+        let _ = r.recv().await.unwrap();
+
+        assert_eq!(*sent.0.lock().unwrap(), [
+            (true, sid, 30),
+            (true, sid, 3000)
+        ]);
+        assert_eq!(*received.0.lock().unwrap(), [
+            (false, sid, 30),
+            (false, sid, 3000)
+        ]);
+    }
+
+    #[tokio::test]
+    async fn frame_observer_sees_frames() {
+        let sid = Sid::new(1);
+        let [p1, p2] = tcp_bound(10, None);
+        let (mut s, mut r) = (p1.0, p2.1);
+        let (sent, received) = (
+            Arc::new(RecordingFrames::default()),
+            Arc::new(RecordingFrames::default()),
+        );
+        s.set_frame_observer(Arc::clone(&sent) as Arc<dyn FrameObserver>);
+        r.set_frame_observer(Arc::clone(&received) as Arc<dyn FrameObserver>);
+        let event = ProtocolEvent::OpenStream {
+            sid,
+            prio: 3u8,
+            promises: Promises::ORDERED,
+            guaranteed_bandwidth: 1_000_000,
+            window: DEFAULT_STREAM_WINDOW,
+        };
+        s.send(event).await.unwrap();
+        let _ = r.recv().await.unwrap();
+        let data = Bytes::from(vec![7u8; 3000]);
+        s.send(ProtocolEvent::Message { sid, data }).await.unwrap();
+        s.flush(1_000_000, Duration::from_secs(1)).await.unwrap();
+        let _ = r.recv().await.unwrap();
+
+        let sent = sent.0.lock().unwrap();
+        // open stream, data header, 3 data frames and the first ping
+        assert_eq!(sent.len(), 6);
+        assert!(sent.iter().all(|(sent, _)| *sent));
+        let mut bytes = BytesMut::new();
+        for (_, frame) in sent.iter() {
+            bytes.extend_from_slice(frame);
+        }
+        let mut frames = vec![];
+        while let Some(frame) = ITFrame::read_frame(&mut bytes).unwrap() {
+            frames.push(frame.name());
+        }
+        assert_eq!(frames, [
+            "open_stream",
+            "data_header",
+            "data",
+            "data",
+            "data",
+            "ping"
+        ]);
+        // the ping is still in the buffer of the receiver
+        let received = received.0.lock().unwrap();
+        assert_eq!(received.len(), 5);
+        for ((_, sent), (was_sent, received)) in sent.iter().zip(received.iter()) {
+            assert!(!was_sent);
+            assert_eq!(sent, received);
+        }
+    }
+
     #[tokio::test]
     async fn send_long_msg() {
         let mut metrics =
@@ -475,6 +864,7 @@ mod tests {
             prio: 5u8,
             promises: Promises::COMPRESSED,
             guaranteed_bandwidth: 1_000_000,
+            window: DEFAULT_STREAM_WINDOW,
         };
         s.send(event).await.unwrap();
         let _ = r.recv().await.unwrap();
@@ -490,6 +880,10 @@ mod tests {
         metrics.assert_msg_bytes(sid, 500_000, RemoveReason::Finished);
         metrics.assert_data_frames(358);
         metrics.assert_data_frames_bytes(500_000);
+        // every frame has a 1 byte id, data frames another 10 byte header
+        metrics.assert_frames("open_stream", 1, 27);
+        metrics.assert_frames("data_header", 1, 25);
+        metrics.assert_frames("data", 358, 500_000 + 358 * 11);
     }
 
     #[tokio::test]
@@ -502,6 +896,7 @@ mod tests {
             prio: 5u8,
             promises: Promises::COMPRESSED,
             guaranteed_bandwidth: 0,
+            window: DEFAULT_STREAM_WINDOW,
         };
         s.send(event).await.unwrap();
         let _ = r.recv().await.unwrap();
@@ -530,6 +925,7 @@ mod tests {
             prio: 5u8,
             promises: Promises::COMPRESSED,
             guaranteed_bandwidth: 0,
+            window: DEFAULT_STREAM_WINDOW,
         };
         s.send(event).await.unwrap();
         let _ = r.recv().await.unwrap();
@@ -562,6 +958,7 @@ mod tests {
             prio: 5u8,
             promises: Promises::COMPRESSED,
             guaranteed_bandwidth: 0,
+            window: DEFAULT_STREAM_WINDOW,
         };
         s.send(event).await.unwrap();
         let event = ProtocolEvent::Message {
@@ -602,6 +999,7 @@ mod tests {
             prio: 5u8,
             promises: Promises::COMPRESSED,
             guaranteed_bandwidth: 1_000_000,
+            window: DEFAULT_STREAM_WINDOW,
         }
         .write_bytes(&mut bytes);
         OTFrame::DataHeader {
@@ -635,6 +1033,179 @@ mod tests {
         assert!(matches!(e, ProtocolEvent::CloseStream { .. }));
     }
 
+    /// Serialized frames of a stream carrying messages of different sizes, with
+    /// pings in between
+    fn frame_stream(sid: Sid) -> BytesMut {
+        let mut bytes = BytesMut::new();
+        OTFrame::OpenStream {
+            sid,
+            prio: 5u8,
+            promises: Promises::ORDERED,
+            guaranteed_bandwidth: 1_000_000,
+            window: DEFAULT_STREAM_WINDOW,
+        }
+        .write_bytes(&mut bytes);
+        for (mid, length) in [(1u64, 10usize), (2, 3_000), (3, 200_000)] {
+            let data = (0..length).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+            OTFrame::DataHeader {
+                mid,
+                sid,
+                length: length as u64,
+            }
+            .write_bytes(&mut bytes);
+            for chunk in data.chunks(1_400) {
+                OTFrame::Data {
+                    mid,
+                    data: Bytes::copy_from_slice(chunk),
+                }
+                .write_bytes(&mut bytes);
+            }
+            OTFrame::Ping { timestamp: 0 }.write_bytes(&mut bytes);
+        }
+        OTFrame::CloseStream { sid }.write_bytes(&mut bytes);
+        bytes
+    }
+
+    /// Feeds `stream` to the receiving side in chunks of `chunk_size` bytes
+    async fn recv_in_chunks(stream: &[u8], chunk_size: usize) -> Vec<ProtocolEvent> {
+        let (s, r) = async_channel::unbounded();
+        let m = ProtocolMetricCache::new("tcp", Arc::new(ProtocolMetrics::new().unwrap()));
+        let mut r = super::TcpRecvProtocol::new(super::test_utils::TcpSink { receiver: r }, m);
+        for chunk in stream.chunks(chunk_size) {
+            s.send(BytesMut::from(chunk)).await.unwrap();
+        }
+        drop(s);
+
+        let mut events = Vec::new();
+        while let Ok(e) = r.recv().await {
+            events.push(e);
+        }
+        events
+    }
+
+    #[tokio::test]
+    async fn frames_split_at_any_byte() {
+        let sid = Sid::new(1);
+        let stream = frame_stream(sid);
+        let events = recv_in_chunks(&stream, 65_536).await;
+        assert_eq!(events.len(), 5);
+        assert!(matches!(events[0], ProtocolEvent::OpenStream { .. }));
+        for (event, length) in events[1..4].iter().zip([10, 3_000, 200_000]) {
+            match event {
+                ProtocolEvent::Message { sid: s, data } => {
+                    assert_eq!(*s, sid);
+                    assert_eq!(data.len(), length);
+                    assert!(data.iter().enumerate().all(|(i, b)| *b == (i % 251) as u8));
+                },
+                e => panic!("expected a message, got {:?}", e),
+            }
+        }
+        assert_eq!(events[4], ProtocolEvent::CloseStream { sid });
+
+        assert_eq!(recv_in_chunks(&stream, 1).await, events);
+    }
+
+    #[tokio::test]
+    async fn ping_when_idle() {
+        let (s, r) = async_channel::bounded(10);
+        let m = ProtocolMetricCache::new("tcp", Arc::new(ProtocolMetrics::new().unwrap()));
+        let keepalive = KeepAlive {
+            interval: Duration::from_millis(50),
+            timeout: Duration::from_secs(1),
+        };
+        let mut s =
+            super::TcpSendProtocol::new(super::test_utils::TcpDrain { sender: s }, m, keepalive);
+        let is_ping = |mut bytes: BytesMut| {
+            matches!(
+                ITFrame::read_frame(&mut bytes),
+                Ok(Some(ITFrame::Ping { .. }))
+            ) && bytes.is_empty()
+        };
+
+        // the first flush pings, to measure the round trip time right away
+        s.flush(1_000_000, Duration::from_secs(1)).await.unwrap();
+        assert!(is_ping(r.recv().await.unwrap()));
+        s.flush(1_000_000, Duration::from_secs(1)).await.unwrap();
+        assert!(r.try_recv().is_err());
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        s.flush(1_000_000, Duration::from_secs(1)).await.unwrap();
+        assert!(is_ping(r.recv().await.unwrap()));
+        s.flush(1_000_000, Duration::from_secs(1)).await.unwrap();
+        assert!(r.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn rtt_is_measured() {
+        let [(mut s1, mut r1), (mut s2, mut r2)] = tcp_bound(10, None);
+        assert_eq!(s1.rtt(), None);
+        // pings and pongs are handled while waiting for an event
+        let r1 = tokio::spawn(async move { r1.recv().await });
+        let r2 = tokio::spawn(async move { r2.recv().await });
+        for _ in 0..100 {
+            s1.flush(1_000_000, Duration::from_millis(1)).await.unwrap();
+            s2.flush(1_000_000, Duration::from_millis(1)).await.unwrap();
+            tokio::time::sleep(Duration::from_millis(1)).await;
+            if s1.rtt().is_some() && s2.rtt().is_some() {
+                break;
+            }
+        }
+        for rtt in [s1.rtt(), s2.rtt()] {
+            let rtt = rtt.expect("no rtt measured");
+            assert!(rtt > Duration::ZERO && rtt < Duration::from_millis(50));
+        }
+        drop((s1, s2));
+        assert_eq!(r1.await.unwrap(), Err(ProtocolError::Closed));
+        assert_eq!(r2.await.unwrap(), Err(ProtocolError::Closed));
+    }
+
+    #[tokio::test]
+    async fn ping_is_skipped_on_recv() {
+        let sid = Sid::new(1);
+        let (s, r) = async_channel::bounded(10);
+        let m = ProtocolMetricCache::new("tcp", Arc::new(ProtocolMetrics::new().unwrap()));
+        let mut r = super::TcpRecvProtocol::new(super::test_utils::TcpSink { receiver: r }, m);
+
+        let mut bytes = BytesMut::with_capacity(1500);
+        OTFrame::Ping { timestamp: 0 }.write_bytes(&mut bytes);
+        s.send(bytes.split()).await.unwrap();
+        OTFrame::Pong {
+            timestamp: 0,
+            delay: 0,
+        }
+        .write_bytes(&mut bytes);
+        OTFrame::CloseStream { sid }.write_bytes(&mut bytes);
+        s.send(bytes.split()).await.unwrap();
+
+        let e = r.recv().await.unwrap();
+        assert_eq!(e, ProtocolEvent::CloseStream { sid });
+    }
+
+    #[tokio::test]
+    async fn unknown_frame_closes_connection() {
+        let sid = Sid::new(1);
+        let (s, r) = async_channel::bounded(10);
+        let m = ProtocolMetricCache::new("tcp", Arc::new(ProtocolMetrics::new().unwrap()));
+        let mut r = super::TcpRecvProtocol::new(super::test_utils::TcpSink { receiver: r }, m);
+
+        let mut bytes = BytesMut::with_capacity(1500);
+        OTFrame::OpenStream {
+            sid,
+            prio: 5u8,
+            promises: Promises::COMPRESSED,
+            guaranteed_bandwidth: 1_000_000,
+            window: DEFAULT_STREAM_WINDOW,
+        }
+        .write_bytes(&mut bytes);
+        bytes.extend_from_slice(&[0x63, 1, 2, 3]);
+        // a valid frame after the unknown one must not be read
+        OTFrame::CloseStream { sid }.write_bytes(&mut bytes);
+        s.send(bytes.split()).await.unwrap();
+
+        let e = r.recv().await.unwrap();
+        assert!(matches!(e, ProtocolEvent::OpenStream { .. }));
+        assert_eq!(r.recv().await, Err(ProtocolError::UnknownFrame(0x63)));
+    }
+
     #[tokio::test]
     async fn drop_sink_while_recv() {
         let sid = Sid::new(1);
@@ -649,6 +1220,7 @@ mod tests {
             prio: 5u8,
             promises: Promises::COMPRESSED,
             guaranteed_bandwidth: 1_000_000,
+            window: DEFAULT_STREAM_WINDOW,
         }
         .write_bytes(&mut bytes);
         s.send(bytes.split()).await.unwrap();
@@ -673,6 +1245,7 @@ mod tests {
             prio: 3u8,
             promises: Promises::ORDERED,
             guaranteed_bandwidth: 1_000_000,
+            window: DEFAULT_STREAM_WINDOW,
         };
         p1.0.send(event).await.unwrap();
         let _ = p2.1.recv().await.unwrap();
@@ -696,6 +1269,7 @@ mod tests {
             prio: 3u8,
             promises: Promises::ORDERED,
             guaranteed_bandwidth: 1_000_000,
+            window: DEFAULT_STREAM_WINDOW,
         };
         p1.0.send(event).await.unwrap();
         let e = p2.1.recv().await.unwrap();
@@ -709,4 +1283,205 @@ mod tests {
         let e = p1.1.recv().await.unwrap();
         assert_eq!(event, e);
     }
+
+    #[tokio::test]
+    async fn window_caps_bytes_of_idle_receiver() {
+        let [p1, p2] = tcp_bound(10000, None);
+        let (mut s, mut r) = (p1.0, p2.1);
+        let sid = Sid::new(10);
+        s.send(ProtocolEvent::OpenStream {
+            sid,
+            prio: 3u8,
+            promises: Promises::ORDERED | Promises::GUARANTEED_DELIVERY,
+            guaranteed_bandwidth: 0,
+            window: 20_000,
+        })
+        .await
+        .unwrap();
+        let _ = r.recv().await.unwrap();
+        for i in 0..50u8 {
+            s.send(ProtocolEvent::Message {
+                sid,
+                data: Bytes::from(vec![i; 3_000]),
+            })
+            .await
+            .unwrap();
+        }
+        // the remote application doesn't read, so no window update is sent back
+        let mut received = 0;
+        for _ in 0..10 {
+            s.flush(1_000_000, Duration::from_secs(1)).await.unwrap();
+            while let Ok(Ok(ProtocolEvent::Message { data, .. })) =
+                tokio::time::timeout(Duration::from_millis(10), r.recv()).await
+            {
+                received += data.len();
+            }
+        }
+        assert_eq!(received, 18_000);
+
+        s.notify_from_recv(ProtocolEvent::WindowUpdate { sid, bytes: 6_000 });
+        s.flush(1_000_000, Duration::from_secs(1)).await.unwrap();
+        while let Ok(Ok(ProtocolEvent::Message { data, .. })) =
+            tokio::time::timeout(Duration::from_millis(10), r.recv()).await
+        {
+            received += data.len();
+        }
+        assert_eq!(received, 24_000);
+    }
+
+    #[tokio::test]
+    async fn window_update_frame() {
+        let [p1, p2] = tcp_bound(10, None);
+        let (mut s, mut r) = (p1.0, p2.1);
+        let event = ProtocolEvent::WindowUpdate {
+            sid: Sid::new(10),
+            bytes: 1_337,
+        };
+        s.send(event.clone()).await.unwrap();
+        s.flush(1_000_000, Duration::from_secs(1)).await.unwrap();
+        assert_eq!(r.recv().await.unwrap(), event);
+    }
+
+    async fn encrypted_bound() -> [(TcpSendProtocol<TcpDrain>, TcpRecvProtocol<TcpSink>); 2] {
+        let [p1, p2] = tcp_bound(10, None);
+        let mut p1 = (p1.0.with_encryption(NoiseKeys::default()), p1.1);
+        let mut p2 = (p2.0.with_encryption(NoiseKeys::default()), p2.1);
+        let r1 = tokio::spawn(async move {
+            let r = p1
+                .initialize(true, Pid::fake(2), 1337, VersionPolicy::default())
+                .await;
+            (p1, r)
+        });
+        let r2 = tokio::spawn(async move {
+            let r = p2
+                .initialize(false, Pid::fake(3), 42, VersionPolicy::default())
+                .await;
+            (p2, r)
+        });
+        let ((p1, r1), (p2, r2)) = (r1.await.unwrap(), r2.await.unwrap());
+        assert_eq!(r1, Ok((Pid::fake(3), STREAM_ID_OFFSET1, 42)));
+        assert_eq!(r2, Ok((Pid::fake(2), STREAM_ID_OFFSET2, 1337)));
+        [p1, p2]
+    }
+
+    #[tokio::test]
+    async fn encrypted_channel() {
+        let [p1, p2] = encrypted_bound().await;
+        let (mut s, mut r) = (p1.0, p2.1);
+        let event = ProtocolEvent::OpenStream {
+            sid: Sid::new(10),
+            prio: 3u8,
+            promises: Promises::ORDERED,
+            guaranteed_bandwidth: 1_000_000,
+            window: DEFAULT_STREAM_WINDOW,
+        };
+        s.send(event.clone()).await.unwrap();
+        assert_eq!(r.recv().await.unwrap(), event);
+        for len in [1, 600, 10_000] {
+            let event = ProtocolEvent::Message {
+                sid: Sid::new(10),
+                data: Bytes::from(vec![188u8; len]),
+            };
+            s.send(event.clone()).await.unwrap();
+            s.flush(1_000_000, Duration::from_secs(1)).await.unwrap();
+            assert_eq!(r.recv().await.unwrap(), event);
+        }
+    }
+
+    #[tokio::test]
+    async fn tampered_record_closes_connection() {
+        let [p1, p2] = encrypted_bound().await;
+        let (mut s, mut r) = (p1.0, p2.1);
+        s.send(ProtocolEvent::OpenStream {
+            sid: Sid::new(10),
+            prio: 3u8,
+            promises: Promises::ORDERED,
+            guaranteed_bandwidth: 1_000_000,
+            window: DEFAULT_STREAM_WINDOW,
+        })
+        .await
+        .unwrap();
+        s.send(ProtocolEvent::Message {
+            sid: Sid::new(10),
+            data: Bytes::from(&b"secret secret secret"[..]),
+        })
+        .await
+        .unwrap();
+        s.flush(1_000_000, Duration::from_secs(1)).await.unwrap();
+
+        let open = r.sink.receiver.recv().await.unwrap();
+        let mut records = r.sink.receiver.recv().await.unwrap();
+        assert!(!records.windows(6).any(|w| w == b"secret"));
+        let last = records.len() - 1;
+        records[last] ^= 1;
+        // records split across chunks are reassembled
+        let rest = records.split_off(5);
+        s.drain.sender.send(open).await.unwrap();
+        s.drain.sender.send(records).await.unwrap();
+        s.drain.sender.send(rest).await.unwrap();
+        assert!(matches!(
+            r.recv().await,
+            Ok(ProtocolEvent::OpenStream { .. })
+        ));
+        assert_eq!(r.recv().await, Err(ProtocolError::Violated));
+    }
+
+    #[tokio::test]
+    async fn encryption_mismatch() {
+        let [p1, mut p2] = tcp_bound(10, None);
+        let mut p1 = (p1.0.with_encryption(NoiseKeys::default()), p1.1);
+        let r1 = tokio::spawn(async move {
+            p1.initialize(true, Pid::fake(2), 1337, VersionPolicy::default())
+                .await
+        });
+        let r2 = tokio::spawn(async move {
+            p2.initialize(false, Pid::fake(3), 42, VersionPolicy::default())
+                .await
+        });
+        let (r1, r2) = tokio::join!(r1, r2);
+        assert_eq!(
+            r1.unwrap(),
+            Err(InitProtocolError::EncryptionMismatch {
+                local: true,
+                remote: false,
+            })
+        );
+        assert_eq!(
+            r2.unwrap(),
+            Err(InitProtocolError::EncryptionMismatch {
+                local: false,
+                remote: true,
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn pinned_server_key() {
+        let server_keys = NoiseKeys::default().with_private_key(NoiseKeys::generate_private_key());
+        let public = server_keys.public_key().unwrap();
+        for pinned in [public, NoisePublicKey([7; KEY_LEN])] {
+            let [p1, p2] = tcp_bound(10, None);
+            let mut server = (p1.0.with_encryption(server_keys.clone()), p1.1);
+            let client_keys = NoiseKeys::default().with_remote_key(pinned);
+            let mut client = (p2.0.with_encryption(client_keys), p2.1);
+            let r1 = tokio::spawn(async move {
+                server
+                    .initialize(true, Pid::fake(2), 1337, VersionPolicy::default())
+                    .await
+            });
+            let r2 = tokio::spawn(async move {
+                client
+                    .initialize(false, Pid::fake(3), 42, VersionPolicy::default())
+                    .await
+            });
+            let (r1, r2) = tokio::join!(r1, r2);
+            if pinned == public {
+                assert_eq!(r1.unwrap(), Ok((Pid::fake(3), STREAM_ID_OFFSET1, 42)));
+                assert_eq!(r2.unwrap(), Ok((Pid::fake(2), STREAM_ID_OFFSET2, 1337)));
+            } else {
+                assert_eq!(r1.unwrap(), Err(InitProtocolError::Closed));
+                assert_eq!(r2.unwrap(), Err(InitProtocolError::UntrustedKey));
+            }
+        }
+    }
 }
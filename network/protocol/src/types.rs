@@ -1,6 +1,7 @@
 use bitflags::bitflags;
 use bytes::{Buf, BufMut, BytesMut};
 use rand::Rng;
+use std::time::Duration;
 
 /// MessageID, unique ID per Message.
 pub type Mid = u64;
@@ -48,12 +49,75 @@ impl Promises {
 }
 
 pub(crate) const VELOREN_MAGIC_NUMBER: [u8; 7] = *b"VELOREN";
-/// When this semver differs, 2 Networks can't communicate.
-pub const VELOREN_NETWORK_VERSION: [u32; 3] = [0, 6, 0];
+/// When this semver differs, 2 Networks can't communicate. Bump it on
+/// incompatible changes to the game msg types too, the handshake is the only
+/// version check outdated clients are guaranteed to understand.
+pub const VELOREN_NETWORK_VERSION: [u32; 3] = [0, 16, 0];
 pub(crate) const STREAM_ID_OFFSET1: Sid = Sid::new(0);
 pub(crate) const STREAM_ID_OFFSET2: Sid = Sid::new(u64::MAX / 2);
 /// Maximal possible Prio to choose (for performance reasons)
 pub const HIGHEST_PRIO: u8 = 7;
+/// Bytes of messages a stream may have in flight before the receiving
+/// application consumed them, advertised when the stream is opened.
+/// Only streams with [`Promises::GUARANTEED_DELIVERY`] are flow controlled,
+/// lost messages would never be given back to the window otherwise.
+pub const DEFAULT_STREAM_WINDOW: u64 = 8 * 1024 * 1024;
+
+/// How strictly the network versions of 2 participants have to match during
+/// the handshake
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VersionPolicy {
+    /// Major, minor and patch version need to be equal
+    Exact,
+    /// Only major and minor version need to be equal, patches stay compatible
+    MajorMinor,
+}
+
+impl Default for VersionPolicy {
+    fn default() -> Self { VersionPolicy::MajorMinor }
+}
+
+impl VersionPolicy {
+    pub fn is_compatible(self, local: [u32; 3], remote: [u32; 3]) -> bool {
+        match self {
+            VersionPolicy::Exact => local == remote,
+            VersionPolicy::MajorMinor => local[..2] == remote[..2],
+        }
+    }
+
+    /// Oldest version a remote may have to still be accepted by `local`
+    pub fn min_compatible(self, local: [u32; 3]) -> [u32; 3] {
+        match self {
+            VersionPolicy::Exact => local,
+            VersionPolicy::MajorMinor => [local[0], local[1], 0],
+        }
+    }
+}
+
+/// Detects silently dead connections, e.g. after a NAT mapping was dropped.
+/// The [`SendProtocol`] sends a ping on its first flush and then every
+/// `interval`, along with whatever else is flushed, so a healthy remote never
+/// stays silent for longer than that. The pings also measure the round trip
+/// time, see [`ChannelRtt`]. The protocols have no timers, so the `timeout`
+/// after which a silent channel is considered dead needs to be enforced by
+/// the `UnreliableSink`.
+///
+/// [`SendProtocol`]: crate::SendProtocol
+/// [`ChannelRtt`]: crate::ChannelRtt
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeepAlive {
+    pub interval: Duration,
+    pub timeout: Duration,
+}
+
+impl Default for KeepAlive {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(5),
+            timeout: Duration::from_secs(20),
+        }
+    }
+}
 
 /// Support struct used for uniquely identifying `Participant` over the
 /// `Network`.
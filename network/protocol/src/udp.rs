@@ -1,37 +1,1505 @@
-// TODO: quick and dirty which activly waits for an ack!
-/*
-UDP protocol
-
-All Good Case:
-S --HEADER--> R
-S --DATA--> R
-S --DATA--> R
-S <--FINISHED-- R
-
-
-Delayed HEADER:
-S --HEADER-->
-S --DATA--> R // STORE IT
-  --HEADER--> R // apply left data and continue
-S --DATA--> R
-S <--FINISHED-- R
-
-
-NO HEADER:
-S --HEADER--> !
-S --DATA--> R // STORE IT
-S --DATA--> R // STORE IT
-S <--MISSING_HEADER-- R // SEND AFTER 10 ms after DATA1
-S --HEADER--> R
-S <--FINISHED-- R
-
-
-NO DATA:
-S --HEADER--> R
-S --DATA--> R
-S --DATA--> !
-S --STATUS--> R
-S <--MISSING_DATA -- R
-S --DATA--> R
-S <--FINISHED-- R
-*/
+//! UDP protocol
+//!
+//! Every datagram starts with a byte telling what it contains:
+//!  - `UDP_CONTROL`: a sequence number followed by `OpenStream`, `CloseStream`,
+//!    `WindowUpdate` and `Shutdown` frames, which get the sequence number and
+//!    the ones following it. These are retransmitted with an exponential
+//!    backoff until the remote acknowledges them, the receiver drops duplicates
+//!    and hands them out in sequence order.
+//!  - `UDP_ACK`: the sequence number of a received `UDP_CONTROL` datagram.
+//!  - `UDP_DATA`: a sequence number followed by `DataHeader` and `Data` frames,
+//!    these are fire-and-forget, a message missing a frame is never completed.
+//!    The receiver remembers the latest sequence numbers and drops duplicated
+//!    datagrams, which would otherwise apply the same frame twice. The sequence
+//!    numbers are 32 bits and wrap around. `Ping` and `Pong` frames to measure
+//!    the round trip time are sent along with them, a lost one only delays the
+//!    next measurement.
+//!
+//! Frames are sent on `flush`, as many of them are put into a datagram as fit
+//! in `MAX_DATAGRAM_SIZE`. A frame is never split between datagrams, one that
+//! is too long on its own gets a datagram of its own.
+//!
+//! Every datagram ends with a CRC32 of its content, datagrams that got
+//! corrupted on their way are dropped before they are parsed.
+//!
+//! With a [`ChannelCipher`] everything behind the sequence number is encrypted.
+//! The nonce is made of the kind and the sequence number, a datagram can't be
+//! replayed as another kind or number. Datagrams that can't be decrypted are
+//! dropped and counted as corrupted, a `UDP_ACK` carries the tag of an empty
+//! payload then. The 32 bit sequence numbers of `UDP_DATA` are extended to 64
+//! bits by the receiver, so nonces don't repeat when they wrap around.
+//!
+//! Datagrams that can't be parsed, e.g. because a frame claims to be longer
+//! than the datagram, are dropped and counted as malformed instead of closing
+//! the channel.
+//!
+//! At most `MAX_INCOMING_MESSAGES` messages are assembled at once, headers
+//! of further messages are dropped and counted, so a flooding remote can't
+//! make the receiver buffer without limit.
+//!
+//! Acks are received by the [`UdpRecvProtocol`] and sent by the
+//! [`UdpSendProtocol`] on the next `flush`, both halves of a channel share
+//! them via [`UdpAcks`].
+//!
+//! The handshake is sent in `UDP_INIT` datagrams, a sequence number followed
+//! by one init frame each. They are neither acknowledged nor retransmitted by
+//! the protocol, as nothing is flushed during the handshake. The transport has
+//! to replay them, see [`is_udp_handshake`]. The receiver hands them out in
+//! sequence order and drops duplicates, late ones are ignored after the
+//! handshake.
+use crate::{
+    error::ProtocolError,
+    event::ProtocolEvent,
+    frame::{ITFrame, InitFrame, OTFrame},
+    handshake::{ReliableDrain, ReliableSink},
+    message::{ITMessage, ALLOC_BLOCK},
+    metrics::{ProtocolMetricCache, RemoveReason, StreamObserver},
+    noise::{ChannelCipher, NoiseKeys, TAG_LEN},
+    prio::PrioManager,
+    rtt::ChannelRtt,
+    tcp::write_frame,
+    types::{Bandwidth, Mid, Promises, Sid},
+    RecvProtocol, SendProtocol, UnreliableDrain, UnreliableSink,
+};
+use async_trait::async_trait;
+use bytes::{Buf, BufMut, BytesMut};
+use hashbrown::HashMap;
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+#[cfg(feature = "trace_pedantic")]
+use tracing::trace;
+
+const UDP_CONTROL: u8 = 1;
+const UDP_ACK: u8 = 2;
+const UDP_DATA: u8 = 3;
+const UDP_INIT: u8 = 4;
+/// size of the CRC32 at the end of every datagram
+const CHECKSUM_SIZE: usize = 4;
+/// size of the kind and sequence number of `UDP_CONTROL` and `UDP_ACK`
+const CONTROL_HEADER_SIZE: usize = 9;
+/// size of the kind and sequence number of `UDP_DATA`
+const DATA_HEADER_SIZE: usize = 5;
+/// size of the kind and sequence number of `UDP_INIT`
+const INIT_HEADER_SIZE: usize = 2;
+/// datagrams are filled with frames up to this size, which doesn't get
+/// fragmented on common links
+const MAX_DATAGRAM_SIZE: usize = 1400;
+
+/// first retransmission of an unacknowledged control frame, doubled for every
+/// further one
+const RETRANSMIT_TIMEOUT: Duration = Duration::from_millis(200);
+/// How often a `Ping` is sent, see [`KeepAlive`]
+///
+/// [`KeepAlive`]: crate::KeepAlive
+const PING_INTERVAL: Duration = Duration::from_secs(5);
+const MAX_RETRANSMIT_TIMEOUT: Duration = Duration::from_secs(5);
+/// default bound of incomplete messages a [`UdpRecvProtocol`] buffers
+const MAX_INCOMING_MESSAGES: usize = 1024;
+/// default number of `UDP_DATA` sequence numbers a [`UdpRecvProtocol`]
+/// remembers to drop duplicates
+const DEDUP_WINDOW: u32 = 1024;
+const MAX_DEDUP_WINDOW: u32 = 1 << 20;
+/// handshake datagrams a [`UdpRecvProtocol`] keeps that arrived out of order
+const MAX_PENDING_INIT: usize = 16;
+
+#[derive(Debug, Default)]
+struct AckQueue {
+    /// control frames received that still need to be acknowledged
+    to_send: Vec<u64>,
+    /// acknowledgements received for our control frames
+    received: Vec<u64>,
+}
+
+/// Acknowledgements shared between the [`UdpSendProtocol`] and
+/// [`UdpRecvProtocol`] of a channel
+#[derive(Debug, Default, Clone)]
+pub struct UdpAcks(Arc<Mutex<AckQueue>>);
+
+/// Sequence numbers of the latest `UDP_DATA` datagrams received. A number is
+/// newer than another if it is less than half the number space ahead of it,
+/// so that they can wrap around.
+#[derive(Debug)]
+struct DedupWindow {
+    /// sequence number last stored in each slot, indexed by `seq % len`. The
+    /// length is a power of two, so the slots stay consistent on wraparound.
+    slots: Vec<Option<u32>>,
+    newest: Option<u32>,
+}
+
+impl DedupWindow {
+    fn new(size: u32) -> Self {
+        let size = size.clamp(1, MAX_DEDUP_WINDOW).next_power_of_two();
+        Self {
+            slots: vec![None; size as usize],
+            newest: None,
+        }
+    }
+
+    /// Remembers `seq`, returns false if it was already received. Numbers too
+    /// old to be in the window can't be told apart from duplicates anymore,
+    /// so they are rejected as well.
+    fn insert(&mut self, seq: u32) -> bool {
+        let size = self.slots.len() as u32;
+        match self.newest {
+            Some(newest) if newest.wrapping_sub(seq) <= u32::MAX / 2 => {
+                if newest.wrapping_sub(seq) >= size {
+                    return false;
+                }
+            },
+            _ => self.newest = Some(seq),
+        }
+        let slot = &mut self.slots[(seq % size) as usize];
+        if *slot == Some(seq) {
+            return false;
+        }
+        *slot = Some(seq);
+        true
+    }
+}
+
+#[derive(Debug)]
+struct Unacked {
+    datagram: BytesMut,
+    retransmit_at: Duration,
+    backoff: Duration,
+}
+
+/// UDP implementation of [`SendProtocol`]
+///
+/// [`SendProtocol`]: crate::SendProtocol
+#[derive(Debug)]
+pub struct UdpSendProtocol<D>
+where
+    D: UnreliableDrain<DataFormat = BytesMut>,
+{
+    buffer: BytesMut,
+    store: PrioManager,
+    next_mid: Mid,
+    closing_streams: Vec<Sid>,
+    notify_closing_streams: Vec<Sid>,
+    pending_shutdown: bool,
+    /// control frames sent on the next `flush`
+    control_frames: Vec<OTFrame>,
+    next_seq: u64,
+    /// only the lower 32 bits are sent
+    next_data_seq: u64,
+    unacked: BTreeMap<u64, Unacked>,
+    /// sum of all `dt` passed to `flush`, retransmissions are based on it
+    time: Duration,
+    /// `time` a `Ping` was last sent at
+    last_ping: Option<Duration>,
+    rtt: ChannelRtt,
+    acks: UdpAcks,
+    drain: D,
+    metrics: ProtocolMetricCache,
+    cipher: Option<ChannelCipher>,
+    encryption: Option<NoiseKeys>,
+    next_init_seq: u8,
+}
+
+/// UDP implementation of [`RecvProtocol`]
+///
+/// [`RecvProtocol`]: crate::RecvProtocol
+#[derive(Debug)]
+pub struct UdpRecvProtocol<S>
+where
+    S: UnreliableSink<DataFormat = BytesMut>,
+{
+    /// remaining frames of the last `UDP_DATA` datagram
+    buffer: BytesMut,
+    itmsg_allocator: BytesMut,
+    incoming: HashMap<Mid, ITMessage>,
+    /// message assembled for each stream, a stream sends its messages one
+    /// after another, so older ones can't be completed anymore
+    assembling: HashMap<Sid, Mid>,
+    max_incoming: usize,
+    next_seq: u64,
+    /// control frames received ahead of `next_seq`
+    pending_control: BTreeMap<u64, ITFrame>,
+    data_seqs: DedupWindow,
+    /// newest `UDP_DATA` sequence number received, extended to 64 bits
+    newest_data_seq: u64,
+    rtt: ChannelRtt,
+    acks: UdpAcks,
+    sink: S,
+    metrics: ProtocolMetricCache,
+    cipher: Option<ChannelCipher>,
+    next_init_seq: u8,
+    /// handshake datagrams received ahead of `next_init_seq`
+    pending_init: BTreeMap<u8, BytesMut>,
+}
+
+impl<D> UdpSendProtocol<D>
+where
+    D: UnreliableDrain<DataFormat = BytesMut>,
+{
+    pub fn new(drain: D, acks: UdpAcks, metrics: ProtocolMetricCache) -> Self {
+        Self {
+            buffer: BytesMut::new(),
+            store: PrioManager::new(metrics.clone()),
+            next_mid: 0u64,
+            closing_streams: vec![],
+            notify_closing_streams: vec![],
+            pending_shutdown: false,
+            control_frames: vec![],
+            next_seq: 0,
+            next_data_seq: 0,
+            unacked: BTreeMap::new(),
+            time: Duration::ZERO,
+            last_ping: None,
+            rtt: ChannelRtt::new(),
+            acks,
+            drain,
+            metrics,
+            cipher: None,
+            encryption: None,
+            next_init_seq: 0,
+        }
+    }
+
+    /// Encrypts all datagrams with the keys of a Noise handshake, the remote
+    /// side needs the matching [`ChannelCipher`]. Usually the handshake sets
+    /// it, see [`with_encryption`].
+    ///
+    /// [`with_encryption`]: Self::with_encryption
+    #[must_use]
+    pub fn with_cipher(mut self, cipher: ChannelCipher) -> Self {
+        self.cipher = Some(cipher);
+        self
+    }
+
+    /// Requires the channel to be encrypted, the handshake fails if the remote
+    /// side doesn't encrypt as well. `keys` authenticate us or the remote,
+    /// see [`NoiseKeys`] and [`ChannelCipher`].
+    #[must_use]
+    pub fn with_encryption(mut self, keys: NoiseKeys) -> Self {
+        self.encryption = Some(keys);
+        self
+    }
+
+    /// Shares the round trip time with the [`UdpRecvProtocol`] of the same
+    /// channel, which is needed to answer pings and measure it
+    #[must_use]
+    pub fn with_rtt(mut self, rtt: ChannelRtt) -> Self {
+        self.rtt = rtt;
+        self
+    }
+
+    /// Smoothed round trip time of the channel, see [`ChannelRtt`]
+    pub fn rtt(&self) -> Option<Duration> { self.rtt.rtt() }
+
+    /// Streams that still have messages queued, they are lost along with the
+    /// channel
+    pub fn queued_streams(&self) -> Vec<Sid> { self.store.queued_streams() }
+
+    /// returns all promises that this Protocol can take care of
+    /// If you open a Stream anyway, unsupported promises are ignored.
+    pub fn supported_promises() -> Promises { Promises::COMPRESSED }
+
+    /// Sends the queued control frames, each datagram is retransmitted until
+    /// it is acknowledged
+    async fn send_control_frames(&mut self) -> Result<(), ProtocolError> {
+        let frames = std::mem::take(&mut self.control_frames);
+        let max_len = self.max_payload(CONTROL_HEADER_SIZE);
+        for (count, payload) in batch_frames(frames, max_len, &mut self.metrics) {
+            let seq = self.next_seq;
+            self.next_seq += count;
+            self.buffer.put_u8(UDP_CONTROL);
+            self.buffer.put_u64_le(seq);
+            self.buffer.unsplit(payload);
+            self.encrypt(CONTROL_HEADER_SIZE, nonce(UDP_CONTROL, seq));
+            let datagram = seal(&mut self.buffer);
+            self.unacked.insert(seq, Unacked {
+                datagram: datagram.clone(),
+                retransmit_at: self.time + RETRANSMIT_TIMEOUT,
+                backoff: RETRANSMIT_TIMEOUT,
+            });
+            self.drain.send(datagram).await?;
+        }
+        Ok(())
+    }
+
+    /// Space left for frames in a datagram with a header of `header_size`
+    fn max_payload(&self, header_size: usize) -> usize {
+        let tag_len = if self.cipher.is_some() { TAG_LEN } else { 0 };
+        MAX_DATAGRAM_SIZE - header_size - tag_len - CHECKSUM_SIZE
+    }
+
+    /// Encrypts the datagram in `buffer` behind its header, if the channel is
+    /// encrypted
+    fn encrypt(&mut self, header_size: usize, nonce: u64) {
+        if let Some(cipher) = &self.cipher {
+            let payload = self.buffer.split_off(header_size);
+            cipher.encrypt(nonce, &payload, &mut self.buffer);
+        }
+    }
+
+    /// Reports the messages of every stream to `observer`, see
+    /// [`StreamObserver`]
+    pub fn set_stream_observer(&mut self, observer: Arc<dyn StreamObserver>) {
+        self.metrics.set_stream_observer(observer);
+    }
+}
+
+impl<S> UdpRecvProtocol<S>
+where
+    S: UnreliableSink<DataFormat = BytesMut>,
+{
+    pub fn new(sink: S, acks: UdpAcks, metrics: ProtocolMetricCache) -> Self {
+        Self {
+            buffer: BytesMut::new(),
+            itmsg_allocator: BytesMut::with_capacity(ALLOC_BLOCK),
+            incoming: HashMap::new(),
+            assembling: HashMap::new(),
+            max_incoming: MAX_INCOMING_MESSAGES,
+            next_seq: 0,
+            pending_control: BTreeMap::new(),
+            data_seqs: DedupWindow::new(DEDUP_WINDOW),
+            newest_data_seq: 0,
+            rtt: ChannelRtt::new(),
+            acks,
+            sink,
+            metrics,
+            cipher: None,
+            next_init_seq: 0,
+            pending_init: BTreeMap::new(),
+        }
+    }
+
+    /// Decrypts all datagrams with the keys of a Noise handshake, see
+    /// [`UdpSendProtocol::with_cipher`]
+    #[must_use]
+    pub fn with_cipher(mut self, cipher: ChannelCipher) -> Self {
+        self.cipher = Some(cipher);
+        self
+    }
+
+    /// Shares the round trip time with the [`UdpSendProtocol`] of the same
+    /// channel, which is needed to answer pings and measure it
+    #[must_use]
+    pub fn with_rtt(mut self, rtt: ChannelRtt) -> Self {
+        self.rtt = rtt;
+        self
+    }
+
+    /// Smoothed round trip time of the channel, see [`ChannelRtt`]
+    pub fn rtt(&self) -> Option<Duration> { self.rtt.rtt() }
+
+    /// Sets how many incomplete messages are buffered, defaults to 1024
+    #[must_use]
+    pub fn with_max_incoming(mut self, max_incoming: usize) -> Self {
+        self.max_incoming = max_incoming;
+        self
+    }
+
+    /// Sets how many `UDP_DATA` sequence numbers are remembered to drop
+    /// duplicates, rounded up to a power of two. Defaults to 1024, datagrams
+    /// delayed by more than that many others are dropped.
+    #[must_use]
+    pub fn with_dedup_window(mut self, size: u32) -> Self {
+        self.data_seqs = DedupWindow::new(size);
+        self
+    }
+
+    /// Drops the remaining frames of a malformed `UDP_DATA` datagram. Frames
+    /// of a datagram aren't validated upfront, so frames before the malformed
+    /// one might already have been handed out.
+    fn skip_datagram(&mut self) {
+        #[cfg(feature = "trace_pedantic")]
+        trace!(len = self.buffer.len(), "skip malformed datagram");
+        self.metrics.malformed_frame();
+        self.buffer.clear();
+    }
+
+    /// Decrypts the payload of a datagram if the channel is encrypted, ones
+    /// that were tampered with are dropped and counted as corrupted
+    fn decrypt(&mut self, nonce: u64, payload: BytesMut) -> Option<BytesMut> {
+        let cipher = match &self.cipher {
+            Some(cipher) => cipher,
+            None => return Some(payload),
+        };
+        let mut plaintext = BytesMut::with_capacity(payload.len());
+        if cipher.decrypt(nonce, &payload, &mut plaintext) {
+            Some(plaintext)
+        } else {
+            #[cfg(feature = "trace_pedantic")]
+            trace!(?nonce, "drop datagram that can't be decrypted");
+            self.metrics.corrupted_datagram();
+            None
+        }
+    }
+
+    /// Reports the messages of every stream to `observer`, see
+    /// [`StreamObserver`]
+    pub fn set_stream_observer(&mut self, observer: Arc<dyn StreamObserver>) {
+        self.metrics.set_stream_observer(observer);
+    }
+}
+
+#[async_trait]
+impl<D> SendProtocol for UdpSendProtocol<D>
+where
+    D: UnreliableDrain<DataFormat = BytesMut>,
+{
+    fn notify_from_recv(&mut self, event: ProtocolEvent) {
+        match event {
+            ProtocolEvent::OpenStream {
+                sid,
+                prio,
+                promises,
+                guaranteed_bandwidth,
+                window,
+            } => {
+                self.store
+                    .open_stream(sid, prio, promises, guaranteed_bandwidth, window);
+            },
+            ProtocolEvent::WindowUpdate { sid, bytes } => self.store.window_update(sid, bytes),
+            ProtocolEvent::CloseStream { sid } => {
+                self.store.release_window(sid);
+                if !self.store.try_close_stream(sid) {
+                    #[cfg(feature = "trace_pedantic")]
+                    trace!(?sid, "hold back notify close stream");
+                    self.notify_closing_streams.push(sid);
+                }
+            },
+            _ => {},
+        }
+    }
+
+    async fn send(&mut self, event: ProtocolEvent) -> Result<(), ProtocolError> {
+        #[cfg(feature = "trace_pedantic")]
+        trace!(?event, "send");
+        match event {
+            ProtocolEvent::OpenStream {
+                sid,
+                prio,
+                promises,
+                guaranteed_bandwidth,
+                window,
+            } => {
+                self.store
+                    .open_stream(sid, prio, promises, guaranteed_bandwidth, window);
+                self.control_frames.push(event.to_frame());
+            },
+            // a lost window update would stall the stream for good
+            ProtocolEvent::WindowUpdate { .. } => self.control_frames.push(event.to_frame()),
+            ProtocolEvent::CloseStream { sid } => {
+                if self.store.try_close_stream(sid) {
+                    self.control_frames.push(event.to_frame());
+                } else {
+                    #[cfg(feature = "trace_pedantic")]
+                    trace!(?sid, "hold back close stream");
+                    self.closing_streams.push(sid);
+                }
+            },
+            ProtocolEvent::Shutdown => {
+                if self.store.is_empty() {
+                    self.control_frames.push(event.to_frame());
+                } else {
+                    #[cfg(feature = "trace_pedantic")]
+                    trace!("hold back shutdown");
+                    self.pending_shutdown = true;
+                }
+            },
+            ProtocolEvent::Message { data, sid } => {
+                self.metrics.smsg_ib(sid, data.len() as u64);
+                self.store.add(data, self.next_mid, sid);
+                self.next_mid += 1;
+            },
+        }
+        Ok(())
+    }
+
+    async fn flush(
+        &mut self,
+        bandwidth: Bandwidth,
+        dt: Duration,
+    ) -> Result</* actual */ Bandwidth, ProtocolError> {
+        self.time += dt;
+        let (to_send, received) = {
+            let mut acks = self.acks.0.lock().unwrap();
+            (
+                std::mem::take(&mut acks.to_send),
+                std::mem::take(&mut acks.received),
+            )
+        };
+        for seq in received {
+            self.unacked.remove(&seq);
+        }
+        for seq in to_send {
+            self.buffer.put_u8(UDP_ACK);
+            self.buffer.put_u64_le(seq);
+            self.encrypt(CONTROL_HEADER_SIZE, nonce(UDP_ACK, seq));
+            self.drain.send(seal(&mut self.buffer)).await?;
+        }
+        for unacked in self.unacked.values_mut() {
+            if unacked.retransmit_at <= self.time {
+                unacked.backoff = (unacked.backoff * 2).min(MAX_RETRANSMIT_TIMEOUT);
+                unacked.retransmit_at = self.time + unacked.backoff;
+                self.drain.send(unacked.datagram.clone()).await?;
+            }
+        }
+        // streams are opened before their first message is sent
+        self.send_control_frames().await?;
+
+        let (frames, _) = self.store.grab(bandwidth, dt);
+        let mut data_frames = 0;
+        let mut data_bandwidth = 0;
+        for (_, frame) in &frames {
+            if let OTFrame::Data { mid: _, data } = frame {
+                data_bandwidth += data.len();
+                data_frames += 1;
+            }
+        }
+        let mut frames = frames
+            .into_iter()
+            .map(|(_, frame)| frame)
+            .collect::<Vec<_>>();
+        // pings go out with the data frames, if there are any
+        if let Some((timestamp, delay)) = self.rtt.take_echo() {
+            frames.push(OTFrame::Pong { timestamp, delay });
+        }
+        if self
+            .last_ping
+            .map_or(true, |last| self.time >= last + PING_INTERVAL)
+        {
+            let timestamp = self.rtt.timestamp();
+            frames.push(OTFrame::Ping { timestamp });
+            self.last_ping = Some(self.time);
+        }
+        let max_len = self.max_payload(DATA_HEADER_SIZE);
+        for (_, payload) in batch_frames(frames, max_len, &mut self.metrics) {
+            let seq = self.next_data_seq;
+            self.next_data_seq += 1;
+            self.buffer.put_u8(UDP_DATA);
+            self.buffer.put_u32_le(seq as u32);
+            self.buffer.unsplit(payload);
+            self.encrypt(DATA_HEADER_SIZE, nonce(UDP_DATA, seq));
+            self.drain.send(seal(&mut self.buffer)).await?;
+        }
+        self.metrics
+            .sdata_frames_b(data_frames, data_bandwidth as u64);
+
+        let mut finished_streams = vec![];
+        for (i, &sid) in self.closing_streams.iter().enumerate() {
+            if self.store.try_close_stream(sid) {
+                #[cfg(feature = "trace_pedantic")]
+                trace!(?sid, "close stream, as it's now empty");
+                finished_streams.push(i);
+            }
+        }
+        for i in finished_streams.into_iter().rev() {
+            let sid = self.closing_streams.remove(i);
+            self.control_frames.push(OTFrame::CloseStream { sid });
+        }
+
+        self.notify_closing_streams
+            .retain(|&sid| !self.store.try_close_stream(sid));
+
+        if self.pending_shutdown && self.store.is_empty() {
+            #[cfg(feature = "trace_pedantic")]
+            trace!("shutdown, as it's now empty");
+            self.control_frames.push(OTFrame::Shutdown);
+            self.pending_shutdown = false;
+        }
+        self.send_control_frames().await?;
+        Ok(data_bandwidth as u64)
+    }
+}
+
+/// Writes `frames` into as few payloads as possible, none longer than
+/// `max_len` unless a single frame is. Returns the number of frames in each
+/// payload along with it.
+fn batch_frames(
+    frames: impl IntoIterator<Item = OTFrame>,
+    max_len: usize,
+    metrics: &mut ProtocolMetricCache,
+) -> Vec<(u64, BytesMut)> {
+    let mut payloads = vec![];
+    let mut payload = BytesMut::new();
+    let mut count = 0;
+    for frame in frames {
+        let start = payload.len();
+        write_frame(frame, &mut payload, metrics);
+        if payload.len() > max_len && start > 0 {
+            let frame = payload.split_off(start);
+            payloads.push((count, std::mem::replace(&mut payload, frame)));
+            count = 0;
+        }
+        count += 1;
+    }
+    if count > 0 {
+        payloads.push((count, payload));
+    }
+    payloads
+}
+
+/// Appends the checksum to the datagram in `buffer` and splits it off
+fn seal(buffer: &mut BytesMut) -> BytesMut {
+    let checksum = crc32fast::hash(buffer);
+    buffer.put_u32_le(checksum);
+    buffer.split()
+}
+
+/// Nonce of the encrypted payload of a datagram
+fn nonce(kind: u8, seq: u64) -> u64 { u64::from(kind) << 60 | seq }
+
+/// Extends the 32 bit sequence number of a `UDP_DATA` datagram to the 64 bit
+/// one closest to `newest`
+fn extend_data_seq(newest: u64, seq: u32) -> u64 {
+    const SPAN: u64 = 1 << 32;
+    let extended = (newest & !(SPAN - 1)) | u64::from(seq);
+    if extended > newest && extended - newest > SPAN / 2 && extended >= SPAN {
+        extended - SPAN
+    } else if extended < newest && newest - extended > SPAN / 2 {
+        extended + SPAN
+    } else {
+        extended
+    }
+}
+
+/// Checks and removes the checksum at the end of `datagram`
+fn verify(datagram: &mut BytesMut) -> bool {
+    if datagram.len() < CHECKSUM_SIZE {
+        return false;
+    }
+    let content = datagram.len() - CHECKSUM_SIZE;
+    let checksum = u32::from_le_bytes(datagram[content..].try_into().unwrap());
+    datagram.truncate(content);
+    crc32fast::hash(datagram) == checksum
+}
+
+#[async_trait]
+impl<S> RecvProtocol for UdpRecvProtocol<S>
+where
+    S: UnreliableSink<DataFormat = BytesMut>,
+{
+    async fn recv(&mut self) -> Result<ProtocolEvent, ProtocolError> {
+        loop {
+            if let Some(frame) = self.pending_control.remove(&self.next_seq) {
+                self.next_seq += 1;
+                match frame {
+                    ITFrame::Shutdown => return Ok(ProtocolEvent::Shutdown),
+                    ITFrame::OpenStream {
+                        sid,
+                        prio,
+                        promises,
+                        guaranteed_bandwidth,
+                        window,
+                    } => {
+                        return Ok(ProtocolEvent::OpenStream {
+                            sid,
+                            prio: prio.min(crate::types::HIGHEST_PRIO),
+                            promises,
+                            guaranteed_bandwidth,
+                            window,
+                        });
+                    },
+                    ITFrame::CloseStream { sid } => {
+                        return Ok(ProtocolEvent::CloseStream { sid });
+                    },
+                    ITFrame::WindowUpdate { sid, bytes } => {
+                        return Ok(ProtocolEvent::WindowUpdate { sid, bytes });
+                    },
+                    _ => unreachable!("only control frames are sent reliably"),
+                }
+            }
+
+            let before = self.buffer.len();
+            match ITFrame::read_frame(&mut self.buffer) {
+                Ok(Some(frame)) => {
+                    #[cfg(feature = "trace_pedantic")]
+                    trace!(?frame, "recv");
+                    self.metrics
+                        .rframes_b(frame.id(), (before - self.buffer.len()) as u64);
+                    match frame {
+                        ITFrame::DataHeader { sid, mid, length } => {
+                            if let Some(old) = self.assembling.insert(sid, mid) {
+                                if let Some(m) = self.incoming.remove(&old) {
+                                    #[cfg(feature = "trace_pedantic")]
+                                    trace!(?sid, ?old, "drop superseded message");
+                                    self.metrics.rmsg_ob(
+                                        m.sid,
+                                        RemoveReason::Dropped,
+                                        m.len() as u64,
+                                    );
+                                }
+                            }
+                            if self.incoming.len() >= self.max_incoming {
+                                // the data frames of the message are skipped like a lost header
+                                self.metrics.dropped_frame();
+                                continue;
+                            }
+                            let m = ITMessage::new(sid, length, &mut self.itmsg_allocator);
+                            self.metrics.rmsg_ib(sid, length);
+                            self.incoming.insert(mid, m);
+                        },
+                        ITFrame::Data { mid, data } => {
+                            self.metrics.rdata_frames_b(data.len() as u64);
+                            // the header got lost
+                            let m = match self.incoming.get_mut(&mid) {
+                                Some(m) => m,
+                                None => continue,
+                            };
+                            if m.len() + data.len() > m.length as usize {
+                                let m = self.incoming.remove(&mid).unwrap();
+                                self.metrics
+                                    .rmsg_ob(m.sid, RemoveReason::Dropped, m.len() as u64);
+                                self.skip_datagram();
+                                continue;
+                            }
+                            m.push(data);
+                            if m.is_complete() {
+                                let m = self.incoming.remove(&mid).unwrap();
+                                self.assembling.remove(&m.sid);
+                                self.metrics
+                                    .rmsg_ob(m.sid, RemoveReason::Finished, m.len() as u64);
+                                return Ok(ProtocolEvent::Message {
+                                    sid: m.sid,
+                                    data: m.into_data(),
+                                });
+                            }
+                        },
+                        ITFrame::Ping { timestamp } => self.rtt.ping_received(timestamp),
+                        ITFrame::Pong { timestamp, delay } => {
+                            if let Some(rtt) = self.rtt.pong_received(timestamp, delay) {
+                                self.metrics.ping(rtt);
+                            }
+                        },
+                        // control frames are only valid in `UDP_CONTROL` datagrams
+                        _ => self.skip_datagram(),
+                    }
+                    continue;
+                },
+                // a datagram never continues in the next one, so this frame is truncated
+                Ok(None) if !self.buffer.is_empty() => self.skip_datagram(),
+                Ok(None) => {},
+                Err(_) => self.skip_datagram(),
+            }
+
+            let mut datagram = self.sink.recv().await?;
+            if !verify(&mut datagram) {
+                #[cfg(feature = "trace_pedantic")]
+                trace!(len = datagram.len(), "drop corrupted datagram");
+                self.metrics.corrupted_datagram();
+                continue;
+            }
+            let tag_len = if self.cipher.is_some() { TAG_LEN } else { 0 };
+            match datagram.get(0) {
+                Some(&UDP_CONTROL) if datagram.len() > CONTROL_HEADER_SIZE => {
+                    datagram.advance(1);
+                    let seq = datagram.get_u64_le();
+                    let mut datagram = match self.decrypt(nonce(UDP_CONTROL, seq), datagram) {
+                        Some(datagram) => datagram,
+                        None => continue,
+                    };
+                    // the frames are only handed out if all of them are valid
+                    let mut frames = vec![];
+                    while !datagram.is_empty() {
+                        let before = datagram.len();
+                        match ITFrame::read_frame(&mut datagram) {
+                            Ok(Some(
+                                frame @ (ITFrame::Shutdown
+                                | ITFrame::OpenStream { .. }
+                                | ITFrame::CloseStream { .. }
+                                | ITFrame::WindowUpdate { .. }),
+                            )) => frames.push((frame, before - datagram.len())),
+                            _ => {
+                                frames.clear();
+                                break;
+                            },
+                        }
+                    }
+                    if frames.is_empty() {
+                        self.metrics.malformed_frame();
+                        continue;
+                    }
+                    // the ack might have been lost, so duplicates are acked as well
+                    self.acks.0.lock().unwrap().to_send.push(seq);
+                    // datagrams are retransmitted as a whole, so the first frame
+                    // tells whether all of them were received already
+                    if seq < self.next_seq || self.pending_control.contains_key(&seq) {
+                        #[cfg(feature = "trace_pedantic")]
+                        trace!(?seq, "drop duplicated control frames");
+                        continue;
+                    }
+                    for (i, (frame, len)) in frames.into_iter().enumerate() {
+                        self.metrics.rframes_b(frame.id(), len as u64);
+                        self.pending_control
+                            .insert(seq.wrapping_add(i as u64), frame);
+                    }
+                },
+                Some(&UDP_ACK) if datagram.len() == CONTROL_HEADER_SIZE + tag_len => {
+                    datagram.advance(1);
+                    let seq = datagram.get_u64_le();
+                    if self.decrypt(nonce(UDP_ACK, seq), datagram).is_none() {
+                        continue;
+                    }
+                    self.acks.0.lock().unwrap().received.push(seq);
+                },
+                Some(&UDP_DATA) if datagram.len() >= DATA_HEADER_SIZE => {
+                    datagram.advance(1);
+                    let seq = datagram.get_u32_le();
+                    let extended = extend_data_seq(self.newest_data_seq, seq);
+                    let datagram = match self.decrypt(nonce(UDP_DATA, extended), datagram) {
+                        Some(datagram) => datagram,
+                        None => continue,
+                    };
+                    if !self.data_seqs.insert(seq) {
+                        #[cfg(feature = "trace_pedantic")]
+                        trace!(?seq, "drop duplicated data datagram");
+                        self.metrics.duplicated_datagram();
+                        continue;
+                    }
+                    self.newest_data_seq = self.newest_data_seq.max(extended);
+                    self.buffer = datagram;
+                },
+                // a late or replayed datagram of the handshake
+                Some(&UDP_INIT) => {},
+                _ => self.metrics.malformed_frame(),
+            }
+        }
+    }
+}
+
+/// Whether `datagram` belongs to the handshake of a UDP channel. These aren't
+/// retransmitted by the protocol, a transport replays the ones it sent until
+/// the handshake is done. It replays them later on as well if the remote
+/// repeats its handshake datagrams, as that means the remote missed the last
+/// ones.
+pub fn is_udp_handshake(datagram: &[u8]) -> bool { datagram.first() == Some(&UDP_INIT) }
+
+#[async_trait]
+impl<D> ReliableDrain for UdpSendProtocol<D>
+where
+    D: UnreliableDrain<DataFormat = BytesMut>,
+{
+    async fn send(&mut self, frame: InitFrame) -> Result<(), ProtocolError> {
+        let seq = self.next_init_seq;
+        self.next_init_seq += 1;
+        self.buffer.put_u8(UDP_INIT);
+        self.buffer.put_u8(seq);
+        let id = frame.id();
+        frame.write_bytes(&mut self.buffer);
+        self.metrics
+            .sframes_b(id, (self.buffer.len() - INIT_HEADER_SIZE) as u64);
+        self.encrypt(INIT_HEADER_SIZE, nonce(UDP_INIT, u64::from(seq)));
+        self.drain.send(seal(&mut self.buffer)).await
+    }
+
+    fn encryption(&self) -> Option<&NoiseKeys> { self.encryption.as_ref() }
+
+    fn start_encryption(&mut self, cipher: ChannelCipher) -> Result<(), ProtocolError> {
+        self.cipher = Some(cipher);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<S> ReliableSink for UdpRecvProtocol<S>
+where
+    S: UnreliableSink<DataFormat = BytesMut>,
+{
+    async fn recv(&mut self) -> Result<InitFrame, ProtocolError> {
+        loop {
+            let seq = self.next_init_seq;
+            let datagram = match self.pending_init.remove(&seq) {
+                Some(datagram) => datagram,
+                None => {
+                    let mut datagram = self.sink.recv().await?;
+                    if !verify(&mut datagram) {
+                        self.metrics.corrupted_datagram();
+                        continue;
+                    }
+                    // datagrams of a remote that finished its handshake already are
+                    // dropped, control frames get retransmitted
+                    if !is_udp_handshake(&datagram) || datagram.len() < INIT_HEADER_SIZE {
+                        continue;
+                    }
+                    let received = datagram[1];
+                    datagram.advance(INIT_HEADER_SIZE);
+                    if received < seq {
+                        self.metrics.duplicated_datagram();
+                        continue;
+                    }
+                    if received > seq {
+                        // decrypted once it's its turn, the cipher might not be known yet
+                        if self.pending_init.len() < MAX_PENDING_INIT {
+                            self.pending_init.insert(received, datagram);
+                        }
+                        continue;
+                    }
+                    datagram
+                },
+            };
+            let mut datagram = match self.decrypt(nonce(UDP_INIT, u64::from(seq)), datagram) {
+                Some(datagram) => datagram,
+                None => continue,
+            };
+            let len = datagram.len();
+            match InitFrame::read_frame(&mut datagram) {
+                Ok(Some(frame)) if datagram.is_empty() => {
+                    self.next_init_seq += 1;
+                    self.metrics.rframes_b(frame.id(), len as u64);
+                    return Ok(frame);
+                },
+                _ => self.metrics.malformed_frame(),
+            }
+        }
+    }
+
+    fn start_encryption(&mut self, cipher: ChannelCipher) -> Result<(), ProtocolError> {
+        self.cipher = Some(cipher);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        extend_data_seq, nonce, seal, DedupWindow, UdpAcks, UdpRecvProtocol, UdpSendProtocol,
+        DATA_HEADER_SIZE, MAX_DATAGRAM_SIZE, UDP_DATA, UDP_INIT,
+    };
+    use crate::{
+        error::ProtocolError,
+        frame::{InitFrame, OTFrame},
+        metrics::{ProtocolMetricCache, ProtocolMetrics},
+        noise::{cipher_pair, NoiseKeys},
+        sim::{sim_link, sim_udp_bound, SimConfig, SimDrain},
+        types::{
+            Pid, Promises, Sid, VersionPolicy, DEFAULT_STREAM_WINDOW, STREAM_ID_OFFSET1,
+            STREAM_ID_OFFSET2, VELOREN_MAGIC_NUMBER, VELOREN_NETWORK_VERSION,
+        },
+        InitProtocol, ProtocolEvent, RecvProtocol, SendProtocol, UnreliableDrain,
+    };
+    use async_trait::async_trait;
+    use bytes::{BufMut, Bytes, BytesMut};
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+    use std::{sync::Arc, time::Duration};
+    use tokio::time::{sleep, Instant};
+
+    #[tokio::test(start_paused = true)]
+    async fn control_frames_survive_loss() {
+        const TICK: Duration = Duration::from_millis(10);
+        let config = SimConfig::perfect()
+            .with_latency(Duration::from_millis(30), Duration::from_millis(30))
+            .unreliable();
+        let [(mut s1, mut r1), (mut s2, mut r2)] = sim_udp_bound(config, None);
+        // r1 only receives acks
+        let r1 = tokio::spawn(async move { r1.recv().await });
+
+        let sid = Sid::new(1);
+        let open = ProtocolEvent::OpenStream {
+            sid,
+            prio: 5u8,
+            promises: Promises::empty(),
+            guaranteed_bandwidth: 0,
+            window: DEFAULT_STREAM_WINDOW,
+        };
+        s1.send(open.clone()).await.unwrap();
+        s1.send(ProtocolEvent::Message {
+            sid,
+            data: Bytes::from(&b"fire and forget"[..]),
+        })
+        .await
+        .unwrap();
+        s1.send(ProtocolEvent::CloseStream { sid }).await.unwrap();
+        s1.send(ProtocolEvent::Shutdown).await.unwrap();
+        // the control frames go out first
+        s1.drain.lose_next(1);
+
+        let start = Instant::now();
+        let mut events = vec![];
+        while events.len() < 3 || !s1.unacked.is_empty() {
+            assert!(
+                start.elapsed() < Duration::from_secs(60),
+                "did not converge"
+            );
+            s1.flush(1_000_000, TICK).await.unwrap();
+            s2.flush(1_000_000, TICK).await.unwrap();
+            tokio::select! {
+                event = r2.recv() => match event.unwrap() {
+                    ProtocolEvent::Message { .. } => {},
+                    event => {
+                        // the ack goes out first, so the retransmitted
+                        // control frames arrive twice
+                        if events.is_empty() {
+                            s2.drain.lose_next(1);
+                        }
+                        events.push(event);
+                    },
+                },
+                _ = sleep(TICK) => {},
+            }
+        }
+        assert_eq!(events, vec![
+            open,
+            ProtocolEvent::CloseStream { sid },
+            ProtocolEvent::Shutdown
+        ]);
+
+        assert_eq!((s1.drain.lost, s2.drain.lost), (1, 1));
+
+        // duplicates still in flight aren't delivered again
+        for _ in 0..100 {
+            s1.flush(1_000_000, TICK).await.unwrap();
+            s2.flush(1_000_000, TICK).await.unwrap();
+            tokio::select! {
+                event = r2.recv() => panic!("unexpected event {:?}", event),
+                _ = sleep(TICK) => {},
+            }
+        }
+        drop((s1, s2));
+        assert!(r1.await.unwrap().is_err());
+    }
+
+    #[tokio::test]
+    async fn rtt_is_measured() {
+        let config = SimConfig::perfect().unreliable();
+        let [(mut s1, mut r1), (mut s2, mut r2)] = sim_udp_bound(config, None);
+        assert_eq!(s1.rtt(), None);
+        // pings and pongs are handled while waiting for an event
+        let r1 = tokio::spawn(async move { r1.recv().await });
+        let r2 = tokio::spawn(async move { r2.recv().await });
+        for _ in 0..100 {
+            s1.flush(1_000_000, Duration::from_millis(1)).await.unwrap();
+            s2.flush(1_000_000, Duration::from_millis(1)).await.unwrap();
+            sleep(Duration::from_millis(1)).await;
+            if s1.rtt().is_some() && s2.rtt().is_some() {
+                break;
+            }
+        }
+        for rtt in [s1.rtt(), s2.rtt()] {
+            let rtt = rtt.expect("no rtt measured");
+            assert!(rtt > Duration::ZERO && rtt < Duration::from_millis(50));
+        }
+        r1.abort();
+        r2.abort();
+    }
+
+    /// random garbage is skipped and the channel stays usable
+    #[tokio::test(start_paused = true)]
+    async fn random_datagrams_are_skipped() {
+        let config = SimConfig::perfect().unreliable();
+        // s2 pings on its first flush, so r1 has to stay around
+        let [(mut s1, _r1), (mut s2, mut r2)] = sim_udp_bound(config, None);
+        let mut rng = StdRng::seed_from_u64(42);
+        for _ in 0..10_000 {
+            let len = rng.gen_range(0..64);
+            let mut datagram = BytesMut::with_capacity(len);
+            for i in 0..len {
+                // make sure every datagram kind is hit regularly
+                let byte = if i == 0 {
+                    rng.gen_range(0..5)
+                } else {
+                    rng.gen()
+                };
+                datagram.put_u8(byte);
+            }
+            // with a valid checksum, so the garbage reaches the parser
+            s1.drain.send(seal(&mut datagram)).await.unwrap();
+        }
+        s1.send(ProtocolEvent::Shutdown).await.unwrap();
+        s1.flush(1_000_000, Duration::ZERO).await.unwrap();
+        s2.flush(1_000_000, Duration::ZERO).await.unwrap();
+        assert_eq!(r2.recv().await, Ok(ProtocolEvent::Shutdown));
+        assert!(r2.metrics.malformed_frames() > 0);
+    }
+
+    /// a `UDP_DATA` datagram containing a whole message, without checksum
+    fn data_datagram(seq: u32, mid: u64, length: u64, data: &'static [u8]) -> BytesMut {
+        stream_datagram(seq, Sid::new(1), mid, length, data)
+    }
+
+    /// a `UDP_DATA` datagram containing a whole message of `sid`, without
+    /// checksum
+    fn stream_datagram(seq: u32, sid: Sid, mid: u64, length: u64, data: &'static [u8]) -> BytesMut {
+        let mut datagram = BytesMut::new();
+        datagram.put_u8(UDP_DATA);
+        datagram.put_u32_le(seq);
+        OTFrame::DataHeader { mid, sid, length }.write_bytes(&mut datagram);
+        OTFrame::Data {
+            mid,
+            data: Bytes::from(data),
+        }
+        .write_bytes(&mut datagram);
+        datagram
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn malformed_data_frames_are_skipped() {
+        let [(mut s1, _), (_, mut r2)] = sim_udp_bound(SimConfig::perfect().unreliable(), None);
+        // the data frame claims 10 bytes, but only 5 arrive
+        let mut datagram = data_datagram(0, 0, 10, b"0123456789");
+        datagram.truncate(datagram.len() - 5);
+        s1.drain.send(seal(&mut datagram)).await.unwrap();
+        // the data frame is longer than its message
+        let mut datagram = data_datagram(1, 1, 5, b"too long");
+        s1.drain.send(seal(&mut datagram)).await.unwrap();
+        let mut datagram = data_datagram(2, 2, 2, b"ok");
+        s1.drain.send(seal(&mut datagram)).await.unwrap();
+
+        assert_eq!(
+            r2.recv().await,
+            Ok(ProtocolEvent::Message {
+                sid: Sid::new(1),
+                data: Bytes::from(&b"ok"[..]),
+            })
+        );
+        assert_eq!(r2.metrics.malformed_frames(), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn corrupted_datagrams_are_dropped() {
+        let [(mut s1, _), (_, mut r2)] = sim_udp_bound(SimConfig::perfect().unreliable(), None);
+        let mut datagram = data_datagram(0, 0, 6, b"broken");
+        let mut corrupted = seal(&mut datagram);
+        let last_data_byte = corrupted.len() - 5;
+        corrupted[last_data_byte] ^= 0x20;
+        s1.drain.send(corrupted).await.unwrap();
+        // too short to even carry a checksum
+        s1.drain
+            .send(BytesMut::from(&[UDP_DATA][..]))
+            .await
+            .unwrap();
+        let mut datagram = data_datagram(1, 1, 2, b"ok");
+        s1.drain.send(seal(&mut datagram)).await.unwrap();
+
+        assert_eq!(
+            r2.recv().await,
+            Ok(ProtocolEvent::Message {
+                sid: Sid::new(1),
+                data: Bytes::from(&b"ok"[..]),
+            })
+        );
+        assert_eq!(r2.metrics.corrupted_datagrams(), 2);
+        assert_eq!(r2.metrics.malformed_frames(), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn incoming_messages_are_bounded() {
+        let [(mut s1, _), (_, r2)] = sim_udp_bound(SimConfig::perfect().unreliable(), None);
+        let mut r2 = r2.with_max_incoming(1);
+        // the first half of a message keeps the only slot busy
+        let mut datagram = data_datagram(0, 0, 10, b"01234");
+        s1.drain.send(seal(&mut datagram)).await.unwrap();
+        let mut datagram = stream_datagram(1, Sid::new(2), 1, 2, b"ok");
+        s1.drain.send(seal(&mut datagram)).await.unwrap();
+        let mut datagram = BytesMut::new();
+        datagram.put_u8(UDP_DATA);
+        datagram.put_u32_le(2);
+        OTFrame::Data {
+            mid: 0,
+            data: Bytes::from(&b"56789"[..]),
+        }
+        .write_bytes(&mut datagram);
+        s1.drain.send(seal(&mut datagram)).await.unwrap();
+
+        assert_eq!(
+            r2.recv().await,
+            Ok(ProtocolEvent::Message {
+                sid: Sid::new(1),
+                data: Bytes::from(&b"0123456789"[..]),
+            })
+        );
+        assert_eq!(r2.metrics.dropped_frames(), 1);
+        assert_eq!(r2.metrics.malformed_frames(), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn superseded_messages_are_dropped() {
+        let [(mut s1, _), (_, r2)] = sim_udp_bound(SimConfig::perfect().unreliable(), None);
+        let mut r2 = r2.with_max_incoming(1);
+        // the rest of the message got lost, the stream continues with the next one
+        let mut datagram = data_datagram(0, 0, 10, b"01234");
+        s1.drain.send(seal(&mut datagram)).await.unwrap();
+        let mut datagram = data_datagram(1, 1, 2, b"ok");
+        s1.drain.send(seal(&mut datagram)).await.unwrap();
+
+        assert_eq!(
+            r2.recv().await,
+            Ok(ProtocolEvent::Message {
+                sid: Sid::new(1),
+                data: Bytes::from(&b"ok"[..]),
+            })
+        );
+        assert!(r2.incoming.is_empty());
+        assert!(r2.assembling.is_empty());
+        assert_eq!(r2.metrics.dropped_frames(), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn duplicated_datagrams_are_dropped() {
+        let [(mut s1, _), (_, mut r2)] = sim_udp_bound(SimConfig::perfect().unreliable(), None);
+        let mut datagram = data_datagram(0, 0, 2, b"ok");
+        let datagram = seal(&mut datagram);
+        s1.drain.send(datagram.clone()).await.unwrap();
+        s1.drain.send(datagram).await.unwrap();
+        let mut datagram = data_datagram(1, 1, 4, b"next");
+        s1.drain.send(seal(&mut datagram)).await.unwrap();
+
+        assert_eq!(
+            r2.recv().await,
+            Ok(ProtocolEvent::Message {
+                sid: Sid::new(1),
+                data: Bytes::from(&b"ok"[..]),
+            })
+        );
+        assert_eq!(
+            r2.recv().await,
+            Ok(ProtocolEvent::Message {
+                sid: Sid::new(1),
+                data: Bytes::from(&b"next"[..]),
+            })
+        );
+        assert_eq!(r2.metrics.duplicated_datagrams(), 1);
+        assert_eq!(r2.metrics.malformed_frames(), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn encrypted_datagrams() {
+        let (c1, c2) = cipher_pair();
+        let [(s1, r1), (s2, r2)] = sim_udp_bound(SimConfig::perfect().unreliable(), None);
+        let (mut s1, mut r1) = (s1.with_cipher(c1.clone()), r1.with_cipher(c1.clone()));
+        let (mut s2, mut r2) = (s2.with_cipher(c2.clone()), r2.with_cipher(c2));
+
+        let sid = Sid::new(1);
+        let open = ProtocolEvent::OpenStream {
+            sid,
+            prio: 5u8,
+            promises: Promises::empty(),
+            guaranteed_bandwidth: 0,
+            window: DEFAULT_STREAM_WINDOW,
+        };
+        let msg = ProtocolEvent::Message {
+            sid,
+            data: Bytes::from(&b"encrypted"[..]),
+        };
+        s1.send(open.clone()).await.unwrap();
+        s1.send(msg.clone()).await.unwrap();
+        s1.flush(1_000_000, Duration::from_secs(1)).await.unwrap();
+        assert_eq!(r2.recv().await, Ok(open));
+        assert_eq!(r2.recv().await, Ok(msg));
+        // the ack of the open stream goes back encrypted
+        s2.flush(1_000_000, Duration::ZERO).await.unwrap();
+        tokio::select! {
+            event = r1.recv() => panic!("unexpected event {:?}", event),
+            _ = sleep(Duration::from_millis(10)) => {},
+        }
+        s1.flush(1_000_000, Duration::ZERO).await.unwrap();
+        assert!(s1.unacked.is_empty());
+
+        // the message was sent in a single datagram with 0, the pong of the ping
+        // that came with the ack in 1
+        let mut datagram = data_datagram(2, 1, 2, b"ok");
+        let payload = datagram.split_off(DATA_HEADER_SIZE);
+        c1.encrypt(nonce(UDP_DATA, 2), &payload, &mut datagram);
+        let mut tampered = datagram.clone();
+        tampered[DATA_HEADER_SIZE + 3] ^= 0x20;
+        s1.drain.send(seal(&mut tampered)).await.unwrap();
+        // plaintext from a peer without the keys
+        let mut plaintext = data_datagram(3, 2, 2, b"no");
+        s1.drain.send(seal(&mut plaintext)).await.unwrap();
+        // dropped datagrams don't count as received, so this isn't a duplicate
+        s1.drain.send(seal(&mut datagram)).await.unwrap();
+
+        assert_eq!(
+            r2.recv().await,
+            Ok(ProtocolEvent::Message {
+                sid,
+                data: Bytes::from(&b"ok"[..]),
+            })
+        );
+        assert_eq!(r2.metrics.corrupted_datagrams(), 2);
+        assert_eq!(r2.metrics.duplicated_datagrams(), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn reordered_handshake() {
+        let [(s1, r1), (mut s2, _r2)] = sim_udp_bound(SimConfig::perfect().unreliable(), None);
+        let mut p1 = (s1, r1);
+        let client = tokio::spawn(async move {
+            p1.initialize(false, Pid::fake(2), 1337, VersionPolicy::default())
+                .await
+        });
+        // the listener's `Init` overtakes its `Handshake`, which arrives twice
+        let handshake = InitFrame::Handshake {
+            magic_number: VELOREN_MAGIC_NUMBER,
+            version: VELOREN_NETWORK_VERSION,
+            encrypted: false,
+        };
+        let init = InitFrame::Init {
+            pid: Pid::fake(3),
+            secret: 42,
+        };
+        for (seq, frame) in [(1, init), (0, handshake.clone()), (0, handshake)] {
+            let mut datagram = BytesMut::new();
+            datagram.put_u8(UDP_INIT);
+            datagram.put_u8(seq);
+            frame.write_bytes(&mut datagram);
+            s2.drain.send(seal(&mut datagram)).await.unwrap();
+        }
+        assert_eq!(
+            client.await.unwrap(),
+            Ok((Pid::fake(3), STREAM_ID_OFFSET2, 42))
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn encrypted_handshake() {
+        let [(s1, r1), (s2, r2)] = sim_udp_bound(SimConfig::perfect().unreliable(), None);
+        let mut p1 = (s1.with_encryption(NoiseKeys::default()), r1);
+        let mut p2 = (s2.with_encryption(NoiseKeys::default()), r2);
+        let (r1, r2) = tokio::join!(
+            p1.initialize(false, Pid::fake(2), 1337, VersionPolicy::default()),
+            p2.initialize(true, Pid::fake(3), 42, VersionPolicy::default()),
+        );
+        assert_eq!(r1.unwrap(), (Pid::fake(3), STREAM_ID_OFFSET2, 42));
+        assert_eq!(r2.unwrap(), (Pid::fake(2), STREAM_ID_OFFSET1, 1337));
+        let ((mut s1, _), (_, mut r2)) = (p1, p2);
+        assert!(s1.cipher.is_some() && r2.cipher.is_some());
+
+        // a handshake datagram arriving late is ignored
+        let mut late = BytesMut::new();
+        late.put_u8(UDP_INIT);
+        late.put_u8(1);
+        InitFrame::Init {
+            pid: Pid::fake(2),
+            secret: 1337,
+        }
+        .write_bytes(&mut late);
+        s1.drain.send(seal(&mut late)).await.unwrap();
+
+        let msg = ProtocolEvent::Message {
+            sid: Sid::new(1),
+            data: Bytes::from(&b"encrypted"[..]),
+        };
+        s1.send(ProtocolEvent::OpenStream {
+            sid: Sid::new(1),
+            prio: 5u8,
+            promises: Promises::empty(),
+            guaranteed_bandwidth: 0,
+            window: DEFAULT_STREAM_WINDOW,
+        })
+        .await
+        .unwrap();
+        s1.send(msg.clone()).await.unwrap();
+        s1.flush(1_000_000, Duration::from_secs(1)).await.unwrap();
+        assert!(matches!(
+            r2.recv().await,
+            Ok(ProtocolEvent::OpenStream { .. })
+        ));
+        assert_eq!(r2.recv().await, Ok(msg));
+        assert_eq!(r2.metrics.malformed_frames(), 0);
+    }
+
+    /// counts the datagrams sent and their sizes
+    #[derive(Debug)]
+    struct CountingDrain {
+        drain: SimDrain,
+        datagrams: usize,
+        largest: usize,
+    }
+
+    #[async_trait]
+    impl UnreliableDrain for CountingDrain {
+        type DataFormat = BytesMut;
+
+        async fn send(&mut self, data: Self::DataFormat) -> Result<(), ProtocolError> {
+            self.datagrams += 1;
+            self.largest = self.largest.max(data.len());
+            self.drain.send(data).await
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn small_frames_are_batched() {
+        let (drain, sink) = sim_link(SimConfig::perfect().unreliable());
+        let metrics = ProtocolMetricCache::new("sim", Arc::new(ProtocolMetrics::new().unwrap()));
+        let drain = CountingDrain {
+            drain,
+            datagrams: 0,
+            largest: 0,
+        };
+        let mut s = UdpSendProtocol::new(drain, UdpAcks::default(), metrics.clone());
+        let mut r = UdpRecvProtocol::new(sink, UdpAcks::default(), metrics);
+
+        let sids = (1..=10).map(Sid::new).collect::<Vec<_>>();
+        for &sid in &sids {
+            s.send(ProtocolEvent::OpenStream {
+                sid,
+                prio: 5u8,
+                promises: Promises::empty(),
+                guaranteed_bandwidth: 0,
+                window: DEFAULT_STREAM_WINDOW,
+            })
+            .await
+            .unwrap();
+            for i in 0..10u8 {
+                s.send(ProtocolEvent::Message {
+                    sid,
+                    data: Bytes::from(vec![i; 4]),
+                })
+                .await
+                .unwrap();
+            }
+            s.send(ProtocolEvent::CloseStream { sid }).await.unwrap();
+        }
+        s.flush(1_000_000, Duration::from_secs(1)).await.unwrap();
+        // 10 opened and closed streams and 100 messages of two frames each
+        assert!(s.drain.datagrams < 10, "{} datagrams", s.drain.datagrams);
+        assert!(s.drain.largest <= MAX_DATAGRAM_SIZE);
+
+        let (mut opened, mut messages, mut closed) = (0, 0, 0);
+        while closed < sids.len() {
+            match r.recv().await.unwrap() {
+                ProtocolEvent::OpenStream { .. } => opened += 1,
+                ProtocolEvent::Message { data, .. } => {
+                    assert_eq!(data.len(), 4);
+                    messages += 1;
+                },
+                ProtocolEvent::CloseStream { .. } => closed += 1,
+                event => panic!("unexpected event {:?}", event),
+            }
+        }
+        assert_eq!((opened, messages), (10, 100));
+        assert_eq!(r.metrics.malformed_frames(), 0);
+    }
+
+    #[test]
+    fn data_seq_is_extended() {
+        assert_eq!(extend_data_seq(0, 0), 0);
+        assert_eq!(extend_data_seq(0, 5), 5);
+        // reordered before the first one
+        assert_eq!(extend_data_seq(5, u32::MAX), u64::from(u32::MAX));
+        let newest = (3 << 32) + 10;
+        assert_eq!(extend_data_seq(newest, 12), newest + 2);
+        assert_eq!(extend_data_seq(newest, 8), newest - 2);
+        // late from before the wraparound
+        assert_eq!(extend_data_seq(newest, u32::MAX), (3 << 32) - 1);
+        // early after the next one
+        let newest = (4 << 32) - 2;
+        assert_eq!(extend_data_seq(newest, 1), (4 << 32) + 1);
+    }
+
+    #[test]
+    fn dedup_window_wraps_around() {
+        let mut window = DedupWindow::new(6);
+        assert_eq!(window.slots.len(), 8);
+        let start = u32::MAX - 3;
+        for i in 0..8 {
+            assert!(window.insert(start.wrapping_add(i)));
+        }
+        // reordered within the window
+        assert!(window.insert(start.wrapping_add(9)));
+        assert!(window.insert(start.wrapping_add(8)));
+        for i in 2..10 {
+            assert!(!window.insert(start.wrapping_add(i)));
+        }
+        // fell out of the window
+        assert!(!window.insert(start));
+    }
+}
@@ -1,20 +1,24 @@
 use crate::{
     message::{partial_eq_bincode, Message},
+    metrics::{NetworkMetrics, StreamMetrics},
     participant::{A2bStreamOpen, S2bShutdownBparticipant},
-    scheduler::{A2sConnect, Scheduler},
+    scheduler::{A2sConnect, Connected, Scheduler},
 };
 use bytes::Bytes;
 use hashbrown::HashMap;
 #[cfg(feature = "compression")]
 use lz_fear::raw::DecodeError;
-use network_protocol::{Bandwidth, InitProtocolError, Pid, Prio, Promises, Sid};
+use network_protocol::{
+    Bandwidth, InitProtocolError, NoiseKeys, Pid, Prio, Promises, Sid, VersionPolicy,
+};
 #[cfg(feature = "metrics")]
 use prometheus::Registry;
 use serde::{de::DeserializeOwned, Serialize};
+#[cfg(feature = "debug")] use std::path::PathBuf;
 use std::{
     net::SocketAddr,
     sync::{
-        atomic::{AtomicBool, Ordering},
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
         Arc,
     },
     time::Duration,
@@ -28,20 +32,32 @@ use tracing::*;
 
 type A2sDisconnect = Arc<Mutex<Option<mpsc::UnboundedSender<(Pid, S2bShutdownBparticipant)>>>>;
 
-/// Represents a Tcp, Quic, Udp or Mpsc connection address
+/// Represents a Tcp, Tls, Quic, Udp, WebSocket or Mpsc connection address
+///
+/// WebSocket connections are accepted by a [`ListenAddr::Tcp`] listener, they
+/// use the same byte layout as Tcp, wrapped in binary WebSocket messages.
 #[derive(Clone, Debug)]
 pub enum ConnectAddr {
     Tcp(SocketAddr),
+    /// Tcp encrypted with Tls, the certificate of the server is checked
+    /// against the name. See [`crate::tls`] for servers with a self-signed
+    /// certificate.
+    #[cfg(feature = "tls")]
+    TcpTls(SocketAddr, Arc<rustls::ClientConfig>, String),
     Udp(SocketAddr),
     #[cfg(feature = "quic")]
     Quic(SocketAddr, quinn::ClientConfig, String),
+    #[cfg(feature = "websocket")]
+    Ws(SocketAddr),
     Mpsc(u64),
 }
 
-/// Represents a Tcp, Quic, Udp or Mpsc listen address
+/// Represents a Tcp, Tls, Quic, Udp or Mpsc listen address
 #[derive(Clone, Debug)]
 pub enum ListenAddr {
     Tcp(SocketAddr),
+    #[cfg(feature = "tls")]
+    TcpTls(SocketAddr, Arc<rustls::ServerConfig>),
     Udp(SocketAddr),
     #[cfg(feature = "quic")]
     Quic(SocketAddr, quinn::ServerConfig),
@@ -60,8 +76,12 @@ pub struct Participant {
     remote_pid: Pid,
     a2b_open_stream_s: Mutex<mpsc::UnboundedSender<A2bStreamOpen>>,
     b2a_stream_opened_r: Mutex<mpsc::UnboundedReceiver<Stream>>,
+    b2a_stream_closed_r: Mutex<mpsc::UnboundedReceiver<Sid>>,
     b2a_bandwidth_stats_r: watch::Receiver<f32>,
+    b2a_rtt_r: watch::Receiver<Option<Duration>>,
+    a2b_bandwidth_limit_s: watch::Sender<Option<Bandwidth>>,
     a2s_disconnect_s: A2sDisconnect,
+    metrics: Arc<NetworkMetrics>,
 }
 
 /// `Streams` represents a channel to send `n` messages with a certain priority
@@ -82,15 +102,19 @@ pub struct Stream {
     local_pid: Pid,
     remote_pid: Pid,
     sid: Sid,
-    #[allow(dead_code)]
     prio: Prio,
     promises: Promises,
     #[allow(dead_code)]
     guaranteed_bandwidth: Bandwidth,
     send_closed: Arc<AtomicBool>,
     a2b_msg_s: crossbeam_channel::Sender<(Sid, Bytes)>,
+    /// bytes of received messages handed to the application, they are given
+    /// back to the flow control window of the remote
+    a2b_consumed_s: crossbeam_channel::Sender<(Sid, u64)>,
     b2a_msg_recv_r: Option<async_channel::Receiver<Bytes>>,
     a2b_close_stream_s: Option<mpsc::UnboundedSender<Sid>>,
+    #[cfg_attr(not(feature = "compression"), allow(dead_code))]
+    metrics: Arc<NetworkMetrics>,
 }
 
 /// Error type thrown by [`Networks`](Network) methods
@@ -108,6 +132,12 @@ pub enum NetworkConnectError {
     InvalidSecret,
     Handshake(InitProtocolError),
     Io(std::io::Error),
+    /// The remote is connected already, the connection was added as another
+    /// channel to its [`Participant`]
+    AlreadyConnected(Pid),
+    /// The address belongs to another [`Participant`] than the one a channel
+    /// should be opened to
+    WrongParticipant(Pid),
 }
 
 /// Error type thrown by [`Participants`](Participant) methods
@@ -131,6 +161,12 @@ pub enum StreamError {
     StreamClosed,
     #[cfg(feature = "compression")]
     Compression(DecodeError),
+    /// the message claims to be bigger after decompression than it could be
+    #[cfg(feature = "compression")]
+    DecompressedTooLarge {
+        len: u64,
+        limit: u64,
+    },
     Deserialize(bincode::Error),
 }
 
@@ -140,6 +176,12 @@ pub struct StreamParams {
     pub(crate) promises: Promises,
 }
 
+impl StreamParams {
+    /// The [`Promises`] of the Stream, e.g. only streams with
+    /// [`Promises::GUARANTEED_DELIVERY`] are flow controlled
+    pub fn promises(&self) -> Promises { self.promises }
+}
+
 /// Use the `Network` to create connections to other [`Participants`]
 ///
 /// The `Network` is the single source that handles all connections in your
@@ -189,9 +231,27 @@ pub struct Network {
     connect_sender: Mutex<mpsc::UnboundedSender<A2sConnect>>,
     connected_receiver: Mutex<mpsc::UnboundedReceiver<Participant>>,
     shutdown_network_s: Option<oneshot::Sender<oneshot::Sender<()>>>,
+    stream_capacity: Arc<AtomicUsize>,
+    /// in milliseconds
+    shutdown_grace: Arc<AtomicU64>,
+    encryption: Arc<std::sync::Mutex<Option<NoiseKeys>>>,
+    exact_version: Arc<AtomicBool>,
+    #[cfg(feature = "debug")]
+    frame_log_dir: Arc<std::sync::Mutex<Option<PathBuf>>>,
 }
 
 impl Network {
+    /// How long queued messages are still sent to a [`Participant`] that is
+    /// shut down by default, see [`set_shutdown_grace`]
+    ///
+    /// [`set_shutdown_grace`]: Network::set_shutdown_grace
+    pub const DEFAULT_SHUTDOWN_GRACE: Duration = Duration::from_secs(2);
+    /// Messages a [`Stream`] buffers by default before the remote is slowed
+    /// down, see [`set_stream_capacity`]
+    ///
+    /// [`set_stream_capacity`]: Network::set_stream_capacity
+    pub const DEFAULT_STREAM_CAPACITY: usize = 4096;
+
     /// Generates a new `Network` to handle all connections in an Application
     ///
     /// # Arguments
@@ -264,9 +324,25 @@ impl Network {
         let p = participant_id;
         let span = tracing::info_span!("network", ?p);
         span.in_scope(|| trace!("Starting Network"));
+        let stream_capacity = Arc::new(AtomicUsize::new(Self::DEFAULT_STREAM_CAPACITY));
+        let shutdown_grace = Arc::new(AtomicU64::new(
+            Self::DEFAULT_SHUTDOWN_GRACE.as_millis() as u64
+        ));
+        let encryption = Arc::new(std::sync::Mutex::new(None));
+        let exact_version = Arc::new(AtomicBool::new(
+            VersionPolicy::default() == VersionPolicy::Exact,
+        ));
+        #[cfg(feature = "debug")]
+        let frame_log_dir = Arc::new(std::sync::Mutex::new(None));
         let (scheduler, listen_sender, connect_sender, connected_receiver, shutdown_sender) =
             Scheduler::new(
                 participant_id,
+                Arc::clone(&stream_capacity),
+                Arc::clone(&shutdown_grace),
+                Arc::clone(&encryption),
+                Arc::clone(&exact_version),
+                #[cfg(feature = "debug")]
+                Arc::clone(&frame_log_dir),
                 #[cfg(feature = "metrics")]
                 registry,
             );
@@ -294,9 +370,85 @@ impl Network {
             connect_sender: Mutex::new(connect_sender),
             connected_receiver: Mutex::new(connected_receiver),
             shutdown_network_s: Some(shutdown_network_s),
+            stream_capacity,
+            shutdown_grace,
+            encryption,
+            exact_version,
+            #[cfg(feature = "debug")]
+            frame_log_dir,
         }
     }
 
+    /// Sets how many received messages every [`Stream`] buffers until they
+    /// are read via [`recv`]. Once a `Stream` is full, the whole
+    /// [`Participant`] stops reading from its channels, so the remote is slowed
+    /// down by the flow control of the protocol instead of filling up our
+    /// memory. Only applies to `Participants` connecting afterwards, defaults
+    /// to [`DEFAULT_STREAM_CAPACITY`]. Sending isn't affected, outgoing
+    /// messages are queued until the channel has bandwidth for them.
+    ///
+    /// # Panics
+    /// if `capacity` is 0
+    ///
+    /// [`recv`]: Stream::recv
+    /// [`DEFAULT_STREAM_CAPACITY`]: Network::DEFAULT_STREAM_CAPACITY
+    pub fn set_stream_capacity(&self, capacity: usize) {
+        assert!(
+            capacity > 0,
+            "a Stream needs to buffer at least one message"
+        );
+        self.stream_capacity.store(capacity, Ordering::Relaxed);
+    }
+
+    /// Sets how long messages that are still queued are sent once a
+    /// [`Participant`] is shut down, e.g. via [`disconnect`]. Messages that
+    /// didn't make it by then are dropped. Only applies to `Participants`
+    /// connecting afterwards, defaults to [`DEFAULT_SHUTDOWN_GRACE`].
+    ///
+    /// [`disconnect`]: Participant::disconnect
+    /// [`DEFAULT_SHUTDOWN_GRACE`]: Network::DEFAULT_SHUTDOWN_GRACE
+    pub fn set_shutdown_grace(&self, grace: Duration) {
+        self.shutdown_grace
+            .store(grace.as_millis() as u64, Ordering::Relaxed);
+    }
+
+    /// Requires all Tcp, WebSocket and Udp channels to be encrypted with keys
+    /// negotiated in their handshake, which also protects them against
+    /// tampering. Remotes that don't encrypt as well are rejected with
+    /// [`InitProtocolError::EncryptionMismatch`]. A listening side sets a
+    /// static private key in [`NoiseKeys`] and publishes its public key, the
+    /// connecting side pins it so nobody can pose as the listening side. Only
+    /// applies to channels opened afterwards, disabled by default.
+    ///
+    /// [`InitProtocolError::EncryptionMismatch`]: crate::InitProtocolError::EncryptionMismatch
+    pub fn set_encryption(&self, encryption: Option<NoiseKeys>) {
+        *self.encryption.lock().unwrap() = encryption;
+    }
+
+    /// Sets how strictly the network version of remotes connecting to one of
+    /// our [`ListenAddr`] has to match ours, incompatible ones are rejected
+    /// with [`InitProtocolError::VersionMismatch`] on their side. Remotes we
+    /// [`connect`] to decide that on their own. Only applies to channels opened
+    /// afterwards, defaults to [`VersionPolicy::MajorMinor`].
+    ///
+    /// [`InitProtocolError::VersionMismatch`]: crate::InitProtocolError::VersionMismatch
+    /// [`connect`]: Network::connect
+    pub fn set_version_policy(&self, policy: VersionPolicy) {
+        self.exact_version
+            .store(policy == VersionPolicy::Exact, Ordering::Relaxed);
+    }
+
+    /// Logs the frames every Tcp, Tls and WebSocket channel sends and receives
+    /// after its handshake into `dir`, one [`FrameLogger`] log per channel and
+    /// direction. Only applies to channels opened afterwards, disabled by
+    /// default.
+    ///
+    /// [`FrameLogger`]: crate::debug::FrameLogger
+    #[cfg(feature = "debug")]
+    pub fn set_frame_log_dir(&self, dir: Option<PathBuf>) {
+        *self.frame_log_dir.lock().unwrap() = dir;
+    }
+
     /// starts listening on an [`ListenAddr`].
     /// When the method returns the `Network` is ready to listen for incoming
     /// connections OR has returned a [`NetworkError`] (e.g. port already used).
@@ -353,23 +505,23 @@ impl Network {
     /// use veloren_network::{Network, Pid, ListenAddr, ConnectAddr};
     ///
     /// # fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
-    /// // Create a Network, connect on port `2010` TCP and `2011` UDP like listening above
+    /// // Create a Network, connect on port `2010` TCP and `2011` TCP like listening above
     /// let runtime = Runtime::new().unwrap();
     /// let network = Network::new(Pid::new(), &runtime);
     /// # let remote = Network::new(Pid::new(), &runtime);
     /// runtime.block_on(async {
     ///     # remote.listen(ListenAddr::Tcp("127.0.0.1:2010".parse().unwrap())).await?;
-    ///     # remote.listen(ListenAddr::Udp("127.0.0.1:2011".parse().unwrap())).await?;
+    ///     # remote.listen(ListenAddr::Tcp("127.0.0.1:2011".parse().unwrap())).await?;
     ///     let p1 = network
     ///         .connect(ConnectAddr::Tcp("127.0.0.1:2010".parse().unwrap()))
     ///         .await?;
-    ///     # //this doesn't work yet, so skip the test
-    ///     # //TODO fixme!
-    ///     # return Ok(());
-    ///     let p2 = network
-    ///         .connect(ConnectAddr::Udp("127.0.0.1:2011".parse().unwrap()))
+    ///     // the same remote is reachable on another port, use it as a 2nd channel
+    ///     network
+    ///         .open_channel(
+    ///             p1.remote_pid(),
+    ///             ConnectAddr::Tcp("127.0.0.1:2011".parse().unwrap()),
+    ///         )
     ///         .await?;
-    ///     assert_eq!(&p1, &p2);
     ///     # Ok(())
     /// })?;
     /// drop(network);
@@ -379,24 +531,30 @@ impl Network {
     /// ```
     /// Usually the `Network` guarantees that a operation on a [`Participant`]
     /// succeeds, e.g. by automatic retrying unless it fails completely e.g. by
-    /// disconnecting from the remote. If 2 [`ConnectAddr] you
-    /// `connect` to belongs to the same [`Participant`], you get the same
-    /// [`Participant`] as a result. This is useful e.g. by connecting to
-    /// the same [`Participant`] via multiple Protocols.
+    /// disconnecting from the remote. If the [`ConnectAddr`] belongs to a
+    /// [`Participant`] that is connected already, the connection is added to it
+    /// as another channel and [`NetworkConnectError::AlreadyConnected`] is
+    /// returned, use [`open_channel`] to do so on purpose.
     ///
+    /// [`open_channel`]: crate::api::Network::open_channel
     /// [`Streams`]: crate::api::Stream
     /// [`ConnectAddr`]: crate::api::ConnectAddr
     #[instrument(name="network", skip(self, address), fields(p = %self.local_pid))]
     pub async fn connect(&self, address: ConnectAddr) -> Result<Participant, NetworkError> {
         let (pid_sender, pid_receiver) =
-            oneshot::channel::<Result<Participant, NetworkConnectError>>();
+            oneshot::channel::<Result<Connected, NetworkConnectError>>();
         debug!(?address, "Connect to address");
         self.connect_sender
             .lock()
             .await
             .send((address, pid_sender))?;
         let participant = match pid_receiver.await? {
-            Ok(p) => p,
+            Ok(Connected::Participant(p)) => p,
+            Ok(Connected::Channel(pid)) => {
+                return Err(NetworkError::ConnectFailed(
+                    NetworkConnectError::AlreadyConnected(pid),
+                ));
+            },
             Err(e) => return Err(NetworkError::ConnectFailed(e)),
         };
         let remote_pid = participant.remote_pid;
@@ -408,6 +566,40 @@ impl Network {
         Ok(participant)
     }
 
+    /// Opens another channel to the already connected [`Participant`] with
+    /// the `pid`, e.g. a 2nd TCP connection, so that a congested channel
+    /// doesn't delay the [`Streams`] sent over the others. New [`Streams`] are
+    /// spread over all channels and the [`Participant`] stays connected as
+    /// long as one of its channels remains.
+    ///
+    /// [`NetworkConnectError::WrongParticipant`] is returned if the address
+    /// belongs to another [`Participant`].
+    ///
+    /// [`Streams`]: crate::api::Stream
+    #[instrument(name="network", skip(self, address), fields(p = %self.local_pid))]
+    pub async fn open_channel(&self, pid: Pid, address: ConnectAddr) -> Result<(), NetworkError> {
+        let (pid_sender, pid_receiver) =
+            oneshot::channel::<Result<Connected, NetworkConnectError>>();
+        debug!(?address, ?pid, "Open channel to address");
+        self.connect_sender
+            .lock()
+            .await
+            .send((address, pid_sender))?;
+        let remote_pid = match pid_receiver.await? {
+            Ok(Connected::Channel(remote_pid)) if remote_pid == pid => return Ok(()),
+            Ok(Connected::Channel(remote_pid)) => remote_pid,
+            Ok(Connected::Participant(participant)) => {
+                let remote_pid = participant.remote_pid;
+                let _ = participant.disconnect().await;
+                remote_pid
+            },
+            Err(e) => return Err(NetworkError::ConnectFailed(e)),
+        };
+        Err(NetworkError::ConnectFailed(
+            NetworkConnectError::WrongParticipant(remote_pid),
+        ))
+    }
+
     /// returns a [`Participant`] created from a [`ListenAddr`] you
     /// called [`listen`] on before. This function will either return a
     /// working [`Participant`] ready to open [`Streams`] on OR has returned
@@ -514,21 +706,30 @@ impl Network {
 }
 
 impl Participant {
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn new(
         local_pid: Pid,
         remote_pid: Pid,
         a2b_open_stream_s: mpsc::UnboundedSender<A2bStreamOpen>,
         b2a_stream_opened_r: mpsc::UnboundedReceiver<Stream>,
+        b2a_stream_closed_r: mpsc::UnboundedReceiver<Sid>,
         b2a_bandwidth_stats_r: watch::Receiver<f32>,
+        b2a_rtt_r: watch::Receiver<Option<Duration>>,
+        a2b_bandwidth_limit_s: watch::Sender<Option<Bandwidth>>,
         a2s_disconnect_s: mpsc::UnboundedSender<(Pid, S2bShutdownBparticipant)>,
+        metrics: Arc<NetworkMetrics>,
     ) -> Self {
         Self {
             local_pid,
             remote_pid,
             a2b_open_stream_s: Mutex::new(a2b_open_stream_s),
             b2a_stream_opened_r: Mutex::new(b2a_stream_opened_r),
+            b2a_stream_closed_r: Mutex::new(b2a_stream_closed_r),
             b2a_bandwidth_stats_r,
+            b2a_rtt_r,
+            a2b_bandwidth_limit_s,
             a2s_disconnect_s: Arc::new(Mutex::new(Some(a2s_disconnect_s))),
+            metrics,
         }
     }
 
@@ -617,6 +818,13 @@ impl Participant {
     /// the other side. A [`ParticipantError`] might be thrown if the
     /// `Participant` is already closed.
     ///
+    /// Every [`Stream`] the remote opens is handed out exactly once, including
+    /// those opened before this method is first called, they are queued until
+    /// then. Use [`Stream::prio`] and [`Stream::promises`] to tell apart
+    /// streams opened by different systems of your application. Streams that
+    /// are still queued when the `Participant` is dropped are closed. See
+    /// [`closed`] to get notified when the remote closes a [`Stream`].
+    ///
     /// # Examples
     /// ```rust
     /// use tokio::runtime::Runtime;
@@ -644,6 +852,7 @@ impl Participant {
     /// [`Streams`]: crate::api::Stream
     /// [`connected`]: Network::connected
     /// [`open`]: Participant::open
+    /// [`closed`]: Participant::closed
     #[instrument(name="network", skip(self), fields(p = %self.local_pid))]
     pub async fn opened(&self) -> Result<Stream, ParticipantError> {
         match self.b2a_stream_opened_r.lock().await.recv().await {
@@ -659,6 +868,60 @@ impl Participant {
         }
     }
 
+    /// Returns the [`Sid`] of the next [`Stream`] that was closed by the remote
+    /// side. Like with [`opened`], notifications are queued until they are
+    /// requested, so none get lost. `Streams` closed locally, by dropping
+    /// them, are not reported. A [`ParticipantError`] is thrown once the
+    /// `Participant` is closed and all notifications were handed out, when
+    /// the remote disconnects gracefully its remaining `Streams` are
+    /// reported first.
+    ///
+    /// Messages that arrived before the close can still be received from the
+    /// [`Stream`] afterwards.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use tokio::runtime::Runtime;
+    /// use veloren_network::{ConnectAddr, ListenAddr, Network, Pid, Promises};
+    ///
+    /// # fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
+    /// // Create a Network, connect on port 2120 and wait for the other side to close a stream
+    /// let runtime = Runtime::new().unwrap();
+    /// let network = Network::new(Pid::new(), &runtime);
+    /// # let remote = Network::new(Pid::new(), &runtime);
+    /// runtime.block_on(async {
+    ///     # remote.listen(ListenAddr::Tcp("127.0.0.1:2120".parse().unwrap())).await?;
+    ///     let p1 = network
+    ///         .connect(ConnectAddr::Tcp("127.0.0.1:2120".parse().unwrap()))
+    ///         .await?;
+    ///     # let p2 = remote.connected().await?;
+    ///     # let s2 = p2.open(4, Promises::ORDERED, 0).await?;
+    ///     let s1 = p1.opened().await?;
+    ///     # drop(s2);
+    ///     assert_eq!(p1.closed().await?, s1.sid());
+    ///     drop(network);
+    ///     # drop(remote);
+    ///     # Ok(())
+    /// })
+    /// # }
+    /// ```
+    ///
+    /// [`Sid`]: network_protocol::Sid
+    /// [`opened`]: Participant::opened
+    #[instrument(name="network", skip(self), fields(p = %self.local_pid))]
+    pub async fn closed(&self) -> Result<Sid, ParticipantError> {
+        match self.b2a_stream_closed_r.lock().await.recv().await {
+            Some(sid) => {
+                debug!(?sid, "Receive closed stream");
+                Ok(sid)
+            },
+            None => {
+                debug!("stream_closed_receiver failed, closing participant");
+                Err(ParticipantError::ParticipantDisconnected)
+            },
+        }
+    }
+
     /// disconnecting a `Participant` in a async way.
     /// Use this rather than `Participant::Drop` if you want to close multiple
     /// `Participants`.
@@ -755,6 +1018,48 @@ impl Participant {
     /// This WILL fluctuate based on the amount/size of send messages.
     pub fn bandwidth(&self) -> f32 { *self.b2a_bandwidth_stats_r.borrow() }
 
+    /// Returns the smoothed round trip time of the fastest channel to the
+    /// remote. `None` until it was measured, which happens right after
+    /// connecting, and for local `Mpsc` and `Quic` channels which don't
+    /// measure it.
+    pub fn rtt(&self) -> Option<Duration> { *self.b2a_rtt_r.borrow() }
+
+    /// Limits the upload to this `Participant` to `limit` bytes per second,
+    /// `None` removes the limit. Can be changed at any time, to limit a
+    /// `Participant` from the start call it before opening any [`Stream`].
+    /// Messages that exceed the limit stay queued, sorted by [`Prio`], till
+    /// bandwidth is available. The guaranteed bandwidth of a [`Stream`] is
+    /// still granted on top. Shutting down the `Participant` ignores the
+    /// limit, so disconnects are never delayed by it. Local `Mpsc` channels
+    /// aren't limited.
+    pub fn set_bandwidth_limit(&self, limit: Option<Bandwidth>) {
+        let _ = self.a2b_bandwidth_limit_s.send(limit);
+    }
+
+    /// Returns the limit set via [`set_bandwidth_limit`]
+    ///
+    /// [`set_bandwidth_limit`]: Participant::set_bandwidth_limit
+    pub fn bandwidth_limit(&self) -> Option<Bandwidth> { *self.a2b_bandwidth_limit_s.borrow() }
+
+    /// Returns the bytes and messages send and received by every [`Stream`] of
+    /// this `Participant`, sorted by [`Sid`]. Closed `Streams` are kept for 60
+    /// seconds. Only available with the `metrics` feature, otherwise this is
+    /// always empty.
+    ///
+    /// [`Sid`]: network_protocol::Sid
+    pub fn stream_summary(&self) -> Vec<(Sid, StreamMetrics)> {
+        self.metrics.stream_summary(self.remote_pid)
+    }
+
+    /// Returns the traffic of a single [`Stream`] of this `Participant`, see
+    /// [`stream_summary`]. Cheap enough to be polled every frame, e.g. to show
+    /// the throughput of a [`Stream`] with [`StreamMetrics::throughput_since`].
+    ///
+    /// [`stream_summary`]: Participant::stream_summary
+    pub fn stream_metrics(&self, sid: Sid) -> Option<StreamMetrics> {
+        self.metrics.stream_metrics(self.remote_pid, sid)
+    }
+
     /// Returns the remote [`Pid`](network_protocol::Pid)
     pub fn remote_pid(&self) -> Pid { self.remote_pid }
 }
@@ -769,8 +1074,10 @@ impl Stream {
         guaranteed_bandwidth: Bandwidth,
         send_closed: Arc<AtomicBool>,
         a2b_msg_s: crossbeam_channel::Sender<(Sid, Bytes)>,
+        a2b_consumed_s: crossbeam_channel::Sender<(Sid, u64)>,
         b2a_msg_recv_r: async_channel::Receiver<Bytes>,
         a2b_close_stream_s: mpsc::UnboundedSender<Sid>,
+        metrics: Arc<NetworkMetrics>,
     ) -> Self {
         Self {
             local_pid,
@@ -781,8 +1088,10 @@ impl Stream {
             guaranteed_bandwidth,
             send_closed,
             a2b_msg_s,
+            a2b_consumed_s,
             b2a_msg_recv_r: Some(b2a_msg_recv_r),
             a2b_close_stream_s: Some(a2b_close_stream_s),
+            metrics,
         }
     }
 
@@ -898,6 +1207,11 @@ impl Stream {
         }
         #[cfg(debug_assertions)]
         message.verify(self.params());
+        #[cfg(feature = "compression")]
+        if let (true, Some(uncompressed_len)) = (message.compressed, message.uncompressed_len) {
+            self.metrics
+                .compressed_message(uncompressed_len, message.data.len());
+        }
         self.a2b_msg_s.send((self.sid, message.data.clone()))?;
         Ok(())
     }
@@ -982,9 +1296,11 @@ impl Stream {
             Some(b2a_msg_recv_r) => {
                 match b2a_msg_recv_r.recv().await {
                     Ok(data) => Ok(Message {
-                        data,
+                        data: self.consumed(data),
                         #[cfg(feature = "compression")]
                         compressed: self.promises.contains(Promises::COMPRESSED),
+                        #[cfg(feature = "compression")]
+                        uncompressed_len: None,
                     }),
                     Err(_) => {
                         self.b2a_msg_recv_r = None; //prevent panic
@@ -1036,9 +1352,11 @@ impl Stream {
             Some(b2a_msg_recv_r) => match b2a_msg_recv_r.try_recv() {
                 Ok(data) => Ok(Some(
                     Message {
-                        data,
+                        data: self.consumed(data),
                         #[cfg(feature = "compression")]
                         compressed: self.promises.contains(Promises::COMPRESSED),
+                        #[cfg(feature = "compression")]
+                        uncompressed_len: None,
                     }
                     .deserialize()?,
                 )),
@@ -1052,11 +1370,39 @@ impl Stream {
         }
     }
 
+    /// Only streams with [`Promises::GUARANTEED_DELIVERY`] are flow controlled
+    fn consumed(&self, data: Bytes) -> Bytes {
+        if self.promises.contains(Promises::GUARANTEED_DELIVERY) {
+            // the participant might be gone already, nothing to give back then
+            let _ = self.a2b_consumed_s.send((self.sid, data.len() as u64));
+        }
+        data
+    }
+
     pub fn params(&self) -> StreamParams {
         StreamParams {
             promises: self.promises,
         }
     }
+
+    /// Returns the [`Sid`] of this `Stream`, it's the same on both sides
+    ///
+    /// [`Sid`]: network_protocol::Sid
+    pub fn sid(&self) -> Sid { self.sid }
+
+    /// Returns the priority this `Stream` was opened with
+    pub fn prio(&self) -> u8 { self.prio }
+
+    /// Returns the [`Promises`] this `Stream` was opened with
+    ///
+    /// [`Promises`]: network_protocol::Promises
+    pub fn promises(&self) -> Promises { self.promises }
+
+    /// Returns `true` once [`send`] fails with [`StreamError::StreamClosed`],
+    /// because this `Stream`, the remote one or the [`Participant`] was closed
+    ///
+    /// [`send`]: Stream::send
+    pub fn is_closed(&self) -> bool { self.send_closed.load(Ordering::Relaxed) }
 }
 
 impl core::cmp::PartialEq for Participant {
@@ -1225,6 +1571,12 @@ impl core::fmt::Display for StreamError {
             StreamError::StreamClosed => write!(f, "stream closed"),
             #[cfg(feature = "compression")]
             StreamError::Compression(err) => write!(f, "compression error on message: {}", err),
+            #[cfg(feature = "compression")]
+            StreamError::DecompressedTooLarge { len, limit } => write!(
+                f,
+                "message would decompress to {} bytes, limit is {}",
+                len, limit
+            ),
             StreamError::Deserialize(err) => write!(f, "deserialize error on message: {}", err),
         }
     }
@@ -1259,6 +1611,12 @@ impl core::fmt::Display for NetworkConnectError {
             NetworkConnectError::InvalidSecret => {
                 write!(f, "You specified the wrong secret on your second channel")
             },
+            NetworkConnectError::AlreadyConnected(pid) => {
+                write!(f, "Already connected to {}, added another channel", pid)
+            },
+            NetworkConnectError::WrongParticipant(pid) => {
+                write!(f, "The address belongs to another participant: {}", pid)
+            },
         }
     }
 }
@@ -1266,26 +1624,24 @@ impl core::fmt::Display for NetworkConnectError {
 /// implementing PartialEq as it's super convenient in tests
 impl core::cmp::PartialEq for StreamError {
     fn eq(&self, other: &Self) -> bool {
-        match self {
-            StreamError::StreamClosed => match other {
-                StreamError::StreamClosed => true,
-                #[cfg(feature = "compression")]
-                StreamError::Compression(_) => false,
-                StreamError::Deserialize(_) => false,
-            },
+        match (self, other) {
+            (StreamError::StreamClosed, StreamError::StreamClosed) => true,
             #[cfg(feature = "compression")]
-            StreamError::Compression(err) => match other {
-                StreamError::StreamClosed => false,
-                #[cfg(feature = "compression")]
-                StreamError::Compression(other_err) => err == other_err,
-                StreamError::Deserialize(_) => false,
+            (StreamError::Compression(err), StreamError::Compression(other_err)) => {
+                err == other_err
             },
-            StreamError::Deserialize(err) => match other {
-                StreamError::StreamClosed => false,
-                #[cfg(feature = "compression")]
-                StreamError::Compression(_) => false,
-                StreamError::Deserialize(other_err) => partial_eq_bincode(err, other_err),
+            #[cfg(feature = "compression")]
+            (
+                StreamError::DecompressedTooLarge { len, limit },
+                StreamError::DecompressedTooLarge {
+                    len: other_len,
+                    limit: other_limit,
+                },
+            ) => len == other_len && limit == other_limit,
+            (StreamError::Deserialize(err), StreamError::Deserialize(other_err)) => {
+                partial_eq_bincode(err, other_err)
             },
+            _ => false,
         }
     }
 }
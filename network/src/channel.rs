@@ -2,13 +2,21 @@ use crate::api::NetworkConnectError;
 use async_trait::async_trait;
 use bytes::BytesMut;
 use futures_util::FutureExt;
-#[cfg(feature = "quic")]
+#[cfg(any(feature = "quic", feature = "websocket"))]
 use futures_util::StreamExt;
+#[cfg(feature = "websocket")]
+use futures_util::{
+    stream::{SplitSink, SplitStream},
+    SinkExt,
+};
 use hashbrown::HashMap;
+#[cfg(feature = "debug")]
+use network_protocol::FrameObserver;
 use network_protocol::{
-    Bandwidth, Cid, InitProtocolError, MpscMsg, MpscRecvProtocol, MpscSendProtocol, Pid,
-    ProtocolError, ProtocolEvent, ProtocolMetricCache, ProtocolMetrics, Sid, TcpRecvProtocol,
-    TcpSendProtocol, UnreliableDrain, UnreliableSink,
+    is_udp_handshake, Bandwidth, ChannelRtt, Cid, InitProtocolError, KeepAlive, MpscMsg,
+    MpscRecvProtocol, MpscSendProtocol, NoiseKeys, Pid, ProtocolError, ProtocolEvent,
+    ProtocolMetricCache, ProtocolMetrics, Sid, StreamObserver, TcpRecvProtocol, TcpSendProtocol,
+    UdpAcks, UdpRecvProtocol, UdpSendProtocol, UnreliableDrain, UnreliableSink, VersionPolicy,
 };
 #[cfg(feature = "quic")]
 use network_protocol::{QuicDataFormat, QuicDataFormatStream, QuicRecvProtocol, QuicSendProtocol};
@@ -21,38 +29,62 @@ use std::{
     },
     time::Duration,
 };
+#[cfg(feature = "tls")]
+use tokio::io::{ReadHalf, WriteHalf};
 use tokio::{
-    io::{AsyncReadExt, AsyncWriteExt},
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
     net,
     net::tcp::{OwnedReadHalf, OwnedWriteHalf},
     select,
     sync::{mpsc, oneshot, Mutex},
 };
-use tracing::{error, info, trace, warn};
+#[cfg(feature = "tls")]
+use tokio_rustls::{TlsAcceptor, TlsConnector, TlsStream};
+#[cfg(feature = "websocket")]
+use tokio_tungstenite::{tungstenite::Message as WsMessage, WebSocketStream};
+use tracing::{debug, error, info, trace, warn};
 
 #[allow(clippy::large_enum_variant)]
 #[derive(Debug)]
 pub(crate) enum Protocols {
     Tcp((TcpSendProtocol<TcpDrain>, TcpRecvProtocol<TcpSink>)),
+    #[cfg(feature = "tls")]
+    TcpTls((TcpSendProtocol<TlsDrain>, TcpRecvProtocol<TlsSink>)),
     Mpsc((MpscSendProtocol<MpscDrain>, MpscRecvProtocol<MpscSink>)),
     #[cfg(feature = "quic")]
     Quic((QuicSendProtocol<QuicDrain>, QuicRecvProtocol<QuicSink>)),
+    #[cfg(feature = "websocket")]
+    Ws((TcpSendProtocol<WsDrain>, TcpRecvProtocol<WsSink>)),
+    Udp(
+        (UdpSendProtocol<UdpDrain>, UdpRecvProtocol<UdpSink>),
+        Arc<std::sync::Mutex<UdpHandshake>>,
+    ),
 }
 
 #[derive(Debug)]
 pub(crate) enum SendProtocols {
     Tcp(TcpSendProtocol<TcpDrain>),
+    #[cfg(feature = "tls")]
+    TcpTls(TcpSendProtocol<TlsDrain>),
     Mpsc(MpscSendProtocol<MpscDrain>),
     #[cfg(feature = "quic")]
     Quic(QuicSendProtocol<QuicDrain>),
+    #[cfg(feature = "websocket")]
+    Ws(TcpSendProtocol<WsDrain>),
+    Udp(UdpSendProtocol<UdpDrain>),
 }
 
 #[derive(Debug)]
 pub(crate) enum RecvProtocols {
     Tcp(TcpRecvProtocol<TcpSink>),
+    #[cfg(feature = "tls")]
+    TcpTls(TcpRecvProtocol<TlsSink>),
     Mpsc(MpscRecvProtocol<MpscSink>),
     #[cfg(feature = "quic")]
     Quic(QuicRecvProtocol<QuicSink>),
+    #[cfg(feature = "websocket")]
+    Ws(TcpRecvProtocol<WsSink>),
+    Udp(UdpRecvProtocol<UdpSink>),
 }
 
 lazy_static::lazy_static! {
@@ -68,6 +100,15 @@ pub(crate) type C2cMpscConnect = (
 
 impl Protocols {
     const MPSC_CHANNEL_BOUND: usize = 1000;
+    /// Clients that didn't finish the Tls handshake by then are dropped
+    #[cfg(feature = "tls")]
+    const TLS_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+    /// Datagrams buffered per Udp channel of a listener, more get dropped
+    const UDP_CHANNEL_BOUND: usize = 1000;
+    /// Udp channels that didn't finish the handshake by then are dropped
+    const UDP_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+    /// How often a Udp listener forgets the remotes of closed channels
+    const UDP_PRUNE_INTERVAL: Duration = Duration::from_secs(1);
 
     pub(crate) async fn with_tcp_connect(
         addr: SocketAddr,
@@ -84,7 +125,7 @@ impl Protocols {
             "Connecting Tcp to: {}",
             stream.peer_addr().map_err(NetworkConnectError::Io)?
         );
-        Ok(Self::new_tcp(stream, metrics))
+        Ok(Self::new_tcp(stream, metrics, KeepAlive::default()))
     }
 
     pub(crate) async fn with_tcp_listen(
@@ -94,6 +135,52 @@ impl Protocols {
         s2s_stop_listening_r: oneshot::Receiver<()>,
         c2s_protocol_s: mpsc::UnboundedSender<(Self, Cid)>,
     ) -> std::io::Result<()> {
+        let listener = Self::bind_tcp(addr)?;
+        trace!(?addr, "Tcp Listener bound");
+        let mut end_receiver = s2s_stop_listening_r.fuse();
+        tokio::spawn(async move {
+            while let Some(data) = select! {
+                    next = listener.accept().fuse() => Some(next),
+                    _ = &mut end_receiver => None,
+            } {
+                let (stream, remote_addr) = match data {
+                    Ok((s, p)) => (s, p),
+                    Err(e) => {
+                        trace!(?e, "TcpStream Error, ignoring connection attempt");
+                        continue;
+                    },
+                };
+                if let Err(e) = stream.set_nodelay(true) {
+                    warn!(
+                        ?e,
+                        "Failed to set TCP_NODELAY, client may have degraded latency"
+                    );
+                }
+                let cid = cids.fetch_add(1, Ordering::Relaxed);
+                info!(?remote_addr, ?cid, "Accepting Tcp from");
+                let metrics = ProtocolMetricCache::new(&cid.to_string(), Arc::clone(&metrics));
+                #[cfg(feature = "websocket")]
+                {
+                    // detecting the transport needs to wait for the first bytes of the client,
+                    // don't block the listener on it
+                    let c2s_protocol_s = c2s_protocol_s.clone();
+                    tokio::spawn(async move {
+                        if let Some(protocol) = Self::detect_tcp_or_ws(stream, metrics).await {
+                            let _ = c2s_protocol_s.send((protocol, cid));
+                        }
+                    });
+                }
+                #[cfg(not(feature = "websocket"))]
+                let _ = c2s_protocol_s.send((
+                    Self::new_tcp(stream, metrics.clone(), KeepAlive::default()),
+                    cid,
+                ));
+            }
+        });
+        Ok(())
+    }
+
+    fn bind_tcp(addr: SocketAddr) -> std::io::Result<net::TcpListener> {
         use socket2::{Domain, Socket, Type};
         let domain = Domain::for_address(addr);
         let socket2_socket = Socket::new(domain, Type::STREAM, None)?;
@@ -108,8 +195,122 @@ impl Protocols {
         socket2_socket.bind(&socket2_addr)?;
         socket2_socket.listen(1024)?;
         let std_listener: std::net::TcpListener = socket2_socket.into();
-        let listener = tokio::net::TcpListener::from_std(std_listener)?;
-        trace!(?addr, "Tcp Listener bound");
+        net::TcpListener::from_std(std_listener)
+    }
+
+    /// The connecting side starts the handshake on every transport: a Tcp
+    /// client opens with a `Handshake` frame while a WebSocket client sends a
+    /// HTTP `GET` upgrade request. The first byte tells them apart, so this
+    /// only waits for it or for the client to disconnect.
+    #[cfg(feature = "websocket")]
+    async fn detect_tcp_or_ws(
+        stream: tokio::net::TcpStream,
+        metrics: ProtocolMetricCache,
+    ) -> Option<Self> {
+        let mut first = [0u8; 1];
+        match stream.peek(&mut first).await {
+            Ok(1) if first[0] == b'G' => match tokio_tungstenite::accept_async(stream).await {
+                Ok(ws) => Some(Self::new_ws(ws, metrics, KeepAlive::default())),
+                Err(e) => {
+                    trace!(
+                        ?e,
+                        "WebSocket handshake failed, ignoring connection attempt"
+                    );
+                    None
+                },
+            },
+            Ok(0) | Err(_) => {
+                trace!("Tcp client disconnected, ignoring connection attempt");
+                None
+            },
+            Ok(_) => Some(Self::new_tcp(stream, metrics, KeepAlive::default())),
+        }
+    }
+
+    /// Requires the channel to be encrypted with `keys`, see [`ChannelCipher`].
+    /// Only Tcp, WebSocket and Udp channels are encrypted this way, Quic comes
+    /// with its own encryption and Mpsc channels never leave the process.
+    ///
+    /// [`ChannelCipher`]: network_protocol::ChannelCipher
+    pub(crate) fn with_encryption(self, keys: NoiseKeys) -> Self {
+        match self {
+            Protocols::Tcp((s, r)) => Protocols::Tcp((s.with_encryption(keys), r)),
+            #[cfg(feature = "tls")]
+            Protocols::TcpTls((s, r)) => Protocols::TcpTls((s.with_encryption(keys), r)),
+            #[cfg(feature = "websocket")]
+            Protocols::Ws((s, r)) => Protocols::Ws((s.with_encryption(keys), r)),
+            Protocols::Udp((s, r), handshake) => {
+                Protocols::Udp((s.with_encryption(keys), r), handshake)
+            },
+            protocols => protocols,
+        }
+    }
+
+    pub(crate) fn new_tcp(
+        stream: tokio::net::TcpStream,
+        metrics: ProtocolMetricCache,
+        keepalive: KeepAlive,
+    ) -> Self {
+        let (r, w) = stream.into_split();
+        let metrics = metrics.with_protocol("tcp");
+        let rtt = ChannelRtt::new();
+        let sp = TcpSendProtocol::new(TcpDrain { half: w }, metrics.clone(), keepalive)
+            .with_rtt(rtt.clone());
+        let rp = TcpRecvProtocol::new(
+            TcpSink {
+                half: r,
+                buffer: BytesMut::new(),
+                timeout: keepalive.timeout,
+            },
+            metrics,
+        )
+        .with_rtt(rtt);
+        Protocols::Tcp((sp, rp))
+    }
+
+    #[cfg(feature = "tls")]
+    pub(crate) async fn with_tcp_tls_connect(
+        addr: SocketAddr,
+        config: Arc<rustls::ClientConfig>,
+        name: String,
+        metrics: ProtocolMetricCache,
+    ) -> Result<Self, NetworkConnectError> {
+        let server_name = rustls::ServerName::try_from(name.as_str())
+            .map_err(|e| NetworkConnectError::Io(io::Error::new(io::ErrorKind::InvalidInput, e)))?;
+        let stream = net::TcpStream::connect(addr)
+            .await
+            .and_then(|s| {
+                s.set_nodelay(true)?;
+                Ok(s)
+            })
+            .map_err(NetworkConnectError::Io)?;
+        info!("Connecting Tls to: {}", addr);
+        let stream = TlsConnector::from(config)
+            .connect(server_name, stream)
+            .await
+            .map_err(|e| {
+                trace!(?e, "error with tls handshake");
+                NetworkConnectError::Io(e)
+            })?;
+        Ok(Self::new_tcp_tls(
+            stream.into(),
+            metrics,
+            KeepAlive::default(),
+        ))
+    }
+
+    #[cfg(feature = "tls")]
+    pub(crate) async fn with_tcp_tls_listen(
+        addr: SocketAddr,
+        server_config: Arc<rustls::ServerConfig>,
+        cids: Arc<AtomicU64>,
+        metrics: Arc<ProtocolMetrics>,
+        s2s_stop_listening_r: oneshot::Receiver<()>,
+        c2s_protocol_s: mpsc::UnboundedSender<(Self, Cid)>,
+    ) -> std::io::Result<()> {
+        let listener = Self::bind_tcp(addr)?;
+        trace!(?addr, "Tls Listener bound");
+        let acceptor = TlsAcceptor::from(server_config);
         let mut end_receiver = s2s_stop_listening_r.fuse();
         tokio::spawn(async move {
             while let Some(data) = select! {
@@ -130,25 +331,94 @@ impl Protocols {
                     );
                 }
                 let cid = cids.fetch_add(1, Ordering::Relaxed);
-                info!(?remote_addr, ?cid, "Accepting Tcp from");
+                info!(?remote_addr, ?cid, "Accepting Tls from");
                 let metrics = ProtocolMetricCache::new(&cid.to_string(), Arc::clone(&metrics));
-                let _ = c2s_protocol_s.send((Self::new_tcp(stream, metrics.clone()), cid));
+                // the handshake takes a few round trips, don't block the listener on it
+                let accept = acceptor.accept(stream);
+                let c2s_protocol_s = c2s_protocol_s.clone();
+                tokio::spawn(async move {
+                    match tokio::time::timeout(Self::TLS_HANDSHAKE_TIMEOUT, accept).await {
+                        Ok(Ok(stream)) => {
+                            let protocol =
+                                Self::new_tcp_tls(stream.into(), metrics, KeepAlive::default());
+                            let _ = c2s_protocol_s.send((protocol, cid));
+                        },
+                        Ok(Err(e)) => {
+                            trace!(?e, "Tls handshake failed, ignoring connection attempt")
+                        },
+                        Err(_) => trace!("Tls handshake timed out, ignoring connection attempt"),
+                    }
+                });
             }
         });
         Ok(())
     }
 
-    pub(crate) fn new_tcp(stream: tokio::net::TcpStream, metrics: ProtocolMetricCache) -> Self {
-        let (r, w) = stream.into_split();
-        let sp = TcpSendProtocol::new(TcpDrain { half: w }, metrics.clone());
+    #[cfg(feature = "tls")]
+    pub(crate) fn new_tcp_tls(
+        stream: TlsStream<net::TcpStream>,
+        metrics: ProtocolMetricCache,
+        keepalive: KeepAlive,
+    ) -> Self {
+        let (r, w) = tokio::io::split(stream);
+        let metrics = metrics.with_protocol("tcp_tls");
+        let rtt = ChannelRtt::new();
+        let sp = TcpSendProtocol::new(TcpDrain { half: w }, metrics.clone(), keepalive)
+            .with_rtt(rtt.clone());
         let rp = TcpRecvProtocol::new(
             TcpSink {
                 half: r,
                 buffer: BytesMut::new(),
+                timeout: keepalive.timeout,
             },
             metrics,
-        );
-        Protocols::Tcp((sp, rp))
+        )
+        .with_rtt(rtt);
+        Protocols::TcpTls((sp, rp))
+    }
+
+    #[cfg(feature = "websocket")]
+    pub(crate) async fn with_ws_connect(
+        addr: SocketAddr,
+        metrics: ProtocolMetricCache,
+    ) -> Result<Self, NetworkConnectError> {
+        let stream = net::TcpStream::connect(addr)
+            .await
+            .and_then(|s| {
+                s.set_nodelay(true)?;
+                Ok(s)
+            })
+            .map_err(NetworkConnectError::Io)?;
+        info!("Connecting WebSocket to: {}", addr);
+        let (ws, _) = tokio_tungstenite::client_async(format!("ws://{}/", addr), stream)
+            .await
+            .map_err(|e| {
+                trace!(?e, "error with websocket handshake");
+                NetworkConnectError::Io(io::Error::new(io::ErrorKind::ConnectionAborted, e))
+            })?;
+        Ok(Self::new_ws(ws, metrics, KeepAlive::default()))
+    }
+
+    #[cfg(feature = "websocket")]
+    pub(crate) fn new_ws(
+        stream: WebSocketStream<tokio::net::TcpStream>,
+        metrics: ProtocolMetricCache,
+        keepalive: KeepAlive,
+    ) -> Self {
+        let (w, r) = stream.split();
+        let metrics = metrics.with_protocol("websocket");
+        let rtt = ChannelRtt::new();
+        let sp = TcpSendProtocol::new(WsDrain { half: w }, metrics.clone(), keepalive)
+            .with_rtt(rtt.clone());
+        let rp = TcpRecvProtocol::new(
+            WsSink {
+                half: r,
+                timeout: keepalive.timeout,
+            },
+            metrics,
+        )
+        .with_rtt(rtt);
+        Protocols::Ws((sp, rp))
     }
 
     pub(crate) async fn with_mpsc_connect(
@@ -232,6 +502,146 @@ impl Protocols {
         Protocols::Mpsc((sp, rp))
     }
 
+    pub(crate) async fn with_udp_connect(
+        addr: SocketAddr,
+        metrics: ProtocolMetricCache,
+    ) -> Result<Self, NetworkConnectError> {
+        use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+        let bindsock = match addr {
+            SocketAddr::V4(_) => SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0),
+            SocketAddr::V6(_) => SocketAddr::new(IpAddr::V6(Ipv6Addr::UNSPECIFIED), 0),
+        };
+        let socket = net::UdpSocket::bind(bindsock)
+            .await
+            .map_err(NetworkConnectError::Io)?;
+        socket
+            .connect(addr)
+            .await
+            .map_err(NetworkConnectError::Io)?;
+        info!("Connecting Udp to: {}", &addr);
+        let peer = UdpPeer {
+            socket: Arc::new(socket),
+            remote: None,
+        };
+        Ok(Self::new_udp(peer, None, false, metrics))
+    }
+
+    /// All Udp channels of a listener share its socket. The listener passes
+    /// datagrams on to the channel of their remote address, handshake
+    /// datagrams from other addresses open a new channel. Remotes are
+    /// forgotten once their channel is closed, which includes a failed or
+    /// timed out handshake.
+    pub(crate) async fn with_udp_listen(
+        addr: SocketAddr,
+        cids: Arc<AtomicU64>,
+        metrics: Arc<ProtocolMetrics>,
+        s2s_stop_listening_r: oneshot::Receiver<()>,
+        c2s_protocol_s: mpsc::UnboundedSender<(Self, Cid)>,
+    ) -> std::io::Result<()> {
+        let socket = Arc::new(net::UdpSocket::bind(addr).await?);
+        trace!(?addr, "Udp Listener bound");
+        let mut end_receiver = s2s_stop_listening_r.fuse();
+        tokio::spawn(async move {
+            let mut channels: HashMap<SocketAddr, mpsc::Sender<BytesMut>> = HashMap::new();
+            let mut listening = true;
+            let mut prune = tokio::time::interval(Self::UDP_PRUNE_INTERVAL);
+            let mut buffer = vec![0u8; UDP_MAX_DATAGRAM];
+            loop {
+                let (len, remote_addr) = select! {
+                    next = socket.recv_from(&mut buffer) => match next {
+                        Ok(next) => next,
+                        Err(e) => {
+                            trace!(?e, "Udp recv error, ignoring datagram");
+                            continue;
+                        },
+                    },
+                    _ = &mut end_receiver, if listening => {
+                        // established channels keep using the socket
+                        listening = false;
+                        continue;
+                    },
+                    _ = prune.tick() => {
+                        channels.retain(|_, datagram_s| !datagram_s.is_closed());
+                        if !listening && channels.is_empty() {
+                            break;
+                        }
+                        continue;
+                    },
+                };
+                let datagram = BytesMut::from(&buffer[..len]);
+                let datagram = match channels.get(&remote_addr) {
+                    Some(datagram_s) => match datagram_s.try_send(datagram) {
+                        // like on a congested link
+                        Ok(()) | Err(mpsc::error::TrySendError::Full(_)) => continue,
+                        Err(mpsc::error::TrySendError::Closed(datagram)) => {
+                            channels.remove(&remote_addr);
+                            datagram
+                        },
+                    },
+                    None => datagram,
+                };
+                if !listening || !is_udp_handshake(&datagram) {
+                    continue;
+                }
+                let (datagram_s, datagram_r) = mpsc::channel(Self::UDP_CHANNEL_BOUND);
+                let _ = datagram_s.try_send(datagram);
+                channels.insert(remote_addr, datagram_s);
+
+                let cid = cids.fetch_add(1, Ordering::Relaxed);
+                info!(?remote_addr, ?cid, "Accepting Udp from");
+                let metrics = ProtocolMetricCache::new(&cid.to_string(), Arc::clone(&metrics));
+                let peer = UdpPeer {
+                    socket: Arc::clone(&socket),
+                    remote: Some(remote_addr),
+                };
+                let _ = c2s_protocol_s
+                    .send((Self::new_udp(peer, Some(datagram_r), true, metrics), cid));
+            }
+            trace!(?addr, "Udp Listener stopped");
+        });
+        Ok(())
+    }
+
+    /// `datagrams` are passed on by a listener, without them they are read
+    /// from the socket, which is connected to the remote then
+    fn new_udp(
+        peer: UdpPeer,
+        datagrams: Option<mpsc::Receiver<BytesMut>>,
+        listen: bool,
+        metrics: ProtocolMetricCache,
+    ) -> Self {
+        let metrics = metrics.with_protocol("udp");
+        let handshake = Arc::new(std::sync::Mutex::new(UdpHandshake {
+            listen,
+            ..UdpHandshake::default()
+        }));
+        let acks = UdpAcks::default();
+        let rtt = ChannelRtt::new();
+        let sp = UdpSendProtocol::new(
+            UdpDrain {
+                peer: peer.clone(),
+                handshake: Arc::clone(&handshake),
+            },
+            acks.clone(),
+            metrics.clone(),
+        )
+        .with_rtt(rtt.clone());
+        let rp = UdpRecvProtocol::new(
+            UdpSink {
+                peer,
+                datagrams,
+                buffer: vec![0u8; UDP_MAX_DATAGRAM],
+                handshake: Arc::clone(&handshake),
+                retransmit: UDP_HANDSHAKE_RETRANSMIT,
+            },
+            acks,
+            metrics,
+        )
+        .with_rtt(rtt);
+        Protocols::Udp((sp, rp), handshake)
+    }
+
     #[cfg(feature = "quic")]
     pub(crate) async fn with_quic_connect(
         addr: SocketAddr,
@@ -330,18 +740,21 @@ impl Protocols {
         listen: bool,
         metrics: ProtocolMetricCache,
     ) -> Result<Self, quinn::ConnectionError> {
+        // the connecting side sends the first handshake frame, a stream is only
+        // announced to the remote once data is sent on it
         let (sendstream, recvstream) = if listen {
-            connection.connection.open_bi().await?
-        } else {
             connection
                 .bi_streams
                 .next()
                 .await
                 .ok_or(quinn::ConnectionError::LocallyClosed)??
+        } else {
+            connection.connection.open_bi().await?
         };
         let (recvstreams_s, recvstreams_r) = mpsc::unbounded_channel();
         let streams_s_clone = recvstreams_s.clone();
         let (sendstreams_s, sendstreams_r) = mpsc::unbounded_channel();
+        let metrics = metrics.with_protocol("quic");
         let sp = QuicSendProtocol::new(
             QuicDrain {
                 con: connection.connection.clone(),
@@ -366,12 +779,73 @@ impl Protocols {
         Ok(Protocols::Quic((sp, rp)))
     }
 
+    /// Reports the messages of every stream of the channel to `observer`
+    pub(crate) fn set_stream_observer(&mut self, observer: Arc<dyn StreamObserver>) {
+        match self {
+            Protocols::Tcp((s, r)) => {
+                s.set_stream_observer(Arc::clone(&observer));
+                r.set_stream_observer(observer);
+            },
+            #[cfg(feature = "tls")]
+            Protocols::TcpTls((s, r)) => {
+                s.set_stream_observer(Arc::clone(&observer));
+                r.set_stream_observer(observer);
+            },
+            Protocols::Mpsc((s, r)) => {
+                s.set_stream_observer(Arc::clone(&observer));
+                r.set_stream_observer(observer);
+            },
+            #[cfg(feature = "quic")]
+            Protocols::Quic((s, r)) => {
+                s.set_stream_observer(Arc::clone(&observer));
+                r.set_stream_observer(observer);
+            },
+            #[cfg(feature = "websocket")]
+            Protocols::Ws((s, r)) => {
+                s.set_stream_observer(Arc::clone(&observer));
+                r.set_stream_observer(observer);
+            },
+            Protocols::Udp((s, r), _) => {
+                s.set_stream_observer(Arc::clone(&observer));
+                r.set_stream_observer(observer);
+            },
+        }
+    }
+
+    /// Shows the frames of the channel to `observer`. Only Tcp based channels
+    /// have frames in the byte layout of Tcp, the others are left alone.
+    #[cfg(feature = "debug")]
+    pub(crate) fn set_frame_observer(&mut self, observer: Arc<dyn FrameObserver>) {
+        match self {
+            Protocols::Tcp((s, r)) => {
+                s.set_frame_observer(Arc::clone(&observer));
+                r.set_frame_observer(observer);
+            },
+            #[cfg(feature = "tls")]
+            Protocols::TcpTls((s, r)) => {
+                s.set_frame_observer(Arc::clone(&observer));
+                r.set_frame_observer(observer);
+            },
+            #[cfg(feature = "websocket")]
+            Protocols::Ws((s, r)) => {
+                s.set_frame_observer(Arc::clone(&observer));
+                r.set_frame_observer(observer);
+            },
+            _ => {},
+        }
+    }
+
     pub(crate) fn split(self) -> (SendProtocols, RecvProtocols) {
         match self {
             Protocols::Tcp((s, r)) => (SendProtocols::Tcp(s), RecvProtocols::Tcp(r)),
+            #[cfg(feature = "tls")]
+            Protocols::TcpTls((s, r)) => (SendProtocols::TcpTls(s), RecvProtocols::TcpTls(r)),
             Protocols::Mpsc((s, r)) => (SendProtocols::Mpsc(s), RecvProtocols::Mpsc(r)),
             #[cfg(feature = "quic")]
             Protocols::Quic((s, r)) => (SendProtocols::Quic(s), RecvProtocols::Quic(r)),
+            #[cfg(feature = "websocket")]
+            Protocols::Ws((s, r)) => (SendProtocols::Ws(s), RecvProtocols::Ws(r)),
+            Protocols::Udp((s, r), _) => (SendProtocols::Udp(s), RecvProtocols::Udp(r)),
         }
     }
 }
@@ -383,12 +857,75 @@ impl network_protocol::InitProtocol for Protocols {
         initializer: bool,
         local_pid: Pid,
         secret: u128,
+        version_policy: VersionPolicy,
     ) -> Result<(Pid, Sid, u128), InitProtocolError> {
         match self {
-            Protocols::Tcp(p) => p.initialize(initializer, local_pid, secret).await,
-            Protocols::Mpsc(p) => p.initialize(initializer, local_pid, secret).await,
+            Protocols::Tcp(p) => {
+                p.initialize(initializer, local_pid, secret, version_policy)
+                    .await
+            },
+            #[cfg(feature = "tls")]
+            Protocols::TcpTls(p) => {
+                p.initialize(initializer, local_pid, secret, version_policy)
+                    .await
+            },
+            Protocols::Mpsc(p) => {
+                p.initialize(initializer, local_pid, secret, version_policy)
+                    .await
+            },
+            #[cfg(feature = "quic")]
+            Protocols::Quic(p) => {
+                p.initialize(initializer, local_pid, secret, version_policy)
+                    .await
+            },
+            #[cfg(feature = "websocket")]
+            Protocols::Ws(p) => {
+                p.initialize(initializer, local_pid, secret, version_policy)
+                    .await
+            },
+            Protocols::Udp(p, handshake) => {
+                let result = tokio::time::timeout(
+                    Self::UDP_HANDSHAKE_TIMEOUT,
+                    p.initialize(initializer, local_pid, secret, version_policy),
+                )
+                .await
+                .unwrap_or(Err(InitProtocolError::Closed));
+                handshake.lock().unwrap().finished = true;
+                result
+            },
+        }
+    }
+}
+
+impl SendProtocols {
+    /// Smoothed round trip time of the channel, only measured by the
+    /// protocols that send pings
+    pub(crate) fn rtt(&self) -> Option<Duration> {
+        match self {
+            SendProtocols::Tcp(s) => s.rtt(),
+            #[cfg(feature = "tls")]
+            SendProtocols::TcpTls(s) => s.rtt(),
+            SendProtocols::Mpsc(_) => None,
             #[cfg(feature = "quic")]
-            Protocols::Quic(p) => p.initialize(initializer, local_pid, secret).await,
+            SendProtocols::Quic(_) => None,
+            #[cfg(feature = "websocket")]
+            SendProtocols::Ws(s) => s.rtt(),
+            SendProtocols::Udp(s) => s.rtt(),
+        }
+    }
+
+    /// Streams that still have messages queued, mpsc hands them over right away
+    pub(crate) fn queued_streams(&self) -> Vec<Sid> {
+        match self {
+            SendProtocols::Tcp(s) => s.queued_streams(),
+            #[cfg(feature = "tls")]
+            SendProtocols::TcpTls(s) => s.queued_streams(),
+            SendProtocols::Mpsc(_) => Vec::new(),
+            #[cfg(feature = "quic")]
+            SendProtocols::Quic(s) => s.queued_streams(),
+            #[cfg(feature = "websocket")]
+            SendProtocols::Ws(s) => s.queued_streams(),
+            SendProtocols::Udp(s) => s.queued_streams(),
         }
     }
 }
@@ -398,18 +935,28 @@ impl network_protocol::SendProtocol for SendProtocols {
     fn notify_from_recv(&mut self, event: ProtocolEvent) {
         match self {
             SendProtocols::Tcp(s) => s.notify_from_recv(event),
+            #[cfg(feature = "tls")]
+            SendProtocols::TcpTls(s) => s.notify_from_recv(event),
             SendProtocols::Mpsc(s) => s.notify_from_recv(event),
             #[cfg(feature = "quic")]
             SendProtocols::Quic(s) => s.notify_from_recv(event),
+            #[cfg(feature = "websocket")]
+            SendProtocols::Ws(s) => s.notify_from_recv(event),
+            SendProtocols::Udp(s) => s.notify_from_recv(event),
         }
     }
 
     async fn send(&mut self, event: ProtocolEvent) -> Result<(), ProtocolError> {
         match self {
             SendProtocols::Tcp(s) => s.send(event).await,
+            #[cfg(feature = "tls")]
+            SendProtocols::TcpTls(s) => s.send(event).await,
             SendProtocols::Mpsc(s) => s.send(event).await,
             #[cfg(feature = "quic")]
             SendProtocols::Quic(s) => s.send(event).await,
+            #[cfg(feature = "websocket")]
+            SendProtocols::Ws(s) => s.send(event).await,
+            SendProtocols::Udp(s) => s.send(event).await,
         }
     }
 
@@ -420,9 +967,14 @@ impl network_protocol::SendProtocol for SendProtocols {
     ) -> Result<Bandwidth, ProtocolError> {
         match self {
             SendProtocols::Tcp(s) => s.flush(bandwidth, dt).await,
+            #[cfg(feature = "tls")]
+            SendProtocols::TcpTls(s) => s.flush(bandwidth, dt).await,
             SendProtocols::Mpsc(s) => s.flush(bandwidth, dt).await,
             #[cfg(feature = "quic")]
             SendProtocols::Quic(s) => s.flush(bandwidth, dt).await,
+            #[cfg(feature = "websocket")]
+            SendProtocols::Ws(s) => s.flush(bandwidth, dt).await,
+            SendProtocols::Udp(s) => s.flush(bandwidth, dt).await,
         }
     }
 }
@@ -432,48 +984,79 @@ impl network_protocol::RecvProtocol for RecvProtocols {
     async fn recv(&mut self) -> Result<ProtocolEvent, ProtocolError> {
         match self {
             RecvProtocols::Tcp(r) => r.recv().await,
+            #[cfg(feature = "tls")]
+            RecvProtocols::TcpTls(r) => r.recv().await,
             RecvProtocols::Mpsc(r) => r.recv().await,
             #[cfg(feature = "quic")]
             RecvProtocols::Quic(r) => r.recv().await,
+            #[cfg(feature = "websocket")]
+            RecvProtocols::Ws(r) => r.recv().await,
+            RecvProtocols::Udp(r) => r.recv().await,
         }
     }
 }
 
 ///////////////////////////////////////
 //// TCP
+// Tls uses them too, with the halves of the encrypted stream
 #[derive(Debug)]
-pub struct TcpDrain {
-    half: OwnedWriteHalf,
+pub struct TcpDrain<W = OwnedWriteHalf> {
+    half: W,
 }
 
+/// Reads go into the spare capacity of one big buffer and the chunks handed
+/// out are slices of it, so a large message doesn't cost an allocation per
+/// read
+const TCP_READ_BUFFER: usize = 65_536;
+/// A read never has less room than this
+const TCP_MIN_READ: usize = 1500;
+
 #[derive(Debug)]
-pub struct TcpSink {
-    half: OwnedReadHalf,
+pub struct TcpSink<R = OwnedReadHalf> {
+    half: R,
     buffer: BytesMut,
+    /// the remote is considered dead once nothing was received for this long
+    timeout: Duration,
 }
 
+#[cfg(feature = "tls")]
+pub type TlsDrain = TcpDrain<WriteHalf<TlsStream<net::TcpStream>>>;
+#[cfg(feature = "tls")]
+pub type TlsSink = TcpSink<ReadHalf<TlsStream<net::TcpStream>>>;
+
 #[async_trait]
-impl UnreliableDrain for TcpDrain {
+impl<W: AsyncWrite + Unpin + Send> UnreliableDrain for TcpDrain<W> {
     type DataFormat = BytesMut;
 
     async fn send(&mut self, data: Self::DataFormat) -> Result<(), ProtocolError> {
+        // flushing is a noop for plain Tcp, Tls might still hold back a record
         match self.half.write_all(&data).await {
-            Ok(()) => Ok(()),
+            Ok(()) => self.half.flush().await.map_err(|_| ProtocolError::Closed),
             Err(_) => Err(ProtocolError::Closed),
         }
     }
 }
 
 #[async_trait]
-impl UnreliableSink for TcpSink {
+impl<R: AsyncRead + Unpin + Send> UnreliableSink for TcpSink<R> {
     type DataFormat = BytesMut;
 
     async fn recv(&mut self) -> Result<Self::DataFormat, ProtocolError> {
-        self.buffer.resize(1500, 0u8);
-        match self.half.read(&mut self.buffer).await {
-            Ok(0) => Err(ProtocolError::Closed),
-            Ok(n) => Ok(self.buffer.split_to(n)),
-            Err(_) => Err(ProtocolError::Closed),
+        if self.buffer.capacity() < TCP_MIN_READ {
+            // reuses the old buffer if all chunks of it were dropped
+            self.buffer.reserve(TCP_READ_BUFFER);
+        }
+        match tokio::time::timeout(self.timeout, self.half.read_buf(&mut self.buffer)).await {
+            Ok(Ok(0)) => Err(ProtocolError::Closed),
+            Ok(Ok(_)) => Ok(self.buffer.split()),
+            Ok(Err(e)) => {
+                debug!(?e, "tcp read failed, closing channel");
+                Err(ProtocolError::Closed)
+            },
+            Err(_) => {
+                info!(timeout = ?self.timeout, "tcp remote went silent, closing channel");
+                Err(ProtocolError::Closed)
+            },
         }
     }
 }
@@ -651,7 +1234,10 @@ impl UnreliableSink for QuicSink {
                 data: buffer.split_to(n),
             }),
             Ok(None) => Err(ProtocolError::Closed),
-            Err(_) => Err(ProtocolError::Closed),
+            Err(e) => {
+                debug!(?e, "quic read failed, closing channel");
+                Err(ProtocolError::Closed)
+            },
         }?;
 
         let streams_s_clone = self.recvstreams_s.clone();
@@ -664,12 +1250,219 @@ impl UnreliableSink for QuicSink {
     }
 }
 
+///////////////////////////////////////
+//// WEBSOCKET
+// uses the same byte layout as TCP, every flush ends up in one binary message
+#[cfg(feature = "websocket")]
+#[derive(Debug)]
+pub struct WsDrain {
+    half: SplitSink<WebSocketStream<tokio::net::TcpStream>, WsMessage>,
+}
+
+#[cfg(feature = "websocket")]
+#[derive(Debug)]
+pub struct WsSink {
+    half: SplitStream<WebSocketStream<tokio::net::TcpStream>>,
+    /// the remote is considered dead once nothing was received for this long
+    timeout: Duration,
+}
+
+#[cfg(feature = "websocket")]
+#[async_trait]
+impl UnreliableDrain for WsDrain {
+    type DataFormat = BytesMut;
+
+    async fn send(&mut self, data: Self::DataFormat) -> Result<(), ProtocolError> {
+        match self.half.send(WsMessage::Binary(data.to_vec())).await {
+            Ok(()) => Ok(()),
+            Err(_) => Err(ProtocolError::Closed),
+        }
+    }
+}
+
+#[cfg(feature = "websocket")]
+#[async_trait]
+impl UnreliableSink for WsSink {
+    type DataFormat = BytesMut;
+
+    async fn recv(&mut self) -> Result<Self::DataFormat, ProtocolError> {
+        loop {
+            let next = match tokio::time::timeout(self.timeout, self.half.next()).await {
+                Ok(next) => next,
+                Err(_) => {
+                    info!(timeout = ?self.timeout, "websocket remote went silent, closing channel");
+                    return Err(ProtocolError::Closed);
+                },
+            };
+            match next {
+                Some(Ok(WsMessage::Binary(data))) => return Ok(BytesMut::from(&data[..])),
+                // answered by tungstenite itself
+                Some(Ok(WsMessage::Ping(_) | WsMessage::Pong(_))) => continue,
+                Some(Ok(WsMessage::Text(_) | WsMessage::Frame(_))) => {
+                    return Err(ProtocolError::Violated);
+                },
+                Some(Err(e)) => {
+                    debug!(?e, "websocket read failed, closing channel");
+                    return Err(ProtocolError::Closed);
+                },
+                Some(Ok(WsMessage::Close(_))) | None => return Err(ProtocolError::Closed),
+            }
+        }
+    }
+}
+
+///////////////////////////////////////
+//// UDP
+/// Largest payload of a Udp datagram
+const UDP_MAX_DATAGRAM: usize = 65_507;
+/// First replay of the handshake datagrams if the remote doesn't answer,
+/// doubled for every further one
+const UDP_HANDSHAKE_RETRANSMIT: Duration = Duration::from_millis(250);
+const UDP_MAX_HANDSHAKE_RETRANSMIT: Duration = Duration::from_secs(2);
+/// Handshake datagrams remembered to detect repeated ones
+const UDP_MAX_HANDSHAKE_DATAGRAMS: usize = 16;
+
+/// The protocol doesn't retransmit handshake datagrams, so both sides replay
+/// the ones they sent until their handshake is done. Repeated datagrams of
+/// the remote are dropped. After the handshake, only the connecting side
+/// replays, when the listener repeats itself: the listener is still waiting
+/// for the last `Init` then. The listener never replays on repeated
+/// datagrams, so both sides can't keep triggering each other.
+#[derive(Debug, Default)]
+pub(crate) struct UdpHandshake {
+    sent: Vec<BytesMut>,
+    received: Vec<BytesMut>,
+    finished: bool,
+    listen: bool,
+}
+
+#[derive(Debug, Clone)]
+struct UdpPeer {
+    socket: Arc<net::UdpSocket>,
+    /// `None` if the socket is connected to the remote
+    remote: Option<SocketAddr>,
+}
+
+impl UdpPeer {
+    async fn send(&self, datagram: &[u8]) -> Result<(), ProtocolError> {
+        let result = match self.remote {
+            Some(remote) => self.socket.send_to(datagram, remote).await,
+            None => self.socket.send(datagram).await,
+        };
+        result.map(|_| ()).map_err(|e| {
+            debug!(?e, "udp send failed, closing channel");
+            ProtocolError::Closed
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct UdpDrain {
+    peer: UdpPeer,
+    handshake: Arc<std::sync::Mutex<UdpHandshake>>,
+}
+
+#[derive(Debug)]
+pub struct UdpSink {
+    peer: UdpPeer,
+    /// passed on by the listener, otherwise the socket is read directly
+    datagrams: Option<mpsc::Receiver<BytesMut>>,
+    buffer: Vec<u8>,
+    handshake: Arc<std::sync::Mutex<UdpHandshake>>,
+    retransmit: Duration,
+}
+
+impl UdpSink {
+    async fn next(&mut self) -> Result<BytesMut, ProtocolError> {
+        match &mut self.datagrams {
+            Some(datagrams) => datagrams.recv().await.ok_or(ProtocolError::Closed),
+            None => match self.peer.socket.recv(&mut self.buffer).await {
+                Ok(len) => Ok(BytesMut::from(&self.buffer[..len])),
+                Err(e) => {
+                    debug!(?e, "udp read failed, closing channel");
+                    Err(ProtocolError::Closed)
+                },
+            },
+        }
+    }
+
+    async fn replay(&mut self) -> Result<(), ProtocolError> {
+        let sent = self.handshake.lock().unwrap().sent.clone();
+        trace!(datagrams = sent.len(), "replay udp handshake");
+        for datagram in sent {
+            self.peer.send(&datagram).await?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl UnreliableDrain for UdpDrain {
+    type DataFormat = BytesMut;
+
+    async fn send(&mut self, data: Self::DataFormat) -> Result<(), ProtocolError> {
+        {
+            let mut handshake = self.handshake.lock().unwrap();
+            if !handshake.finished && is_udp_handshake(&data) {
+                handshake.sent.push(data.clone());
+            }
+        }
+        self.peer.send(&data).await
+    }
+}
+
+#[async_trait]
+impl UnreliableSink for UdpSink {
+    type DataFormat = BytesMut;
+
+    async fn recv(&mut self) -> Result<Self::DataFormat, ProtocolError> {
+        loop {
+            let finished = self.handshake.lock().unwrap().finished;
+            let datagram = if finished {
+                self.next().await?
+            } else {
+                match tokio::time::timeout(self.retransmit, self.next()).await {
+                    Ok(datagram) => datagram?,
+                    Err(_) => {
+                        self.retransmit = (self.retransmit * 2).min(UDP_MAX_HANDSHAKE_RETRANSMIT);
+                        self.replay().await?;
+                        continue;
+                    },
+                }
+            };
+            if is_udp_handshake(&datagram) {
+                // `Some(replay)` for a repeated datagram
+                let repeated = {
+                    let mut handshake = self.handshake.lock().unwrap();
+                    if handshake.received.contains(&datagram) {
+                        Some(handshake.finished && !handshake.listen)
+                    } else {
+                        if handshake.received.len() < UDP_MAX_HANDSHAKE_DATAGRAMS {
+                            handshake.received.push(datagram.clone());
+                        }
+                        None
+                    }
+                };
+                match repeated {
+                    Some(true) => self.replay().await?,
+                    Some(false) => {},
+                    None => return Ok(datagram),
+                }
+                continue;
+            }
+            return Ok(datagram);
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use bytes::Bytes;
-    use network_protocol::{Promises, ProtocolMetrics, RecvProtocol, SendProtocol};
-    use std::sync::Arc;
+    use network_protocol::{
+        Promises, ProtocolMetrics, RecvProtocol, SendProtocol, DEFAULT_STREAM_WINDOW,
+    };
+    use std::{sync::Arc, time::Instant};
     use tokio::net::{TcpListener, TcpStream};
 
     #[tokio::test]
@@ -682,8 +1475,8 @@ mod tests {
         let client = TcpStream::connect("127.0.0.1:5000").await.unwrap();
         let (_listener, server) = r1.await.unwrap();
         let metrics = ProtocolMetricCache::new("0", Arc::new(ProtocolMetrics::new().unwrap()));
-        let client = Protocols::new_tcp(client, metrics.clone());
-        let server = Protocols::new_tcp(server, metrics);
+        let client = Protocols::new_tcp(client, metrics.clone(), KeepAlive::default());
+        let server = Protocols::new_tcp(server, metrics, KeepAlive::default());
         let (mut s, _) = client.split();
         let (_, mut r) = server.split();
         let event = ProtocolEvent::OpenStream {
@@ -691,6 +1484,7 @@ mod tests {
             prio: 4u8,
             promises: Promises::GUARANTEED_DELIVERY,
             guaranteed_bandwidth: 1_000,
+            window: DEFAULT_STREAM_WINDOW,
         };
         s.send(event.clone()).await.unwrap();
         s.send(ProtocolEvent::Message {
@@ -708,7 +1502,7 @@ mod tests {
                 sid,
                 prio,
                 promises,
-                guaranteed_bandwidth: _,
+                ..
             }) => {
                 assert_eq!(sid, Sid::new(1));
                 assert_eq!(prio, 4u8);
@@ -731,8 +1525,8 @@ mod tests {
         let client = TcpStream::connect("127.0.0.1:5001").await.unwrap();
         let (_listener, server) = r1.await.unwrap();
         let metrics = ProtocolMetricCache::new("0", Arc::new(ProtocolMetrics::new().unwrap()));
-        let client = Protocols::new_tcp(client, metrics.clone());
-        let server = Protocols::new_tcp(server, metrics);
+        let client = Protocols::new_tcp(client, metrics.clone(), KeepAlive::default());
+        let server = Protocols::new_tcp(server, metrics, KeepAlive::default());
         let (s, _) = client.split();
         let (_, mut r) = server.split();
         let e = tokio::spawn(async move { r.recv().await });
@@ -741,4 +1535,201 @@ mod tests {
         assert!(e.is_err());
         assert_eq!(e.unwrap_err(), ProtocolError::Closed);
     }
+
+    #[tokio::test]
+    async fn tokio_sink_detects_silent_remote() {
+        let listener = TcpListener::bind("127.0.0.1:5002").await.unwrap();
+        let r1 = tokio::spawn(async move {
+            let (server, _) = listener.accept().await.unwrap();
+            (listener, server)
+        });
+        let client = TcpStream::connect("127.0.0.1:5002").await.unwrap();
+        let (_listener, server) = r1.await.unwrap();
+        let metrics = ProtocolMetricCache::new("0", Arc::new(ProtocolMetrics::new().unwrap()));
+        let keepalive = KeepAlive {
+            interval: Duration::from_millis(100),
+            timeout: Duration::from_millis(500),
+        };
+        let client = Protocols::new_tcp(client, metrics.clone(), keepalive);
+        let server = Protocols::new_tcp(server, metrics, keepalive);
+        let (mut s, _r) = client.split();
+        let (_, mut r) = server.split();
+        let e = tokio::spawn(async move { r.recv().await });
+
+        // nothing to send, but the pings keep the channel open
+        let start = Instant::now();
+        while start.elapsed() < keepalive.timeout * 3 {
+            s.flush(1_000_000, Duration::from_millis(50)).await.unwrap();
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        // the client hangs without closing its socket
+        let hang = Instant::now();
+        let e = e.await.unwrap();
+        assert_eq!(e.unwrap_err(), ProtocolError::Closed);
+        // the last ping was send up to one interval (plus a flush) before
+        assert!(hang.elapsed() >= keepalive.timeout / 2);
+        drop(s);
+    }
+
+    #[cfg(feature = "tls")]
+    async fn tls_pair(
+        port: u16,
+        fingerprint: Option<crate::tls::CertFingerprint>,
+    ) -> io::Result<(TlsStream<TcpStream>, TlsStream<TcpStream>)> {
+        use crate::tls::{pinned_client_config, CertFingerprint};
+        let cert = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        let cert_der = rustls::Certificate(cert.serialize_der().unwrap());
+        let key = rustls::PrivateKey(cert.serialize_private_key_der());
+        let fingerprint = fingerprint.unwrap_or_else(|| CertFingerprint::of(&cert_der));
+        let server_config = rustls::ServerConfig::builder()
+            .with_safe_defaults()
+            .with_no_client_auth()
+            .with_single_cert(vec![cert_der], key)
+            .unwrap();
+
+        let listener = TcpListener::bind(("127.0.0.1", port)).await.unwrap();
+        let acceptor = TlsAcceptor::from(Arc::new(server_config));
+        let r1 = tokio::spawn(async move {
+            let (server, _) = listener.accept().await.unwrap();
+            acceptor.accept(server).await
+        });
+        let client = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+        let server_name = rustls::ServerName::try_from("localhost").unwrap();
+        let client = TlsConnector::from(pinned_client_config(fingerprint))
+            .connect(server_name, client)
+            .await;
+        let server = r1.await.unwrap();
+        Ok((client?.into(), server?.into()))
+    }
+
+    #[cfg(feature = "tls")]
+    #[tokio::test]
+    async fn tls_handshake_and_frame() {
+        use network_protocol::InitProtocol;
+        let (client, server) = tls_pair(5003, None).await.unwrap();
+        let metrics = ProtocolMetricCache::new("0", Arc::new(ProtocolMetrics::new().unwrap()));
+        let mut client = Protocols::new_tcp_tls(client, metrics.clone(), KeepAlive::default());
+        let mut server = Protocols::new_tcp_tls(server, metrics, KeepAlive::default());
+        let (c, s) = tokio::join!(
+            client.initialize(false, Pid::fake(2), 1337, VersionPolicy::default()),
+            server.initialize(true, Pid::fake(3), 42, VersionPolicy::default()),
+        );
+        assert_eq!(c.unwrap().0, Pid::fake(3));
+        assert_eq!(s.unwrap().0, Pid::fake(2));
+
+        let (mut s, _) = client.split();
+        let (_, mut r) = server.split();
+        s.send(ProtocolEvent::OpenStream {
+            sid: Sid::new(10),
+            prio: 4u8,
+            promises: Promises::GUARANTEED_DELIVERY,
+            guaranteed_bandwidth: 1_000,
+            window: DEFAULT_STREAM_WINDOW,
+        })
+        .await
+        .unwrap();
+        s.send(ProtocolEvent::Message {
+            sid: Sid::new(10),
+            data: Bytes::from(&[8u8; 8][..]),
+        })
+        .await
+        .unwrap();
+        s.flush(1_000_000, Duration::from_secs(1)).await.unwrap();
+        assert!(matches!(
+            r.recv().await,
+            Ok(ProtocolEvent::OpenStream { sid, .. }) if sid == Sid::new(10)
+        ));
+        match r.recv().await {
+            Ok(ProtocolEvent::Message { sid, data }) => {
+                assert_eq!(sid, Sid::new(10));
+                assert_eq!(&data[..], &[8u8; 8]);
+            },
+            e => panic!("wrong type {:?}", e),
+        }
+    }
+
+    #[cfg(feature = "tls")]
+    #[tokio::test]
+    async fn tls_rejects_wrong_fingerprint() {
+        let fingerprint = crate::tls::CertFingerprint([7; 32]);
+        assert!(tls_pair(5004, Some(fingerprint)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn udp_handshake_survives_loss() {
+        use network_protocol::InitProtocol;
+        let listen_addr: SocketAddr = "127.0.0.1:5005".parse().unwrap();
+        let metrics = Arc::new(ProtocolMetrics::new().unwrap());
+        let (_stop_s, stop_r) = oneshot::channel();
+        let (c2s_protocol_s, mut c2s_protocol_r) = mpsc::unbounded_channel();
+        Protocols::with_udp_listen(
+            listen_addr,
+            Arc::new(AtomicU64::new(0)),
+            Arc::clone(&metrics),
+            stop_r,
+            c2s_protocol_s,
+        )
+        .await
+        .unwrap();
+
+        // relays between client and listener, dropping the first datagram of
+        // either side
+        let relay = Arc::new(net::UdpSocket::bind("127.0.0.1:5006").await.unwrap());
+        let relay_addr = relay.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut client_addr = None;
+            let mut dropped = (false, false);
+            let mut buffer = vec![0u8; UDP_MAX_DATAGRAM];
+            while let Ok((len, from)) = relay.recv_from(&mut buffer).await {
+                let (to, dropped) = if from == listen_addr {
+                    (client_addr, &mut dropped.0)
+                } else {
+                    client_addr = Some(from);
+                    (Some(listen_addr), &mut dropped.1)
+                };
+                if !*dropped {
+                    *dropped = true;
+                    continue;
+                }
+                if let Some(to) = to {
+                    let _ = relay.send_to(&buffer[..len], to).await;
+                }
+            }
+        });
+
+        let cache = ProtocolMetricCache::new("0", metrics);
+        let mut client = Protocols::with_udp_connect(relay_addr, cache)
+            .await
+            .unwrap();
+        let client = tokio::spawn(async move {
+            let result = client
+                .initialize(false, Pid::fake(0), 0, VersionPolicy::default())
+                .await;
+            (client, result)
+        });
+        let (mut server, _) = c2s_protocol_r.recv().await.unwrap();
+        let server_result = server
+            .initialize(true, Pid::fake(1), 1, VersionPolicy::default())
+            .await;
+        let (client, client_result) = client.await.unwrap();
+        assert_eq!(server_result.unwrap().0, Pid::fake(0));
+        assert_eq!(client_result.unwrap().0, Pid::fake(1));
+
+        let (mut s, _r) = client.split();
+        let (_s, mut r) = server.split();
+        s.send(ProtocolEvent::OpenStream {
+            sid: Sid::new(10),
+            prio: 4u8,
+            promises: Promises::GUARANTEED_DELIVERY,
+            guaranteed_bandwidth: 1_000,
+            window: DEFAULT_STREAM_WINDOW,
+        })
+        .await
+        .unwrap();
+        s.flush(1_000_000, Duration::from_secs(1)).await.unwrap();
+        assert!(matches!(
+            r.recv().await,
+            Ok(ProtocolEvent::OpenStream { sid, .. }) if sid == Sid::new(10)
+        ));
+    }
 }
@@ -0,0 +1,560 @@
+//! Tools to look at the frames of a channel while debugging, reading raw
+//! bytes in a hex dump is painful.
+use bytes::{Buf, BufMut, BytesMut};
+use hashbrown::HashMap;
+use network_protocol::{
+    Cid, FrameObserver,
+    _internal::{ITFrame, InitFrame, OTFrame},
+};
+use std::{
+    fmt::Write as _,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+use tracing::warn;
+
+/// Upper bounds of the size buckets used by [`FrameLogSummary`], everything
+/// bigger ends up in the last bucket.
+const SIZE_BUCKETS: [usize; 6] = [16, 64, 256, 1024, 1500, usize::MAX];
+/// Upper bounds of the buckets of the time since the previous frame of the
+/// same type, in microseconds
+const INTERVAL_BUCKETS: [u64; 6] = [100, 1_000, 10_000, 100_000, 1_000_000, u64::MAX];
+/// Length of the timestamp in front of every frame of a [`FrameLogger`] log
+const TIMESTAMP_LEN: usize = 8;
+
+/// Helper to make frames readable for humans.
+///
+/// # Example
+/// ```rust
+/// use bytes::Bytes;
+/// use network_protocol::_internal::OTFrame;
+/// use veloren_network::debug::FrameInspector;
+///
+/// let frame = OTFrame::Data {
+///     mid: 42,
+///     data: Bytes::from(&[0u8; 512][..]),
+/// };
+/// assert_eq!(
+///     FrameInspector::format_ot(&frame),
+///     "Frame::Data { mid: 42, len: 512 }"
+/// );
+/// ```
+pub struct FrameInspector;
+
+/// Statistics about a single frame type, collected by
+/// [`FrameInspector::summarize_log`].
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct FrameTypeSummary {
+    pub count: u64,
+    /// total bytes on the wire, including the frame id
+    pub bytes: u64,
+    pub min_size: usize,
+    pub max_size: usize,
+    /// number of frames per size bucket, see `FrameLogSummary::SIZE_BUCKETS`
+    pub size_distribution: [u64; SIZE_BUCKETS.len()],
+    /// number of frames per bucket of the time since the previous frame of
+    /// this type, see `FrameLogSummary::INTERVAL_BUCKETS`. The first frame of
+    /// a type isn't counted.
+    pub interval_distribution: [u64; INTERVAL_BUCKETS.len()],
+    /// time of the last frame of this type, in microseconds
+    last_micros: Option<u64>,
+}
+
+/// Summary of a whole frame log, see [`FrameInspector::summarize_log`].
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct FrameLogSummary {
+    pub frames: HashMap<&'static str, FrameTypeSummary>,
+    /// time between the first and the last frame of the log
+    pub duration: Duration,
+    /// bytes at the end of the log that didn't form a complete frame
+    pub trailing_bytes: usize,
+    /// set if the log contained bytes that are no valid frame, parsing stops
+    /// there
+    pub invalid_at: Option<usize>,
+}
+
+impl FrameLogSummary {
+    pub const INTERVAL_BUCKETS: [Duration; INTERVAL_BUCKETS.len()] = [
+        Duration::from_micros(INTERVAL_BUCKETS[0]),
+        Duration::from_micros(INTERVAL_BUCKETS[1]),
+        Duration::from_micros(INTERVAL_BUCKETS[2]),
+        Duration::from_micros(INTERVAL_BUCKETS[3]),
+        Duration::from_micros(INTERVAL_BUCKETS[4]),
+        Duration::MAX,
+    ];
+    pub const SIZE_BUCKETS: [usize; SIZE_BUCKETS.len()] = SIZE_BUCKETS;
+
+    pub fn total_frames(&self) -> u64 { self.frames.values().map(|s| s.count).sum() }
+
+    pub fn total_bytes(&self) -> u64 { self.frames.values().map(|s| s.bytes).sum() }
+
+    fn add(&mut self, name: &'static str, size: usize, micros: u64) {
+        let s = self.frames.entry(name).or_default();
+        if s.count == 0 || size < s.min_size {
+            s.min_size = size;
+        }
+        s.max_size = s.max_size.max(size);
+        s.count += 1;
+        s.bytes += size as u64;
+        let bucket = SIZE_BUCKETS
+            .iter()
+            .position(|&b| size <= b)
+            .unwrap_or(SIZE_BUCKETS.len() - 1);
+        s.size_distribution[bucket] += 1;
+        if let Some(last) = s.last_micros {
+            let interval = micros.saturating_sub(last);
+            let bucket = INTERVAL_BUCKETS
+                .iter()
+                .position(|&b| interval <= b)
+                .unwrap_or(INTERVAL_BUCKETS.len() - 1);
+            s.interval_distribution[bucket] += 1;
+        }
+        s.last_micros = Some(micros);
+    }
+}
+
+/// A frame read back from a [`FrameLogger`] log
+struct LogRecord {
+    micros: u64,
+    frame: ITFrame,
+    /// bytes of the frame on the wire, without the timestamp
+    size: usize,
+}
+
+/// How reading a [`FrameLogger`] log ended
+enum LogEnd {
+    /// Number of bytes that didn't form a complete record
+    Trailing(usize),
+    /// Offset of the first byte that isn't a valid frame
+    Invalid(usize),
+}
+
+/// Reads all records of a [`FrameLogger`] log
+fn read_log(bytes: &[u8]) -> (Vec<LogRecord>, LogEnd) {
+    let mut buffer = BytesMut::from(bytes);
+    let mut records = Vec::new();
+    loop {
+        if buffer.len() <= TIMESTAMP_LEN {
+            return (records, LogEnd::Trailing(buffer.len()));
+        }
+        let offset = bytes.len() - buffer.len();
+        let mut frame_bytes = buffer.split_off(TIMESTAMP_LEN);
+        let micros = buffer.get_u64_le();
+        let before = frame_bytes.len();
+        match ITFrame::read_frame(&mut frame_bytes) {
+            Ok(Some(frame)) => records.push(LogRecord {
+                micros,
+                frame,
+                size: before - frame_bytes.len(),
+            }),
+            Ok(None) => return (records, LogEnd::Trailing(TIMESTAMP_LEN + before)),
+            Err(_) => return (records, LogEnd::Invalid(offset + TIMESTAMP_LEN)),
+        }
+        buffer = frame_bytes;
+    }
+}
+
+impl FrameInspector {
+    pub fn format_init(frame: &InitFrame) -> String {
+        match frame {
+            InitFrame::Handshake {
+                magic_number,
+                version,
+                encrypted,
+            } => format!(
+                "Frame::Handshake {{ magic_number: {:?}, version: {}.{}.{}, encrypted: {} }}",
+                String::from_utf8_lossy(magic_number),
+                version[0],
+                version[1],
+                version[2],
+                encrypted
+            ),
+            InitFrame::Init { pid, secret: _ } => {
+                format!("Frame::Init {{ pid: {}, secret: <hidden> }}", pid)
+            },
+            InitFrame::VersionMismatch {
+                server_version: s,
+                min_client: m,
+            } => format!(
+                "Frame::VersionMismatch {{ server_version: {}.{}.{}, min_client: {}.{}.{} }}",
+                s[0], s[1], s[2], m[0], m[1], m[2]
+            ),
+            InitFrame::Raw(data) => format!("Frame::Raw {{ len: {} }}", data.len()),
+            InitFrame::Noise(data) => format!("Frame::Noise {{ len: {} }}", data.len()),
+        }
+    }
+
+    pub fn format_ot(frame: &OTFrame) -> String {
+        match frame {
+            OTFrame::Shutdown => "Frame::Shutdown".to_string(),
+            OTFrame::Ping { timestamp } => format!("Frame::Ping {{ timestamp: {} }}", timestamp),
+            OTFrame::Pong { timestamp, delay } => format!(
+                "Frame::Pong {{ timestamp: {}, delay: {} }}",
+                timestamp, delay
+            ),
+            OTFrame::OpenStream {
+                sid,
+                prio,
+                promises,
+                guaranteed_bandwidth,
+                window,
+            } => format!(
+                "Frame::OpenStream {{ sid: {}, prio: {}, promises: {:?}, guaranteed_bandwidth: \
+                 {}, window: {} }}",
+                sid, prio, promises, guaranteed_bandwidth, window
+            ),
+            OTFrame::CloseStream { sid } => format!("Frame::CloseStream {{ sid: {} }}", sid),
+            OTFrame::WindowUpdate { sid, bytes } => {
+                format!("Frame::WindowUpdate {{ sid: {}, bytes: {} }}", sid, bytes)
+            },
+            OTFrame::DataHeader { mid, sid, length } => format!(
+                "Frame::DataHeader {{ mid: {}, sid: {}, length: {} }}",
+                mid, sid, length
+            ),
+            OTFrame::Data { mid, data } => {
+                format!("Frame::Data {{ mid: {}, len: {} }}", mid, data.len())
+            },
+        }
+    }
+
+    /// Same as [`format_ot`](FrameInspector::format_ot), a received frame
+    /// looks just like it did when it was sent
+    pub fn format(frame: &ITFrame) -> String { Self::format_ot(&OTFrame::from(frame.clone())) }
+
+    /// Formats a whole TCP byte stream (as send on the wire after the
+    /// handshake) into one line per frame.
+    pub fn format_stream(bytes: &[u8]) -> String {
+        let mut buffer = BytesMut::from(bytes);
+        let mut out = String::new();
+        loop {
+            let before = buffer.len();
+            match ITFrame::read_frame(&mut buffer) {
+                Ok(Some(frame)) => {
+                    let _ = writeln!(
+                        out,
+                        "{} ({} bytes)",
+                        Self::format(&frame),
+                        before - buffer.len()
+                    );
+                },
+                Ok(None) => {
+                    if !buffer.is_empty() {
+                        let _ = writeln!(out, "<incomplete frame: {} bytes>", buffer.len());
+                    }
+                    break;
+                },
+                Err(_) => {
+                    let _ = writeln!(out, "<invalid frame id: {:?}>", buffer.first());
+                    break;
+                },
+            }
+        }
+        out
+    }
+
+    /// Parses a log written by a [`FrameLogger`] and returns counts, size
+    /// distributions and timing histograms per frame type.
+    pub fn summarize_log(path: &Path) -> io::Result<FrameLogSummary> {
+        let bytes = std::fs::read(path)?;
+        Ok(Self::summarize(&bytes))
+    }
+
+    /// See [`summarize_log`](FrameInspector::summarize_log)
+    pub fn summarize(bytes: &[u8]) -> FrameLogSummary {
+        let mut summary = FrameLogSummary::default();
+        let (records, end) = read_log(bytes);
+        for record in &records {
+            summary.add(record.frame.name(), record.size, record.micros);
+        }
+        if let (Some(first), Some(last)) = (records.first(), records.last()) {
+            summary.duration = Duration::from_micros(last.micros.saturating_sub(first.micros));
+        }
+        match end {
+            LogEnd::Trailing(bytes) => summary.trailing_bytes = bytes,
+            LogEnd::Invalid(offset) => summary.invalid_at = Some(offset),
+        }
+        summary
+    }
+}
+
+/// Records frames along with the time they were sent at, to be looked at
+/// later with [`FrameLogger::replay`] or [`FrameInspector::summarize_log`].
+/// [`Network::set_frame_log_dir`] logs the frames of every Tcp channel.
+///
+/// Every record of the log is the time since the logger was created, in
+/// microseconds as a little endian u64, followed by the frame as it is sent
+/// over TCP.
+///
+/// [`Network::set_frame_log_dir`]: crate::Network::set_frame_log_dir
+#[derive(Debug)]
+pub struct FrameLogger<W: Write = BufWriter<File>> {
+    out: W,
+    start: Instant,
+    buffer: BytesMut,
+}
+
+impl FrameLogger {
+    /// Logs into a new file at `path`, replacing the file if there is one
+    pub fn create(path: &Path) -> io::Result<Self> {
+        Ok(Self::new(BufWriter::new(File::create(path)?)))
+    }
+
+    /// Formats the log at `path` with one line per frame, prefixed with the
+    /// time it was logged at
+    pub fn replay(path: &Path) -> io::Result<String> {
+        let bytes = std::fs::read(path)?;
+        Ok(Self::replay_bytes(&bytes))
+    }
+
+    /// See [`replay`](FrameLogger::replay)
+    pub fn replay_bytes(bytes: &[u8]) -> String {
+        let (records, end) = read_log(bytes);
+        let mut out = String::new();
+        for record in records {
+            let _ = writeln!(
+                out,
+                "[{:>10.3}ms] {} ({} bytes)",
+                record.micros as f64 / 1000.0,
+                FrameInspector::format(&record.frame),
+                record.size
+            );
+        }
+        match end {
+            LogEnd::Trailing(0) => {},
+            LogEnd::Trailing(bytes) => {
+                let _ = writeln!(out, "<incomplete frame: {} bytes>", bytes);
+            },
+            LogEnd::Invalid(offset) => {
+                let _ = writeln!(out, "<invalid frame id: {:?}>", bytes.get(offset));
+            },
+        }
+        out
+    }
+}
+
+impl<W: Write> FrameLogger<W> {
+    pub fn new(out: W) -> Self {
+        Self {
+            out,
+            start: Instant::now(),
+            buffer: BytesMut::new(),
+        }
+    }
+
+    /// Logs `frame` as sent now
+    pub fn log(&mut self, frame: OTFrame) -> io::Result<()> {
+        self.log_at(self.start.elapsed(), frame)
+    }
+
+    /// Logs `frame` as sent `elapsed` after the logger was created
+    pub fn log_at(&mut self, elapsed: Duration, frame: OTFrame) -> io::Result<()> {
+        self.buffer.clear();
+        self.buffer.put_u64_le(elapsed.as_micros() as u64);
+        frame.write_bytes(&mut self.buffer);
+        self.out.write_all(&self.buffer)
+    }
+
+    /// Logs a frame already in the byte layout of the wire, as sent now
+    pub fn log_bytes(&mut self, frame: &[u8]) -> io::Result<()> {
+        let micros = self.start.elapsed().as_micros() as u64;
+        self.out.write_all(&micros.to_le_bytes())?;
+        self.out.write_all(frame)
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> { self.out.flush() }
+
+    pub fn into_inner(self) -> W { self.out }
+}
+
+/// Logs the frames a channel sends and receives into one [`FrameLogger`] log
+/// each, see [`Network::set_frame_log_dir`]
+///
+/// [`Network::set_frame_log_dir`]: crate::Network::set_frame_log_dir
+#[derive(Debug)]
+pub(crate) struct ChannelFrameLog {
+    sent: Mutex<FrameLogger>,
+    received: Mutex<FrameLogger>,
+}
+
+impl ChannelFrameLog {
+    /// Creates `<cid>.sent.frames` and `<cid>.recv.frames` in `dir`
+    pub(crate) fn create(dir: &Path, cid: Cid) -> io::Result<Self> {
+        let logger = |direction| {
+            FrameLogger::create(&dir.join(format!("{}.{}.frames", cid, direction))).map(Mutex::new)
+        };
+        Ok(Self {
+            sent: logger("sent")?,
+            received: logger("recv")?,
+        })
+    }
+
+    fn log(logger: &Mutex<FrameLogger>, frame: &[u8]) {
+        if let Err(e) = logger.lock().unwrap().log_bytes(frame) {
+            warn!(?e, "couldn't log frame");
+        }
+    }
+}
+
+impl FrameObserver for ChannelFrameLog {
+    fn frame_sent(&self, frame: &[u8]) { Self::log(&self.sent, frame) }
+
+    fn frame_received(&self, frame: &[u8]) { Self::log(&self.received, frame) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+    use network_protocol::{Pid, Promises, Sid};
+
+    fn ms(ms: u64) -> Duration { Duration::from_millis(ms) }
+
+    fn log() -> Vec<u8> {
+        let mut logger = FrameLogger::new(Vec::new());
+        let frames = [
+            (ms(0), OTFrame::OpenStream {
+                sid: Sid::new(5),
+                prio: 3,
+                promises: Promises::ORDERED,
+                guaranteed_bandwidth: 1000,
+                window: 100_000,
+            }),
+            (ms(1), OTFrame::DataHeader {
+                mid: 42,
+                sid: Sid::new(5),
+                length: 1412,
+            }),
+            (ms(1), OTFrame::Data {
+                mid: 42,
+                data: Bytes::from(&[7u8; 1400][..]),
+            }),
+            (ms(51), OTFrame::Data {
+                mid: 42,
+                data: Bytes::from(&[7u8; 12][..]),
+            }),
+            (ms(2000), OTFrame::Shutdown),
+        ];
+        for (elapsed, frame) in frames {
+            logger.log_at(elapsed, frame).unwrap();
+        }
+        logger.into_inner()
+    }
+
+    #[test]
+    fn format_frames() {
+        let frame = ITFrame::Data {
+            mid: 42,
+            data: Bytes::from(&[0u8; 512][..]),
+        };
+        assert_eq!(
+            FrameInspector::format(&frame),
+            "Frame::Data { mid: 42, len: 512 }"
+        );
+        let frame = InitFrame::Init {
+            pid: Pid::fake(1),
+            secret: 1337,
+        };
+        assert_eq!(
+            FrameInspector::format_init(&frame),
+            format!("Frame::Init {{ pid: {}, secret: <hidden> }}", Pid::fake(1))
+        );
+    }
+
+    #[test]
+    fn format_stream() {
+        let mut bytes = BytesMut::new();
+        OTFrame::Data {
+            mid: 42,
+            data: Bytes::from(&[7u8; 1400][..]),
+        }
+        .write_bytes(&mut bytes);
+        OTFrame::Shutdown.write_bytes(&mut bytes);
+        let out = FrameInspector::format_stream(&bytes);
+        let lines = out.lines().collect::<Vec<_>>();
+        assert_eq!(lines, [
+            "Frame::Data { mid: 42, len: 1400 } (1411 bytes)",
+            "Frame::Shutdown (1 bytes)"
+        ]);
+    }
+
+    #[test]
+    fn replay() {
+        let out = FrameLogger::replay_bytes(&log());
+        let lines = out.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 5);
+        assert_eq!(
+            lines[2],
+            "[     1.000ms] Frame::Data { mid: 42, len: 1400 } (1411 bytes)"
+        );
+        assert_eq!(lines[4], "[  2000.000ms] Frame::Shutdown (1 bytes)");
+    }
+
+    #[test]
+    fn summarize() {
+        let mut bytes = log();
+        bytes.extend_from_slice(&[0u8; TIMESTAMP_LEN]);
+        bytes.extend_from_slice(&[4u8, 1, 2]); // partial OpenStream
+        let summary = FrameInspector::summarize(&bytes);
+        assert_eq!(summary.total_frames(), 5);
+        assert_eq!(summary.duration, ms(2000));
+        assert_eq!(summary.trailing_bytes, TIMESTAMP_LEN + 3);
+        assert_eq!(summary.invalid_at, None);
+        let data = &summary.frames["data"];
+        assert_eq!(data.count, 2);
+        assert_eq!(data.min_size, 23);
+        assert_eq!(data.max_size, 1411);
+        assert_eq!(data.size_distribution, [0, 1, 0, 0, 1, 0]);
+        // the second data frame came 50ms after the first
+        assert_eq!(data.interval_distribution, [0, 0, 0, 1, 0, 0]);
+        assert_eq!(summary.frames["shutdown"].count, 1);
+        assert_eq!(summary.frames["shutdown"].interval_distribution, [0; 6]);
+    }
+
+    #[test]
+    fn summarize_invalid() {
+        let mut bytes = log();
+        let valid = bytes.len();
+        bytes.extend_from_slice(&[0u8; TIMESTAMP_LEN]);
+        bytes.extend_from_slice(&[0x63, 1, 2, 3]);
+        let summary = FrameInspector::summarize(&bytes);
+        assert_eq!(summary.total_frames(), 5);
+        assert_eq!(summary.invalid_at, Some(valid + TIMESTAMP_LEN));
+    }
+
+    #[test]
+    fn log_to_file() {
+        let path = std::env::temp_dir().join(format!("frame_log_{}", std::process::id()));
+        let mut logger = FrameLogger::create(&path).unwrap();
+        logger.log(OTFrame::Shutdown).unwrap();
+        logger.flush().unwrap();
+        let summary = FrameInspector::summarize_log(&path).unwrap();
+        assert_eq!(summary.frames["shutdown"].count, 1);
+        assert!(
+            FrameLogger::replay(&path)
+                .unwrap()
+                .contains("Frame::Shutdown")
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn channel_log() {
+        let dir = std::env::temp_dir().join(format!("channel_frame_log_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let log = ChannelFrameLog::create(&dir, 7).unwrap();
+        let mut bytes = BytesMut::new();
+        OTFrame::CloseStream { sid: Sid::new(5) }.write_bytes(&mut bytes);
+        log.frame_sent(&bytes);
+        log.frame_received(&bytes);
+        log.frame_received(&bytes);
+        drop(log);
+        let sent = FrameInspector::summarize_log(&dir.join("7.sent.frames")).unwrap();
+        assert_eq!(sent.frames["close_stream"].count, 1);
+        let received = FrameInspector::summarize_log(&dir.join("7.recv.frames")).unwrap();
+        assert_eq!(received.frames["close_stream"].count, 2);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -100,10 +100,12 @@
 
 mod api;
 mod channel;
+#[cfg(feature = "debug")] pub mod debug;
 mod message;
 mod metrics;
 mod participant;
 mod scheduler;
+#[cfg(feature = "tls")] pub mod tls;
 mod util;
 
 pub use api::{
@@ -111,4 +113,7 @@ pub use api::{
     ParticipantError, Stream, StreamError, StreamParams,
 };
 pub use message::Message;
-pub use network_protocol::{InitProtocolError, Pid, Promises};
+pub use metrics::StreamMetrics;
+pub use network_protocol::{
+    InitProtocolError, NoiseKeys, NoisePublicKey, ParseKeyError, Pid, Promises, Sid, VersionPolicy,
+};
@@ -7,6 +7,21 @@ use std::io;
 #[cfg(all(feature = "compression", debug_assertions))]
 use tracing::warn;
 
+/// Marker byte prepended to messages on [`Promises::COMPRESSED`] streams,
+/// incompressible data is send raw so it never gets inflated
+#[cfg(feature = "compression")]
+const COMPRESSION_RAW: u8 = 0;
+/// Marker byte of lz compressed messages, followed by the uncompressed length
+/// as little endian u64
+#[cfg(feature = "compression")]
+const COMPRESSION_LZ: u8 = 1;
+#[cfg(feature = "compression")]
+const COMPRESSION_LZ_HEADER: usize = 9;
+/// lz4 can't compress better than this, so a message claiming a bigger
+/// uncompressed length is malicious
+#[cfg(feature = "compression")]
+const COMPRESSION_MAX_RATIO: u64 = 255;
+
 /// Support struct used for optimising sending the same Message to multiple
 /// [`Stream`]
 ///
@@ -18,6 +33,9 @@ pub struct Message {
     pub(crate) data: Bytes,
     #[cfg(feature = "compression")]
     pub(crate) compressed: bool,
+    /// size before compression, only known for locally serialized messages
+    #[cfg(feature = "compression")]
+    pub(crate) uncompressed_len: Option<usize>,
 }
 
 impl Message {
@@ -43,11 +61,22 @@ impl Message {
         #[cfg(feature = "compression")]
         let compressed = stream_params.promises.contains(Promises::COMPRESSED);
         #[cfg(feature = "compression")]
+        let uncompressed_len = serialized_data.len();
+        #[cfg(feature = "compression")]
         let data = if compressed {
-            let mut compressed_data = Vec::with_capacity(serialized_data.len() / 4 + 10);
+            let mut compressed_data = Vec::with_capacity(serialized_data.len() / 4 + 18);
+            compressed_data.push(COMPRESSION_LZ);
+            compressed_data.extend_from_slice(&(serialized_data.len() as u64).to_le_bytes());
             let mut table = lz_fear::raw::U32Table::default();
             lz_fear::raw::compress2(&serialized_data, 0, &mut table, &mut compressed_data).unwrap();
-            compressed_data
+            if compressed_data.len() > serialized_data.len() {
+                let mut raw_data = Vec::with_capacity(serialized_data.len() + 1);
+                raw_data.push(COMPRESSION_RAW);
+                raw_data.extend_from_slice(&serialized_data);
+                raw_data
+            } else {
+                compressed_data
+            }
         } else {
             serialized_data
         };
@@ -60,6 +89,8 @@ impl Message {
             data: Bytes::from(data),
             #[cfg(feature = "compression")]
             compressed,
+            #[cfg(feature = "compression")]
+            uncompressed_len: Some(uncompressed_len),
         }
     }
 
@@ -104,17 +135,43 @@ impl Message {
 
         #[cfg(feature = "compression")]
         let uncompressed_data = if self.compressed {
-            {
-                let mut uncompressed_data = Vec::with_capacity(self.data.len() * 2);
-                if let Err(e) = lz_fear::raw::decompress_raw(
-                    &self.data,
-                    &[0; 0],
-                    &mut uncompressed_data,
-                    usize::MAX,
-                ) {
-                    return Err(StreamError::Compression(e));
-                }
-                Bytes::from(uncompressed_data)
+            match self.data.first() {
+                None => {
+                    return Err(StreamError::Compression(
+                        lz_fear::raw::DecodeError::ExpectedAnotherByte,
+                    ));
+                },
+                Some(&COMPRESSION_RAW) => self.data.slice(1..),
+                Some(_) => {
+                    if self.data.len() < COMPRESSION_LZ_HEADER {
+                        return Err(StreamError::Compression(
+                            lz_fear::raw::DecodeError::ExpectedAnotherByte,
+                        ));
+                    }
+                    let mut len = [0u8; 8];
+                    len.copy_from_slice(&self.data[1..COMPRESSION_LZ_HEADER]);
+                    let len = u64::from_le_bytes(len);
+                    let limit = self.data.len() as u64 * COMPRESSION_MAX_RATIO;
+                    if len > limit {
+                        return Err(StreamError::DecompressedTooLarge { len, limit });
+                    }
+                    let mut uncompressed_data = Vec::with_capacity(len as usize);
+                    if let Err(e) = lz_fear::raw::decompress_raw(
+                        &self.data[COMPRESSION_LZ_HEADER..],
+                        &[0; 0],
+                        &mut uncompressed_data,
+                        // one more byte, to notice if the data doesn't end there
+                        len as usize + 1,
+                    ) {
+                        return Err(StreamError::Compression(e));
+                    }
+                    if uncompressed_data.len() as u64 != len {
+                        return Err(StreamError::Compression(
+                            lz_fear::raw::DecodeError::ExpectedAnotherByte,
+                        ));
+                    }
+                    Bytes::from(uncompressed_data)
+                },
             }
         } else {
             self.data
@@ -201,14 +258,16 @@ mod tests {
     #[cfg(feature = "compression")]
     #[test]
     fn serialize_compress_small() {
+        // lz would need 12 bytes, so it's send raw
         let msg = Message::serialize("abc", stub_stream(true));
         assert_eq!(msg.data.len(), 12);
-        assert_eq!(msg.data[0], 176);
+        assert_eq!(msg.data[0], COMPRESSION_RAW);
         assert_eq!(msg.data[1], 3);
         assert_eq!(msg.data[2..8], [0, 0, 0, 0, 0, 0]);
         assert_eq!(msg.data[9], b'a');
         assert_eq!(msg.data[10], b'b');
         assert_eq!(msg.data[11], b'c');
+        assert_eq!(msg.deserialize::<String>().unwrap(), "abc");
     }
 
     #[cfg(feature = "compression")]
@@ -226,14 +285,16 @@ mod tests {
             "assets/data/plants/flowers/greenrose.ron",
         );
         let msg = Message::serialize(&msg, stub_stream(true));
-        assert_eq!(msg.data.len(), 79);
-        assert_eq!(msg.data[0], 34);
-        assert_eq!(msg.data[1], 5);
-        assert_eq!(msg.data[2], 0);
-        assert_eq!(msg.data[3], 1);
-        assert_eq!(msg.data[20], 20);
-        assert_eq!(msg.data[40], 115);
-        assert_eq!(msg.data[60], 111);
+        assert_eq!(msg.data.len(), 88);
+        assert_eq!(msg.data[0], COMPRESSION_LZ);
+        assert_eq!(msg.data[1..9], 97u64.to_le_bytes());
+        assert_eq!(msg.data[9], 34);
+        assert_eq!(msg.data[10], 5);
+        assert_eq!(msg.data[11], 0);
+        assert_eq!(msg.data[12], 1);
+        assert_eq!(msg.data[29], 20);
+        assert_eq!(msg.data[49], 115);
+        assert_eq!(msg.data[69], 111);
     }
 
     #[cfg(feature = "compression")]
@@ -256,6 +317,55 @@ mod tests {
             }
         }
         let msg = Message::serialize(&msg, stub_stream(true));
-        assert_eq!(msg.data.len(), 1331);
+        assert_eq!(msg.data.len(), 1340);
+        assert_eq!(msg.data[0], COMPRESSION_LZ);
+        assert_eq!(msg.uncompressed_len, Some(10008));
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn compression_roundtrip() {
+        let msg = vec![42u64; 1000];
+        let serialized = Message::serialize(&msg, stub_stream(true));
+        assert_eq!(serialized.data[0], COMPRESSION_LZ);
+        assert_eq!(serialized.deserialize::<Vec<u64>>().unwrap(), msg);
+
+        let mut empty = Message::serialize(&msg, stub_stream(true));
+        empty.data = Bytes::new();
+        assert!(matches!(
+            empty.deserialize::<Vec<u64>>(),
+            Err(StreamError::Compression(_))
+        ));
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn decompression_is_capped() {
+        let msg = vec![42u64; 1000];
+        let serialized = Message::serialize(&msg, stub_stream(true));
+        let limit = serialized.data.len() as u64 * COMPRESSION_MAX_RATIO;
+
+        // claiming more than lz could possibly produce is rejected upfront
+        let mut data = serialized.data.to_vec();
+        data[1..COMPRESSION_LZ_HEADER].copy_from_slice(&(limit + 1).to_le_bytes());
+        let mut bomb = Message::serialize(&msg, stub_stream(true));
+        bomb.data = Bytes::from(data);
+        assert_eq!(
+            bomb.deserialize::<Vec<u64>>(),
+            Err(StreamError::DecompressedTooLarge {
+                len: limit + 1,
+                limit
+            })
+        );
+
+        // data decompressing to more than the declared length is an error
+        let mut data = serialized.data.to_vec();
+        data[1..COMPRESSION_LZ_HEADER].copy_from_slice(&100u64.to_le_bytes());
+        let mut lying = Message::serialize(&msg, stub_stream(true));
+        lying.data = Bytes::from(data);
+        assert!(matches!(
+            lying.deserialize::<Vec<u64>>(),
+            Err(StreamError::Compression(_))
+        ));
     }
 }
@@ -1,12 +1,28 @@
 use crate::api::{ConnectAddr, ListenAddr};
-use network_protocol::{Cid, Pid};
 #[cfg(feature = "metrics")]
-use prometheus::{IntCounter, IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry};
-use std::{error::Error, net::SocketAddr};
+use hashbrown::HashMap;
+use network_protocol::{Cid, Pid, Prio, Promises, Sid, StreamObserver};
+#[cfg(feature = "metrics")]
+use prometheus::{
+    CounterVec, GaugeVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry,
+};
+#[cfg(feature = "metrics")] use std::sync::Mutex;
+use std::{
+    error::Error,
+    net::SocketAddr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+/// closed streams are removed from the stream summary after this time
+#[cfg(feature = "metrics")]
+const CLOSED_STREAM_KEEP: Duration = Duration::from_secs(60);
 
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
 pub(crate) enum ProtocolInfo {
     Tcp(SocketAddr),
+    #[cfg(feature = "tls")]
+    TcpTls(SocketAddr),
     Udp(SocketAddr),
     #[cfg(feature = "quic")]
     Quic(SocketAddr),
@@ -17,6 +33,8 @@ impl From<ListenAddr> for ProtocolInfo {
     fn from(other: ListenAddr) -> ProtocolInfo {
         match other {
             ListenAddr::Tcp(s) => ProtocolInfo::Tcp(s),
+            #[cfg(feature = "tls")]
+            ListenAddr::TcpTls(s, _) => ProtocolInfo::TcpTls(s),
             ListenAddr::Udp(s) => ProtocolInfo::Udp(s),
             #[cfg(feature = "quic")]
             ListenAddr::Quic(s, _) => ProtocolInfo::Quic(s),
@@ -38,18 +56,99 @@ pub struct NetworkMetrics {
     pub participants_channel_ids: IntGaugeVec,
     // upload to remote, averaged, seperated by PARTICIPANT
     pub participants_bandwidth: IntGaugeVec,
+    // time spent with an empty bandwidth limit bucket, seperated by PARTICIPANT
+    pub participants_throttled_seconds_total: CounterVec,
+    // smoothed round trip time of the fastest channel, seperated by PARTICIPANT
+    pub participants_rtt_seconds: GaugeVec,
     // opened Channels, seperated by PARTICIPANT
     pub channels_connected_total: IntCounterVec,
     pub channels_disconnected_total: IntCounterVec,
     // opened streams, seperated by PARTICIPANT
     pub streams_opened_total: IntCounterVec,
     pub streams_closed_total: IntCounterVec,
+    // bytes of messages send on compressed streams, before and after compression
+    pub compression_uncompressed_bytes_total: IntCounter,
+    pub compression_compressed_bytes_total: IntCounter,
+    // traffic of open streams, seperated by PARTICIPANT, STREAM, PRIO, PROMISES
+    // and DIRECTION
+    pub streams_bytes_total: IntCounterVec,
+    pub streams_messages_total: IntCounterVec,
+    // traffic of closed streams, seperated by PRIO, PROMISES and DIRECTION only
+    pub closed_streams_bytes_total: IntCounterVec,
+    pub closed_streams_messages_total: IntCounterVec,
     pub network_info: IntGauge,
+    // traffic of each stream, see `stream_summary`
+    streams: Mutex<HashMap<(Pid, Sid), StreamLine>>,
 }
 
 #[cfg(not(feature = "metrics"))]
 pub struct NetworkMetrics {}
 
+/// Traffic of a single [`Stream`], bytes are counted at message level when
+/// they are handed to or received from the protocol.
+///
+/// [`Stream`]: crate::api::Stream
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct StreamMetrics {
+    /// [`Prio`] the `Stream` was opened with
+    pub prio: Prio,
+    /// [`Promises`] the `Stream` was opened with
+    pub promises: Promises,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    pub messages_sent: u64,
+    pub messages_received: u64,
+    pub last_active: Instant,
+    /// `None` while the `Stream` is still open
+    pub closed: Option<Instant>,
+}
+
+impl StreamMetrics {
+    #[cfg(feature = "metrics")]
+    fn new(prio: Prio, promises: Promises, now: Instant) -> Self {
+        Self {
+            prio,
+            promises,
+            bytes_sent: 0,
+            bytes_received: 0,
+            messages_sent: 0,
+            messages_received: 0,
+            last_active: now,
+            closed: None,
+        }
+    }
+
+    /// Bytes per second sent and received since the `earlier` snapshot of the
+    /// same `Stream`, which was taken `elapsed` ago
+    pub fn throughput_since(&self, earlier: &StreamMetrics, elapsed: Duration) -> (f64, f64) {
+        let secs = elapsed.as_secs_f64();
+        if secs <= 0.0 {
+            return (0.0, 0.0);
+        }
+        (
+            self.bytes_sent.saturating_sub(earlier.bytes_sent) as f64 / secs,
+            self.bytes_received.saturating_sub(earlier.bytes_received) as f64 / secs,
+        )
+    }
+}
+
+/// A [`StreamMetrics`] together with its prometheus counters. They count
+/// towards the aggregate of closed streams once the `Stream` is closed.
+#[cfg(feature = "metrics")]
+struct StreamLine {
+    metrics: StreamMetrics,
+    labels: [String; 4],
+    bytes_sent: IntCounter,
+    bytes_received: IntCounter,
+    messages_sent: IntCounter,
+    messages_received: IntCounter,
+}
+
+#[cfg(feature = "metrics")]
+const SEND: &str = "send";
+#[cfg(feature = "metrics")]
+const RECV: &str = "recv";
+
 #[cfg(feature = "metrics")]
 impl NetworkMetrics {
     pub fn new(local_pid: &Pid) -> Result<Self, Box<dyn Error>> {
@@ -100,6 +199,20 @@ impl NetworkMetrics {
             ),
             &["participant"],
         )?;
+        let participants_throttled_seconds_total = CounterVec::new(
+            Opts::new(
+                "participants_throttled_seconds_total",
+                "time the upload to a Participant was limited by its bandwidth limit",
+            ),
+            &["participant"],
+        )?;
+        let participants_rtt_seconds = GaugeVec::new(
+            Opts::new(
+                "participants_rtt_seconds",
+                "smoothed round trip time of the fastest channel to a Participant",
+            ),
+            &["participant"],
+        )?;
         let channels_connected_total = IntCounterVec::new(
             Opts::new(
                 "channels_connected_total",
@@ -128,6 +241,42 @@ impl NetworkMetrics {
             ),
             &["participant"],
         )?;
+        let compression_uncompressed_bytes_total = IntCounter::with_opts(Opts::new(
+            "compression_uncompressed_bytes_total",
+            "Bytes of messages on compressed streams before compression",
+        ))?;
+        let compression_compressed_bytes_total = IntCounter::with_opts(Opts::new(
+            "compression_compressed_bytes_total",
+            "Bytes of messages on compressed streams after compression",
+        ))?;
+        let streams_bytes_total = IntCounterVec::new(
+            Opts::new(
+                "streams_bytes_total",
+                "Bytes of messages send or received on a stream that is still open",
+            ),
+            &["participant", "stream", "prio", "promises", "direction"],
+        )?;
+        let streams_messages_total = IntCounterVec::new(
+            Opts::new(
+                "streams_messages_total",
+                "Number of messages send or received on a stream that is still open",
+            ),
+            &["participant", "stream", "prio", "promises", "direction"],
+        )?;
+        let closed_streams_bytes_total = IntCounterVec::new(
+            Opts::new(
+                "closed_streams_bytes_total",
+                "Bytes of messages send or received on streams that are closed",
+            ),
+            &["prio", "promises", "direction"],
+        )?;
+        let closed_streams_messages_total = IntCounterVec::new(
+            Opts::new(
+                "closed_streams_messages_total",
+                "Number of messages send or received on streams that are closed",
+            ),
+            &["prio", "promises", "direction"],
+        )?;
         let opts = Opts::new("network_info", "Static Network information")
             .const_label(
                 "version",
@@ -150,11 +299,20 @@ impl NetworkMetrics {
             participants_disconnected_total,
             participants_channel_ids,
             participants_bandwidth,
+            participants_throttled_seconds_total,
+            participants_rtt_seconds,
             channels_connected_total,
             channels_disconnected_total,
             streams_opened_total,
             streams_closed_total,
+            compression_uncompressed_bytes_total,
+            compression_compressed_bytes_total,
+            streams_bytes_total,
+            streams_messages_total,
+            closed_streams_bytes_total,
+            closed_streams_messages_total,
             network_info,
+            streams: Mutex::new(HashMap::new()),
         })
     }
 
@@ -167,10 +325,18 @@ impl NetworkMetrics {
         registry.register(Box::new(self.participants_disconnected_total.clone()))?;
         registry.register(Box::new(self.participants_channel_ids.clone()))?;
         registry.register(Box::new(self.participants_bandwidth.clone()))?;
+        registry.register(Box::new(self.participants_throttled_seconds_total.clone()))?;
+        registry.register(Box::new(self.participants_rtt_seconds.clone()))?;
         registry.register(Box::new(self.channels_connected_total.clone()))?;
         registry.register(Box::new(self.channels_disconnected_total.clone()))?;
         registry.register(Box::new(self.streams_opened_total.clone()))?;
         registry.register(Box::new(self.streams_closed_total.clone()))?;
+        registry.register(Box::new(self.compression_uncompressed_bytes_total.clone()))?;
+        registry.register(Box::new(self.compression_compressed_bytes_total.clone()))?;
+        registry.register(Box::new(self.streams_bytes_total.clone()))?;
+        registry.register(Box::new(self.streams_messages_total.clone()))?;
+        registry.register(Box::new(self.closed_streams_bytes_total.clone()))?;
+        registry.register(Box::new(self.closed_streams_messages_total.clone()))?;
         registry.register(Box::new(self.network_info.clone()))?;
         Ok(())
     }
@@ -201,6 +367,27 @@ impl NetworkMetrics {
             .set(bandwidth as i64);
     }
 
+    pub(crate) fn participant_throttled(&self, remote_p: &str, duration: Duration) {
+        self.participants_throttled_seconds_total
+            .with_label_values(&[remote_p])
+            .inc_by(duration.as_secs_f64());
+    }
+
+    /// `None` removes the gauge, until the round trip time is measured again
+    pub(crate) fn participant_rtt(&self, remote_p: &str, rtt: Option<Duration>) {
+        match rtt {
+            Some(rtt) => self
+                .participants_rtt_seconds
+                .with_label_values(&[remote_p])
+                .set(rtt.as_secs_f64()),
+            None => {
+                let _ = self
+                    .participants_rtt_seconds
+                    .remove_label_values(&[remote_p]);
+            },
+        }
+    }
+
     pub(crate) fn streams_opened(&self, remote_p: &str) {
         self.streams_opened_total
             .with_label_values(&[remote_p])
@@ -213,6 +400,140 @@ impl NetworkMetrics {
             .inc();
     }
 
+    pub(crate) fn stream_opened(&self, remote: Pid, sid: Sid, prio: Prio, promises: Promises) {
+        let now = Instant::now();
+        let labels = [
+            remote.to_string(),
+            sid.to_string(),
+            prio.to_string(),
+            format!("{:?}", promises),
+        ];
+        let counter = |vec: &IntCounterVec, direction: &str| {
+            let [p, s, prio, promises] = &labels;
+            vec.with_label_values(&[p, s, prio, promises, direction])
+        };
+        let line = StreamLine {
+            metrics: StreamMetrics::new(prio, promises, now),
+            bytes_sent: counter(&self.streams_bytes_total, SEND),
+            bytes_received: counter(&self.streams_bytes_total, RECV),
+            messages_sent: counter(&self.streams_messages_total, SEND),
+            messages_received: counter(&self.streams_messages_total, RECV),
+            labels,
+        };
+        let mut streams = self.streams.lock().unwrap();
+        Self::prune_streams(&mut streams, now);
+        if let Some(mut old) = streams.insert((remote, sid), line) {
+            if old.metrics.closed.is_none() {
+                self.aggregate_closed(&mut old);
+            }
+        }
+    }
+
+    /// Moves the traffic of a closed stream from its own counters to the
+    /// aggregate of all closed streams, so the number of label values doesn't
+    /// grow with every stream ever opened
+    pub(crate) fn stream_closed(&self, remote: Pid, sid: Sid) {
+        let now = Instant::now();
+        let mut streams = self.streams.lock().unwrap();
+        if let Some(line) = streams.get_mut(&(remote, sid)) {
+            if line.metrics.closed.is_none() {
+                line.metrics.closed = Some(now);
+                self.aggregate_closed(line);
+            }
+        }
+        Self::prune_streams(&mut streams, now);
+    }
+
+    pub(crate) fn stream_sent(&self, remote: Pid, sid: Sid, bytes: u64) {
+        if let Some(line) = self.streams.lock().unwrap().get_mut(&(remote, sid)) {
+            line.metrics.bytes_sent += bytes;
+            line.metrics.messages_sent += 1;
+            line.metrics.last_active = Instant::now();
+            line.bytes_sent.inc_by(bytes);
+            line.messages_sent.inc();
+        }
+    }
+
+    pub(crate) fn stream_received(&self, remote: Pid, sid: Sid, bytes: u64) {
+        if let Some(line) = self.streams.lock().unwrap().get_mut(&(remote, sid)) {
+            line.metrics.bytes_received += bytes;
+            line.metrics.messages_received += 1;
+            line.metrics.last_active = Instant::now();
+            line.bytes_received.inc_by(bytes);
+            line.messages_received.inc();
+        }
+    }
+
+    /// Traffic of all `Streams` of the `Participant` with `remote` pid, sorted
+    /// by [`Sid`]. Streams closed for more than 60 seconds are not included.
+    pub(crate) fn stream_summary(&self, remote: Pid) -> Vec<(Sid, StreamMetrics)> {
+        let mut streams = self.streams.lock().unwrap();
+        Self::prune_streams(&mut streams, Instant::now());
+        let mut summary = streams
+            .iter()
+            .filter(|((pid, _), _)| *pid == remote)
+            .map(|((_, sid), line)| (*sid, line.metrics))
+            .collect::<Vec<_>>();
+        summary.sort_unstable_by_key(|(sid, _)| *sid);
+        summary
+    }
+
+    /// Traffic of a single `Stream`, without going through all streams like
+    /// [`stream_summary`](NetworkMetrics::stream_summary)
+    pub(crate) fn stream_metrics(&self, remote: Pid, sid: Sid) -> Option<StreamMetrics> {
+        self.streams
+            .lock()
+            .unwrap()
+            .get(&(remote, sid))
+            .map(|line| line.metrics)
+    }
+
+    fn prune_streams(streams: &mut HashMap<(Pid, Sid), StreamLine>, now: Instant) {
+        streams.retain(|_, line| {
+            line.metrics.closed.map_or(true, |closed| {
+                now.duration_since(closed) < CLOSED_STREAM_KEEP
+            })
+        });
+    }
+
+    fn aggregate_closed(&self, line: &mut StreamLine) {
+        let [p, s, prio, promises] = &line.labels;
+        for direction in [SEND, RECV] {
+            let _ = self
+                .streams_bytes_total
+                .remove_label_values(&[p, s, prio, promises, direction]);
+            let _ = self
+                .streams_messages_total
+                .remove_label_values(&[p, s, prio, promises, direction]);
+        }
+        let closed = |vec: &IntCounterVec, direction: &str, open: &IntCounter| {
+            let counter = vec.with_label_values(&[prio, promises, direction]);
+            counter.inc_by(open.get());
+            counter
+        };
+        // messages still drained after the close count towards the aggregate
+        line.bytes_sent = closed(&self.closed_streams_bytes_total, SEND, &line.bytes_sent);
+        line.bytes_received = closed(&self.closed_streams_bytes_total, RECV, &line.bytes_received);
+        line.messages_sent = closed(
+            &self.closed_streams_messages_total,
+            SEND,
+            &line.messages_sent,
+        );
+        line.messages_received = closed(
+            &self.closed_streams_messages_total,
+            RECV,
+            &line.messages_received,
+        );
+    }
+
+    #[cfg(feature = "compression")]
+    pub(crate) fn compressed_message(&self, uncompressed: usize, compressed: usize) {
+        self.compression_uncompressed_bytes_total
+            .inc_by(uncompressed as u64);
+        self.compression_compressed_bytes_total
+            .inc_by(compressed as u64);
+    }
+
     pub(crate) fn listen_request(&self, protocol: &ListenAddr) {
         self.listen_requests_total
             .with_label_values(&[protocollisten_name(protocol)])
@@ -238,6 +559,12 @@ impl NetworkMetrics {
             .channels_disconnected_total
             .remove_label_values(&[remote_p]);
         let _ = self.participants_bandwidth.remove_label_values(&[remote_p]);
+        let _ = self
+            .participants_throttled_seconds_total
+            .remove_label_values(&[remote_p]);
+        let _ = self
+            .participants_rtt_seconds
+            .remove_label_values(&[remote_p]);
         let _ = self.streams_opened_total.remove_label_values(&[remote_p]);
         let _ = self.streams_closed_total.remove_label_values(&[remote_p]);
     }
@@ -247,10 +574,14 @@ impl NetworkMetrics {
 fn protocolconnect_name(protocol: &ConnectAddr) -> &str {
     match protocol {
         ConnectAddr::Tcp(_) => "tcp",
+        #[cfg(feature = "tls")]
+        ConnectAddr::TcpTls(_, _, _) => "tcp_tls",
         ConnectAddr::Udp(_) => "udp",
         ConnectAddr::Mpsc(_) => "mpsc",
         #[cfg(feature = "quic")]
         ConnectAddr::Quic(_, _, _) => "quic",
+        #[cfg(feature = "websocket")]
+        ConnectAddr::Ws(_) => "ws",
     }
 }
 
@@ -258,6 +589,8 @@ fn protocolconnect_name(protocol: &ConnectAddr) -> &str {
 fn protocollisten_name(protocol: &ListenAddr) -> &str {
     match protocol {
         ListenAddr::Tcp(_) => "tcp",
+        #[cfg(feature = "tls")]
+        ListenAddr::TcpTls(_, _) => "tcp_tls",
         ListenAddr::Udp(_) => "udp",
         ListenAddr::Mpsc(_) => "mpsc",
         #[cfg(feature = "quic")]
@@ -275,10 +608,29 @@ impl NetworkMetrics {
 
     pub(crate) fn participant_bandwidth(&self, _remote_p: &str, _bandwidth: f32) {}
 
+    pub(crate) fn participant_throttled(&self, _remote_p: &str, _duration: Duration) {}
+
+    pub(crate) fn participant_rtt(&self, _remote_p: &str, _rtt: Option<Duration>) {}
+
     pub(crate) fn streams_opened(&self, _remote_p: &str) {}
 
     pub(crate) fn streams_closed(&self, _remote_p: &str) {}
 
+    pub(crate) fn stream_opened(&self, _remote: Pid, _sid: Sid, _prio: Prio, _promises: Promises) {}
+
+    pub(crate) fn stream_closed(&self, _remote: Pid, _sid: Sid) {}
+
+    pub(crate) fn stream_sent(&self, _remote: Pid, _sid: Sid, _bytes: u64) {}
+
+    pub(crate) fn stream_received(&self, _remote: Pid, _sid: Sid, _bytes: u64) {}
+
+    pub(crate) fn stream_summary(&self, _remote: Pid) -> Vec<(Sid, StreamMetrics)> { Vec::new() }
+
+    pub(crate) fn stream_metrics(&self, _remote: Pid, _sid: Sid) -> Option<StreamMetrics> { None }
+
+    #[cfg(feature = "compression")]
+    pub(crate) fn compressed_message(&self, _uncompressed: usize, _compressed: usize) {}
+
     pub(crate) fn listen_request(&self, _protocol: &ListenAddr) {}
 
     pub(crate) fn connect_request(&self, _protocol: &ConnectAddr) {}
@@ -292,3 +644,27 @@ impl std::fmt::Debug for NetworkMetrics {
         write!(f, "NetworkMetrics()")
     }
 }
+
+/// Counts the messages the protocols of a participant's channels handle
+/// towards the [`StreamMetrics`] of its streams
+#[derive(Debug)]
+pub(crate) struct ParticipantStreams {
+    metrics: Arc<NetworkMetrics>,
+    remote: Pid,
+}
+
+impl ParticipantStreams {
+    pub(crate) fn new(metrics: Arc<NetworkMetrics>, remote: Pid) -> Self {
+        Self { metrics, remote }
+    }
+}
+
+impl StreamObserver for ParticipantStreams {
+    fn message_sent(&self, sid: Sid, bytes: u64) {
+        self.metrics.stream_sent(self.remote, sid, bytes);
+    }
+
+    fn message_received(&self, sid: Sid, bytes: u64) {
+        self.metrics.stream_received(self.remote, sid, bytes);
+    }
+}
@@ -1,7 +1,7 @@
 use crate::{
     api::{ParticipantError, Stream},
     channel::{Protocols, RecvProtocols, SendProtocols},
-    metrics::NetworkMetrics,
+    metrics::{NetworkMetrics, ParticipantStreams},
     util::DeferredTracer,
 };
 use bytes::Bytes;
@@ -9,7 +9,7 @@ use futures_util::{FutureExt, StreamExt};
 use hashbrown::HashMap;
 use network_protocol::{
     Bandwidth, Cid, Pid, Prio, Promises, ProtocolEvent, RecvProtocol, SendProtocol, Sid,
-    _internal::SortedVec,
+    _internal::SortedVec, DEFAULT_STREAM_WINDOW,
 };
 use std::{
     sync::{
@@ -30,6 +30,11 @@ pub(crate) type A2bStreamOpen = (Prio, Promises, Bandwidth, oneshot::Sender<Stre
 pub(crate) type S2bCreateChannel = (Cid, Sid, Protocols, oneshot::Sender<()>);
 pub(crate) type S2bShutdownBparticipant = (Duration, oneshot::Sender<Result<(), ParticipantError>>);
 pub(crate) type B2sPrioStatistic = (Pid, u64, u64);
+/// a stream the remote opened, the send_mgr needs to know about it too
+type B2bRecvOpen = (Cid, Sid, Prio, Promises, Bandwidth, u64);
+/// a channel the send_mgr should remove, and whether it failed, so queued
+/// messages can't be drained anymore
+type B2bCloseSendProtocol = (Cid, bool);
 
 #[derive(Debug)]
 #[allow(dead_code)]
@@ -40,10 +45,10 @@ struct ChannelInfo {
 
 #[derive(Debug)]
 struct StreamInfo {
-    #[allow(dead_code)]
     prio: Prio,
-    #[allow(dead_code)]
     promises: Promises,
+    guaranteed_bandwidth: Bandwidth,
+    window: u64,
     send_closed: Arc<AtomicBool>,
     b2a_msg_recv_s: Mutex<async_channel::Sender<Bytes>>,
 }
@@ -52,14 +57,83 @@ struct StreamInfo {
 struct ControlChannels {
     a2b_open_stream_r: mpsc::UnboundedReceiver<A2bStreamOpen>,
     b2a_stream_opened_s: mpsc::UnboundedSender<Stream>,
+    b2a_stream_closed_s: mpsc::UnboundedSender<Sid>,
     s2b_create_channel_r: mpsc::UnboundedReceiver<S2bCreateChannel>,
     b2a_bandwidth_stats_s: watch::Sender<f32>,
+    b2a_rtt_s: watch::Sender<Option<Duration>>,
+    a2b_bandwidth_limit_r: watch::Receiver<Option<Bandwidth>>,
     s2b_shutdown_bparticipant_r: oneshot::Receiver<S2bShutdownBparticipant>, /* own */
 }
 
+/// Token bucket limiting the upload to a participant. It's refilled with
+/// `limit` bytes per second and holds at most [`Self::MAX_BURST`] worth of
+/// bytes. As frames can't be split, flushes may overdraw it, the debt is paid
+/// off by the following ticks.
+#[derive(Debug, Default)]
+struct TokenBucket {
+    tokens: f64,
+}
+
+impl TokenBucket {
+    const MAX_BURST: Duration = Duration::from_millis(100);
+
+    fn refill(&mut self, limit: Bandwidth, dt: Duration) {
+        let max = limit as f64 * Self::MAX_BURST.as_secs_f64();
+        self.tokens = (self.tokens + limit as f64 * dt.as_secs_f64()).min(max);
+    }
+
+    /// bandwidth to pass to `flush` so that `used` + flushed bytes stay within
+    /// the bucket
+    fn bandwidth(&self, used: u64, dt: Duration) -> Bandwidth {
+        let available = (self.tokens - used as f64).max(0.0);
+        if dt.is_zero() {
+            0
+        } else {
+            (available / dt.as_secs_f64()) as Bandwidth
+        }
+    }
+
+    /// returns true if the bucket is empty afterwards, i.e. we are throttled
+    fn consume(&mut self, used: u64) -> bool {
+        self.tokens -= used as f64;
+        self.tokens <= 0.0
+    }
+}
+
+/// Everything the send_mgr listens to or reports back to
+struct SendMgrChannels {
+    a2b_open_stream_r: mpsc::UnboundedReceiver<A2bStreamOpen>,
+    a2b_close_stream_r: mpsc::UnboundedReceiver<Sid>,
+    a2b_msg_r: crossbeam_channel::Receiver<(Sid, Bytes)>,
+    a2b_consumed_r: crossbeam_channel::Receiver<(Sid, u64)>,
+    b2b_add_protocol_r: mpsc::UnboundedReceiver<(Cid, SendProtocols)>,
+    b2b_close_send_protocol_r: async_channel::Receiver<B2bCloseSendProtocol>,
+    b2b_notify_send_of_recv_open_r: crossbeam_channel::Receiver<B2bRecvOpen>,
+    b2b_notify_send_of_recv_close_r: crossbeam_channel::Receiver<(Cid, Sid)>,
+    b2b_notify_send_of_recv_window_r: crossbeam_channel::Receiver<(Sid, u64)>,
+    _b2s_prio_statistic_s: mpsc::UnboundedSender<B2sPrioStatistic>,
+    b2a_stream_closed_s: mpsc::UnboundedSender<Sid>,
+    b2a_bandwidth_stats_s: watch::Sender<f32>,
+    b2a_rtt_s: watch::Sender<Option<Duration>>,
+    a2b_bandwidth_limit_r: watch::Receiver<Option<Bandwidth>>,
+}
+
+/// Everything the recv_mgr listens to or reports back to
+struct RecvMgrChannels {
+    b2a_stream_opened_s: mpsc::UnboundedSender<Stream>,
+    b2a_stream_closed_s: mpsc::UnboundedSender<Sid>,
+    b2b_add_protocol_r: mpsc::UnboundedReceiver<(Cid, RecvProtocols)>,
+    b2b_force_close_recv_protocol_r: async_channel::Receiver<Cid>,
+    b2b_close_send_protocol_s: async_channel::Sender<B2bCloseSendProtocol>,
+    b2b_notify_send_of_recv_open_s: crossbeam_channel::Sender<B2bRecvOpen>,
+    b2b_notify_send_of_recv_close_s: crossbeam_channel::Sender<(Cid, Sid)>,
+    b2b_notify_send_of_recv_window_s: crossbeam_channel::Sender<(Sid, u64)>,
+}
+
 #[derive(Debug)]
 struct OpenStreamInfo {
     a2b_msg_s: crossbeam_channel::Sender<(Sid, Bytes)>,
+    a2b_consumed_s: crossbeam_channel::Sender<(Sid, u64)>,
     a2b_close_stream_s: mpsc::UnboundedSender<Sid>,
 }
 
@@ -75,6 +149,10 @@ pub struct BParticipant {
     shutdown_barrier: AtomicI32,
     metrics: Arc<NetworkMetrics>,
     open_stream_channels: Arc<Mutex<Option<OpenStreamInfo>>>,
+    /// how long queued frames are still sent once a channel is shut down
+    shutdown_grace: Duration,
+    /// received messages a stream buffers before we stop reading our channels
+    stream_capacity: usize,
 }
 
 impl BParticipant {
@@ -82,33 +160,48 @@ impl BParticipant {
     const BARR_CHANNEL: i32 = 1;
     const BARR_RECV: i32 = 4;
     const BARR_SEND: i32 = 2;
+    /// queued frames are drained on shutdown regardless of the bandwidth limit
+    const SHUTDOWN_DRAIN_BANDWIDTH: Bandwidth = 1_000_000_000;
     const TICK_TIME: Duration = Duration::from_millis(Self::TICK_TIME_MS);
     const TICK_TIME_MS: u64 = 5;
 
+    #[allow(clippy::type_complexity)]
     pub(crate) fn new(
         local_pid: Pid,
         remote_pid: Pid,
         offset_sid: Sid,
+        stream_capacity: usize,
+        shutdown_grace: Duration,
         metrics: Arc<NetworkMetrics>,
     ) -> (
         Self,
         mpsc::UnboundedSender<A2bStreamOpen>,
         mpsc::UnboundedReceiver<Stream>,
+        mpsc::UnboundedReceiver<Sid>,
         mpsc::UnboundedSender<S2bCreateChannel>,
         oneshot::Sender<S2bShutdownBparticipant>,
         watch::Receiver<f32>,
+        watch::Receiver<Option<Duration>>,
+        watch::Sender<Option<Bandwidth>>,
     ) {
         let (a2b_open_stream_s, a2b_open_stream_r) = mpsc::unbounded_channel::<A2bStreamOpen>();
         let (b2a_stream_opened_s, b2a_stream_opened_r) = mpsc::unbounded_channel::<Stream>();
+        let (b2a_stream_closed_s, b2a_stream_closed_r) = mpsc::unbounded_channel::<Sid>();
         let (s2b_shutdown_bparticipant_s, s2b_shutdown_bparticipant_r) = oneshot::channel();
         let (s2b_create_channel_s, s2b_create_channel_r) = mpsc::unbounded_channel();
         let (b2a_bandwidth_stats_s, b2a_bandwidth_stats_r) = watch::channel::<f32>(0.0);
+        let (b2a_rtt_s, b2a_rtt_r) = watch::channel::<Option<Duration>>(None);
+        let (a2b_bandwidth_limit_s, a2b_bandwidth_limit_r) =
+            watch::channel::<Option<Bandwidth>>(None);
 
         let run_channels = Some(ControlChannels {
             a2b_open_stream_r,
             b2a_stream_opened_s,
+            b2a_stream_closed_s,
             s2b_create_channel_r,
             b2a_bandwidth_stats_s,
+            b2a_rtt_s,
+            a2b_bandwidth_limit_r,
             s2b_shutdown_bparticipant_r,
         });
 
@@ -126,12 +219,17 @@ impl BParticipant {
                 run_channels,
                 metrics,
                 open_stream_channels: Arc::new(Mutex::new(None)),
+                shutdown_grace,
+                stream_capacity,
             },
             a2b_open_stream_s,
             b2a_stream_opened_r,
+            b2a_stream_closed_r,
             s2b_create_channel_s,
             s2b_shutdown_bparticipant_s,
             b2a_bandwidth_stats_r,
+            b2a_rtt_r,
+            a2b_bandwidth_limit_s,
         )
     }
 
@@ -141,44 +239,55 @@ impl BParticipant {
         let (b2b_add_recv_protocol_s, b2b_add_recv_protocol_r) =
             mpsc::unbounded_channel::<(Cid, RecvProtocols)>();
         let (b2b_close_send_protocol_s, b2b_close_send_protocol_r) =
-            async_channel::unbounded::<Cid>();
+            async_channel::unbounded::<B2bCloseSendProtocol>();
         let (b2b_force_close_recv_protocol_s, b2b_force_close_recv_protocol_r) =
             async_channel::unbounded::<Cid>();
         let (b2b_notify_send_of_recv_open_s, b2b_notify_send_of_recv_open_r) =
-            crossbeam_channel::unbounded::<(Cid, Sid, Prio, Promises, u64)>();
+            crossbeam_channel::unbounded::<B2bRecvOpen>();
         let (b2b_notify_send_of_recv_close_s, b2b_notify_send_of_recv_close_r) =
             crossbeam_channel::unbounded::<(Cid, Sid)>();
+        let (b2b_notify_send_of_recv_window_s, b2b_notify_send_of_recv_window_r) =
+            crossbeam_channel::unbounded::<(Sid, u64)>();
 
         let (a2b_close_stream_s, a2b_close_stream_r) = mpsc::unbounded_channel::<Sid>();
         let (a2b_msg_s, a2b_msg_r) = crossbeam_channel::unbounded::<(Sid, Bytes)>();
+        let (a2b_consumed_s, a2b_consumed_r) = crossbeam_channel::unbounded::<(Sid, u64)>();
 
         *self.open_stream_channels.lock().await = Some(OpenStreamInfo {
             a2b_msg_s,
+            a2b_consumed_s,
             a2b_close_stream_s,
         });
         let run_channels = self.run_channels.take().unwrap();
         trace!("start all managers");
         tokio::join!(
-            self.send_mgr(
-                run_channels.a2b_open_stream_r,
+            self.send_mgr(SendMgrChannels {
+                a2b_open_stream_r: run_channels.a2b_open_stream_r,
                 a2b_close_stream_r,
                 a2b_msg_r,
-                b2b_add_send_protocol_r,
+                a2b_consumed_r,
+                b2b_add_protocol_r: b2b_add_send_protocol_r,
                 b2b_close_send_protocol_r,
                 b2b_notify_send_of_recv_open_r,
                 b2b_notify_send_of_recv_close_r,
-                b2s_prio_statistic_s,
-                run_channels.b2a_bandwidth_stats_s,
-            )
+                b2b_notify_send_of_recv_window_r,
+                _b2s_prio_statistic_s: b2s_prio_statistic_s,
+                b2a_stream_closed_s: run_channels.b2a_stream_closed_s.clone(),
+                b2a_bandwidth_stats_s: run_channels.b2a_bandwidth_stats_s,
+                b2a_rtt_s: run_channels.b2a_rtt_s,
+                a2b_bandwidth_limit_r: run_channels.a2b_bandwidth_limit_r,
+            })
             .instrument(tracing::info_span!("send")),
-            self.recv_mgr(
-                run_channels.b2a_stream_opened_s,
-                b2b_add_recv_protocol_r,
+            self.recv_mgr(RecvMgrChannels {
+                b2a_stream_opened_s: run_channels.b2a_stream_opened_s,
+                b2a_stream_closed_s: run_channels.b2a_stream_closed_s,
+                b2b_add_protocol_r: b2b_add_recv_protocol_r,
                 b2b_force_close_recv_protocol_r,
-                b2b_close_send_protocol_s.clone(),
+                b2b_close_send_protocol_s: b2b_close_send_protocol_s.clone(),
                 b2b_notify_send_of_recv_open_s,
                 b2b_notify_send_of_recv_close_s,
-            )
+                b2b_notify_send_of_recv_window_s,
+            })
             .instrument(tracing::info_span!("recv")),
             self.create_channel_mgr(
                 run_channels.s2b_create_channel_r,
@@ -193,14 +302,33 @@ impl BParticipant {
         );
     }
 
-    fn best_protocol(all: &SortedVec<Cid, SendProtocols>, promises: Promises) -> Option<Cid> {
+    /// Picks the channel a new stream is sent over. Among the channels of the
+    /// best kind, the one carrying the fewest streams is used, so streams are
+    /// spread over all channels to a participant.
+    fn best_protocol(
+        all: &SortedVec<Cid, SendProtocols>,
+        streams: &SortedVec<Sid, Cid>,
+        promises: Promises,
+    ) -> Option<Cid> {
+        // websocket channels run the tcp protocol
+        #[cfg(feature = "websocket")]
+        let is_tcp = |p: &SendProtocols| matches!(p, SendProtocols::Tcp(_) | SendProtocols::Ws(_));
+        #[cfg(not(feature = "websocket"))]
+        let is_tcp = |p: &SendProtocols| matches!(p, SendProtocols::Tcp(_));
+        let least_used = |kind: &dyn Fn(&SendProtocols) -> bool| {
+            all.data
+                .iter()
+                .filter(|(_, p)| kind(p))
+                .map(|(c, _)| *c)
+                .min_by_key(|c| streams.data.iter().filter(|(_, sc)| sc == c).count())
+        };
         // check for mpsc
-        all.data.iter().find(|(_, p)| matches!(p, SendProtocols::Mpsc(_))).map(|(c, _)| *c).or_else(
+        least_used(&|p| matches!(p, SendProtocols::Mpsc(_))).or_else(
             || if network_protocol::TcpSendProtocol::<crate::channel::TcpDrain>::supported_promises()
                 .contains(promises)
             {
                 // check for tcp
-                all.data.iter().find(|(_, p)| matches!(p, SendProtocols::Tcp(_))).map(|(c, _)| *c)
+                least_used(&is_tcp)
             } else {
                 None
             }
@@ -209,7 +337,7 @@ impl BParticipant {
             || if network_protocol::QuicSendProtocol::<crate::channel::QuicDrain>::supported_promises()
                 .contains(promises)
             {
-                all.data.iter().find(|(_, p)| matches!(p, SendProtocols::Quic(_))).map(|(c, _)| *c)
+                least_used(&|p| matches!(p, SendProtocols::Quic(_)))
             } else {
                 None
             }
@@ -222,30 +350,30 @@ impl BParticipant {
     }
 
     //TODO: local stream_cid: HashMap<Sid, Cid> to know the respective protocol
-    async fn send_mgr(
-        &self,
-        mut a2b_open_stream_r: mpsc::UnboundedReceiver<A2bStreamOpen>,
-        mut a2b_close_stream_r: mpsc::UnboundedReceiver<Sid>,
-        a2b_msg_r: crossbeam_channel::Receiver<(Sid, Bytes)>,
-        mut b2b_add_protocol_r: mpsc::UnboundedReceiver<(Cid, SendProtocols)>,
-        b2b_close_send_protocol_r: async_channel::Receiver<Cid>,
-        b2b_notify_send_of_recv_open_r: crossbeam_channel::Receiver<(
-            Cid,
-            Sid,
-            Prio,
-            Promises,
-            Bandwidth,
-        )>,
-        b2b_notify_send_of_recv_close_r: crossbeam_channel::Receiver<(Cid, Sid)>,
-        _b2s_prio_statistic_s: mpsc::UnboundedSender<B2sPrioStatistic>,
-        b2a_bandwidth_stats_s: watch::Sender<f32>,
-    ) {
+    async fn send_mgr(&self, channels: SendMgrChannels) {
+        let SendMgrChannels {
+            mut a2b_open_stream_r,
+            mut a2b_close_stream_r,
+            a2b_msg_r,
+            a2b_consumed_r,
+            mut b2b_add_protocol_r,
+            b2b_close_send_protocol_r,
+            b2b_notify_send_of_recv_open_r,
+            b2b_notify_send_of_recv_close_r,
+            b2b_notify_send_of_recv_window_r,
+            _b2s_prio_statistic_s,
+            b2a_stream_closed_s,
+            b2a_bandwidth_stats_s,
+            b2a_rtt_s,
+            a2b_bandwidth_limit_r,
+        } = channels;
         let mut sorted_send_protocols = SortedVec::<Cid, SendProtocols>::default();
         let mut sorted_stream_protocols = SortedVec::<Sid, Cid>::default();
         let mut interval = tokio::time::interval(Self::TICK_TIME);
         let mut last_instant = Instant::now();
         let mut stream_ids = self.offset_sid;
         let mut part_bandwidth = 0.0f32;
+        let mut bucket = TokenBucket::default();
         trace!("workaround, actively wait for first protocol");
         if let Some((c, p)) = b2b_add_protocol_r.recv().await {
             sorted_send_protocols.insert(c, p)
@@ -279,11 +407,22 @@ impl BParticipant {
                 if let Some((prio, promises, guaranteed_bandwidth, return_s)) = open {
                     let sid = stream_ids;
                     stream_ids += Sid::from(1);
-                    cid = Self::best_protocol(&sorted_send_protocols, promises).unwrap();
+                    cid = Self::best_protocol(
+                        &sorted_send_protocols,
+                        &sorted_stream_protocols,
+                        promises,
+                    )
+                    .unwrap();
                     trace!(?sid, ?cid, "open stream");
 
                     let stream = self
-                        .create_stream(sid, prio, promises, guaranteed_bandwidth)
+                        .create_stream(
+                            sid,
+                            prio,
+                            promises,
+                            guaranteed_bandwidth,
+                            DEFAULT_STREAM_WINDOW,
+                        )
                         .await;
 
                     let event = ProtocolEvent::OpenStream {
@@ -291,6 +430,7 @@ impl BParticipant {
                         prio,
                         promises,
                         guaranteed_bandwidth,
+                        window: DEFAULT_STREAM_WINDOW,
                     };
 
                     sorted_stream_protocols.insert(sid, cid);
@@ -303,7 +443,7 @@ impl BParticipant {
                 }
 
                 // process recv content first
-                for (cid, sid, prio, promises, guaranteed_bandwidth) in
+                for (cid, sid, prio, promises, guaranteed_bandwidth, window) in
                     b2b_notify_send_of_recv_open_r.try_iter()
                 {
                     match sorted_send_protocols.get_mut(&cid) {
@@ -314,15 +454,28 @@ impl BParticipant {
                                 prio,
                                 promises,
                                 guaranteed_bandwidth,
+                                window,
                             });
                         },
                         None => warn!(?cid, "couldn't notify create protocol, doesn't exist"),
                     };
                 }
+                for (sid, bytes) in b2b_notify_send_of_recv_window_r.try_iter() {
+                    if let Some(p) = sorted_stream_protocols
+                        .get(&sid)
+                        .and_then(|c| sorted_send_protocols.get_mut(c))
+                    {
+                        p.notify_from_recv(ProtocolEvent::WindowUpdate { sid, bytes });
+                    }
+                }
 
                 // get all messages and assign it to a channel
                 for (sid, buffer) in a2b_msg_r.try_iter() {
-                    cid = *sorted_stream_protocols.get(&sid).unwrap();
+                    cid = match sorted_stream_protocols.get(&sid) {
+                        Some(&c) => c,
+                        // closed after its channel was lost, see `failover_streams`
+                        None => continue,
+                    };
                     let event = ProtocolEvent::Message { data: buffer, sid };
                     sorted_send_protocols
                         .get_mut(&cid)
@@ -331,6 +484,23 @@ impl BParticipant {
                         .await?;
                 }
 
+                // give the remote back what the application consumed, once per tick
+                let mut consumed = HashMap::<Sid, u64>::new();
+                for (sid, bytes) in a2b_consumed_r.try_iter() {
+                    *consumed.entry(sid).or_default() += bytes;
+                }
+                for (sid, bytes) in consumed {
+                    if let Some(&c) = sorted_stream_protocols.get(&sid) {
+                        cid = c;
+                        let event = ProtocolEvent::WindowUpdate { sid, bytes };
+                        sorted_send_protocols
+                            .get_mut(&c)
+                            .unwrap()
+                            .send(event)
+                            .await?;
+                    }
+                }
+
                 // process recv content afterwards
                 for (cid, sid) in b2b_notify_send_of_recv_close_r.try_iter() {
                     match sorted_send_protocols.get_mut(&cid) {
@@ -361,16 +531,39 @@ impl BParticipant {
                 let send_time = Instant::now();
                 let diff = send_time.duration_since(last_instant);
                 last_instant = send_time;
+                let limit = *a2b_bandwidth_limit_r.borrow();
+                match limit {
+                    Some(limit) => bucket.refill(limit, diff),
+                    None => bucket = TokenBucket::default(),
+                }
                 let mut cnt = 0;
                 for (c, p) in sorted_send_protocols.data.iter_mut() {
                     cid = *c;
-                    cnt += p.flush(1_000_000_000, diff).await?; //this actually blocks, so we cant set streams while it.
+                    let bandwidth = match limit {
+                        Some(_) => bucket.bandwidth(cnt, diff),
+                        None => 1_000_000_000,
+                    };
+                    cnt += p.flush(bandwidth, diff).await?; //this actually blocks, so we cant set streams while it.
+                }
+                if limit.is_some() && bucket.consume(cnt) {
+                    self.metrics
+                        .participant_throttled(&self.remote_pid_string, diff);
                 }
                 let flush_time = send_time.elapsed().as_secs_f32();
                 part_bandwidth = 0.99 * part_bandwidth + 0.01 * (cnt as f32 / flush_time);
                 self.metrics
                     .participant_bandwidth(&self.remote_pid_string, part_bandwidth);
                 let _ = b2a_bandwidth_stats_s.send(part_bandwidth);
+                // the fastest channel, that's where most streams end up
+                let rtt = sorted_send_protocols
+                    .data
+                    .iter()
+                    .filter_map(|(_, p)| p.rtt())
+                    .min();
+                if rtt != *b2a_rtt_s.borrow() {
+                    self.metrics.participant_rtt(&self.remote_pid_string, rtt);
+                    let _ = b2a_rtt_s.send(rtt);
+                }
                 let r: Result<(), network_protocol::ProtocolError> = Ok(());
                 r
             }
@@ -379,29 +572,88 @@ impl BParticipant {
                 info!(?cid, ?e, "protocol failed, shutting down channel");
                 // remote recv will now fail, which will trigger remote send which will trigger
                 // recv
-                trace!("TODO: for now decide to FAIL this participant and not wait for a failover");
-                sorted_send_protocols.delete(&cid).unwrap();
+                let lost = sorted_send_protocols.delete(&cid).unwrap().queued_streams();
                 self.metrics.channels_disconnected(&self.remote_pid_string);
                 if sorted_send_protocols.data.is_empty() {
                     break;
                 }
+                self.failover_streams(
+                    cid,
+                    &lost,
+                    &mut sorted_send_protocols,
+                    &mut sorted_stream_protocols,
+                    &b2a_stream_closed_s,
+                )
+                .await;
             }
 
-            if let Some(cid) = remp {
-                debug!(?cid, "remove protocol");
-                match sorted_send_protocols.delete(&cid) {
+            if let Some((cid, failed)) = remp {
+                debug!(?cid, ?failed, "remove protocol");
+                let lost = match sorted_send_protocols.delete(&cid) {
+                    // nothing can be drained from a broken channel
+                    Some(prot) if failed => {
+                        self.metrics.channels_disconnected(&self.remote_pid_string);
+                        prot.queued_streams()
+                    },
                     Some(mut prot) => {
                         self.metrics.channels_disconnected(&self.remote_pid_string);
-                        trace!("blocking flush");
-                        let _ = prot.flush(u64::MAX, Duration::from_secs(1)).await;
+                        trace!("drain queued messages");
+                        let drain = async {
+                            loop {
+                                for (sid, buffer) in a2b_msg_r.try_iter() {
+                                    let event = ProtocolEvent::Message { data: buffer, sid };
+                                    match sorted_stream_protocols.get(&sid) {
+                                        Some(&c) if c == cid => prot.send(event).await?,
+                                        Some(c) => {
+                                            if let Some(p) = sorted_send_protocols.get_mut(c) {
+                                                let _ = p.send(event).await;
+                                            }
+                                        },
+                                        None => {},
+                                    }
+                                }
+                                let sent = prot
+                                    .flush(Self::SHUTDOWN_DRAIN_BANDWIDTH, Self::TICK_TIME)
+                                    .await?;
+                                if sent == 0 && a2b_msg_r.is_empty() {
+                                    break Ok::<_, network_protocol::ProtocolError>(());
+                                }
+                            }
+                        };
+                        match tokio::time::timeout(self.shutdown_grace, drain).await {
+                            Ok(Ok(())) => {},
+                            Ok(Err(e)) => debug!(?cid, ?e, "protocol failed while draining"),
+                            Err(_) => warn!(?cid, "draining protocol timed out"),
+                        }
+                        // the protocol holds the Shutdown back till all its streams are closed
+                        for &(sid, c) in sorted_stream_protocols.data.iter() {
+                            if c == cid {
+                                let _ = prot.send(ProtocolEvent::CloseStream { sid }).await;
+                            }
+                        }
                         trace!("shutdown prot");
                         let _ = prot.send(ProtocolEvent::Shutdown).await;
+                        let _ = prot
+                            .flush(Self::SHUTDOWN_DRAIN_BANDWIDTH, Self::TICK_TIME)
+                            .await;
+                        prot.queued_streams()
+                    },
+                    None => {
+                        trace!("tried to remove protocol twice");
+                        Vec::new()
                     },
-                    None => trace!("tried to remove protocol twice"),
                 };
                 if sorted_send_protocols.data.is_empty() {
                     break;
                 }
+                self.failover_streams(
+                    cid,
+                    &lost,
+                    &mut sorted_send_protocols,
+                    &mut sorted_stream_protocols,
+                    &b2a_stream_closed_s,
+                )
+                .await;
             }
         }
         trace!("stop sending in api!");
@@ -411,21 +663,81 @@ impl BParticipant {
             .fetch_sub(Self::BARR_SEND, Ordering::SeqCst);
     }
 
-    async fn recv_mgr(
+    /// Moves the streams of a removed channel to the remaining channels, the
+    /// participant keeps working as long as one channel is left. Streams that
+    /// still had messages queued in the removed channel (`lost`) are closed on
+    /// both sides instead, as these messages can't be delivered anymore.
+    /// Messages the remote didn't receive although they were already sent can't
+    /// be detected though.
+    async fn failover_streams(
         &self,
-        b2a_stream_opened_s: mpsc::UnboundedSender<Stream>,
-        mut b2b_add_protocol_r: mpsc::UnboundedReceiver<(Cid, RecvProtocols)>,
-        b2b_force_close_recv_protocol_r: async_channel::Receiver<Cid>,
-        b2b_close_send_protocol_s: async_channel::Sender<Cid>,
-        b2b_notify_send_of_recv_open_r: crossbeam_channel::Sender<(
-            Cid,
-            Sid,
-            Prio,
-            Promises,
-            Bandwidth,
-        )>,
-        b2b_notify_send_of_recv_close_s: crossbeam_channel::Sender<(Cid, Sid)>,
+        cid: Cid,
+        lost: &[Sid],
+        send_protocols: &mut SortedVec<Cid, SendProtocols>,
+        stream_protocols: &mut SortedVec<Sid, Cid>,
+        b2a_stream_closed_s: &mpsc::UnboundedSender<Sid>,
     ) {
+        let moved: Vec<Sid> = stream_protocols
+            .data
+            .iter()
+            .filter(|(_, c)| *c == cid)
+            .map(|(sid, _)| *sid)
+            .collect();
+        for sid in moved {
+            let (prio, promises, guaranteed_bandwidth, window) = self
+                .streams
+                .read()
+                .await
+                .get(&sid)
+                .map_or((0, Promises::empty(), 0, DEFAULT_STREAM_WINDOW), |si| {
+                    (si.prio, si.promises, si.guaranteed_bandwidth, si.window)
+                });
+            let new_cid = match Self::best_protocol(send_protocols, stream_protocols, promises) {
+                Some(new_cid) => new_cid,
+                None => continue,
+            };
+            // the remote registers the stream the same way once it notices the removal
+            let p = send_protocols.get_mut(&new_cid).unwrap();
+            p.notify_from_recv(ProtocolEvent::OpenStream {
+                sid,
+                prio,
+                promises,
+                guaranteed_bandwidth,
+                window,
+            });
+            if lost.contains(&sid) {
+                warn!(
+                    ?sid,
+                    ?cid,
+                    "queued messages were lost with the channel, close stream"
+                );
+                stream_protocols.delete(&sid);
+                if let Err(e) = p.send(ProtocolEvent::CloseStream { sid }).await {
+                    debug!(?sid, ?e, "couldn't notify remote about closing");
+                }
+                if self.delete_stream(sid).await && b2a_stream_closed_s.send(sid).is_err() {
+                    debug!(?sid, "Participant was dropped, can't notify about closing");
+                }
+            } else {
+                debug!(?sid, ?cid, ?new_cid, "move stream to another channel");
+                if let Some(c) = stream_protocols.get_mut(&sid) {
+                    *c = new_cid;
+                }
+            }
+        }
+    }
+
+    async fn recv_mgr(&self, channels: RecvMgrChannels) {
+        let RecvMgrChannels {
+            b2a_stream_opened_s,
+            b2a_stream_closed_s,
+            mut b2b_add_protocol_r,
+            b2b_force_close_recv_protocol_r,
+            b2b_close_send_protocol_s,
+            b2b_notify_send_of_recv_open_s,
+            b2b_notify_send_of_recv_close_s,
+            b2b_notify_send_of_recv_window_s,
+        } = channels;
         let mut recv_protocols: HashMap<Cid, JoinHandle<()>> = HashMap::new();
         // we should be able to directly await futures imo
         let (hacky_recv_s, mut hacky_recv_r) = mpsc::unbounded_channel();
@@ -440,6 +752,23 @@ impl BParticipant {
             map.insert(cid, handle);
         };
 
+        // While a stream is full the channel its message came from isn't read, so
+        // the remote gets slowed down by the flow control of the protocol. Other
+        // channels keep being read, and removing the channel aborts the wait.
+        let retrigger_once_sent = |cid: Cid,
+                                   mut p: RecvProtocols,
+                                   b2a_msg_recv_s: async_channel::Sender<Bytes>,
+                                   data: Bytes,
+                                   map: &mut HashMap<_, _>| {
+            let hacky_recv_s = hacky_recv_s.clone();
+            let handle = tokio::spawn(async move {
+                let _ = b2a_msg_recv_s.send(data).await;
+                let r = p.recv().await;
+                let _ = hacky_recv_s.send((cid, r, p)); // ignoring failed
+            });
+            map.insert(cid, handle);
+        };
+
         let remove_c = |recv_protocols: &mut HashMap<Cid, JoinHandle<()>>, cid: &Cid| {
             match recv_protocols.remove(cid) {
                 Some(h) => {
@@ -482,42 +811,73 @@ impl BParticipant {
                         prio,
                         promises,
                         guaranteed_bandwidth,
+                        window,
                     }) => {
                         trace!(?sid, "open stream");
-                        let _ = b2b_notify_send_of_recv_open_r.send((
+                        let _ = b2b_notify_send_of_recv_open_s.send((
                             cid,
                             sid,
                             prio,
                             promises,
                             guaranteed_bandwidth,
+                            window,
                         ));
                         // waiting for receiving is not necessary, because the send_mgr will first
                         // process this before process messages!
                         let stream = self
-                            .create_stream(sid, prio, promises, guaranteed_bandwidth)
+                            .create_stream(sid, prio, promises, guaranteed_bandwidth, window)
                             .await;
-                        b2a_stream_opened_s.send(stream).unwrap();
+                        if b2a_stream_opened_s.send(stream).is_err() {
+                            // dropping the stream closes it again
+                            debug!(?sid, "Participant was dropped, can't hand out stream");
+                        }
                         retrigger(cid, p, &mut recv_protocols);
                     },
                     Ok(ProtocolEvent::CloseStream { sid }) => {
                         trace!(?sid, "close stream");
                         let _ = b2b_notify_send_of_recv_close_s.send((cid, sid));
-                        self.delete_stream(sid).await;
+                        // a simultaneous local close was already noticed by the application
+                        if self.delete_stream(sid).await && b2a_stream_closed_s.send(sid).is_err() {
+                            debug!(?sid, "Participant was dropped, can't notify about closing");
+                        }
+                        retrigger(cid, p, &mut recv_protocols);
+                    },
+                    Ok(ProtocolEvent::WindowUpdate { sid, bytes }) => {
+                        let _ = b2b_notify_send_of_recv_window_s.send((sid, bytes));
                         retrigger(cid, p, &mut recv_protocols);
                     },
                     Ok(ProtocolEvent::Message { data, sid }) => {
-                        let lock = self.streams.read().await;
-                        match lock.get(&sid) {
-                            Some(stream) => {
-                                let _ = stream.b2a_msg_recv_s.lock().await.send(data).await;
+                        let b2a_msg_recv_s = match self.streams.read().await.get(&sid) {
+                            Some(stream) => Some(stream.b2a_msg_recv_s.lock().await.clone()),
+                            None => None,
+                        };
+                        match b2a_msg_recv_s {
+                            Some(b2a_msg_recv_s) => {
+                                match b2a_msg_recv_s.try_send(data) {
+                                    Err(async_channel::TrySendError::Full(data)) => {
+                                        retrigger_once_sent(
+                                            cid,
+                                            p,
+                                            b2a_msg_recv_s,
+                                            data,
+                                            &mut recv_protocols,
+                                        )
+                                    },
+                                    // a closed stream drops its messages
+                                    Ok(()) | Err(async_channel::TrySendError::Closed(_)) => {
+                                        retrigger(cid, p, &mut recv_protocols)
+                                    },
+                                }
+                            },
+                            None => {
+                                defered_orphan.log(sid);
+                                retrigger(cid, p, &mut recv_protocols);
                             },
-                            None => defered_orphan.log(sid),
                         };
-                        retrigger(cid, p, &mut recv_protocols);
                     },
                     Ok(ProtocolEvent::Shutdown) => {
                         info!(?cid, "shutdown protocol");
-                        if let Err(e) = b2b_close_send_protocol_s.send(cid).await {
+                        if let Err(e) = b2b_close_send_protocol_s.send((cid, false)).await {
                             debug!(?e, ?cid, "send_mgr was already closed simultaneously");
                         }
                         if remove_c(&mut recv_protocols, &cid) {
@@ -526,7 +886,7 @@ impl BParticipant {
                     },
                     Err(e) => {
                         info!(?e, ?cid, "protocol failed, shutting down channel");
-                        if let Err(e) = b2b_close_send_protocol_s.send(cid).await {
+                        if let Err(e) = b2b_close_send_protocol_s.send((cid, true)).await {
                             debug!(?e, ?cid, "send_mgr was already closed simultaneously");
                         }
                         if remove_c(&mut recv_protocols, &cid) {
@@ -543,9 +903,10 @@ impl BParticipant {
             }
         }
         trace!("receiving no longer possible, closing all streams");
-        for (_, si) in self.streams.write().await.drain() {
+        for (sid, si) in self.streams.write().await.drain() {
             si.send_closed.store(true, Ordering::SeqCst);
             self.metrics.streams_closed(&self.remote_pid_string);
+            self.metrics.stream_closed(self.remote_pid, sid);
         }
         trace!("Stop recv_mgr");
         self.shutdown_barrier
@@ -560,7 +921,7 @@ impl BParticipant {
     ) {
         let s2b_create_channel_r = UnboundedReceiverStream::new(s2b_create_channel_r);
         s2b_create_channel_r
-            .for_each_concurrent(None, |(cid, _, protocol, b2s_create_channel_done_s)| {
+            .for_each_concurrent(None, |(cid, _, mut protocol, b2s_create_channel_done_s)| {
                 // This channel is now configured, and we are running it in scope of the
                 // participant.
                 let channels = Arc::clone(&self.channels);
@@ -577,10 +938,18 @@ impl BParticipant {
                         }),
                     );
                     drop(lock);
+                    protocol.set_stream_observer(Arc::new(ParticipantStreams::new(
+                        Arc::clone(&self.metrics),
+                        self.remote_pid,
+                    )));
                     let (send, recv) = protocol.split();
-                    b2b_add_send_protocol_s.send((cid, send)).unwrap();
-                    b2b_add_recv_protocol_s.send((cid, recv)).unwrap();
-                    b2s_create_channel_done_s.send(()).unwrap();
+                    if b2b_add_send_protocol_s.send((cid, send)).is_err()
+                        || b2b_add_recv_protocol_s.send((cid, recv)).is_err()
+                    {
+                        warn!(?cid, "managers already stopped, dropping channel");
+                        return;
+                    }
+                    let _ = b2s_create_channel_done_s.send(());
                     if channel_no > 5 {
                         debug!(?channel_no, "metrics will overwrite channel #5");
                         channel_no = 5;
@@ -620,7 +989,7 @@ impl BParticipant {
     async fn participant_shutdown_mgr(
         &self,
         s2b_shutdown_bparticipant_r: oneshot::Receiver<S2bShutdownBparticipant>,
-        b2b_close_send_protocol_s: async_channel::Sender<Cid>,
+        b2b_close_send_protocol_s: async_channel::Sender<B2bCloseSendProtocol>,
         b2b_force_close_recv_protocol_s: async_channel::Sender<Cid>,
     ) {
         let wait_for_manager = || async {
@@ -654,7 +1023,7 @@ impl BParticipant {
             self.remote_pid
         );
         for cid in lock.keys() {
-            if let Err(e) = b2b_close_send_protocol_s.send(*cid).await {
+            if let Err(e) = b2b_close_send_protocol_s.send((*cid, false)).await {
                 debug!(
                     ?e,
                     ?cid,
@@ -706,18 +1075,23 @@ impl BParticipant {
 
     /// Stopping API and participant usage
     /// Protocol will take care of the order of the frame
-    async fn delete_stream(&self, sid: Sid) {
+    /// Returns false if the stream was already closed
+    async fn delete_stream(&self, sid: Sid) -> bool {
         let stream = { self.streams.write().await.remove(&sid) };
-        match stream {
+        let existed = match stream {
             Some(si) => {
                 si.send_closed.store(true, Ordering::SeqCst);
                 si.b2a_msg_recv_s.lock().await.close();
+                true
             },
             None => {
-                trace!("Couldn't find the stream, might be simultaneous close from local/remote")
+                trace!("Couldn't find the stream, might be simultaneous close from local/remote");
+                false
             },
-        }
+        };
         self.metrics.streams_closed(&self.remote_pid_string);
+        self.metrics.stream_closed(self.remote_pid, sid);
+        existed
     }
 
     async fn create_stream(
@@ -726,21 +1100,31 @@ impl BParticipant {
         prio: Prio,
         promises: Promises,
         guaranteed_bandwidth: Bandwidth,
+        window: u64,
     ) -> Stream {
-        let (b2a_msg_recv_s, b2a_msg_recv_r) = async_channel::unbounded::<Bytes>();
+        let (b2a_msg_recv_s, b2a_msg_recv_r) =
+            async_channel::bounded::<Bytes>(self.stream_capacity);
         let send_closed = Arc::new(AtomicBool::new(false));
         self.streams.write().await.insert(sid, StreamInfo {
             prio,
             promises,
+            guaranteed_bandwidth,
+            window,
             send_closed: Arc::clone(&send_closed),
             b2a_msg_recv_s: Mutex::new(b2a_msg_recv_s),
         });
         self.metrics.streams_opened(&self.remote_pid_string);
+        self.metrics
+            .stream_opened(self.remote_pid, sid, prio, promises);
 
-        let (a2b_msg_s, a2b_close_stream_s) = {
+        let (a2b_msg_s, a2b_consumed_s, a2b_close_stream_s) = {
             let lock = self.open_stream_channels.lock().await;
             match &*lock {
-                Some(osi) => (osi.a2b_msg_s.clone(), osi.a2b_close_stream_s.clone()),
+                Some(osi) => (
+                    osi.a2b_msg_s.clone(),
+                    osi.a2b_consumed_s.clone(),
+                    osi.a2b_close_stream_s.clone(),
+                ),
                 None => {
                     // This Stream will not be able to send. feed it some "Dummy" Channels.
                     debug!(
@@ -748,8 +1132,9 @@ impl BParticipant {
                          already closed"
                     );
                     let (a2b_msg_s, _) = crossbeam_channel::unbounded();
+                    let (a2b_consumed_s, _) = crossbeam_channel::unbounded();
                     let (a2b_close_stream_s, _) = mpsc::unbounded_channel();
-                    (a2b_msg_s, a2b_close_stream_s)
+                    (a2b_msg_s, a2b_consumed_s, a2b_close_stream_s)
                 },
             }
         };
@@ -763,8 +1148,10 @@ impl BParticipant {
             guaranteed_bandwidth,
             send_closed,
             a2b_msg_s,
+            a2b_consumed_s,
             b2a_msg_recv_r,
             a2b_close_stream_s,
+            Arc::clone(&self.metrics),
         )
     }
 }
@@ -772,6 +1159,7 @@ impl BParticipant {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::api::Network;
     use network_protocol::{ProtocolMetricCache, ProtocolMetrics};
     use tokio::{
         runtime::Runtime,
@@ -799,16 +1187,26 @@ mod tests {
             bparticipant,
             a2b_open_stream_s,
             b2a_stream_opened_r,
+            _b2a_stream_closed_r,
             s2b_create_channel_s,
             s2b_shutdown_bparticipant_s,
             b2a_bandwidth_stats_r,
+            _b2a_rtt_r,
+            _a2b_bandwidth_limit_s,
         ) = runtime_clone.block_on(async move {
             let local_pid = Pid::fake(0);
             let remote_pid = Pid::fake(1);
             let sid = Sid::new(1000);
             let metrics = Arc::new(NetworkMetrics::new(&local_pid).unwrap());
 
-            BParticipant::new(local_pid, remote_pid, sid, Arc::clone(&metrics))
+            BParticipant::new(
+                local_pid,
+                remote_pid,
+                sid,
+                Network::DEFAULT_STREAM_CAPACITY,
+                Network::DEFAULT_SHUTDOWN_GRACE,
+                Arc::clone(&metrics),
+            )
         });
 
         let handle = runtime_clone.spawn(bparticipant.run(b2s_prio_statistic_s));
@@ -946,11 +1344,13 @@ mod tests {
                 prio,
                 promises,
                 guaranteed_bandwidth,
+                window,
             } => {
                 assert_eq!(sid, Sid::new(1000));
                 assert_eq!(prio, 7u8);
                 assert_eq!(promises, Promises::ENCRYPTED);
                 assert_eq!(guaranteed_bandwidth, 1_000_000);
+                assert_eq!(window, DEFAULT_STREAM_WINDOW);
             },
             _ => panic!("wrong event"),
         };
@@ -995,6 +1395,7 @@ mod tests {
                 prio: 9u8,
                 promises: Promises::ORDERED,
                 guaranteed_bandwidth: 1_000_000,
+                window: DEFAULT_STREAM_WINDOW,
             }))
             .unwrap();
 
@@ -1016,4 +1417,165 @@ mod tests {
         drop((a2b_open_stream_s, b2a_stream_opened_r, b2s_prio_statistic_r));
         drop(runtime);
     }
+
+    #[test]
+    fn remote_opens_stream_after_participant_dropped() {
+        let (
+            runtime,
+            a2b_open_stream_s,
+            b2a_stream_opened_r,
+            mut s2b_create_channel_s,
+            s2b_shutdown_bparticipant_s,
+            b2s_prio_statistic_r,
+            _b2a_bandwidth_stats_r,
+            handle,
+        ) = mock_bparticipant();
+
+        let remote = runtime.block_on(mock_mpsc(0, &runtime, &mut s2b_create_channel_s));
+        std::thread::sleep(Duration::from_millis(50));
+        drop(b2a_stream_opened_r);
+
+        // must not take down the recv_mgr
+        let (mut rs, rr) = remote.split();
+        runtime
+            .block_on(rs.send(ProtocolEvent::OpenStream {
+                sid: Sid::new(1000),
+                prio: 9u8,
+                promises: Promises::ORDERED,
+                guaranteed_bandwidth: 1_000_000,
+                window: DEFAULT_STREAM_WINDOW,
+            }))
+            .unwrap();
+        std::thread::sleep(Duration::from_millis(50));
+
+        let (s, r) = oneshot::channel();
+        runtime.block_on(async {
+            drop(s2b_create_channel_s);
+            s2b_shutdown_bparticipant_s
+                .send((Duration::from_secs(1), s))
+                .unwrap();
+            drop((rs, rr));
+            r.await.unwrap().unwrap();
+        });
+
+        runtime.block_on(handle).unwrap();
+
+        drop((a2b_open_stream_s, b2s_prio_statistic_r));
+        drop(runtime);
+    }
+
+    #[test]
+    fn shutdown_drains_queued_messages() {
+        use network_protocol::KeepAlive;
+        use tokio::net::{TcpListener, TcpStream};
+
+        let runtime = Runtime::new().unwrap();
+        let (b2s_prio_statistic_s, b2s_prio_statistic_r) = mpsc::unbounded_channel();
+        let local_pid = Pid::fake(0);
+        let metrics = Arc::new(NetworkMetrics::new(&local_pid).unwrap());
+        let (
+            bparticipant,
+            a2b_open_stream_s,
+            b2a_stream_opened_r,
+            _b2a_stream_closed_r,
+            s2b_create_channel_s,
+            s2b_shutdown_bparticipant_s,
+            _b2a_bandwidth_stats_r,
+            _b2a_rtt_r,
+            a2b_bandwidth_limit_s,
+        ) = BParticipant::new(
+            local_pid,
+            Pid::fake(1),
+            Sid::new(1000),
+            Network::DEFAULT_STREAM_CAPACITY,
+            Network::DEFAULT_SHUTDOWN_GRACE,
+            metrics,
+        );
+        let handle = runtime.spawn(bparticipant.run(b2s_prio_statistic_s));
+
+        let (local, remote) = runtime.block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            let (local, remote) = tokio::join!(TcpStream::connect(addr), listener.accept());
+            (local.unwrap(), remote.unwrap().0)
+        });
+        let met = Arc::new(ProtocolMetrics::new().unwrap());
+        let (complete_s, complete_r) = oneshot::channel();
+        let local = Protocols::new_tcp(
+            local,
+            ProtocolMetricCache::new("0", Arc::clone(&met)),
+            KeepAlive::default(),
+        );
+        s2b_create_channel_s
+            .send((0, Sid::new(0), local, complete_s))
+            .unwrap();
+        runtime.block_on(complete_r).unwrap();
+        let remote = Protocols::new_tcp(
+            remote,
+            ProtocolMetricCache::new("1", met),
+            KeepAlive::default(),
+        );
+        let (rs, mut rr) = remote.split();
+
+        // throttle, so most messages are still queued when shutting down
+        a2b_bandwidth_limit_s.send(Some(1_000)).unwrap();
+        let (stream_s, stream_r) = oneshot::channel();
+        a2b_open_stream_s
+            .send((5u8, Promises::ORDERED, 0, stream_s))
+            .unwrap();
+        let mut stream = runtime.block_on(stream_r).unwrap();
+        assert!(matches!(
+            runtime.block_on(rr.recv()),
+            Ok(ProtocolEvent::OpenStream { .. })
+        ));
+        for i in 0..50u8 {
+            stream.send(vec![i; 100]).unwrap();
+        }
+
+        let (s, r) = oneshot::channel();
+        runtime.block_on(async {
+            drop(s2b_create_channel_s);
+            s2b_shutdown_bparticipant_s
+                .send((Duration::from_secs(1), s))
+                .unwrap();
+            for i in 0..50u8 {
+                match rr.recv().await {
+                    Ok(ProtocolEvent::Message { data, .. }) => assert_eq!(data.last(), Some(&i)),
+                    e => panic!("expected message {}, got {:?}", i, e),
+                }
+            }
+            assert!(matches!(
+                rr.recv().await,
+                Ok(ProtocolEvent::CloseStream { .. })
+            ));
+            assert!(matches!(rr.recv().await, Ok(ProtocolEvent::Shutdown)));
+            drop((rs, rr));
+            r.await.unwrap().unwrap();
+        });
+
+        runtime.block_on(handle).unwrap();
+
+        drop((a2b_open_stream_s, b2a_stream_opened_r, b2s_prio_statistic_r));
+        drop(runtime);
+    }
+
+    #[test]
+    fn token_bucket_limits_and_caps_burst() {
+        let mut bucket = TokenBucket::default();
+        let dt = Duration::from_millis(5);
+        // 10_000 B/s -> 50 bytes per 5ms tick
+        bucket.refill(10_000, dt);
+        assert_eq!(bucket.bandwidth(0, dt), 10_000);
+        assert_eq!(bucket.bandwidth(20, dt), 6_000);
+        assert!(!bucket.consume(20));
+        // overdraw by a frame, following ticks pay the debt
+        assert!(bucket.consume(1400));
+        for _ in 0..27 {
+            bucket.refill(10_000, dt);
+        }
+        assert_eq!(bucket.bandwidth(0, dt), 0);
+        // idle time doesn't accumulate more than MAX_BURST
+        bucket.refill(10_000, Duration::from_secs(10));
+        assert_eq!(bucket.bandwidth(0, Duration::from_secs(1)), 1_000);
+    }
 }
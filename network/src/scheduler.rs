@@ -6,13 +6,14 @@ use crate::{
 };
 use futures_util::StreamExt;
 use hashbrown::HashMap;
-use network_protocol::{Cid, Pid, ProtocolMetricCache, ProtocolMetrics};
+use network_protocol::{Cid, NoiseKeys, Pid, ProtocolMetricCache, ProtocolMetrics, VersionPolicy};
 #[cfg(feature = "metrics")]
 use prometheus::Registry;
 use rand::Rng;
+#[cfg(feature = "debug")] use std::path::PathBuf;
 use std::{
     sync::{
-        atomic::{AtomicBool, AtomicU64, Ordering},
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
         Arc,
     },
     time::Duration,
@@ -36,7 +37,6 @@ use tracing::*;
 #[derive(Debug)]
 struct ParticipantInfo {
     secret: u128,
-    #[allow(dead_code)]
     s2b_create_channel_s: mpsc::UnboundedSender<S2bCreateChannel>,
     s2b_shutdown_bparticipant_s: Option<oneshot::Sender<S2bShutdownBparticipant>>,
 }
@@ -44,10 +44,21 @@ struct ParticipantInfo {
 type A2sListen = (ListenAddr, oneshot::Sender<io::Result<()>>);
 pub(crate) type A2sConnect = (
     ConnectAddr,
-    oneshot::Sender<Result<Participant, NetworkConnectError>>,
+    oneshot::Sender<Result<Connected, NetworkConnectError>>,
 );
 type A2sDisconnect = (Pid, S2bShutdownBparticipant);
 
+/// Outcome of a successful connect
+#[derive(Debug)]
+#[allow(clippy::large_enum_variant)]
+pub(crate) enum Connected {
+    /// The channel belongs to a new participant
+    Participant(Participant),
+    /// The channel was added to the participant with this pid, which was
+    /// connected already
+    Channel(Pid),
+}
+
 #[derive(Debug)]
 struct ControlChannels {
     a2s_listen_r: mpsc::UnboundedReceiver<A2sListen>,
@@ -76,11 +87,23 @@ pub struct Scheduler {
     channel_listener: Mutex<HashMap<ProtocolInfo, oneshot::Sender<()>>>,
     metrics: Arc<NetworkMetrics>,
     protocol_metrics: Arc<ProtocolMetrics>,
+    stream_capacity: Arc<AtomicUsize>,
+    /// in milliseconds
+    shutdown_grace: Arc<AtomicU64>,
+    encryption: Arc<std::sync::Mutex<Option<NoiseKeys>>>,
+    exact_version: Arc<AtomicBool>,
+    #[cfg(feature = "debug")]
+    frame_log_dir: Arc<std::sync::Mutex<Option<PathBuf>>>,
 }
 
 impl Scheduler {
     pub fn new(
         local_pid: Pid,
+        stream_capacity: Arc<AtomicUsize>,
+        shutdown_grace: Arc<AtomicU64>,
+        encryption: Arc<std::sync::Mutex<Option<NoiseKeys>>>,
+        exact_version: Arc<AtomicBool>,
+        #[cfg(feature = "debug")] frame_log_dir: Arc<std::sync::Mutex<Option<PathBuf>>>,
         #[cfg(feature = "metrics")] registry: Option<&Registry>,
     ) -> (
         Self,
@@ -137,6 +160,12 @@ impl Scheduler {
                 channel_listener: Mutex::new(HashMap::new()),
                 metrics,
                 protocol_metrics,
+                stream_capacity,
+                shutdown_grace,
+                encryption,
+                exact_version,
+                #[cfg(feature = "debug")]
+                frame_log_dir,
             },
             a2s_listen_s,
             a2s_connect_s,
@@ -197,6 +226,18 @@ impl Scheduler {
                             )
                             .await
                         },
+                        #[cfg(feature = "tls")]
+                        ListenAddr::TcpTls(addr, ref server_config) => {
+                            Protocols::with_tcp_tls_listen(
+                                addr,
+                                Arc::clone(server_config),
+                                cids,
+                                metrics,
+                                s2s_stop_listening_r,
+                                c2s_protocol_s,
+                            )
+                            .await
+                        },
                         #[cfg(feature = "quic")]
                         ListenAddr::Quic(addr, ref server_config) => {
                             Protocols::with_quic_listen(
@@ -209,6 +250,16 @@ impl Scheduler {
                             )
                             .await
                         },
+                        ListenAddr::Udp(addr) => {
+                            Protocols::with_udp_listen(
+                                addr,
+                                cids,
+                                metrics,
+                                s2s_stop_listening_r,
+                                c2s_protocol_s,
+                            )
+                            .await
+                        },
                         ListenAddr::Mpsc(addr) => {
                             Protocols::with_mpsc_listen(
                                 addr,
@@ -219,7 +270,6 @@ impl Scheduler {
                             )
                             .await
                         },
-                        _ => unimplemented!(),
                     };
                     let _ = s2a_listen_result_s.send(res);
 
@@ -241,12 +291,18 @@ impl Scheduler {
             self.metrics.connect_request(&addr);
             let protocol = match addr {
                 ConnectAddr::Tcp(addr) => Protocols::with_tcp_connect(addr, metrics).await,
+                #[cfg(feature = "tls")]
+                ConnectAddr::TcpTls(addr, ref config, name) => {
+                    Protocols::with_tcp_tls_connect(addr, Arc::clone(config), name, metrics).await
+                },
                 #[cfg(feature = "quic")]
                 ConnectAddr::Quic(addr, ref config, name) => {
                     Protocols::with_quic_connect(addr, config.clone(), name, metrics).await
                 },
+                #[cfg(feature = "websocket")]
+                ConnectAddr::Ws(addr) => Protocols::with_ws_connect(addr, metrics).await,
+                ConnectAddr::Udp(addr) => Protocols::with_udp_connect(addr, metrics).await,
                 ConnectAddr::Mpsc(addr) => Protocols::with_mpsc_connect(addr, metrics).await,
-                _ => unimplemented!(),
             };
             let protocol = match protocol {
                 Ok(p) => p,
@@ -376,22 +432,40 @@ impl Scheduler {
         &self,
         mut protocol: Protocols,
         cid: Cid,
-        s2a_return_pid_s: Option<oneshot::Sender<Result<Participant, NetworkConnectError>>>,
-        send_handshake: bool,
+        s2a_return_pid_s: Option<oneshot::Sender<Result<Connected, NetworkConnectError>>>,
+        initializer: bool,
     ) {
         //channels are unknown till PID is known!
-        /* When A connects to a NETWORK, we, the listener answers with a Handshake.
-          Pro: - Its easier to debug, as someone who opens a port gets a magic number back!
-          Contra: - DOS possibility because we answer first
-                  - Speed, because otherwise the message can be send with the creation
+        /* When A connects to a NETWORK, A sends the first Handshake and we, the listener
+           (initializer), answer it.
+          Pro: - A listener can tell the transport (e.g. Tcp or WebSocket) by the first bytes
+               - No DOS possibility because we never answer first
+          Contra: - Someone who opens a port doesn't get a magic number back
         */
         let participant_channels = self.participant_channels.lock().await.clone().unwrap();
         // spawn is needed here, e.g. for TCP connect it would mean that only 1
         // participant can be in handshake phase ever! Someone could deadlock
-        // the whole server easily for new clients UDP doesnt work at all, as
-        // the UDP listening is done in another place.
+        // the whole server easily for new clients
         let participants = Arc::clone(&self.participants);
         let metrics = Arc::clone(&self.metrics);
+        let stream_capacity = self.stream_capacity.load(Ordering::Relaxed);
+        let shutdown_grace = Duration::from_millis(self.shutdown_grace.load(Ordering::Relaxed));
+        let encryption = self.encryption.lock().unwrap().clone();
+        if let Some(keys) = encryption {
+            protocol = protocol.with_encryption(keys);
+        }
+        #[cfg(feature = "debug")]
+        if let Some(dir) = self.frame_log_dir.lock().unwrap().as_ref() {
+            match crate::debug::ChannelFrameLog::create(dir, cid) {
+                Ok(log) => protocol.set_frame_observer(Arc::new(log)),
+                Err(e) => warn!(?e, ?cid, "couldn't create the frame log of the channel"),
+            }
+        }
+        let version_policy = if self.exact_version.load(Ordering::Relaxed) {
+            VersionPolicy::Exact
+        } else {
+            VersionPolicy::MajorMinor
+        };
         let local_pid = self.local_pid;
         let local_secret = self.local_secret;
         // this is necessary for UDP to work at all and to remove code duplication
@@ -400,7 +474,7 @@ impl Scheduler {
                 trace!(?cid, "Open channel and be ready for Handshake");
                 use network_protocol::InitProtocol;
                 let init_result = protocol
-                    .initialize(send_handshake, local_pid, local_secret)
+                    .initialize(initializer, local_pid, local_secret, version_policy)
                     .instrument(tracing::info_span!("handshake", ?cid))
                     .await;
                 match init_result {
@@ -417,18 +491,32 @@ impl Scheduler {
                                 bparticipant,
                                 a2b_open_stream_s,
                                 b2a_stream_opened_r,
+                                b2a_stream_closed_r,
                                 s2b_create_channel_s,
                                 s2b_shutdown_bparticipant_s,
                                 b2a_bandwidth_stats_r,
-                            ) = BParticipant::new(local_pid, pid, sid, Arc::clone(&metrics));
+                                b2a_rtt_r,
+                                a2b_bandwidth_limit_s,
+                            ) = BParticipant::new(
+                                local_pid,
+                                pid,
+                                sid,
+                                stream_capacity,
+                                shutdown_grace,
+                                Arc::clone(&metrics),
+                            );
 
                             let participant = Participant::new(
                                 local_pid,
                                 pid,
                                 a2b_open_stream_s,
                                 b2a_stream_opened_r,
+                                b2a_stream_closed_r,
                                 b2a_bandwidth_stats_r,
+                                b2a_rtt_r,
+                                a2b_bandwidth_limit_s,
                                 participant_channels.a2s_disconnect_s,
+                                Arc::clone(&metrics),
                             );
 
                             #[cfg(feature = "metrics")]
@@ -456,7 +544,9 @@ impl Scheduler {
                             b2s_create_channel_done_r.await.unwrap();
                             if let Some(pid_oneshot) = s2a_return_pid_s {
                                 // someone is waiting with `connect`, so give them their PID
-                                pid_oneshot.send(Ok(participant)).unwrap();
+                                pid_oneshot
+                                    .send(Ok(Connected::Participant(participant)))
+                                    .unwrap();
                             } else {
                                 // no one is waiting on this Participant, return in to Network
                                 if participant_channels
@@ -490,11 +580,21 @@ impl Scheduler {
                                 }
                                 return;
                             }
-                            error!(
-                                ?cid,
-                                "Ufff i cant answer the pid_oneshot. as i need to create the SAME \
-                                 participant. maybe switch to ARC"
-                            );
+                            debug!(?cid, ?pid, "Additional channel of a connected participant");
+                            let s2b_create_channel_s = pi.s2b_create_channel_s.clone();
+                            drop(participants);
+                            let (b2s_create_channel_done_s, b2s_create_channel_done_r) =
+                                oneshot::channel();
+                            let created = s2b_create_channel_s
+                                .send((cid, sid, protocol, b2s_create_channel_done_s))
+                                .is_ok()
+                                && b2s_create_channel_done_r.await.is_ok();
+                            if !created {
+                                debug!(?cid, ?pid, "Participant shut down before adding channel");
+                            }
+                            if let Some(pid_oneshot) = s2a_return_pid_s {
+                                let _ = pid_oneshot.send(Ok(Connected::Channel(pid)));
+                            }
                         }
                         //From now on this CHANNEL can receiver other frames!
                         // move directly to participant!
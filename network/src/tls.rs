@@ -0,0 +1,155 @@
+//! Helpers for [`ConnectAddr::TcpTls`] and [`ListenAddr::TcpTls`].
+//!
+//! Servers with a certificate signed by a public CA can be reached with the
+//! usual web pki roots. Small servers usually run with a self-signed
+//! certificate, for them the client pins the [`CertFingerprint`] the server
+//! admin published instead.
+//!
+//! [`ConnectAddr::TcpTls`]: crate::api::ConnectAddr::TcpTls
+//! [`ListenAddr::TcpTls`]: crate::api::ListenAddr::TcpTls
+
+use ring::digest::{digest, SHA256};
+use rustls::{
+    client::{ServerCertVerified, ServerCertVerifier},
+    Certificate, ClientConfig, Error, RootCertStore, ServerName,
+};
+use std::{fmt, str::FromStr, sync::Arc, time::SystemTime};
+use tracing::*;
+
+/// SHA-256 hash of the DER encoded certificate of a server
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct CertFingerprint(pub [u8; 32]);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParseFingerprintError;
+
+impl CertFingerprint {
+    pub fn of(cert: &Certificate) -> Self {
+        let mut fingerprint = [0u8; 32];
+        fingerprint.copy_from_slice(digest(&SHA256, &cert.0).as_ref());
+        Self(fingerprint)
+    }
+}
+
+/// Lowercase hex, the format accepted by [`FromStr`]
+impl fmt::Display for CertFingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.iter().try_for_each(|b| write!(f, "{:02x}", b))
+    }
+}
+
+/// Parses hex, optionally with the bytes separated by `:` like openssl prints
+/// them
+impl FromStr for CertFingerprint {
+    type Err = ParseFingerprintError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hex = s.trim().replace(':', "");
+        if hex.len() != 64 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(ParseFingerprintError);
+        }
+        let mut fingerprint = [0u8; 32];
+        for (i, byte) in fingerprint.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16)
+                .map_err(|_| ParseFingerprintError)?;
+        }
+        Ok(Self(fingerprint))
+    }
+}
+
+impl fmt::Display for ParseFingerprintError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "expected a SHA-256 fingerprint of 64 hex digits")
+    }
+}
+
+impl std::error::Error for ParseFingerprintError {}
+
+/// Accepts exactly the certificate with the pinned fingerprint, regardless of
+/// who signed it and which name it was issued for. The handshake signature is
+/// still checked, so the server has to own the private key of the certificate.
+struct PinnedCertVerifier(CertFingerprint);
+
+impl ServerCertVerifier for PinnedCertVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &Certificate,
+        _intermediates: &[Certificate],
+        _server_name: &ServerName,
+        _scts: &mut dyn Iterator<Item = &[u8]>,
+        _ocsp_response: &[u8],
+        _now: SystemTime,
+    ) -> Result<ServerCertVerified, Error> {
+        if CertFingerprint::of(end_entity) == self.0 {
+            Ok(ServerCertVerified::assertion())
+        } else {
+            Err(Error::InvalidCertificateData(
+                "certificate doesn't match the pinned fingerprint".to_string(),
+            ))
+        }
+    }
+}
+
+/// Client config that only trusts the certificate with `fingerprint`, for
+/// servers with a self-signed certificate
+pub fn pinned_client_config(fingerprint: CertFingerprint) -> Arc<ClientConfig> {
+    Arc::new(
+        ClientConfig::builder()
+            .with_safe_defaults()
+            .with_custom_certificate_verifier(Arc::new(PinnedCertVerifier(fingerprint)))
+            .with_no_client_auth(),
+    )
+}
+
+/// Client config that trusts the root certificates of the system, for
+/// servers with a certificate signed by a public CA. Roots that can't be
+/// loaded are skipped.
+pub fn native_roots_client_config() -> Arc<ClientConfig> {
+    let mut roots = RootCertStore::empty();
+    match rustls_native_certs::load_native_certs() {
+        Ok(certs) => {
+            for cert in certs {
+                if let Err(e) = roots.add(&Certificate(cert.0)) {
+                    debug!(?e, "skipping invalid root certificate");
+                }
+            }
+        },
+        Err(e) => warn!(?e, "couldn't load the root certificates of the system"),
+    }
+    Arc::new(
+        ClientConfig::builder()
+            .with_safe_defaults()
+            .with_root_certificates(roots)
+            .with_no_client_auth(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fingerprint_roundtrip() {
+        let fingerprint = CertFingerprint::of(&Certificate(vec![1, 2, 3]));
+        let hex = fingerprint.to_string();
+        assert_eq!(hex.len(), 64);
+        assert_eq!(hex.parse(), Ok(fingerprint));
+
+        let openssl = fingerprint
+            .0
+            .iter()
+            .map(|b| format!("{:02X}", b))
+            .collect::<Vec<_>>()
+            .join(":");
+        assert_eq!(openssl.parse(), Ok(fingerprint));
+
+        assert_eq!(
+            hex[2..].parse::<CertFingerprint>(),
+            Err(ParseFingerprintError)
+        );
+        assert_eq!(
+            "zz".repeat(32).parse::<CertFingerprint>(),
+            Err(ParseFingerprintError)
+        );
+    }
+}
@@ -0,0 +1,71 @@
+//! Counts the allocations of moving a big message through Tcp, the fragments
+//! of it must not be allocated one by one.
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+mod helper;
+use helper::{network_participant_stream, tcp};
+
+/// Allocations of at least this size are counted, the network allocates small
+/// things like metrics labels or channel nodes for every message anyway
+const COUNTED_SIZE: usize = 1024;
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+struct CountingAllocator;
+
+#[allow(unsafe_code)]
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        if layout.size() >= COUNTED_SIZE {
+            ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        }
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) { System.dealloc(ptr, layout) }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        if new_size >= COUNTED_SIZE {
+            ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        }
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+#[test]
+fn big_message_allocations() {
+    const SIZE: usize = 1024 * 1024;
+    // the message is split into frames of 1400 bytes
+    const FRAMES: usize = SIZE / 1400;
+    // serializing, buffering and deserializing the message on both sides,
+    // independent of how many frames it is split into
+    const MAX_ALLOCATIONS: usize = 16;
+
+    let (_, _) = helper::setup(false, 0);
+    let (r, _n_a, _p_a, mut s1_a, _n_b, _p_b, mut s1_b) = network_participant_stream(tcp());
+    let msg = vec![42u8; SIZE];
+    // warm up, so the buffers of both sides already grew
+    s1_a.send(&msg).unwrap();
+    assert_eq!(
+        r.block_on(s1_b.recv::<Vec<u8>>()).map(|m| m.len()),
+        Ok(SIZE)
+    );
+
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    s1_a.send(&msg).unwrap();
+    let received = r.block_on(s1_b.recv::<Vec<u8>>()).unwrap();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+
+    assert_eq!(received, msg);
+    assert!(
+        allocations <= MAX_ALLOCATIONS,
+        "{} allocations for {} frames",
+        allocations,
+        FRAMES
+    );
+    drop((_n_a, _n_b, _p_a, _p_b)); //clean teardown
+}
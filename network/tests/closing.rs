@@ -31,6 +31,7 @@ fn close_network() {
 
     std::thread::sleep(SLEEP_INTERNAL);
 
+    assert!(s1_a.is_closed());
     assert_eq!(s1_a.send("Hello World"), Err(StreamError::StreamClosed));
     let msg1: Result<String, _> = r.block_on(s1_b.recv());
     assert_eq!(msg1, Err(StreamError::StreamClosed));
@@ -40,6 +41,7 @@ fn close_network() {
 fn close_participant() {
     let (_, _) = helper::setup(false, 0);
     let (r, _n_a, p1_a, mut s1_a, _n_b, p1_b, mut s1_b) = network_participant_stream(tcp());
+    assert!(!s1_a.is_closed());
 
     r.block_on(p1_a.disconnect()).unwrap();
     r.block_on(p1_b.disconnect()).unwrap();
@@ -59,6 +61,7 @@ fn close_stream() {
     // s1_b is dropped directly while s1_a isn't
     std::thread::sleep(SLEEP_INTERNAL);
 
+    assert!(s1_a.is_closed());
     assert_eq!(s1_a.send("Hello World"), Err(StreamError::StreamClosed));
     assert_eq!(
         r.block_on(s1_a.recv::<String>()),
@@ -447,3 +450,62 @@ fn dont_panic_on_multiple_send_after_close() {
     assert_eq!(s1_b.send("foobar"), Err(StreamError::StreamClosed));
     assert_eq!(s1_b.send("foobar"), Err(StreamError::StreamClosed));
 }
+
+#[test]
+fn streams_opened_before_calling_opened_are_queued() {
+    let (_, _) = helper::setup(false, 0);
+    let (r, _n_a, p_a, _s1_a, _n_b, p_b, _s1_b) = network_participant_stream(tcp());
+    let mut s2_a = r.block_on(p_a.open(3, Promises::ORDERED, 0)).unwrap();
+    let s3_a = r.block_on(p_a.open(5, Promises::COMPRESSED, 0)).unwrap();
+    s2_a.send("Map").unwrap();
+    std::thread::sleep(SLEEP_EXTERNAL);
+    let mut s2_b = r.block_on(p_b.opened()).unwrap();
+    let s3_b = r.block_on(p_b.opened()).unwrap();
+    assert_eq!(
+        (s2_b.sid(), s2_b.prio(), s2_b.promises()),
+        (s2_a.sid(), 3, Promises::ORDERED)
+    );
+    assert_eq!(
+        (s3_b.sid(), s3_b.prio(), s3_b.promises()),
+        (s3_a.sid(), 5, Promises::COMPRESSED)
+    );
+    assert_eq!(r.block_on(s2_b.recv()), Ok("Map".to_string()));
+    drop((_n_a, _n_b, p_a, p_b)); //clean teardown
+}
+
+#[test]
+fn remote_stream_close_is_notified() {
+    let (_, _) = helper::setup(false, 0);
+    let (r, _n_a, p_a, s1_a, _n_b, p_b, mut s1_b) = network_participant_stream(tcp());
+    let mut s2_a = r.block_on(p_a.open(4, Promises::ORDERED, 0)).unwrap();
+    let sid1 = s1_a.sid();
+    let sid2 = s2_a.sid();
+    s2_a.send(42u32).unwrap();
+    // closed before the application asked for it
+    drop(s2_a);
+    std::thread::sleep(SLEEP_EXTERNAL);
+    let mut s2_b = r.block_on(p_b.opened()).unwrap();
+    assert_eq!(r.block_on(p_b.closed()), Ok(sid2));
+    assert_eq!(r.block_on(s2_b.recv()), Ok(42u32));
+    assert_eq!(
+        r.block_on(s2_b.recv::<u32>()),
+        Err(StreamError::StreamClosed)
+    );
+
+    drop(s1_a);
+    assert_eq!(r.block_on(p_b.closed()), Ok(sid1));
+    assert_eq!(
+        r.block_on(s1_b.recv::<u32>()),
+        Err(StreamError::StreamClosed)
+    );
+
+    // locally closed streams and disconnects aren't notified
+    let s3_b = r.block_on(p_b.open(4, Promises::ORDERED, 0)).unwrap();
+    drop(s3_b);
+    drop(p_a);
+    assert_eq!(
+        r.block_on(p_b.closed()),
+        Err(ParticipantError::ParticipantDisconnected)
+    );
+    drop((_n_a, _n_b, p_b)); //clean teardown
+}
@@ -1,4 +1,5 @@
 use lazy_static::*;
+use sim::SimConfig;
 use std::{
     net::{Ipv4Addr, SocketAddr},
     sync::{
@@ -13,6 +14,10 @@ use tracing::*;
 use tracing_subscriber::EnvFilter;
 use veloren_network::{ConnectAddr, ListenAddr, Network, Participant, Pid, Promises, Stream};
 
+#[allow(dead_code)]
+#[path = "helper/sim.rs"]
+pub mod sim;
+
 // sleep time when only internal rust calculations are done
 #[allow(dead_code)]
 pub const SLEEP_INTERNAL: std::time::Duration = std::time::Duration::from_millis(3000);
@@ -66,6 +71,58 @@ pub fn network_participant_stream(
     Stream,
 ) {
     let runtime = Arc::new(Runtime::new().unwrap());
+    network_participant_stream_on(runtime, addr)
+}
+
+/// Like [`network_participant_stream`], but `b` connects to `a` over Tcp
+/// through a [`sim::sim_proxy`]
+#[allow(dead_code)]
+pub fn network_participant_stream_sim(
+    config: SimConfig,
+) -> (
+    Arc<Runtime>,
+    Network,
+    Participant,
+    Stream,
+    Network,
+    Participant,
+    Stream,
+) {
+    let runtime = Arc::new(Runtime::new().unwrap());
+    let addr = sim_tcp(&runtime, config);
+    network_participant_stream_on(runtime, addr)
+}
+
+/// Tcp addresses where the connecting side goes through a [`sim::sim_proxy`],
+/// the proxy runs as long as `runtime`
+#[allow(dead_code)]
+pub fn sim_tcp(runtime: &Runtime, config: SimConfig) -> (ListenAddr, ConnectAddr) {
+    let (listen, target) = tcp();
+    let (_, proxy) = tcp();
+    let socket_addr = |addr: &ConnectAddr| match addr {
+        ConnectAddr::Tcp(addr) => *addr,
+        _ => unreachable!(),
+    };
+    runtime.block_on(sim::sim_proxy(
+        config,
+        socket_addr(&proxy),
+        socket_addr(&target),
+    ));
+    (listen, proxy)
+}
+
+fn network_participant_stream_on(
+    runtime: Arc<Runtime>,
+    addr: (ListenAddr, ConnectAddr),
+) -> (
+    Arc<Runtime>,
+    Network,
+    Participant,
+    Stream,
+    Network,
+    Participant,
+    Stream,
+) {
     let (n_a, p1_a, s1_a, n_b, p1_b, s1_b) = runtime.block_on(async {
         let n_a = Network::new(Pid::fake(0), &runtime);
         let n_b = Network::new(Pid::fake(1), &runtime);
@@ -94,6 +151,18 @@ pub fn tcp() -> (ListenAddr, ConnectAddr) {
     )
 }
 
+#[allow(dead_code)]
+pub fn ws() -> (ListenAddr, ConnectAddr) {
+    lazy_static! {
+        static ref PORTS: AtomicU16 = AtomicU16::new(5700);
+    }
+    let port = PORTS.fetch_add(1, Ordering::Relaxed);
+    (
+        ListenAddr::Tcp(SocketAddr::from((Ipv4Addr::LOCALHOST, port))),
+        ConnectAddr::Ws(SocketAddr::from((Ipv4Addr::LOCALHOST, port))),
+    )
+}
+
 lazy_static! {
     static ref UDP_PORTS: AtomicU16 = AtomicU16::new(5000);
 }
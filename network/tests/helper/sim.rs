@@ -0,0 +1,193 @@
+//! Simulated network links, loopback delivers instantly and never loses
+//! anything, which hides most timing related bugs.
+//!
+//! [`sim_duplex`] creates a pair of in-memory streams implementing
+//! `AsyncRead`/`AsyncWrite`, bytes written to one end arrive at the other after
+//! a configurable latency plus jitter, limited by a bandwidth. Like Tcp, a lost
+//! segment isn't dropped but retransmitted after a round trip, so everything
+//! arrives in order. [`sim_proxy`] puts such a link in front of a listening
+//! `Network`, so whole participants can be tested over it.
+//!
+//! Everything is seeded, so the delays are the same on every run.
+use futures_util::{stream::FuturesUnordered, StreamExt};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::{net::SocketAddr, time::Duration};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, DuplexStream},
+    net::{TcpListener, TcpStream},
+    sync::mpsc,
+    task::JoinHandle,
+    time::{sleep_until, Instant},
+};
+
+/// segments are delayed and lost as a whole
+const SEGMENT_SIZE: usize = 1400;
+const BUFFER_SIZE: usize = 64 * 1024;
+
+#[derive(Debug, Clone, Copy)]
+pub struct SimConfig {
+    /// one-way latency
+    pub latency: Duration,
+    /// every segment is delayed by an additional, uniformly distributed,
+    /// `0..jitter`
+    pub jitter: Duration,
+    /// probability `0.0..=1.0` of a segment getting lost
+    pub loss: f64,
+    /// bytes per second, `None` for unlimited
+    pub bandwidth: Option<u64>,
+    pub seed: u64,
+}
+
+impl SimConfig {
+    /// a link without any delay or loss
+    pub fn perfect() -> Self {
+        Self {
+            latency: Duration::ZERO,
+            jitter: Duration::ZERO,
+            loss: 0.0,
+            bandwidth: None,
+            seed: 0,
+        }
+    }
+
+    /// 100ms latency and 2% loss, what players on a bad connection have
+    pub fn bad() -> Self {
+        Self::perfect()
+            .with_latency(Duration::from_millis(100), Duration::from_millis(20))
+            .with_loss(0.02)
+            .with_seed(42)
+    }
+
+    pub fn with_latency(mut self, latency: Duration, jitter: Duration) -> Self {
+        self.latency = latency;
+        self.jitter = jitter;
+        self
+    }
+
+    pub fn with_loss(mut self, loss: f64) -> Self {
+        self.loss = loss;
+        self
+    }
+
+    pub fn with_bandwidth(mut self, bytes_per_sec: u64) -> Self {
+        self.bandwidth = Some(bytes_per_sec);
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+}
+
+/// Creates two connected streams, see module docs. `config` is used for both
+/// directions, the seed of the second one is increased by 1.
+pub fn sim_duplex(config: SimConfig) -> (DuplexStream, DuplexStream) {
+    let (a, a_inner) = tokio::io::duplex(BUFFER_SIZE);
+    let (b, b_inner) = tokio::io::duplex(BUFFER_SIZE);
+    let (a_read, a_write) = tokio::io::split(a_inner);
+    let (b_read, b_write) = tokio::io::split(b_inner);
+    tokio::spawn(link(a_read, b_write, config));
+    tokio::spawn(link(
+        b_read,
+        a_write,
+        config.with_seed(config.seed.wrapping_add(1)),
+    ));
+    (a, b)
+}
+
+/// Listens on `listen` and forwards every connection to `target` over a
+/// [`sim_duplex`]. Aborting the returned handle cuts all connections.
+pub async fn sim_proxy(
+    config: SimConfig,
+    listen: SocketAddr,
+    target: SocketAddr,
+) -> JoinHandle<()> {
+    let listener = TcpListener::bind(listen).await.unwrap();
+    tokio::spawn(async move {
+        // connections are driven by this task, so they end when it's aborted
+        let mut connections = FuturesUnordered::new();
+        loop {
+            tokio::select! {
+                accepted = listener.accept() => {
+                    let mut incoming = match accepted {
+                        Ok((incoming, _)) => incoming,
+                        Err(_) => break,
+                    };
+                    let mut outgoing = match TcpStream::connect(target).await {
+                        Ok(outgoing) => outgoing,
+                        Err(_) => continue,
+                    };
+                    let (mut near, mut far) = sim_duplex(config);
+                    connections.push(async move {
+                        let _ = tokio::join!(
+                            tokio::io::copy_bidirectional(&mut incoming, &mut near),
+                            tokio::io::copy_bidirectional(&mut far, &mut outgoing),
+                        );
+                    });
+                },
+                Some(()) = connections.next(), if !connections.is_empty() => {},
+            }
+        }
+    })
+}
+
+/// Moves everything from `src` to `dst`, delayed according to `config`
+async fn link<R, W>(mut src: R, mut dst: W, config: SimConfig)
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin + Send + 'static,
+{
+    let (sender, mut receiver) = mpsc::unbounded_channel::<(Instant, Vec<u8>)>();
+    let writer = tokio::spawn(async move {
+        // segments are queued in delivery order
+        while let Some((deliver_at, segment)) = receiver.recv().await {
+            sleep_until(deliver_at).await;
+            if dst.write_all(&segment).await.is_err() {
+                return;
+            }
+        }
+        let _ = dst.shutdown().await;
+    });
+
+    let mut rng = StdRng::seed_from_u64(config.seed);
+    let mut delay = move || {
+        let jitter = if config.jitter.is_zero() {
+            Duration::ZERO
+        } else {
+            config.jitter.mul_f64(rng.gen::<f64>())
+        };
+        (config.latency + jitter, rng.gen_bool(config.loss))
+    };
+    let mut link_free_at = Instant::now();
+    let mut last_deliver_at = link_free_at;
+    let mut buffer = vec![0u8; SEGMENT_SIZE];
+    loop {
+        let len = match src.read(&mut buffer).await {
+            Ok(0) | Err(_) => break,
+            Ok(len) => len,
+        };
+        let now = Instant::now();
+        link_free_at = match config.bandwidth {
+            Some(bandwidth) => {
+                link_free_at.max(now) + Duration::from_secs_f64(len as f64 / bandwidth as f64)
+            },
+            None => now,
+        };
+        let (latency, mut lost) = delay();
+        let mut deliver_at = link_free_at + latency;
+        while lost {
+            // the sender notices the missing ack and retransmits
+            let (latency, lost_again) = delay();
+            deliver_at += config.latency * 2 + latency;
+            lost = lost_again;
+        }
+        deliver_at = deliver_at.max(last_deliver_at);
+        last_deliver_at = deliver_at;
+        if sender.send((deliver_at, buffer[..len].to_vec())).is_err() {
+            break;
+        }
+    }
+    drop(sender);
+    let _ = writer.await;
+}
@@ -1,10 +1,16 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 use tokio::runtime::Runtime;
-use veloren_network::{NetworkError, StreamError};
+use veloren_network::{
+    InitProtocolError, NetworkConnectError, NetworkError, NoiseKeys, NoisePublicKey, StreamError,
+};
 mod helper;
-use helper::{mpsc, network_participant_stream, quic, tcp, udp, SLEEP_EXTERNAL, SLEEP_INTERNAL};
+use helper::{
+    mpsc, network_participant_stream, network_participant_stream_sim, quic,
+    sim::{sim_duplex, sim_proxy, SimConfig},
+    tcp, udp, ws, SLEEP_EXTERNAL, SLEEP_INTERNAL,
+};
 use std::io::ErrorKind;
-use veloren_network::{ConnectAddr, ListenAddr, Network, Pid, Promises};
+use veloren_network::{ConnectAddr, ListenAddr, Network, Pid, Promises, StreamMetrics};
 
 #[test]
 fn stream_simple() {
@@ -16,6 +22,382 @@ fn stream_simple() {
     drop((_n_a, _n_b, _p_a, _p_b)); //clean teardown
 }
 
+#[test]
+fn stream_bandwidth_limit() {
+    let (_, _) = helper::setup(false, 0);
+    let (r, _n_a, p_a, mut s1_a, _n_b, _p_b, mut s1_b) = network_participant_stream(tcp());
+
+    p_a.set_bandwidth_limit(Some(50_000));
+    assert_eq!(p_a.bandwidth_limit(), Some(50_000));
+    let start = std::time::Instant::now();
+    for _ in 0..5 {
+        s1_a.send(vec![42u8; 10_000]).unwrap();
+    }
+    for _ in 0..5 {
+        assert_eq!(r.block_on(s1_b.recv::<Vec<u8>>()), Ok(vec![42u8; 10_000]));
+    }
+    // 50kB at 50kB/s, minus what fits in the initial burst
+    assert!(start.elapsed() >= std::time::Duration::from_millis(800));
+    drop((_n_a, _n_b, p_a, _p_b)); //clean teardown
+}
+
+#[test]
+fn stream_capacity_backpressure() {
+    const CAPACITY: usize = 8;
+    const MSG_SIZE: usize = 1_000;
+    let (_, _) = helper::setup(false, 0);
+    let r = Arc::new(Runtime::new().unwrap());
+    let (n_a, n_b, p_a, p_b, mut s1_a, mut s1_b) = r.block_on(async {
+        let n_a = Network::new(Pid::fake(0), &r);
+        let n_b = Network::new(Pid::fake(1), &r);
+        n_b.set_stream_capacity(CAPACITY);
+        let (listen, connect) = tcp();
+        n_a.listen(listen).await.unwrap();
+        let p_b = n_b.connect(connect).await.unwrap();
+        let p_a = n_a.connected().await.unwrap();
+        let s1_a = p_a.open(4, Promises::ORDERED, 0).await.unwrap();
+        let s1_b = p_b.opened().await.unwrap();
+        (n_a, n_b, p_a, p_b, s1_a, s1_b)
+    });
+
+    for i in 0..1_000 {
+        s1_a.send(vec![(i % 256) as u8; MSG_SIZE]).unwrap();
+    }
+    // the consumer stalls, only the buffered messages and the one waiting for a
+    // free slot are taken off the channel
+    std::thread::sleep(std::time::Duration::from_secs(1));
+    let bytes_received = p_b.stream_summary()[0].1.bytes_received;
+    assert!(bytes_received > 0);
+    assert!(bytes_received <= ((CAPACITY + 1) * (MSG_SIZE + 8)) as u64);
+
+    for i in 0..1_000 {
+        assert_eq!(
+            r.block_on(s1_b.recv::<Vec<u8>>()),
+            Ok(vec![(i % 256) as u8; MSG_SIZE])
+        );
+    }
+    drop((n_a, n_b, p_a, p_b)); //clean teardown
+}
+
+#[test]
+fn full_stream_doesnt_stall_other_channels() {
+    const CAPACITY: usize = 8;
+    let (_, _) = helper::setup(false, 0);
+    let r = Arc::new(Runtime::new().unwrap());
+    let (listen1, connect1) = tcp();
+    let (listen2, connect2) = tcp();
+    let (n_a, n_b, p_a, p_b, mut s1_a, mut s1_b, mut s2_a, mut s2_b) = r.block_on(async {
+        let n_a = Network::new(Pid::fake(0), &r);
+        let n_b = Network::new(Pid::fake(1), &r);
+        n_b.set_stream_capacity(CAPACITY);
+        n_a.listen(listen1).await.unwrap();
+        n_a.listen(listen2).await.unwrap();
+        let p_b = n_b.connect(connect1).await.unwrap();
+        let p_a = n_a.connected().await.unwrap();
+        n_b.open_channel(p_b.remote_pid(), connect2).await.unwrap();
+        tokio::time::sleep(SLEEP_INTERNAL / 10).await;
+        // streams are spread over both channels
+        let s1_a = p_a.open(4, Promises::ORDERED, 0).await.unwrap();
+        let s1_b = p_b.opened().await.unwrap();
+        let s2_a = p_a.open(4, Promises::ORDERED, 0).await.unwrap();
+        let s2_b = p_b.opened().await.unwrap();
+        (n_a, n_b, p_a, p_b, s1_a, s1_b, s2_a, s2_b)
+    });
+
+    // nobody reads the 1st stream, so its channel is no longer read
+    for i in 0..100u32 {
+        s1_a.send(i).unwrap();
+    }
+    std::thread::sleep(SLEEP_INTERNAL / 10);
+    s2_a.send("Hello World").unwrap();
+    let received = r.block_on(async {
+        tokio::time::timeout(Duration::from_secs(5), s2_b.recv::<String>()).await
+    });
+    assert_eq!(received, Ok(Ok("Hello World".to_string())));
+
+    for i in 0..100u32 {
+        assert_eq!(r.block_on(s1_b.recv()), Ok(i));
+    }
+    drop((n_a, n_b, p_a, p_b)); //clean teardown
+}
+
+#[test]
+fn multiple_channels_survive_closing_one() {
+    let (_, _) = helper::setup(false, 0);
+    let r = Arc::new(Runtime::new().unwrap());
+    let (listen1, connect1) = tcp();
+    let (listen2, connect2) = tcp();
+    let tcp_addr = |addr: &ConnectAddr| match addr {
+        ConnectAddr::Tcp(addr) => *addr,
+        _ => unreachable!(),
+    };
+    let target = tcp_addr(&connect2);
+    let proxy_addr = tcp_addr(&tcp().1);
+    let (n_a, n_b, p_a, p_b, proxy) = r.block_on(async {
+        let n_a = Network::new(Pid::fake(0), &r);
+        let n_b = Network::new(Pid::fake(1), &r);
+        n_a.listen(listen1).await.unwrap();
+        n_a.listen(listen2).await.unwrap();
+        // the 2nd channel runs through a bad link, so it can be cut off on its own
+        let proxy = sim_proxy(SimConfig::bad(), proxy_addr, target).await;
+        let p_b = n_b.connect(connect1).await.unwrap();
+        let p_a = n_a.connected().await.unwrap();
+        n_b.open_channel(p_b.remote_pid(), ConnectAddr::Tcp(proxy_addr))
+            .await
+            .unwrap();
+        // the 2nd channel doesn't create another participant
+        let another = tokio::time::timeout(SLEEP_INTERNAL / 10, n_a.connected()).await;
+        assert!(another.is_err());
+        assert!(matches!(
+            n_b.connect(connect2).await,
+            Err(NetworkError::ConnectFailed(
+                veloren_network::NetworkConnectError::AlreadyConnected(_)
+            ))
+        ));
+        (n_a, n_b, p_a, p_b, proxy)
+    });
+
+    let mut streams = r.block_on(async {
+        let mut streams = Vec::new();
+        for _ in 0..4 {
+            let s_b = p_b.open(4, Promises::ORDERED | Promises::GUARANTEED_DELIVERY, 0);
+            let s_b = s_b.await.unwrap();
+            let s_a = p_a.opened().await.unwrap();
+            streams.push((s_a, s_b));
+        }
+        streams
+    });
+    for (s_a, s_b) in streams.iter_mut() {
+        s_b.send("before").unwrap();
+        assert_eq!(r.block_on(s_a.recv()), Ok("before".to_string()));
+    }
+
+    proxy.abort();
+    std::thread::sleep(SLEEP_INTERNAL / 3);
+
+    // all streams continue on the remaining channel
+    for (s_a, s_b) in streams.iter_mut() {
+        s_b.send("after").unwrap();
+        s_a.send("reply").unwrap();
+        assert_eq!(r.block_on(s_a.recv()), Ok("after".to_string()));
+        assert_eq!(r.block_on(s_b.recv()), Ok("reply".to_string()));
+    }
+    drop((n_a, n_b, p_a, p_b)); //clean teardown
+}
+
+#[test]
+fn losing_a_channel_closes_streams_with_queued_messages() {
+    const MSG_SIZE: usize = 5_000;
+    let (_, _) = helper::setup(false, 0);
+    let r = Arc::new(Runtime::new().unwrap());
+    let (listen1, connect1) = tcp();
+    let (listen2, connect2) = tcp();
+    let tcp_addr = |addr: &ConnectAddr| match addr {
+        ConnectAddr::Tcp(addr) => *addr,
+        _ => unreachable!(),
+    };
+    let target = tcp_addr(&connect2);
+    let proxy_addr = tcp_addr(&tcp().1);
+    let (n_a, n_b, p_a, p_b, proxy) = r.block_on(async {
+        let n_a = Network::new(Pid::fake(0), &r);
+        let n_b = Network::new(Pid::fake(1), &r);
+        n_a.listen(listen1).await.unwrap();
+        n_a.listen(listen2).await.unwrap();
+        let proxy = sim_proxy(SimConfig::perfect(), proxy_addr, target).await;
+        let p_b = n_b.connect(connect1).await.unwrap();
+        let p_a = n_a.connected().await.unwrap();
+        n_b.open_channel(p_b.remote_pid(), ConnectAddr::Tcp(proxy_addr))
+            .await
+            .unwrap();
+        tokio::time::sleep(SLEEP_INTERNAL / 10).await;
+        (n_a, n_b, p_a, p_b, proxy)
+    });
+
+    // one stream on each channel
+    let mut streams = r.block_on(async {
+        let mut streams = Vec::new();
+        for _ in 0..2 {
+            let s_b = p_b.open(4, Promises::ORDERED | Promises::GUARANTEED_DELIVERY, 0);
+            let s_b = s_b.await.unwrap();
+            let s_a = p_a.opened().await.unwrap();
+            streams.push((s_a, s_b));
+        }
+        streams
+    });
+    // the messages stay queued for a while
+    p_b.set_bandwidth_limit(Some(20_000));
+    for (_, s_b) in streams.iter_mut() {
+        for _ in 0..5 {
+            s_b.send(vec![42u8; MSG_SIZE]).unwrap();
+        }
+    }
+    proxy.abort();
+
+    // the stream of the lost channel is closed on both sides
+    let closed = |p: &veloren_network::Participant| {
+        r.block_on(async { tokio::time::timeout(SLEEP_INTERNAL, p.closed()).await })
+    };
+    let sid = closed(&p_b).unwrap().unwrap();
+    assert_eq!(closed(&p_a).unwrap(), Ok(sid));
+    for (s_a, s_b) in streams.iter_mut() {
+        if s_b.sid() == sid {
+            assert_eq!(s_b.send("after"), Err(StreamError::StreamClosed));
+            // it ends after whatever made it through the channel
+            while r.block_on(s_a.recv::<Vec<u8>>()).is_ok() {}
+        } else {
+            // the other stream doesn't lose anything
+            for _ in 0..5 {
+                assert_eq!(r.block_on(s_a.recv::<Vec<u8>>()), Ok(vec![42u8; MSG_SIZE]));
+            }
+        }
+    }
+    drop((n_a, n_b, p_a, p_b)); //clean teardown
+}
+
+#[test]
+fn stream_summary() {
+    let (_, _) = helper::setup(false, 0);
+    let (r, _n_a, p_a, mut s1_a, _n_b, p_b, mut s1_b) = network_participant_stream(tcp());
+
+    for _ in 0..3 {
+        s1_a.send(vec![42u8; 1_000]).unwrap();
+    }
+    for _ in 0..3 {
+        assert_eq!(r.block_on(s1_b.recv::<Vec<u8>>()), Ok(vec![42u8; 1_000]));
+    }
+    let summary_a = p_a.stream_summary();
+    let summary_b = p_b.stream_summary();
+    assert_eq!(summary_a.len(), 1);
+    assert_eq!(summary_b.len(), 1);
+    let (sid_a, stream_a) = summary_a[0];
+    let (sid_b, stream_b) = summary_b[0];
+    assert_eq!(sid_a, sid_b);
+    assert_eq!((stream_b.prio, stream_b.promises), (4, Promises::ORDERED));
+    assert!(stream_a.bytes_sent >= 3_000);
+    assert_eq!(stream_a.bytes_sent, stream_b.bytes_received);
+    assert_eq!(stream_a.bytes_received, 0);
+    assert_eq!(stream_a.messages_sent, 3);
+    assert_eq!(stream_b.messages_received, 3);
+    assert_eq!(stream_a.closed, None);
+    assert_eq!(p_b.stream_metrics(sid_b), Some(stream_b));
+    let (sent, received) = stream_a.throughput_since(
+        &StreamMetrics {
+            bytes_sent: 0,
+            ..stream_a
+        },
+        Duration::from_secs(2),
+    );
+    assert_eq!(sent, stream_a.bytes_sent as f64 / 2.0);
+    assert_eq!(received, 0.0);
+
+    drop(s1_a);
+    std::thread::sleep(SLEEP_EXTERNAL);
+    assert!(p_a.stream_summary()[0].1.closed.is_some());
+    drop((_n_a, _n_b, p_a, p_b)); //clean teardown
+}
+
+#[test]
+fn participant_rtt() {
+    let (_, _) = helper::setup(false, 0);
+    let (_r, _n_a, p_a, _s1_a, _n_b, p_b, _s1_b) = network_participant_stream(tcp());
+
+    // the first flush pings, so nothing needs to be sent for a measurement
+    let start = std::time::Instant::now();
+    while (p_a.rtt().is_none() || p_b.rtt().is_none())
+        && start.elapsed() < std::time::Duration::from_secs(5)
+    {
+        std::thread::sleep(std::time::Duration::from_millis(5));
+    }
+    for rtt in [p_a.rtt(), p_b.rtt()] {
+        let rtt = rtt.expect("no rtt measured");
+        assert!(rtt > std::time::Duration::ZERO);
+        // loopback, but the test machine may be busy
+        assert!(rtt < std::time::Duration::from_secs(1), "{:?}", rtt);
+    }
+    drop((_n_a, _n_b, p_a, p_b)); //clean teardown
+}
+
+#[test]
+fn participant_rtt_udp() {
+    let (_, _) = helper::setup(false, 0);
+    let (_r, _n_a, p_a, _s1_a, _n_b, p_b, _s1_b) = network_participant_stream(udp());
+
+    let start = std::time::Instant::now();
+    while (p_a.rtt().is_none() || p_b.rtt().is_none())
+        && start.elapsed() < std::time::Duration::from_secs(5)
+    {
+        std::thread::sleep(std::time::Duration::from_millis(5));
+    }
+    for rtt in [p_a.rtt(), p_b.rtt()] {
+        let rtt = rtt.expect("no rtt measured");
+        assert!(rtt > std::time::Duration::ZERO);
+        assert!(rtt < std::time::Duration::from_secs(1), "{:?}", rtt);
+    }
+    drop((_n_a, _n_b, p_a, p_b)); //clean teardown
+}
+
+#[test]
+fn sim_link_delays_and_keeps_order() {
+    let (_, _) = helper::setup(false, 0);
+    let r = Runtime::new().unwrap();
+    r.block_on(async {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        let (mut a, mut b) = sim_duplex(SimConfig::bad());
+        let data = (0..100_000u32).map(|i| i as u8).collect::<Vec<_>>();
+        let start = std::time::Instant::now();
+        a.write_all(&data).await.unwrap();
+        a.shutdown().await.unwrap();
+        let mut received = Vec::new();
+        b.read_to_end(&mut received).await.unwrap();
+        assert_eq!(received, data);
+        assert!(start.elapsed() >= Duration::from_millis(100));
+    });
+}
+
+#[test]
+fn participant_rtt_bad_link() {
+    let (_, _) = helper::setup(false, 0);
+    let (_r, _n_a, p_a, _s1_a, _n_b, p_b, _s1_b) = network_participant_stream_sim(SimConfig::bad());
+
+    let start = std::time::Instant::now();
+    while (p_a.rtt().is_none() || p_b.rtt().is_none())
+        && start.elapsed() < std::time::Duration::from_secs(10)
+    {
+        std::thread::sleep(std::time::Duration::from_millis(5));
+    }
+    for rtt in [p_a.rtt(), p_b.rtt()] {
+        let rtt = rtt.expect("no rtt measured");
+        // 2 * 100ms latency, plus jitter and retransmissions
+        assert!(rtt >= std::time::Duration::from_millis(200), "{:?}", rtt);
+        assert!(rtt < std::time::Duration::from_secs(2), "{:?}", rtt);
+    }
+    drop((_n_a, _n_b, p_a, p_b)); //clean teardown
+}
+
+#[test]
+fn stream_reliable_over_bad_link() {
+    let (_, _) = helper::setup(false, 0);
+    let (r, _n_a, p_a, _s1_a, _n_b, p_b, _s1_b) = network_participant_stream_sim(SimConfig::bad());
+    let mut s_b = r
+        .block_on(p_b.open(4, Promises::ORDERED | Promises::GUARANTEED_DELIVERY, 0))
+        .unwrap();
+    let mut s_a = r.block_on(p_a.opened()).unwrap();
+
+    let start = std::time::Instant::now();
+    for i in 0..50u32 {
+        s_b.send((i, vec![i as u8; 10_000])).unwrap();
+    }
+    // everything arrives, in order, despite the lost segments
+    for i in 0..50u32 {
+        assert_eq!(
+            r.block_on(s_a.recv::<(u32, Vec<u8>)>()),
+            Ok((i, vec![i as u8; 10_000]))
+        );
+    }
+    assert!(start.elapsed() >= Duration::from_millis(100));
+    drop((_n_a, _n_b, p_a, p_b)); //clean teardown
+}
+
 #[test]
 fn stream_try_recv() {
     let (_, _) = helper::setup(false, 0);
@@ -75,6 +457,18 @@ fn stream_simple_quic() {
     drop((_n_a, _n_b, _p_a, _p_b)); //clean teardown
 }
 
+#[test]
+fn stream_simple_ws() {
+    let (_, _) = helper::setup(false, 0);
+    let (r, _n_a, _p_a, mut s1_a, _n_b, _p_b, mut s1_b) = network_participant_stream(ws());
+
+    s1_a.send("Hello World").unwrap();
+    s1_a.send(1337).unwrap();
+    assert_eq!(r.block_on(s1_b.recv()), Ok("Hello World".to_string()));
+    assert_eq!(r.block_on(s1_b.recv()), Ok(1337));
+    drop((_n_a, _n_b, _p_a, _p_b)); //clean teardown
+}
+
 #[test]
 fn stream_simple_quic_3msg() {
     let (_, _) = helper::setup(false, 0);
@@ -90,7 +484,31 @@ fn stream_simple_quic_3msg() {
 }
 
 #[test]
-#[ignore]
+fn stream_interleaved_quic() {
+    let (_, _) = helper::setup(false, 0);
+    let (r, _n_a, p_a, mut s1_a, _n_b, p_b, mut s1_b) = network_participant_stream(quic());
+    let (mut s2_a, mut s2_b) = r.block_on(async {
+        let s2_a = p_a
+            .open(5, Promises::ORDERED | Promises::CONSISTENCY, 0)
+            .await
+            .unwrap();
+        (s2_a, p_b.opened().await.unwrap())
+    });
+
+    // Each stream is a QUIC stream of its own, messages must not end up on the
+    // other one
+    for i in 0..10u32 {
+        s1_a.send(i).unwrap();
+        s2_a.send(format!("msg{}", i)).unwrap();
+    }
+    for i in 0..10u32 {
+        assert_eq!(r.block_on(s2_b.recv()), Ok(format!("msg{}", i)));
+        assert_eq!(r.block_on(s1_b.recv()), Ok(i));
+    }
+    drop((_n_a, _n_b, p_a, p_b)); //clean teardown
+}
+
+#[test]
 fn stream_simple_udp() {
     let (_, _) = helper::setup(false, 0);
     let (r, _n_a, _p_a, mut s1_a, _n_b, _p_b, mut s1_b) = network_participant_stream(udp());
@@ -101,7 +519,6 @@ fn stream_simple_udp() {
 }
 
 #[test]
-#[ignore]
 fn stream_simple_udp_3msg() {
     let (_, _) = helper::setup(false, 0);
     let (r, _n_a, _p_a, mut s1_a, _n_b, _p_b, mut s1_b) = network_participant_stream(udp());
@@ -116,7 +533,6 @@ fn stream_simple_udp_3msg() {
 }
 
 #[test]
-#[ignore]
 fn tcp_and_udp_2_connections() -> std::result::Result<(), Box<dyn std::error::Error>> {
     let (_, _) = helper::setup(false, 0);
     let r = Arc::new(Runtime::new().unwrap());
@@ -134,16 +550,21 @@ fn tcp_and_udp_2_connections() -> std::result::Result<(), Box<dyn std::error::Er
         let p1 = network
             .connect(ConnectAddr::Tcp("127.0.0.1:2000".parse().unwrap()))
             .await?;
+        // the udp connection becomes another channel of the same participant
         let p2 = network
             .connect(ConnectAddr::Udp("127.0.0.1:2001".parse().unwrap()))
-            .await?;
-        assert_eq!(&p1, &p2);
+            .await;
+        match p2 {
+            Err(NetworkError::ConnectFailed(NetworkConnectError::AlreadyConnected(pid))) => {
+                assert_eq!(pid, p1.remote_pid())
+            },
+            _ => panic!("udp didn't join the tcp participant"),
+        }
         Ok(())
     })
 }
 
 #[test]
-#[ignore]
 fn failed_listen_on_used_ports() -> std::result::Result<(), Box<dyn std::error::Error>> {
     let (_, _) = helper::setup(false, 0);
     let r = Arc::new(Runtime::new().unwrap());
@@ -232,6 +653,87 @@ fn api_stream_recv_main() -> std::result::Result<(), Box<dyn std::error::Error>>
     })
 }
 
+#[test]
+fn api_tcp_and_ws_on_same_listener() -> std::result::Result<(), Box<dyn std::error::Error>> {
+    let (_, _) = helper::setup(false, 0);
+    // Listen on Port `1240`, a Tcp and a WebSocket client connect to it
+    let r = Arc::new(Runtime::new().unwrap());
+    let network = Network::new(Pid::new(), &r);
+    let tcp_remote = Network::new(Pid::new(), &r);
+    let ws_remote = Network::new(Pid::new(), &r);
+    r.block_on(async {
+        let network = network;
+        network
+            .listen(ListenAddr::Tcp("127.0.0.1:1240".parse().unwrap()))
+            .await?;
+        let addr = "127.0.0.1:1240".parse().unwrap();
+        for (remote, addr) in [
+            (&tcp_remote, ConnectAddr::Tcp(addr)),
+            (&ws_remote, ConnectAddr::Ws(addr)),
+        ] {
+            let remote_p = remote.connect(addr).await?;
+            let mut stream_p = remote_p
+                .open(4, Promises::ORDERED | Promises::CONSISTENCY, 0)
+                .await?;
+            stream_p.send("Hello World")?;
+            let participant_a = network.connected().await?;
+            let mut stream_a = participant_a.opened().await?;
+            assert_eq!("Hello World".to_string(), stream_a.recv::<String>().await?);
+        }
+        Ok(())
+    })
+}
+
+#[test]
+fn encrypted_tcp() -> std::result::Result<(), Box<dyn std::error::Error>> {
+    let (_, _) = helper::setup(false, 0);
+    // Listen on Port `1250`, only encrypting clients are accepted
+    let r = Arc::new(Runtime::new().unwrap());
+    let network = Network::new(Pid::new(), &r);
+    let remote = Network::new(Pid::new(), &r);
+    let plain_remote = Network::new(Pid::new(), &r);
+    let misled_remote = Network::new(Pid::new(), &r);
+    let keys = NoiseKeys::default().with_private_key(NoiseKeys::generate_private_key());
+    let public = keys.public_key().unwrap();
+    network.set_encryption(Some(keys));
+    remote.set_encryption(Some(NoiseKeys::default().with_remote_key(public)));
+    misled_remote.set_encryption(Some(
+        NoiseKeys::default().with_remote_key(NoisePublicKey([7; 32])),
+    ));
+    r.block_on(async {
+        let network = network;
+        network
+            .listen(ListenAddr::Tcp("127.0.0.1:1250".parse().unwrap()))
+            .await?;
+        let addr = "127.0.0.1:1250".parse().unwrap();
+        let remote_p = remote.connect(ConnectAddr::Tcp(addr)).await?;
+        let mut stream_p = remote_p
+            .open(4, Promises::ORDERED | Promises::CONSISTENCY, 0)
+            .await?;
+        stream_p.send("Hello World")?;
+        let participant_a = network.connected().await?;
+        let mut stream_a = participant_a.opened().await?;
+        assert_eq!("Hello World".to_string(), stream_a.recv::<String>().await?);
+
+        match plain_remote.connect(ConnectAddr::Tcp(addr)).await {
+            Err(NetworkError::ConnectFailed(NetworkConnectError::Handshake(
+                InitProtocolError::EncryptionMismatch {
+                    local: false,
+                    remote: true,
+                },
+            ))) => (),
+            _ => panic!("a plaintext client must be rejected"),
+        }
+        match misled_remote.connect(ConnectAddr::Tcp(addr)).await {
+            Err(NetworkError::ConnectFailed(NetworkConnectError::Handshake(
+                InitProtocolError::UntrustedKey,
+            ))) => (),
+            _ => panic!("a client pinning another key must not connect"),
+        }
+        Ok(())
+    })
+}
+
 #[test]
 fn wrong_parse() {
     let (_, _) = helper::setup(false, 0);
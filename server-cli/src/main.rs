@@ -176,6 +176,11 @@ fn main() -> io::Result<()> {
                 cert_file_path: _,
                 key_file_path: _,
             } => ("QUIC", address),
+            Protocol::TcpTls {
+                address,
+                cert_file_path: _,
+                key_file_path: _,
+            } => ("TLS", address),
         });
 
     info!(
@@ -0,0 +1,212 @@
+//! Cooking pots and farm plots are entities without a body that hold the state
+//! of a sprite players tended to. They stay when their chunk unloads and are
+//! persisted, so that neither a dish nor a crop is lost to nobody being around.
+
+use atomicwrites::{AtomicFile, OverwriteBehavior};
+use common::{
+    comp::{CookingPot, FarmPlot, Pos},
+    resources::Time,
+};
+use common_state::State;
+use serde::{Deserialize, Serialize};
+use specs::{Builder, Join, World, WorldExt};
+use std::{
+    fs,
+    io::Write as _,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+use tracing::{error, info};
+use vek::*;
+
+const FILENAME: &str = "block_entities.ron";
+/// How often the block entities are written to disk, in case of a crash
+const AUTOSAVE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The block entities as they are written to disk
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SavedBlockEntities {
+    cooking_pots: Vec<(Vec3<i32>, CookingPot)>,
+    /// `Time` restarts from zero with the server, so `last_watered` is stored
+    /// relative to the time of saving
+    farm_plots: Vec<(Vec3<i32>, FarmPlot)>,
+}
+
+impl SavedBlockEntities {
+    fn is_empty(&self) -> bool { self.cooking_pots.is_empty() && self.farm_plots.is_empty() }
+}
+
+fn block_center(pos: Vec3<i32>) -> Pos { Pos(pos.map(|e| e as f32 + 0.5)) }
+
+pub struct BlockEntityPersistence {
+    path: PathBuf,
+    last_autosave: Instant,
+    /// Whether the file may hold block entities, so that servers that never
+    /// had any don't write it
+    has_saved: bool,
+}
+
+impl BlockEntityPersistence {
+    pub fn new(data_dir: &Path) -> Self {
+        let path = data_dir.join(FILENAME);
+        Self {
+            has_saved: path.exists(),
+            path,
+            last_autosave: Instant::now(),
+        }
+    }
+
+    /// Spawns the block entities that were saved when the server last stopped
+    pub fn load(&self, state: &mut State) {
+        let saved = match fs::read_to_string(&self.path) {
+            Ok(ron) => ron,
+            Err(_) => return,
+        };
+        let saved = match ron::de::from_str::<SavedBlockEntities>(&saved) {
+            Ok(saved) => saved,
+            Err(e) => {
+                let backup_path = self.path.with_extension("ron_backup");
+                error!(
+                    ?e,
+                    "Failed to load block entities, moving possibly corrupt data to {:?} for you \
+                     to repair.",
+                    backup_path
+                );
+                if let Err(e) = fs::rename(&self.path, backup_path) {
+                    error!(?e, "Failed to rename invalid block entities file");
+                }
+                return;
+            },
+        };
+
+        info!(
+            "Restoring {} cooking pots and {} farm plots",
+            saved.cooking_pots.len(),
+            saved.farm_plots.len()
+        );
+        let time = *state.ecs().read_resource::<Time>();
+        for (pos, cooking_pot) in saved.cooking_pots {
+            state
+                .ecs_mut()
+                .create_entity()
+                .with(block_center(pos))
+                .with(cooking_pot)
+                .build();
+        }
+        for (pos, mut farm_plot) in saved.farm_plots {
+            farm_plot.last_watered = farm_plot
+                .last_watered
+                .map(|watered| Time(time.0 + watered.0));
+            state
+                .ecs_mut()
+                .create_entity()
+                .with(block_center(pos))
+                .with(farm_plot)
+                .build();
+        }
+    }
+
+    /// Writes the block entities to disk every once in a while
+    pub fn maintain(&mut self, ecs: &World) {
+        if self.last_autosave.elapsed() >= AUTOSAVE_INTERVAL {
+            self.last_autosave = Instant::now();
+            self.save(ecs);
+        }
+    }
+
+    pub fn save(&mut self, ecs: &World) {
+        let time = ecs.read_resource::<Time>();
+        let positions = ecs.read_storage::<Pos>();
+        let block_pos = |pos: &Pos| pos.0.map(|e| e.floor() as i32);
+        let saved = SavedBlockEntities {
+            cooking_pots: (&ecs.read_storage::<CookingPot>(), &positions)
+                .join()
+                .map(|(cooking_pot, pos)| (block_pos(pos), cooking_pot.clone()))
+                .collect(),
+            farm_plots: (&ecs.read_storage::<FarmPlot>(), &positions)
+                .join()
+                .map(|(farm_plot, pos)| {
+                    let mut farm_plot = farm_plot.clone();
+                    farm_plot.last_watered = farm_plot
+                        .last_watered
+                        .map(|watered| Time(watered.0 - time.0));
+                    (block_pos(pos), farm_plot)
+                })
+                .collect(),
+        };
+        if saved.is_empty() && !self.has_saved {
+            return;
+        }
+
+        let ron = match ron::ser::to_string_pretty(&saved, ron::ser::PrettyConfig::default()) {
+            Ok(ron) => ron,
+            Err(e) => {
+                error!(?e, "Failed to serialize block entities");
+                return;
+            },
+        };
+        let file = AtomicFile::new(&self.path, OverwriteBehavior::AllowOverwrite);
+        match file.write(|file| file.write_all(ron.as_bytes())) {
+            Ok(()) => self.has_saved = !saved.is_empty(),
+            Err(e) => error!(?e, "Failed to write block entities to file"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::comp::CropKind;
+
+    fn spawn(state: &mut State, pos: Vec3<i32>, component: impl specs::Component + Send + Sync) {
+        state
+            .ecs_mut()
+            .create_entity()
+            .with(block_center(pos))
+            .with(component)
+            .build();
+    }
+
+    #[test]
+    fn block_entities_survive_restart() {
+        let data_dir =
+            std::env::temp_dir().join(format!("veloren-block-entities-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&data_dir);
+        fs::create_dir_all(&data_dir).unwrap();
+
+        let mut state = State::server();
+        spawn(&mut state, Vec3::new(1, -2, 3), CookingPot {
+            fuel_remaining: 42.0,
+            recipe: Some("fish_cooked".to_owned()),
+            ..Default::default()
+        });
+        let mut plot = FarmPlot::new(CropKind::Carrot);
+        plot.water(Time(60.0));
+        spawn(&mut state, Vec3::new(4, 5, 6), plot);
+        state.ecs_mut().insert(Time(100.0));
+        BlockEntityPersistence::new(&data_dir).save(state.ecs());
+
+        // Time starts over after the restart
+        let mut state = State::server();
+        BlockEntityPersistence::new(&data_dir).load(&mut state);
+        let ecs = state.ecs();
+        let positions = ecs.read_storage::<Pos>();
+        let pots = (&ecs.read_storage::<CookingPot>(), &positions)
+            .join()
+            .map(|(pot, pos)| (pot.clone(), pos.0))
+            .collect::<Vec<_>>();
+        assert_eq!(pots.len(), 1);
+        assert_eq!(pots[0].0.fuel_remaining, 42.0);
+        assert_eq!(pots[0].0.recipe.as_deref(), Some("fish_cooked"));
+        assert_eq!(pots[0].1, Vec3::new(1.5, -1.5, 3.5));
+        let plots = ecs
+            .read_storage::<FarmPlot>()
+            .join()
+            .cloned()
+            .collect::<Vec<_>>();
+        assert_eq!(plots.len(), 1);
+        assert_eq!(plots[0].last_watered, Some(Time(-40.0)));
+
+        let _ = fs::remove_dir_all(&data_dir);
+    }
+}
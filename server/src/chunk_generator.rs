@@ -1,15 +1,31 @@
+//! Chunks are generated by a limited number of workers. Requests wait in a
+//! queue until a worker is free, the chunks closest to players and wanted by
+//! most of them go first. A chunk only depends on the world seed and its key,
+//! so the order in which the workers finish doesn't change the world.
+//!
+//! After a teleport all chunks in view distance are requested at once, the
+//! queue makes sure the ones around the player are there first.
+
 use crate::metrics::ChunkGenMetrics;
 #[cfg(not(feature = "worldgen"))]
 use crate::test_world::{IndexOwned, World};
 use common::{
-    calendar::Calendar, generation::ChunkSupplement, resources::TimeOfDay, slowjob::SlowJobPool,
-    terrain::TerrainChunk,
+    calendar::Calendar,
+    generation::ChunkSupplement,
+    resources::TimeOfDay,
+    slowjob::SlowJobPool,
+    terrain::{erosion::ErosionSimulator, TerrainChunk},
 };
 use hashbrown::{hash_map::Entry, HashMap};
 use specs::Entity as EcsEntity;
-use std::sync::{
-    atomic::{AtomicBool, Ordering},
-    Arc,
+use std::{
+    cmp::Reverse,
+    collections::BinaryHeap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Instant,
 };
 use vek::*;
 #[cfg(feature = "worldgen")]
@@ -20,56 +36,206 @@ type ChunkGenResult = (
     Result<(TerrainChunk, ChunkSupplement), Option<EcsEntity>>,
 );
 
+/// How urgently a chunk is needed, by its distance to the closest player
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ChunkPriority {
+    /// Within 2 chunks, players can walk into it any moment
+    High,
+    Normal,
+    /// Further than 8 chunks away
+    Low,
+}
+
+impl ChunkPriority {
+    pub fn from_dist_sqr(dist_sqr: u32) -> Self {
+        if dist_sqr <= 2u32.pow(2) {
+            Self::High
+        } else if dist_sqr <= 8u32.pow(2) {
+            Self::Normal
+        } else {
+            Self::Low
+        }
+    }
+}
+
+/// Lower goes first
+type Priority = (ChunkPriority, u32, Reverse<usize>, i32, i32);
+
+/// Chunk waiting for a free worker
+struct QueuedChunk {
+    /// Players that asked for the chunk, the first one is told when
+    /// generation fails
+    requesters: Vec<EcsEntity>,
+    /// Squared distance in chunks to the closest requester
+    dist_sqr: u32,
+}
+
+impl QueuedChunk {
+    fn priority(&self, key: Vec2<i32>) -> Priority {
+        // The key breaks ties, so that the order doesn't depend on the hasher
+        (
+            ChunkPriority::from_dist_sqr(self.dist_sqr),
+            self.dist_sqr,
+            Reverse(self.requesters.len()),
+            key.x,
+            key.y,
+        )
+    }
+}
+
 pub struct ChunkGenerator {
     chunk_tx: crossbeam_channel::Sender<ChunkGenResult>,
     chunk_rx: crossbeam_channel::Receiver<ChunkGenResult>,
+    queued_chunks: HashMap<Vec2<i32>, QueuedChunk>,
+    /// Priorities of the queued chunks, with outdated entries for chunks that
+    /// were cancelled or moved up since. Those are skipped when popped.
+    queue: BinaryHeap<Reverse<Priority>>,
+    /// Chunks handed to a worker, with the flag cancelling their generation
     pending_chunks: HashMap<Vec2<i32>, Arc<AtomicBool>>,
     metrics: Arc<ChunkGenMetrics>,
+    erosion_iterations: u32,
+    /// Chunks generated at the same time at most
+    workers: usize,
 }
 impl ChunkGenerator {
-    pub fn new(metrics: ChunkGenMetrics) -> Self {
+    pub fn new(metrics: ChunkGenMetrics, erosion_iterations: u32, workers: usize) -> Self {
         let (chunk_tx, chunk_rx) = crossbeam_channel::unbounded();
         Self {
             chunk_tx,
             chunk_rx,
+            queued_chunks: HashMap::new(),
+            queue: BinaryHeap::new(),
             pending_chunks: HashMap::new(),
             metrics: Arc::new(metrics),
+            erosion_iterations,
+            workers: workers.max(1),
+        }
+    }
+
+    /// Queues the chunk at `key` for generation. `dist_sqr` is the squared
+    /// distance in chunks between the requester and the chunk, requests
+    /// without a requester should pass 0 to go first.
+    pub fn generate_chunk(&mut self, entity: Option<EcsEntity>, key: Vec2<i32>, dist_sqr: u32) {
+        if self.pending_chunks.contains_key(&key) {
+            return;
+        }
+        let (queued, old_priority) = match self.queued_chunks.entry(key) {
+            Entry::Occupied(o) => {
+                let queued = o.into_mut();
+                let priority = queued.priority(key);
+                (queued, Some(priority))
+            },
+            Entry::Vacant(v) => {
+                self.metrics.chunks_requested.inc();
+                self.metrics.chunks_queued.inc();
+                let queued = v.insert(QueuedChunk {
+                    requesters: Vec::new(),
+                    dist_sqr,
+                });
+                (queued, None)
+            },
+        };
+        queued.dist_sqr = queued.dist_sqr.min(dist_sqr);
+        if let Some(entity) = entity {
+            if !queued.requesters.contains(&entity) {
+                queued.requesters.push(entity);
+            }
         }
+        let priority = queued.priority(key);
+        if old_priority != Some(priority) {
+            self.queue.push(Reverse(priority));
+        }
+        self.update_pending_metric();
     }
 
-    pub fn generate_chunk(
+    /// Hands the queued chunks with the highest priority to the free workers
+    pub fn dispatch(
         &mut self,
-        entity: Option<EcsEntity>,
-        key: Vec2<i32>,
         slowjob_pool: &SlowJobPool,
-        world: Arc<World>,
-        index: IndexOwned,
+        world: &Arc<World>,
+        index: &IndexOwned,
         time: (TimeOfDay, Calendar),
     ) {
-        let v = if let Entry::Vacant(v) = self.pending_chunks.entry(key) {
-            v
-        } else {
-            return;
-        };
-        let cancel = Arc::new(AtomicBool::new(false));
-        v.insert(Arc::clone(&cancel));
-        let chunk_tx = self.chunk_tx.clone();
-        self.metrics.chunks_requested.inc();
-        slowjob_pool.spawn("CHUNK_GENERATOR", move || {
-            let index = index.as_index_ref();
-            let payload = world
-                .generate_chunk(index, key, || cancel.load(Ordering::Relaxed), Some(time))
-                .map_err(|_| entity);
-            let _ = chunk_tx.send((key, payload));
+        let erosion_iterations = self.erosion_iterations;
+        self.dispatch_with(|key, entity, cancel, chunk_tx, metrics| {
+            let world = Arc::clone(world);
+            let index = index.clone();
+            let time = time.clone();
+            slowjob_pool.spawn("CHUNK_GENERATOR", move || {
+                let index = index.as_index_ref();
+                let start = Instant::now();
+                let payload = world
+                    .generate_chunk(index, key, || cancel.load(Ordering::Relaxed), Some(time))
+                    .map(|(mut chunk, supplement)| {
+                        // Runs before persisted terrain changes are applied to the chunk
+                        ErosionSimulator::simulate(&mut chunk, erosion_iterations);
+                        (chunk, supplement)
+                    })
+                    .map_err(|_| entity);
+                if payload.is_ok() {
+                    metrics
+                        .chunk_generation_time
+                        .observe(start.elapsed().as_secs_f64());
+                }
+                let _ = chunk_tx.send((key, payload));
+            });
         });
     }
 
+    /// Calls `spawn` for the queued chunks that fit on the free workers, in
+    /// order of priority. The spawned job sends its result on the given
+    /// channel.
+    fn dispatch_with(
+        &mut self,
+        mut spawn: impl FnMut(
+            Vec2<i32>,
+            Option<EcsEntity>,
+            Arc<AtomicBool>,
+            crossbeam_channel::Sender<ChunkGenResult>,
+            Arc<ChunkGenMetrics>,
+        ),
+    ) {
+        // Outdated entries pile up when chunks are cancelled faster than
+        // they are generated
+        if self.queue.len() > 2 * self.queued_chunks.len() + 64 {
+            self.queue = self
+                .queued_chunks
+                .iter()
+                .map(|(key, queued)| Reverse(queued.priority(*key)))
+                .collect();
+        }
+        let mut free = self.workers.saturating_sub(self.pending_chunks.len());
+        while free > 0 {
+            let priority = match self.queue.pop() {
+                Some(Reverse(priority)) => priority,
+                None => break,
+            };
+            let key = Vec2::new(priority.3, priority.4);
+            let queued = match self.queued_chunks.entry(key) {
+                Entry::Occupied(o) if o.get().priority(key) == priority => o.remove(),
+                _ => continue,
+            };
+            self.metrics.chunks_queued.dec();
+            let cancel = Arc::new(AtomicBool::new(false));
+            self.pending_chunks.insert(key, Arc::clone(&cancel));
+            spawn(
+                key,
+                queued.requesters.first().copied(),
+                cancel,
+                self.chunk_tx.clone(),
+                Arc::clone(&self.metrics),
+            );
+            free -= 1;
+        }
+    }
+
     pub fn recv_new_chunk(&mut self) -> Option<ChunkGenResult> {
         // Make sure chunk wasn't cancelled and if it was check to see if there are more
         // chunks to receive
         while let Ok((key, res)) = self.chunk_rx.try_recv() {
             if self.pending_chunks.remove(&key).is_some() {
                 self.metrics.chunks_served.inc();
+                self.update_pending_metric();
                 // TODO: do anything else if res is an Err?
                 return Some((key, res));
             }
@@ -78,22 +244,161 @@ impl ChunkGenerator {
         None
     }
 
+    /// Chunks that are queued or being generated
     pub fn pending_chunks(&self) -> impl Iterator<Item = Vec2<i32>> + '_ {
-        self.pending_chunks.keys().copied()
+        self.queued_chunks
+            .keys()
+            .chain(self.pending_chunks.keys())
+            .copied()
     }
 
     pub fn cancel_if_pending(&mut self, key: Vec2<i32>) {
-        if let Some(cancel) = self.pending_chunks.remove(&key) {
+        if self.queued_chunks.remove(&key).is_some() {
+            self.metrics.chunks_queued.dec();
+            self.metrics.chunks_canceled.inc();
+        } else if let Some(cancel) = self.pending_chunks.remove(&key) {
             cancel.store(true, Ordering::Relaxed);
             self.metrics.chunks_canceled.inc();
         }
+        self.update_pending_metric();
     }
 
     pub fn cancel_all(&mut self) {
         let metrics = Arc::clone(&self.metrics);
+        self.queued_chunks.drain().for_each(|_| {
+            metrics.chunks_queued.dec();
+            metrics.chunks_canceled.inc();
+        });
         self.pending_chunks.drain().for_each(|(_, cancel)| {
             cancel.store(true, Ordering::Relaxed);
             metrics.chunks_canceled.inc();
         });
+        self.queue.clear();
+        self.update_pending_metric();
+    }
+
+    fn update_pending_metric(&self) {
+        self.metrics
+            .chunks_pending
+            .set((self.queued_chunks.len() + self.pending_chunks.len()) as i64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hashbrown::HashSet;
+    use prometheus::Registry;
+    use specs::{Builder, WorldExt};
+
+    fn generator(workers: usize) -> ChunkGenerator {
+        let metrics = ChunkGenMetrics::new(&Registry::new()).unwrap();
+        ChunkGenerator::new(metrics, 0, workers)
+    }
+
+    /// Runs the workers until nothing is left, returns the keys in the order
+    /// their generation started
+    fn run(generator: &mut ChunkGenerator) -> Vec<Vec2<i32>> {
+        let mut generated = Vec::new();
+        while generator.pending_chunks().next().is_some() {
+            generator.dispatch_with(|key, entity, _, chunk_tx, _| {
+                generated.push(key);
+                // Stands in for the chunk, the tests don't need one
+                let _ = chunk_tx.send((key, Err(entity)));
+            });
+            while generator.recv_new_chunk().is_some() {}
+        }
+        generated
+    }
+
+    #[test]
+    fn cancelled_chunks_are_not_generated() {
+        let mut generator = generator(8);
+        let keys = (0..500)
+            .map(|i| Vec2::new(i % 25, i / 25))
+            .collect::<Vec<_>>();
+        for key in &keys {
+            generator.generate_chunk(None, *key, 0);
+        }
+        for key in keys.iter().step_by(2) {
+            generator.cancel_if_pending(*key);
+        }
+        assert_eq!(generator.metrics.chunks_queued.get(), 250);
+        assert_eq!(generator.metrics.chunks_canceled.get(), 250);
+
+        let generated = run(&mut generator);
+        assert_eq!(generated.len(), 250);
+        let generated = generated.into_iter().collect::<HashSet<_>>();
+        let expected = keys.into_iter().skip(1).step_by(2).collect::<HashSet<_>>();
+        assert_eq!(generated, expected);
+        assert_eq!(generator.metrics.chunks_queued.get(), 0);
+    }
+
+    #[test]
+    fn closest_and_most_wanted_chunks_go_first() {
+        let mut generator = generator(1);
+        let mut world = specs::World::new();
+        let players = (0..2)
+            .map(|_| world.create_entity().build())
+            .collect::<Vec<_>>();
+        generator.generate_chunk(Some(players[0]), Vec2::new(5, 0), 25);
+        generator.generate_chunk(Some(players[0]), Vec2::new(1, 0), 1);
+        generator.generate_chunk(Some(players[0]), Vec2::new(0, 1), 1);
+        generator.generate_chunk(Some(players[1]), Vec2::new(0, 1), 1);
+        // Repeated requests by the same player count once
+        generator.generate_chunk(Some(players[0]), Vec2::new(1, 0), 1);
+        // A closer requester moves the chunk up
+        generator.generate_chunk(Some(players[1]), Vec2::new(9, 0), 0);
+        generator.generate_chunk(Some(players[1]), Vec2::new(9, 0), 81);
+
+        assert_eq!(run(&mut generator), [
+            Vec2::new(9, 0),
+            Vec2::new(0, 1),
+            Vec2::new(1, 0),
+            Vec2::new(5, 0),
+        ]);
+    }
+
+    #[test]
+    fn priority_by_distance() {
+        assert_eq!(ChunkPriority::from_dist_sqr(0), ChunkPriority::High);
+        assert_eq!(ChunkPriority::from_dist_sqr(4), ChunkPriority::High);
+        assert_eq!(ChunkPriority::from_dist_sqr(5), ChunkPriority::Normal);
+        assert_eq!(ChunkPriority::from_dist_sqr(64), ChunkPriority::Normal);
+        assert_eq!(ChunkPriority::from_dist_sqr(65), ChunkPriority::Low);
+    }
+
+    #[test]
+    fn requeued_chunks_are_generated_once() {
+        let mut generator = generator(2);
+        for x in 0..5 {
+            generator.generate_chunk(None, Vec2::new(x, 0), 0);
+        }
+        // Leaves an outdated entry in the queue
+        generator.cancel_if_pending(Vec2::new(0, 0));
+        assert_eq!(generator.metrics.chunks_pending.get(), 4);
+        generator.generate_chunk(None, Vec2::new(0, 0), 0);
+        assert_eq!(generator.metrics.chunks_pending.get(), 5);
+
+        let generated = run(&mut generator);
+        assert_eq!(
+            generated,
+            (0..5).map(|x| Vec2::new(x, 0)).collect::<Vec<_>>()
+        );
+        assert_eq!(generator.metrics.chunks_pending.get(), 0);
+    }
+
+    #[test]
+    fn workers_limit_chunks_in_flight() {
+        let mut generator = generator(3);
+        for x in 0..10 {
+            generator.generate_chunk(None, Vec2::new(x, 0), 0);
+        }
+        let mut started = 0;
+        generator.dispatch_with(|_, _, _, _, _| started += 1);
+        assert_eq!(started, 3);
+        generator.dispatch_with(|_, _, _, _, _| started += 1);
+        assert_eq!(started, 3);
+        assert_eq!(generator.pending_chunks().count(), 10);
     }
 }
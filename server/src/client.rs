@@ -1,9 +1,13 @@
-use common_net::msg::{ClientType, ServerGeneral, ServerMsg};
+use common_net::{
+    msg::{ClientType, ConnectionStats, MessageBatch, PingClock, ServerGeneral, ServerMsg},
+    sync::PhysicsBaselines,
+};
 use network::{Message, Participant, Stream, StreamError, StreamParams};
 use serde::{de::DeserializeOwned, Serialize};
 use specs::Component;
 use specs_idvs::IdvStorage;
 use std::sync::{atomic::AtomicBool, Mutex};
+use tracing::debug;
 
 /// Client handles ALL network related information of everything that connects
 /// to the server Client DOES NOT handle game states
@@ -15,7 +19,15 @@ pub struct Client {
     pub client_type: ClientType,
     pub participant: Option<Participant>,
     pub last_ping: Mutex<f64>,
+    /// Timestamps the pings to this client and the answers to its pings
+    pub ping_clock: PingClock,
+    /// Round trip time of the pings to this client, `None` until the first
+    /// pong arrived
+    pub connection_stats: Mutex<Option<ConnectionStats>>,
     pub login_msg_sent: AtomicBool,
+    /// Physics states last sent for each entity, later updates are encoded
+    /// against them
+    pub physics_baselines: Mutex<PhysicsBaselines>,
 
     //TODO: improve network crate so that `send` is no longer `&mut self` and we can get rid of
     // this Mutex. This Mutex is just to please the compiler as we do not get into contention
@@ -26,17 +38,28 @@ pub struct Client {
     in_game_stream: Mutex<Stream>,
     terrain_stream: Mutex<Stream>,
 
-    general_stream_params: StreamParams,
     ping_stream_params: StreamParams,
     register_stream_params: StreamParams,
     character_screen_stream_params: StreamParams,
-    in_game_stream_params: StreamParams,
     terrain_stream_params: StreamParams,
+
+    /// Messages for the in-game and general streams, sent at the start and end
+    /// of the tick with [`Client::flush_batches`]
+    in_game_batch: Mutex<MessageBatch>,
+    general_batch: Mutex<MessageBatch>,
+    /// A batch is sent early when it would grow beyond this
+    max_batch_bytes: usize,
 }
 
 pub struct PreparedMsg {
     stream_id: u8,
-    message: Message,
+    message: PreparedPayload,
+}
+
+enum PreparedPayload {
+    Message(Message),
+    /// Encoded for a [`MessageBatch`]
+    Batched(Vec<u8>),
 }
 
 impl Component for Client {
@@ -54,30 +77,33 @@ impl Client {
         character_screen_stream: Stream,
         in_game_stream: Stream,
         terrain_stream: Stream,
+        max_batch_bytes: usize,
     ) -> Self {
-        let general_stream_params = general_stream.params();
         let ping_stream_params = ping_stream.params();
         let register_stream_params = register_stream.params();
         let character_screen_stream_params = character_screen_stream.params();
-        let in_game_stream_params = in_game_stream.params();
         let terrain_stream_params = terrain_stream.params();
         Client {
             client_type,
             participant: Some(participant),
             last_ping: Mutex::new(last_ping),
+            ping_clock: PingClock::new(),
+            connection_stats: Mutex::new(None),
             login_msg_sent: AtomicBool::new(false),
+            physics_baselines: Mutex::default(),
             general_stream: Mutex::new(general_stream),
             ping_stream: Mutex::new(ping_stream),
             register_stream: Mutex::new(register_stream),
             character_screen_stream: Mutex::new(character_screen_stream),
             in_game_stream: Mutex::new(in_game_stream),
             terrain_stream: Mutex::new(terrain_stream),
-            general_stream_params,
             ping_stream_params,
             register_stream_params,
             character_screen_stream_params,
-            in_game_stream_params,
             terrain_stream_params,
+            in_game_batch: Mutex::default(),
+            general_batch: Mutex::default(),
+            max_batch_bytes,
         }
     }
 
@@ -96,13 +122,16 @@ impl Client {
                     ServerGeneral::CharacterDataLoadError(_)
                     | ServerGeneral::CharacterListUpdate(_)
                     | ServerGeneral::CharacterActionError(_)
+                    | ServerGeneral::CharacterCreationError(_)
                     | ServerGeneral::CharacterCreated(_)
                     | ServerGeneral::CharacterEdited(_)
-                    | ServerGeneral::CharacterSuccess => {
+                    | ServerGeneral::CharacterSuccess
+                    | ServerGeneral::Rules(_) => {
                         self.character_screen_stream.lock().unwrap().send(g)
                     },
                     //Ingame related
                     ServerGeneral::GroupUpdate(_)
+                    | ServerGeneral::GuildUpdate(_)
                     | ServerGeneral::Invite { .. }
                     | ServerGeneral::InvitePending(_)
                     | ServerGeneral::InviteComplete { .. }
@@ -112,6 +141,10 @@ impl Client {
                     | ServerGeneral::SiteEconomy(_)
                     | ServerGeneral::Outcomes(_)
                     | ServerGeneral::Knockback(_)
+                    | ServerGeneral::FishingUpdate { .. }
+                    | ServerGeneral::CookingUpdate(..)
+                    | ServerGeneral::EntityAnimation { .. }
+                    | ServerGeneral::Gravestones(_)
                     | ServerGeneral::UpdatePendingTrade(_, _, _)
                     | ServerGeneral::FinishedTrade(_) => {
                         self.in_game_stream.lock().unwrap().send(g)
@@ -127,12 +160,19 @@ impl Client {
                     | ServerGeneral::ChatMode(_)
                     | ServerGeneral::SetPlayerEntity(_)
                     | ServerGeneral::TimeOfDay(_, _)
+                    | ServerGeneral::GameTime(_)
                     | ServerGeneral::EntitySync(_)
                     | ServerGeneral::CompSync(_)
+                    | ServerGeneral::SkillSetDelta(_)
+                    | ServerGeneral::EntityPhysicsDelta(_)
                     | ServerGeneral::CreateEntity(_)
                     | ServerGeneral::DeleteEntity(_)
                     | ServerGeneral::Disconnect(_)
-                    | ServerGeneral::Notification(_) => self.general_stream.lock().unwrap().send(g),
+                    | ServerGeneral::Notification(_)
+                    | ServerGeneral::FriendStatus { .. }
+                    | ServerGeneral::FriendRemoved(_) => {
+                        self.general_stream.lock().unwrap().send(g)
+                    },
                 }
             },
             ServerMsg::Ping(m) => self.ping_stream.lock().unwrap().send(m),
@@ -142,21 +182,67 @@ impl Client {
     pub(crate) fn send_fallible<M: Into<ServerMsg>>(&self, msg: M) { let _ = self.send(msg); }
 
     pub(crate) fn send_prepared(&self, msg: &PreparedMsg) -> Result<(), StreamError> {
+        let message = match &msg.message {
+            PreparedPayload::Message(message) => message,
+            PreparedPayload::Batched(encoded) => {
+                let (batch, stream) = match msg.stream_id {
+                    2 => (&self.in_game_batch, &self.in_game_stream),
+                    3 => (&self.general_batch, &self.general_stream),
+                    _ => unreachable!("stream is not batched"),
+                };
+                return self.push_to_batch(batch, stream, encoded);
+            },
+        };
         match msg.stream_id {
-            0 => self.register_stream.lock().unwrap().send_raw(&msg.message),
+            0 => self.register_stream.lock().unwrap().send_raw(message),
             1 => self
                 .character_screen_stream
                 .lock()
                 .unwrap()
-                .send_raw(&msg.message),
-            2 => self.in_game_stream.lock().unwrap().send_raw(&msg.message),
-            3 => self.general_stream.lock().unwrap().send_raw(&msg.message),
-            4 => self.ping_stream.lock().unwrap().send_raw(&msg.message),
-            5 => self.terrain_stream.lock().unwrap().send_raw(&msg.message),
+                .send_raw(message),
+            4 => self.ping_stream.lock().unwrap().send_raw(message),
+            5 => self.terrain_stream.lock().unwrap().send_raw(message),
             _ => unreachable!("invalid stream id"),
         }
     }
 
+    /// Fails right away if the stream is closed, like an unbatched message
+    fn push_to_batch(
+        &self,
+        batch: &Mutex<MessageBatch>,
+        stream: &Mutex<Stream>,
+        encoded: &[u8],
+    ) -> Result<(), StreamError> {
+        if stream.lock().unwrap().is_closed() {
+            return Err(StreamError::StreamClosed);
+        }
+        let mut batch = batch.lock().unwrap();
+        let mut result = Ok(());
+        if !batch.is_empty() && batch.byte_len() + encoded.len() > self.max_batch_bytes {
+            result = stream.lock().unwrap().send(std::mem::take(&mut *batch));
+        }
+        if !batch.push_encoded(encoded) {
+            result = result.and(stream.lock().unwrap().send(std::mem::take(&mut *batch)));
+            // Can't fail on an empty batch
+            let _ = batch.push_encoded(encoded);
+        }
+        result
+    }
+
+    /// Sends the batched messages, called at the start and end of every tick
+    pub(crate) fn flush_batches(&self) -> Result<(), StreamError> {
+        let flush = |batch: &Mutex<MessageBatch>, stream: &Mutex<Stream>| {
+            let batch = std::mem::take(&mut *batch.lock().unwrap());
+            if batch.is_empty() {
+                Ok(())
+            } else {
+                stream.lock().unwrap().send(batch)
+            }
+        };
+        flush(&self.in_game_batch, &self.in_game_stream)
+            .and(flush(&self.general_batch, &self.general_stream))
+    }
+
     pub(crate) fn prepare<M: Into<ServerMsg>>(&self, msg: M) -> PreparedMsg {
         match msg.into() {
             ServerMsg::Info(m) => PreparedMsg::new(0, &m, &self.register_stream_params),
@@ -168,13 +254,16 @@ impl Client {
                     ServerGeneral::CharacterDataLoadError(_)
                     | ServerGeneral::CharacterListUpdate(_)
                     | ServerGeneral::CharacterActionError(_)
+                    | ServerGeneral::CharacterCreationError(_)
                     | ServerGeneral::CharacterCreated(_)
                     | ServerGeneral::CharacterEdited(_)
-                    | ServerGeneral::CharacterSuccess => {
+                    | ServerGeneral::CharacterSuccess
+                    | ServerGeneral::Rules(_) => {
                         PreparedMsg::new(1, &g, &self.character_screen_stream_params)
                     },
                     //Ingame related
                     ServerGeneral::GroupUpdate(_)
+                    | ServerGeneral::GuildUpdate(_)
                     | ServerGeneral::Invite { .. }
                     | ServerGeneral::InvitePending(_)
                     | ServerGeneral::InviteComplete { .. }
@@ -183,12 +272,14 @@ impl Client {
                     | ServerGeneral::SetViewDistance(_)
                     | ServerGeneral::Outcomes(_)
                     | ServerGeneral::Knockback(_)
+                    | ServerGeneral::FishingUpdate { .. }
+                    | ServerGeneral::CookingUpdate(..)
+                    | ServerGeneral::EntityAnimation { .. }
+                    | ServerGeneral::Gravestones(_)
                     | ServerGeneral::SiteEconomy(_)
                     | ServerGeneral::UpdatePendingTrade(_, _, _)
                     | ServerGeneral::FinishedTrade(_)
-                    | ServerGeneral::MapMarker(_) => {
-                        PreparedMsg::new(2, &g, &self.in_game_stream_params)
-                    },
+                    | ServerGeneral::MapMarker(_) => PreparedMsg::batched(2, &g),
                     //Ingame related, terrain
                     ServerGeneral::TerrainChunkUpdate { .. }
                     | ServerGeneral::TerrainBlockUpdates(_) => {
@@ -200,14 +291,17 @@ impl Client {
                     | ServerGeneral::ChatMode(_)
                     | ServerGeneral::SetPlayerEntity(_)
                     | ServerGeneral::TimeOfDay(_, _)
+                    | ServerGeneral::GameTime(_)
                     | ServerGeneral::EntitySync(_)
                     | ServerGeneral::CompSync(_)
+                    | ServerGeneral::SkillSetDelta(_)
+                    | ServerGeneral::EntityPhysicsDelta(_)
                     | ServerGeneral::CreateEntity(_)
                     | ServerGeneral::DeleteEntity(_)
                     | ServerGeneral::Disconnect(_)
-                    | ServerGeneral::Notification(_) => {
-                        PreparedMsg::new(3, &g, &self.general_stream_params)
-                    },
+                    | ServerGeneral::Notification(_)
+                    | ServerGeneral::FriendStatus { .. }
+                    | ServerGeneral::FriendRemoved(_) => PreparedMsg::batched(3, &g),
                 }
             },
             ServerMsg::Ping(m) => PreparedMsg::new(4, &m, &self.ping_stream_params),
@@ -235,7 +329,123 @@ impl PreparedMsg {
     fn new<M: Serialize + ?Sized>(id: u8, msg: &M, stream_params: &StreamParams) -> PreparedMsg {
         Self {
             stream_id: id,
-            message: Message::serialize(&msg, stream_params.clone()),
+            message: PreparedPayload::Message(Message::serialize(&msg, stream_params.clone())),
+        }
+    }
+
+    fn batched<M: Serialize>(id: u8, msg: &M) -> PreparedMsg {
+        Self {
+            stream_id: id,
+            message: PreparedPayload::Batched(MessageBatch::encode(msg)),
+        }
+    }
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        // Messages like the reason of a kick would get lost otherwise
+        if let Err(e) = self.flush_batches() {
+            debug!(?e, "Failed to send the last batched messages of a client");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::uid::Uid;
+    use network::{ConnectAddr, ListenAddr, Network, Pid, Promises};
+    use std::time::{Duration, Instant};
+    use tokio::runtime::Runtime;
+
+    /// A `Client` connected over mpsc, along with the remote general and
+    /// in-game streams
+    fn connect(
+        runtime: &Runtime,
+        port: u64,
+    ) -> (Network, Network, Client, Participant, Stream, Stream) {
+        let server = Network::new(Pid::new(), runtime);
+        let remote = Network::new(Pid::new(), runtime);
+        runtime.block_on(async {
+            server.listen(ListenAddr::Mpsc(port)).await.unwrap();
+            let remote_participant = remote.connect(ConnectAddr::Mpsc(port)).await.unwrap();
+            let participant = server.connected().await.unwrap();
+            let mut streams = Vec::new();
+            let mut remote_streams = Vec::new();
+            for _ in 0..6 {
+                let promises = Promises::ORDERED | Promises::CONSISTENCY;
+                streams.push(participant.open(3, promises, 0).await.unwrap());
+                remote_streams.push(remote_participant.opened().await.unwrap());
+            }
+            let mut streams = streams.into_iter();
+            let mut stream = || streams.next().unwrap();
+            let client = Client::new(
+                ClientType::Game,
+                participant,
+                0.0,
+                stream(),
+                stream(),
+                stream(),
+                stream(),
+                stream(),
+                stream(),
+                1024,
+            );
+            let in_game = remote_streams.swap_remove(4);
+            let general = remote_streams.swap_remove(0);
+            (server, remote, client, remote_participant, general, in_game)
+        })
+    }
+
+    #[test]
+    fn batches_are_sent_on_flush() {
+        let runtime = Runtime::new().unwrap();
+        let (_server, _remote, client, _remote_participant, mut general, mut in_game) =
+            connect(&runtime, 12770);
+
+        client.send(ServerGeneral::DeleteEntity(Uid(1))).unwrap();
+        client.send(ServerGeneral::SetViewDistance(5)).unwrap();
+        client.send(ServerGeneral::DeleteEntity(Uid(2))).unwrap();
+        std::thread::sleep(Duration::from_millis(100));
+        assert!(general.try_recv::<MessageBatch>().unwrap().is_none());
+
+        client.flush_batches().unwrap();
+        let batch = runtime.block_on(general.recv::<MessageBatch>()).unwrap();
+        let uids = batch
+            .messages::<ServerGeneral>()
+            .map(|msg| match msg.unwrap() {
+                ServerGeneral::DeleteEntity(uid) => uid,
+                msg => panic!("unexpected message {:?}", msg),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(uids, [Uid(1), Uid(2)]);
+        let batch = runtime.block_on(in_game.recv::<MessageBatch>()).unwrap();
+        let msgs = batch
+            .messages::<ServerGeneral>()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert!(matches!(msgs[..], [ServerGeneral::SetViewDistance(5)]));
+    }
+
+    #[test]
+    fn batched_send_fails_once_closed() {
+        let runtime = Runtime::new().unwrap();
+        let (_server, _remote, client, remote_participant, _general, _in_game) =
+            connect(&runtime, 12771);
+        drop(remote_participant);
+
+        let start = Instant::now();
+        while !client.general_stream.lock().unwrap().is_closed() {
+            assert!(
+                start.elapsed() < Duration::from_secs(10),
+                "stream not closed"
+            );
+            std::thread::sleep(Duration::from_millis(10));
         }
+        assert!(matches!(
+            client.send(ServerGeneral::DeleteEntity(Uid(1))),
+            Err(StreamError::StreamClosed)
+        ));
+        assert!(client.general_batch.lock().unwrap().is_empty());
     }
 }
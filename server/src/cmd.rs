@@ -4,11 +4,13 @@
 
 use crate::{
     client::Client,
+    events::{CombatEvent, CombatLog},
     location::Locations,
     login_provider::LoginProvider,
     settings::{
         Ban, BanAction, BanInfo, EditableSetting, SettingError, WhitelistInfo, WhitelistRecord,
     },
+    spawn::{BossEncounter, DEFAULT_PHASE_THRESHOLDS},
     sys::terrain::NpcData,
     wiring,
     wiring::{Logic, OutputFormula},
@@ -124,11 +126,13 @@ fn do_command(
         ChatCommand::Ban => handle_ban,
         ChatCommand::BattleMode => handle_battlemode,
         ChatCommand::BattleModeForce => handle_battlemode_force,
+        ChatCommand::Boss => handle_boss,
         ChatCommand::Build => handle_build,
         ChatCommand::BuildAreaAdd => handle_build_area_add,
         ChatCommand::BuildAreaList => handle_build_area_list,
         ChatCommand::BuildAreaRemove => handle_build_area_remove,
         ChatCommand::Campfire => handle_spawn_campfire,
+        ChatCommand::CombatLog => handle_combat_log,
         ChatCommand::DebugColumn => handle_debug_column,
         ChatCommand::DisconnectAllPlayers => handle_disconnect_all_players,
         ChatCommand::DropAll => handle_drop_all,
@@ -1086,7 +1090,9 @@ fn handle_health(
                 amount: hp - health.current(),
                 by: None,
                 cause: None,
+                kind: None,
                 time: *time,
+                crit: false,
             };
             health.change_by(change);
             Ok(())
@@ -1264,6 +1270,60 @@ fn handle_spawn(
     }
 }
 
+fn handle_boss(
+    server: &mut Server,
+    client: EcsEntity,
+    target: EcsEntity,
+    args: Vec<String>,
+    action: &ChatCommand,
+) -> CmdResult<()> {
+    if let (Some(npc::NpcBody(id, mut body)), first, second) =
+        parse_args!(args, npc::NpcBody, f32, f32)
+    {
+        let pos = position(server, target, "target")?;
+        let body = body();
+        let loadout = LoadoutBuilder::from_default(&body).build();
+        let boss = server
+            .state
+            .create_npc(
+                pos,
+                comp::Stats::new(get_npc_name(id, npc::BodyType::from_body(body))),
+                comp::SkillSet::default(),
+                Some(comp::Health::new(body, 1)),
+                comp::Poise::new(body),
+                Inventory::new_with_loadout(loadout),
+                body,
+            )
+            .with(comp::Agent::from_body(&body).with_patrol_origin(pos.0))
+            .with(comp::Alignment::Enemy)
+            .with(comp::group::ENEMY)
+            .build();
+        let boss_uid = uid(server, boss, "boss")?;
+        let thresholds = first.into_iter().chain(second).collect::<Vec<_>>();
+        let thresholds = if thresholds.is_empty() {
+            DEFAULT_PHASE_THRESHOLDS.to_vec()
+        } else {
+            thresholds
+        };
+        insert_or_replace_component(
+            server,
+            boss,
+            BossEncounter::new(boss_uid, thresholds),
+            "boss",
+        )?;
+        server.notify_client(
+            client,
+            ServerGeneral::server_msg(
+                ChatType::CommandInfo,
+                format!("Spawned boss with ID: {}", boss_uid),
+            ),
+        );
+        Ok(())
+    } else {
+        Err(action.help_string())
+    }
+}
+
 fn handle_spawn_training_dummy(
     server: &mut Server,
     client: EcsEntity,
@@ -1459,6 +1519,7 @@ fn handle_spawn_campfire(
             flicker: 1.0,
             animated: true,
         })
+        .with(comp::AudioEmitter::new(comp::AudioEmitterKind::Campfire))
         .with(WaypointArea::default())
         .with(comp::Auras::new(vec![
             Aura::new(
@@ -2881,6 +2942,42 @@ spawn_rate {:?} "#,
     }
 }
 
+fn handle_combat_log(
+    server: &mut Server,
+    client: EcsEntity,
+    _target: EcsEntity,
+    args: Vec<String>,
+    action: &ChatCommand,
+) -> CmdResult<()> {
+    let (uid, last_n, format) = parse_args!(args, u64, usize, String);
+    let uid = Uid(uid.ok_or_else(|| action.help_string())?);
+    let csv = match format.as_deref() {
+        None | Some("text") => false,
+        Some("csv") => true,
+        Some(_) => return Err(action.help_string()),
+    };
+    let msg = {
+        let log = server.state.ecs().read_resource::<CombatLog>();
+        let events = log.last_involving(uid, last_n.unwrap_or(10));
+        if events.is_empty() {
+            return Err(format!("No damage dealt or received by {} was logged", uid));
+        }
+        let mut lines = Vec::with_capacity(events.len() + 1);
+        if csv {
+            lines.push(CombatEvent::CSV_HEADER.to_string());
+            lines.extend(events.iter().map(|event| event.to_csv_row()));
+        } else {
+            lines.extend(events.iter().map(|event| event.to_string()));
+        }
+        lines.join("\n")
+    };
+    server.notify_client(
+        client,
+        ServerGeneral::server_msg(ChatType::CommandInfo, msg),
+    );
+    Ok(())
+}
+
 fn handle_disconnect_all_players(
     server: &mut Server,
     client: EcsEntity,
@@ -2965,6 +3062,8 @@ fn parse_skill_tree(skill_tree: &str) -> CmdResult<comp::skillset::SkillGroupKin
         "staff" => Ok(SkillGroupKind::Weapon(ToolKind::Staff)),
         "sceptre" => Ok(SkillGroupKind::Weapon(ToolKind::Sceptre)),
         "mining" => Ok(SkillGroupKind::Weapon(ToolKind::Pick)),
+        "magic" => Ok(SkillGroupKind::Magic),
+        "crafting" => Ok(SkillGroupKind::Crafting),
         _ => Err(format!("{} is not a skill group!", skill_tree)),
     }
 }
@@ -3155,6 +3254,7 @@ fn kick_player(
     (client, client_uuid): (EcsEntity, Uuid),
     (target_player, target_player_uuid): (EcsEntity, Uuid),
     reason: &str,
+    reconnect_allowed: bool,
 ) -> CmdResult<()> {
     verify_above_role(
         server,
@@ -3164,7 +3264,10 @@ fn kick_player(
     )?;
     server.notify_client(
         target_player,
-        ServerGeneral::Disconnect(DisconnectReason::Kicked(reason.to_string())),
+        ServerGeneral::Disconnect(DisconnectReason::Kicked {
+            reason: reason.to_string(),
+            reconnect_allowed,
+        }),
     );
     server
         .state
@@ -3189,7 +3292,7 @@ fn handle_kick(
         let ecs = server.state.ecs();
         let target_player = find_alias(ecs, &target_alias)?;
 
-        kick_player(server, (client, client_uuid), target_player, &reason)?;
+        kick_player(server, (client, client_uuid), target_player, &reason, true)?;
         server.notify_client(
             client,
             ServerGeneral::server_msg(
@@ -3276,6 +3379,7 @@ fn handle_ban(
                 (client, client_uuid),
                 (target_player, player_uuid),
                 &reason,
+                false,
             );
         }
         Ok(())
@@ -3579,6 +3683,8 @@ fn set_skills(skill_set: &mut comp::SkillSet, preset: &str) -> CmdResult<()> {
         },
     };
     if let Some(preset) = presets.get(preset) {
+        let mut skills = Vec::new();
+        let mut costs = HashMap::new();
         for (skill, level) in preset {
             let group = if let Some(group) = skill.skill_group_kind() {
                 group
@@ -3586,16 +3692,43 @@ fn set_skills(skill_set: &mut comp::SkillSet, preset: &str) -> CmdResult<()> {
                 warn!("Skill in preset doesn't exist in any group");
                 return Err("Preset is broken".to_owned());
             };
-            for _ in 0..*level {
-                let cost = skill_set.skill_cost(*skill);
-                skill_set.add_skill_points(group, cost);
-                match skill_set.unlock_skill(*skill) {
-                    Ok(_) | Err(comp::skillset::SkillUnlockError::SkillAlreadyUnlocked) => Ok(()),
-                    Err(err) => Err(format!("{:?}", err)),
-                }?;
+            // Levels the skill has already don't need to be paid for again
+            let current = skill_set.skill_level(*skill).unwrap_or(0);
+            for level in current + 1..=*level {
+                *costs.entry(group).or_insert(0u16) += skill.skill_cost(level);
+                skills.push(*skill);
             }
         }
-        Ok(())
+        // Applied completely or not at all
+        let snapshot = skill_set.snapshot();
+        for (group, cost) in &costs {
+            skill_set.add_skill_points(*group, *cost);
+        }
+        // The points aren't granted for skill groups that are locked
+        let missing = costs
+            .iter()
+            .find(|(group, cost)| skill_set.available_sp(**group) < **cost);
+        if let Some((group, cost)) = missing {
+            let available = skill_set.available_sp(*group);
+            skill_set.restore(snapshot);
+            return Err(format!(
+                "Preset needs {} skill points in {:?}, only {} are available",
+                cost, group, available
+            ));
+        }
+        // Presets aren't ordered, bulk_unlock takes care of unlocking the
+        // prerequisites first
+        let result = skill_set
+            .bulk_unlock(skills)
+            .into_iter()
+            .try_for_each(|(skill, result)| match result {
+                Ok(_) | Err(comp::skillset::SkillUnlockError::SkillAlreadyUnlocked) => Ok(()),
+                Err(err) => Err(format!("{:?}: {:?}", skill, err)),
+            });
+        if result.is_err() {
+            skill_set.restore(snapshot);
+        }
+        result
     } else {
         Err("Such preset doesn't exist".to_owned())
     }
@@ -9,6 +9,7 @@ use tracing::{debug, error, trace, warn};
 pub(crate) struct ServerInfoPacket {
     pub info: ServerInfo,
     pub time: f64,
+    pub max_batch_bytes: usize,
 }
 
 pub(crate) type IncomingClient = Client;
@@ -133,6 +134,7 @@ impl ConnectionHandler {
             character_screen_stream,
             in_game_stream,
             terrain_stream,
+            server_data.max_batch_bytes,
         );
 
         client_sender.send(client)?;
@@ -0,0 +1,206 @@
+use specs::Component;
+use specs_idvs::IdvStorage;
+use std::{collections::VecDeque, fmt};
+
+pub const COPPER_PER_SILVER: u64 = 100;
+pub const SILVER_PER_GOLD: u64 = 100;
+pub const COPPER_PER_GOLD: u64 = COPPER_PER_SILVER * SILVER_PER_GOLD;
+
+/// The value of the given coins in copper
+pub const fn copper_value(gold: u32, silver: u32, copper: u32) -> u64 {
+    gold as u64 * COPPER_PER_GOLD + silver as u64 * COPPER_PER_SILVER + copper as u64
+}
+
+/// A change of the balance of a [`PlayerWallet`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Transaction {
+    /// Copper added to the wallet, negative if coins were taken out
+    pub change: i64,
+    /// Value of the wallet in copper after the transaction
+    pub balance: u64,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct InsufficientFunds {
+    /// Copper that would have been needed in addition
+    pub missing: u64,
+}
+
+impl fmt::Display for InsufficientFunds {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Insufficient funds, {} copper missing", self.missing)
+    }
+}
+
+impl std::error::Error for InsufficientFunds {}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct WalletFull {
+    /// Copper that doesn't fit into the wallet anymore
+    pub excess: u64,
+}
+
+impl fmt::Display for WalletFull {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Wallet is full, {} copper too much", self.excess)
+    }
+}
+
+impl std::error::Error for WalletFull {}
+
+/// Coins held by a player, the latest transactions are kept for the history
+/// shown to them.
+///
+/// Nothing inserts or persists the wallet yet, coins are still items in the
+/// inventory and that is what e.g. a skill respec is paid with.
+#[derive(Clone, Debug, Default)]
+pub struct PlayerWallet {
+    pub gold: u32,
+    pub silver: u32,
+    pub copper: u32,
+    pub transactions: VecDeque<Transaction>,
+}
+
+impl PlayerWallet {
+    /// Most a wallet can hold, all gold coins with the smaller coins carried
+    /// over
+    pub const MAX_COPPER: u64 = copper_value(
+        u32::MAX,
+        SILVER_PER_GOLD as u32 - 1,
+        COPPER_PER_SILVER as u32 - 1,
+    );
+    pub const MAX_TRANSACTIONS: usize = 50;
+
+    /// Value of all coins in copper
+    pub fn total_copper(&self) -> u64 { copper_value(self.gold, self.silver, self.copper) }
+
+    /// Adds the given amount, carrying it over into larger coins. Nothing is
+    /// added if the wallet can't hold the amount.
+    pub fn add(&mut self, gold: u32, silver: u32, copper: u32) -> Result<(), WalletFull> {
+        let amount = copper_value(gold, silver, copper);
+        let total = self.total_copper();
+        let new_total = total
+            .checked_add(amount)
+            .filter(|new_total| *new_total <= Self::MAX_COPPER)
+            .ok_or_else(|| WalletFull {
+                excess: total.saturating_add(amount) - Self::MAX_COPPER,
+            })?;
+        self.set_total_copper(new_total);
+        self.record(amount as i64);
+        Ok(())
+    }
+
+    /// Pays the given amount, breaking larger coins into change if needed.
+    /// Nothing is taken out if the wallet holds less than the amount.
+    pub fn subtract(
+        &mut self,
+        gold: u32,
+        silver: u32,
+        copper: u32,
+    ) -> Result<(), InsufficientFunds> {
+        let cost = copper_value(gold, silver, copper);
+        let total = self.total_copper();
+        if cost > total {
+            return Err(InsufficientFunds {
+                missing: cost - total,
+            });
+        }
+        self.set_total_copper(total - cost);
+        self.record(-(cost as i64));
+        Ok(())
+    }
+
+    /// Carries 100 copper over into a silver and 100 silver into a gold coin
+    pub fn normalize(&mut self) {
+        self.silver = self
+            .silver
+            .saturating_add(self.copper / COPPER_PER_SILVER as u32);
+        self.copper %= COPPER_PER_SILVER as u32;
+        self.gold = self
+            .gold
+            .saturating_add(self.silver / SILVER_PER_GOLD as u32);
+        self.silver %= SILVER_PER_GOLD as u32;
+    }
+
+    /// `total` must not exceed [`Self::MAX_COPPER`]
+    fn set_total_copper(&mut self, total: u64) {
+        debug_assert!(total <= Self::MAX_COPPER);
+        self.gold = (total / COPPER_PER_GOLD) as u32;
+        self.silver = (total % COPPER_PER_GOLD / COPPER_PER_SILVER) as u32;
+        self.copper = (total % COPPER_PER_SILVER) as u32;
+    }
+
+    fn record(&mut self, change: i64) {
+        if self.transactions.len() >= Self::MAX_TRANSACTIONS {
+            self.transactions.pop_front();
+        }
+        self.transactions.push_back(Transaction {
+            change,
+            balance: self.total_copper(),
+        });
+    }
+}
+
+impl Component for PlayerWallet {
+    type Storage = IdvStorage<Self>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn add_normalizes() {
+        let mut wallet = PlayerWallet::default();
+        assert_eq!(wallet.add(1, 150, 250), Ok(()));
+        assert_eq!((wallet.gold, wallet.silver, wallet.copper), (2, 52, 50));
+        assert_eq!(wallet.total_copper(), 25_250);
+    }
+
+    #[test]
+    fn subtract_makes_change() {
+        let mut wallet = PlayerWallet::default();
+        assert_eq!(wallet.add(1, 0, 0), Ok(()));
+        assert_eq!(wallet.subtract(0, 0, 1), Ok(()));
+        assert_eq!((wallet.gold, wallet.silver, wallet.copper), (0, 99, 99));
+        assert_eq!(
+            wallet.subtract(1, 0, 0),
+            Err(InsufficientFunds { missing: 1 })
+        );
+        assert_eq!(wallet.total_copper(), 9_999);
+    }
+
+    #[test]
+    fn add_refuses_overflow() {
+        let mut wallet = PlayerWallet::default();
+        assert_eq!(wallet.add(u32::MAX, 0, 0), Ok(()));
+        assert_eq!(wallet.add(0, 99, 99), Ok(()));
+        assert_eq!(wallet.total_copper(), PlayerWallet::MAX_COPPER);
+        assert_eq!(wallet.add(0, 1, 1), Err(WalletFull { excess: 101 }));
+        assert_eq!(wallet.total_copper(), PlayerWallet::MAX_COPPER);
+        assert_eq!(
+            wallet.transactions.back(),
+            Some(&Transaction {
+                change: 9_999,
+                balance: PlayerWallet::MAX_COPPER
+            })
+        );
+    }
+
+    #[test]
+    fn history_is_capped() {
+        let mut wallet = PlayerWallet::default();
+        for _ in 0..PlayerWallet::MAX_TRANSACTIONS + 10 {
+            assert_eq!(wallet.add(0, 0, 1), Ok(()));
+        }
+        assert_eq!(wallet.subtract(0, 0, 5), Ok(()));
+        assert_eq!(wallet.transactions.len(), PlayerWallet::MAX_TRANSACTIONS);
+        assert_eq!(
+            wallet.transactions.back(),
+            Some(&Transaction {
+                change: -5,
+                balance: 55
+            })
+        );
+    }
+}
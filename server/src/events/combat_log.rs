@@ -0,0 +1,177 @@
+use common::{comp::HealthChange, uid::Uid, DamageSource};
+use std::{collections::VecDeque, fmt};
+
+/// A single instance of damage dealt to an entity
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CombatEvent {
+    pub tick: u64,
+    /// `None` if the damage wasn't caused by an entity, e.g. fall damage
+    pub attacker: Option<Uid>,
+    pub defender: Uid,
+    pub damage: f32,
+    pub source: Option<DamageSource>,
+    pub crit: bool,
+}
+
+impl CombatEvent {
+    pub const CSV_HEADER: &'static str = "tick,attacker,defender,damage,source,crit";
+
+    /// Returns `None` for health changes that aren't damage, e.g. healing
+    pub fn from_health_change(tick: u64, defender: Uid, change: &HealthChange) -> Option<Self> {
+        (change.amount < 0.0).then(|| Self {
+            tick,
+            attacker: change.by.map(|by| by.uid()),
+            defender,
+            damage: -change.amount,
+            source: change.cause,
+            crit: change.crit,
+        })
+    }
+
+    pub fn involves(&self, uid: Uid) -> bool { self.defender == uid || self.attacker == Some(uid) }
+
+    pub fn to_csv_row(&self) -> String {
+        format!(
+            "{},{},{},{:.2},{},{}",
+            self.tick,
+            self.attacker
+                .map_or_else(String::new, |uid| uid.to_string()),
+            self.defender,
+            self.damage,
+            self.source
+                .map_or_else(String::new, |source| format!("{:?}", source)),
+            self.crit,
+        )
+    }
+}
+
+impl fmt::Display for CombatEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[{}] ", self.tick)?;
+        match self.attacker {
+            Some(attacker) => write!(f, "{} -> {}", attacker, self.defender)?,
+            None => write!(f, "{}", self.defender)?,
+        }
+        write!(f, ": {:.1} damage", self.damage)?;
+        if let Some(source) = self.source {
+            write!(f, " ({:?})", source)?;
+        }
+        if self.crit {
+            write!(f, ", critical")?;
+        }
+        Ok(())
+    }
+}
+
+/// Recent damage dealt on the server, used to review fights with the
+/// `/combat_log` command. Only the latest `max_events` are kept and nothing
+/// is persisted across restarts.
+pub struct CombatLog {
+    events: VecDeque<CombatEvent>,
+    max_events: usize,
+}
+
+impl CombatLog {
+    pub const DEFAULT_MAX_EVENTS: usize = 10_000;
+
+    pub fn new(max_events: usize) -> Self {
+        Self {
+            events: VecDeque::with_capacity(max_events.min(Self::DEFAULT_MAX_EVENTS)),
+            max_events,
+        }
+    }
+
+    pub fn push(&mut self, event: CombatEvent) {
+        if self.max_events == 0 {
+            return;
+        }
+        if self.events.len() >= self.max_events {
+            self.events.pop_front();
+        }
+        self.events.push_back(event);
+    }
+
+    /// The last `n` events that `uid` dealt or received, oldest first
+    pub fn last_involving(&self, uid: Uid, n: usize) -> Vec<&CombatEvent> {
+        let mut events = self
+            .events
+            .iter()
+            .rev()
+            .filter(|event| event.involves(uid))
+            .take(n)
+            .collect::<Vec<_>>();
+        events.reverse();
+        events
+    }
+
+    pub fn len(&self) -> usize { self.events.len() }
+
+    pub fn is_empty(&self) -> bool { self.events.is_empty() }
+}
+
+impl Default for CombatLog {
+    fn default() -> Self { Self::new(Self::DEFAULT_MAX_EVENTS) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(tick: u64, attacker: u64, defender: u64) -> CombatEvent {
+        CombatEvent {
+            tick,
+            attacker: Some(Uid(attacker)),
+            defender: Uid(defender),
+            damage: 10.0,
+            source: Some(DamageSource::Melee),
+            crit: false,
+        }
+    }
+
+    #[test]
+    fn drops_oldest_events() {
+        let mut log = CombatLog::new(3);
+        for tick in 0..5 {
+            log.push(event(tick, 1, 2));
+        }
+        assert_eq!(log.len(), 3);
+        let ticks = log
+            .last_involving(Uid(1), 10)
+            .iter()
+            .map(|e| e.tick)
+            .collect::<Vec<_>>();
+        assert_eq!(ticks, vec![2, 3, 4]);
+    }
+
+    #[test]
+    fn filters_by_uid() {
+        let mut log = CombatLog::default();
+        log.push(event(0, 1, 2));
+        log.push(event(1, 3, 4));
+        log.push(event(2, 2, 1));
+        log.push(event(3, 1, 3));
+        let ticks = |uid, n| {
+            log.last_involving(Uid(uid), n)
+                .iter()
+                .map(|e| e.tick)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(ticks(2, 10), vec![0, 2]);
+        assert_eq!(ticks(1, 2), vec![2, 3]);
+        assert_eq!(ticks(5, 10), Vec::<u64>::new());
+    }
+
+    #[test]
+    fn csv_row() {
+        let mut e = event(7, 1, 2);
+        assert_eq!(e.to_csv_row(), "7,1,2,10.00,Melee,false");
+        e.attacker = None;
+        e.source = None;
+        e.crit = true;
+        assert_eq!(e.to_csv_row(), "7,,2,10.00,,true");
+        assert_eq!(
+            CombatEvent::CSV_HEADER.split(',').count(),
+            e.to_csv_row().split(',').count()
+        );
+    }
+}
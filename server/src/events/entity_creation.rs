@@ -1,4 +1,6 @@
-use crate::{client::Client, persistence::PersistedComponents, sys, Server, StateExt};
+use crate::{
+    client::Client, guild::GuildManager, persistence::PersistedComponents, sys, Server, StateExt,
+};
 use common::{
     character::CharacterId,
     comp::{
@@ -30,6 +32,15 @@ pub fn handle_initialize_character(
     character_id: CharacterId,
 ) {
     server.state.initialize_character_data(entity, character_id);
+    let guild = server
+        .state
+        .ecs()
+        .read_resource::<GuildManager>()
+        .guild_of(character_id)
+        .cloned();
+    if let Some(guild) = guild {
+        server.notify_client(entity, ServerGeneral::GuildUpdate(guild));
+    }
 }
 
 pub fn handle_loaded_character_data(
@@ -242,6 +253,7 @@ pub fn handle_create_waypoint(server: &mut Server, pos: Vec3<f32>) {
             flicker: 1.0,
             animated: true,
         })
+        .with(comp::AudioEmitter::new(comp::AudioEmitterKind::Campfire))
         .with(WaypointArea::default())
         .with(comp::Immovable)
         .with(comp::Auras::new(vec![
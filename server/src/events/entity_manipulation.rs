@@ -6,23 +6,28 @@ use crate::{
         skillset::SkillGroupKind,
         BuffKind, BuffSource, PhysicsState,
     },
+    events::{CombatEvent, CombatLog},
+    gravestone,
+    presence::Presence,
     rtsim::RtSim,
     sys::terrain::SAFE_ZONE_RADIUS,
-    Server, SpawnPoint, StateExt,
+    Server, SpawnPoint, StateExt, Tick,
 };
 use common::{
+    assets::AssetExt,
     combat,
     combat::DamageContributor,
     comp::{
         self, aura, buff,
         chat::{KillSource, KillType},
-        inventory::item::MaterialStatManifest,
+        cooldown::COOLDOWN_MANIFEST,
+        inventory::item::{ItemDef, MaterialStatManifest},
         Alignment, Auras, Body, CharacterState, Energy, Group, Health, HealthChange, Inventory,
         Player, Poise, Pos, SkillSet, Stats,
     },
     event::{EventBus, ServerEvent},
     outcome::Outcome,
-    resources::Time,
+    resources::{GameTime, Time},
     rtsim::RtSimEntity,
     terrain::{Block, BlockKind, TerrainGrid},
     uid::{Uid, UidAllocator},
@@ -30,7 +35,10 @@ use common::{
     vol::ReadVol,
     Damage, DamageKind, DamageSource, Explosion, GroupTarget, RadiusEffect,
 };
-use common_net::{msg::ServerGeneral, sync::WorldSyncExt};
+use common_net::{
+    msg::{PresenceKind, ServerGeneral},
+    sync::WorldSyncExt,
+};
 use common_state::BlockChange;
 use comp::chat::GenericChatMsg;
 use hashbrown::HashSet;
@@ -38,7 +46,7 @@ use rand::Rng;
 use specs::{
     join::Join, saveload::MarkerAllocator, Builder, Entity as EcsEntity, Entity, WorldExt,
 };
-use std::{collections::HashMap, iter};
+use std::{collections::HashMap, iter, sync::Arc};
 use tracing::{debug, error};
 use vek::{Vec2, Vec3};
 
@@ -61,7 +69,9 @@ pub fn handle_poise(server: &Server, entity: EcsEntity, change: comp::PoiseChang
                 amount: health_change,
                 by: None,
                 cause: None,
+                kind: None,
                 time: *time,
+                crit: false,
             };
             let server_eventbus = ecs.read_resource::<EventBus<ServerEvent>>();
             server_eventbus.emit_now(ServerEvent::HealthChange {
@@ -79,6 +89,12 @@ pub fn handle_health_change(server: &Server, entity: EcsEntity, change: HealthCh
     if let Some(mut health) = ecs.write_storage::<Health>().get_mut(entity) {
         health.change_by(change);
     }
+    if let Some(uid) = ecs.read_storage::<Uid>().get(entity) {
+        let tick = ecs.read_resource::<Tick>().0;
+        if let Some(event) = CombatEvent::from_health_change(tick, *uid, &change) {
+            ecs.write_resource::<CombatLog>().push(event);
+        }
+    }
     // This if statement filters out anything under 5 damage, for DOT ticks
     // TODO: Find a better way to separate direct damage from DOT here
     let damage = -change.amount;
@@ -112,6 +128,25 @@ pub fn handle_knockback(server: &Server, entity: EcsEntity, impulse: Vec3<f32>)
         if let Some(client) = clients.get(entity) {
             client.send_fallible(ServerGeneral::Knockback(impulse));
         }
+
+        // Strong knockbacks stagger the entity, interrupting what it was doing
+        if let (Some(mut char_state), Some(mut poise), Some(pos)) = (
+            ecs.write_storage::<CharacterState>().get_mut(entity),
+            ecs.write_storage::<Poise>().get_mut(entity),
+            ecs.read_storage::<Pos>().get(entity),
+        ) {
+            if let Some((stunned_state, stunned_duration)) =
+                poise.knockback_stagger(&char_state, impulse.magnitude())
+            {
+                poise.reset(*ecs.read_resource::<Time>(), stunned_duration);
+                *char_state = stunned_state;
+                let mut outcomes = ecs.write_resource::<Vec<Outcome>>();
+                outcomes.push(Outcome::PoiseChange {
+                    pos: pos.0,
+                    state: comp::poise::PoiseState::Interrupted,
+                });
+            }
+        }
     }
 }
 
@@ -129,6 +164,14 @@ pub fn handle_knockback(server: &Server, entity: EcsEntity, impulse: Vec3<f32>)
 // loop would currently be very inefficient since it has to rescan every entity
 // on the server again.
 pub fn handle_destroy(server: &mut Server, entity: EcsEntity, last_change: HealthChange) {
+    let death_items = {
+        let settings = server.settings();
+        (
+            settings.death_item_policy,
+            settings.gravestone_grace_period,
+            settings.gravestone_lifetime,
+        )
+    };
     let state = server.state_mut();
 
     // TODO: Investigate duplicate `Destroy` events (but don't remove this).
@@ -391,6 +434,31 @@ pub fn handle_destroy(server: &mut Server, entity: EcsEntity, last_change: Healt
         .get_mut(entity)
         .is_some()
     {
+        let character = state
+            .ecs()
+            .read_storage::<Presence>()
+            .get(entity)
+            .and_then(|presence| match presence.kind {
+                PresenceKind::Character(id) => Some(id),
+                PresenceKind::Spectator | PresenceKind::Possessor => None,
+            });
+        if let Some(character) = character {
+            let (policy, grace_period, lifetime) = death_items;
+            let gravestone = gravestone::leave_gravestone(
+                state,
+                entity,
+                character,
+                policy,
+                grace_period,
+                lifetime,
+            );
+            if gravestone.is_some() {
+                let _ = state.ecs().write_storage().insert(
+                    entity,
+                    comp::InventoryUpdate::new(comp::InventoryUpdateEvent::Dropped),
+                );
+            }
+        }
         state
             .ecs()
             .write_storage()
@@ -540,6 +608,60 @@ pub fn handle_land_on_ground(server: &Server, entity: EcsEntity, vel: Vec3<f32>)
             };
             poise.change(poise_change);
         }
+        // Lets clients shake the camera of the player that landed
+        if let (Some(pos), Some(uid)) = (
+            ecs.read_storage::<Pos>().get(entity),
+            ecs.read_storage::<Uid>().get(entity),
+        ) {
+            ecs.write_resource::<Vec<Outcome>>()
+                .push(Outcome::FallDamage {
+                    pos: pos.0,
+                    uid: *uid,
+                    damage: falldmg,
+                });
+        }
+    }
+}
+
+/// Resets all skills of the entity, but only once it paid for it in coins
+pub fn handle_respec_skills(server: &Server, entity: EcsEntity) {
+    let ecs = server.state.ecs();
+    let paid = {
+        let mut skill_sets = ecs.write_storage::<SkillSet>();
+        let mut inventories = ecs.write_storage::<Inventory>();
+        let (mut skill_set, mut inventory) =
+            match (skill_sets.get_mut(entity), inventories.get_mut(entity)) {
+                (Some(skill_set), Some(inventory)) => (skill_set, inventory),
+                _ => return,
+            };
+        if !skill_set.can_respec() {
+            return;
+        }
+
+        let cost = skill_set.respec_cost();
+        let coins = Arc::<ItemDef>::load_expect_cloned("common.items.utility.coins");
+        if inventory.remove_item_amount(&coins, cost) {
+            skill_set.full_respec();
+            Ok(())
+        } else {
+            Err(cost)
+        }
+    };
+
+    match paid {
+        Ok(()) => {
+            let _ = ecs.write_storage().insert(
+                entity,
+                comp::InventoryUpdate::new(comp::InventoryUpdateEvent::Gave),
+            );
+        },
+        Err(cost) => server.notify_client(
+            entity,
+            ServerGeneral::server_msg(
+                comp::ChatType::CommandError,
+                format!("Resetting your skills costs {} coins", cost),
+            ),
+        ),
     }
 }
 
@@ -785,6 +907,7 @@ pub fn handle_explosion(server: &Server, pos: Vec3<f32>, explosion: Explosion, o
                 let energies = &ecs.read_storage::<comp::Energy>();
                 let combos = &ecs.read_storage::<comp::Combo>();
                 let inventories = &ecs.read_storage::<comp::Inventory>();
+                let skill_sets = &ecs.read_storage::<comp::SkillSet>();
                 let alignments = &ecs.read_storage::<Alignment>();
                 let uid_allocator = &ecs.read_resource::<UidAllocator>();
                 let players = &ecs.read_storage::<comp::Player>();
@@ -850,6 +973,7 @@ pub fn handle_explosion(server: &Server, pos: Vec3<f32>, explosion: Explosion, o
                                     energy: energies.get(entity),
                                     combo: combos.get(entity),
                                     inventory: inventories.get(entity),
+                                    skill_set: skill_sets.get(entity),
                                 });
 
                         let target_info = combat::TargetInfo {
@@ -1124,10 +1248,11 @@ fn handle_exp_gain(
     add_tool_from_slot(EquipSlot::InactiveMainhand);
     add_tool_from_slot(EquipSlot::InactiveOffhand);
     let num_pools = xp_pools.len() as f32;
+    let mut exp_gained = 0.0;
     for pool in xp_pools.iter() {
-        if let Some(level_outcome) =
-            skill_set.add_experience(*pool, (exp_reward / num_pools).ceil() as u32)
-        {
+        let exp = exp_reward * skill_set.xp_gain_multiplier(*pool) / num_pools;
+        exp_gained += exp;
+        if let Some(level_outcome) = skill_set.add_experience(*pool, exp.ceil() as u32) {
             outcomes.push(Outcome::SkillPointGain {
                 uid: *uid,
                 skill_tree: *pool,
@@ -1138,7 +1263,7 @@ fn handle_exp_gain(
     }
     outcomes.push(Outcome::ExpChange {
         uid: *uid,
-        exp: exp_reward as u32,
+        exp: exp_gained as u32,
         xp_pools,
     });
 }
@@ -1158,6 +1283,24 @@ pub fn handle_combo_change(server: &Server, entity: EcsEntity, change: i32) {
     }
 }
 
+/// Puts the ability on cooldown, only players have cooldowns for now so that
+/// npcs don't get stuck trying to use an ability that isn't ready
+pub fn handle_start_cooldown(server: &Server, entity: EcsEntity, ability_id: String) {
+    let ecs = &server.state.ecs();
+    if !ecs.read_storage::<Player>().contains(entity) {
+        return;
+    }
+    if let Some(duration) = COOLDOWN_MANIFEST.read().cooldown(&ability_id) {
+        let game_time = *ecs.read_resource::<GameTime>();
+        let mut cooldowns = ecs.write_storage::<comp::Cooldowns>();
+        if let Ok(entry) = cooldowns.entry(entity) {
+            let cooldowns = entry.or_insert_with(comp::Cooldowns::default);
+            cooldowns.remove_expired(game_time);
+            cooldowns.start(&ability_id, game_time, duration);
+        }
+    }
+}
+
 pub fn handle_parry(server: &Server, entity: EcsEntity, energy_cost: f32) {
     let ecs = &server.state.ecs();
     if let Some(mut character) = ecs.write_storage::<comp::CharacterState>().get_mut(entity) {
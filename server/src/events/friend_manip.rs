@@ -0,0 +1,203 @@
+use super::invite::handle_invite;
+use crate::{
+    client::Client,
+    friends::{FriendManager, FriendRequest, FriendStatus},
+    persistence::character_updater::CharacterUpdater,
+    Server,
+};
+use common::{
+    comp::{
+        invite::InviteKind,
+        social::{FriendError, FriendManip},
+        ChatType, Player,
+    },
+    uid::Uid,
+};
+use common_net::{msg::ServerGeneral, sync::WorldSyncExt};
+use common_state::State;
+use hashbrown::HashMap;
+use specs::{world::WorldExt, Entity, Join};
+
+/// Uuid and alias of a player
+type PlayerName = (String, String);
+
+fn player_name(state: &State, entity: Entity) -> Option<PlayerName> {
+    state
+        .ecs()
+        .read_storage::<Player>()
+        .get(entity)
+        .map(|player| (player.uuid().to_string(), player.alias.clone()))
+}
+
+fn as_name(player: &PlayerName) -> (&str, &str) { (&player.0, &player.1) }
+
+/// Uids of the online players, by player uuid
+fn online_players(state: &State) -> HashMap<String, Uid> {
+    let ecs = state.ecs();
+    (&ecs.read_storage::<Player>(), &ecs.read_storage::<Uid>())
+        .join()
+        .map(|(player, uid)| (player.uuid().to_string(), *uid))
+        .collect()
+}
+
+/// The online player with the given alias
+fn find_player(state: &State, alias: &str) -> Option<(Entity, PlayerName)> {
+    let ecs = state.ecs();
+    (&ecs.entities(), &ecs.read_storage::<Player>())
+        .join()
+        .find(|(_, player)| player.alias.eq_ignore_ascii_case(alias))
+        .map(|(entity, player)| (entity, (player.uuid().to_string(), player.alias.clone())))
+}
+
+fn send_statuses(state: &State, statuses: Vec<FriendStatus>) {
+    let clients = state.ecs().read_storage::<Client>();
+    for status in statuses {
+        if let Some(client) = state
+            .ecs()
+            .entity_from_uid(status.recipient.into())
+            .and_then(|entity| clients.get(entity))
+        {
+            client.send_fallible(status.into_msg());
+        }
+    }
+}
+
+pub fn handle_friend(server: &mut Server, entity: Entity, manip: FriendManip) {
+    let player = match player_name(server.state(), entity) {
+        Some(player) => player,
+        None => return,
+    };
+    let result = match manip {
+        FriendManip::Add(name) => add_friend(server, entity, &player, &name),
+        FriendManip::Remove(name) => remove_friend(server.state(), entity, &player, &name),
+    };
+    if let Err(err) = result {
+        notify_error(server, entity, err);
+    }
+}
+
+fn add_friend(
+    server: &mut Server,
+    entity: Entity,
+    player: &PlayerName,
+    name: &str,
+) -> Result<(), FriendError> {
+    let state = server.state();
+    let (target, target_player) = find_player(state, name).ok_or(FriendError::NotOnline)?;
+    let request = state
+        .ecs()
+        .write_resource::<FriendManager>()
+        .request(as_name(player), as_name(&target_player))?;
+    match request {
+        FriendRequest::Sent => {
+            let target_uid = state.ecs().read_storage::<Uid>().get(target).copied();
+            if let Some(target_uid) = target_uid {
+                handle_invite(server, entity, target_uid, InviteKind::Friend);
+            }
+        },
+        FriendRequest::Accepted => befriend(state, (entity, player), (target, &target_player)),
+    }
+    Ok(())
+}
+
+fn remove_friend(
+    state: &State,
+    entity: Entity,
+    player: &PlayerName,
+    name: &str,
+) -> Result<(), FriendError> {
+    let mut friends = state.ecs().write_resource::<FriendManager>();
+    match friends.remove(&player.0, name) {
+        Ok((friend, friend_name)) => {
+            drop(friends);
+            let clients = state.ecs().read_storage::<Client>();
+            if let Some(client) = clients.get(entity) {
+                client.send_fallible(ServerGeneral::FriendRemoved(friend_name));
+            }
+            if let Some(client) = online_players(state)
+                .get(&friend)
+                .and_then(|uid| state.ecs().entity_from_uid((*uid).into()))
+                .and_then(|friend| clients.get(friend))
+            {
+                client.send_fallible(ServerGeneral::FriendRemoved(player.1.clone()));
+            }
+            state
+                .ecs()
+                .write_resource::<CharacterUpdater>()
+                .delete_friendship(player.0.clone(), friend);
+            Ok(())
+        },
+        // Requests are only made to online players
+        Err(err) => match find_player(state, name) {
+            Some((_, (other, _))) if friends.cancel_request(&player.0, &other) => Ok(()),
+            _ => Err(err),
+        },
+    }
+}
+
+/// Makes the players friends once the invitee accepted the friend request
+pub fn accept_request(server: &mut Server, inviter: Entity, invitee: Entity) {
+    let state = server.state();
+    if let (Some(inviter_name), Some(invitee_name)) =
+        (player_name(state, inviter), player_name(state, invitee))
+    {
+        let result = state
+            .ecs()
+            .write_resource::<FriendManager>()
+            .accept(as_name(&invitee_name), as_name(&inviter_name));
+        match result {
+            Ok(()) => befriend(state, (inviter, &inviter_name), (invitee, &invitee_name)),
+            Err(err) => notify_error(server, invitee, err),
+        }
+    }
+}
+
+pub fn decline_request(state: &State, inviter: Entity, invitee: Entity) {
+    if let (Some(inviter_name), Some(invitee_name)) =
+        (player_name(state, inviter), player_name(state, invitee))
+    {
+        state
+            .ecs()
+            .write_resource::<FriendManager>()
+            .decline(&invitee_name.0, &inviter_name.0);
+    }
+}
+
+/// Persists the new friendship and tells both players about it
+fn befriend(state: &State, a: (Entity, &PlayerName), b: (Entity, &PlayerName)) {
+    {
+        let clients = state.ecs().read_storage::<Client>();
+        let uids = state.ecs().read_storage::<Uid>();
+        for ((entity, _), (friend, friend_name)) in [(a, b), (b, a)] {
+            if let Some(client) = clients.get(entity) {
+                client.send_fallible(ServerGeneral::FriendStatus {
+                    uid: uids.get(friend).copied(),
+                    name: friend_name.1.clone(),
+                    online: true,
+                });
+            }
+        }
+    }
+    state
+        .ecs()
+        .write_resource::<CharacterUpdater>()
+        .save_friendship(a.1.clone(), b.1.clone());
+}
+
+/// Tells the online friends of a player that logs out about it
+pub fn notify_logout(state: &State, entity: Entity) {
+    if let Some(player) = player_name(state, entity) {
+        let statuses = state
+            .ecs()
+            .write_resource::<FriendManager>()
+            .logout(as_name(&player), &online_players(state));
+        send_statuses(state, statuses);
+    }
+}
+
+fn notify_error(server: &Server, entity: Entity, err: FriendError) {
+    server.notify_client(
+        entity,
+        ServerGeneral::server_msg(ChatType::Meta, err.to_string()),
+    );
+}
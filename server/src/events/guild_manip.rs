@@ -0,0 +1,166 @@
+use super::invite::handle_invite;
+use crate::{
+    client::Client, guild::GuildManager, persistence::character_updater::CharacterUpdater,
+    presence::Presence, Server,
+};
+use common::{
+    character::CharacterId,
+    comp::{
+        invite::InviteKind,
+        social::{Guild, GuildError, GuildManip},
+        ChatType,
+    },
+};
+use common_net::msg::{PresenceKind, ServerGeneral};
+use common_state::State;
+use specs::{world::WorldExt, Entity, Join};
+
+/// The character an entity is playing, guilds are joined by characters
+pub fn character_id(state: &State, entity: Entity) -> Option<CharacterId> {
+    match state.ecs().read_storage::<Presence>().get(entity)?.kind {
+        PresenceKind::Character(character_id) => Some(character_id),
+        PresenceKind::Spectator | PresenceKind::Possessor => None,
+    }
+}
+
+/// Checks that `inviter` may invite others into their guild and that `invitee`
+/// isn't part of a guild yet
+pub fn can_invite(state: &State, inviter: Entity, invitee: Entity) -> Result<(), GuildError> {
+    let guild_manager = state.ecs().read_resource::<GuildManager>();
+    let inviter = character_id(state, inviter).ok_or(GuildError::NotACharacter)?;
+    match guild_manager.guild_of(inviter) {
+        Some(guild) if guild.can_invite(inviter) => {},
+        Some(_) => return Err(GuildError::InsufficientRank),
+        None => return Err(GuildError::NotInGuild),
+    }
+    match character_id(state, invitee) {
+        Some(invitee) if guild_manager.guild_of(invitee).is_some() => {
+            Err(GuildError::AlreadyInGuild)
+        },
+        Some(_) => Ok(()),
+        None => Err(GuildError::NotACharacter),
+    }
+}
+
+/// Adds the invitee to the inviter's guild once they accepted the invite
+pub fn add_member(server: &mut Server, inviter: Entity, invitee: Entity) {
+    let state = server.state();
+    let result = match (character_id(state, inviter), character_id(state, invitee)) {
+        (Some(inviter), Some(invitee)) => {
+            let mut guild_manager = state.ecs().write_resource::<GuildManager>();
+            if guild_manager.guild_of(invitee).is_some() {
+                Err(GuildError::AlreadyInGuild)
+            } else {
+                // The inviter could have left or been demoted in the meantime
+                match guild_manager.guild_of(inviter).map(|guild| guild.id) {
+                    Some(id) => guild_manager
+                        .guild_mut(id)
+                        .filter(|guild| guild.can_invite(inviter))
+                        .ok_or(GuildError::InsufficientRank)
+                        .and_then(|guild| {
+                            guild.add_member(invitee)?;
+                            Ok(guild.clone())
+                        }),
+                    None => Err(GuildError::NotInGuild),
+                }
+            }
+        },
+        _ => Err(GuildError::NotACharacter),
+    };
+    match result {
+        Ok(guild) => save_and_broadcast(server, guild, None),
+        Err(err) => notify_error(server, invitee, err),
+    }
+}
+
+pub fn handle_guild(server: &mut Server, entity: Entity, manip: GuildManip) {
+    let character_id = match character_id(server.state(), entity) {
+        Some(character_id) => character_id,
+        None => return notify_error(server, entity, GuildError::NotACharacter),
+    };
+
+    let result = match manip {
+        GuildManip::Create { name, tag } => server
+            .state
+            .ecs()
+            .write_resource::<GuildManager>()
+            .create(name, tag, character_id)
+            .map(|guild| (guild.clone(), None)),
+        GuildManip::Invite(uid) => {
+            // Permissions are checked when the invite is sent
+            handle_invite(server, entity, uid, InviteKind::Guild);
+            return;
+        },
+        GuildManip::Kick(target) => modify_guild(server.state(), character_id, |guild| {
+            guild.kick(character_id, target)
+        })
+        .map(|guild| (guild, Some(target))),
+        GuildManip::Promote(target, rank) => modify_guild(server.state(), character_id, |guild| {
+            guild.promote(character_id, target, rank)
+        })
+        .map(|guild| (guild, None)),
+        GuildManip::Leave => server
+            .state
+            .ecs()
+            .write_resource::<GuildManager>()
+            .leave(character_id)
+            .map(|guild| (guild, Some(character_id))),
+    };
+
+    match result {
+        Ok((guild, removed)) => save_and_broadcast(server, guild, removed),
+        Err(err) => notify_error(server, entity, err),
+    }
+}
+
+/// Removes a deleted character from its guild, the database forgets its
+/// membership along with the character
+pub fn remove_deleted_character(server: &Server, character_id: CharacterId) {
+    let guild = server
+        .state
+        .ecs()
+        .write_resource::<GuildManager>()
+        .leave(character_id);
+    if let Ok(guild) = guild {
+        save_and_broadcast(server, guild, None);
+    }
+}
+
+/// Applies `f` to the guild of the character, returning the changed guild
+fn modify_guild(
+    state: &State,
+    character_id: CharacterId,
+    f: impl FnOnce(&mut Guild) -> Result<(), GuildError>,
+) -> Result<Guild, GuildError> {
+    let mut guild_manager = state.ecs().write_resource::<GuildManager>();
+    let id = guild_manager
+        .guild_of(character_id)
+        .map(|guild| guild.id)
+        .ok_or(GuildError::NotInGuild)?;
+    let guild = guild_manager.guild_mut(id).ok_or(GuildError::NotInGuild)?;
+    f(guild)?;
+    Ok(guild.clone())
+}
+
+/// Persists the guild and sends it to all of its online members, as well as
+/// to the member that left or was kicked from it
+fn save_and_broadcast(server: &Server, guild: Guild, removed: Option<CharacterId>) {
+    let ecs = server.state.ecs();
+    let clients = ecs.read_storage::<Client>();
+    let presences = ecs.read_storage::<Presence>();
+    for (client, presence) in (&clients, &presences).join() {
+        if let PresenceKind::Character(character_id) = presence.kind {
+            if guild.member(character_id).is_some() || removed == Some(character_id) {
+                client.send_fallible(ServerGeneral::GuildUpdate(guild.clone()));
+            }
+        }
+    }
+    ecs.write_resource::<CharacterUpdater>().save_guild(guild);
+}
+
+fn notify_error(server: &Server, entity: Entity, err: GuildError) {
+    server.notify_client(
+        entity,
+        ServerGeneral::server_msg(ChatType::Meta, err.to_string()),
+    );
+}
@@ -6,22 +6,30 @@ use common::{
     comp::{
         self,
         agent::{AgentEvent, Sound, SoundKind},
+        cooking::{COOKING_DURATION, FUEL_ITEM, FUEL_PER_LOG},
         dialogue::Subject,
         inventory::slot::EquipSlot,
-        tool::ToolKind,
-        Inventory, Pos, SkillGroupKind,
+        tool::{AbilityMap, MaterialStatManifest, ToolKind},
+        ChatType, Inventory, Pos, SkillGroupKind,
     },
-    consts::{MAX_MOUNT_RANGE, SOUND_TRAVEL_DIST_PER_VOLUME},
+    consts::{MAX_MOUNT_RANGE, MAX_PICKUP_RANGE, SOUND_TRAVEL_DIST_PER_VOLUME},
     link::Is,
     mounting::{Mount, Mounting, Rider},
     outcome::Outcome,
-    terrain::{Block, SpriteKind},
+    recipe::default_recipe_book,
+    resources::Time,
+    terrain::{BiomeKind, Block, SpriteKind},
     uid::Uid,
     vol::ReadVol,
 };
-use common_net::sync::WorldSyncExt;
+use common_net::{msg::ServerGeneral, sync::WorldSyncExt};
 
-use crate::{state_ext::StateExt, Server};
+use crate::{
+    fishing::{self, FishingLine, FishingMinigame},
+    state_ext::StateExt,
+    sys::sleep::Sleepers,
+    Server,
+};
 
 use crate::pet::tame_pet;
 use hashbrown::{HashMap, HashSet};
@@ -182,10 +190,12 @@ pub fn handle_mine_block(
                             .get(item.item_definition_id()),
                     ) {
                         let skill_group = SkillGroupKind::Weapon(tool);
+                        let exp = (*exp_reward as f32 * skillset.xp_gain_multiplier(skill_group))
+                            .round() as u32;
                         let mut outcomes = state.ecs().write_resource::<Vec<Outcome>>();
                         let positions = state.ecs().read_component::<comp::Pos>();
                         if let (Some(level_outcome), Some(pos)) = (
-                            skillset.add_experience(skill_group, *exp_reward),
+                            skillset.add_experience(skill_group, exp),
                             positions.get(entity),
                         ) {
                             outcomes.push(Outcome::SkillPointGain {
@@ -197,7 +207,7 @@ pub fn handle_mine_block(
                         }
                         outcomes.push(Outcome::ExpChange {
                             uid,
-                            exp: *exp_reward,
+                            exp,
                             xp_pools: HashSet::from_iter(vec![skill_group]),
                         });
                     }
@@ -251,6 +261,370 @@ pub fn handle_mine_block(
     }
 }
 
+/// Players can't lie down for this long after they were hurt, in seconds
+const SLEEP_COMBAT_COOLDOWN: f64 = 10.0;
+
+pub fn handle_sleep(server: &mut Server, entity: EcsEntity, bed: Vec3<i32>) {
+    let refusal = {
+        let state = server.state();
+        let ecs = state.ecs();
+        let in_reach = ecs.read_storage::<Pos>().get(entity).map_or(false, |pos| {
+            pos.0.distance_squared(bed.map(|e| e as f32 + 0.5)) < MAX_PICKUP_RANGE.powi(2)
+        });
+        let is_bed = state
+            .terrain()
+            .get(bed)
+            .ok()
+            .and_then(|block| block.get_sprite())
+            == Some(SpriteKind::Bed);
+        let in_combat = ecs
+            .read_storage::<comp::Health>()
+            .get(entity)
+            .map_or(false, |health| {
+                health.last_change.amount < 0.0
+                    && ecs.read_resource::<Time>().0 - health.last_change.time.0
+                        < SLEEP_COMBAT_COOLDOWN
+            });
+
+        if !in_reach || !is_bed {
+            return;
+        } else if in_combat {
+            Some("You can't sleep while in combat")
+        } else if !state.get_day_period().is_dark() {
+            Some("You can only sleep at night")
+        } else {
+            None
+        }
+    };
+
+    if let Some(refusal) = refusal {
+        server.notify_client(
+            entity,
+            ServerGeneral::server_msg(ChatType::CommandError, refusal),
+        );
+        return;
+    }
+
+    let ecs = server.state().ecs();
+    if let Some(health) = ecs.read_storage::<comp::Health>().get(entity) {
+        let _ = ecs
+            .write_storage()
+            .insert(entity, comp::CharacterState::Sit);
+        ecs.write_resource::<Sleepers>()
+            .lie_down(entity, health.current());
+    }
+}
+
+pub fn handle_start_fishing(server: &mut Server, entity: EcsEntity) {
+    let ecs = server.state().ecs();
+    let cast = {
+        let holds_rod = ecs
+            .read_storage::<Inventory>()
+            .get(entity)
+            .map_or(false, fishing::holds_fishing_rod);
+        let is_fishing = ecs.read_storage::<FishingLine>().contains(entity);
+        match (
+            ecs.read_storage::<Pos>().get(entity),
+            ecs.read_storage::<comp::Body>().get(entity),
+            ecs.read_storage::<comp::Controller>().get(entity),
+            ecs.uid_from_entity(entity),
+        ) {
+            (Some(pos), Some(body), Some(controller), Some(uid)) if holds_rod && !is_fishing => {
+                let terrain = ecs.read_resource::<common::terrain::TerrainGrid>();
+                let eye = pos.0 + Vec3::unit_z() * body.eye_height();
+                fishing::cast_line(&*terrain, eye, *controller.inputs.look_dir).map(|landing| {
+                    let biome = terrain
+                        .get_key(terrain.pos_key(landing.as_()))
+                        .map_or(BiomeKind::Void, |chunk| chunk.meta().biome());
+                    (uid, landing, biome)
+                })
+            },
+            _ => None,
+        }
+    };
+
+    if let Some((uid, landing, biome)) = cast {
+        let bobber = server
+            .state
+            .create_object(Pos(landing), comp::object::Body::FishingBobber)
+            .with(comp::Object::Bobber { owner: uid })
+            .build();
+        let ecs = server.state().ecs();
+        let line = FishingLine::new(bobber, ecs.read_resource::<Time>().0, rand::random(), biome);
+        let _ = ecs.write_storage().insert(entity, line);
+        let _ = ecs.write_storage().insert(
+            entity,
+            comp::CharacterState::Fishing(common::states::fishing::Data { bobber: landing }),
+        );
+        ecs.write_resource::<Vec<Outcome>>().push(Outcome::Splash {
+            pos: landing,
+            power: 0.5,
+        });
+    }
+}
+
+/// Remembers when the player reeled in, the fishing system judges whether a
+/// fish was hooked
+pub fn handle_reel_in(server: &mut Server, entity: EcsEntity) {
+    let ecs = server.state().ecs();
+    if let Some(line) = ecs.write_storage::<FishingLine>().get_mut(entity) {
+        line.reel_in.get_or_insert(ecs.read_resource::<Time>().0);
+    }
+}
+
+pub fn handle_reel_fishing_line(server: &mut Server, entity: EcsEntity, reeling: bool) {
+    if let Some(minigame) = server
+        .state()
+        .ecs()
+        .write_storage::<FishingMinigame>()
+        .get_mut(entity)
+    {
+        minigame.reeling = reeling;
+    }
+}
+
+pub fn handle_create_item_drop(server: &mut Server, pos: Pos, item: comp::Item) {
+    server.state.create_item_drop(pos, &item).build();
+}
+
+/// Whether the block at `pos` is close enough to `entity` to interact with it
+fn within_reach(server: &Server, entity: EcsEntity, pos: Vec3<i32>) -> bool {
+    server
+        .state()
+        .ecs()
+        .read_storage::<Pos>()
+        .get(entity)
+        .map_or(false, |entity_pos| {
+            entity_pos.0.distance_squared(pos.map(|e| e as f32 + 0.5)) < MAX_PICKUP_RANGE.powi(2)
+        })
+}
+
+/// The farm plot at `pos`, a crop sprite becomes one once it's tended to
+fn farm_plot_at(server: &mut Server, pos: Vec3<i32>, create: bool) -> Option<EcsEntity> {
+    let existing = {
+        let ecs = server.state().ecs();
+        (
+            &ecs.entities(),
+            &ecs.read_storage::<Pos>(),
+            &ecs.read_storage::<comp::FarmPlot>(),
+        )
+            .join()
+            .find(|(_, plot_pos, _)| plot_pos.0.map(|e| e.floor() as i32) == pos)
+            .map(|(entity, _, _)| entity)
+    };
+    if existing.is_some() || !create {
+        return existing;
+    }
+
+    let crop = server
+        .state()
+        .terrain()
+        .get(pos)
+        .ok()
+        .and_then(|block| block.get_sprite())
+        .and_then(comp::CropKind::from_sprite)?;
+    Some(
+        server
+            .state_mut()
+            .ecs_mut()
+            .create_entity()
+            .with(Pos(pos.map(|e| e as f32 + 0.5)))
+            .with(comp::FarmPlot::new(crop))
+            .build(),
+    )
+}
+
+pub fn handle_water_crop(server: &mut Server, entity: EcsEntity, pos: Vec3<i32>) {
+    if !within_reach(server, entity, pos) {
+        return;
+    }
+    if let Some(plot) = farm_plot_at(server, pos, true) {
+        let ecs = server.state().ecs();
+        let time = *ecs.read_resource::<Time>();
+        if let Some(plot) = ecs.write_storage::<comp::FarmPlot>().get_mut(plot) {
+            plot.water(time);
+        }
+    }
+}
+
+pub fn handle_harvest_crop(server: &mut Server, entity: EcsEntity, pos: Vec3<i32>) {
+    if !within_reach(server, entity, pos) {
+        return;
+    }
+    let harvest = farm_plot_at(server, pos, false).and_then(|plot| {
+        server
+            .state()
+            .ecs()
+            .write_storage::<comp::FarmPlot>()
+            .get_mut(plot)
+            .and_then(|plot| plot.harvest())
+    });
+    if let Some((item_id, amount)) = harvest {
+        let mut item = comp::Item::new_from_asset_expect(item_id);
+        let _ = item.set_amount(amount);
+        server
+            .state
+            .create_item_drop(Pos(pos.map(|e| e as f32) + Vec3::new(0.5, 0.5, 1.0)), &item)
+            .build();
+    }
+}
+
+/// The cooking pot at `pos`, a cooking pot sprite becomes one once a recipe is
+/// loaded into it
+fn cooking_pot_at(server: &mut Server, pos: Vec3<i32>) -> Option<EcsEntity> {
+    let existing = {
+        let ecs = server.state().ecs();
+        (
+            &ecs.entities(),
+            &ecs.read_storage::<Pos>(),
+            &ecs.read_storage::<comp::CookingPot>(),
+        )
+            .join()
+            .find(|(_, pot_pos, _)| pot_pos.0.map(|e| e.floor() as i32) == pos)
+            .map(|(entity, _, _)| entity)
+    };
+    if existing.is_some() {
+        return existing;
+    }
+
+    let sprite = server
+        .state()
+        .terrain()
+        .get(pos)
+        .ok()
+        .and_then(|block| block.get_sprite());
+    (sprite == Some(SpriteKind::CookingPot)).then(|| {
+        server
+            .state_mut()
+            .ecs_mut()
+            .create_entity()
+            .with(Pos(pos.map(|e| e as f32 + 0.5)))
+            .with(comp::CookingPot::default())
+            .build()
+    })
+}
+
+pub fn handle_load_recipe_into_pot(
+    server: &mut Server,
+    entity: EcsEntity,
+    pos: Vec3<i32>,
+    recipe_id: String,
+) {
+    if !within_reach(server, entity, pos) {
+        return;
+    }
+    let recipe = match default_recipe_book()
+        .read()
+        .get(&recipe_id)
+        .filter(|recipe| recipe.craft_sprite == Some(SpriteKind::CookingPot))
+    {
+        Some(recipe) => recipe.clone(),
+        None => return,
+    };
+    let pot = match cooking_pot_at(server, pos) {
+        Some(pot) => pot,
+        None => return,
+    };
+
+    let ecs = server.state().ecs();
+    let mut inventories = ecs.write_storage::<Inventory>();
+    let mut pots = ecs.write_storage::<comp::CookingPot>();
+    let (inventory, pot) = match (inventories.get_mut(entity), pots.get_mut(pot)) {
+        (Some(inventory), Some(pot)) => (inventory, pot),
+        _ => return,
+    };
+    let slots = match recipe.inventory_contains_ingredients(inventory) {
+        Ok(slots) => slots,
+        Err(_) => return,
+    };
+    let ability_map = ecs.read_resource::<AbilityMap>();
+    let msm = ecs.read_resource::<MaterialStatManifest>();
+
+    // Inputs that aren't consumed, like tools, stay in the inventory
+    for (i, (_, mut needed)) in recipe.inputs().enumerate() {
+        for &(_, slot) in slots.iter().filter(|(input, _)| *input == i as u32) {
+            while needed > 0 {
+                let item = match inventory.take(slot, &ability_map, &msm) {
+                    Some(item) => item,
+                    None => break,
+                };
+                needed -= 1;
+                let stacked = pot.contents.iter_mut().any(|stack| {
+                    stack.item_definition_id() == item.item_definition_id()
+                        && stack.increase_amount(1).is_ok()
+                });
+                if !stacked {
+                    pot.contents.push(item);
+                }
+            }
+        }
+    }
+    if pot.fuel_remaining < COOKING_DURATION {
+        let fuel = inventory
+            .slots_with_id()
+            .find(|(_, slot)| {
+                slot.as_ref()
+                    .map_or(false, |item| item.item_definition_id() == FUEL_ITEM)
+            })
+            .map(|(slot, _)| slot);
+        if fuel
+            .and_then(|slot| inventory.take(slot, &ability_map, &msm))
+            .is_some()
+        {
+            pot.refuel(FUEL_PER_LOG);
+        }
+    }
+    pot.recipe = Some(recipe_id);
+
+    let _ = ecs.write_storage().insert(
+        entity,
+        comp::InventoryUpdate::new(comp::InventoryUpdateEvent::Used),
+    );
+}
+
+pub fn handle_take_from_pot(server: &mut Server, entity: EcsEntity, pos: Vec3<i32>) {
+    if !within_reach(server, entity, pos) {
+        return;
+    }
+    let pot = match cooking_pot_at(server, pos) {
+        Some(pot) => pot,
+        None => return,
+    };
+
+    let overflow = {
+        let ecs = server.state().ecs();
+        let mut inventories = ecs.write_storage::<Inventory>();
+        let mut pots = ecs.write_storage::<comp::CookingPot>();
+        let (inventory, pot) = match (inventories.get_mut(entity), pots.get_mut(pot)) {
+            (Some(inventory), Some(pot)) => (inventory, pot),
+            _ => return,
+        };
+        let recipe_book = default_recipe_book().read();
+        let recipe = pot
+            .recipe
+            .as_ref()
+            .and_then(|recipe| recipe_book.get(recipe));
+        let dishes = pot.take_dishes(recipe);
+        if dishes.is_empty() {
+            return;
+        }
+        let _ = ecs.write_storage().insert(
+            entity,
+            comp::InventoryUpdate::new(comp::InventoryUpdateEvent::Collected(dishes[0].clone())),
+        );
+        dishes
+            .into_iter()
+            .filter_map(|dish| inventory.push(dish).err())
+            .collect::<Vec<_>>()
+    };
+    for dish in overflow {
+        server
+            .state
+            .create_item_drop(Pos(pos.map(|e| e as f32) + Vec3::new(0.5, 0.5, 1.0)), &dish)
+            .build();
+    }
+}
+
 pub fn handle_sound(server: &mut Server, sound: &Sound) {
     let ecs = &server.state.ecs();
     let positions = &ecs.read_storage::<comp::Pos>();
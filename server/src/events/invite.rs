@@ -1,23 +1,28 @@
-use super::group_manip::{self, update_map_markers};
-use crate::{client::Client, Server};
+use super::{
+    friend_manip,
+    group_manip::{self, update_map_markers},
+    guild_manip,
+};
+use crate::{client::Client, presence::Presence, Server};
 use common::{
     comp::{
         self,
         agent::{Agent, AgentEvent},
         group::GroupManager,
         invite::{Invite, InviteKind, InviteResponse, PendingInvites},
-        ChatType, Pos,
+        ChatType, Gravestone, Pos,
     },
     consts::MAX_TRADE_RANGE,
+    resources::Time,
     trade::{TradeResult, Trades},
     uid::Uid,
 };
 use common_net::{
-    msg::{InviteAnswer, ServerGeneral},
+    msg::{InviteAnswer, PresenceKind, ServerGeneral},
     sync::WorldSyncExt,
 };
 use common_state::State;
-use specs::{world::WorldExt, Entity};
+use specs::{world::WorldExt, Entity, ReadStorage};
 use std::time::{Duration, Instant};
 use tracing::{error, warn};
 
@@ -72,38 +77,61 @@ pub fn handle_invite(
         }
     }
 
-    if let InviteKind::Group = kind {
-        if !group_manip::can_invite(
-            state,
-            &clients,
-            &mut pending_invites,
-            max_group_size,
-            inviter,
-            invitee,
-        ) {
-            return;
-        }
-    } else {
-        // cancel current trades for inviter before inviting someone else to trade
-        let mut trades = state.ecs().write_resource::<Trades>();
-        if let Some(inviter_uid) = uids.get(inviter).copied() {
-            if let Some(active_trade) = trades.entity_trades.get(&inviter_uid).copied() {
-                trades
-                    .decline_trade(active_trade, inviter_uid)
-                    .and_then(|u| state.ecs().entity_from_uid(u.0))
-                    .map(|e| {
-                        if let Some(client) = clients.get(e) {
-                            client
-                                .send_fallible(ServerGeneral::FinishedTrade(TradeResult::Declined));
-                        }
-                        if let Some(agent) = agents.get_mut(e) {
-                            agent
-                                .inbox
-                                .push_back(AgentEvent::FinishedTrade(TradeResult::Declined));
-                        }
-                    });
+    match kind {
+        InviteKind::Group => {
+            if !group_manip::can_invite(
+                state,
+                &clients,
+                &mut pending_invites,
+                max_group_size,
+                inviter,
+                invitee,
+            ) {
+                return;
             }
-        };
+        },
+        InviteKind::Guild => {
+            if let Err(err) = guild_manip::can_invite(state, inviter, invitee) {
+                if let Some(client) = clients.get(inviter) {
+                    client.send_fallible(ServerGeneral::server_msg(
+                        ChatType::Meta,
+                        format!("Invite failed, {}", err),
+                    ));
+                }
+                return;
+            }
+        },
+        // Checked when the friend request was made
+        InviteKind::Friend => {},
+        InviteKind::Trade => {
+            // cancel current trades for inviter before inviting someone else to trade
+            let mut trades = state.ecs().write_resource::<Trades>();
+            if let Some(inviter_uid) = uids.get(inviter).copied() {
+                if let Some(active_trade) = trades.entity_trades.get(&inviter_uid).copied() {
+                    trades
+                        .decline_trade(active_trade, inviter_uid)
+                        .and_then(|u| state.ecs().entity_from_uid(u.0))
+                        .map(|e| {
+                            if let Some(client) = clients.get(e) {
+                                client.send_fallible(ServerGeneral::FinishedTrade(
+                                    TradeResult::Declined,
+                                ));
+                            }
+                            if let Some(agent) = agents.get_mut(e) {
+                                agent
+                                    .inbox
+                                    .push_back(AgentEvent::FinishedTrade(TradeResult::Declined));
+                            }
+                        });
+                }
+            };
+            // Gravestones can't answer invites, so looting them starts right away
+            if state.ecs().read_storage::<Gravestone>().contains(invitee) {
+                drop(trades);
+                loot_gravestone(state, &clients, &uids, inviter, invitee);
+                return;
+            }
+        },
     }
 
     if invites.contains(invitee) {
@@ -180,6 +208,56 @@ pub fn handle_invite(
         }
     }
 }
+
+/// Starts a trade between `looter` and a gravestone, if the looter may take
+/// the items in it
+fn loot_gravestone(
+    state: &State,
+    clients: &ReadStorage<Client>,
+    uids: &ReadStorage<Uid>,
+    looter: Entity,
+    gravestone: Entity,
+) {
+    let character = state
+        .ecs()
+        .read_storage::<Presence>()
+        .get(looter)
+        .and_then(|presence| match presence.kind {
+            PresenceKind::Character(id) => Some(id),
+            PresenceKind::Spectator | PresenceKind::Possessor => None,
+        });
+    let time = *state.ecs().read_resource::<Time>();
+    let send_failure = |msg: &str| {
+        if let Some(client) = clients.get(looter) {
+            client.send_fallible(ServerGeneral::server_msg(ChatType::Meta, msg));
+        }
+    };
+    if !state
+        .ecs()
+        .read_storage::<Gravestone>()
+        .get(gravestone)
+        .map_or(false, |gravestone| gravestone.can_loot(character, time))
+    {
+        send_failure("Only its owner can loot this gravestone for now.");
+        return;
+    }
+
+    if let (Some(looter_uid), Some(gravestone_uid)) =
+        (uids.get(looter).copied(), uids.get(gravestone).copied())
+    {
+        let mut trades = state.ecs().write_resource::<Trades>();
+        if trades.entity_trades.contains_key(&gravestone_uid) {
+            send_failure("Someone else is looting this gravestone.");
+            return;
+        }
+        let id = trades.begin_trade(looter_uid, gravestone_uid);
+        let trade = trades.trades[&id].clone();
+        if let Some(client) = clients.get(looter) {
+            client.send_fallible(ServerGeneral::UpdatePendingTrade(id, trade, None));
+        }
+    }
+}
+
 pub fn handle_invite_response(
     server: &mut Server,
     entity: specs::Entity,
@@ -196,6 +274,11 @@ pub fn handle_invite_accept(server: &mut Server, entity: specs::Entity) {
     let state = server.state_mut();
     if let Some((inviter, kind)) = get_inviter_and_kind(entity, state) {
         handle_invite_answer(state, inviter, entity, InviteAnswer::Accepted, kind);
+        match kind {
+            InviteKind::Guild => return guild_manip::add_member(server, inviter, entity),
+            InviteKind::Friend => return friend_manip::accept_request(server, inviter, entity),
+            InviteKind::Group | InviteKind::Trade => {},
+        }
         let clients = state.ecs().read_storage::<Client>();
         let uids = state.ecs().read_storage::<Uid>();
         let mut agents = state.ecs().write_storage::<Agent>();
@@ -280,6 +363,8 @@ pub fn handle_invite_accept(server: &mut Server, entity: specs::Entity) {
                         .map(|c| c.send(ServerGeneral::UpdatePendingTrade(id, trade, pricing)));
                 }
             },
+            // Handled above, since they need the whole server
+            InviteKind::Guild | InviteKind::Friend => {},
         }
     }
 }
@@ -345,6 +430,9 @@ fn handle_invite_answer(
 pub fn handle_invite_decline(server: &mut Server, entity: specs::Entity) {
     let state = server.state_mut();
     if let Some((inviter, kind)) = get_inviter_and_kind(entity, state) {
+        if kind == InviteKind::Friend {
+            friend_manip::decline_request(state, inviter, entity);
+        }
         // Inform inviter of rejection
         handle_invite_answer(state, inviter, entity, InviteAnswer::Declined, kind)
     }
@@ -12,13 +12,18 @@ use entity_manipulation::{
     handle_aura, handle_bonk, handle_buff, handle_change_ability, handle_combo_change,
     handle_delete, handle_destroy, handle_energy_change, handle_entity_attacked_hook,
     handle_explosion, handle_health_change, handle_knockback, handle_land_on_ground, handle_parry,
-    handle_poise, handle_respawn, handle_teleport_to, handle_update_map_marker,
+    handle_poise, handle_respawn, handle_respec_skills, handle_start_cooldown, handle_teleport_to,
+    handle_update_map_marker,
 };
+use friend_manip::handle_friend;
 use group_manip::handle_group;
+use guild_manip::handle_guild;
 use information::handle_site_info;
 use interaction::{
-    handle_create_sprite, handle_lantern, handle_mine_block, handle_mount, handle_npc_interaction,
-    handle_sound, handle_unmount,
+    handle_create_item_drop, handle_create_sprite, handle_harvest_crop, handle_lantern,
+    handle_load_recipe_into_pot, handle_mine_block, handle_mount, handle_npc_interaction,
+    handle_reel_fishing_line, handle_reel_in, handle_sleep, handle_sound, handle_start_fishing,
+    handle_take_from_pot, handle_unmount, handle_water_crop,
 };
 use inventory_manip::handle_inventory;
 use invite::{handle_invite, handle_invite_response};
@@ -26,11 +31,16 @@ use player::{handle_client_disconnect, handle_exit_ingame, handle_possess};
 use specs::{Builder, Entity as EcsEntity, WorldExt};
 use trade::{cancel_trade_for, handle_process_trade_action};
 
+pub use combat_log::{CombatEvent, CombatLog};
 pub use group_manip::update_map_markers;
+pub use guild_manip::remove_deleted_character;
 
+mod combat_log;
 mod entity_creation;
 mod entity_manipulation;
+mod friend_manip;
 mod group_manip;
+mod guild_manip;
 mod information;
 mod interaction;
 mod inventory_manip;
@@ -106,6 +116,26 @@ impl Server {
                 ServerEvent::Destroy { entity, cause } => handle_destroy(self, entity, cause),
                 ServerEvent::InventoryManip(entity, manip) => handle_inventory(self, entity, manip),
                 ServerEvent::GroupManip(entity, manip) => handle_group(self, entity, manip),
+                ServerEvent::GuildManip(entity, manip) => handle_guild(self, entity, manip),
+                ServerEvent::FriendManip(entity, manip) => handle_friend(self, entity, manip),
+                ServerEvent::RespecSkills(entity) => handle_respec_skills(self, entity),
+                ServerEvent::Sleep { entity, bed } => handle_sleep(self, entity, bed),
+                ServerEvent::StartFishing(entity) => handle_start_fishing(self, entity),
+                ServerEvent::ReelIn(entity) => handle_reel_in(self, entity),
+                ServerEvent::ReelFishingLine { entity, reeling } => {
+                    handle_reel_fishing_line(self, entity, reeling)
+                },
+                ServerEvent::CreateItemDrop { pos, item } => {
+                    handle_create_item_drop(self, pos, item)
+                },
+                ServerEvent::WaterCrop { entity, pos } => handle_water_crop(self, entity, pos),
+                ServerEvent::HarvestCrop { entity, pos } => handle_harvest_crop(self, entity, pos),
+                ServerEvent::LoadRecipeIntoPot {
+                    entity,
+                    pos,
+                    recipe,
+                } => handle_load_recipe_into_pot(self, entity, pos, recipe),
+                ServerEvent::TakeFromPot { entity, pos } => handle_take_from_pot(self, entity, pos),
                 ServerEvent::Respawn(entity) => handle_respawn(self, entity),
                 ServerEvent::LandOnGround { entity, vel } => {
                     handle_land_on_ground(self, entity, vel)
@@ -231,6 +261,9 @@ impl Server {
                 ServerEvent::ComboChange { entity, change } => {
                     handle_combo_change(self, entity, change)
                 },
+                ServerEvent::StartCooldown { entity, ability_id } => {
+                    handle_start_cooldown(self, entity, ability_id)
+                },
                 ServerEvent::Parry {
                     entity,
                     energy_cost,
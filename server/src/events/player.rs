@@ -173,6 +173,7 @@ pub fn handle_client_disconnect(
         state.read_storage::<comp::Player>().get(entity),
     ) {
         state.notify_players(ServerGeneral::server_msg(comp::ChatType::Offline(*uid), ""));
+        super::friend_manip::notify_logout(state, entity);
 
         state.notify_players(ServerGeneral::PlayerListUpdate(PlayerListUpdate::Remove(
             *uid,
@@ -6,6 +6,7 @@ use common::{
             item::{tool::AbilityMap, MaterialStatManifest},
             Inventory,
         },
+        Gravestone,
     },
     trade::{PendingTrade, ReducedInventory, TradeAction, TradeId, TradeResult, Trades},
 };
@@ -86,7 +87,29 @@ pub fn handle_process_trade_action(
                         None
                     }
                 };
+                let accepted_phase = match action {
+                    TradeAction::Accept(phase) => Some(phase),
+                    _ => None,
+                };
                 trades.process_trade_action(trade_id, uid, action, get_inventory);
+                // Gravestones agree to give their items away, as long as nothing is put into
+                // them in return
+                let gravestone = trades.trades.get(&trade_id).and_then(|trade| {
+                    let gravestones = ecs.read_storage::<Gravestone>();
+                    let i = trade.parties.iter().position(|party| {
+                        ecs.entity_from_uid(party.0)
+                            .map_or(false, |entity| gravestones.contains(entity))
+                    })?;
+                    trade.offers[1 - i].is_empty().then(|| trade.parties[i])
+                });
+                if let (Some(phase), Some(gravestone)) = (accepted_phase, gravestone) {
+                    trades.process_trade_action(
+                        trade_id,
+                        gravestone,
+                        TradeAction::Accept(phase),
+                        get_inventory,
+                    );
+                }
             }
             if let Entry::Occupied(entry) = trades.trades.entry(trade_id) {
                 let parties = entry.get().parties;
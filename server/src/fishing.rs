@@ -0,0 +1,408 @@
+//! Fishing minigame, started by casting the line of a fishing rod into water.
+//!
+//! The bobber floats on the water until a fish bites, after a random delay.
+//! The bobber dips then and the player has a short time to reel in and hook
+//! the fish, reeling in at any other time brings in nothing. The timing is
+//! only judged by the server, by when the reel in arrived.
+//!
+//! Once a fish is on the line the player has to reel it in by holding the
+//! reel key, which strains the line. The fish pulls on the line as well, so
+//! the player has to let go whenever the tension gets too high, the line
+//! breaks once it reaches 1.0. A landed fish is rolled on the loot table of
+//! the water the bobber floats on.
+
+use common::{
+    comp::{
+        inventory::slot::EquipSlot,
+        item::{tool::ToolKind, Item, ItemKind},
+        Inventory,
+    },
+    lottery::{LootSpec, Lottery},
+    ray::{RayCast, RayTarget},
+    terrain::{BiomeKind, Block},
+    vol::ReadVol,
+};
+use common_net::msg::FishingState;
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use specs::{Component, Entity as EcsEntity};
+use specs_idvs::IdvStorage;
+use vek::*;
+
+/// How far the line of a fishing rod flies when cast
+pub const CAST_RANGE: f32 = 12.0;
+/// How far the line drops at the end of its flight to find water
+const CAST_DEPTH: f32 = 8.0;
+
+/// Shortest time the line has to be in the water for a fish to bite, in
+/// seconds
+pub const MIN_CAST_DURATION: f64 = 4.0;
+/// Longest time until a fish bites, in seconds
+const MAX_BITE_DELAY: f64 = 20.0;
+/// How long the player has to reel in after a fish bites, in seconds
+pub const REACTION_WINDOW: f64 = 1.0;
+/// Extra time on top of the reaction window, for the bite to reach the client
+/// and the reel in to come back on a connection with jittery latency
+pub const REACTION_GRACE: f64 = 0.25;
+/// Reeling in sooner than this after the bite can't be a reaction to it
+pub const MIN_REACTION_TIME: f64 = 0.1;
+/// Speed the bobber is pulled under water with when a fish bites
+pub const BITE_DIP_SPEED: f32 = 3.0;
+
+/// Tension of the line when it isn't strained
+const BASE_TENSION: f32 = 0.25;
+/// How much reeling strains the line per second
+const REEL_STRAIN: f32 = 0.25;
+/// How fast the strain eases off per second when not reeling
+const STRAIN_RELIEF: f32 = 0.8;
+/// How much of the line is reeled in per second
+const REEL_SPEED: f32 = 0.15;
+/// How much line the fish takes back per second when it isn't reeled in
+const SLIP_SPEED: f32 = 0.05;
+
+/// When a fish bites at a cast line
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BiteTimer {
+    cast_at: f64,
+    bite_at: f64,
+}
+
+/// What reeling in the line at some time does
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReelIn {
+    /// No fish bites yet, or the reel in came quicker than anyone can react
+    TooEarly,
+    Hooked,
+    /// The fish let go of the bobber again
+    TooLate,
+}
+
+impl BiteTimer {
+    /// The bite delay comes from the same seed as the catch
+    pub fn new(cast_at: f64, seed: u32) -> Self {
+        let mut rng = SmallRng::seed_from_u64(u64::from(seed));
+        Self {
+            cast_at,
+            bite_at: cast_at + rng.gen_range(MIN_CAST_DURATION..MAX_BITE_DELAY),
+        }
+    }
+
+    pub fn is_biting(&self, time: f64) -> bool { time >= self.bite_at }
+
+    /// Whether the fish let go without the line being reeled in
+    pub fn timed_out(&self, time: f64) -> bool {
+        time > self.bite_at + REACTION_WINDOW + REACTION_GRACE
+    }
+
+    pub fn reel_in(&self, time: f64) -> ReelIn {
+        if time - self.cast_at < MIN_CAST_DURATION || time < self.bite_at + MIN_REACTION_TIME {
+            ReelIn::TooEarly
+        } else if self.timed_out(time) {
+            ReelIn::TooLate
+        } else {
+            ReelIn::Hooked
+        }
+    }
+}
+
+/// The line of a fishing rod cast into water, from the cast until it is
+/// reeled in. The fish is played with a [`FishingMinigame`] once hooked.
+#[derive(Clone, Debug)]
+pub struct FishingLine {
+    pub bobber: EcsEntity,
+    pub timer: BiteTimer,
+    /// Seed of the bite delay, the fish and the catch
+    pub seed: u32,
+    pub loot_table: &'static str,
+    /// Whether the bite was shown to the players around
+    pub bitten: bool,
+    /// Time the player asked to reel in, judged by the fishing system
+    pub reel_in: Option<f64>,
+}
+
+impl FishingLine {
+    pub fn new(bobber: EcsEntity, cast_at: f64, seed: u32, biome: BiomeKind) -> Self {
+        Self {
+            bobber,
+            timer: BiteTimer::new(cast_at, seed),
+            seed,
+            loot_table: loot_table(biome),
+            bitten: false,
+            reel_in: None,
+        }
+    }
+
+    /// Hooks the fish that bit, the seed also decides how hard it fights
+    pub fn hook(&self) -> FishingMinigame {
+        // Not the same numbers as the bite delay
+        let mut rng = SmallRng::seed_from_u64(u64::from(!self.seed));
+        FishingMinigame::new(FishKind::random(&mut rng), self.seed)
+    }
+
+    /// Rolls the catch on the loot table of the water, `None` for loot
+    /// tables that come up empty
+    pub fn catch(&self) -> Option<Item> {
+        Lottery::<LootSpec<String>>::load_expect(self.loot_table)
+            .read()
+            .choose_seeded(self.seed)
+            .to_item()
+    }
+}
+
+impl Component for FishingLine {
+    type Storage = IdvStorage<Self>;
+}
+
+/// Loot table for fish caught in the water of a biome, water outside of
+/// oceans and lakes is taken to be a river
+pub fn loot_table(biome: BiomeKind) -> &'static str {
+    match biome {
+        BiomeKind::Ocean => "common.loot_tables.fishing.ocean",
+        BiomeKind::Lake | BiomeKind::Swamp => "common.loot_tables.fishing.lake",
+        _ => "common.loot_tables.fishing.river",
+    }
+}
+
+/// Where the bobber lands when the line is cast from `from` in direction
+/// `dir`. The line flies straight until it hits something or its range runs
+/// out, then it drops down. It has to land on water.
+pub fn cast_line<V: ReadVol<Vox = Block>>(
+    terrain: &V,
+    from: Vec3<f32>,
+    dir: Vec3<f32>,
+) -> Option<Vec3<f32>> {
+    let stops_line = |block: &Block| block.is_filled() || block.is_liquid();
+    let hit = RayCast::new(terrain, from, dir, CAST_RANGE)
+        .until(stops_line)
+        .cast()
+        .or_else(|| {
+            let end = from + dir.try_normalized()? * CAST_RANGE;
+            RayCast::new(terrain, end, -Vec3::unit_z(), CAST_DEPTH)
+                .until(stops_line)
+                .cast()
+        })?;
+
+    let mut surface = match hit.target {
+        RayTarget::Block(pos) if terrain.get(pos).map_or(false, Block::is_liquid) => pos,
+        _ => return None,
+    };
+    while terrain
+        .get(surface + Vec3::unit_z())
+        .map_or(false, Block::is_liquid)
+    {
+        surface.z += 1;
+    }
+    Some(hit.pos.with_z(surface.z as f32 + 1.0))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FishKind {
+    Minnow,
+    Trout,
+    Pike,
+}
+
+impl FishKind {
+    pub fn random(rng: &mut impl Rng) -> Self {
+        match rng.gen_range(0..10) {
+            0..=4 => FishKind::Minnow,
+            5..=8 => FishKind::Trout,
+            _ => FishKind::Pike,
+        }
+    }
+
+    /// How hard the fish pulls on the line
+    fn strength(self) -> f32 {
+        match self {
+            FishKind::Minnow => 0.15,
+            FishKind::Trout => 0.3,
+            FishKind::Pike => 0.45,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct FishingMinigame {
+    /// How much of the line was reeled in, the fish is caught at 1.0
+    pub progress: f32,
+    pub fish_kind: FishKind,
+    /// The line breaks at 1.0
+    pub tension: f32,
+    pub state: FishingState,
+    /// Whether the player holds the reel key
+    pub reeling: bool,
+    /// Strain from reeling, on top of the pull of the fish
+    strain: f32,
+    /// Seconds since the fish was hooked, drives the pull of the fish
+    elapsed: f32,
+    seed: u32,
+}
+
+impl FishingMinigame {
+    pub fn new(fish_kind: FishKind, seed: u32) -> Self {
+        Self {
+            progress: 0.0,
+            fish_kind,
+            tension: BASE_TENSION,
+            state: FishingState::Reeling,
+            reeling: false,
+            strain: 0.0,
+            elapsed: 0.0,
+            seed,
+        }
+    }
+
+    pub fn tick(&mut self, dt: f32) -> FishingState {
+        if self.state != FishingState::Reeling {
+            return self.state;
+        }
+        self.elapsed += dt;
+
+        // Reeling at a high tension only strains the line
+        if self.reeling && self.tension < FishingState::MAX_REEL_TENSION {
+            self.progress += REEL_SPEED * dt;
+        } else {
+            self.progress -= SLIP_SPEED * dt;
+        }
+        self.progress = self.progress.clamp(0.0, 1.0);
+
+        self.strain = if self.reeling {
+            self.strain + REEL_STRAIN * dt
+        } else {
+            (self.strain - STRAIN_RELIEF * dt).max(0.0)
+        };
+        let pull = self.fish_kind.strength() * (value_noise(self.elapsed, self.seed) + 1.0) / 2.0;
+        self.tension = BASE_TENSION + pull + self.strain;
+
+        self.state = if self.tension >= 1.0 {
+            FishingState::LineBroken
+        } else if self.progress >= 1.0 {
+            FishingState::Caught
+        } else {
+            FishingState::Reeling
+        };
+        self.state
+    }
+}
+
+impl Component for FishingMinigame {
+    type Storage = IdvStorage<Self>;
+}
+
+/// Whether a fishing rod is wielded in the main hand
+pub fn holds_fishing_rod(inventory: &Inventory) -> bool {
+    inventory.equipped(EquipSlot::ActiveMainhand).map_or(
+        false,
+        |item| matches!(item.kind(), ItemKind::Tool(tool) if tool.kind == ToolKind::Fishing),
+    )
+}
+
+/// Smooth noise in `-1.0..=1.0`, changing direction about once per second
+fn value_noise(t: f32, seed: u32) -> f32 {
+    fn lattice(i: u32, seed: u32) -> f32 {
+        let mut h = i.wrapping_mul(0x9E37_79B1) ^ seed.wrapping_mul(0x85EB_CA6B);
+        h ^= h >> 15;
+        h = h.wrapping_mul(0x2C1B_3C6D);
+        h ^= h >> 12;
+        (h & 0xFFFF) as f32 / 32767.5 - 1.0
+    }
+
+    let i = t.floor();
+    let f = t - i;
+    let smooth = f * f * (3.0 - 2.0 * f);
+    let (a, b) = (lattice(i as u32, seed), lattice(i as u32 + 1, seed));
+    a + (b - a) * smooth
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DT: f32 = 1.0 / 30.0;
+
+    fn play(fish: &mut FishingMinigame, reel: impl Fn(&FishingMinigame) -> bool) -> FishingState {
+        for _ in 0..30 * 120 {
+            fish.reeling = reel(fish);
+            match fish.tick(DT) {
+                FishingState::Reeling => {},
+                state => return state,
+            }
+        }
+        fish.state
+    }
+
+    #[test]
+    fn careful_reeling_catches_the_fish() {
+        for seed in 0..20 {
+            let mut fish = FishingMinigame::new(FishKind::Pike, seed);
+            let state = play(&mut fish, |fish| fish.tension < 0.7);
+            assert_eq!(state, FishingState::Caught, "seed {}", seed);
+            assert_eq!(fish.progress, 1.0);
+        }
+    }
+
+    #[test]
+    fn reeling_without_pause_breaks_the_line() {
+        let mut fish = FishingMinigame::new(FishKind::Minnow, 7);
+        assert_eq!(play(&mut fish, |_| true), FishingState::LineBroken);
+        assert!(fish.tension >= 1.0);
+    }
+
+    #[test]
+    fn the_fish_gets_away_when_not_reeling() {
+        let mut fish = FishingMinigame::new(FishKind::Trout, 3);
+        fish.progress = 0.5;
+        assert_eq!(play(&mut fish, |_| false), FishingState::Reeling);
+        assert_eq!(fish.progress, 0.0);
+        assert!(fish.tension < 1.0);
+    }
+
+    #[test]
+    fn bites_come_after_the_minimum_cast_duration() {
+        for seed in 0..200 {
+            let timer = BiteTimer::new(100.0, seed);
+            assert!(timer.bite_at >= 100.0 + MIN_CAST_DURATION, "seed {}", seed);
+            assert!(timer.bite_at < 100.0 + MAX_BITE_DELAY, "seed {}", seed);
+            assert_eq!(BiteTimer::new(100.0, seed), timer);
+        }
+    }
+
+    #[test]
+    fn reeling_in_before_the_bite_brings_in_nothing() {
+        let timer = BiteTimer::new(0.0, 1);
+        // Spamming the reel key from the start
+        let mut time = 0.0;
+        while time < timer.bite_at {
+            assert_eq!(timer.reel_in(time), ReelIn::TooEarly, "at {}", time);
+            time += 0.05;
+        }
+        // Reacting in no time is a macro
+        assert_eq!(timer.reel_in(timer.bite_at), ReelIn::TooEarly);
+        assert_eq!(
+            timer.reel_in(timer.bite_at + MIN_REACTION_TIME / 2.0),
+            ReelIn::TooEarly
+        );
+    }
+
+    #[test]
+    fn reaction_window_allows_for_jitter() {
+        let timer = BiteTimer::new(0.0, 2);
+        assert_eq!(timer.reel_in(timer.bite_at + 0.3), ReelIn::Hooked);
+        // A reaction in time that was held up on the way
+        let delayed = timer.bite_at + REACTION_WINDOW + REACTION_GRACE / 2.0;
+        assert_eq!(timer.reel_in(delayed), ReelIn::Hooked);
+        assert!(!timer.timed_out(delayed));
+
+        let late = timer.bite_at + REACTION_WINDOW + REACTION_GRACE + 0.01;
+        assert_eq!(timer.reel_in(late), ReelIn::TooLate);
+        assert!(timer.timed_out(late));
+    }
+
+    #[test]
+    fn noise_is_bounded() {
+        for i in 0..1000 {
+            let n = value_noise(i as f32 * 0.037, 42);
+            assert!((-1.0..=1.0).contains(&n));
+        }
+    }
+}
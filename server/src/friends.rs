@@ -0,0 +1,311 @@
+use common::{comp::social::FriendError, uid::Uid};
+use common_net::msg::ServerGeneral;
+use hashbrown::{HashMap, HashSet};
+
+/// Friends of all players of the server, loaded from the database on startup.
+/// Changes are written back through the `CharacterUpdater`, friend requests
+/// are only kept until either player logs out.
+///
+/// Players are identified by their uuid, friends are listed with the alias
+/// they had when the friendship started.
+#[derive(Default)]
+pub struct FriendManager {
+    /// Friends of each player along with their names, by player uuid
+    friends: HashMap<String, HashMap<String, String>>,
+    /// Requests that weren't answered yet, as the uuids of the player who sent
+    /// it and the one it was sent to
+    requests: HashSet<(String, String)>,
+}
+
+/// A player and their alias
+pub type FriendName<'a> = (&'a str, &'a str);
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FriendRequest {
+    /// The other player has to accept the request
+    Sent,
+    /// The other player had sent a request as well, so they are friends now
+    Accepted,
+}
+
+/// Online status of a friend to send to an online player
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FriendStatus {
+    pub recipient: Uid,
+    /// `None` while the friend is offline
+    pub uid: Option<Uid>,
+    pub name: String,
+    pub online: bool,
+}
+
+impl FriendStatus {
+    pub fn into_msg(self) -> ServerGeneral {
+        ServerGeneral::FriendStatus {
+            uid: self.uid,
+            name: self.name,
+            online: self.online,
+        }
+    }
+}
+
+impl FriendManager {
+    /// Takes the friendships as the uuid of a player, the uuid of their friend
+    /// and the name of the friend, each of them is stored in both directions
+    pub fn new(friendships: impl IntoIterator<Item = (String, String, String)>) -> Self {
+        let mut friends: HashMap<_, HashMap<_, _>> = HashMap::new();
+        for (player, friend, name) in friendships {
+            friends.entry(player).or_default().insert(friend, name);
+        }
+        Self {
+            friends,
+            requests: HashSet::new(),
+        }
+    }
+
+    pub fn are_friends(&self, player: &str, other: &str) -> bool {
+        self.friends
+            .get(player)
+            .map_or(false, |friends| friends.contains_key(other))
+    }
+
+    /// The uuids and names of the friends of the player
+    pub fn friends_of<'a>(&'a self, player: &str) -> impl Iterator<Item = (&'a str, &'a str)> {
+        self.friends
+            .get(player)
+            .into_iter()
+            .flatten()
+            .map(|(uuid, name)| (uuid.as_str(), name.as_str()))
+    }
+
+    /// Sends a friend request, which accepts the one `to` sent `from` if there
+    /// is one
+    pub fn request(
+        &mut self,
+        from: FriendName,
+        to: FriendName,
+    ) -> Result<FriendRequest, FriendError> {
+        if from.0 == to.0 {
+            Err(FriendError::Yourself)
+        } else if self.are_friends(from.0, to.0) {
+            Err(FriendError::AlreadyFriends)
+        } else if self.requests.remove(&(to.0.to_owned(), from.0.to_owned())) {
+            self.add(from, to);
+            Ok(FriendRequest::Accepted)
+        } else if self.requests.insert((from.0.to_owned(), to.0.to_owned())) {
+            Ok(FriendRequest::Sent)
+        } else {
+            Err(FriendError::AlreadyRequested)
+        }
+    }
+
+    /// Accepts the request `from` sent to `player`
+    pub fn accept(&mut self, player: FriendName, from: FriendName) -> Result<(), FriendError> {
+        if self
+            .requests
+            .remove(&(from.0.to_owned(), player.0.to_owned()))
+        {
+            self.add(player, from);
+            Ok(())
+        } else {
+            Err(FriendError::NoRequest)
+        }
+    }
+
+    /// Declines the request `from` sent to `player`, returns false if there
+    /// was none
+    pub fn decline(&mut self, player: &str, from: &str) -> bool {
+        self.requests.remove(&(from.to_owned(), player.to_owned()))
+    }
+
+    fn add(&mut self, a: FriendName, b: FriendName) {
+        for (player, friend) in [(a, b), (b, a)] {
+            self.friends
+                .entry(player.0.to_owned())
+                .or_default()
+                .insert(friend.0.to_owned(), friend.1.to_owned());
+        }
+    }
+
+    /// Ends the friendship with the friend of the given name, returns the uuid
+    /// and name of the former friend
+    pub fn remove(&mut self, player: &str, name: &str) -> Result<(String, String), FriendError> {
+        let (friend, name) = self
+            .friends_of(player)
+            .find(|(_, friend_name)| friend_name.eq_ignore_ascii_case(name))
+            .map(|(uuid, name)| (uuid.to_owned(), name.to_owned()))
+            .ok_or(FriendError::NotFriends)?;
+        for (a, b) in [(player, friend.as_str()), (friend.as_str(), player)] {
+            if let Some(friends) = self.friends.get_mut(a) {
+                friends.remove(b);
+                if friends.is_empty() {
+                    self.friends.remove(a);
+                }
+            }
+        }
+        Ok((friend, name))
+    }
+
+    /// Withdraws or declines the request between the two players, returns
+    /// false if there was none
+    pub fn cancel_request(&mut self, player: &str, other: &str) -> bool {
+        let sent = self.requests.remove(&(player.to_owned(), other.to_owned()));
+        let received = self.requests.remove(&(other.to_owned(), player.to_owned()));
+        sent || received
+    }
+
+    /// Statuses to send when a player logs in: the ones of all of their
+    /// friends to them, and theirs to their online friends. `online` holds the
+    /// uids of the players that are online by uuid, including the player.
+    pub fn login(
+        &self,
+        player: FriendName,
+        uid: Uid,
+        online: &HashMap<String, Uid>,
+    ) -> Vec<FriendStatus> {
+        let mut statuses = Vec::new();
+        for (friend, name) in self.friends_of(player.0) {
+            let friend_uid = online.get(friend).copied();
+            statuses.push(FriendStatus {
+                recipient: uid,
+                uid: friend_uid,
+                name: name.to_owned(),
+                online: friend_uid.is_some(),
+            });
+            if let Some(friend_uid) = friend_uid {
+                statuses.push(FriendStatus {
+                    recipient: friend_uid,
+                    uid: Some(uid),
+                    name: player.1.to_owned(),
+                    online: true,
+                });
+            }
+        }
+        statuses
+    }
+
+    /// Statuses to send to the online friends of a player that logs out, and
+    /// drops the requests sent by or to them
+    pub fn logout(
+        &mut self,
+        player: FriendName,
+        online: &HashMap<String, Uid>,
+    ) -> Vec<FriendStatus> {
+        self.requests
+            .retain(|(from, to)| from != player.0 && to != player.0);
+        self.friends_of(player.0)
+            .filter_map(|(friend, _)| online.get(friend))
+            .map(|friend_uid| FriendStatus {
+                recipient: *friend_uid,
+                uid: None,
+                name: player.1.to_owned(),
+                online: false,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALICE: FriendName = ("uuid-alice", "alice");
+    const BOB: FriendName = ("uuid-bob", "bob");
+
+    #[test]
+    fn requests_have_to_be_accepted() {
+        let mut friends = FriendManager::default();
+        assert_eq!(friends.request(ALICE, ALICE), Err(FriendError::Yourself));
+        assert_eq!(friends.request(ALICE, BOB), Ok(FriendRequest::Sent));
+        assert_eq!(
+            friends.request(ALICE, BOB),
+            Err(FriendError::AlreadyRequested)
+        );
+        assert!(!friends.are_friends(ALICE.0, BOB.0));
+        // Only the player the request was sent to can accept it
+        assert_eq!(friends.accept(ALICE, BOB), Err(FriendError::NoRequest));
+        assert_eq!(friends.accept(BOB, ALICE), Ok(()));
+        assert!(friends.are_friends(ALICE.0, BOB.0) && friends.are_friends(BOB.0, ALICE.0));
+        assert_eq!(friends.friends_of(BOB.0).collect::<Vec<_>>(), [ALICE]);
+        assert_eq!(
+            friends.request(BOB, ALICE),
+            Err(FriendError::AlreadyFriends)
+        );
+
+        assert_eq!(
+            friends.remove(ALICE.0, "BOB"),
+            Ok((BOB.0.to_owned(), BOB.1.to_owned()))
+        );
+        assert!(!friends.are_friends(BOB.0, ALICE.0));
+        assert_eq!(friends.remove(ALICE.0, "bob"), Err(FriendError::NotFriends));
+    }
+
+    #[test]
+    fn requests_can_be_answered_in_kind_or_declined() {
+        let mut friends = FriendManager::default();
+        assert_eq!(friends.request(ALICE, BOB), Ok(FriendRequest::Sent));
+        assert!(friends.decline(BOB.0, ALICE.0));
+        assert_eq!(friends.accept(BOB, ALICE), Err(FriendError::NoRequest));
+
+        // Adding a player who sent a request accepts it
+        assert_eq!(friends.request(ALICE, BOB), Ok(FriendRequest::Sent));
+        assert_eq!(friends.request(BOB, ALICE), Ok(FriendRequest::Accepted));
+        assert!(friends.are_friends(ALICE.0, BOB.0));
+
+        // Requests don't outlive the session
+        let carol = ("uuid-carol", "carol");
+        assert_eq!(friends.request(carol, ALICE), Ok(FriendRequest::Sent));
+        friends.logout(carol, &HashMap::new());
+        assert_eq!(friends.accept(ALICE, carol), Err(FriendError::NoRequest));
+    }
+
+    #[test]
+    fn both_sides_are_notified_of_a_login() {
+        let carol = ("uuid-carol", "carol");
+        let mut friends = FriendManager::new(
+            [
+                (ALICE.0, BOB.0, BOB.1),
+                (BOB.0, ALICE.0, ALICE.1),
+                (BOB.0, carol.0, carol.1),
+                (carol.0, BOB.0, BOB.1),
+            ]
+            .map(|(a, b, c)| (a.to_owned(), b.to_owned(), c.to_owned())),
+        );
+        let (alice_uid, bob_uid) = (Uid(1), Uid(2));
+        let mut online = HashMap::new();
+        online.insert(ALICE.0.to_owned(), alice_uid);
+        online.insert(BOB.0.to_owned(), bob_uid);
+
+        let mut statuses = friends.login(BOB, bob_uid, &online);
+        statuses.sort_by(|a, b| a.name.cmp(&b.name));
+        assert_eq!(statuses, [
+            // Bob learns about his friends, whether they are online or not
+            FriendStatus {
+                recipient: bob_uid,
+                uid: Some(alice_uid),
+                name: ALICE.1.to_owned(),
+                online: true,
+            },
+            // and his online friends learn about him
+            FriendStatus {
+                recipient: alice_uid,
+                uid: Some(bob_uid),
+                name: BOB.1.to_owned(),
+                online: true,
+            },
+            FriendStatus {
+                recipient: bob_uid,
+                uid: None,
+                name: carol.1.to_owned(),
+                online: false,
+            },
+        ]);
+
+        online.remove(BOB.0);
+        assert_eq!(friends.logout(BOB, &online), [FriendStatus {
+            recipient: alice_uid,
+            uid: None,
+            name: BOB.1.to_owned(),
+            online: false,
+        }]);
+    }
+}
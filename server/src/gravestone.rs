@@ -0,0 +1,352 @@
+//! Gravestones hold the items players lose when they die, according to the
+//! server's [`DeathItemPolicy`]. They are looted through the trade UI, and
+//! persisted so that a restart doesn't destroy the items left in them.
+
+use crate::{settings::DeathItemPolicy, StateExt};
+use atomicwrites::{AtomicFile, OverwriteBehavior};
+use common::{
+    character::CharacterId,
+    comp::{object, Gravestone, Inventory, Item, Pos, Stats},
+    resources::Time,
+};
+use common_state::State;
+use serde::{Deserialize, Serialize};
+use specs::{Builder, Entity as EcsEntity, Join, World, WorldExt};
+use std::{
+    fs,
+    io::Write as _,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+use tracing::{error, info};
+use vek::*;
+
+/// Item lost on death under [`DeathItemPolicy::DropCoinsOnly`]
+pub const COINS: &str = "common.items.utility.coins";
+
+const FILENAME: &str = "gravestones.ron";
+/// How often the gravestones are written to disk, in case of a crash
+const AUTOSAVE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Takes the items a dying player loses under `policy` out of their
+/// inventory. Equipped items are always kept.
+pub fn take_lost_items(policy: DeathItemPolicy, inventory: &mut Inventory) -> Vec<Item> {
+    match policy {
+        DeathItemPolicy::KeepAll => Vec::new(),
+        DeathItemPolicy::DropAll => inventory.drain().collect(),
+        DeathItemPolicy::DropCoinsOnly => {
+            let coin_slots = inventory
+                .slots_with_id()
+                .filter(|(_, slot)| {
+                    slot.as_ref()
+                        .map_or(false, |item| item.item_definition_id() == COINS)
+                })
+                .map(|(slot_id, _)| slot_id)
+                .collect::<Vec<_>>();
+            coin_slots
+                .into_iter()
+                .filter_map(|slot_id| inventory.remove(slot_id))
+                .collect()
+        },
+    }
+}
+
+fn container_with(items: Vec<Item>) -> Inventory {
+    let mut inventory = Inventory::new_container(items.len());
+    for item in items {
+        // Can't fail, there is a slot for every item
+        let _ = inventory.push(item);
+    }
+    inventory
+}
+
+fn create_gravestone(
+    state: &mut State,
+    pos: Vec3<f32>,
+    gravestone: Gravestone,
+    items: Vec<Item>,
+) -> EcsEntity {
+    state
+        .create_object(Pos(pos), object::Body::Gravestone)
+        .with(gravestone)
+        .with(container_with(items))
+        .build()
+}
+
+/// Moves the items `entity` loses on death under `policy` into a new
+/// gravestone at its position. Returns the gravestone, if anything was lost.
+pub fn leave_gravestone(
+    state: &mut State,
+    entity: EcsEntity,
+    owner: CharacterId,
+    policy: DeathItemPolicy,
+    grace_period: Duration,
+    lifetime: Duration,
+) -> Option<EcsEntity> {
+    let pos = state.ecs().read_storage::<Pos>().get(entity)?.0;
+    let items = take_lost_items(
+        policy,
+        &mut *state.ecs().write_storage::<Inventory>().get_mut(entity)?,
+    );
+    if items.is_empty() {
+        return None;
+    }
+
+    let time = *state.ecs().read_resource::<Time>();
+    let gravestone = Gravestone {
+        owner,
+        owner_name: state
+            .ecs()
+            .read_storage::<Stats>()
+            .get(entity)
+            .map(|stats| stats.name.clone())
+            .unwrap_or_default(),
+        public_at: Time(time.0 + grace_period.as_secs_f64()),
+        despawn_at: Time(time.0 + lifetime.as_secs_f64()),
+    };
+    Some(create_gravestone(state, pos, gravestone, items))
+}
+
+/// A gravestone as it is written to disk. `Time` restarts from zero with the
+/// server, so the remaining durations are stored instead.
+#[derive(Debug, Serialize, Deserialize)]
+struct SavedGravestone {
+    pos: Vec3<f32>,
+    owner: CharacterId,
+    owner_name: String,
+    /// Seconds left until anyone can loot the gravestone
+    public_in: f64,
+    /// Seconds left until the gravestone despawns
+    despawn_in: f64,
+    items: Vec<Item>,
+}
+
+pub struct GravestonePersistence {
+    path: PathBuf,
+    last_autosave: Instant,
+    /// Whether the file may hold gravestones, so that servers that never had
+    /// any don't write it
+    has_saved: bool,
+}
+
+impl GravestonePersistence {
+    pub fn new(data_dir: &Path) -> Self {
+        let path = data_dir.join(FILENAME);
+        Self {
+            has_saved: path.exists(),
+            path,
+            last_autosave: Instant::now(),
+        }
+    }
+
+    /// Spawns the gravestones that were saved when the server last stopped
+    pub fn load(&self, state: &mut State) {
+        let saved = match fs::read_to_string(&self.path) {
+            Ok(ron) => ron,
+            Err(_) => return,
+        };
+        let saved = match ron::de::from_str::<Vec<SavedGravestone>>(&saved) {
+            Ok(saved) => saved,
+            Err(e) => {
+                let backup_path = self.path.with_extension("ron_backup");
+                error!(
+                    ?e,
+                    "Failed to load gravestones, moving possibly corrupt data to {:?} for you to \
+                     repair.",
+                    backup_path
+                );
+                if let Err(e) = fs::rename(&self.path, backup_path) {
+                    error!(?e, "Failed to rename invalid gravestones file");
+                }
+                return;
+            },
+        };
+
+        info!("Restoring {} gravestones", saved.len());
+        let time = *state.ecs().read_resource::<Time>();
+        for saved in saved {
+            let gravestone = Gravestone {
+                owner: saved.owner,
+                owner_name: saved.owner_name,
+                public_at: Time(time.0 + saved.public_in),
+                despawn_at: Time(time.0 + saved.despawn_in),
+            };
+            create_gravestone(state, saved.pos, gravestone, saved.items);
+        }
+    }
+
+    /// Writes the gravestones to disk every once in a while
+    pub fn maintain(&mut self, ecs: &World) {
+        if self.last_autosave.elapsed() >= AUTOSAVE_INTERVAL {
+            self.last_autosave = Instant::now();
+            self.save(ecs);
+        }
+    }
+
+    pub fn save(&mut self, ecs: &World) {
+        let time = ecs.read_resource::<Time>();
+        let saved = (
+            &ecs.read_storage::<Gravestone>(),
+            &ecs.read_storage::<Pos>(),
+            &ecs.read_storage::<Inventory>(),
+        )
+            .join()
+            .map(|(gravestone, pos, inventory)| SavedGravestone {
+                pos: pos.0,
+                owner: gravestone.owner,
+                owner_name: gravestone.owner_name.clone(),
+                public_in: (gravestone.public_at.0 - time.0).max(0.0),
+                despawn_in: (gravestone.despawn_at.0 - time.0).max(0.0),
+                items: inventory.slots().flatten().cloned().collect(),
+            })
+            .collect::<Vec<_>>();
+        if saved.is_empty() && !self.has_saved {
+            return;
+        }
+
+        let ron = match ron::ser::to_string_pretty(&saved, ron::ser::PrettyConfig::default()) {
+            Ok(ron) => ron,
+            Err(e) => {
+                error!(?e, "Failed to serialize gravestones");
+                return;
+            },
+        };
+        let file = AtomicFile::new(&self.path, OverwriteBehavior::AllowOverwrite);
+        match file.write(|file| file.write_all(ron.as_bytes())) {
+            Ok(()) => self.has_saved = !saved.is_empty(),
+            Err(e) => error!(?e, "Failed to write gravestones to file"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GRACE_PERIOD: Duration = Duration::from_secs(60);
+    const LIFETIME: Duration = Duration::from_secs(600);
+
+    fn item(id: &str, amount: u32) -> Item {
+        let mut item = Item::new_from_asset_expect(id);
+        item.set_amount(amount).unwrap();
+        item
+    }
+
+    fn spawn_player(state: &mut State) -> EcsEntity {
+        let mut inventory = Inventory::new_empty();
+        inventory.push(item(COINS, 50)).unwrap();
+        inventory.push(item("common.items.food.apple", 3)).unwrap();
+        state
+            .ecs_mut()
+            .create_entity()
+            .with(Pos(Vec3::new(10.0, 20.0, 30.0)))
+            .with(Stats::new("Owner".to_owned()))
+            .with(inventory)
+            .build()
+    }
+
+    fn die(state: &mut State, player: EcsEntity, policy: DeathItemPolicy) -> Option<EcsEntity> {
+        leave_gravestone(state, player, 1, policy, GRACE_PERIOD, LIFETIME)
+    }
+
+    fn item_count(state: &State, entity: EcsEntity) -> usize {
+        state
+            .ecs()
+            .read_storage::<Inventory>()
+            .get(entity)
+            .map_or(0, |inventory| inventory.populated_slots())
+    }
+
+    #[test]
+    fn policies_decide_lost_items() {
+        let mut state = State::server();
+        let player = spawn_player(&mut state);
+
+        assert_eq!(die(&mut state, player, DeathItemPolicy::KeepAll), None);
+        assert_eq!(item_count(&state, player), 2);
+
+        let gravestone = die(&mut state, player, DeathItemPolicy::DropCoinsOnly).unwrap();
+        assert_eq!(item_count(&state, player), 1);
+        assert_eq!(item_count(&state, gravestone), 1);
+        assert_eq!(
+            state.ecs().read_storage::<Pos>().get(gravestone),
+            Some(&Pos(Vec3::new(10.0, 20.0, 30.0)))
+        );
+
+        // No coins left, so there's nothing to leave behind
+        assert_eq!(
+            die(&mut state, player, DeathItemPolicy::DropCoinsOnly),
+            None
+        );
+    }
+
+    #[test]
+    fn dying_twice_leaves_two_gravestones() {
+        let mut state = State::server();
+        let player = spawn_player(&mut state);
+        let first = die(&mut state, player, DeathItemPolicy::DropAll).unwrap();
+        assert_eq!(item_count(&state, player), 0);
+
+        // Pick up new items before dying again
+        state
+            .ecs()
+            .write_storage::<Inventory>()
+            .get_mut(player)
+            .unwrap()
+            .push(item(COINS, 5))
+            .unwrap();
+        state.ecs_mut().insert(Time(30.0));
+        let second = die(&mut state, player, DeathItemPolicy::DropAll).unwrap();
+
+        assert_ne!(first, second);
+        assert_eq!(item_count(&state, first), 2);
+        assert_eq!(item_count(&state, second), 1);
+        let gravestones = state.ecs().read_storage::<Gravestone>();
+        assert_eq!(gravestones.get(first).unwrap().despawn_at, Time(600.0));
+        assert_eq!(gravestones.get(second).unwrap().despawn_at, Time(630.0));
+    }
+
+    #[test]
+    fn gravestones_survive_restart() {
+        let data_dir =
+            std::env::temp_dir().join(format!("veloren-gravestones-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&data_dir);
+        fs::create_dir_all(&data_dir).unwrap();
+
+        let mut state = State::server();
+        let player = spawn_player(&mut state);
+        die(&mut state, player, DeathItemPolicy::DropAll).unwrap();
+        state.ecs_mut().insert(Time(40.0));
+        GravestonePersistence::new(&data_dir).save(state.ecs());
+
+        // Time starts over after the restart
+        let mut state = State::server();
+        GravestonePersistence::new(&data_dir).load(&mut state);
+        let (gravestone, inventory) = {
+            let ecs = state.ecs();
+            let (gravestones, inventories) = (
+                ecs.read_storage::<Gravestone>(),
+                ecs.read_storage::<Inventory>(),
+            );
+            let restored = (&gravestones, &inventories).join().collect::<Vec<_>>();
+            assert_eq!(restored.len(), 1);
+            (restored[0].0.clone(), restored[0].1.clone())
+        };
+        assert_eq!(gravestone.owner, 1);
+        assert_eq!(gravestone.owner_name, "Owner");
+        assert_eq!(gravestone.public_at, Time(20.0));
+        assert_eq!(gravestone.despawn_at, Time(560.0));
+        let mut items = inventory
+            .slots()
+            .flatten()
+            .map(|item| (item.item_definition_id().to_owned(), item.amount()))
+            .collect::<Vec<_>>();
+        items.sort();
+        assert_eq!(items, [
+            ("common.items.food.apple".to_owned(), 3),
+            (COINS.to_owned(), 50),
+        ]);
+
+        let _ = fs::remove_dir_all(&data_dir);
+    }
+}
@@ -0,0 +1,131 @@
+use common::{
+    character::CharacterId,
+    comp::social::{Guild, GuildError, GuildId},
+};
+use hashbrown::HashMap;
+
+/// All guilds of the server, loaded from the database on startup. Changes are
+/// written back through the `CharacterUpdater`.
+#[derive(Default)]
+pub struct GuildManager {
+    guilds: HashMap<GuildId, Guild>,
+    next_id: GuildId,
+}
+
+impl GuildManager {
+    pub fn new(guilds: Vec<Guild>) -> Self {
+        Self {
+            next_id: guilds.iter().map(|guild| guild.id + 1).max().unwrap_or(1),
+            guilds: guilds.into_iter().map(|guild| (guild.id, guild)).collect(),
+        }
+    }
+
+    pub fn guild(&self, id: GuildId) -> Option<&Guild> { self.guilds.get(&id) }
+
+    pub fn guild_mut(&mut self, id: GuildId) -> Option<&mut Guild> { self.guilds.get_mut(&id) }
+
+    /// The guild the character is a member of
+    pub fn guild_of(&self, character_id: CharacterId) -> Option<&Guild> {
+        self.guilds
+            .values()
+            .find(|guild| guild.member(character_id).is_some())
+    }
+
+    /// Removes the character from its guild, e.g. because it left or was
+    /// deleted. Returns the changed guild, a guild without members is removed
+    /// and returned empty, so it gets deleted from the database as well.
+    pub fn leave(&mut self, character_id: CharacterId) -> Result<Guild, GuildError> {
+        let id = self
+            .guild_of(character_id)
+            .map(|guild| guild.id)
+            .ok_or(GuildError::NotInGuild)?;
+        let guild = self.guilds.get_mut(&id).ok_or(GuildError::NotInGuild)?;
+        guild.leave(character_id)?;
+        let guild = guild.clone();
+        if guild.members.is_empty() {
+            self.guilds.remove(&id);
+        }
+        Ok(guild)
+    }
+
+    /// Creates a guild led by `leader`, names and tags have to be unique
+    /// regardless of case
+    pub fn create(
+        &mut self,
+        name: String,
+        tag: String,
+        leader: CharacterId,
+    ) -> Result<&Guild, GuildError> {
+        if self.guild_of(leader).is_some() {
+            return Err(GuildError::AlreadyInGuild);
+        }
+        let guild = Guild::new(self.next_id, name, tag, leader)?;
+        if self.guilds.values().any(|other| {
+            other.name.eq_ignore_ascii_case(&guild.name)
+                || other.tag.eq_ignore_ascii_case(&guild.tag)
+        }) {
+            return Err(GuildError::NameTaken);
+        }
+        self.next_id += 1;
+        Ok(self.guilds.entry(guild.id).or_insert(guild))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_and_tags_are_unique() {
+        let mut guilds = GuildManager::new(Vec::new());
+        let id = guilds
+            .create("Dome Keepers".to_owned(), "DK".to_owned(), 1)
+            .unwrap()
+            .id;
+        assert_eq!(guilds.guild_of(1).map(|guild| guild.id), Some(id));
+        assert_eq!(
+            guilds
+                .create("dome keepers".to_owned(), "DKX".to_owned(), 2)
+                .err(),
+            Some(GuildError::NameTaken)
+        );
+        assert_eq!(
+            guilds
+                .create("Other Guild".to_owned(), "dk".to_owned(), 2)
+                .err(),
+            Some(GuildError::NameTaken)
+        );
+        assert_eq!(
+            guilds
+                .create("Other Guild".to_owned(), "OG".to_owned(), 1)
+                .err(),
+            Some(GuildError::AlreadyInGuild)
+        );
+        assert_ne!(
+            guilds
+                .create("Other Guild".to_owned(), "OG".to_owned(), 2)
+                .map(|guild| guild.id),
+            Ok(id)
+        );
+    }
+
+    #[test]
+    fn empty_guilds_are_disbanded() {
+        let mut guilds = GuildManager::new(Vec::new());
+        let id = guilds
+            .create("Dome Keepers".to_owned(), "DK".to_owned(), 1)
+            .unwrap()
+            .id;
+        guilds.guild_mut(id).unwrap().add_member(2).unwrap();
+        assert_eq!(guilds.leave(1).map(|guild| guild.members.len()), Ok(1));
+        assert_eq!(guilds.leave(1).err(), Some(GuildError::NotInGuild));
+        assert_eq!(guilds.leave(2).map(|guild| guild.members.len()), Ok(0));
+        assert!(guilds.guild(id).is_none());
+        // The name is free again
+        assert!(
+            guilds
+                .create("Dome Keepers".to_owned(), "DK".to_owned(), 2)
+                .is_ok()
+        );
+    }
+}
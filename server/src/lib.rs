@@ -14,14 +14,20 @@
 #![cfg_attr(not(feature = "worldgen"), feature(const_panic))]
 
 pub mod alias_validator;
+pub mod block_entities;
 mod character_creator;
 pub mod chunk_generator;
 pub mod client;
 pub mod cmd;
 pub mod connection_handler;
 mod data_dir;
+pub mod economy;
 pub mod error;
 pub mod events;
+pub mod fishing;
+pub mod friends;
+pub mod gravestone;
+pub mod guild;
 pub mod input;
 pub mod location;
 pub mod login_provider;
@@ -30,7 +36,9 @@ pub mod persistence;
 mod pet;
 pub mod presence;
 pub mod rtsim;
+pub mod rules;
 pub mod settings;
+pub mod spawn;
 pub mod state_ext;
 pub mod sys;
 #[cfg(feature = "persistent_world")]
@@ -51,16 +59,21 @@ pub use crate::{
 use crate::terrain_persistence::TerrainPersistence;
 use crate::{
     alias_validator::AliasValidator,
+    block_entities::BlockEntityPersistence,
     chunk_generator::ChunkGenerator,
     client::Client,
     cmd::ChatCommandExt,
     connection_handler::ConnectionHandler,
     data_dir::DataDir,
+    friends::FriendManager,
+    gravestone::GravestonePersistence,
+    guild::GuildManager,
     location::Locations,
     login_provider::LoginProvider,
     persistence::PersistedComponents,
     presence::{Presence, RegionSubscription, RepositionOnChunkLoad},
     rtsim::RtSim,
+    rules::ServerRules,
     state_ext::StateExt,
     sys::sentinel::{DeletedEntities, TrackedStorages},
 };
@@ -90,7 +103,7 @@ use common_net::{
 use common_state::{BuildAreas, State};
 use common_systems::add_local_systems;
 use metrics::{EcsSystemMetrics, PhysicsMetrics, TickMetrics};
-use network::{ListenAddr, Network, Pid};
+use network::{tls::CertFingerprint, ListenAddr, Network, NoiseKeys, Pid};
 use persistence::{
     character_loader::{CharacterLoader, CharacterLoaderResponseKind},
     character_updater::CharacterUpdater,
@@ -101,6 +114,7 @@ use specs::{join::Join, Builder, Entity as EcsEntity, Entity, SystemData, WorldE
 use std::{
     i32,
     ops::{Deref, DerefMut},
+    path::Path,
     sync::Arc,
     time::{Duration, Instant},
 };
@@ -224,6 +238,26 @@ impl Server {
         debug!("Running DB migrations...");
         persistence::run_migrations(&database_settings);
 
+        let guilds = persistence::guild::load_guilds(&persistence::establish_connection(
+            &database_settings,
+            persistence::ConnectionMode::ReadOnly,
+        ))
+        .expect("Failed to load guilds, server startup aborted");
+        info!("Loaded {} guilds", guilds.len());
+
+        let accepted_rules =
+            persistence::rules::load_accepted_rules(&persistence::establish_connection(
+                &database_settings,
+                persistence::ConnectionMode::ReadOnly,
+            ))
+            .expect("Failed to load accepted rules, server startup aborted");
+
+        let friends = persistence::friends::load_friends(&persistence::establish_connection(
+            &database_settings,
+            persistence::ConnectionMode::ReadOnly,
+        ))
+        .expect("Failed to load friends, server startup aborted");
+
         let database_settings = Arc::new(RwLock::new(database_settings));
 
         let registry = Arc::new(Registry::new());
@@ -255,6 +289,11 @@ impl Server {
             rayon_threads: num_cpus::get() as u32,
         });
         state.ecs_mut().insert(Tick(0));
+        state.ecs_mut().insert(events::CombatLog::default());
+        state.ecs_mut().insert(sys::sleep::Sleepers::default());
+        state
+            .ecs_mut()
+            .insert(sys::gravestone::GravestoneMarkers::default());
         state.ecs_mut().insert(TickStart(Instant::now()));
         state.ecs_mut().insert(job_metrics);
         state.ecs_mut().insert(network_request_metrics);
@@ -262,6 +301,12 @@ impl Server {
         state.ecs_mut().insert(ecs_system_metrics);
         state.ecs_mut().insert(tick_metrics);
         state.ecs_mut().insert(physics_metrics);
+        let gravestone_persistence = GravestonePersistence::new(data_dir);
+        gravestone_persistence.load(&mut state);
+        state.ecs_mut().insert(gravestone_persistence);
+        let block_entity_persistence = BlockEntityPersistence::new(data_dir);
+        block_entity_persistence.load(&mut state);
+        state.ecs_mut().insert(block_entity_persistence);
         if settings.experimental_terrain_persistence {
             #[cfg(feature = "persistent_world")]
             {
@@ -281,17 +326,29 @@ impl Server {
                  compiled with the feature. Terrain modifications will *not* be persisted."
             );
         }
+        let threads = state.thread_pool().current_num_threads() as u64;
+        let chunk_gen_workers = settings
+            .chunk_gen_workers
+            .unwrap_or(threads / 2 + threads / 4)
+            .max(1);
         state
             .ecs_mut()
             .write_resource::<SlowJobPool>()
-            .configure("CHUNK_GENERATOR", |n| n / 2 + n / 4);
-        state
-            .ecs_mut()
-            .insert(ChunkGenerator::new(chunk_gen_metrics));
+            .configure("CHUNK_GENERATOR", |n| chunk_gen_workers.min(n));
+        state.ecs_mut().insert(ChunkGenerator::new(
+            chunk_gen_metrics,
+            settings.erosion_iterations,
+            chunk_gen_workers as usize,
+        ));
 
         state.ecs_mut().insert(CharacterUpdater::new(
             Arc::<RwLock<DatabaseSettings>>::clone(&database_settings),
         )?);
+        state.ecs_mut().insert(GuildManager::new(guilds));
+        state.ecs_mut().insert(FriendManager::new(friends));
+        state
+            .ecs_mut()
+            .insert(ServerRules::new(settings.rules.clone(), accepted_rules));
 
         let ability_map = comp::item::tool::AbilityMap::<comp::AbilityItem>::load_expect_cloned(
             "common.abilities.ability_set_manifest",
@@ -320,6 +377,11 @@ impl Server {
         state.ecs_mut().register::<comp::Pet>();
         state.ecs_mut().register::<login_provider::PendingLogin>();
         state.ecs_mut().register::<RepositionOnChunkLoad>();
+        state.ecs_mut().register::<economy::PlayerWallet>();
+        state.ecs_mut().register::<fishing::FishingMinigame>();
+        state.ecs_mut().register::<fishing::FishingLine>();
+        state.ecs_mut().register::<spawn::BossEncounter>();
+        state.ecs_mut().register::<sys::animation::AnimationSpeed>();
 
         //Alias validator
         let banned_words_paths = &settings.banned_words_files;
@@ -383,6 +445,7 @@ impl Server {
             alt: Grid::new(Vec2::new(1, 1), 1),
             sites: Vec::new(),
             pois: Vec::new(),
+            thermals: Vec::new(),
         };
 
         #[cfg(feature = "worldgen")]
@@ -423,6 +486,9 @@ impl Server {
 
         // Set the spawn point we calculated above
         state.ecs_mut().insert(spawn_point);
+        state
+            .ecs_mut()
+            .insert(comp::Thermals::new(map.thermals.clone()));
 
         // Insert a default AABB for the world
         // TODO: prevent this from being deleted
@@ -460,6 +526,22 @@ impl Server {
         state.ecs_mut().insert(DeletedEntities::default());
 
         let network = Network::new_with_registry(Pid::new(), &runtime, &registry);
+        network.set_shutdown_grace(settings.network_shutdown_grace);
+        if let Some(path) = &settings.encryption_key_file_path {
+            let key = load_noise_key(path).map_err(|e| {
+                Error::Other(format!(
+                    "Failed to load the encryption key {}: {}",
+                    path.display(),
+                    e
+                ))
+            })?;
+            let keys = NoiseKeys::default().with_private_key(key);
+            // Players pin it to make sure nobody poses as the server
+            if let Some(public_key) = keys.public_key() {
+                info!(%public_key, "Clients have to encrypt their channel");
+            }
+            network.set_encryption(Some(keys));
+        }
         let metrics_shutdown = Arc::new(Notify::new());
         let metrics_shutdown_clone = Arc::clone(&metrics_shutdown);
         let addr = settings.metrics_address;
@@ -483,38 +565,11 @@ impl Server {
                     cert_file_path,
                     key_file_path,
                 } => {
-                    use rustls_pemfile::Item;
-                    use std::fs;
-
-                    match || -> Result<_, Box<dyn std::error::Error>> {
-                        let key = fs::read(&key_file_path)?;
-                        let key = if key_file_path.extension().map_or(false, |x| x == "der") {
-                            rustls::PrivateKey(key)
-                        } else {
-                            debug!("convert pem key to der");
-                            let key = rustls_pemfile::read_all(&mut key.as_slice())?
-                                .into_iter()
-                                .find_map(|item| match item {
-                                    Item::RSAKey(v) | Item::PKCS8Key(v) => Some(v),
-                                    Item::ECKey(_) => None,
-                                    Item::X509Certificate(_) => None,
-                                    _ => None,
-                                })
-                                .ok_or("No valid pem key in file")?;
-                            rustls::PrivateKey(key)
-                        };
-                        let cert_chain = fs::read(&cert_file_path)?;
-                        let cert_chain = if cert_file_path.extension().map_or(false, |x| x == "der")
-                        {
-                            vec![rustls::Certificate(cert_chain)]
-                        } else {
-                            debug!("convert pem cert to der");
-                            let certs = rustls_pemfile::certs(&mut cert_chain.as_slice())?;
-                            certs.into_iter().map(rustls::Certificate).collect()
-                        };
-                        let server_config = quinn::ServerConfig::with_single_cert(cert_chain, key)?;
-                        Ok(server_config)
-                    }() {
+                    match load_tls_cert(cert_file_path, key_file_path).and_then(
+                        |(cert_chain, key)| {
+                            Ok(quinn::ServerConfig::with_single_cert(cert_chain, key)?)
+                        },
+                    ) {
                         Ok(server_config) => {
                             runtime.block_on(
                                 network.listen(ListenAddr::Quic(*address, server_config.clone())),
@@ -537,6 +592,41 @@ impl Server {
                         },
                     }
                 },
+                Protocol::TcpTls {
+                    address,
+                    cert_file_path,
+                    key_file_path,
+                } => {
+                    match load_tls_cert(cert_file_path, key_file_path).and_then(
+                        |(cert_chain, key)| {
+                            let fingerprint = cert_chain.first().map(CertFingerprint::of);
+                            let server_config = rustls::ServerConfig::builder()
+                                .with_safe_defaults()
+                                .with_no_client_auth()
+                                .with_single_cert(cert_chain, key)?;
+                            Ok((server_config, fingerprint))
+                        },
+                    ) {
+                        Ok((server_config, fingerprint)) => {
+                            runtime.block_on(
+                                network
+                                    .listen(ListenAddr::TcpTls(*address, Arc::new(server_config))),
+                            )?;
+                            // Players connecting to servers with a self-signed certificate have
+                            // to pin it
+                            if let Some(fingerprint) = fingerprint {
+                                info!(%fingerprint, "Listening for Tls on {}", *address);
+                            }
+                        },
+                        Err(e) => {
+                            error!(
+                                ?e,
+                                "Failed to load the TLS certificate, running without TLS {}",
+                                *address
+                            );
+                        },
+                    }
+                },
             }
         }
 
@@ -665,6 +755,10 @@ impl Server {
         // 9) Finish the tick, passing control of the main thread back
         //    to the frontend
 
+        // Messages the frontend sent since the last tick, e.g. by a command,
+        // shouldn't wait for the end of this one
+        self.flush_client_batches();
+
         // 1) Build up a list of events for this frame, to be passed to the frontend.
         let mut frontend_events = Vec::new();
 
@@ -769,10 +863,15 @@ impl Server {
                 &self.state.ecs().entities(),
                 &self.state.ecs().read_storage::<comp::Pos>(),
                 !&self.state.ecs().read_storage::<Presence>(),
+                // Gravestones stay until they expire, even while nobody is around
+                !&self.state.ecs().read_storage::<comp::Gravestone>(),
+                // So do the cooking pots and farm plots players tended to
+                !&self.state.ecs().read_storage::<comp::CookingPot>(),
+                !&self.state.ecs().read_storage::<comp::FarmPlot>(),
                 self.state.ecs().read_storage::<comp::Anchor>().maybe(),
             )
                 .join()
-                .filter(|(_, pos, _, anchor)| {
+                .filter(|(_, pos, _, _, _, _, anchor)| {
                     let chunk_key = terrain.pos_key(pos.0.map(|e| e.floor() as i32));
                     match anchor {
                         Some(Anchor::Chunk(hc)) => {
@@ -786,7 +885,7 @@ impl Server {
                         None => terrain.get_key(chunk_key).is_none(),
                     }
                 })
-                .map(|(entity, _, _, _)| entity)
+                .map(|(entity, _, _, _, _, _, _)| entity)
                 .collect::<Vec<_>>()
         };
 
@@ -834,6 +933,9 @@ impl Server {
             .ecs()
             .read_resource::<persistence::character_updater::CharacterUpdater>();
 
+        // Guilds are updated once the database resources aren't borrowed anymore
+        let mut deleted_characters = Vec::new();
+
         // Get character-related database responses and notify the requesting client
         character_loader
             .messages()
@@ -881,6 +983,19 @@ impl Server {
                         ServerGeneral::CharacterActionError(error.to_string()),
                     ),
                 },
+                CharacterLoaderResponseKind::CharacterDeletion(result) => match result {
+                    Ok((character_id, list)) => {
+                        self.notify_client(
+                            query_result.entity,
+                            ServerGeneral::CharacterListUpdate(list),
+                        );
+                        deleted_characters.push(character_id);
+                    },
+                    Err(error) => self.notify_client(
+                        query_result.entity,
+                        ServerGeneral::CharacterActionError(error.to_string()),
+                    ),
+                },
                 CharacterLoaderResponseKind::CharacterData(result) => {
                     let message = match *result {
                         Ok(character_data) => {
@@ -935,17 +1050,21 @@ impl Server {
         drop(character_loader);
         drop(character_updater);
 
+        for character_id in deleted_characters {
+            events::remove_deleted_character(self, character_id);
+        }
+
         {
             // Check for new chunks; cancel and regenerate all chunks if the asset has been
             // reloaded. Note that all of these assignments are no-ops, so the
             // only work we do here on the fast path is perform a relaxed read on an atomic.
             // boolean.
             let index = &mut self.index;
-            let world = &mut self.world;
             let ecs = self.state.ecs_mut();
-            let slow_jobs = ecs.write_resource::<SlowJobPool>();
 
             index.reload_if_changed(|index| {
+                // Chunks are generated with the index of the ecs
+                *ecs.write_resource::<IndexOwned>() = index.clone();
                 let mut chunk_generator = ecs.write_resource::<ChunkGenerator>();
                 let client = ecs.read_storage::<Client>();
                 let mut terrain = ecs.write_resource::<common::terrain::TerrainGrid>();
@@ -959,22 +1078,15 @@ impl Server {
                 } else {
                     // There's at least one client, so regenerate all chunks.
                     terrain.iter().for_each(|(pos, _)| {
-                        chunk_generator.generate_chunk(
-                            None,
-                            pos,
-                            &slow_jobs,
-                            Arc::clone(world),
-                            index.clone(),
-                            (
-                                *ecs.read_resource::<TimeOfDay>(),
-                                (*ecs.read_resource::<Calendar>()).clone(),
-                            ),
-                        );
+                        chunk_generator.generate_chunk(None, pos, 0);
                     });
                 }
             });
         }
 
+        // Send the messages of this tick, a batch per stream and client
+        self.flush_client_batches();
+
         let end_of_server_tick = Instant::now();
 
         // 8) Update Metrics
@@ -1016,6 +1128,15 @@ impl Server {
         // Cleanup the local state
         self.state.cleanup();
 
+        self.state
+            .ecs()
+            .write_resource::<GravestonePersistence>()
+            .maintain(self.state.ecs());
+        self.state
+            .ecs()
+            .write_resource::<BlockEntityPersistence>()
+            .maintain(self.state.ecs());
+
         // Maintain persisted terrain
         #[cfg(feature = "persistent_world")]
         self.state
@@ -1024,6 +1145,21 @@ impl Server {
             .map(|mut t| t.maintain());
     }
 
+    /// Sends the batched messages of all clients
+    fn flush_client_batches(&self) {
+        let ecs = self.state.ecs();
+        for (entity, client) in (&ecs.entities(), &ecs.read_storage::<Client>()).join() {
+            if let Err(e) = client.flush_batches() {
+                // The client gets disconnected once its closed streams are noticed
+                debug!(
+                    ?e,
+                    ?entity,
+                    "Failed to send the batched messages of a client"
+                );
+            }
+        }
+    }
+
     fn initialize_client(
         &mut self,
         client: crate::connection_handler::IncomingClient,
@@ -1062,12 +1198,13 @@ impl Server {
             .send(ServerInit::GameSync {
                 // Send client their entity
                 entity_package: TrackedStorages::fetch(self.state.ecs())
-                    .create_entity_package(entity, None, None, None)
+                    .create_entity_package(entity, None, None, None, false)
                     .expect(
                         "We just created this entity as marked() (using create_entity_synced) so \
                          it definitely has a uid",
                     ),
                 time_of_day: *self.state.ecs().read_resource(),
+                game_time: *self.state.ecs().read_resource(),
                 max_group_size: self.settings().max_player_group_size,
                 client_timeout: self.settings().client_timeout,
                 world_map: self.map.clone(),
@@ -1142,6 +1279,7 @@ impl Server {
             let _ = sender.send(crate::connection_handler::ServerInfoPacket {
                 info: self.get_server_info(),
                 time: self.state.get_time(),
+                max_batch_bytes: self.settings().max_batch_bytes,
             });
         }
 
@@ -1174,18 +1312,17 @@ impl Server {
 
     pub fn generate_chunk(&mut self, entity: EcsEntity, key: Vec2<i32>) {
         let ecs = self.state.ecs();
-        let slow_jobs = ecs.read_resource::<SlowJobPool>();
-        ecs.write_resource::<ChunkGenerator>().generate_chunk(
-            Some(entity),
-            key,
-            &slow_jobs,
-            Arc::clone(&self.world),
-            self.index.clone(),
-            (
-                *ecs.read_resource::<TimeOfDay>(),
-                (*ecs.read_resource::<Calendar>()).clone(),
-            ),
-        );
+        let dist_sqr = ecs
+            .read_storage::<comp::Pos>()
+            .get(entity)
+            .map_or(0, |pos| {
+                let chunk_pos = self.state.terrain().pos_key(pos.0.map(|e| e as i32));
+                (key - chunk_pos)
+                    .map(|e| e.unsigned_abs())
+                    .magnitude_squared()
+            });
+        ecs.write_resource::<ChunkGenerator>()
+            .generate_chunk(Some(entity), key, dist_sqr);
     }
 
     fn process_command(&mut self, entity: EcsEntity, name: String, args: Vec<String>) {
@@ -1371,9 +1508,7 @@ impl Server {
         // let rand_pos = world_dims_blocks.map(|e| e as i32).map(|e| e / 2 +
         // rng.gen_range(-e/2..e/2 + 1));
         let pos = comp::Pos(Vec3::from(world_dims_blocks.map(|e| e as f32 / 2.0)));
-        self.state
-            .create_persister(pos, view_distance, &self.world, &self.index)
-            .build();
+        self.state.create_persister(pos, view_distance).build();
     }
 
     /// Sets the SQL log mode at runtime
@@ -1412,10 +1547,104 @@ impl Drop for Server {
                 info!("Unloading terrain persistence...");
                 terrain_persistence.unload_all()
             });
+
+        info!("Saving gravestones...");
+        self.state
+            .ecs()
+            .write_resource::<GravestonePersistence>()
+            .save(self.state.ecs());
+
+        info!("Saving cooking pots and farm plots...");
+        self.state
+            .ecs()
+            .write_resource::<BlockEntityPersistence>()
+            .save(self.state.ecs());
     }
 }
 
 #[must_use]
+/// Reads a certificate chain and its private key, either as DER or PEM
+/// depending on the file extension
+fn load_tls_cert(
+    cert_file_path: &Path,
+    key_file_path: &Path,
+) -> Result<(Vec<rustls::Certificate>, rustls::PrivateKey), Box<dyn std::error::Error>> {
+    use rustls_pemfile::Item;
+    use std::fs;
+
+    let key = fs::read(key_file_path)?;
+    let key = if key_file_path.extension().map_or(false, |x| x == "der") {
+        rustls::PrivateKey(key)
+    } else {
+        debug!("convert pem key to der");
+        let key = rustls_pemfile::read_all(&mut key.as_slice())?
+            .into_iter()
+            .find_map(|item| match item {
+                Item::RSAKey(v) | Item::PKCS8Key(v) => Some(v),
+                Item::ECKey(_) => None,
+                Item::X509Certificate(_) => None,
+                _ => None,
+            })
+            .ok_or("No valid pem key in file")?;
+        rustls::PrivateKey(key)
+    };
+    let cert_chain = fs::read(cert_file_path)?;
+    let cert_chain = if cert_file_path.extension().map_or(false, |x| x == "der") {
+        vec![rustls::Certificate(cert_chain)]
+    } else {
+        debug!("convert pem cert to der");
+        let certs = rustls_pemfile::certs(&mut cert_chain.as_slice())?;
+        certs.into_iter().map(rustls::Certificate).collect()
+    };
+    Ok((cert_chain, key))
+}
+
+/// Reads the static key of encrypted channels, a new one is generated and
+/// written to `path` if there is no file yet. Anyone who can read the key can
+/// pose as the server, so on unix the file is only readable by its owner and
+/// a key others can read is refused.
+fn load_noise_key(path: &Path) -> Result<[u8; 32], Box<dyn std::error::Error>> {
+    use std::{
+        fs::{self, OpenOptions},
+        io::{ErrorKind, Write},
+    };
+
+    match fs::read(path) {
+        Ok(key) => {
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                let mode = fs::metadata(path)?.permissions().mode();
+                if mode & 0o077 != 0 {
+                    return Err(format!(
+                        "the key is readable by others (mode {:o}), restrict it with `chmod 600`",
+                        mode & 0o777
+                    )
+                    .into());
+                }
+            }
+            Ok(key
+                .as_slice()
+                .try_into()
+                .map_err(|_| "expected a key of 32 bytes")?)
+        },
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            let key = NoiseKeys::generate_private_key();
+            let mut options = OpenOptions::new();
+            options.write(true).create_new(true);
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::OpenOptionsExt;
+                options.mode(0o600);
+            }
+            options.open(path)?.write_all(&key)?;
+            info!("Generated a new encryption key at {}", path.display());
+            Ok(key)
+        },
+        Err(e) => Err(e.into()),
+    }
+}
+
 pub fn handle_edit<T, S: settings::EditableSetting>(
     data: T,
     result: Option<(String, Result<(), settings::SettingError<S>>)>,
@@ -42,6 +42,9 @@ pub struct ChunkGenMetrics {
     pub chunks_requested: IntCounter,
     pub chunks_served: IntCounter,
     pub chunks_canceled: IntCounter,
+    pub chunks_queued: IntGauge,
+    pub chunks_pending: IntGauge,
+    pub chunk_generation_time: Histogram,
 }
 
 pub struct JobMetrics {
@@ -233,15 +236,45 @@ impl ChunkGenMetrics {
             "chunks_canceled",
             "number of all canceled chunks on the server",
         ))?;
+        let chunks_queued = IntGauge::with_opts(Opts::new(
+            "chunks_queued",
+            "number of requested chunks waiting for a free worker",
+        ))?;
+        let chunks_pending = IntGauge::with_opts(Opts::new(
+            "chunks_pending",
+            "number of requested chunks waiting for a free worker or being generated",
+        ))?;
+        let bucket = vec![
+            Duration::from_millis(10).as_secs_f64(),
+            Duration::from_millis(50).as_secs_f64(),
+            Duration::from_millis(100).as_secs_f64(),
+            Duration::from_millis(250).as_secs_f64(),
+            Duration::from_millis(500).as_secs_f64(),
+            Duration::from_millis(1000).as_secs_f64(),
+            Duration::from_millis(5000).as_secs_f64(),
+        ];
+        let chunk_generation_time = Histogram::with_opts(
+            HistogramOpts::new(
+                "chunk_generation_time",
+                "shows the time it took to generate each chunk, without the time it was queued",
+            )
+            .buckets(bucket),
+        )?;
 
         registry.register(Box::new(chunks_requested.clone()))?;
         registry.register(Box::new(chunks_served.clone()))?;
         registry.register(Box::new(chunks_canceled.clone()))?;
+        registry.register(Box::new(chunks_queued.clone()))?;
+        registry.register(Box::new(chunks_pending.clone()))?;
+        registry.register(Box::new(chunk_generation_time.clone()))?;
 
         Ok(Self {
             chunks_requested,
             chunks_served,
             chunks_canceled,
+            chunks_queued,
+            chunks_pending,
+            chunk_generation_time,
         })
     }
 }
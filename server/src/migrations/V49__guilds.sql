@@ -0,0 +1,17 @@
+-- Creates the guild tables, the guild bank is stored as JSON
+CREATE TABLE "guild" (
+    "guild_id" INT NOT NULL,
+    "name" TEXT NOT NULL UNIQUE,
+    "tag" TEXT NOT NULL UNIQUE,
+    "bank" TEXT NOT NULL,
+    PRIMARY KEY("guild_id")
+);
+
+CREATE TABLE "guild_member" (
+    "character_id" INT NOT NULL,
+    "guild_id" INT NOT NULL,
+    "rank" TEXT NOT NULL,
+    PRIMARY KEY("character_id"),
+    FOREIGN KEY("character_id") REFERENCES "character"("character_id"),
+    FOREIGN KEY("guild_id") REFERENCES "guild"("guild_id")
+);
@@ -0,0 +1,6 @@
+-- Stores the hash of the server rules each player accepted last
+CREATE TABLE "accepted_rules" (
+    "player_uuid" TEXT NOT NULL,
+    "rules_hash" INT NOT NULL,
+    PRIMARY KEY("player_uuid")
+);
@@ -0,0 +1,8 @@
+-- Friendships between players, stored once for each direction along with the
+-- name of the friend
+CREATE TABLE "friend" (
+    "player_uuid" TEXT NOT NULL,
+    "friend_uuid" TEXT NOT NULL,
+    "friend_name" TEXT NOT NULL,
+    PRIMARY KEY("player_uuid", "friend_uuid")
+);
@@ -633,6 +633,17 @@ pub fn delete_character(
     stmt.execute(&[&char_id])?;
     drop(stmt);
 
+    // Delete guild membership
+    let mut stmt = transaction.prepare_cached(
+        "
+        DELETE
+        FROM    guild_member
+        WHERE   character_id = ?1",
+    )?;
+
+    stmt.execute(&[&char_id])?;
+    drop(stmt);
+
     // Delete character
     let mut stmt = transaction.prepare_cached(
         "
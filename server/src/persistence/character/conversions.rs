@@ -570,14 +570,17 @@ fn convert_skill_groups_from_database(
             match serde_json::from_str::<Vec<skills::Skill>>(&skill_group.skills) {
                 // If it correctly deserializes, return the persisted skills
                 Ok(skills) => Ok(skills),
-                // Else if doesn't deserialize correctly, force a respec
-                Err(err) => {
-                    warn!(
-                        "Skills failed to correctly deserialized\nError: {:#?}\nRaw JSON: {:#?}",
-                        err, &skill_group.skills
-                    );
-                    Err(SkillsPersistenceError::DeserializationFailure)
-                },
+                // Else try to migrate skills that were renamed or removed since, and only
+                // force a respec if that fails as well
+                Err(err) => migrate_skills(skill_group_kind, skill_group_exp, &skill_group.skills)
+                    .ok_or_else(|| {
+                        warn!(
+                            "Skills failed to correctly deserialized\nError: {:#?}\nRaw JSON: \
+                             {:#?}",
+                            err, &skill_group.skills
+                        );
+                        SkillsPersistenceError::DeserializationFailure
+                    }),
             }
         };
 
@@ -588,6 +591,32 @@ fn convert_skill_groups_from_database(
     (new_skill_groups, deserialized_skills)
 }
 
+/// Skills of a skill group that were saved by an older version of the game,
+/// with the renames of the `common.skill_trees.skill_migrations` asset applied.
+/// Skills that don't exist anymore are dropped.
+fn migrate_skills(
+    skill_group_kind: skillset::SkillGroupKind,
+    earned_exp: u32,
+    skills: &str,
+) -> Option<Vec<skills::Skill>> {
+    let ordered_skills = serde_json::from_str::<Vec<serde_json::Value>>(skills).ok()?;
+    // Skill groups are stored like the skill sets from before they were versioned
+    let raw = serde_json::json!({
+        "skill_groups": [{
+            "skill_group_kind": skill_group_kind,
+            "earned_exp": earned_exp,
+            "ordered_skills": ordered_skills,
+        }],
+    });
+    let skill_set = skillset::migration::migrate_skill_set(raw.to_string().as_bytes())
+        .map_err(|err| warn!(?err, "Failed to migrate skills"))
+        .ok()?;
+    skill_set
+        .skill_groups()
+        .find(|group| group.skill_group_kind == skill_group_kind)
+        .map(|group| group.ordered_skills.clone())
+}
+
 pub fn convert_skill_groups_to_database<'a, I: Iterator<Item = &'a skillset::SkillGroup>>(
     entity_id: CharacterId,
     skill_groups: I,
@@ -634,3 +663,45 @@ pub fn convert_active_abilities_from_database(
         });
     json_models::active_abilities_from_db_model(ability_sets)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::comp::{item::tool::ToolKind, skills::MiningSkill};
+
+    #[test]
+    fn skill_set_round_trips_through_database() {
+        let mut skill_set = SkillSet::default();
+        let pick = skillset::SkillGroupKind::Weapon(ToolKind::Pick);
+        skill_set.add_skill_points(pick, 3);
+        skill_set
+            .unlock_skill(Skill::Pick(MiningSkill::Speed))
+            .unwrap();
+        skill_set
+            .unlock_skill(Skill::Pick(MiningSkill::Speed))
+            .unwrap();
+
+        let mut skill_groups = convert_skill_groups_to_database(1, skill_set.skill_groups());
+        let loaded = convert_skill_set_from_database(&skill_groups);
+        assert_eq!(
+            loaded.skill_level(Skill::Pick(MiningSkill::Speed)).ok(),
+            Some(2)
+        );
+        assert_eq!(loaded.available_sp(pick), skill_set.available_sp(pick));
+        assert!(loaded.persistence_load_error.is_none());
+
+        // Skills that were removed since are dropped by the migration instead of
+        // forcing a respec
+        let pick_group = skill_groups
+            .iter_mut()
+            .find(|group| group.skill_group_kind == "Weapon Pick")
+            .unwrap();
+        pick_group.skills = r#"[{"Pick": "Speed"}, {"Pick": "Removed"}, {"Pick": "Speed"}]"#.into();
+        let migrated = convert_skill_set_from_database(&skill_groups);
+        assert_eq!(
+            migrated.skill_level(Skill::Pick(MiningSkill::Speed)).ok(),
+            Some(2)
+        );
+        assert!(migrated.persistence_load_error.is_none());
+    }
+}
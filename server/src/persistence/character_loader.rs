@@ -13,6 +13,8 @@ pub(crate) type CharacterListResult = Result<Vec<CharacterItem>, PersistenceErro
 pub(crate) type CharacterCreationResult =
     Result<(CharacterId, Vec<CharacterItem>), PersistenceError>;
 pub(crate) type CharacterEditResult = Result<(CharacterId, Vec<CharacterItem>), PersistenceError>;
+pub(crate) type CharacterDeletionResult =
+    Result<(CharacterId, Vec<CharacterItem>), PersistenceError>;
 pub(crate) type CharacterDataResult = Result<PersistedComponents, PersistenceError>;
 type CharacterLoaderRequest = (specs::Entity, CharacterLoaderRequestKind);
 
@@ -35,6 +37,7 @@ pub enum CharacterLoaderResponseKind {
     CharacterData(Box<CharacterDataResult>),
     CharacterCreation(CharacterCreationResult),
     CharacterEdit(CharacterEditResult),
+    CharacterDeletion(CharacterDeletionResult),
 }
 
 /// Common message format dispatched in response to an update request
@@ -51,6 +54,7 @@ impl CharacterLoaderResponse {
             CharacterLoaderResponseKind::CharacterData(box Err(_))
                 | CharacterLoaderResponseKind::CharacterList(Err(_))
                 | CharacterLoaderResponseKind::CharacterCreation(Err(_))
+                | CharacterLoaderResponseKind::CharacterDeletion(Err(_))
         )
     }
 }
@@ -1,5 +1,5 @@
 use crate::comp;
-use common::character::CharacterId;
+use common::{character::CharacterId, comp::social::Guild};
 
 use crate::persistence::{
     character_loader::{CharacterLoaderResponse, CharacterLoaderResponseKind},
@@ -51,6 +51,14 @@ pub enum CharacterUpdaterEvent {
         requesting_player_uuid: String,
         character_id: CharacterId,
     },
+    SaveGuild(Guild),
+    AcceptRules {
+        player_uuid: String,
+        rules_hash: u64,
+    },
+    /// Each player as their uuid and name
+    SaveFriendship((String, String), (String, String)),
+    DeleteFriendship(String, String),
     DisconnectedSuccess,
 }
 
@@ -192,6 +200,44 @@ impl CharacterUpdater {
                                 ),
                             }
                         },
+                        CharacterUpdaterEvent::SaveGuild(guild) => {
+                            conn.update_log_mode(&settings);
+                            if let Err(e) = execute_guild_save(&guild, &mut conn) {
+                                error!("Error saving guild {}, error: {:?}", guild.id, e);
+                            }
+                        },
+                        CharacterUpdaterEvent::AcceptRules {
+                            player_uuid,
+                            rules_hash,
+                        } => {
+                            conn.update_log_mode(&settings);
+                            if let Err(e) =
+                                execute_accept_rules(&player_uuid, rules_hash, &mut conn)
+                            {
+                                error!(
+                                    "Error saving accepted rules of player {}, error: {:?}",
+                                    player_uuid, e
+                                );
+                            }
+                        },
+                        CharacterUpdaterEvent::SaveFriendship(a, b) => {
+                            conn.update_log_mode(&settings);
+                            if let Err(e) = execute_friendship_save(&a, &b, &mut conn) {
+                                error!(
+                                    "Error saving friendship of players {} and {}, error: {:?}",
+                                    a.0, b.0, e
+                                );
+                            }
+                        },
+                        CharacterUpdaterEvent::DeleteFriendship(a, b) => {
+                            conn.update_log_mode(&settings);
+                            if let Err(e) = execute_friendship_delete(&a, &b, &mut conn) {
+                                error!(
+                                    "Error deleting friendship of players {} and {}, error: {:?}",
+                                    a, b, e
+                                );
+                            }
+                        },
                         CharacterUpdaterEvent::DisconnectedSuccess => {
                             info!(
                                 "CharacterUpdater received DisconnectedSuccess event, resuming \
@@ -322,6 +368,58 @@ impl CharacterUpdater {
         }
     }
 
+    /// Writes the guild and its members to the database
+    pub fn save_guild(&mut self, guild: Guild) {
+        if let Err(e) = self
+            .update_tx
+            .as_ref()
+            .unwrap()
+            .send(CharacterUpdaterEvent::SaveGuild(guild))
+        {
+            error!(?e, "Could not send guild save request");
+        }
+    }
+
+    /// Remembers that the player accepted the rules with the given hash
+    pub fn accept_rules(&mut self, player_uuid: String, rules_hash: u64) {
+        if let Err(e) = self
+            .update_tx
+            .as_ref()
+            .unwrap()
+            .send(CharacterUpdaterEvent::AcceptRules {
+                player_uuid,
+                rules_hash,
+            })
+        {
+            error!(?e, "Could not send accept rules request");
+        }
+    }
+
+    /// Writes a new friendship to the database, each player as their uuid and
+    /// name
+    pub fn save_friendship(&mut self, a: (String, String), b: (String, String)) {
+        if let Err(e) = self
+            .update_tx
+            .as_ref()
+            .unwrap()
+            .send(CharacterUpdaterEvent::SaveFriendship(a, b))
+        {
+            error!(?e, "Could not send friendship save request");
+        }
+    }
+
+    /// Removes the friendship of two players from the database
+    pub fn delete_friendship(&mut self, a: String, b: String) {
+        if let Err(e) = self
+            .update_tx
+            .as_ref()
+            .unwrap()
+            .send(CharacterUpdaterEvent::DeleteFriendship(a, b))
+        {
+            error!(?e, "Could not send friendship delete request");
+        }
+    }
+
     /// Updates a collection of characters based on their id and components
     pub fn batch_update<'a>(
         &mut self,
@@ -418,6 +516,72 @@ fn execute_batch_update(
     Ok(())
 }
 
+fn execute_guild_save(
+    guild: &Guild,
+    connection: &mut VelorenConnection,
+) -> Result<(), PersistenceError> {
+    let mut transaction = connection.connection.transaction()?;
+    transaction.set_drop_behavior(DropBehavior::Rollback);
+    super::guild::save_guild(guild, &mut transaction)?;
+    transaction.commit()?;
+
+    trace!("Commit for guild {} completed", guild.id);
+    Ok(())
+}
+
+fn execute_accept_rules(
+    player_uuid: &str,
+    rules_hash: u64,
+    connection: &mut VelorenConnection,
+) -> Result<(), PersistenceError> {
+    let mut transaction = connection.connection.transaction()?;
+    transaction.set_drop_behavior(DropBehavior::Rollback);
+    super::rules::save_accepted_rules(player_uuid, rules_hash, &mut transaction)?;
+    transaction.commit()?;
+
+    trace!(
+        "Commit for accepted rules of player {} completed",
+        player_uuid
+    );
+    Ok(())
+}
+
+fn execute_friendship_save(
+    a: &(String, String),
+    b: &(String, String),
+    connection: &mut VelorenConnection,
+) -> Result<(), PersistenceError> {
+    let mut transaction = connection.connection.transaction()?;
+    transaction.set_drop_behavior(DropBehavior::Rollback);
+    super::friends::save_friendship((&a.0, &a.1), (&b.0, &b.1), &mut transaction)?;
+    transaction.commit()?;
+
+    trace!(
+        "Commit for friendship of players {} and {} completed",
+        a.0,
+        b.0
+    );
+    Ok(())
+}
+
+fn execute_friendship_delete(
+    a: &str,
+    b: &str,
+    connection: &mut VelorenConnection,
+) -> Result<(), PersistenceError> {
+    let mut transaction = connection.connection.transaction()?;
+    transaction.set_drop_behavior(DropBehavior::Rollback);
+    super::friends::delete_friendship(a, b, &mut transaction)?;
+    transaction.commit()?;
+
+    trace!(
+        "Deletion of friendship of players {} and {} completed",
+        a,
+        b
+    );
+    Ok(())
+}
+
 fn execute_character_create(
     entity: Entity,
     alias: String,
@@ -462,11 +626,10 @@ fn execute_character_delete(
     connection: &mut VelorenConnection,
 ) -> Result<CharacterLoaderResponse, PersistenceError> {
     let mut transaction = connection.connection.transaction()?;
-    let result = CharacterLoaderResponseKind::CharacterList(super::character::delete_character(
-        requesting_player_uuid,
-        character_id,
-        &mut transaction,
-    ));
+    let result = CharacterLoaderResponseKind::CharacterDeletion(
+        super::character::delete_character(requesting_player_uuid, character_id, &mut transaction)
+            .map(|list| (character_id, list)),
+    );
     check_response(entity, transaction, result)
 }
 
@@ -0,0 +1,67 @@
+//! Database operations for the friends of players
+
+use crate::persistence::error::PersistenceError;
+use rusqlite::{Connection, ToSql, Transaction, NO_PARAMS};
+
+/// Loads all friendships as the uuid of a player, the uuid of their friend and
+/// the name of the friend
+pub fn load_friends(
+    connection: &Connection,
+) -> Result<Vec<(String, String, String)>, PersistenceError> {
+    let mut stmt = connection.prepare_cached(
+        "
+        SELECT  player_uuid,
+                friend_uuid,
+                friend_name
+        FROM    friend",
+    )?;
+
+    let friends = stmt
+        .query_map(NO_PARAMS, |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+            ))
+        })?
+        .collect::<Result<_, _>>()?;
+
+    Ok(friends)
+}
+
+/// Stores a friendship in both directions, each player along with their name
+pub fn save_friendship(
+    a: (&str, &str),
+    b: (&str, &str),
+    transaction: &mut Transaction,
+) -> Result<(), PersistenceError> {
+    let mut stmt = transaction.prepare_cached(
+        "
+        INSERT OR REPLACE INTO friend (player_uuid, friend_uuid, friend_name)
+        VALUES (?1, ?2, ?3)",
+    )?;
+
+    for (player, friend) in [(a, b), (b, a)] {
+        stmt.execute(&[&player.0 as &dyn ToSql, &friend.0, &friend.1])?;
+    }
+
+    Ok(())
+}
+
+/// Removes a friendship in both directions
+pub fn delete_friendship(
+    a: &str,
+    b: &str,
+    transaction: &mut Transaction,
+) -> Result<(), PersistenceError> {
+    let mut stmt = transaction.prepare_cached(
+        "
+        DELETE FROM friend
+        WHERE   (player_uuid = ?1 AND friend_uuid = ?2)
+        OR      (player_uuid = ?2 AND friend_uuid = ?1)",
+    )?;
+
+    stmt.execute(&[&a, &b])?;
+
+    Ok(())
+}
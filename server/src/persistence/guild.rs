@@ -0,0 +1,175 @@
+//! Database operations for guilds and their members
+
+use crate::persistence::error::PersistenceError;
+use common::comp::{
+    social::{Guild, GuildId, GuildMember, GuildRank},
+    Item,
+};
+use rusqlite::{Connection, ToSql, Transaction, NO_PARAMS};
+use tracing::warn;
+
+/// Loads all guilds along with their members
+pub fn load_guilds(connection: &Connection) -> Result<Vec<Guild>, PersistenceError> {
+    let mut stmt = connection.prepare_cached(
+        "
+        SELECT  guild_id,
+                name,
+                tag,
+                bank
+        FROM    guild
+        ORDER BY guild_id",
+    )?;
+
+    let rows = stmt
+        .query_map(NO_PARAMS, |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+            ))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    drop(stmt);
+
+    let mut guilds = rows
+        .into_iter()
+        .map(|(id, name, tag, bank)| {
+            Ok(Guild {
+                id: id as u64,
+                name,
+                tag,
+                members: Vec::new(),
+                bank: serde_json::from_str::<Vec<Item>>(&bank)?,
+            })
+        })
+        .collect::<Result<Vec<_>, PersistenceError>>()?;
+
+    let mut stmt = connection.prepare_cached(
+        "
+        SELECT  character_id,
+                guild_id,
+                rank
+        FROM    guild_member",
+    )?;
+
+    let members = stmt
+        .query_map(NO_PARAMS, |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, String>(2)?,
+            ))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    for (character_id, guild_id, rank) in members {
+        let rank = convert_rank_from_database(&rank)?;
+        match guilds.iter_mut().find(|guild| guild.id == guild_id as u64) {
+            Some(guild) => guild.members.push(GuildMember { character_id, rank }),
+            None => warn!(
+                "Guild member {} belongs to guild {} which doesn't exist",
+                character_id, guild_id
+            ),
+        }
+    }
+
+    Ok(guilds)
+}
+
+/// Inserts or updates the guild and replaces its members, a guild without
+/// members is deleted
+pub fn save_guild(guild: &Guild, transaction: &mut Transaction) -> Result<(), PersistenceError> {
+    if guild.members.is_empty() {
+        return delete_guild(guild.id, transaction);
+    }
+
+    let mut stmt = transaction.prepare_cached(
+        "
+        INSERT INTO guild (guild_id, name, tag, bank)
+        VALUES (?1, ?2, ?3, ?4)
+        ON CONFLICT(guild_id) DO UPDATE
+        SET name = excluded.name,
+            tag = excluded.tag,
+            bank = excluded.bank",
+    )?;
+
+    stmt.execute(&[
+        &(guild.id as i64) as &dyn ToSql,
+        &guild.name,
+        &guild.tag,
+        &serde_json::to_string(&guild.bank)?,
+    ])?;
+    drop(stmt);
+
+    let mut stmt = transaction.prepare_cached(
+        "
+        DELETE
+        FROM    guild_member
+        WHERE   guild_id = ?1",
+    )?;
+
+    stmt.execute(&[guild.id as i64])?;
+    drop(stmt);
+
+    // Characters can be deleted while the guild is held in memory, those are
+    // skipped rather than failing the foreign key constraint
+    let mut stmt = transaction.prepare_cached(
+        "
+        INSERT INTO guild_member (character_id, guild_id, rank)
+        SELECT  ?1, ?2, ?3
+        WHERE   EXISTS (SELECT 1 FROM character WHERE character_id = ?1)",
+    )?;
+
+    for member in guild.members.iter() {
+        stmt.execute(&[
+            &member.character_id as &dyn ToSql,
+            &(guild.id as i64),
+            &convert_rank_to_database(member.rank),
+        ])?;
+    }
+
+    Ok(())
+}
+
+fn delete_guild(guild_id: GuildId, transaction: &mut Transaction) -> Result<(), PersistenceError> {
+    let mut stmt = transaction.prepare_cached(
+        "
+        DELETE
+        FROM    guild_member
+        WHERE   guild_id = ?1",
+    )?;
+
+    stmt.execute(&[guild_id as i64])?;
+    drop(stmt);
+
+    let mut stmt = transaction.prepare_cached(
+        "
+        DELETE
+        FROM    guild
+        WHERE   guild_id = ?1",
+    )?;
+
+    stmt.execute(&[guild_id as i64])?;
+    Ok(())
+}
+
+fn convert_rank_to_database(rank: GuildRank) -> &'static str {
+    match rank {
+        GuildRank::Member => "Member",
+        GuildRank::Officer => "Officer",
+        GuildRank::Leader => "Leader",
+    }
+}
+
+fn convert_rank_from_database(rank: &str) -> Result<GuildRank, PersistenceError> {
+    match rank {
+        "Member" => Ok(GuildRank::Member),
+        "Officer" => Ok(GuildRank::Officer),
+        "Leader" => Ok(GuildRank::Leader),
+        _ => Err(PersistenceError::ConversionError(format!(
+            "Unknown guild rank: {}",
+            rank
+        ))),
+    }
+}
@@ -77,12 +77,15 @@ pub fn skill_group_to_db_string(skill_group: comp::skillset::SkillGroupKind) ->
         Weapon(ToolKind::Staff) => "Weapon Staff",
         Weapon(ToolKind::Sceptre) => "Weapon Sceptre",
         Weapon(ToolKind::Pick) => "Weapon Pick",
+        Magic => "Magic",
+        Crafting => "Crafting",
         Weapon(ToolKind::Dagger)
         | Weapon(ToolKind::Shield)
         | Weapon(ToolKind::Spear)
         | Weapon(ToolKind::Blowgun)
         | Weapon(ToolKind::Debug)
         | Weapon(ToolKind::Farming)
+        | Weapon(ToolKind::Fishing)
         | Weapon(ToolKind::Empty)
         | Weapon(ToolKind::Natural) => panic!(
             "Tried to add unsupported skill group to database: {:?}",
@@ -103,6 +106,8 @@ pub fn db_string_to_skill_group(skill_group_string: &str) -> comp::skillset::Ski
         "Weapon Staff" => Weapon(ToolKind::Staff),
         "Weapon Sceptre" => Weapon(ToolKind::Sceptre),
         "Weapon Pick" => Weapon(ToolKind::Pick),
+        "Magic" => Magic,
+        "Crafting" => Crafting,
         _ => panic!(
             "Tried to convert an unsupported string from the database: {}",
             skill_group_string
@@ -200,6 +205,7 @@ fn tool_kind_to_string(tool: Option<common::comp::item::tool::ToolKind>) -> Stri
 
         // Toolkinds that are not anticipated to have many active aiblities (if any at all)
         Some(Farming) => "Farming",
+        Some(Fishing) => "Fishing",
         Some(Debug) => "Debug",
         Some(Natural) => "Natural",
         Some(Empty) => "Empty",
@@ -223,6 +229,7 @@ fn tool_kind_from_string(tool: String) -> Option<common::comp::item::tool::ToolK
         "Pick" => Some(Pick),
 
         "Farming" => Some(Farming),
+        "Fishing" => Some(Fishing),
         "Debug" => Some(Debug),
         "Natural" => Some(Natural),
         "Empty" => Some(Empty),
@@ -5,8 +5,11 @@ pub mod character_loader;
 pub mod character_updater;
 mod diesel_to_rusqlite;
 pub mod error;
+pub mod friends;
+pub mod guild;
 mod json_models;
 mod models;
+pub mod rules;
 
 use crate::persistence::character_updater::PetPersistenceData;
 use common::comp;
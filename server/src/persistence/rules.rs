@@ -0,0 +1,46 @@
+//! Database operations for the server rules accepted by players
+
+use crate::persistence::error::PersistenceError;
+use rusqlite::{Connection, ToSql, Transaction, NO_PARAMS};
+use std::collections::HashMap;
+
+/// Loads the hash of the rules each player accepted last, by player uuid
+pub fn load_accepted_rules(
+    connection: &Connection,
+) -> Result<HashMap<String, u64>, PersistenceError> {
+    let mut stmt = connection.prepare_cached(
+        "
+        SELECT  player_uuid,
+                rules_hash
+        FROM    accepted_rules",
+    )?;
+
+    let accepted = stmt
+        .query_map(NO_PARAMS, |row| {
+            // The hash is stored as the signed integer of the same bits
+            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as u64))
+        })?
+        .collect::<Result<_, _>>()?;
+
+    Ok(accepted)
+}
+
+/// Stores the hash of the rules the player accepted, replacing the previous
+/// one
+pub fn save_accepted_rules(
+    player_uuid: &str,
+    rules_hash: u64,
+    transaction: &mut Transaction,
+) -> Result<(), PersistenceError> {
+    let mut stmt = transaction.prepare_cached(
+        "
+        INSERT INTO accepted_rules (player_uuid, rules_hash)
+        VALUES (?1, ?2)
+        ON CONFLICT(player_uuid) DO UPDATE
+        SET rules_hash = excluded.rules_hash",
+    )?;
+
+    stmt.execute(&[&player_uuid as &dyn ToSql, &(rules_hash as i64)])?;
+
+    Ok(())
+}
@@ -0,0 +1,71 @@
+use common_net::msg::rules_hash;
+use hashbrown::HashMap;
+
+/// Rules of the server and the players who accepted them, loaded from the
+/// database on startup. Acceptances are written back through the
+/// `CharacterUpdater`.
+pub struct ServerRules {
+    /// The rules along with their hash, `None` if the server has no rules
+    rules: Option<(String, u64)>,
+    /// Hash of the rules each player accepted last, by player uuid
+    accepted: HashMap<String, u64>,
+}
+
+impl ServerRules {
+    pub fn new(rules: Option<String>, accepted: impl IntoIterator<Item = (String, u64)>) -> Self {
+        Self {
+            rules: rules.filter(|rules| !rules.trim().is_empty()).map(|rules| {
+                let hash = rules_hash(&rules);
+                (rules, hash)
+            }),
+            accepted: accepted.into_iter().collect(),
+        }
+    }
+
+    /// The rules if the player has yet to accept them, either because they
+    /// never did or because the rules changed since
+    pub fn pending(&self, player_uuid: &str) -> Option<&str> {
+        self.rules
+            .as_ref()
+            .filter(|(_, hash)| self.accepted.get(player_uuid) != Some(hash))
+            .map(|(rules, _)| rules.as_str())
+    }
+
+    /// Accepts the rules for the player, returns false if `hash` isn't the
+    /// one of the current rules
+    pub fn accept(&mut self, player_uuid: &str, hash: u64) -> bool {
+        match &self.rules {
+            Some((_, current)) if *current == hash => {
+                self.accepted.insert(player_uuid.to_owned(), hash);
+                true
+            },
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_rules_are_never_pending() {
+        let rules = ServerRules::new(Some(" \n".to_owned()), []);
+        assert_eq!(rules.pending("a"), None);
+        assert_eq!(ServerRules::new(None, []).pending("a"), None);
+    }
+
+    #[test]
+    fn changed_rules_are_pending_again() {
+        let mut rules = ServerRules::new(Some("Be nice".to_owned()), []);
+        assert_eq!(rules.pending("a"), Some("Be nice"));
+        assert!(!rules.accept("a", rules_hash("Be mean")));
+        assert!(rules.accept("a", rules_hash("Be nice")));
+        assert_eq!(rules.pending("a"), None);
+        assert_eq!(rules.pending("b"), Some("Be nice"));
+
+        let accepted = rules.accepted;
+        let rules = ServerRules::new(Some("Be very nice".to_owned()), accepted);
+        assert_eq!(rules.pending("a"), Some("Be very nice"));
+    }
+}
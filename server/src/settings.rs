@@ -18,7 +18,9 @@ use common::{
     calendar::{Calendar, CalendarEvent},
     resources::BattleMode,
 };
+use common_net::msg::DEFAULT_MAX_BATCH_BYTES;
 use core::time::Duration;
+use network::Network;
 use portpicker::pick_unused_port;
 use serde::{Deserialize, Serialize};
 use std::{
@@ -69,6 +71,13 @@ pub enum Protocol {
     Tcp {
         address: SocketAddr,
     },
+    /// Tcp encrypted with Tls. The fingerprint of the certificate is logged on
+    /// startup, players need it to connect if the certificate is self-signed.
+    TcpTls {
+        address: SocketAddr,
+        cert_file_path: PathBuf,
+        key_file_path: PathBuf,
+    },
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -94,6 +103,22 @@ impl CalendarMode {
     }
 }
 
+/// What happens to the items of a player that dies
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DeathItemPolicy {
+    /// Players keep all their items
+    KeepAll,
+    /// Everything that isn't equipped is put into a gravestone where the
+    /// player died
+    DropAll,
+    /// Only coins are put into a gravestone where the player died
+    DropCoinsOnly,
+}
+
+impl Default for DeathItemPolicy {
+    fn default() -> Self { Self::KeepAll }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(default)]
 pub struct Settings {
@@ -116,6 +141,36 @@ pub struct Settings {
     pub safe_spawn: bool,
     pub max_player_for_kill_broadcast: Option<usize>,
     pub calendar_mode: CalendarMode,
+    /// Passes of hydraulic erosion run on every generated chunk, more passes
+    /// give smoother terrain but slow down chunk generation. 0 disables it.
+    pub erosion_iterations: u32,
+    /// Chunks generated at the same time at most, defaults to three quarters
+    /// of the available threads
+    pub chunk_gen_workers: Option<u64>,
+    /// Fraction of the online players that have to sleep in a bed to skip the
+    /// night
+    pub sleep_fraction: f32,
+    pub death_item_policy: DeathItemPolicy,
+    /// Time during which only the owner of a gravestone can loot it
+    pub gravestone_grace_period: Duration,
+    /// Time after which a gravestone despawns, along with the items left in it
+    pub gravestone_lifetime: Duration,
+    /// The messages sent to a client during a tick are batched, a batch is
+    /// sent early when it grows beyond this many bytes
+    pub max_batch_bytes: usize,
+    /// How long messages still queued for a disconnecting client are sent,
+    /// e.g. the reason it got kicked. Those not sent by then are dropped.
+    pub network_shutdown_grace: Duration,
+    /// When set, clients have to encrypt their Tcp channel. The server
+    /// authenticates with the key stored in this file, which is generated if
+    /// it doesn't exist. Its public key is logged on startup, players pin it
+    /// so that nobody in between can pose as the server. On unix a key file
+    /// that others than its owner can read is refused.
+    pub encryption_key_file_path: Option<PathBuf>,
+    /// Rules players have to accept before they can select a character, they
+    /// are asked again whenever the rules change. Lines starting with `#` are
+    /// headings and lines starting with `-` or `*` are list items.
+    pub rules: Option<String>,
 
     /// Experimental feature. No guaranteed forwards-compatibility, may be
     /// removed at *any time* with no migration.
@@ -150,6 +205,16 @@ impl Default for Settings {
             spawn_town: None,
             safe_spawn: true,
             max_player_for_kill_broadcast: None,
+            erosion_iterations: 50,
+            chunk_gen_workers: None,
+            sleep_fraction: 1.0,
+            death_item_policy: DeathItemPolicy::default(),
+            gravestone_grace_period: Duration::from_secs(5 * 60),
+            gravestone_lifetime: Duration::from_secs(30 * 60),
+            max_batch_bytes: DEFAULT_MAX_BATCH_BYTES,
+            network_shutdown_grace: Network::DEFAULT_SHUTDOWN_GRACE,
+            encryption_key_file_path: None,
+            rules: None,
             experimental_terrain_persistence: false,
         }
     }
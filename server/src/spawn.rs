@@ -0,0 +1,186 @@
+//! Scripted encounters of spawned entities.
+//!
+//! A boss fight is split into phases by health thresholds. Whenever the
+//! health of the boss drops below the next threshold it gets stronger, stops
+//! backing off and calls in adds.
+
+use common::{
+    comp::{
+        self,
+        buff::{Buff, BuffCategory, BuffData, BuffKind, BuffSource},
+        Agent, Alignment, Body,
+    },
+    event::ServerEvent,
+    lottery::LootSpec,
+    uid::Uid,
+    LoadoutBuilder,
+};
+use specs::Component;
+use specs_idvs::IdvStorage;
+use std::f32::consts::PI;
+use vek::*;
+
+/// Health fractions at which bosses spawned without their own thresholds
+/// enter their next phases
+pub const DEFAULT_PHASE_THRESHOLDS: [f32; 2] = [0.66, 0.33];
+
+/// Adds spawned per phase the boss is in, so later phases call in more of them
+const ADDS_PER_PHASE: u32 = 2;
+/// Distance from the boss at which adds are spawned
+const ADD_DISTANCE: f32 = 4.0;
+/// Fraction of the health of the boss body adds spawn with
+const ADD_HEALTH_FRACTION: f32 = 0.2;
+/// How much faster the boss moves and attacks per phase, see
+/// [`BuffKind::Hastened`]
+const HASTE_PER_PHASE: f32 = 0.15;
+/// How much the boss resists damage per phase, see
+/// [`BuffKind::ProtectingWard`]
+const WARD_PER_PHASE: f32 = 0.2;
+
+#[derive(Clone, Debug)]
+pub struct BossEncounter {
+    pub boss_uid: Uid,
+    /// Phase the boss is in, 0 until the first threshold is crossed
+    pub phase: u8,
+    /// Health fractions below which the boss enters the next phase, in
+    /// decreasing order
+    pub phase_thresholds: Vec<f32>,
+}
+
+impl Component for BossEncounter {
+    type Storage = IdvStorage<Self>;
+}
+
+/// What changes about the boss when it enters a phase
+#[derive(Debug)]
+pub struct PhaseTransition {
+    /// Buffs the boss keeps for the rest of the fight
+    pub buffs: Vec<Buff>,
+    /// Whether the boss attacks anyone it is aware of from now on, instead of
+    /// waiting for them to come close
+    pub always_aggro: bool,
+    pub adds: u32,
+}
+
+impl PhaseTransition {
+    /// Changes the behavior of the boss for the new phase. Bosses never flee
+    /// once the fight got going.
+    pub fn apply_to(&self, agent: &mut Agent) {
+        agent.set_no_flee();
+        if self.always_aggro {
+            agent.psyche.aggro_dist = None;
+        }
+    }
+}
+
+impl BossEncounter {
+    pub fn new(boss_uid: Uid, mut phase_thresholds: Vec<f32>) -> Self {
+        phase_thresholds.sort_by(|a, b| b.partial_cmp(a).unwrap_or(std::cmp::Ordering::Equal));
+        Self {
+            boss_uid,
+            phase: 0,
+            phase_thresholds,
+        }
+    }
+
+    /// The phase to enter next at `health_fraction`, if the boss crossed the
+    /// threshold of its current phase
+    pub fn next_phase(&self, health_fraction: f32) -> Option<u8> {
+        self.phase_thresholds
+            .get(usize::from(self.phase))
+            .filter(|threshold| health_fraction < **threshold)
+            .map(|_| self.phase + 1)
+    }
+
+    /// Enters `phase`, returning what changes about the boss
+    pub fn trigger_transition(&mut self, phase: u8) -> PhaseTransition {
+        self.phase = phase;
+        let strength = f32::from(phase);
+        let buff = |kind, strength| {
+            Buff::new(
+                kind,
+                BuffData::new(strength, None),
+                vec![BuffCategory::PersistOnDeath],
+                BuffSource::World,
+            )
+        };
+        PhaseTransition {
+            buffs: vec![
+                buff(BuffKind::Hastened, HASTE_PER_PHASE * strength),
+                buff(BuffKind::ProtectingWard, WARD_PER_PHASE * strength),
+            ],
+            always_aggro: usize::from(phase) >= self.phase_thresholds.len(),
+            adds: ADDS_PER_PHASE * u32::from(phase),
+        }
+    }
+}
+
+/// Event spawning add number `i` of `count` around the boss at `pos`. Adds
+/// are smaller and much weaker versions of the boss, they only get a fraction
+/// of its health and its main tool without any armor.
+pub fn spawn_add(body: Body, pos: Vec3<f32>, i: u32, count: u32) -> ServerEvent {
+    let angle = i as f32 / count.max(1) as f32 * 2.0 * PI;
+    let offset = Vec3::new(angle.cos(), angle.sin(), 0.0) * ADD_DISTANCE;
+    let loadout = LoadoutBuilder::empty().with_default_maintool(&body).build();
+    ServerEvent::CreateNpc {
+        pos: comp::Pos(pos + offset),
+        stats: comp::Stats::new("Minion".to_owned()),
+        skill_set: comp::SkillSet::default(),
+        health: Some(comp::Health::new(body, 0).scaled(ADD_HEALTH_FRACTION)),
+        poise: comp::Poise::new(body),
+        inventory: comp::Inventory::new_with_loadout(loadout),
+        body,
+        agent: Some(Agent::from_body(&body).with_no_flee_if(true)),
+        alignment: Alignment::Enemy,
+        scale: comp::Scale(0.6),
+        anchor: None,
+        loot: LootSpec::Nothing,
+        rtsim_entity: None,
+        projectile: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn phases_follow_thresholds() {
+        let mut encounter = BossEncounter::new(Uid(1), vec![0.3, 0.7]);
+        assert_eq!(encounter.phase_thresholds, [0.7, 0.3]);
+        assert_eq!(encounter.next_phase(0.8), None);
+
+        // Crossing both thresholds at once goes through every phase
+        assert_eq!(encounter.next_phase(0.2), Some(1));
+        let transition = encounter.trigger_transition(1);
+        assert_eq!(transition.adds, ADDS_PER_PHASE);
+        assert!(!transition.always_aggro);
+        assert_eq!(encounter.next_phase(0.2), Some(2));
+        let transition = encounter.trigger_transition(2);
+        assert!(transition.always_aggro);
+        assert_eq!(encounter.next_phase(0.0), None);
+    }
+
+    #[test]
+    fn adds_are_weaker_than_the_boss() {
+        let body = Body::BipedLarge(comp::biped_large::Body::random());
+        let boss_health = comp::Health::new(body, 0);
+        match spawn_add(body, Vec3::zero(), 0, 2) {
+            ServerEvent::CreateNpc {
+                health: Some(health),
+                inventory,
+                ..
+            } => {
+                assert!(health.maximum() < boss_health.maximum() * 0.5);
+                assert_eq!(health.current(), health.maximum());
+                assert!(
+                    inventory
+                        .equipped_items()
+                        .all(|item| matches!(item.kind(), comp::item::ItemKind::Tool(_))),
+                    "Adds don't wear the armor of the boss"
+                );
+            },
+            _ => panic!("Adds are spawned as NPCs"),
+        }
+    }
+}
@@ -9,7 +9,6 @@ use crate::{
     wiring, BattleModeBuffer, SpawnPoint,
 };
 use common::{
-    calendar::Calendar,
     character::CharacterId,
     combat,
     combat::DamageContributor,
@@ -21,8 +20,7 @@ use common::{
     effect::Effect,
     link::{Link, LinkHandle},
     mounting::Mounting,
-    resources::{Time, TimeOfDay},
-    slowjob::SlowJobPool,
+    resources::Time,
     uid::{Uid, UidAllocator},
 };
 use common_net::{
@@ -97,13 +95,7 @@ pub trait StateExt {
     /// Queues chunk generation in the view distance of the persister, this
     /// entity must be built before those chunks are received (the builder
     /// borrows the ecs world so that is kind of impossible in practice)
-    fn create_persister(
-        &mut self,
-        pos: comp::Pos,
-        view_distance: u32,
-        world: &std::sync::Arc<world::World>,
-        index: &world::IndexOwned,
-    ) -> EcsEntityBuilder;
+    fn create_persister(&mut self, pos: comp::Pos, view_distance: u32) -> EcsEntityBuilder;
     /// Insert common/default components for a new character joining the server
     fn initialize_character_data(&mut self, entity: EcsEntity, character_id: CharacterId);
     /// Update the components associated with the entity's current character.
@@ -233,6 +225,7 @@ impl StateExt for State {
             )
             .with(body.mass())
             .with(body.density())
+            .with(comp::TerminalVelocity::default())
             .with(match body {
                 comp::Body::Ship(ship) => ship.make_collider(),
                 _ => capsule(&body),
@@ -433,19 +426,11 @@ impl StateExt for State {
     /// Queues chunk generation in the view distance of the persister, this
     /// entity must be built before those chunks are received (the builder
     /// borrows the ecs world so that is kind of impossible in practice)
-    fn create_persister(
-        &mut self,
-        pos: comp::Pos,
-        view_distance: u32,
-        world: &std::sync::Arc<world::World>,
-        index: &world::IndexOwned,
-    ) -> EcsEntityBuilder {
+    fn create_persister(&mut self, pos: comp::Pos, view_distance: u32) -> EcsEntityBuilder {
         use common::{terrain::TerrainChunkSize, vol::RectVolSize};
-        use std::sync::Arc;
         // Request chunks
         {
             let ecs = self.ecs();
-            let slow_jobs = ecs.write_resource::<SlowJobPool>();
             let mut chunk_generator =
                 ecs.write_resource::<crate::chunk_generator::ChunkGenerator>();
             let chunk_pos = self.terrain().pos_key(pos.0.map(|e| e as i32));
@@ -463,11 +448,10 @@ impl StateExt for State {
                     * TerrainChunkSize::RECT_SIZE.x as f64
             })
             .for_each(|chunk_key| {
-                #[cfg(feature = "worldgen")]
-                {
-                    let time = (*ecs.read_resource::<TimeOfDay>(), (*ecs.read_resource::<Calendar>()).clone());
-                    chunk_generator.generate_chunk(None, chunk_key, &slow_jobs, Arc::clone(world), index.clone(), time);
-                }
+                let dist_sqr = (chunk_key - chunk_pos)
+                    .map(|e| e.unsigned_abs())
+                    .magnitude_squared();
+                chunk_generator.generate_chunk(None, chunk_key, dist_sqr);
             });
         }
 
@@ -489,6 +473,7 @@ impl StateExt for State {
             self.write_component_ignore_entity_dead(entity, comp::Pos(spawn_point));
             self.write_component_ignore_entity_dead(entity, comp::Vel(Vec3::zero()));
             self.write_component_ignore_entity_dead(entity, comp::Ori::default());
+            self.write_component_ignore_entity_dead(entity, comp::TerminalVelocity::default());
             self.write_component_ignore_entity_dead(entity, comp::Collider::CapsulePrism {
                 p0: Vec2::zero(),
                 p1: Vec2::zero(),
@@ -2,9 +2,9 @@ use crate::sys::agent::{AgentData, ReadData};
 use common::{
     comp::{agent::Psyche, buff::BuffKind, Alignment, Pos},
     consts::GRAVITY,
+    ray::RayCast,
     terrain::{Block, TerrainGrid},
     util::Dir,
-    vol::ReadVol,
 };
 use specs::{
     saveload::{Marker, MarkerAllocator},
@@ -13,13 +13,10 @@ use specs::{
 use vek::*;
 
 pub fn can_see_tgt(terrain: &TerrainGrid, pos: &Pos, tgt_pos: &Pos, dist_sqrd: f32) -> bool {
-    terrain
-        .ray(pos.0 + Vec3::unit_z(), tgt_pos.0 + Vec3::unit_z())
+    RayCast::between(terrain, pos.0 + Vec3::unit_z(), tgt_pos.0 + Vec3::unit_z())
         .until(Block::is_opaque)
         .cast()
-        .0
-        .powi(2)
-        >= dist_sqrd
+        .map_or(true, |hit| hit.dist.powi(2) >= dist_sqrd)
 }
 
 pub fn is_dead_or_invulnerable(entity: EcsEntity, read_data: &ReadData) -> bool {
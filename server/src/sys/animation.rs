@@ -0,0 +1,92 @@
+use crate::{client::Client, Tick};
+use common::{
+    comp::{CharacterState, Pos, Stats},
+    uid::Uid,
+};
+use common_ecs::{Job, Origin, Phase, System};
+use common_net::msg::ServerGeneral;
+use specs::{Component, Entities, Join, Read, ReadStorage, WriteStorage};
+use specs_idvs::IdvStorage;
+
+/// Ticks between two updates of the modifiers that aren't the normal speed,
+/// for the clients that came close since they changed
+const RESEND_INTERVAL: u64 = 30;
+/// Clients further away from an entity than this don't get its updates
+const UPDATE_RANGE: f32 = 128.0;
+
+/// Speed modifier of the animations of an entity that was last sent to the
+/// clients
+pub struct AnimationSpeed(pub f32);
+
+impl Component for AnimationSpeed {
+    type Storage = IdvStorage<Self>;
+}
+
+/// How much faster than normal the animations of a character in `character`
+/// play, attacks follow the attack speed and everything else the move speed
+pub fn speed_modifier(character: &CharacterState, stats: &Stats) -> f32 {
+    if character.is_attack() {
+        stats.attack_speed_modifier
+    } else {
+        stats.move_speed_modifier
+    }
+}
+
+/// This system tells the clients nearby how fast the animations of hastened
+/// or slowed characters play
+#[derive(Default)]
+pub struct Sys;
+impl<'a> System<'a> for Sys {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, Tick>,
+        ReadStorage<'a, Uid>,
+        ReadStorage<'a, Pos>,
+        ReadStorage<'a, CharacterState>,
+        ReadStorage<'a, Stats>,
+        ReadStorage<'a, Client>,
+        WriteStorage<'a, AnimationSpeed>,
+    );
+
+    const NAME: &'static str = "animation";
+    const ORIGIN: Origin = Origin::Server;
+    const PHASE: Phase = Phase::Create;
+
+    fn run(
+        _job: &mut Job<Self>,
+        (
+            entities,
+            tick,
+            uids,
+            positions,
+            character_states,
+            stats,
+            clients,
+            mut animation_speeds,
+        ): Self::SystemData,
+    ) {
+        let resend = tick.0 % RESEND_INTERVAL == 0;
+        for (entity, uid, pos, character, stats) in
+            (&entities, &uids, &positions, &character_states, &stats).join()
+        {
+            let speed_modifier = speed_modifier(character, stats);
+            let last_sent = animation_speeds.get(entity).map_or(1.0, |speed| speed.0);
+            let changed = (speed_modifier - last_sent).abs() > f32::EPSILON;
+            if !changed && !(resend && speed_modifier != 1.0) {
+                continue;
+            }
+            if changed {
+                let _ = animation_speeds.insert(entity, AnimationSpeed(speed_modifier));
+            }
+
+            for (client, client_pos) in (&clients, &positions).join() {
+                if client_pos.0.distance_squared(pos.0) < UPDATE_RANGE.powi(2) {
+                    client.send_fallible(ServerGeneral::EntityAnimation {
+                        entity: *uid,
+                        speed_modifier,
+                    });
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use crate::{
+    client::Client,
+    presence::Presence,
+    spawn::{spawn_add, BossEncounter},
+};
+use common::{
+    comp::{Agent, Body, BuffChange, Health, Pos},
+    event::{EventBus, ServerEvent},
+};
+use common_ecs::{Job, Origin, Phase, System};
+use common_net::msg::{ServerGeneral, WorldEventKind};
+use specs::{Entities, Join, Read, ReadStorage, WriteStorage};
+
+/// This system moves bosses on to their next phase once their health drops
+/// below its threshold, and tells every player about it
+#[derive(Default)]
+pub struct Sys;
+impl<'a> System<'a> for Sys {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, EventBus<ServerEvent>>,
+        WriteStorage<'a, BossEncounter>,
+        WriteStorage<'a, Agent>,
+        ReadStorage<'a, Health>,
+        ReadStorage<'a, Pos>,
+        ReadStorage<'a, Body>,
+        ReadStorage<'a, Presence>,
+        ReadStorage<'a, Client>,
+    );
+
+    const NAME: &'static str = "boss";
+    const ORIGIN: Origin = Origin::Server;
+    const PHASE: Phase = Phase::Create;
+
+    fn run(
+        _job: &mut Job<Self>,
+        (
+            entities,
+            server_bus,
+            mut encounters,
+            mut agents,
+            healths,
+            positions,
+            bodies,
+            presences,
+            clients,
+        ): Self::SystemData,
+    ) {
+        let mut server_emitter = server_bus.emitter();
+        let mut events = Vec::new();
+        for (entity, encounter, health, pos, body) in
+            (&entities, &mut encounters, &healths, &positions, &bodies).join()
+        {
+            if health.is_dead {
+                continue;
+            }
+            // Heavy hits can skip phases, their transitions still all happen
+            while let Some(phase) = encounter.next_phase(health.fraction()) {
+                let transition = encounter.trigger_transition(phase);
+                for buff in transition.buffs.iter().cloned() {
+                    server_emitter.emit(ServerEvent::Buff {
+                        entity,
+                        buff_change: BuffChange::Add(buff),
+                    });
+                }
+                if let Some(agent) = agents.get_mut(entity) {
+                    transition.apply_to(agent);
+                }
+                for i in 0..transition.adds {
+                    server_emitter.emit(spawn_add(*body, pos.0, i, transition.adds));
+                }
+                events.push(WorldEventKind::BossPhaseChange {
+                    boss_uid: encounter.boss_uid,
+                    new_phase: phase,
+                });
+            }
+        }
+
+        for event in events {
+            for (client, _) in (&clients, &presences).join() {
+                client.send_fallible(ServerGeneral::WorldEvent(event));
+            }
+        }
+    }
+}
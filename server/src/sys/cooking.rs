@@ -0,0 +1,67 @@
+use crate::{client::Client, Tick};
+use common::{
+    comp::{
+        item::{tool::AbilityMap, MaterialStatManifest},
+        CookingPot, Pos,
+    },
+    recipe::default_recipe_book,
+    resources::DeltaTime,
+};
+use common_ecs::{Job, Origin, Phase, System};
+use common_net::msg::ServerGeneral;
+use specs::{Join, Read, ReadExpect, ReadStorage, WriteStorage};
+
+/// Ticks between two updates of the cooking pots sent to clients
+const UPDATE_INTERVAL: u64 = 5;
+/// Clients further away from a pot than this don't get its updates
+const UPDATE_RANGE: f32 = 64.0;
+
+/// This system cooks the recipes loaded into cooking pots that have fuel left
+/// and keeps the clients nearby informed about their progress
+#[derive(Default)]
+pub struct Sys;
+impl<'a> System<'a> for Sys {
+    type SystemData = (
+        Read<'a, DeltaTime>,
+        Read<'a, Tick>,
+        ReadExpect<'a, AbilityMap>,
+        ReadExpect<'a, MaterialStatManifest>,
+        ReadStorage<'a, Pos>,
+        ReadStorage<'a, Client>,
+        WriteStorage<'a, CookingPot>,
+    );
+
+    const NAME: &'static str = "cooking";
+    const ORIGIN: Origin = Origin::Server;
+    const PHASE: Phase = Phase::Create;
+
+    fn run(
+        _job: &mut Job<Self>,
+        (dt, tick, ability_map, msm, positions, clients, mut cooking_pots): Self::SystemData,
+    ) {
+        let recipe_book = default_recipe_book().read();
+        for cooking_pot in (&mut cooking_pots).join() {
+            let recipe = cooking_pot
+                .recipe
+                .as_ref()
+                .and_then(|recipe| recipe_book.get(recipe));
+            cooking_pot.tick(dt.0, recipe, &ability_map, &msm);
+        }
+
+        if tick.0 % UPDATE_INTERVAL != 0 {
+            return;
+        }
+        for (cooking_pot, pot_pos) in (&cooking_pots, &positions).join() {
+            let block_pos = pot_pos.0.map(|e| e.floor() as i32);
+            for (client, pos) in (&clients, &positions).join() {
+                if pos.0.distance_squared(pot_pos.0) < UPDATE_RANGE.powi(2) {
+                    client.send_fallible(ServerGeneral::CookingUpdate(
+                        block_pos,
+                        cooking_pot.progress,
+                        cooking_pot.fuel_remaining,
+                    ));
+                }
+            }
+        }
+    }
+}
@@ -1,4 +1,7 @@
-use super::sentinel::{DeletedEntities, TrackedStorages, UpdateTrackers};
+use super::{
+    sentinel::{DeletedEntities, TrackedStorages, UpdateTrackers},
+    sleep::Sleepers,
+};
 use crate::{
     client::Client,
     presence::{Presence, RegionSubscription},
@@ -9,13 +12,16 @@ use common::{
     comp::{Collider, ForceUpdate, InventoryUpdate, Last, Ori, Pos, Vel},
     outcome::Outcome,
     region::{Event as RegionEvent, RegionMap},
-    resources::{PlayerPhysicsSettings, TimeOfDay},
+    resources::{GameTime, PlayerPhysicsSettings, TimeOfDay},
     terrain::TerrainChunkSize,
     uid::Uid,
     vol::RectVolSize,
 };
 use common_ecs::{Job, Origin, Phase, System};
-use common_net::{msg::ServerGeneral, sync::CompSyncPackage};
+use common_net::{
+    msg::ServerGeneral,
+    sync::{CompSyncPackage, PhysicsDeltaPackage, PhysicsSnapshot},
+};
 use itertools::Either;
 use specs::{Entities, Join, Read, ReadExpect, ReadStorage, Write, WriteStorage};
 use vek::*;
@@ -32,7 +38,9 @@ impl<'a> System<'a> for Sys {
         Read<'a, PlayerPhysicsSettings>,
         TrackedStorages<'a>,
         ReadExpect<'a, TimeOfDay>,
+        Read<'a, GameTime>,
         ReadExpect<'a, Calendar>,
+        Read<'a, Sleepers>,
         ReadExpect<'a, RegionMap>,
         ReadExpect<'a, UpdateTrackers>,
         ReadStorage<'a, Pos>,
@@ -62,7 +70,9 @@ impl<'a> System<'a> for Sys {
             player_physics_settings,
             tracked_storages,
             time_of_day,
+            game_time,
             calendar,
+            sleepers,
             region_map,
             trackers,
             positions,
@@ -163,6 +173,7 @@ impl<'a> System<'a> for Sys {
                                         Some(*pos),
                                         vel.copied(),
                                         ori.copied(),
+                                        true,
                                     )
                                 })
                             {
@@ -225,6 +236,8 @@ impl<'a> System<'a> for Sys {
 
                 for (client, _, client_entity, client_pos) in &mut subscribers {
                     let mut comp_sync_package = CompSyncPackage::new();
+                    let mut physics_package = PhysicsDeltaPackage::default();
+                    let mut physics_baselines = client.physics_baselines.lock().unwrap();
 
                     for (_, entity, &uid, (&pos, last_pos), vel, ori, force_update, collider) in (
                         region.entities(),
@@ -283,34 +296,53 @@ impl<'a> System<'a> for Sys {
 
                         if last_pos.is_none() {
                             comp_sync_package.comp_inserted(uid, pos);
-                        } else if send_now {
-                            comp_sync_package.comp_modified(uid, pos);
                         }
 
-                        if let Some((v, last_vel)) = vel {
-                            if last_vel.is_none() {
-                                comp_sync_package.comp_inserted(uid, *v);
-                            } else if send_now {
-                                comp_sync_package.comp_modified(uid, *v);
-                            }
+                        if let Some((v, None)) = vel {
+                            comp_sync_package.comp_inserted(uid, *v);
                         }
 
-                        if let Some((o, last_ori)) = ori {
-                            if last_ori.is_none() {
-                                comp_sync_package.comp_inserted(uid, *o);
-                            } else if send_now {
-                                comp_sync_package.comp_modified(uid, *o);
-                            }
+                        if let Some((o, None)) = ori {
+                            comp_sync_package.comp_inserted(uid, *o);
+                        }
+
+                        if send_now {
+                            let snapshot = PhysicsSnapshot::new(
+                                pos,
+                                vel.map(|(v, _)| *v),
+                                ori.map(|(o, _)| *o),
+                            );
+                            physics_package
+                                .updates
+                                .push((uid, physics_baselines.encode(uid, snapshot)));
+                        } else {
+                            physics_baselines.keep(uid);
                         }
                     }
+                    drop(physics_baselines);
 
                     client.send_fallible(ServerGeneral::CompSync(comp_sync_package));
+                    if !physics_package.is_empty() {
+                        client.send_fallible(ServerGeneral::EntityPhysicsDelta(physics_package));
+                    }
                 }
             },
         );
         drop(guard);
         job.cpu_stats.measure(common_ecs::ParMode::Single);
 
+        // Drop the physics baselines of entities that went out of sight, so that
+        // they get a full update when they come back
+        for client in (&clients).join() {
+            let forgotten = client.physics_baselines.lock().unwrap().take_unused();
+            if !forgotten.is_empty() {
+                client.send_fallible(ServerGeneral::EntityPhysicsDelta(PhysicsDeltaPackage {
+                    updates: Vec::new(),
+                    forgotten,
+                }));
+            }
+        }
+
         // Update the last physics components for each entity
         for (_, &pos, vel, ori, last_pos, last_vel, last_ori) in (
             &entities,
@@ -364,6 +396,9 @@ impl<'a> System<'a> for Sys {
             if !comp_sync_package.is_empty() {
                 client.send_fallible(ServerGeneral::CompSync(comp_sync_package));
             }
+            if let Some(diff) = trackers.create_skill_set_delta(&tracked_storages, entity) {
+                client.send_fallible(ServerGeneral::SkillSetDelta(diff));
+            }
         }
 
         // Sync outcomes
@@ -395,16 +430,22 @@ impl<'a> System<'a> for Sys {
         // TODO: doesn't really belong in this system (rename system or create another
         // system?)
         const TOD_SYNC_FREQ: u64 = 100;
-        if tick % TOD_SYNC_FREQ == 0 {
+        // Clients would only catch up with a skipped night at the next sync otherwise
+        if tick % TOD_SYNC_FREQ == 0 || sleepers.is_fast_forwarding() {
             let mut tod_lazymsg = None;
+            let mut game_time_lazymsg = None;
             for client in (&clients).join() {
                 let msg = tod_lazymsg.unwrap_or_else(|| {
                     client.prepare(ServerGeneral::TimeOfDay(*time_of_day, (*calendar).clone()))
                 });
+                let game_time_msg = game_time_lazymsg
+                    .unwrap_or_else(|| client.prepare(ServerGeneral::GameTime(*game_time)));
                 // We don't care much about stream errors here since they could just represent
                 // network disconnection, which is handled elsewhere.
                 let _ = client.send_prepared(&msg);
+                let _ = client.send_prepared(&game_time_msg);
                 tod_lazymsg = Some(msg);
+                game_time_lazymsg = Some(game_time_msg);
             }
         }
     }
@@ -0,0 +1,20 @@
+use common::{comp::FarmPlot, resources::Time};
+use common_ecs::{Job, Origin, Phase, System};
+use specs::{Join, Read, WriteStorage};
+
+/// This system grows the crops of farm plots that were watered recently
+#[derive(Default)]
+pub struct Sys;
+impl<'a> System<'a> for Sys {
+    type SystemData = (Read<'a, Time>, WriteStorage<'a, FarmPlot>);
+
+    const NAME: &'static str = "farming";
+    const ORIGIN: Origin = Origin::Server;
+    const PHASE: Phase = Phase::Create;
+
+    fn run(_job: &mut Job<Self>, (time, mut farm_plots): Self::SystemData) {
+        for farm_plot in (&mut farm_plots).join() {
+            farm_plot.tick(*time);
+        }
+    }
+}
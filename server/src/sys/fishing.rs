@@ -0,0 +1,145 @@
+use crate::{
+    client::Client,
+    fishing::{holds_fishing_rod, FishingLine, FishingMinigame, ReelIn, BITE_DIP_SPEED},
+};
+use common::{
+    comp::{CharacterState, Inventory, Pos, Vel},
+    event::{EventBus, ServerEvent},
+    outcome::Outcome,
+    resources::{DeltaTime, Time},
+    states::wielding,
+};
+use common_ecs::{Job, Origin, Phase, System};
+use common_net::msg::{FishingState, Notification, ServerGeneral};
+use specs::{Entities, Join, Read, ReadStorage, Write, WriteStorage};
+
+/// This system waits for fish to bite at the cast lines, judges whether the
+/// players reeled in at the right time, plays the fishing minigame of every
+/// player with a fish on the line and hands out the catch
+#[derive(Default)]
+pub struct Sys;
+impl<'a> System<'a> for Sys {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, Time>,
+        Read<'a, DeltaTime>,
+        Read<'a, EventBus<ServerEvent>>,
+        Write<'a, Vec<Outcome>>,
+        ReadStorage<'a, Pos>,
+        ReadStorage<'a, Inventory>,
+        ReadStorage<'a, Client>,
+        WriteStorage<'a, Vel>,
+        WriteStorage<'a, CharacterState>,
+        WriteStorage<'a, FishingLine>,
+        WriteStorage<'a, FishingMinigame>,
+    );
+
+    const NAME: &'static str = "fishing";
+    const ORIGIN: Origin = Origin::Server;
+    const PHASE: Phase = Phase::Create;
+
+    fn run(
+        _job: &mut Job<Self>,
+        (
+            entities,
+            time,
+            dt,
+            server_bus,
+            mut outcomes,
+            positions,
+            inventories,
+            clients,
+            mut velocities,
+            mut character_states,
+            mut lines,
+            mut minigames,
+        ): Self::SystemData,
+    ) {
+        let mut server_emitter = server_bus.emitter();
+        let mut finished = Vec::new();
+
+        for (entity, line, pos, inventory, character_state, client) in (
+            &entities,
+            &mut lines,
+            &positions,
+            &inventories,
+            &character_states,
+            clients.maybe(),
+        )
+            .join()
+        {
+            let state = if !holds_fishing_rod(inventory)
+                || !matches!(character_state, CharacterState::Fishing(_))
+            {
+                FishingState::Abandoned
+            } else if let Some(minigame) = minigames.get_mut(entity) {
+                minigame.tick(dt.0)
+            } else if let Some(reel_in) = line.reel_in.take() {
+                match line.timer.reel_in(reel_in) {
+                    ReelIn::Hooked => {
+                        let _ = minigames.insert(entity, line.hook());
+                        FishingState::Reeling
+                    },
+                    ReelIn::TooEarly | ReelIn::TooLate => FishingState::Missed,
+                }
+            } else if line.timer.timed_out(time.0) {
+                FishingState::Missed
+            } else {
+                if !line.bitten && line.timer.is_biting(time.0) {
+                    line.bitten = true;
+                    if let Some(vel) = velocities.get_mut(line.bobber) {
+                        vel.0.z = -BITE_DIP_SPEED;
+                    }
+                    if let Some(bobber_pos) = positions.get(line.bobber) {
+                        outcomes.push(Outcome::Splash {
+                            pos: bobber_pos.0,
+                            power: 1.0,
+                        });
+                    }
+                }
+                // Still waiting for the fish, nothing to tell the player
+                continue;
+            };
+
+            if state == FishingState::Caught {
+                if let Some(item) = line.catch() {
+                    if let Some(client) = client {
+                        client.send_fallible(ServerGeneral::Notification(Notification::Caught(
+                            item.name().to_owned(),
+                            item.amount(),
+                        )));
+                    }
+                    server_emitter.emit(ServerEvent::CreateItemDrop { pos: *pos, item });
+                }
+            }
+            if state != FishingState::Reeling {
+                finished.push(entity);
+            }
+
+            if let Some(client) = client {
+                let (progress, tension) = minigames
+                    .get(entity)
+                    .map_or((0.0, 0.0), |minigame| (minigame.progress, minigame.tension));
+                client.send_fallible(ServerGeneral::FishingUpdate {
+                    progress,
+                    tension,
+                    state,
+                });
+            }
+        }
+
+        // The bobbers of the lines are removed along with them, see the object
+        // system
+        for entity in finished {
+            lines.remove(entity);
+            minigames.remove(entity);
+            if let Some(character_state) = character_states.get_mut(entity) {
+                if matches!(*character_state, CharacterState::Fishing(_)) {
+                    *character_state =
+                        CharacterState::Wielding(wielding::Data { is_sneaking: false });
+                }
+            }
+        }
+    }
+}
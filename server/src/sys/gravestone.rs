@@ -0,0 +1,94 @@
+use crate::{client::Client, presence::Presence, Tick};
+use common::{
+    character::CharacterId,
+    comp::{Gravestone, Inventory, Pos},
+    event::{EventBus, ServerEvent},
+    resources::Time,
+    trade::Trades,
+    uid::Uid,
+};
+use common_ecs::{Job, Origin, Phase, System};
+use common_net::msg::{PresenceKind, ServerGeneral};
+use hashbrown::HashMap;
+use specs::{Entities, Entity as EcsEntity, Join, Read, ReadStorage, Write};
+use vek::*;
+
+/// Ticks between two checks of the gravestones
+const UPDATE_INTERVAL: u64 = 10;
+
+/// Positions of the gravestones each player was last told about
+#[derive(Debug, Default)]
+pub struct GravestoneMarkers(HashMap<(EcsEntity, CharacterId), Vec<Vec3<f32>>>);
+
+/// This system despawns gravestones that expired or were emptied, and keeps
+/// their owners informed about where their remaining gravestones are
+#[derive(Default)]
+pub struct Sys;
+impl<'a> System<'a> for Sys {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, Tick>,
+        Read<'a, Time>,
+        Read<'a, Trades>,
+        Read<'a, EventBus<ServerEvent>>,
+        Write<'a, GravestoneMarkers>,
+        ReadStorage<'a, Uid>,
+        ReadStorage<'a, Pos>,
+        ReadStorage<'a, Gravestone>,
+        ReadStorage<'a, Inventory>,
+        ReadStorage<'a, Presence>,
+        ReadStorage<'a, Client>,
+    );
+
+    const NAME: &'static str = "gravestone";
+    const ORIGIN: Origin = Origin::Server;
+    const PHASE: Phase = Phase::Create;
+
+    fn run(
+        _job: &mut Job<Self>,
+        (
+            entities,
+            tick,
+            time,
+            trades,
+            server_bus,
+            mut markers,
+            uids,
+            positions,
+            gravestones,
+            inventories,
+            presences,
+            clients,
+        ): Self::SystemData,
+    ) {
+        if tick.0 % UPDATE_INTERVAL != 0 {
+            return;
+        }
+
+        let mut server_emitter = server_bus.emitter();
+        let mut owned = HashMap::<CharacterId, Vec<Vec3<f32>>>::new();
+        for (entity, uid, pos, gravestone, inventory) in
+            (&entities, &uids, &positions, &gravestones, &inventories).join()
+        {
+            let emptied =
+                inventory.populated_slots() == 0 && !trades.entity_trades.contains_key(uid);
+            if emptied || gravestone.is_expired(*time) {
+                server_emitter.emit(ServerEvent::Delete(entity));
+            } else {
+                owned.entry(gravestone.owner).or_default().push(pos.0);
+            }
+        }
+
+        let mut sent = HashMap::new();
+        for (entity, client, presence) in (&entities, &clients, &presences).join() {
+            if let PresenceKind::Character(id) = presence.kind {
+                let positions = owned.remove(&id).unwrap_or_default();
+                if markers.0.get(&(entity, id)) != Some(&positions) {
+                    client.send_fallible(ServerGeneral::Gravestones(positions.clone()));
+                }
+                sent.insert((entity, id), positions);
+            }
+        }
+        markers.0 = sent;
+    }
+}
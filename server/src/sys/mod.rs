@@ -1,5 +1,11 @@
 pub mod agent;
+pub mod animation;
+pub mod boss;
+pub mod cooking;
 pub mod entity_sync;
+pub mod farming;
+pub mod fishing;
+pub mod gravestone;
 pub mod invite_timeout;
 pub mod metrics;
 pub mod msg;
@@ -7,6 +13,7 @@ pub mod object;
 pub mod persistence;
 pub mod pets;
 pub mod sentinel;
+pub mod sleep;
 pub mod subscription;
 pub mod terrain;
 pub mod terrain_sync;
@@ -33,6 +40,13 @@ pub fn add_server_systems(dispatch_builder: &mut DispatcherBuilder) {
     dispatch::<persistence::Sys>(dispatch_builder, &[]);
     dispatch::<object::Sys>(dispatch_builder, &[]);
     dispatch::<wiring::Sys>(dispatch_builder, &[]);
+    dispatch::<sleep::Sys>(dispatch_builder, &[]);
+    dispatch::<fishing::Sys>(dispatch_builder, &[]);
+    dispatch::<farming::Sys>(dispatch_builder, &[]);
+    dispatch::<cooking::Sys>(dispatch_builder, &[]);
+    dispatch::<gravestone::Sys>(dispatch_builder, &[]);
+    dispatch::<boss::Sys>(dispatch_builder, &[]);
+    dispatch::<animation::Sys>(dispatch_builder, &[]);
 }
 
 pub fn run_sync_systems(ecs: &mut specs::World) {
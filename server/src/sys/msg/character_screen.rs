@@ -4,9 +4,11 @@ use crate::{
     client::Client,
     persistence::{character_loader::CharacterLoader, character_updater::CharacterUpdater},
     presence::Presence,
+    rules::ServerRules,
     EditableSettings,
 };
 use common::{
+    character,
     comp::{ChatType, Player, UnresolvedChatMsg},
     event::{EventBus, ServerEvent},
     uid::Uid,
@@ -29,8 +31,21 @@ impl Sys {
         presences: &ReadStorage<'_, Presence>,
         editable_settings: &ReadExpect<'_, EditableSettings>,
         alias_validator: &ReadExpect<'_, AliasValidator>,
+        server_rules: &mut WriteExpect<'_, ServerRules>,
         msg: ClientGeneral,
     ) -> Result<(), crate::error::Error> {
+        // Until the player accepted the rules they are sent in reply to anything
+        // else, the client shows them when asking for the character list
+        if !matches!(msg, ClientGeneral::AcceptRules(_)) {
+            if let Some(rules) = players
+                .get(entity)
+                .and_then(|player| server_rules.pending(&player.uuid().to_string()))
+            {
+                client.send(ServerGeneral::Rules(rules.to_owned()))?;
+                return Ok(());
+            }
+        }
+
         match msg {
             // Request spectator state
             ClientGeneral::Spectate => {
@@ -120,7 +135,14 @@ impl Sys {
                 offhand,
                 body,
             } => {
-                if let Err(error) = alias_validator.validate(&alias) {
+                if let Err(error) = character::validate(&alias, &body) {
+                    debug!(
+                        ?error,
+                        ?alias,
+                        "denied creating character with invalid name or body"
+                    );
+                    client.send(ServerGeneral::CharacterCreationError(error))?;
+                } else if let Err(error) = alias_validator.validate(&alias) {
                     debug!(?error, ?alias, "denied alias as it contained a banned word");
                     client.send(ServerGeneral::CharacterActionError(error.to_string()))?;
                 } else if let Some(player) = players.get(entity) {
@@ -145,7 +167,14 @@ impl Sys {
                 }
             },
             ClientGeneral::EditCharacter { id, alias, body } => {
-                if let Err(error) = alias_validator.validate(&alias) {
+                if let Err(error) = character::validate(&alias, &body) {
+                    debug!(
+                        ?error,
+                        ?alias,
+                        "denied editing character with invalid name or body"
+                    );
+                    client.send(ServerGeneral::CharacterCreationError(error))?;
+                } else if let Err(error) = alias_validator.validate(&alias) {
                     debug!(?error, ?alias, "denied alias as it contained a banned word");
                     client.send(ServerGeneral::CharacterActionError(error.to_string()))?;
                 } else if let Some(player) = players.get(entity) {
@@ -175,6 +204,17 @@ impl Sys {
                     );
                 }
             },
+            ClientGeneral::AcceptRules(hash) => {
+                if let Some(player) = players.get(entity) {
+                    let player_uuid = player.uuid().to_string();
+                    if server_rules.accept(&player_uuid, hash) {
+                        character_updater.accept_rules(player_uuid, hash);
+                    } else if let Some(rules) = server_rules.pending(&player_uuid) {
+                        // The rules changed since they were sent
+                        client.send(ServerGeneral::Rules(rules.to_owned()))?;
+                    }
+                }
+            },
             _ => {
                 debug!("Kicking possibly misbehaving client due to invalid character request");
                 server_emitter.emit(ServerEvent::ClientDisconnect(
@@ -202,6 +242,7 @@ impl<'a> System<'a> for Sys {
         ReadStorage<'a, Presence>,
         ReadExpect<'a, EditableSettings>,
         ReadExpect<'a, AliasValidator>,
+        WriteExpect<'a, ServerRules>,
     );
 
     const NAME: &'static str = "msg::character_screen";
@@ -221,6 +262,7 @@ impl<'a> System<'a> for Sys {
             presences,
             editable_settings,
             alias_validator,
+            mut server_rules,
         ): Self::SystemData,
     ) {
         let mut server_emitter = server_event_bus.emitter();
@@ -238,6 +280,7 @@ impl<'a> System<'a> for Sys {
                     &presences,
                     &editable_settings,
                     &alias_validator,
+                    &mut server_rules,
                     msg,
                 )
             });
@@ -3,6 +3,7 @@ use crate::TerrainPersistence;
 use crate::{client::Client, presence::Presence, Settings};
 use common::{
     comp::{
+        social::{FriendManip, GuildManip},
         Admin, CanBuild, ControlEvent, Controller, ForceUpdate, Health, Ori, Player, Pos, SkillSet,
         Vel,
     },
@@ -259,6 +260,38 @@ impl Sys {
                     .get_mut(entity)
                     .map(|mut skill_set| skill_set.unlock_skill_group(skill_group_kind));
             },
+            ClientGeneral::RespecSkills => {
+                // Needs the inventory to pay for it
+                server_emitter.emit(ServerEvent::RespecSkills(entity));
+            },
+            ClientGeneral::Sleep(bed) => {
+                server_emitter.emit(ServerEvent::Sleep { entity, bed });
+            },
+            ClientGeneral::StartFishing => {
+                server_emitter.emit(ServerEvent::StartFishing(entity));
+            },
+            ClientGeneral::ReelIn => {
+                server_emitter.emit(ServerEvent::ReelIn(entity));
+            },
+            ClientGeneral::ReelFishingLine(reeling) => {
+                server_emitter.emit(ServerEvent::ReelFishingLine { entity, reeling });
+            },
+            ClientGeneral::WaterCrop(pos) => {
+                server_emitter.emit(ServerEvent::WaterCrop { entity, pos });
+            },
+            ClientGeneral::HarvestCrop(pos) => {
+                server_emitter.emit(ServerEvent::HarvestCrop { entity, pos });
+            },
+            ClientGeneral::LoadRecipeIntoPot(pos, recipe) => {
+                server_emitter.emit(ServerEvent::LoadRecipeIntoPot {
+                    entity,
+                    pos,
+                    recipe,
+                });
+            },
+            ClientGeneral::TakeFromPot(pos) => {
+                server_emitter.emit(ServerEvent::TakeFromPot { entity, pos });
+            },
             ClientGeneral::RequestSiteInfo(id) => {
                 server_emitter.emit(ServerEvent::RequestSiteInfo { entity, id });
             },
@@ -288,6 +321,36 @@ impl Sys {
             ClientGeneral::UpdateMapMarker(update) => {
                 server_emitter.emit(ServerEvent::UpdateMapMarker { entity, update });
             },
+            ClientGeneral::CreateGuild { name, tag } => {
+                server_emitter.emit(ServerEvent::GuildManip(entity, GuildManip::Create {
+                    name,
+                    tag,
+                }));
+            },
+            ClientGeneral::InviteToGuild(uid) => {
+                server_emitter.emit(ServerEvent::GuildManip(entity, GuildManip::Invite(uid)));
+            },
+            ClientGeneral::KickFromGuild(character_id) => {
+                server_emitter.emit(ServerEvent::GuildManip(
+                    entity,
+                    GuildManip::Kick(character_id),
+                ));
+            },
+            ClientGeneral::PromoteMember(character_id, rank) => {
+                server_emitter.emit(ServerEvent::GuildManip(
+                    entity,
+                    GuildManip::Promote(character_id, rank),
+                ));
+            },
+            ClientGeneral::LeaveGuild => {
+                server_emitter.emit(ServerEvent::GuildManip(entity, GuildManip::Leave));
+            },
+            ClientGeneral::FriendAdd(name) => {
+                server_emitter.emit(ServerEvent::FriendManip(entity, FriendManip::Add(name)));
+            },
+            ClientGeneral::FriendRemove(name) => {
+                server_emitter.emit(ServerEvent::FriendManip(entity, FriendManip::Remove(name)));
+            },
             ClientGeneral::RequestCharacterList
             | ClientGeneral::CreateCharacter { .. }
             | ClientGeneral::EditCharacter { .. }
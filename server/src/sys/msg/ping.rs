@@ -4,15 +4,21 @@ use common::{
     resources::Time,
 };
 use common_ecs::{Job, Origin, Phase, System};
-use common_net::msg::PingMsg;
+use common_net::msg::{ConnectionStats, PingMsg};
 use specs::{Entities, Join, Read, ReadStorage};
-use tracing::{debug, info};
+use tracing::{debug, info, trace};
 
 impl Sys {
     fn handle_ping_msg(client: &Client, msg: PingMsg) -> Result<(), crate::error::Error> {
         match msg {
-            PingMsg::Ping => client.send(PingMsg::Pong)?,
-            PingMsg::Pong => {},
+            PingMsg::Ping { sent_at_ns } => client.send(client.ping_clock.pong(sent_at_ns))?,
+            PingMsg::Pong { sent_at_ns, .. } => {
+                let rtt = client.ping_clock.rtt(sent_at_ns);
+                // The clocks of both sides aren't synchronized, so half of the round trip
+                // is the best guess of the latency in each direction
+                trace!(latency = ?rtt / 2, "pong received");
+                ConnectionStats::update(&mut client.connection_stats.lock().unwrap(), rtt);
+            },
         }
         Ok(())
     }
@@ -67,7 +73,7 @@ impl<'a> System<'a> for Sys {
                         ));
                     } else if time.0 - last_ping > settings.client_timeout.as_secs() as f64 * 0.5 {
                         // Try pinging the client if the timeout is nearing.
-                        client.send_fallible(PingMsg::Ping);
+                        client.send_fallible(client.ping_clock.ping());
                     }
                 },
             }
@@ -1,5 +1,6 @@
 use crate::{
     client::Client,
+    friends::FriendManager,
     login_provider::{LoginProvider, PendingLogin},
     metrics::PlayerMetrics,
     EditableSettings, Settings,
@@ -40,6 +41,7 @@ pub struct ReadData<'a> {
     player_metrics: ReadExpect<'a, PlayerMetrics>,
     settings: ReadExpect<'a, Settings>,
     editable_settings: ReadExpect<'a, EditableSettings>,
+    friends: ReadExpect<'a, FriendManager>,
     _healths: ReadStorage<'a, Health>, // used by plugin feature
     _plugin_mgr: ReadPlugin<'a>,       // used by plugin feature
     _uid_allocator: Read<'a, UidAllocator>, // used by plugin feature
@@ -158,9 +160,11 @@ impl<'a> System<'a> for Sys {
                         old_entity,
                         common::comp::DisconnectReason::NewerLogin,
                     ));
-                    let _ = old_client.send(ServerGeneral::Disconnect(DisconnectReason::Kicked(
-                        String::from("You have logged in from another location."),
-                    )));
+                    let _ = old_client.send(ServerGeneral::Disconnect(DisconnectReason::Kicked {
+                        reason: String::from("You have logged in from another location."),
+                        // Getting back in would kick the new login
+                        reconnect_allowed: false,
+                    }));
                     // We can't login the new client right now as the
                     // removal of the old client and player occurs later in
                     // the tick, so we instead setup the new login to be
@@ -229,7 +233,21 @@ impl<'a> System<'a> for Sys {
             let player_info = read_data.uids.get(entity).zip(players.get(entity));
             player_info.map(|(u, p)| (entity, u, p))
         };
+        let online = if new_players.is_empty() {
+            HashMap::new()
+        } else {
+            (&read_data.uids, &players)
+                .join()
+                .map(|(uid, player)| (player.uuid().to_string(), *uid))
+                .collect()
+        };
+        let mut friend_statuses = Vec::new();
         for (entity, uid, player) in new_players.into_iter().filter_map(player_info) {
+            friend_statuses.extend(read_data.friends.login(
+                (player.uuid().to_string().as_str(), player.alias.as_str()),
+                *uid,
+                &online,
+            ));
             let mut lazy_msg = None;
             for (_, client) in (&players, &read_data.clients).join() {
                 if lazy_msg.is_none() {
@@ -245,5 +263,15 @@ impl<'a> System<'a> for Sys {
                 lazy_msg.as_ref().map(|msg| client.send_prepared(msg));
             }
         }
+
+        // Tell the new players about their friends and their friends about them
+        for status in friend_statuses {
+            if let Some((_, client)) = (&read_data.uids, &read_data.clients)
+                .join()
+                .find(|(uid, _)| **uid == status.recipient)
+            {
+                client.send_fallible(status.into_msg());
+            }
+        }
     }
 }
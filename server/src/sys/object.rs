@@ -1,12 +1,14 @@
+use crate::fishing::FishingLine;
 use common::{
     comp::{Object, PhysicsState, Pos, Vel},
     effect::Effect,
     event::{EventBus, ServerEvent},
     resources::DeltaTime,
+    uid::UidAllocator,
     Damage, DamageKind, DamageSource, Explosion, RadiusEffect,
 };
 use common_ecs::{Job, Origin, Phase, System};
-use specs::{Entities, Join, Read, ReadStorage, WriteStorage};
+use specs::{saveload::MarkerAllocator, Entities, Join, Read, ReadStorage, WriteStorage};
 
 /// This system is responsible for handling misc object behaviours
 #[derive(Default)]
@@ -16,9 +18,11 @@ impl<'a> System<'a> for Sys {
         Entities<'a>,
         Read<'a, DeltaTime>,
         Read<'a, EventBus<ServerEvent>>,
+        Read<'a, UidAllocator>,
         ReadStorage<'a, Pos>,
         ReadStorage<'a, Vel>,
         ReadStorage<'a, PhysicsState>,
+        ReadStorage<'a, FishingLine>,
         WriteStorage<'a, Object>,
     );
 
@@ -28,7 +32,17 @@ impl<'a> System<'a> for Sys {
 
     fn run(
         _job: &mut Job<Self>,
-        (entities, _dt, server_bus, positions, velocities, physics_states, mut objects): Self::SystemData,
+        (
+            entities,
+            _dt,
+            server_bus,
+            uid_allocator,
+            positions,
+            velocities,
+            physics_states,
+            fishing_lines,
+            mut objects,
+        ): Self::SystemData,
     ) {
         let mut server_emitter = server_bus.emitter();
 
@@ -159,6 +173,15 @@ impl<'a> System<'a> for Sys {
                         });
                     }
                 },
+                Object::Bobber { owner } => {
+                    let line_cast = uid_allocator
+                        .retrieve_entity_internal(owner.0)
+                        .and_then(|owner| fishing_lines.get(owner))
+                        .map_or(false, |line| line.bobber == entity);
+                    if !line_cast {
+                        server_emitter.emit(ServerEvent::Delete(entity));
+                    }
+                },
             }
         }
     }
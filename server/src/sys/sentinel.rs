@@ -2,6 +2,7 @@
 use common::{
     comp::{
         item::{tool::AbilityMap, MaterialStatManifest},
+        skillset::{SkillSet, SkillSetDiff},
         Ori, Pos, Vel,
     },
     uid::Uid,
@@ -57,12 +58,14 @@ macro_rules! trackers {
             /// entity is within the area synced to the client).
             ///
             /// Note: This is only for components that are synced to the client for all entities.
+            /// Set `spectator` unless the package is for the client controlling the entity.
             pub fn create_entity_package(
                 &self,
                 entity: EcsEntity,
                 pos: Option<Pos>,
                 vel: Option<Vel>,
                 ori: Option<Ori>,
+                spectator: bool,
             ) -> Option<EntityPackage<EcsCompPacket>> {
                 let uid = self.uid.get(entity).copied()?.0;
                 let mut comps = Vec::new();
@@ -85,7 +88,13 @@ macro_rules! trackers {
                             // NetClone trait?
                             //
                             //.map(|item| item.duplicate(&self.ability_map, &self.msm))
-                            .cloned()
+                            .map(|c| {
+                                if spectator {
+                                    <$component_type as NetSync>::clone_for_spectator(c)
+                                } else {
+                                    c.clone()
+                                }
+                            })
                             .map(|c| comps.push(c.into()));
                     }
                 )*
@@ -116,6 +125,10 @@ macro_rules! trackers {
                         if let Some(comp) = self.$component_name.get(new_entity).cloned() {
                             comp_sync_package.comp_inserted(new_uid, comp);
                         }
+                    } else if <$component_type as NetSync>::SPECTATOR_VIEW {
+                        if let Some(comp) = self.$component_name.get(new_entity).cloned() {
+                            comp_sync_package.comp_modified(new_uid, comp);
+                        }
                     }
                 )*
 
@@ -208,6 +221,7 @@ macro_rules! trackers {
                             &self.$component_name,
                             &comps.$component_name,
                             filter,
+                            <$component_type as NetSync>::clone_for_spectator,
                         );
                     }
                 )*
@@ -216,7 +230,9 @@ macro_rules! trackers {
             }
 
 
-            /// Create sync package for components that are only synced for the client's entity.
+            /// Create sync package for components that are only synced for the client's entity,
+            /// as well as the full version of components with a spectator view, unless only
+            /// the difference to it is sent.
             pub fn create_sync_from_client_package(
                 &self,
                 comps: &TrackedStorages,
@@ -238,7 +254,9 @@ macro_rules! trackers {
                     if matches!(
                         <$component_type as NetSync>::SYNC_FROM,
                         SyncFrom::ClientEntity,
-                    ) {
+                    ) || (<$component_type as NetSync>::SPECTATOR_VIEW
+                        && !<$component_type as NetSync>::SPECTATOR_DELTA)
+                    {
                         comp_sync_package.add_component_update(
                             &self.$component_name,
                             &comps.$component_name,
@@ -262,6 +280,25 @@ use common_net::synced_components::*;
 // of components. This will declare the types defined in the macro above.
 common_net::synced_components!(trackers);
 
+impl UpdateTrackers {
+    /// What the skill set of the client's entity has on top of its spectator
+    /// view, if it was synced this tick. The client gets the spectator view
+    /// along with everyone else.
+    pub fn create_skill_set_delta(
+        &self,
+        comps: &TrackedStorages,
+        entity: EcsEntity,
+    ) -> Option<SkillSetDiff> {
+        let id = entity.id();
+        if !self.skill_set.inserted().contains(id) && !self.skill_set.modified().contains(id) {
+            return None;
+        }
+        let skill_set = comps.skill_set.get(entity)?;
+        let diff = SkillSet::diff(&skill_set.clone_for_spectator(), skill_set);
+        (!diff.is_empty()).then(|| diff)
+    }
+}
+
 /// Deleted entities grouped by region
 pub struct DeletedEntities {
     map: HashMap<Vec2<i32>, Vec<u64>>,
@@ -0,0 +1,241 @@
+use crate::{presence::Presence, settings::Settings};
+use common::{
+    comp::{CharacterState, Health},
+    resources::{DeltaTime, TimeOfDay},
+    time::DayPeriod,
+};
+use common_ecs::{Job, Origin, Phase, System};
+use common_net::msg::PresenceKind;
+use hashbrown::HashMap;
+use specs::{Entities, Entity as EcsEntity, Join, Read, ReadStorage, Write, WriteExpect};
+
+const DAY: f64 = 24.0 * 3600.0;
+/// Time of day the night is skipped to
+const MORNING: f64 = 6.0 * 3600.0;
+/// Real time a night skip takes, in seconds
+const FAST_FORWARD_DURATION: f64 = 4.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct FastForward {
+    to: f64,
+    /// In seconds of the time of day per second
+    rate: f64,
+}
+
+/// Players that lie in a bed, the night is skipped once enough of the online
+/// players sleep.
+#[derive(Debug, Default)]
+pub struct Sleepers {
+    /// Health of each sleeper when they lay down, losing any of it wakes them
+    sleeping: HashMap<EcsEntity, f32>,
+    fast_forward: Option<FastForward>,
+}
+
+impl Sleepers {
+    pub fn lie_down(&mut self, entity: EcsEntity, health: f32) {
+        self.sleeping.insert(entity, health);
+    }
+
+    /// Wakes up a sleeper, which stops the night skip for everyone
+    pub fn wake(&mut self, entity: EcsEntity) {
+        if self.sleeping.remove(&entity).is_some() {
+            self.fast_forward = None;
+        }
+    }
+
+    pub fn is_sleeping(&self, entity: EcsEntity) -> bool { self.sleeping.contains_key(&entity) }
+
+    pub fn is_fast_forwarding(&self) -> bool { self.fast_forward.is_some() }
+
+    /// Starts the night skip once at least `fraction` of the `online` players
+    /// sleep, and stops it when players joining or waking up make them fall
+    /// short of that. Sleepers that went offline are forgotten.
+    pub fn update(&mut self, online: &[EcsEntity], fraction: f32, time_of_day: f64) {
+        self.sleeping.retain(|entity, _| online.contains(entity));
+
+        let needed = ((online.len() as f32 * fraction.clamp(0.0, 1.0)).ceil() as usize).max(1);
+        if self.sleeping.len() < needed {
+            self.fast_forward = None;
+        } else if self.fast_forward.is_none() && DayPeriod::from(time_of_day).is_dark() {
+            let tod = time_of_day.rem_euclid(DAY);
+            let to = time_of_day - tod
+                + if tod < MORNING {
+                    MORNING
+                } else {
+                    DAY + MORNING
+                };
+            self.fast_forward = Some(FastForward {
+                to,
+                rate: (to - time_of_day) / FAST_FORWARD_DURATION,
+            });
+        }
+    }
+
+    /// Advances the time of day during a night skip, wakes everyone up once
+    /// it's morning
+    pub fn advance(&mut self, time_of_day: &mut f64, dt: f64) {
+        if let Some(FastForward { to, rate }) = self.fast_forward {
+            *time_of_day = (*time_of_day + rate * dt).min(to);
+            if *time_of_day >= to {
+                self.fast_forward = None;
+                self.sleeping.clear();
+            }
+        }
+    }
+}
+
+/// This system wakes up sleepers that got up or were hurt and skips the
+/// night once enough players sleep
+#[derive(Default)]
+pub struct Sys;
+impl<'a> System<'a> for Sys {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, DeltaTime>,
+        Read<'a, Settings>,
+        Write<'a, Sleepers>,
+        WriteExpect<'a, TimeOfDay>,
+        ReadStorage<'a, Presence>,
+        ReadStorage<'a, CharacterState>,
+        ReadStorage<'a, Health>,
+    );
+
+    const NAME: &'static str = "sleep";
+    const ORIGIN: Origin = Origin::Server;
+    const PHASE: Phase = Phase::Create;
+
+    fn run(
+        _job: &mut Job<Self>,
+        (
+            entities,
+            dt,
+            settings,
+            mut sleepers,
+            mut time_of_day,
+            presences,
+            character_states,
+            healths,
+        ): Self::SystemData,
+    ) {
+        let woken = sleepers
+            .sleeping
+            .iter()
+            .filter(|(entity, health)| {
+                !matches!(character_states.get(**entity), Some(CharacterState::Sit))
+                    || healths
+                        .get(**entity)
+                        .map_or(true, |h| h.current() < **health)
+            })
+            .map(|(entity, _)| *entity)
+            .collect::<Vec<_>>();
+        for entity in woken {
+            sleepers.wake(entity);
+        }
+
+        let online = (&entities, &presences)
+            .join()
+            .filter(|(_, presence)| matches!(presence.kind, PresenceKind::Character(_)))
+            .map(|(entity, _)| entity)
+            .collect::<Vec<_>>();
+        sleepers.update(&online, settings.sleep_fraction, time_of_day.0);
+        sleepers.advance(&mut time_of_day.0, dt.0 as f64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use specs::{Builder, World, WorldExt};
+
+    const MIDNIGHT: f64 = 10.0 * DAY;
+
+    fn players(n: usize) -> Vec<EcsEntity> {
+        let mut world = World::new();
+        (0..n).map(|_| world.create_entity().build()).collect()
+    }
+
+    #[test]
+    fn singleplayer_skips_the_night() {
+        let player = players(1);
+        let mut sleepers = Sleepers::default();
+        sleepers.lie_down(player[0], 100.0);
+        sleepers.update(&player, 1.0, MIDNIGHT);
+        assert!(sleepers.is_fast_forwarding());
+
+        let mut tod = MIDNIGHT;
+        for _ in 0..(FAST_FORWARD_DURATION * 10.0) as usize + 1 {
+            sleepers.advance(&mut tod, 0.1);
+        }
+        assert_eq!(tod, MIDNIGHT + MORNING);
+        assert!(!sleepers.is_fast_forwarding());
+        assert!(!sleepers.is_sleeping(player[0]));
+    }
+
+    #[test]
+    fn no_skip_during_the_day() {
+        let player = players(1);
+        let mut sleepers = Sleepers::default();
+        sleepers.lie_down(player[0], 100.0);
+        sleepers.update(&player, 1.0, MIDNIGHT + 12.0 * 3600.0);
+        assert!(!sleepers.is_fast_forwarding());
+    }
+
+    #[test]
+    fn evening_skips_to_the_next_morning() {
+        let player = players(1);
+        let mut sleepers = Sleepers::default();
+        sleepers.lie_down(player[0], 100.0);
+        let tod = MIDNIGHT + 22.0 * 3600.0;
+        sleepers.update(&player, 1.0, tod);
+        assert_eq!(
+            sleepers.fast_forward.map(|ff| ff.to),
+            Some(MIDNIGHT + DAY + MORNING)
+        );
+    }
+
+    #[test]
+    fn joins_and_leaves_mid_sleep() {
+        let all = players(3);
+        let mut sleepers = Sleepers::default();
+        sleepers.lie_down(all[0], 100.0);
+        sleepers.lie_down(all[1], 100.0);
+        sleepers.update(&all[..2], 1.0, MIDNIGHT);
+        assert!(sleepers.is_fast_forwarding());
+
+        // A player that is awake joins
+        sleepers.update(&all, 1.0, MIDNIGHT);
+        assert!(!sleepers.is_fast_forwarding());
+        // Two of three are enough with a lower threshold
+        sleepers.update(&all, 0.5, MIDNIGHT);
+        assert!(sleepers.is_fast_forwarding());
+
+        // A sleeper leaves, the awake player keeps the night going
+        sleepers.update(&all[1..], 1.0, MIDNIGHT);
+        assert!(!sleepers.is_fast_forwarding());
+        assert!(!sleepers.is_sleeping(all[0]));
+        // The awake player leaves as well
+        sleepers.update(&all[1..2], 1.0, MIDNIGHT);
+        assert!(sleepers.is_fast_forwarding());
+    }
+
+    #[test]
+    fn waking_cancels_the_skip() {
+        let all = players(2);
+        let mut sleepers = Sleepers::default();
+        sleepers.lie_down(all[0], 100.0);
+        sleepers.lie_down(all[1], 100.0);
+        sleepers.update(&all, 1.0, MIDNIGHT);
+
+        let mut tod = MIDNIGHT;
+        sleepers.advance(&mut tod, 1.0);
+        assert!(tod > MIDNIGHT);
+        sleepers.wake(all[1]);
+        assert!(!sleepers.is_fast_forwarding());
+
+        let before = tod;
+        sleepers.advance(&mut tod, 1.0);
+        assert_eq!(tod, before);
+        sleepers.update(&all, 1.0, tod);
+        assert!(!sleepers.is_fast_forwarding());
+    }
+}
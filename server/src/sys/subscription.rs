@@ -189,6 +189,7 @@ impl<'a> System<'a> for Sys {
                                         Some(*pos),
                                         vel.copied(),
                                         ori.copied(),
+                                        true,
                                     )
                                 })
                                 .for_each(|msg| {
@@ -240,6 +241,7 @@ pub fn initialize_region_subscription(world: &World, entity: specs::Entity) {
                         Some(*pos),
                         vel.copied(),
                         ori.copied(),
+                        true,
                     )
                 )
                 .for_each(|msg| {
@@ -255,6 +257,7 @@ pub fn initialize_region_subscription(world: &World, entity: specs::Entity) {
             Some(*client_pos),
             world.read_storage().get(entity).copied(),
             world.read_storage().get(entity).copied(),
+            false,
         ) {
             client.send_fallible(ServerGeneral::CreateEntity(pkg));
         }
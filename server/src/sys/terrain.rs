@@ -175,14 +175,14 @@ impl<'a> System<'a> for Sys {
         // don't create duplicate work for chunks that just finished but are not
         // yet added to the terrain.
         chunk_requests.drain(..).for_each(|request| {
-            chunk_generator.generate_chunk(
-                Some(request.entity),
-                request.key,
-                &slow_jobs,
-                Arc::clone(&world),
-                index.clone(),
-                (*time_of_day, calendar.clone()),
-            )
+            // Chunks closer to the requester are generated first
+            let dist_sqr = positions.get(request.entity).map_or(0, |pos| {
+                let chunk_pos = terrain.pos_key(pos.0.map(|e| e as i32));
+                (request.key - chunk_pos)
+                    .map(|e| e.unsigned_abs())
+                    .magnitude_squared()
+            });
+            chunk_generator.generate_chunk(Some(request.entity), request.key, dist_sqr)
         });
 
         // Fetch any generated `TerrainChunk`s and insert them into the terrain.
@@ -285,6 +285,9 @@ impl<'a> System<'a> for Sys {
             }
         }
 
+        // Workers freed by the chunks above pick up the next ones
+        chunk_generator.dispatch(&slow_jobs, &world, &index, (*time_of_day, calendar.clone()));
+
         let mut repositioned = Vec::new();
         for (entity, pos, _) in (&entities, &mut positions, &reposition_on_load).join() {
             // If an entity is marked as needing repositioning once the chunk loads (e.g.
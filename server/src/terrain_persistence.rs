@@ -10,13 +10,18 @@ use std::{
     fs::File,
     io::{self, Read as _, Write as _},
     path::PathBuf,
+    time::{Duration, Instant},
 };
 use tracing::{debug, error, info, warn};
 use vek::*;
 
+/// How often modified chunks are written back while they stay loaded
+const AUTOSAVE_INTERVAL: Duration = Duration::from_secs(60);
+
 pub struct TerrainPersistence {
     path: PathBuf,
     chunks: HashMap<Vec2<i32>, Chunk>,
+    last_autosave: Instant,
 }
 
 impl TerrainPersistence {
@@ -39,6 +44,7 @@ impl TerrainPersistence {
         Self {
             path,
             chunks: HashMap::default(),
+            last_autosave: Instant::now(),
         }
     }
 
@@ -67,13 +73,39 @@ impl TerrainPersistence {
         }
     }
 
-    /// Maintain terrain persistence (writing changes changes back to
-    /// filesystem, etc.)
+    /// Maintain terrain persistence (writing changes back to the filesystem,
+    /// etc.)
     pub fn maintain(&mut self) {
-        // Currently, this does nothing because filesystem writeback occurs on
-        // chunk unload However, this is not a particularly reliable
-        // mechanism (it doesn't survive power loss, say). Later, a more
-        // reliable strategy should be implemented here.
+        // Chunks are written back when they get unloaded, but chunks that stay
+        // loaded for a long time (e.g: around a player's base) would lose their
+        // changes on a crash or power loss. Write those out periodically too.
+        if self.last_autosave.elapsed() >= AUTOSAVE_INTERVAL {
+            self.last_autosave = Instant::now();
+            self.autosave();
+        }
+    }
+
+    /// Write all chunks with unsaved changes to the filesystem, keeping them
+    /// loaded.
+    pub fn autosave(&mut self) {
+        let dirty = self
+            .chunks
+            .iter()
+            .filter(|(_, chunk)| chunk.dirty)
+            .map(|(key, _)| *key)
+            .collect::<Vec<_>>();
+        if !dirty.is_empty() {
+            debug!("Autosaving {} modified terrain chunks", dirty.len());
+        }
+        for key in dirty {
+            if let Some(chunk) = self.chunks.get(&key) {
+                if self.write_chunk(key, chunk) {
+                    if let Some(chunk) = self.chunks.get_mut(&key) {
+                        chunk.dirty = false;
+                    }
+                }
+            }
+        }
     }
 
     fn path_for(&self, key: Vec2<i32>) -> PathBuf {
@@ -129,26 +161,33 @@ impl TerrainPersistence {
         })
     }
 
+    /// Returns whether the chunk was written successfully
+    fn write_chunk(&self, key: Vec2<i32>, chunk: &Chunk) -> bool {
+        let bytes = match bincode::serialize::<version::Current>(&chunk.prepare_raw()) {
+            Err(err) => {
+                error!("Failed to serialize chunk data: {:?}", err);
+                return false;
+            },
+            Ok(bytes) => bytes,
+        };
+
+        let atomic_file = AtomicFile::new(self.path_for(key), OverwriteBehavior::AllowOverwrite);
+        if let Err(err) = atomic_file.write(|file| file.write_all(&bytes)) {
+            error!("Failed to write chunk data to file: {:?}", err);
+            return false;
+        }
+        true
+    }
+
     pub fn unload_chunk(&mut self, key: Vec2<i32>) {
         if let Some(chunk) = self.chunks.remove(&key) {
-            // No need to write if no blocks have ever been written
-            if chunk.blocks.is_empty() {
+            // No need to write if nothing changed since the chunk was loaded or
+            // last autosaved
+            if !chunk.dirty {
                 return;
             }
 
-            let bytes = match bincode::serialize::<version::Current>(&chunk.prepare_raw()) {
-                Err(err) => {
-                    error!("Failed to serialize chunk data: {:?}", err);
-                    return;
-                },
-                Ok(bytes) => bytes,
-            };
-
-            let atomic_file =
-                AtomicFile::new(self.path_for(key), OverwriteBehavior::AllowOverwrite);
-            if let Err(err) = atomic_file.write(|file| file.write_all(&bytes)) {
-                error!("Failed to write chunk data to file: {:?}", err);
-            }
+            self.write_chunk(key, &chunk);
         }
     }
 
@@ -162,9 +201,11 @@ impl TerrainPersistence {
         let key = pos
             .xy()
             .map2(TerrainChunk::RECT_SIZE, |e, sz| e.div_euclid(sz as i32));
-        self.load_chunk(key)
+        let chunk = self.load_chunk(key);
+        chunk
             .blocks
             .insert(pos - key * TerrainChunk::RECT_SIZE.map(|e| e as i32), block);
+        chunk.dirty = true;
     }
 }
 
@@ -175,6 +216,9 @@ impl Drop for TerrainPersistence {
 #[derive(Default, Serialize, Deserialize)]
 pub struct Chunk {
     blocks: HashMap<Vec3<i32>, Block>,
+    /// Whether there are changes that haven't been written to the filesystem
+    #[serde(skip)]
+    dirty: bool,
 }
 
 impl Chunk {
@@ -182,7 +226,7 @@ impl Chunk {
         version::try_load(reader)
     }
 
-    fn prepare_raw(self) -> version::Current { self.into() }
+    fn prepare_raw(&self) -> version::Current { self.into() }
 
     fn blocks(&self) -> impl Iterator<Item = (Vec3<i32>, Block)> + '_ {
         self.blocks.iter().map(|(k, b)| (*k, *b))
@@ -234,13 +278,13 @@ mod version {
 
     // Convert back to current
 
-    impl From<Chunk> for Current {
-        fn from(chunk: Chunk) -> Self {
+    impl From<&Chunk> for Current {
+        fn from(chunk: &Chunk) -> Self {
             Self {
                 version: version_magic(3),
                 blocks: chunk
                     .blocks
-                    .into_iter()
+                    .iter()
                     .map(|(pos, b)| (pos.x as u8, pos.y as u8, pos.z as i16, b.to_u32()))
                     .collect(),
             }
@@ -268,6 +312,7 @@ mod version {
                         )
                     })
                     .collect(),
+                dirty: false,
             }
         }
     }
@@ -288,6 +333,7 @@ mod version {
                     .into_iter()
                     .map(|(x, y, z, b)| (Vec3::new(x as i32, y as i32, z as i32), b))
                     .collect(),
+                dirty: false,
             }
         }
     }
@@ -299,12 +345,17 @@ mod version {
     }
 
     impl From<V1> for Chunk {
-        fn from(v1: V1) -> Self { Self { blocks: v1.blocks } }
+        fn from(v1: V1) -> Self {
+            Self {
+                blocks: v1.blocks,
+                dirty: false,
+            }
+        }
     }
 
     // Utility things
 
-    fn version_magic(n: u16) -> u64 { (n as u64) | (0x3352ACEEA789 << 16) }
+    pub fn version_magic(n: u16) -> u64 { (n as u64) | (0x3352ACEEA789 << 16) }
 
     fn version<'de, D: serde::Deserializer<'de>, const V: u16>(de: D) -> Result<u64, D::Error> {
         u64::deserialize(de).and_then(|x| {
@@ -342,3 +393,75 @@ mod version {
             })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::terrain::BlockKind;
+
+    fn persistence(name: &str) -> TerrainPersistence {
+        let data_dir =
+            std::env::temp_dir().join(format!("veloren-terrain-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&data_dir);
+        TerrainPersistence::new(data_dir)
+    }
+
+    fn rock() -> Block { Block::new(BlockKind::Rock, Rgb::new(10, 20, 30)) }
+
+    #[test]
+    fn edit_survives_eviction() {
+        let mut persistence = persistence("eviction");
+        let key = Vec2::new(-1, 2);
+        let pos = (key * TerrainChunk::RECT_SIZE.map(|e| e as i32)).with_z(0) + Vec3::new(3, 4, 50);
+        persistence.set_block(pos, rock());
+        persistence.unload_chunk(key);
+        assert!(!persistence.chunks.contains_key(&key));
+        assert!(persistence.path_for(key).exists());
+
+        let chunk = persistence.load_chunk(key);
+        assert_eq!(chunk.blocks().collect::<Vec<_>>(), vec![(
+            Vec3::new(3, 4, 50),
+            rock()
+        )]);
+        assert!(!chunk.dirty);
+    }
+
+    #[test]
+    fn unmodified_chunks_are_not_written() {
+        let mut persistence = persistence("unmodified");
+        let key = Vec2::new(5, 5);
+        persistence.load_chunk(key);
+        persistence.unload_chunk(key);
+        assert!(!persistence.path_for(key).exists());
+    }
+
+    #[test]
+    fn autosave_keeps_chunks_loaded() {
+        let mut persistence = persistence("autosave");
+        persistence.set_block(Vec3::new(1, 1, 1), rock());
+        persistence.autosave();
+        assert!(persistence.path_for(Vec2::zero()).exists());
+        assert!(!persistence.chunks[&Vec2::zero()].dirty);
+    }
+
+    #[test]
+    fn old_formats_are_migrated() {
+        let mut persistence = persistence("migration");
+        let key = Vec2::zero();
+        let v2 = (version::version_magic(2), vec![(1u8, 2u8, 3i16, rock())]);
+        std::fs::write(persistence.path_for(key), bincode::serialize(&v2).unwrap()).unwrap();
+
+        persistence.set_block(Vec3::new(4, 5, 6), rock());
+        persistence.unload_chunk(key);
+
+        // The chunk is written back in the current format
+        let bytes = std::fs::read(persistence.path_for(key)).unwrap();
+        let current = bincode::deserialize::<version::Current>(&bytes).unwrap();
+        let mut blocks = Chunk::from(current).blocks().collect::<Vec<_>>();
+        blocks.sort_by_key(|(pos, _)| pos.into_array());
+        assert_eq!(blocks, vec![
+            (Vec3::new(1, 2, 3), rock()),
+            (Vec3::new(4, 5, 6), rock())
+        ]);
+    }
+}
@@ -31,7 +31,8 @@ impl Animation for ClimbAnimation {
         let mut next = (*skeleton).clone();
         let lateral = Vec2::<f32>::from(velocity).magnitude();
         let speed = velocity.z;
-        *rate = speed;
+        // Climbing down plays at the same pace as climbing up
+        *rate = speed.abs();
         let constant: f32 = 1.0;
         let smooth = (anim_time * constant * 1.5).sin();
         let smootha = (anim_time * constant * 1.5 + PI / 2.0).sin();
@@ -0,0 +1,141 @@
+use super::{
+    super::{vek::*, Animation},
+    CharacterSkeleton, SkeletonAttr,
+};
+use common::comp::item::{Hands, ToolKind};
+use core::f32::consts::PI;
+
+/// Falling over backwards, then lying still
+pub struct DeathAnimation;
+
+impl Animation for DeathAnimation {
+    type Dependency<'a> = (
+        Option<ToolKind>,
+        Option<ToolKind>,
+        (Option<Hands>, Option<Hands>),
+        f32,
+    );
+    type Skeleton = CharacterSkeleton;
+
+    #[cfg(feature = "use-dyn-lib")]
+    const UPDATE_FN: &'static [u8] = b"character_death\0";
+
+    #[cfg_attr(feature = "be-dyn-lib", export_name = "character_death")]
+    fn update_skeleton_inner<'a>(
+        skeleton: &Self::Skeleton,
+        (active_tool_kind, second_tool_kind, hands, fall_duration): Self::Dependency<'a>,
+        anim_time: f32,
+        rate: &mut f32,
+        s_a: &SkeletonAttr,
+    ) -> Self::Skeleton {
+        let mut next = (*skeleton).clone();
+        *rate = 1.0;
+
+        // Speeds up like a real fall and stays down once it hit the ground
+        let fall = (anim_time / fall_duration.max(0.01)).min(1.0).powi(2);
+        let slump = fall.sqrt();
+
+        next.head.position = Vec3::new(0.0, s_a.head.0, s_a.head.1);
+        next.head.orientation = Quaternion::rotation_x(-0.4 * slump);
+        next.head.scale = Vec3::one() * s_a.head_scale;
+
+        next.chest.position = Vec3::new(0.0, s_a.chest.0, s_a.chest.1);
+        next.chest.orientation = Quaternion::rotation_x(-0.1 * slump);
+        next.chest.scale = Vec3::one() * 1.01;
+
+        next.belt.position = Vec3::new(0.0, s_a.belt.0, s_a.belt.1);
+        next.belt.orientation = Quaternion::rotation_x(0.1 * slump);
+
+        next.back.position = Vec3::new(0.0, s_a.back.0, s_a.back.1);
+        next.back.scale = Vec3::one() * 1.02;
+
+        next.shorts.position = Vec3::new(0.0, s_a.shorts.0, s_a.shorts.1);
+        next.shorts.orientation = Quaternion::rotation_x(0.2 * slump);
+
+        // The arms fly out to the sides
+        next.hand_l.position = Vec3::new(
+            -s_a.hand.0 - 4.0 * slump,
+            s_a.hand.1,
+            s_a.hand.2 + 3.0 * slump,
+        );
+        next.hand_l.orientation = Quaternion::rotation_y(-0.8 * slump);
+        next.hand_l.scale = Vec3::one() * 1.04;
+
+        next.hand_r.position = Vec3::new(
+            s_a.hand.0 + 4.0 * slump,
+            s_a.hand.1,
+            s_a.hand.2 + 3.0 * slump,
+        );
+        next.hand_r.orientation = Quaternion::rotation_y(0.8 * slump);
+        next.hand_r.scale = Vec3::one() * 1.04;
+
+        next.foot_l.position = Vec3::new(-s_a.foot.0, s_a.foot.1 + 2.0 * slump, s_a.foot.2);
+        next.foot_l.orientation = Quaternion::rotation_x(0.3 * slump);
+
+        next.foot_r.position = Vec3::new(s_a.foot.0, s_a.foot.1 - 1.0 * slump, s_a.foot.2);
+        next.foot_r.orientation = Quaternion::rotation_x(0.1 * slump);
+
+        next.shoulder_l.position = Vec3::new(-s_a.shoulder.0, s_a.shoulder.1, s_a.shoulder.2);
+        next.shoulder_l.scale = Vec3::one() * 1.1;
+
+        next.shoulder_r.position = Vec3::new(s_a.shoulder.0, s_a.shoulder.1, s_a.shoulder.2);
+        next.shoulder_r.scale = Vec3::one() * 1.1;
+
+        next.glider.position = Vec3::new(0.0, 0.0, 10.0);
+        next.glider.scale = Vec3::one() * 0.0;
+
+        let main_tool = if let (None, Some(Hands::Two)) = hands {
+            second_tool_kind
+        } else {
+            active_tool_kind
+        };
+
+        // Weapons stay stowed on the back
+        match main_tool {
+            Some(ToolKind::Dagger) => {
+                next.main.position = Vec3::new(5.0, 1.0, 2.0);
+                next.main.orientation =
+                    Quaternion::rotation_x(-1.35 * PI) * Quaternion::rotation_z(2.0 * PI);
+            },
+            Some(ToolKind::Shield) => {
+                next.main.position = Vec3::new(-0.0, -5.0, 3.0);
+                next.main.orientation =
+                    Quaternion::rotation_y(0.25 * PI) * Quaternion::rotation_z(-1.5 * PI);
+            },
+            _ => {
+                next.main.position = Vec3::new(-7.0, -5.0, 15.0);
+                next.main.orientation =
+                    Quaternion::rotation_y(2.5) * Quaternion::rotation_z(PI / 2.0);
+            },
+        }
+
+        match second_tool_kind {
+            Some(ToolKind::Dagger) => {
+                next.second.position = Vec3::new(-5.0, 1.0, 2.0);
+                next.second.orientation =
+                    Quaternion::rotation_x(-1.35 * PI) * Quaternion::rotation_z(-2.0 * PI);
+            },
+            Some(ToolKind::Shield) => {
+                next.second.position = Vec3::new(0.0, -4.0, 3.0);
+                next.second.orientation =
+                    Quaternion::rotation_y(-0.25 * PI) * Quaternion::rotation_z(1.5 * PI);
+            },
+            _ => {
+                next.second.position = Vec3::new(-7.0, -5.0, 15.0);
+                next.second.orientation =
+                    Quaternion::rotation_y(2.5) * Quaternion::rotation_z(PI / 2.0);
+            },
+        }
+
+        next.lantern.position = Vec3::new(s_a.lantern.0, s_a.lantern.1, s_a.lantern.2);
+        next.lantern.scale = Vec3::one() * 0.65;
+        next.hold.scale = Vec3::one() * 0.0;
+
+        // Tips over backwards around the feet, lifted a bit so the back doesn't
+        // sink into the ground
+        next.torso.position = Vec3::new(0.0, 0.0, 2.0 * fall);
+        next.torso.orientation = Quaternion::rotation_x(-PI / 2.0 * fall);
+
+        next
+    }
+}
@@ -8,6 +8,7 @@ pub mod collect;
 pub mod consume;
 pub mod dance;
 pub mod dash;
+pub mod death;
 pub mod equip;
 pub mod glidewield;
 pub mod gliding;
@@ -30,6 +31,7 @@ pub mod staggered;
 pub mod stand;
 pub mod stunned;
 pub mod swim;
+pub mod swimidle;
 pub mod swimwield;
 pub mod talk;
 pub mod wallrun;
@@ -39,15 +41,16 @@ pub mod wield;
 pub use self::{
     alpha::AlphaAnimation, beam::BeamAnimation, beta::BetaAnimation, block::BlockAnimation,
     chargeswing::ChargeswingAnimation, climb::ClimbAnimation, collect::CollectAnimation,
-    consume::ConsumeAnimation, dance::DanceAnimation, dash::DashAnimation, equip::EquipAnimation,
-    glidewield::GlideWieldAnimation, gliding::GlidingAnimation, idle::IdleAnimation,
-    jump::JumpAnimation, leapmelee::LeapAnimation, mount::MountAnimation,
+    consume::ConsumeAnimation, dance::DanceAnimation, dash::DashAnimation, death::DeathAnimation,
+    equip::EquipAnimation, glidewield::GlideWieldAnimation, gliding::GlidingAnimation,
+    idle::IdleAnimation, jump::JumpAnimation, leapmelee::LeapAnimation, mount::MountAnimation,
     repeater::RepeaterAnimation, roll::RollAnimation, run::RunAnimation,
     shockwave::ShockwaveAnimation, shoot::ShootAnimation, sit::SitAnimation, sneak::SneakAnimation,
     sneakequip::SneakEquipAnimation, sneakwield::SneakWieldAnimation, spin::SpinAnimation,
     spinmelee::SpinMeleeAnimation, staggered::StaggeredAnimation, stand::StandAnimation,
-    stunned::StunnedAnimation, swim::SwimAnimation, swimwield::SwimWieldAnimation,
-    talk::TalkAnimation, wallrun::WallrunAnimation, wield::WieldAnimation,
+    stunned::StunnedAnimation, swim::SwimAnimation, swimidle::SwimIdleAnimation,
+    swimwield::SwimWieldAnimation, talk::TalkAnimation, wallrun::WallrunAnimation,
+    wield::WieldAnimation,
 };
 use super::{make_bone, vek::*, FigureBoneData, Offsets, Skeleton, TrailSource};
 use common::comp;
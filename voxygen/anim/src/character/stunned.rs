@@ -148,7 +148,7 @@ impl Animation for StunnedAnimation {
                         next.main.position = Vec3::new(-5.0, 5.0, 23.0);
                         next.main.orientation = Quaternion::rotation_x(PI);
                     },
-                    Some(ToolKind::Farming) => {
+                    Some(ToolKind::Farming | ToolKind::Fishing) => {
                         next.hand_l.position = Vec3::new(9.0, 1.0, 1.0);
                         next.hand_l.orientation = Quaternion::rotation_x(PI / 2.0);
                         next.hand_r.position = Vec3::new(9.0, 1.0, 11.0);
@@ -26,7 +26,8 @@ pub fn draw_char_state_group(
         | CharacterState::Sit
         | CharacterState::GlideWield(_)
         | CharacterState::Talk
-        | CharacterState::Wielding(_) => {},
+        | CharacterState::Wielding(_)
+        | CharacterState::Fishing(_) => {},
         CharacterState::LeapMelee(data) => leap_melee_grid(ui, data),
         _ => {
             ui.label("<Rendering not yet implemented for this state>");
@@ -20,6 +20,7 @@ use crate::audio::{
     fader::{FadeDirection, Fader},
     Listener,
 };
+use common::uid::Uid;
 use rodio::{OutputStreamHandle, Sample, Sink, Source, SpatialSink};
 use serde::Deserialize;
 use tracing::warn;
@@ -218,6 +219,8 @@ impl AmbientChannel {
 pub struct SfxChannel {
     sink: SpatialSink,
     pub pos: Vec3<f32>,
+    /// The entity this sound follows as it moves
+    pub attached_to: Option<Uid>,
 }
 
 impl SfxChannel {
@@ -226,6 +229,7 @@ impl SfxChannel {
             sink: SpatialSink::try_new(stream, [0.0; 3], [1.0, 0.0, 0.0], [-1.0, 0.0, 0.0])
                 .unwrap(),
             pos: Vec3::zero(),
+            attached_to: None,
         }
     }
 
@@ -267,3 +271,69 @@ impl SfxChannel {
             .set_right_ear_position(listener.ear_right_rpos.into_array());
     }
 }
+
+/// An EmitterChannel loops the sound of an entity's
+/// [`AudioEmitter`](common::comp::AudioEmitter) until it is stopped. The sound
+/// is muffled with a low pass filter while terrain blocks the way to the
+/// listener.
+pub struct EmitterChannel {
+    sink: SpatialSink,
+    id: Uid,
+    occluded: bool,
+    pub pos: Vec3<f32>,
+}
+
+impl EmitterChannel {
+    /// Volume multiplier applied on top of the low pass filter while occluded
+    const OCCLUDED_VOLUME: f32 = 0.5;
+
+    pub fn new(stream: &OutputStreamHandle, id: Uid, occluded: bool) -> Self {
+        Self {
+            sink: SpatialSink::try_new(stream, [0.0; 3], [1.0, 0.0, 0.0], [-1.0, 0.0, 0.0])
+                .unwrap(),
+            id,
+            occluded,
+            pos: Vec3::zero(),
+        }
+    }
+
+    /// Loops `source` until the channel is stopped or dropped
+    pub fn play<S>(&mut self, source: S)
+    where
+        S: Sized + Send + 'static,
+        S: Source<Item = f32>,
+    {
+        let source = source.repeat_infinite();
+        if self.occluded {
+            self.sink.append(source.low_pass(800));
+        } else {
+            self.sink.append(source);
+        }
+    }
+
+    pub fn stop(&mut self) { self.sink.stop(); }
+
+    pub fn set_volume(&mut self, volume: f32) {
+        let occlusion = if self.occluded {
+            Self::OCCLUDED_VOLUME
+        } else {
+            1.0
+        };
+        self.sink.set_volume(volume * occlusion);
+    }
+
+    pub fn get_id(&self) -> Uid { self.id }
+
+    pub fn is_occluded(&self) -> bool { self.occluded }
+
+    pub fn update(&mut self, listener: &Listener) {
+        const FALLOFF: f32 = 0.13;
+
+        self.sink
+            .set_emitter_position(((self.pos - listener.pos) * FALLOFF).into_array());
+        self.sink
+            .set_left_ear_position(listener.ear_left_rpos.into_array());
+        self.sink
+            .set_right_ear_position(listener.ear_right_rpos.into_array());
+    }
+}
@@ -4,10 +4,13 @@ pub mod ambient;
 pub mod channel;
 pub mod fader;
 pub mod music;
+pub mod occlusion;
 pub mod sfx;
 pub mod soundcache;
 
-use channel::{AmbientChannel, AmbientChannelTag, MusicChannel, MusicChannelTag, SfxChannel};
+use channel::{
+    AmbientChannel, AmbientChannelTag, EmitterChannel, MusicChannel, MusicChannelTag, SfxChannel,
+};
 use fader::Fader;
 use music::MusicTransitionManifest;
 use sfx::{SfxEvent, SfxTriggerItem};
@@ -15,7 +18,10 @@ use soundcache::load_ogg;
 use std::time::Duration;
 use tracing::{debug, error, warn};
 
-use common::assets::{AssetExt, AssetHandle};
+use common::{
+    assets::{AssetExt, AssetHandle},
+    uid::Uid,
+};
 use rodio::{source::Source, OutputStream, OutputStreamHandle, StreamError};
 use vek::*;
 
@@ -28,6 +34,16 @@ pub struct Listener {
     ear_right_rpos: Vec3<f32>,
 }
 
+/// A looping sound requested by an entity's
+/// [`AudioEmitter`](common::comp::AudioEmitter) for the current frame
+pub struct EmitterVoice<'a> {
+    pub id: Uid,
+    pub sound: &'a str,
+    pub pos: Vec3<f32>,
+    pub volume: f32,
+    pub occluded: bool,
+}
+
 /// Holds information about the system audio devices and internal channels used
 /// for sfx and music playback. An instance of `AudioFrontend` is used by
 /// Voxygen's [`GlobalState`](../struct.GlobalState.html#structfield.audio) to
@@ -43,6 +59,7 @@ pub struct AudioFrontend {
     music_channels: Vec<MusicChannel>,
     ambient_channels: Vec<AmbientChannel>,
     sfx_channels: Vec<SfxChannel>,
+    emitter_channels: Vec<EmitterChannel>,
     sfx_volume: f32,
     music_volume: f32,
     master_volume: f32,
@@ -90,6 +107,7 @@ impl AudioFrontend {
             audio_stream,
             music_channels: Vec::new(),
             sfx_channels,
+            emitter_channels: Vec::new(),
             ambient_channels: Vec::new(),
             sfx_volume: 1.0,
             music_volume: 1.0,
@@ -119,6 +137,7 @@ impl AudioFrontend {
             audio_stream: None,
             music_channels: Vec::new(),
             sfx_channels: Vec::new(),
+            emitter_channels: Vec::new(),
             ambient_channels: Vec::new(),
             sfx_volume: 1.0,
             music_volume: 1.0,
@@ -231,6 +250,19 @@ impl AudioFrontend {
         position: Vec3<f32>,
         volume: Option<f32>,
         underwater: bool,
+    ) {
+        self.emit_sfx_attached(trigger_item, None, position, volume, underwater);
+    }
+
+    /// Same as `emit_sfx`, but the sound follows the entity `attached_to` as
+    /// it moves, see [`AudioFrontend::update_attached_sfx`]
+    pub fn emit_sfx_attached(
+        &mut self,
+        trigger_item: Option<(&SfxEvent, &SfxTriggerItem)>,
+        attached_to: Option<Uid>,
+        position: Vec3<f32>,
+        volume: Option<f32>,
+        underwater: bool,
     ) {
         if let Some((event, item)) = trigger_item {
             let sfx_file = match item.files.len() {
@@ -249,7 +281,7 @@ impl AudioFrontend {
                 },
             };
 
-            match self.play_sfx(sfx_file, position, volume, underwater) {
+            match self.play_sfx_attached(sfx_file, attached_to, position, volume, underwater) {
                 Ok(_) => {},
                 Err(e) => warn!("Failed to play sfx '{:?}'. {}", sfx_file, e),
             }
@@ -269,12 +301,24 @@ impl AudioFrontend {
         pos: Vec3<f32>,
         vol: Option<f32>,
         underwater: bool,
+    ) -> Result<(), rodio::decoder::DecoderError> {
+        self.play_sfx_attached(sound, None, pos, vol, underwater)
+    }
+
+    fn play_sfx_attached(
+        &mut self,
+        sound: &str,
+        attached_to: Option<Uid>,
+        pos: Vec3<f32>,
+        vol: Option<f32>,
+        underwater: bool,
     ) -> Result<(), rodio::decoder::DecoderError> {
         if self.audio_stream.is_some() {
             let sound = load_ogg(sound).amplify(vol.unwrap_or(1.0));
 
             let listener = self.listener.clone();
             if let Some(channel) = self.get_sfx_channel() {
+                channel.attached_to = attached_to;
                 channel.set_pos(pos);
                 channel.update(&listener);
                 if underwater {
@@ -287,6 +331,57 @@ impl AudioFrontend {
         Ok(())
     }
 
+    /// Moves the playing sfx that are attached to an entity to its current
+    /// position, `pos_of` returns `None` for entities that are gone
+    pub fn update_attached_sfx(&mut self, pos_of: impl Fn(Uid) -> Option<Vec3<f32>>) {
+        for channel in self.sfx_channels.iter_mut().filter(|c| !c.is_done()) {
+            if let Some(pos) = channel.attached_to.and_then(&pos_of) {
+                channel.set_pos(pos);
+                channel.update(&self.listener);
+            }
+        }
+    }
+
+    /// Plays the looping sounds of the given emitters and stops those of
+    /// emitters that aren't in the list anymore. Emitters whose occlusion
+    /// changed are restarted with or without the low pass filter.
+    pub fn maintain_emitters(&mut self, voices: &[EmitterVoice]) {
+        let sfx_volume = self.get_sfx_volume();
+        let audio_stream = match &self.audio_stream {
+            Some(audio_stream) => audio_stream,
+            None => return,
+        };
+
+        let wanted = |c: &EmitterChannel| {
+            voices
+                .iter()
+                .any(|v| v.id == c.get_id() && v.occluded == c.is_occluded())
+        };
+        for channel in self.emitter_channels.iter_mut().filter(|c| !wanted(c)) {
+            channel.stop();
+        }
+        self.emitter_channels.retain(wanted);
+
+        for voice in voices {
+            let channel = match self
+                .emitter_channels
+                .iter_mut()
+                .position(|c| c.get_id() == voice.id)
+            {
+                Some(i) => &mut self.emitter_channels[i],
+                None => {
+                    let mut channel = EmitterChannel::new(audio_stream, voice.id, voice.occluded);
+                    channel.play(load_ogg(voice.sound).convert_samples());
+                    self.emitter_channels.push(channel);
+                    self.emitter_channels.last_mut().unwrap()
+                },
+            };
+            channel.pos = voice.pos;
+            channel.set_volume(voice.volume * sfx_volume);
+            channel.update(&self.listener);
+        }
+    }
+
     fn play_ambient(
         &mut self,
         channel_tag: AmbientChannelTag,
@@ -393,6 +488,10 @@ impl AudioFrontend {
                 channel.update(&self.listener);
             }
         }
+
+        for channel in self.emitter_channels.iter_mut() {
+            channel.update(&self.listener);
+        }
     }
 
     /// Switches the playing music to the title music, which is pinned to a
@@ -451,10 +550,16 @@ impl AudioFrontend {
         }
     }
 
+    /// Stops the ambient sounds and the looping sounds of entity emitters
     pub fn stop_ambient_sounds(&mut self) {
         for channel in self.ambient_channels.iter_mut() {
             channel.stop()
         }
+
+        for channel in self.emitter_channels.iter_mut() {
+            channel.stop()
+        }
+        self.emitter_channels.clear();
     }
 
     // The following is for the disabled device switcher
@@ -0,0 +1,115 @@
+//! Caches whether terrain is in the way between the listener and sound
+//! emitters, so the raycasts only run a few times a second instead of every
+//! frame
+
+use common::{
+    terrain::{Block, TerrainGrid},
+    uid::Uid,
+    vol::ReadVol,
+};
+use hashbrown::HashMap;
+use std::time::{Duration, Instant};
+use vek::*;
+
+/// How long an occlusion result is reused before the ray is cast again
+const REFRESH_INTERVAL: Duration = Duration::from_millis(250);
+
+struct Occlusion {
+    occluded: bool,
+    checked: Instant,
+}
+
+#[derive(Default)]
+pub struct OcclusionCache {
+    emitters: HashMap<Uid, Occlusion>,
+    listener_chunk: Option<Vec2<i32>>,
+}
+
+impl OcclusionCache {
+    /// Forgets all results once the listener enters another chunk, since the
+    /// terrain between it and the emitters is different now
+    pub fn set_listener_pos(&mut self, pos: Vec3<f32>) {
+        let chunk = TerrainGrid::chunk_key(pos.xy().map(|e| e.floor() as i32));
+        if self.listener_chunk != Some(chunk) {
+            self.listener_chunk = Some(chunk);
+            self.emitters.clear();
+        }
+    }
+
+    /// Returns the cached result for `emitter`, `raycast` is only called when
+    /// there is none or it is older than the refresh interval
+    pub fn is_occluded(
+        &mut self,
+        emitter: Uid,
+        now: Instant,
+        raycast: impl FnOnce() -> bool,
+    ) -> bool {
+        match self.emitters.get(&emitter) {
+            Some(occlusion)
+                if now.saturating_duration_since(occlusion.checked) < REFRESH_INTERVAL =>
+            {
+                occlusion.occluded
+            },
+            _ => {
+                let occluded = raycast();
+                self.emitters.insert(emitter, Occlusion {
+                    occluded,
+                    checked: now,
+                });
+                occluded
+            },
+        }
+    }
+
+    /// Drops the results of emitters that aren't heard anymore
+    pub fn retain(&mut self, mut f: impl FnMut(Uid) -> bool) {
+        self.emitters.retain(|uid, _| f(*uid));
+    }
+}
+
+/// Whether an opaque block is in the way between `listener` and `emitter`
+pub fn is_occluded<V: ReadVol<Vox = Block>>(
+    terrain: &V,
+    listener: Vec3<f32>,
+    emitter: Vec3<f32>,
+) -> bool {
+    // Aim a bit above the emitter so the ground it stands on doesn't count
+    let emitter = emitter + Vec3::unit_z();
+    let dist = listener.distance(emitter);
+    matches!(
+        terrain.ray(listener, emitter).until(Block::is_opaque).cast(),
+        (hit, Ok(Some(_))) if hit < dist - 1.0
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reuses_results_until_refresh() {
+        let mut cache = OcclusionCache::default();
+        let now = Instant::now();
+        cache.set_listener_pos(Vec3::new(10.0, 10.0, 0.0));
+
+        assert!(cache.is_occluded(Uid(1), now, || true));
+        assert!(cache.is_occluded(Uid(1), now + REFRESH_INTERVAL / 2, || false));
+        assert!(!cache.is_occluded(Uid(1), now + REFRESH_INTERVAL, || false));
+    }
+
+    #[test]
+    fn invalidated_when_listener_moves_chunks() {
+        let mut cache = OcclusionCache::default();
+        let now = Instant::now();
+        cache.set_listener_pos(Vec3::new(10.0, 10.0, 0.0));
+        assert!(cache.is_occluded(Uid(1), now, || true));
+
+        // Moving within the chunk keeps the cached result
+        cache.set_listener_pos(Vec3::new(20.0, 12.0, 40.0));
+        assert!(cache.is_occluded(Uid(1), now, || false));
+
+        // Entering the next chunk casts the ray again
+        cache.set_listener_pos(Vec3::new(-1.0, 12.0, 40.0));
+        assert!(!cache.is_occluded(Uid(1), now, || false));
+    }
+}
@@ -137,6 +137,7 @@ fn matches_ability_stage() {
                     base_swing_duration: Duration::from_millis(200),
                     hit_timing: 0.5,
                     base_recover_duration: Duration::from_millis(400),
+                    combo_window: Duration::default(),
                     forward_movement: 0.5,
                     damage_kind: DamageKind::Slashing,
                     damage_effect: None,
@@ -199,6 +200,7 @@ fn ignores_different_ability_stage() {
                     base_swing_duration: Duration::from_millis(200),
                     hit_timing: 0.5,
                     base_recover_duration: Duration::from_millis(400),
+                    combo_window: Duration::default(),
                     forward_movement: 0.5,
                     damage_kind: DamageKind::Slashing,
                     damage_effect: None,
@@ -0,0 +1,102 @@
+/// EventMapper::Emitter plays the looping sounds of entities with an
+/// `AudioEmitter`, muffled while terrain is in the way
+use crate::{
+    audio::{
+        occlusion::{self, OcclusionCache},
+        sfx::{SfxEvent, SfxTriggers},
+        EmitterVoice,
+    },
+    scene::{Camera, Terrain},
+    AudioFrontend,
+};
+
+use super::EventMapper;
+
+use client::Client;
+use common::{
+    comp::{AudioEmitter, AudioEmitterKind, Pos},
+    terrain::TerrainChunk,
+    uid::Uid,
+};
+use common_state::State;
+use specs::{Join, WorldExt};
+use std::{cmp::Ordering, time::Instant};
+
+/// The number of emitters heard at once, the loudest ones are picked
+const MAX_EMITTER_VOICES: usize = 16;
+
+pub struct EmitterEventMapper {
+    occlusion: OcclusionCache,
+}
+
+impl EventMapper for EmitterEventMapper {
+    fn maintain(
+        &mut self,
+        audio: &mut AudioFrontend,
+        state: &State,
+        _player_entity: specs::Entity,
+        camera: &Camera,
+        triggers: &SfxTriggers,
+        _terrain: &Terrain<TerrainChunk>,
+        _client: &Client,
+    ) {
+        let ecs = state.ecs();
+        let focus_off = camera.get_focus_pos().map(f32::trunc);
+        let cam_pos = camera.dependents().cam_pos + focus_off;
+        self.occlusion.set_listener_pos(cam_pos);
+
+        let mut audible = (
+            &ecs.read_storage::<Uid>(),
+            &ecs.read_storage::<AudioEmitter>(),
+            &ecs.read_storage::<Pos>(),
+        )
+            .join()
+            .map(|(uid, emitter, pos)| {
+                let volume = emitter.volume_at(pos.0.distance(cam_pos));
+                (*uid, emitter.kind, pos.0, volume)
+            })
+            .filter(|(_, _, _, volume)| *volume > 0.0)
+            .collect::<Vec<_>>();
+        audible.sort_by(|a, b| b.3.partial_cmp(&a.3).unwrap_or(Ordering::Equal));
+        audible.truncate(MAX_EMITTER_VOICES);
+        self.occlusion
+            .retain(|uid| audible.iter().any(|(other, ..)| *other == uid));
+
+        let now = Instant::now();
+        let terrain = state.terrain();
+        let voices = audible
+            .into_iter()
+            .filter_map(|(id, kind, pos, volume)| {
+                let sound = triggers.get_trigger(&SfxEvent::from(kind))?.files.first()?;
+                let occluded = self
+                    .occlusion
+                    .is_occluded(id, now, || occlusion::is_occluded(&*terrain, cam_pos, pos));
+                Some(EmitterVoice {
+                    id,
+                    sound,
+                    pos,
+                    volume,
+                    occluded,
+                })
+            })
+            .collect::<Vec<_>>();
+        audio.maintain_emitters(&voices);
+    }
+}
+
+impl EmitterEventMapper {
+    pub fn new() -> Self {
+        Self {
+            occlusion: OcclusionCache::default(),
+        }
+    }
+}
+
+impl From<AudioEmitterKind> for SfxEvent {
+    fn from(kind: AudioEmitterKind) -> Self {
+        match kind {
+            AudioEmitterKind::Campfire => SfxEvent::Campfire,
+            AudioEmitterKind::Waterfall => SfxEvent::RunningWaterFast,
+        }
+    }
+}
@@ -1,6 +1,6 @@
 mod block;
-mod campfire;
 mod combat;
+mod emitter;
 mod movement;
 
 use client::Client;
@@ -8,8 +8,8 @@ use common::terrain::TerrainChunk;
 use common_state::State;
 
 use block::BlockEventMapper;
-use campfire::CampfireEventMapper;
 use combat::CombatEventMapper;
+use emitter::EmitterEventMapper;
 use movement::MovementEventMapper;
 
 use super::SfxTriggers;
@@ -42,7 +42,7 @@ impl SfxEventMapper {
                 Box::new(CombatEventMapper::new()),
                 Box::new(MovementEventMapper::new()),
                 Box::new(BlockEventMapper::new()),
-                Box::new(CampfireEventMapper::new()),
+                Box::new(EmitterEventMapper::new()),
             ],
         }
     }
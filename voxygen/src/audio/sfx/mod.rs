@@ -84,7 +84,10 @@ mod event_mapper;
 
 use crate::{
     audio::AudioFrontend,
-    scene::{Camera, Terrain},
+    scene::{
+        figure::{AnimationEvent, AnimationEventInfo},
+        Camera, Terrain,
+    },
 };
 
 use client::Client;
@@ -96,16 +99,19 @@ use common::{
         object,
         poise::PoiseState,
         quadruped_low, quadruped_medium, quadruped_small, Body, CharacterAbilityType,
-        InventoryUpdateEvent, UtteranceKind,
+        InventoryUpdateEvent, Pos, UtteranceKind,
     },
+    event::EventBus,
     outcome::Outcome,
     terrain::{BlockKind, TerrainChunk},
+    uid::{Uid, UidAllocator},
 };
 use common_state::State;
 use event_mapper::SfxEventMapper;
 use hashbrown::HashMap;
 use rand::prelude::*;
 use serde::Deserialize;
+use specs::{saveload::MarkerAllocator, WorldExt};
 use tracing::{debug, warn};
 use vek::*;
 
@@ -123,11 +129,18 @@ pub struct SfxEventItem {
     pub pos: Option<Vec3<f32>>,
     /// The volume to play the sound at
     pub vol: Option<f32>,
+    /// The entity the sound follows while it plays, `pos` is ignored if set
+    pub attached_to: Option<Uid>,
 }
 
 impl SfxEventItem {
     pub fn new(sfx: SfxEvent, pos: Option<Vec3<f32>>, vol: Option<f32>) -> Self {
-        Self { sfx, pos, vol }
+        Self {
+            sfx,
+            pos,
+            vol,
+            attached_to: None,
+        }
     }
 
     pub fn at_player_position(sfx: SfxEvent) -> Self {
@@ -135,6 +148,17 @@ impl SfxEventItem {
             sfx,
             pos: None,
             vol: None,
+            attached_to: None,
+        }
+    }
+
+    /// Plays at the position of `entity` and pans along as it moves
+    pub fn attached_to(sfx: SfxEvent, entity: Uid, vol: Option<f32>) -> Self {
+        Self {
+            sfx,
+            pos: None,
+            vol,
+            attached_to: Some(entity),
         }
     }
 }
@@ -379,6 +403,7 @@ impl SfxMgr {
         // Checks if the SFX volume is set to zero or audio is disabled
         // This prevents us from running all the following code unnecessarily
         if !audio.sfx_enabled() {
+            audio.maintain_emitters(&[]);
             return;
         }
 
@@ -400,6 +425,55 @@ impl SfxMgr {
             terrain,
             client,
         );
+
+        let ecs = state.ecs();
+        let uid_allocator = ecs.read_resource::<UidAllocator>();
+        let positions = ecs.read_storage::<Pos>();
+        let pos_of = |uid: Uid| {
+            uid_allocator
+                .retrieve_entity_internal(uid.into())
+                .and_then(|entity| positions.get(entity))
+                .map(|pos| pos.0)
+        };
+        for item in ecs.read_resource::<EventBus<SfxEventItem>>().recv_all() {
+            let pos = match item.attached_to {
+                Some(uid) => pos_of(uid),
+                None => item.pos,
+            };
+            audio.emit_sfx_attached(
+                triggers.get_key_value(&item.sfx),
+                item.attached_to,
+                pos.unwrap_or(cam_pos),
+                item.vol,
+                false,
+            );
+        }
+        audio.update_attached_sfx(pos_of);
+    }
+
+    /// Plays the footsteps of animations, matching the block underfoot
+    pub fn handle_animation_event(
+        &mut self,
+        info: &AnimationEventInfo,
+        audio: &mut AudioFrontend,
+        state: &State,
+    ) {
+        if !audio.sfx_enabled() || info.event != AnimationEvent::Footstep {
+            return;
+        }
+        let underfoot = info.pos.map(|e| e.floor() as i32) - Vec3::unit_z();
+        let sfx = match state.get_block(underfoot).map(|block| block.kind()) {
+            Some(BlockKind::Snow) => SfxEvent::Run(BlockKind::Snow),
+            Some(BlockKind::Rock | BlockKind::WeakRock | BlockKind::Ice) => {
+                SfxEvent::Run(BlockKind::Rock)
+            },
+            Some(BlockKind::Earth) => SfxEvent::Run(BlockKind::Earth),
+            Some(BlockKind::Sand) => SfxEvent::Run(BlockKind::Sand),
+            Some(BlockKind::Air) | None => return,
+            Some(_) => SfxEvent::Run(BlockKind::Grass),
+        };
+        let triggers = self.triggers.read();
+        audio.emit_sfx(triggers.get_key_value(&sfx), info.pos, Some(1.0), false);
     }
 
     #[allow(clippy::single_match)]
@@ -577,9 +651,15 @@ impl SfxMgr {
                     audio.emit_sfx(sfx_trigger_item, *pos, Some(1.0), false);
                 }
             },
+            Outcome::Splash { pos, power } => {
+                let sfx_trigger_item = triggers.get_key_value(&SfxEvent::Swim);
+                audio.emit_sfx(sfx_trigger_item, *pos, Some(power.min(1.0)), false);
+            },
             Outcome::ExpChange { .. }
             | Outcome::ComboChange { .. }
-            | Outcome::SummonedCreature { .. } => {},
+            | Outcome::SummonedCreature { .. }
+            | Outcome::CriticalHit { .. }
+            | Outcome::FallDamage { .. } => {},
         }
     }
 
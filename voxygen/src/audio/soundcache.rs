@@ -8,9 +8,9 @@ use tracing::warn;
 // Implementation of sound taken from this github issue:
 // https://github.com/RustAudio/rodio/issues/141
 
-struct SoundLoader;
+pub(crate) struct SoundLoader;
 #[derive(Clone)]
-struct OggSound(Buffered<Decoder<io::Cursor<Vec<u8>>>>);
+pub(crate) struct OggSound(Buffered<Decoder<io::Cursor<Vec<u8>>>>);
 
 impl Loader<OggSound> for SoundLoader {
     fn load(content: Cow<[u8]>, _: &str) -> Result<OggSound, assets::BoxedError> {
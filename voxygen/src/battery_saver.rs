@@ -0,0 +1,95 @@
+use crate::settings::GraphicsSettings;
+use std::time::{Duration, Instant};
+
+/// How often the platform is asked whether the device runs on battery
+const PROBE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Tracks whether the battery saver overlay of the [`GraphicsSettings`] is
+/// currently applied
+#[derive(Default)]
+pub struct BatterySaver {
+    active: bool,
+    on_battery: bool,
+    last_probe: Option<Instant>,
+}
+
+impl BatterySaver {
+    pub fn active(&self) -> bool { self.active }
+
+    /// Probes the power source if auto enabling is on, returns `true` if the
+    /// overlay got toggled
+    pub fn maintain(&mut self, graphics: &GraphicsSettings) -> bool {
+        if graphics.battery_saver_auto
+            && self
+                .last_probe
+                .map_or(true, |last| last.elapsed() >= PROBE_INTERVAL)
+        {
+            self.last_probe = Some(Instant::now());
+            self.on_battery = on_battery();
+        }
+        self.update(graphics, self.on_battery)
+    }
+
+    fn update(&mut self, graphics: &GraphicsSettings, on_battery: bool) -> bool {
+        let active = graphics.battery_saver_active(on_battery);
+        let changed = active != self.active;
+        self.active = active;
+        changed
+    }
+}
+
+/// Whether the device is discharging and not plugged in
+#[cfg(target_os = "linux")]
+fn on_battery() -> bool {
+    use std::{fs, path::Path};
+
+    let read = |path: &Path, file: &str| {
+        fs::read_to_string(path.join(file))
+            .map(|s| s.trim().to_owned())
+            .unwrap_or_default()
+    };
+    let entries = match fs::read_dir("/sys/class/power_supply") {
+        Ok(entries) => entries,
+        Err(_) => return false,
+    };
+    let mut discharging = false;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        match read(&path, "type").as_str() {
+            "Mains" if read(&path, "online") == "1" => return false,
+            "Battery" => discharging |= read(&path, "status") == "Discharging",
+            _ => {},
+        }
+    }
+    discharging
+}
+
+// TODO: probe the power source on other platforms
+#[cfg(not(target_os = "linux"))]
+fn on_battery() -> bool { false }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn toggles_with_power_source() {
+        let mut graphics = GraphicsSettings {
+            battery_saver_auto: true,
+            ..GraphicsSettings::default()
+        };
+        let mut saver = BatterySaver::default();
+        assert!(!saver.update(&graphics, false));
+        assert!(saver.update(&graphics, true));
+        assert!(saver.active());
+        assert!(!saver.update(&graphics, true));
+        assert!(saver.update(&graphics, false));
+        assert!(!saver.active());
+
+        // turning auto off while unplugged restores the user's settings
+        assert!(saver.update(&graphics, true));
+        graphics.battery_saver_auto = false;
+        assert!(saver.update(&graphics, true));
+        assert!(!saver.active());
+    }
+}
@@ -1,4 +1,4 @@
-use common::comp::Ori;
+use common::{combat::DamageKind, comp::Ori};
 use specs::Component;
 use specs_idvs::IdvStorage;
 use vek::*;
@@ -10,6 +10,8 @@ pub struct HpFloater {
     pub timer: f32,
     // Numbers of times significant damage has been dealt
     pub hp_change: f32,
+    // Tints the number, floaters of different kinds aren't merged
+    pub kind: Option<DamageKind>,
     // Used for randomly offsetting
     pub rand: f32,
 }
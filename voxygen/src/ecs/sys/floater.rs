@@ -98,7 +98,9 @@ impl<'a> System<'a> for Sys {
                 } {
                     let last_floater = hp_floater_list.floaters.last_mut();
                     match last_floater {
-                        Some(f) if f.timer < HP_ACCUMULATETIME => {
+                        Some(f)
+                            if f.timer < HP_ACCUMULATETIME && f.kind == health.last_change.kind =>
+                        {
                             //TODO: Add "jumping" animation on floater when it changes its value
                             f.hp_change += health.last_change.amount;
                         },
@@ -106,6 +108,7 @@ impl<'a> System<'a> for Sys {
                             hp_floater_list.floaters.push(HpFloater {
                                 timer: 0.0,
                                 hp_change: health.last_change.amount,
+                                kind: health.last_change.kind,
                                 rand: rand::random(),
                             });
                         },
@@ -25,8 +25,10 @@ widget_ids! {
         debuff_test,
         buffs[],
         buff_timers[],
+        buff_borders[],
         debuffs[],
         debuff_timers[],
+        debuff_borders[],
         buff_txts[],
     }
 }
@@ -177,6 +179,12 @@ impl<'a> Widget for BuffsBar<'a> {
             if state.ids.debuff_timers.len() < debuff_count {
                 state.update(|state| state.ids.debuff_timers.resize(debuff_count, gen));
             };
+            if state.ids.buff_borders.len() < buff_count {
+                state.update(|state| state.ids.buff_borders.resize(buff_count, gen));
+            };
+            if state.ids.debuff_borders.len() < debuff_count {
+                state.update(|state| state.ids.debuff_borders.resize(debuff_count, gen));
+            };
 
             // Create Buff Widgets
             let mut buff_vec = state
@@ -248,6 +256,11 @@ impl<'a> Widget for BuffsBar<'a> {
                     {
                         event.push(Event::RemoveBuff(buff.kind));
                     };
+                    Rectangle::outline([40.0, 40.0])
+                        .color(BUFF_COLOR)
+                        .middle_of(*id)
+                        .graphics_for(*timer_id)
+                        .set(state.ids.buff_borders[i], ui);
                 });
 
             // Create Debuff Widgets
@@ -314,6 +327,11 @@ impl<'a> Widget for BuffsBar<'a> {
                             DEBUFF_COLOR,
                         )
                         .set(*timer_id, ui);
+                    Rectangle::outline([40.0, 40.0])
+                        .color(DEBUFF_COLOR)
+                        .middle_of(*id)
+                        .graphics_for(*timer_id)
+                        .set(state.ids.debuff_borders[i], ui);
                 });
         }
 
@@ -338,6 +356,9 @@ impl<'a> Widget for BuffsBar<'a> {
             if state.ids.buff_txts.len() < buff_count {
                 state.update(|state| state.ids.buff_txts.resize(buff_count, gen));
             };
+            if state.ids.buff_borders.len() < buff_count {
+                state.update(|state| state.ids.buff_borders.resize(buff_count, gen));
+            };
 
             // Create Buff Widgets
 
@@ -414,6 +435,15 @@ impl<'a> Widget for BuffsBar<'a> {
                     {
                         event.push(Event::RemoveBuff(buff.kind));
                     }
+                    Rectangle::outline([40.0, 40.0])
+                        .color(if buff.is_buff {
+                            BUFF_COLOR
+                        } else {
+                            DEBUFF_COLOR
+                        })
+                        .middle_of(*id)
+                        .graphics_for(*timer_id)
+                        .set(state.ids.buff_borders[i], ui);
                     Text::new(&remaining_time)
                         .down_from(*timer_id, 1.0)
                         .font_size(self.fonts.cyri.scale(10))
@@ -363,14 +363,19 @@ impl<'a> Widget for Buttons<'a> {
                 .mid_top_with_margin_on(state.ids.spellbook_button, -12.0 + arrow_ani as f64)
                 .color(Some(QUALITY_LEGENDARY))
                 .set(state.ids.sp_arrow, ui);
-            Text::new(localized_strings.get("hud.sp_arrow_txt"))
+            let sp_text = format!(
+                "{} {}",
+                self.skill_set.unspent_sp(),
+                localized_strings.get("hud.sp_arrow_txt")
+            );
+            Text::new(&sp_text)
                 .mid_top_with_margin_on(state.ids.sp_arrow, -18.0)
                 .graphics_for(state.ids.spellbook_button)
                 .font_id(self.fonts.cyri.conrod_id)
                 .font_size(self.fonts.cyri.scale(14))
                 .color(BLACK)
                 .set(state.ids.sp_arrow_txt_bg, ui);
-            Text::new(localized_strings.get("hud.sp_arrow_txt"))
+            Text::new(&sp_text)
                 .graphics_for(state.ids.spellbook_button)
                 .bottom_right_with_margins_on(state.ids.sp_arrow_txt_bg, 1.0, 1.0)
                 .font_id(self.fonts.cyri.conrod_id)
@@ -1,13 +1,20 @@
 use super::{
     img_ids::Imgs, ChatTab, ERROR_COLOR, FACTION_COLOR, GROUP_COLOR, INFO_COLOR, KILL_COLOR,
-    OFFLINE_COLOR, ONLINE_COLOR, REGION_COLOR, SAY_COLOR, TELL_COLOR, TEXT_COLOR, WORLD_COLOR,
+    OFFLINE_COLOR, ONLINE_COLOR, REGION_COLOR, SAY_COLOR, TELL_COLOR, TEXT_COLOR, UI_HIGHLIGHT_0,
+    WORLD_COLOR,
+};
+use crate::{
+    settings::{chat::MAX_CHAT_TABS, ChatSettings},
+    ui::fonts::Fonts,
 };
-use crate::{settings::chat::MAX_CHAT_TABS, ui::fonts::Fonts, GlobalState};
 use client::{cmd, Client};
-use common::comp::{
-    chat::{KillSource, KillType},
-    group::Role,
-    BuffKind, ChatMode, ChatMsg, ChatType,
+use common::{
+    comp::{
+        chat::{KillSource, KillType},
+        group::Role,
+        BuffKind, ChatMode, ChatMsg, ChatType,
+    },
+    uid::Uid,
 };
 use common_net::msg::validate_chat_msg;
 use conrod_core::{
@@ -22,8 +29,10 @@ use conrod_core::{
     widget_ids, Color, Colorable, Labelable, Positionable, Sizeable, Ui, UiCell, Widget,
     WidgetCommon,
 };
+use hashbrown::HashMap;
 use i18n::Localization;
 use std::collections::{HashSet, VecDeque};
+use vek::*;
 
 widget_ids! {
     struct Ids {
@@ -32,6 +41,7 @@ widget_ids! {
         chat_input,
         chat_input_bg,
         chat_input_icon,
+        chat_share_position,
         chat_arrow,
         chat_icon_align,
         chat_icons[],
@@ -58,16 +68,99 @@ const CHAT_BOX_HEIGHT: f64 = 154.0;
 const CHAT_TAB_HEIGHT: f64 = 20.0;
 const CHAT_TAB_ALL_WIDTH: f64 = 40.0;
 
+/// The parts of the client the chat uses, so the chat can be tested without a
+/// connection to a server
+pub trait ChatClient {
+    fn chat_mode(&self) -> &ChatMode;
+    fn group_members(&self) -> &HashMap<Uid, Role>;
+    fn format_message(&self, msg: &ChatMsg, character_name: bool) -> String;
+    fn complete_command(&self, line: &str) -> Vec<String>;
+    /// Position of the player, shared with the share position button
+    fn position(&self) -> Option<Vec3<f32>>;
+}
+
+impl ChatClient for Client {
+    fn chat_mode(&self) -> &ChatMode { &self.chat_mode }
+
+    fn group_members(&self) -> &HashMap<Uid, Role> { Client::group_members(self) }
+
+    fn format_message(&self, msg: &ChatMsg, character_name: bool) -> String {
+        Client::format_message(self, msg, character_name)
+    }
+
+    fn complete_command(&self, line: &str) -> Vec<String> { cmd::complete(line, self) }
+
+    fn position(&self) -> Option<Vec3<f32>> { Client::position(self) }
+}
+
+/// Part of a chat message. Messages are plain text, apart from the spans
+/// that get special treatment in the chat box.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ChatSpan<'a> {
+    Text(&'a str),
+    /// Position in the world, written like `[x, y]`. Clicking it marks the
+    /// position on the map.
+    Position(&'a str, Vec2<i32>),
+}
+
+/// Text of a link to `pos`, as parsed by [`parse_spans`]
+pub fn position_link(pos: Vec2<i32>) -> String { format!("[{}, {}]", pos.x, pos.y) }
+
+fn parse_position(link: &str) -> Option<Vec2<i32>> {
+    let (x, y) = link.strip_prefix('[')?.strip_suffix(']')?.split_once(',')?;
+    Some(Vec2::new(x.trim().parse().ok()?, y.trim().parse().ok()?))
+}
+
+/// Splits `message` into its spans
+pub fn parse_spans(message: &str) -> Vec<ChatSpan> {
+    let mut spans = Vec::new();
+    let mut text_start = 0;
+    let mut search_from = 0;
+    while let Some(open) = message[search_from..].find('[').map(|i| search_from + i) {
+        let close = match message[open..].find(']') {
+            Some(i) => open + i,
+            None => break,
+        };
+        let link = &message[open..=close];
+        search_from = match parse_position(link) {
+            Some(pos) => {
+                if open > text_start {
+                    spans.push(ChatSpan::Text(&message[text_start..open]));
+                }
+                spans.push(ChatSpan::Position(link, pos));
+                text_start = close + 1;
+                close + 1
+            },
+            // There may be a link starting in between, like in `[[1, 2]`
+            None => open + 1,
+        };
+    }
+    if text_start < message.len() {
+        spans.push(ChatSpan::Text(&message[text_start..]));
+    }
+    spans
+}
+
+/// First position linked in `message`
+fn linked_position(message: &str) -> Option<Vec2<i32>> {
+    parse_spans(message)
+        .into_iter()
+        .find_map(|span| match span {
+            ChatSpan::Position(_, pos) => Some(pos),
+            ChatSpan::Text(_) => None,
+        })
+}
+
 #[derive(WidgetCommon)]
 pub struct Chat<'a> {
     pulse: f32,
     new_messages: &'a mut VecDeque<ChatMsg>,
-    client: &'a Client,
+    client: &'a dyn ChatClient,
     force_input: Option<String>,
     force_cursor: Option<Index>,
     force_completions: Option<Vec<String>>,
 
-    global_state: &'a GlobalState,
+    chat_settings: &'a ChatSettings,
     imgs: &'a Imgs,
     fonts: &'a Fonts,
 
@@ -83,8 +176,8 @@ pub struct Chat<'a> {
 impl<'a> Chat<'a> {
     pub fn new(
         new_messages: &'a mut VecDeque<ChatMsg>,
-        client: &'a Client,
-        global_state: &'a GlobalState,
+        client: &'a dyn ChatClient,
+        chat_settings: &'a ChatSettings,
         pulse: f32,
         imgs: &'a Imgs,
         fonts: &'a Fonts,
@@ -99,7 +192,7 @@ impl<'a> Chat<'a> {
             force_completions: None,
             imgs,
             fonts,
-            global_state,
+            chat_settings,
             common: widget::CommonBuilder::default(),
             history_max: 32,
             localized_strings,
@@ -108,7 +201,7 @@ impl<'a> Chat<'a> {
 
     pub fn prepare_tab_completion(mut self, input: String) -> Self {
         self.force_completions = if let Some(index) = input.find('\t') {
-            Some(cmd::complete(&input[..index], self.client))
+            Some(self.client.complete_command(&input[..index]))
         } else {
             None
         };
@@ -175,6 +268,8 @@ pub enum Event {
     Focus(Id),
     ChangeChatTab(Option<usize>),
     ShowChatTabSettings(usize),
+    /// A position linked in a message was clicked
+    PingPosition(Vec2<i32>),
 }
 
 impl<'a> Widget for Chat<'a> {
@@ -210,7 +305,7 @@ impl<'a> Widget for Chat<'a> {
 
         let mut events = Vec::new();
 
-        let chat_settings = &self.global_state.settings.chat;
+        let chat_settings = self.chat_settings;
 
         let chat_tabs = &chat_settings.chat_tabs;
         let current_chat_tab = chat_settings.chat_tab_index.and_then(|i| chat_tabs.get(i));
@@ -335,17 +430,20 @@ impl<'a> Widget for Chat<'a> {
             state.update(|s| s.input.message = input.to_string());
         }
 
-        let input_focused =
-            keyboard_capturer == Some(state.ids.chat_input) || keyboard_capturer == Some(id);
+        // Clicking the share position button takes the focus for a moment, the input
+        // has to stay until the click is done
+        let input_focused = keyboard_capturer == Some(state.ids.chat_input)
+            || keyboard_capturer == Some(state.ids.chat_share_position)
+            || keyboard_capturer == Some(id);
 
         // Only show if it has the keyboard captured.
         // Chat input uses a rectangle as its background.
         if input_focused {
             // Shallow comparison of ChatMode.
             let discrim = std::mem::discriminant;
-            if discrim(&state.input.mode) != discrim(&self.client.chat_mode) {
+            if discrim(&state.input.mode) != discrim(self.client.chat_mode()) {
                 state.update(|s| {
-                    s.input.mode = self.client.chat_mode.clone();
+                    s.input.mode = self.client.chat_mode().clone();
                 });
             }
 
@@ -355,6 +453,34 @@ impl<'a> Widget for Chat<'a> {
                 .top_left_with_margin_on(state.ids.chat_input_bg, 2.0)
                 .set(state.ids.chat_input_icon, ui);
 
+            // Share position, inserts a link to it into the message
+            if Button::image(self.imgs.location_marker)
+                .w_h(CHAT_ICON_WIDTH, CHAT_ICON_HEIGHT)
+                .top_right_with_margins_on(state.ids.chat_input_bg, 2.0, -CHAT_ICON_WIDTH - 4.0)
+                .image_color(TEXT_COLOR)
+                .hover_image(self.imgs.location_marker)
+                .set(state.ids.chat_share_position, ui)
+                .was_clicked()
+            {
+                if let Some(pos) = self.client.position() {
+                    let link = position_link(pos.xy().map(|e| e.floor() as i32));
+                    state.update(|s| {
+                        if !s.input.message.is_empty() && !s.input.message.ends_with(' ') {
+                            s.input.message.push(' ');
+                        }
+                        s.input.message.push_str(&link);
+                    });
+                    force_cursor = cursor_offset_to_index(
+                        state.input.message.chars().count(),
+                        &state.input.message,
+                        ui,
+                        self.fonts,
+                    );
+                }
+                // Clicking the button took the focus from the input
+                events.push(Event::Focus(state.ids.chat_input));
+            }
+
             // Any changes to this TextEdit's width and font size must be reflected in
             // `cursor_offset_to_index` below.
             let mut text_edit = TextEdit::new(&state.input.message)
@@ -466,7 +592,18 @@ impl<'a> Widget for Chat<'a> {
             // This would be easier if conrod used the v-metrics from rusttype.
             if item.i < messages.len() {
                 let message = &messages[item.i];
-                let (color, icon) = render_chat_line(&message.chat_type, self.imgs);
+                let (mut color, icon) = render_chat_line(&message.chat_type, self.imgs);
+                // Messages linking a position are highlighted while hovered, clicking them
+                // marks it on the map
+                if let Some(pos) = linked_position(&message.message) {
+                    let input = ui.widget_input(item.widget_id);
+                    if input.clicks().left().next().is_some() {
+                        events.push(Event::PingPosition(pos));
+                    }
+                    if input.mouse().map_or(false, |m| m.is_over()) {
+                        color = UI_HIGHLIGHT_0;
+                    }
+                }
                 // For each ChatType needing localization get/set matching pre-formatted
                 // localized string. This string will be formatted with the data
                 // provided in ChatType in the client/src/mod.rs
@@ -859,6 +996,98 @@ fn parse_cmd(msg: &str) -> Result<(String, Vec<String>), String> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::ui::headless::HeadlessUi;
+    use i18n::LocalizationHandle;
+    use vek::*;
+
+    struct FakeClient {
+        chat_mode: ChatMode,
+        group_members: HashMap<Uid, Role>,
+    }
+
+    impl ChatClient for FakeClient {
+        fn chat_mode(&self) -> &ChatMode { &self.chat_mode }
+
+        fn group_members(&self) -> &HashMap<Uid, Role> { &self.group_members }
+
+        fn format_message(&self, msg: &ChatMsg, _: bool) -> String { msg.message.clone() }
+
+        fn complete_command(&self, _: &str) -> Vec<String> { Vec::new() }
+
+        fn position(&self) -> Option<Vec3<f32>> { None }
+    }
+
+    fn set_chat(
+        ui: &mut HeadlessUi,
+        id: Id,
+        client: &FakeClient,
+        settings: &ChatSettings,
+        imgs: &Imgs,
+        fonts: &Fonts,
+        i18n: &Localization,
+    ) -> Vec<Event> {
+        let mut new_messages = VecDeque::new();
+        ui.frame(|ui| {
+            Chat::new(&mut new_messages, client, settings, 0.0, imgs, fonts, i18n).set(id, ui)
+        })
+    }
+
+    #[test]
+    fn focusing_the_chat_focuses_its_input() {
+        let mut ui = HeadlessUi::new(Vec2::new(1920, 1080));
+        let i18n = LocalizationHandle::load_expect("en").read();
+        let imgs = Imgs::load(&mut ui).unwrap();
+        let fonts = Fonts::load(i18n.fonts(), &mut ui).unwrap();
+        let client = FakeClient {
+            chat_mode: ChatMode::default(),
+            group_members: HashMap::new(),
+        };
+        let settings = ChatSettings::default();
+        let id = ui.new_id();
+
+        assert!(set_chat(&mut ui, id, &client, &settings, &imgs, &fonts, &i18n).is_empty());
+        assert!(ui.query().of_type::<TextEdit>().is_empty());
+
+        // Pressing enter in game focuses the chat, which passes the focus on
+        ui.focus_widget(Some(id));
+        let input = match set_chat(&mut ui, id, &client, &settings, &imgs, &fonts, &i18n).as_slice()
+        {
+            [Event::Focus(input)] => *input,
+            _ => panic!("Chat didn't pass on the focus"),
+        };
+        ui.focus_widget(Some(input));
+        set_chat(&mut ui, id, &client, &settings, &imgs, &fonts, &i18n);
+        assert_eq!(ui.query().of_type::<TextEdit>(), vec![input]);
+        assert!(ui.query().is_captured::<TextEdit>());
+
+        ui.type_text("hello");
+        set_chat(&mut ui, id, &client, &settings, &imgs, &fonts, &i18n);
+        ui.press_key(Key::Return);
+        let events = set_chat(&mut ui, id, &client, &settings, &imgs, &fonts, &i18n);
+        assert!(matches!(events.as_slice(), [Event::SendMessage(msg)] if msg == "hello"));
+
+        // Clicking somewhere else hides the input again
+        ui.click([800.0, 400.0]);
+        set_chat(&mut ui, id, &client, &settings, &imgs, &fonts, &i18n);
+        assert!(!ui.query().is_captured::<TextEdit>());
+        assert!(ui.query().of_type::<TextEdit>().is_empty());
+    }
+
+    #[test]
+    fn position_links() {
+        let link = position_link(Vec2::new(120, -45));
+        assert_eq!(link, "[120, -45]");
+        assert_eq!(parse_spans(&format!("meet at {} now", link)), [
+            ChatSpan::Text("meet at "),
+            ChatSpan::Position("[120, -45]", Vec2::new(120, -45)),
+            ChatSpan::Text(" now"),
+        ]);
+        // Only pairs of numbers are links
+        assert_eq!(parse_spans("[Player] said [1, 2, 3]"), [ChatSpan::Text(
+            "[Player] said [1, 2, 3]"
+        )]);
+        assert_eq!(linked_position("[[3,4]]"), Some(Vec2::new(3, 4)));
+    }
 
     #[test]
     fn parse_cmds() {
@@ -0,0 +1,244 @@
+use super::{img_ids::Imgs, util, CoordinatesDisplay, TEXT_COLOR, UI_HIGHLIGHT_0};
+use crate::ui::fonts::Fonts;
+use client::{self, Client};
+use common::{comp, terrain::TerrainChunkSize, vol::RectVolSize};
+use conrod_core::{
+    color, position,
+    widget::{self, Image, Rectangle, Text},
+    widget_ids, Color, Colorable, Positionable, Sizeable, Widget, WidgetCommon,
+};
+use specs::WorldExt;
+use std::{
+    cmp::Ordering,
+    f32::consts::{FRAC_PI_2, PI, TAU},
+};
+use vek::*;
+
+/// Width of the strip in pixels
+const WIDTH: f64 = 400.0;
+const HEIGHT: f64 = 34.0;
+/// Angle covered by the whole strip, centered on the view direction
+const FOV: f32 = PI;
+/// Sites further away aren't shown
+const MAX_SITE_DISTANCE: f32 = 3000.0;
+const MAX_SITES: usize = 5;
+
+widget_ids! {
+    struct Ids {
+        bg,
+        center,
+        coordinates,
+        directions[],
+        sites[],
+    }
+}
+
+/// Strip at the top of the screen showing cardinal directions and nearby sites
+/// relative to the camera orientation, with the coordinates of the player
+/// below it
+#[derive(WidgetCommon)]
+pub struct Compass<'a> {
+    client: &'a Client,
+    imgs: &'a Imgs,
+    fonts: &'a Fonts,
+    ori: Vec3<f32>,
+    coordinates: CoordinatesDisplay,
+    #[conrod(common_builder)]
+    common: widget::CommonBuilder,
+}
+
+impl<'a> Compass<'a> {
+    pub fn new(
+        client: &'a Client,
+        imgs: &'a Imgs,
+        fonts: &'a Fonts,
+        ori: Vec3<f32>,
+        coordinates: CoordinatesDisplay,
+    ) -> Self {
+        Self {
+            client,
+            imgs,
+            fonts,
+            ori,
+            coordinates,
+            common: widget::CommonBuilder::default(),
+        }
+    }
+}
+
+pub struct State {
+    ids: Ids,
+}
+
+/// Horizontal offset from the center of the strip for something in the
+/// direction of `bearing`, `None` if it's outside of the strip
+fn bearing_to_x(bearing: f32, yaw: f32) -> Option<f64> {
+    // wrap into -PI..PI, positive is to the right
+    let diff = (bearing - yaw + PI).rem_euclid(TAU) - PI;
+    (diff.abs() <= FOV / 2.0).then(|| (diff / (FOV / 2.0)) as f64 * WIDTH / 2.0)
+}
+
+/// Readout of `pos` at the chosen precision, `None` if it's turned off
+fn format_coordinates(pos: Vec3<f32>, precision: CoordinatesDisplay) -> Option<String> {
+    match precision {
+        CoordinatesDisplay::Off => None,
+        CoordinatesDisplay::Chunk => {
+            let chunk = pos.xy().map2(TerrainChunkSize::RECT_SIZE, |e, sz| {
+                (e.floor() as i32).div_euclid(sz as i32)
+            });
+            Some(format!("{}, {}", chunk.x, chunk.y))
+        },
+        CoordinatesDisplay::Block => {
+            let pos = pos.map(|e| e.floor() as i32);
+            Some(format!("{}, {}, {}", pos.x, pos.y, pos.z))
+        },
+    }
+}
+
+impl<'a> Widget for Compass<'a> {
+    type Event = ();
+    type State = State;
+    type Style = ();
+
+    fn init_state(&self, id_gen: widget::id::Generator) -> Self::State {
+        State {
+            ids: Ids::new(id_gen),
+        }
+    }
+
+    fn style(&self) -> Self::Style {}
+
+    fn update(self, args: widget::UpdateArgs<Self>) -> Self::Event {
+        common_base::prof_span!("Compass::update");
+        let widget::UpdateArgs { state, ui, .. } = args;
+        // yaw 0 looks north, increasing clockwise
+        let yaw = self.ori.x;
+
+        Rectangle::fill_with([WIDTH, HEIGHT], color::BLACK.alpha(0.4))
+            .mid_top_with_margin_on(ui.window, 5.0)
+            .set(state.ids.bg, ui);
+        Rectangle::fill_with([1.0, HEIGHT], UI_HIGHLIGHT_0)
+            .middle_of(state.ids.bg)
+            .set(state.ids.center, ui);
+
+        // Cardinal directions
+        let dirs = ["N", "NE", "E", "SE", "S", "SW", "W", "NW"];
+        if state.ids.directions.len() < dirs.len() {
+            state.update(|s| {
+                s.ids
+                    .directions
+                    .resize(dirs.len(), &mut ui.widget_id_generator())
+            });
+        }
+        for (i, name) in dirs.iter().enumerate() {
+            let x = match bearing_to_x(i as f32 * FRAC_PI_2 / 2.0, yaw) {
+                Some(x) => x,
+                None => continue,
+            };
+            let cardinal = i % 2 == 0;
+            Text::new(name)
+                .x_y_position_relative_to(
+                    state.ids.bg,
+                    position::Relative::Scalar(x),
+                    position::Relative::Scalar(6.0),
+                )
+                .font_size(self.fonts.cyri.scale(if cardinal { 16 } else { 12 }))
+                .font_id(self.fonts.cyri.conrod_id)
+                .color(if i == 0 {
+                    Color::Rgba(0.75, 0.0, 0.0, 1.0)
+                } else {
+                    TEXT_COLOR
+                })
+                .set(state.ids.directions[i], ui);
+        }
+
+        let player_pos = self
+            .client
+            .state()
+            .ecs()
+            .read_storage::<comp::Pos>()
+            .get(self.client.entity())
+            .map_or(Vec3::zero(), |pos| pos.0);
+        if let Some(coordinates) = format_coordinates(player_pos, self.coordinates) {
+            Text::new(&coordinates)
+                .mid_bottom_with_margin_on(state.ids.bg, -18.0)
+                .font_size(self.fonts.cyri.scale(14))
+                .font_id(self.fonts.cyri.conrod_id)
+                .color(TEXT_COLOR)
+                .set(state.ids.coordinates, ui);
+        }
+
+        // Nearby sites
+        let player_pos = player_pos.xy();
+        let mut sites = self
+            .client
+            .sites()
+            .values()
+            .map(|site_rich| {
+                let site = &site_rich.site;
+                (site, site.wpos.map(|e| e as f32) - player_pos)
+            })
+            .filter(|(_, rpos)| rpos.magnitude_squared() < MAX_SITE_DISTANCE.powi(2))
+            .collect::<Vec<_>>();
+        sites.sort_by(|(_, a), (_, b)| {
+            a.magnitude_squared()
+                .partial_cmp(&b.magnitude_squared())
+                .unwrap_or(Ordering::Equal)
+        });
+        sites.truncate(MAX_SITES);
+        if state.ids.sites.len() < sites.len() {
+            state.update(|s| {
+                s.ids
+                    .sites
+                    .resize(sites.len(), &mut ui.widget_id_generator())
+            });
+        }
+        for (i, (site, rpos)) in sites.iter().enumerate() {
+            let x = match bearing_to_x(rpos.x.atan2(rpos.y), yaw) {
+                Some(x) => x,
+                None => continue,
+            };
+            // closer sites are more visible
+            let fade = 1.0 - rpos.magnitude() / MAX_SITE_DISTANCE * 0.6;
+            Image::new(util::site_icon(self.imgs, &site.kind))
+                .x_y_position_relative_to(
+                    state.ids.bg,
+                    position::Relative::Scalar(x),
+                    position::Relative::Scalar(-8.0),
+                )
+                .w_h(14.0, 14.0)
+                .color(Some(UI_HIGHLIGHT_0.alpha(fade)))
+                .set(state.ids.sites[i], ui);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bearing_offsets() {
+        assert_eq!(bearing_to_x(0.0, 0.0), Some(0.0));
+        // east while looking north is at the right edge
+        assert!((bearing_to_x(FRAC_PI_2, 0.0).unwrap() - WIDTH / 2.0).abs() < 0.01);
+        // south is behind us
+        assert_eq!(bearing_to_x(PI * 0.9, 0.0), None);
+        // wraps around, north while looking at north-north-west
+        assert!(bearing_to_x(0.0, TAU - 0.3).unwrap() > 0.0);
+    }
+
+    #[test]
+    fn coordinates_precision() {
+        let pos = Vec3::new(70.5, -0.5, 12.9);
+        assert_eq!(format_coordinates(pos, CoordinatesDisplay::Off), None);
+        assert_eq!(
+            format_coordinates(pos, CoordinatesDisplay::Chunk).as_deref(),
+            Some("2, -1")
+        );
+        assert_eq!(
+            format_coordinates(pos, CoordinatesDisplay::Block).as_deref(),
+            Some("70, -1, 12")
+        );
+    }
+}
@@ -0,0 +1,197 @@
+use super::{img_ids::Imgs, CrosshairType};
+use conrod_core::{
+    color,
+    widget::{self, Image, Rectangle},
+    widget_ids, Color, Colorable, Positionable, Sizeable, Widget, WidgetCommon,
+};
+use vek::*;
+
+const RING_SIZE: f64 = 21.0 * 1.5;
+const DOT_SIZE: f64 = 3.0;
+/// Distance of the four dots from the center while standing still
+const MIN_SPREAD: f64 = 6.0;
+const MAX_SPREAD: f64 = 24.0;
+/// Additional spread of the dots per block per second the player moves at
+const SPREAD_PER_SPEED: f64 = 1.5;
+/// How long the crosshair flashes after a critical hit
+const CRIT_FLASH: f32 = 0.15;
+/// How long the crosshair flashes after a normal hit
+const HIT_FLASH: f32 = 0.08;
+const CRIT_COLOR: Rgb<f32> = Rgb::new(1.0, 0.1, 0.1);
+
+widget_ids! {
+    struct Ids {
+        ring,
+        dots[],
+        center,
+    }
+}
+
+/// What the crosshair points at, shown by the color of its center dot
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CrosshairTarget {
+    Nothing,
+    Interactable,
+    Enemy,
+}
+
+/// Flash of the crosshair confirming that the player hit something
+#[derive(Default)]
+pub struct HitFlash {
+    crit: bool,
+    /// Seconds until the flash ends
+    remaining: f32,
+}
+
+impl HitFlash {
+    pub fn hit(&mut self, crit: bool) {
+        // Don't cut a critical flash short with a normal one
+        if crit || !self.crit || self.remaining <= 0.0 {
+            self.crit = crit;
+            self.remaining = if crit { CRIT_FLASH } else { HIT_FLASH };
+        }
+    }
+
+    pub fn maintain(&mut self, dt: f32) { self.remaining = (self.remaining - dt).max(0.0); }
+
+    /// Color of the flash, `None` when there is no flash. Critical hits fade
+    /// from white to red, normal hits stay white.
+    pub fn color(&self) -> Option<Rgb<f32>> {
+        if self.remaining <= 0.0 {
+            None
+        } else if self.crit {
+            let progress = 1.0 - self.remaining / CRIT_FLASH;
+            Some(Lerp::lerp(Rgb::one(), CRIT_COLOR, progress))
+        } else {
+            Some(Rgb::one())
+        }
+    }
+}
+
+/// Distance of the four dots from the center when moving at `speed`
+fn spread(speed: f32) -> f64 { (MIN_SPREAD + f64::from(speed) * SPREAD_PER_SPEED).min(MAX_SPREAD) }
+
+/// Crosshair made of a ring, four dots spreading apart while the player moves
+/// and a center dot colored after the target
+#[derive(WidgetCommon)]
+pub struct Crosshair<'a> {
+    imgs: &'a Imgs,
+    crosshair_type: CrosshairType,
+    opacity: f32,
+    speed: f32,
+    flash: &'a HitFlash,
+    target: CrosshairTarget,
+    #[conrod(common_builder)]
+    common: widget::CommonBuilder,
+}
+
+impl<'a> Crosshair<'a> {
+    pub fn new(
+        imgs: &'a Imgs,
+        crosshair_type: CrosshairType,
+        opacity: f32,
+        speed: f32,
+        flash: &'a HitFlash,
+        target: CrosshairTarget,
+    ) -> Self {
+        Self {
+            imgs,
+            crosshair_type,
+            opacity,
+            speed,
+            flash,
+            target,
+            common: widget::CommonBuilder::default(),
+        }
+    }
+}
+
+pub struct State {
+    ids: Ids,
+}
+
+impl<'a> Widget for Crosshair<'a> {
+    type Event = ();
+    type State = State;
+    type Style = ();
+
+    fn init_state(&self, id_gen: widget::id::Generator) -> Self::State {
+        State {
+            ids: Ids::new(id_gen),
+        }
+    }
+
+    fn style(&self) -> Self::Style {}
+
+    fn update(self, args: widget::UpdateArgs<Self>) -> Self::Event {
+        common_base::prof_span!("Crosshair::update");
+        let widget::UpdateArgs { id, state, ui, .. } = args;
+
+        let flash = self.flash.color().unwrap_or_else(Rgb::one);
+        let flash = Color::Rgba(flash.r, flash.g, flash.b, self.opacity);
+
+        Image::new(match self.crosshair_type {
+            CrosshairType::Round => self.imgs.crosshair_outer_round,
+            CrosshairType::RoundEdges => self.imgs.crosshair_outer_round_edges,
+            CrosshairType::Edges => self.imgs.crosshair_outer_edges,
+        })
+        .w_h(RING_SIZE, RING_SIZE)
+        .middle_of(id)
+        .color(Some(flash))
+        .set(state.ids.ring, ui);
+
+        if state.ids.dots.len() < 4 {
+            state.update(|s| s.ids.dots.resize(4, &mut ui.widget_id_generator()));
+        }
+        let spread = spread(self.speed);
+        let offsets = [(0.0, spread), (spread, 0.0), (0.0, -spread), (-spread, 0.0)];
+        for (i, (x, y)) in offsets.iter().enumerate() {
+            Rectangle::fill_with([DOT_SIZE, DOT_SIZE], flash)
+                .x_y_relative_to(state.ids.ring, *x, *y)
+                .set(state.ids.dots[i], ui);
+        }
+
+        let center = match self.target {
+            CrosshairTarget::Nothing => color::WHITE,
+            CrosshairTarget::Interactable => Color::Rgba(1.0, 0.85, 0.1, 1.0),
+            CrosshairTarget::Enemy => Color::Rgba(1.0, 0.1, 0.1, 1.0),
+        };
+        Rectangle::fill_with([DOT_SIZE, DOT_SIZE], center.alpha(self.opacity))
+            .middle_of(state.ids.ring)
+            .set(state.ids.center, ui);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dots_spread_with_speed() {
+        assert_eq!(spread(0.0), MIN_SPREAD);
+        assert!(spread(5.0) > spread(1.0));
+        assert_eq!(spread(100.0), MAX_SPREAD);
+    }
+
+    #[test]
+    fn crit_flash_turns_red() {
+        let mut flash = HitFlash::default();
+        assert_eq!(flash.color(), None);
+
+        flash.hit(true);
+        assert_eq!(flash.color(), Some(Rgb::one()));
+        flash.maintain(0.1);
+        let color = flash.color().unwrap();
+        assert!(color.r > color.g);
+        // A normal hit doesn't replace the red flash
+        flash.hit(false);
+        assert!(flash.crit);
+        flash.maintain(0.1);
+        assert_eq!(flash.color(), None);
+
+        flash.hit(false);
+        assert_eq!(flash.color(), Some(Rgb::one()));
+        flash.maintain(HIT_FLASH);
+        assert_eq!(flash.color(), None);
+    }
+}
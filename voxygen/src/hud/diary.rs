@@ -41,8 +41,9 @@ use common::{
             slot::EquipSlot,
         },
         skills::{
-            self, AxeSkill, BowSkill, ClimbSkill, GeneralSkill, HammerSkill, MiningSkill,
-            RollSkill, SceptreSkill, Skill, StaffSkill, SwimSkill, SwordSkill, SKILL_MODIFIERS,
+            self, AxeSkill, BowSkill, ClimbSkill, CraftingSkill, GeneralSkill, HammerSkill,
+            MagicSkill, MiningSkill, RollSkill, SceptreSkill, Skill, StaffSkill, SwimSkill,
+            SwordSkill, SKILL_MODIFIERS,
         },
         skillset::{SkillGroupKind, SkillSet},
         Body, Energy, Health, Inventory, Poise,
@@ -70,6 +71,7 @@ widget_ids! {
         exp_bar_content,
         exp_bar_rank,
         exp_bar_txt,
+        exp_bar_multiplier,
         tree_title_txt,
         lock_imgs[],
         available_pts_txt,
@@ -663,7 +665,7 @@ impl<'a> Widget for Diary<'a> {
                 // Exp Bars and Rank Display
                 let current_exp = self.skill_set.available_experience(*sel_tab) as f64;
                 let max_exp = self.skill_set.skill_point_cost(*sel_tab) as f64;
-                let exp_percentage = current_exp / max_exp;
+                let exp_percentage = self.skill_set.exp_fraction_to_next_sp(*sel_tab) as f64;
                 let rank = self.skill_set.earned_sp(*sel_tab);
                 let rank_txt = format!("{}", rank);
                 let exp_txt = format!("{}/{}", current_exp, max_exp);
@@ -714,6 +716,21 @@ impl<'a> Widget for Diary<'a> {
                     }))
                     .color(TEXT_COLOR)
                     .set(state.ids.exp_bar_rank, ui);
+                // High level characters earn more exp
+                let xp_multiplier = self.skill_set.xp_gain_multiplier(*sel_tab);
+                if xp_multiplier > 1.0 {
+                    Text::new(
+                        &self
+                            .localized_strings
+                            .get("hud.skill.xp_multiplier")
+                            .replace("{multiplier}", &format!("{}", xp_multiplier)),
+                    )
+                    .top_right_with_margins_on(state.ids.exp_bar_frame, 8.0, 45.0)
+                    .font_id(self.fonts.cyri.conrod_id)
+                    .font_size(self.fonts.cyri.scale(16))
+                    .color(XP_COLOR)
+                    .set(state.ids.exp_bar_multiplier, ui);
+                }
 
                 Text::new(
                     &self
@@ -2797,6 +2814,9 @@ pub fn skill_strings(skill: Skill, i18n: &Localization) -> (&str, Cow<str>) {
         Skill::Swim(s) => swim_skill_strings(s, i18n),
         // mining
         Skill::Pick(s) => mining_skill_strings(s, i18n),
+        // planned trees
+        Skill::Magic(s) => magic_skill_strings(s, i18n),
+        Skill::Crafting(s) => crafting_skill_strings(s, i18n),
     }
 }
 
@@ -2841,6 +2861,14 @@ fn unlock_skill_strings(group: SkillGroupKind, i18n: &Localization) -> (&str, Co
             "hud.skill.unlck_sceptre_title",
             "hud.skill.unlck_sceptre",
         ),
+        SkillGroupKind::Magic => {
+            localize(i18n, "hud.skill.unlck_magic_title", "hud.skill.unlck_magic")
+        },
+        SkillGroupKind::Crafting => localize(
+            i18n,
+            "hud.skill.unlck_crafting_title",
+            "hud.skill.unlck_crafting",
+        ),
         SkillGroupKind::General
         | SkillGroupKind::Weapon(
             ToolKind::Dagger
@@ -2849,6 +2877,7 @@ fn unlock_skill_strings(group: SkillGroupKind, i18n: &Localization) -> (&str, Co
             | ToolKind::Blowgun
             | ToolKind::Debug
             | ToolKind::Farming
+            | ToolKind::Fishing
             | ToolKind::Pick
             | ToolKind::Natural
             | ToolKind::Empty,
@@ -3467,6 +3496,69 @@ fn mining_skill_strings(skill: MiningSkill, i18n: &Localization) -> (&str, Cow<s
     }
 }
 
+fn magic_skill_strings(skill: MagicSkill, i18n: &Localization) -> (&str, Cow<str>) {
+    match skill {
+        MagicSkill::EnergyRegen => localize(
+            i18n,
+            "hud.skill.magic_energy_regen_title",
+            "hud.skill.magic_energy_regen",
+        ),
+        MagicSkill::SpellDamage => localize(
+            i18n,
+            "hud.skill.magic_spell_damage_title",
+            "hud.skill.magic_spell_damage",
+        ),
+        MagicSkill::SpellCost => localize(
+            i18n,
+            "hud.skill.magic_spell_cost_title",
+            "hud.skill.magic_spell_cost",
+        ),
+        MagicSkill::UnlockWard => {
+            localize(i18n, "hud.skill.magic_ward_title", "hud.skill.magic_ward")
+        },
+        MagicSkill::WardStrength => localize(
+            i18n,
+            "hud.skill.magic_ward_strength_title",
+            "hud.skill.magic_ward_strength",
+        ),
+        MagicSkill::WardDuration => localize(
+            i18n,
+            "hud.skill.magic_ward_duration_title",
+            "hud.skill.magic_ward_duration",
+        ),
+    }
+}
+
+fn crafting_skill_strings(skill: CraftingSkill, i18n: &Localization) -> (&str, Cow<str>) {
+    match skill {
+        CraftingSkill::Speed => localize(
+            i18n,
+            "hud.skill.crafting_speed_title",
+            "hud.skill.crafting_speed",
+        ),
+        CraftingSkill::MaterialSaving => localize(
+            i18n,
+            "hud.skill.crafting_material_saving_title",
+            "hud.skill.crafting_material_saving",
+        ),
+        CraftingSkill::SalvageYield => localize(
+            i18n,
+            "hud.skill.crafting_salvage_yield_title",
+            "hud.skill.crafting_salvage_yield",
+        ),
+        CraftingSkill::UnlockMasterwork => localize(
+            i18n,
+            "hud.skill.crafting_masterwork_title",
+            "hud.skill.crafting_masterwork",
+        ),
+        CraftingSkill::MasterworkChance => localize(
+            i18n,
+            "hud.skill.crafting_masterwork_chance_title",
+            "hud.skill.crafting_masterwork_chance",
+        ),
+    }
+}
+
 /// Helper function which takes title i18n key and description i18n key
 /// and returns localized title and localized description replacing "{boost}"
 /// placeholder with passed constant.
@@ -0,0 +1,146 @@
+use super::{animate_by_pulse, item_imgs::ItemImgs};
+use common::comp::item::item_key::ItemKey;
+use common_net::msg::FishingState;
+use conrod_core::{
+    color, position,
+    widget::{self, Image, Rectangle},
+    widget_ids, Color, Colorable, Positionable, Sizeable, Widget, WidgetCommon,
+};
+
+const BAR_WIDTH: f64 = 14.0;
+const BAR_HEIGHT: f64 = 160.0;
+const BAR_SPACING: f64 = 6.0;
+/// Horizontal offset of the bars from the center of the screen, so they don't
+/// cover the crosshair
+const OFFSET_X: f64 = 150.0;
+const FISH_ICON: &str = "common.items.food.meat.fish_raw";
+const PROGRESS_COLOR: Color = Color::Rgba(0.2, 0.45, 0.9, 1.0);
+const TENSION_COLOR: Color = Color::Rgba(0.85, 0.15, 0.1, 1.0);
+
+widget_ids! {
+    struct Ids {
+        progress_bg,
+        progress,
+        tension_bg,
+        tension,
+        max_tension,
+        fish,
+    }
+}
+
+/// Two vertical bars next to the crosshair showing how far the fish on the
+/// line was reeled in and how strained the line is
+#[derive(WidgetCommon)]
+pub struct FishingMeter<'a> {
+    item_imgs: &'a ItemImgs,
+    progress: f32,
+    tension: f32,
+    pulse: f32,
+    #[conrod(common_builder)]
+    common: widget::CommonBuilder,
+}
+
+impl<'a> FishingMeter<'a> {
+    pub fn new(item_imgs: &'a ItemImgs, (progress, tension): (f32, f32), pulse: f32) -> Self {
+        Self {
+            item_imgs,
+            progress,
+            tension,
+            pulse,
+            common: widget::CommonBuilder::default(),
+        }
+    }
+}
+
+pub struct State {
+    ids: Ids,
+}
+
+/// Opacity of the tension bar, it pulses once reeling doesn't make progress
+/// anymore and faster the closer the line is to breaking
+fn tension_alpha(tension: f32, pulse: f32) -> f32 {
+    if tension < FishingState::MAX_REEL_TENSION {
+        1.0
+    } else {
+        let speed = 6.0 + 12.0 * (tension - FishingState::MAX_REEL_TENSION);
+        0.4 + 0.6 * (pulse * speed).sin().abs()
+    }
+}
+
+impl<'a> Widget for FishingMeter<'a> {
+    type Event = ();
+    type State = State;
+    type Style = ();
+
+    fn init_state(&self, id_gen: widget::id::Generator) -> Self::State {
+        State {
+            ids: Ids::new(id_gen),
+        }
+    }
+
+    fn style(&self) -> Self::Style {}
+
+    fn update(self, args: widget::UpdateArgs<Self>) -> Self::Event {
+        common_base::prof_span!("FishingMeter::update");
+        let widget::UpdateArgs { state, ui, .. } = args;
+        let window = ui.window;
+        let bar = |x: f64| {
+            Rectangle::fill_with([BAR_WIDTH, BAR_HEIGHT], color::BLACK.alpha(0.5))
+                .x_y_position_relative_to(
+                    window,
+                    position::Relative::Scalar(x),
+                    position::Relative::Scalar(0.0),
+                )
+        };
+        let height = |value: f32| BAR_HEIGHT * f64::from(value.clamp(0.0, 1.0));
+
+        // Progress
+        bar(OFFSET_X).set(state.ids.progress_bg, ui);
+        Rectangle::fill_with([BAR_WIDTH, height(self.progress)], PROGRESS_COLOR)
+            .mid_bottom_of(state.ids.progress_bg)
+            .set(state.ids.progress, ui);
+
+        // Tension
+        bar(OFFSET_X + BAR_WIDTH + BAR_SPACING).set(state.ids.tension_bg, ui);
+        Rectangle::fill_with(
+            [BAR_WIDTH, height(self.tension)],
+            TENSION_COLOR.alpha(tension_alpha(self.tension, self.pulse)),
+        )
+        .mid_bottom_of(state.ids.tension_bg)
+        .set(state.ids.tension, ui);
+        Rectangle::fill_with([BAR_WIDTH + 4.0, 2.0], color::WHITE.alpha(0.8))
+            .mid_bottom_with_margin_on(state.ids.tension_bg, height(FishingState::MAX_REEL_TENSION))
+            .set(state.ids.max_tension, ui);
+
+        // Silhouette of the fish on the line
+        Image::new(animate_by_pulse(
+            &self
+                .item_imgs
+                .img_ids_or_not_found_img(ItemKey::Consumable(FISH_ICON.to_string())),
+            self.pulse,
+        ))
+        .w_h(28.0, 28.0)
+        .x_y_position_relative_to(
+            window,
+            position::Relative::Scalar(OFFSET_X + (BAR_WIDTH + BAR_SPACING) / 2.0),
+            position::Relative::Scalar(BAR_HEIGHT / 2.0 + 20.0),
+        )
+        .color(Some(color::BLACK.alpha(0.85)))
+        .set(state.ids.fish, ui);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tension_pulses_near_breaking() {
+        for pulse in [0.0, 0.3, 1.7] {
+            assert_eq!(tension_alpha(0.5, pulse), 1.0);
+        }
+        let alphas = [0.1, 0.2, 0.3].map(|pulse| tension_alpha(0.95, pulse));
+        assert!(alphas.iter().all(|a| (0.4..=1.0).contains(a)));
+        assert!(alphas.windows(2).any(|w| (w[0] - w[1]).abs() > 0.01));
+    }
+}
@@ -1,7 +1,7 @@
 use super::{
     img_ids::{Imgs, ImgsRot},
-    MapMarkers, QUALITY_COMMON, QUALITY_EPIC, QUALITY_HIGH, QUALITY_LOW, QUALITY_MODERATE, TEXT_BG,
-    TEXT_BLUE_COLOR, TEXT_COLOR, TEXT_GRAY_COLOR, TEXT_VELORITE, UI_HIGHLIGHT_0, UI_MAIN,
+    util, MapMarkers, QUALITY_COMMON, QUALITY_EPIC, QUALITY_HIGH, QUALITY_LOW, QUALITY_MODERATE,
+    TEXT_BG, TEXT_BLUE_COLOR, TEXT_COLOR, TEXT_GRAY_COLOR, TEXT_VELORITE, UI_HIGHLIGHT_0, UI_MAIN,
 };
 use crate::{
     game_input::GameInput,
@@ -873,6 +873,7 @@ impl<'a> Widget for Map<'a> {
                 SiteKind::Cave => i18n.get("hud.map.cave"),
                 SiteKind::Tree => i18n.get("hud.map.tree"),
                 SiteKind::Gnarling => i18n.get("hud.map.gnarling"),
+                SiteKind::Unknown => i18n.get("hud.map.unknown_site"),
             });
             let (difficulty, desc) = match &site.kind {
                 SiteKind::Town => (None, i18n.get("hud.map.town").to_string()),
@@ -895,59 +896,51 @@ impl<'a> Widget for Map<'a> {
                 SiteKind::Cave => (None, i18n.get("hud.map.cave").to_string()),
                 SiteKind::Tree => (None, i18n.get("hud.map.tree").to_string()),
                 SiteKind::Gnarling => (Some(0), i18n.get("hud.map.gnarling").to_string()),
+                SiteKind::Unknown => (None, i18n.get("hud.map.unknown_site").to_string()),
             };
             let desc = desc + &get_site_economy(site_rich);
-            let site_btn = Button::image(match &site.kind {
-                SiteKind::Town => self.imgs.mmap_site_town,
-                SiteKind::Castle => self.imgs.mmap_site_castle,
-                SiteKind::Cave => self.imgs.mmap_site_cave,
-                SiteKind::Tree => self.imgs.mmap_site_tree,
-                SiteKind::Gnarling => self.imgs.mmap_site_gnarling,
-                SiteKind::Dungeon { difficulty } => match difficulty {
-                    4 => self.imgs.mmap_site_minotaur,
-                    5 => self.imgs.mmap_site_mindflayer,
-                    _ => self.imgs.mmap_site_dungeon,
-                },
-            })
-            .x_y_position_relative_to(
-                state.ids.map_layers[0],
-                position::Relative::Scalar(rpos.x as f64),
-                position::Relative::Scalar(rpos.y as f64),
-            )
-            .w_h(rside as f64, rside as f64)
-            .hover_image(match &site.kind {
-                SiteKind::Town => self.imgs.mmap_site_town_hover,
-                SiteKind::Castle => self.imgs.mmap_site_castle_hover,
-                SiteKind::Cave => self.imgs.mmap_site_cave_hover,
-                SiteKind::Tree => self.imgs.mmap_site_tree_hover,
-                SiteKind::Gnarling => self.imgs.mmap_site_gnarling_hover,
-                SiteKind::Dungeon { difficulty } => match difficulty {
-                    4 => self.imgs.mmap_site_minotaur_hover,
-                    5 => self.imgs.mmap_site_mindflayer_hover,
-                    _ => self.imgs.mmap_site_dungeon_hover,
-                },
-            })
-            .image_color(UI_HIGHLIGHT_0.alpha(fade))
-            .with_tooltip(
-                self.tooltip_manager,
-                title,
-                &desc,
-                &site_tooltip,
-                match &site.kind {
-                    SiteKind::Town => TEXT_COLOR,
-                    SiteKind::Castle => TEXT_COLOR,
-                    SiteKind::Dungeon { .. } | SiteKind::Gnarling => match difficulty {
-                        Some(0) => QUALITY_LOW,
-                        Some(1) => QUALITY_COMMON,
-                        Some(2) => QUALITY_MODERATE,
-                        Some(3) => QUALITY_HIGH,
-                        Some(4 | 5) => QUALITY_EPIC,
-                        _ => TEXT_COLOR,
+            let site_btn = Button::image(util::site_icon(self.imgs, &site.kind))
+                .x_y_position_relative_to(
+                    state.ids.map_layers[0],
+                    position::Relative::Scalar(rpos.x as f64),
+                    position::Relative::Scalar(rpos.y as f64),
+                )
+                .w_h(rside as f64, rside as f64)
+                .hover_image(match &site.kind {
+                    SiteKind::Town => self.imgs.mmap_site_town_hover,
+                    SiteKind::Castle => self.imgs.mmap_site_castle_hover,
+                    SiteKind::Cave => self.imgs.mmap_site_cave_hover,
+                    SiteKind::Tree => self.imgs.mmap_site_tree_hover,
+                    SiteKind::Gnarling => self.imgs.mmap_site_gnarling_hover,
+                    SiteKind::Unknown => self.imgs.mmap_site_excl,
+                    SiteKind::Dungeon { difficulty } => match difficulty {
+                        4 => self.imgs.mmap_site_minotaur_hover,
+                        5 => self.imgs.mmap_site_mindflayer_hover,
+                        _ => self.imgs.mmap_site_dungeon_hover,
                     },
-                    SiteKind::Cave => TEXT_COLOR,
-                    SiteKind::Tree => TEXT_COLOR,
-                },
-            );
+                })
+                .image_color(UI_HIGHLIGHT_0.alpha(fade))
+                .with_tooltip(
+                    self.tooltip_manager,
+                    title,
+                    &desc,
+                    &site_tooltip,
+                    match &site.kind {
+                        SiteKind::Town => TEXT_COLOR,
+                        SiteKind::Castle => TEXT_COLOR,
+                        SiteKind::Dungeon { .. } | SiteKind::Gnarling => match difficulty {
+                            Some(0) => QUALITY_LOW,
+                            Some(1) => QUALITY_COMMON,
+                            Some(2) => QUALITY_MODERATE,
+                            Some(3) => QUALITY_HIGH,
+                            Some(4 | 5) => QUALITY_EPIC,
+                            _ => TEXT_COLOR,
+                        },
+                        SiteKind::Cave => TEXT_COLOR,
+                        SiteKind::Tree => TEXT_COLOR,
+                        SiteKind::Unknown => TEXT_COLOR,
+                    },
+                );
 
             handle_widget_mouse_events(
                 state.ids.mmap_site_icons[i],
@@ -964,6 +957,8 @@ impl<'a> Widget for Map<'a> {
                 SiteKind::Castle => show_castles,
                 SiteKind::Cave => show_caves,
                 SiteKind::Tree => show_trees,
+                // there is no filter for kinds we don't know about
+                SiteKind::Unknown => true,
             };
             if show_site {
                 let tooltip_visible = site_btn.set_ext(state.ids.mmap_site_icons[i], ui).1;
@@ -1036,6 +1031,7 @@ impl<'a> Widget for Map<'a> {
                             dif_img.set(state.ids.site_difs[i], ui)
                         }
                     },
+                    SiteKind::Unknown => dif_img.set(state.ids.site_difs[i], ui),
                 }
 
                 handle_widget_mouse_events(
@@ -1,6 +1,6 @@
 use super::{
     img_ids::{Imgs, ImgsRot},
-    MapMarkers, QUALITY_COMMON, QUALITY_DEBUG, QUALITY_EPIC, QUALITY_HIGH, QUALITY_LOW,
+    util, MapMarkers, QUALITY_COMMON, QUALITY_DEBUG, QUALITY_EPIC, QUALITY_HIGH, QUALITY_LOW,
     QUALITY_MODERATE, TEXT_COLOR, UI_HIGHLIGHT_0, UI_MAIN,
 };
 use crate::{
@@ -51,6 +51,8 @@ pub struct VoxelMinimap {
 }
 
 const VOXEL_MINIMAP_SIDELENGTH: u32 = 256;
+/// Number of out of view sites shown at the edge of the minimap
+const MAX_EDGE_SITES: usize = 3;
 
 impl VoxelMinimap {
     pub fn new(ui: &mut Ui) -> Self {
@@ -367,9 +369,11 @@ widget_ids! {
         mmap_west,
         mmap_site_icons_bgs[],
         mmap_site_icons[],
+        mmap_site_edge_markers[],
         member_indicators[],
         location_marker,
         location_marker_group[],
+        gravestone_markers[],
         voxel_minimap,
     }
 }
@@ -697,6 +701,7 @@ impl<'a> Widget for MiniMap<'a> {
                     SiteKind::Cave => None,
                     SiteKind::Tree => None,
                     SiteKind::Gnarling => Some(0),
+                    SiteKind::Unknown => None,
                 };
 
                 Image::new(match &site.kind {
@@ -706,6 +711,7 @@ impl<'a> Widget for MiniMap<'a> {
                     SiteKind::Cave => self.imgs.mmap_site_cave_bg,
                     SiteKind::Tree => self.imgs.mmap_site_tree,
                     SiteKind::Gnarling => self.imgs.mmap_site_gnarling_bg,
+                    SiteKind::Unknown => self.imgs.mmap_site_excl,
                 })
                 .x_y_position_relative_to(
                     state.ids.map_layers[0],
@@ -730,6 +736,7 @@ impl<'a> Widget for MiniMap<'a> {
                     SiteKind::Cave => self.imgs.mmap_site_cave,
                     SiteKind::Tree => self.imgs.mmap_site_tree,
                     SiteKind::Gnarling => self.imgs.mmap_site_gnarling,
+                    SiteKind::Unknown => self.imgs.mmap_site_excl,
                 })
                 .middle_of(state.ids.mmap_site_icons_bgs[i])
                 .w_h(20.0, 20.0)
@@ -737,6 +744,44 @@ impl<'a> Widget for MiniMap<'a> {
                 .set(state.ids.mmap_site_icons[i], ui);
             }
 
+            // Pin the nearest sites that are out of view to the edge of the minimap
+            let mut hidden_sites = self
+                .client
+                .sites()
+                .values()
+                .map(|site_rich| &site_rich.site)
+                .filter(|site| wpos_to_rpos(site.wpos.map(|e| e as f32), false).is_none())
+                .collect::<Vec<_>>();
+            hidden_sites.sort_by_key(|site| {
+                (site.wpos - player_pos.xy().map(|e| e as i32))
+                    .map(i64::from)
+                    .magnitude_squared()
+            });
+            hidden_sites.truncate(MAX_EDGE_SITES);
+            if state.ids.mmap_site_edge_markers.len() < hidden_sites.len() {
+                state.update(|state| {
+                    state
+                        .ids
+                        .mmap_site_edge_markers
+                        .resize(hidden_sites.len(), &mut ui.widget_id_generator())
+                });
+            }
+            for (i, site) in hidden_sites.iter().enumerate() {
+                if let Some(rpos) = wpos_to_rpos(site.wpos.map(|e| e as f32), true) {
+                    // keep the marker inside of the frame
+                    let rpos = rpos - rpos.map(|e| e.signum() * 6.0);
+                    Image::new(util::site_icon(self.imgs, &site.kind))
+                        .x_y_position_relative_to(
+                            state.ids.map_layers[0],
+                            position::Relative::Scalar(rpos.x as f64),
+                            position::Relative::Scalar(rpos.y as f64),
+                        )
+                        .w_h(12.0, 12.0)
+                        .color(Some(UI_HIGHLIGHT_0.alpha(0.7)))
+                        .set(state.ids.mmap_site_edge_markers[i], ui);
+                }
+            }
+
             // Group member indicators
             let client_state = self.client.state();
             let member_pos = client_state.ecs().read_storage::<common::comp::Pos>();
@@ -820,6 +865,29 @@ impl<'a> Widget for MiniMap<'a> {
                 }
             }
 
+            // Gravestones holding the items the player lost
+            let gravestones = self.client.gravestones();
+            if state.ids.gravestone_markers.len() < gravestones.len() {
+                state.update(|s| {
+                    s.ids
+                        .gravestone_markers
+                        .resize(gravestones.len(), &mut ui.widget_id_generator())
+                })
+            };
+            for (i, gravestone) in gravestones.iter().enumerate() {
+                if let Some(rpos) = wpos_to_rpos(gravestone.xy(), true) {
+                    Image::new(self.imgs.skull)
+                        .x_y_position_relative_to(
+                            state.ids.map_layers[0],
+                            position::Relative::Scalar(rpos.x as f64),
+                            position::Relative::Scalar(rpos.y as f64),
+                        )
+                        .w_h(16.0, 16.0)
+                        .parent(ui.window)
+                        .set(state.ids.gravestone_markers[i], ui)
+                }
+            }
+
             // Location marker
             if let Some(rpos) = self
                 .location_markers
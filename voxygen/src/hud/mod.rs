@@ -3,9 +3,12 @@ mod bag;
 mod buffs;
 mod buttons;
 mod chat;
+mod compass;
 mod crafting;
+mod crosshair;
 mod diary;
 mod esc_menu;
+mod fishing_meter;
 mod group;
 mod hotbar;
 pub mod img_ids;
@@ -35,9 +38,12 @@ use buffs::BuffsBar;
 use buttons::Buttons;
 use chat::Chat;
 use chrono::NaiveTime;
+use compass::Compass;
 use crafting::Crafting;
+use crosshair::{Crosshair, CrosshairTarget, HitFlash};
 use diary::{Diary, SelectedSkillTree};
 use esc_menu::EscMenu;
+use fishing_meter::FishingMeter;
 use group::Group;
 use img_ids::Imgs;
 use item_imgs::ItemImgs;
@@ -49,7 +55,7 @@ use prompt_dialog::PromptDialog;
 use serde::{Deserialize, Serialize};
 use settings_window::{SettingsTab, SettingsWindow};
 use skillbar::Skillbar;
-use social::Social;
+use social::{Social, SocialTab};
 use trade::Trade;
 
 use crate::{
@@ -68,14 +74,14 @@ use crate::{
     settings::chat::ChatFilter,
     ui::{
         self, fonts::Fonts, img_ids::Rotations, slot, slot::SlotKey, Graphic, Ingameable,
-        ScaleMode, Ui,
+        ScaleMode, Ui, WidgetQuery,
     },
     window::Event as WinEvent,
     GlobalState,
 };
 use client::Client;
 use common::{
-    combat,
+    combat::{self, DamageKind},
     comp::{
         self,
         ability::AuxiliaryAbility,
@@ -206,8 +212,7 @@ const SPEECH_BUBBLE_RANGE: f32 = NAMETAG_RANGE;
 widget_ids! {
     struct Ids {
         // Crosshair
-        crosshair_inner,
-        crosshair_outer,
+        crosshair,
 
         // SCT
         player_scts[],
@@ -226,6 +231,7 @@ widget_ids! {
         sct_lvl,
         hurt_bg,
         death_bg,
+        time_skip_bg,
         sct_bgs[],
         scts[],
 
@@ -239,6 +245,7 @@ widget_ids! {
         debug_bg,
         fps_counter,
         ping,
+        connection_stats,
         coordinates,
         velocity,
         glide_ratio,
@@ -255,6 +262,7 @@ widget_ids! {
         current_biome,
         current_site,
         graphics_backend,
+        graphics_adapter,
         gpu_timings[],
 
         // Game Version
@@ -285,6 +293,8 @@ widget_ids! {
         character_window,
         popup,
         minimap,
+        compass,
+        fishing_meter,
         prompt_dialog,
         bag,
         trade,
@@ -534,6 +544,8 @@ pub enum Event {
         salvage_pos: Vec3<i32>,
     },
     InviteMember(Uid),
+    AddFriend(String),
+    RemoveFriend(String),
     AcceptInvite,
     DeclineInvite,
     KickMember(Uid),
@@ -600,6 +612,15 @@ pub enum BuffPosition {
     Bar,
 }
 
+/// Precision of the coordinates shown below the compass
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CoordinatesDisplay {
+    Chunk,
+    Block,
+    #[serde(other)]
+    Off,
+}
+
 #[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub enum PressBehavior {
     Hold = 1,
@@ -696,6 +717,7 @@ pub struct Show {
     crafting_search_key: Option<String>,
     craft_sprite: Option<(Vec3<i32>, SpriteKind)>,
     social_search_key: Option<String>,
+    social_tab: SocialTab,
     want_grab: bool,
     stats: bool,
     free_look: bool,
@@ -904,6 +926,8 @@ impl Show {
         self.social_search_key = search_key;
     }
 
+    fn selected_social_tab(&mut self, tab: SocialTab) { self.social_tab = tab; }
+
     /// If all of the menus are closed, adjusts coordinates of cursor to center
     /// of screen
     fn toggle_cursor_on_menu_close(&self, global_state: &mut GlobalState, ui: &mut Ui) {
@@ -978,7 +1002,9 @@ impl PromptDialogSettings {
 
 pub struct Floaters {
     pub exp_floaters: Vec<ExpFloater>,
-    pub skill_point_displays: Vec<SkillPointGain>,
+    /// Banners of skill points earned by the player, shown one after the
+    /// other
+    pub skill_point_displays: VecDeque<SkillPointGain>,
     pub combo_floaters: VecDeque<ComboFloater>,
     pub block_floaters: Vec<BlockFloater>,
 }
@@ -1011,6 +1037,7 @@ pub struct Hud {
     hotbar: hotbar::State,
     events: Vec<Event>,
     crosshair_opacity: f32,
+    hit_flash: HitFlash,
     floaters: Floaters,
     voxel_minimap: VoxelMinimap,
     map_drag: Vec2<f64>,
@@ -1109,6 +1136,7 @@ impl Hud {
                 crafting_search_key: None,
                 craft_sprite: None,
                 social_search_key: None,
+                social_tab: SocialTab::Online,
                 want_grab: true,
                 ingame: true,
                 stats: false,
@@ -1131,9 +1159,10 @@ impl Hud {
             hotbar: hotbar_state,
             events: Vec::new(),
             crosshair_opacity: 0.0,
+            hit_flash: HitFlash::default(),
             floaters: Floaters {
                 exp_floaters: Vec::new(),
-                skill_point_displays: Vec::new(),
+                skill_point_displays: VecDeque::new(),
                 combo_floaters: VecDeque::new(),
                 block_floaters: Vec::new(),
             },
@@ -1279,6 +1308,16 @@ impl Hud {
                         .graphics_for(ui_widgets.window)
                         .color(Some(Color::Rgba(0.0, 0.0, 0.0, 1.0)))
                         .set(self.ids.death_bg, ui_widgets);
+                }
+                // Fade out while the night is skipped
+                let time_skip_fade = client.time_skip_fade();
+                if time_skip_fade > 0.01 {
+                    Image::new(self.imgs.death_bg)
+                        .wh_of(ui_widgets.window)
+                        .middle_of(ui_widgets.window)
+                        .graphics_for(ui_widgets.window)
+                        .color(Some(Color::Rgba(0.0, 0.0, 0.0, time_skip_fade)))
+                        .set(self.ids.time_skip_bg, ui_widgets);
                 } // Crosshair
                 let show_crosshair = (info.is_aiming || info.is_first_person) && !health.is_dead;
                 self.crosshair_opacity = Lerp::lerp(
@@ -1287,29 +1326,37 @@ impl Hud {
                     5.0 * dt.as_secs_f32(),
                 );
 
+                self.hit_flash.maintain(dt.as_secs_f32());
+
                 if !self.show.help {
-                    Image::new(
-                        // TODO: Do we want to match on this every frame?
-                        match global_state.settings.interface.crosshair_type {
-                            CrosshairType::Round => self.imgs.crosshair_outer_round,
-                            CrosshairType::RoundEdges => self.imgs.crosshair_outer_round_edges,
-                            CrosshairType::Edges => self.imgs.crosshair_outer_edges,
-                        },
+                    let speed = ecs
+                        .read_storage::<comp::Vel>()
+                        .get(me)
+                        .map_or(0.0, |vel| vel.0.magnitude());
+                    let is_enemy = info.target_entity.map_or(false, |target| {
+                        matches!(
+                            (alignments.get(target), alignments.get(me)),
+                            (Some(target), Some(me)) if target.hostile_towards(*me)
+                        ) && healths.get(target).map_or(false, |health| !health.is_dead)
+                    });
+                    let target = if is_enemy {
+                        CrosshairTarget::Enemy
+                    } else if interactable.is_some() {
+                        CrosshairTarget::Interactable
+                    } else {
+                        CrosshairTarget::Nothing
+                    };
+                    Crosshair::new(
+                        &self.imgs,
+                        global_state.settings.interface.crosshair_type,
+                        self.crosshair_opacity * global_state.settings.interface.crosshair_opacity,
+                        speed,
+                        &self.hit_flash,
+                        target,
                     )
                     .w_h(21.0 * 1.5, 21.0 * 1.5)
                     .middle_of(ui_widgets.window)
-                    .color(Some(Color::Rgba(
-                        1.0,
-                        1.0,
-                        1.0,
-                        self.crosshair_opacity * global_state.settings.interface.crosshair_opacity,
-                    )))
-                    .set(self.ids.crosshair_outer, ui_widgets);
-                    Image::new(self.imgs.crosshair_inner)
-                        .w_h(21.0 * 2.0, 21.0 * 2.0)
-                        .middle_of(self.ids.crosshair_outer)
-                        .color(Some(Color::Rgba(1.0, 1.0, 1.0, 0.6)))
-                        .set(self.ids.crosshair_inner, ui_widgets);
+                    .set(self.ids.crosshair, ui_widgets);
                 }
             }
 
@@ -1557,21 +1604,19 @@ impl Hud {
                         }
                     }
                 }
-                // Skill points
-                self.floaters
-                    .skill_point_displays
-                    .iter_mut()
-                    .for_each(|f| f.timer -= dt.as_secs_f32());
-                self.floaters
-                    .skill_point_displays
-                    .retain(|d| d.timer > 0_f32);
+                // Skill points, only the banner in front of the queue counts down so that
+                // points earned in quick succession don't overlap
                 if let Some(uid) = uids.get(me) {
-                    if let Some(display) = self
-                        .floaters
+                    self.floaters
                         .skill_point_displays
-                        .iter_mut()
-                        .find(|d| d.owner == *uid)
-                    {
+                        .retain(|d| d.owner == *uid);
+                    if let Some(display) = self.floaters.skill_point_displays.front_mut() {
+                        display.timer -= dt.as_secs_f32();
+                        if display.timer <= 0.0 {
+                            self.floaters.skill_point_displays.pop_front();
+                        }
+                    }
+                    if let Some(display) = self.floaters.skill_point_displays.front() {
                         let fade = if display.timer < 3.0 {
                             display.timer as f32 * 0.33
                         } else if display.timer < 2.0 {
@@ -1838,6 +1883,9 @@ impl Hud {
                             Interaction::Mine => {
                                 vec![(GameInput::Primary, i18n.get("hud.mine").to_string())]
                             },
+                            Interaction::Sleep => {
+                                vec![(GameInput::Interact, i18n.get("hud.sleep").to_string())]
+                            },
                         },
                     )
                     .set(overitem_id, ui_widgets);
@@ -1853,7 +1901,12 @@ impl Hud {
                         overitem_properties,
                         self.pulse,
                         &global_state.window.key_layout,
-                        vec![(GameInput::Interact, i18n.get("hud.use").to_string())],
+                        match interaction {
+                            Interaction::Sleep => {
+                                vec![(GameInput::Interact, i18n.get("hud.sleep").to_string())]
+                            },
+                            _ => vec![(GameInput::Interact, i18n.get("hud.use").to_string())],
+                        },
                     )
                     .x_y(0.0, 100.0)
                     .position_ingame(over_pos)
@@ -2114,6 +2167,14 @@ impl Hud {
                     let font_col = |font_size: u32| {
                         DAMAGE_COLORS[(font_size.saturating_sub(36) / 5).min(5) as usize]
                     };
+                    // Elemental damage keeps its tint regardless of the amount
+                    const FIRE: Rgb<f32> = Rgb::new(1.0, 0.45, 0.1);
+                    const POISON: Rgb<f32> = Rgb::new(0.55, 0.9, 0.2);
+                    let kind_col = |kind: Option<DamageKind>| match kind {
+                        Some(DamageKind::Fire) => Some(FIRE),
+                        Some(DamageKind::Poison) => Some(POISON),
+                        _ => None,
+                    };
 
                     if global_state.settings.interface.sct_damage_batch {
                         let number_speed = 50.0; // Damage number speed
@@ -2145,7 +2206,12 @@ impl Hud {
                             } else {
                                 0
                             };
-                        let font_col = font_col(font_size);
+                        // Only tint the total if all of it is of the same kind
+                        let batch_kind = floaters
+                            .first()
+                            .and_then(|f| f.kind)
+                            .filter(|kind| floaters.iter().all(|f| f.kind == Some(*kind)));
+                        let font_col = kind_col(batch_kind).unwrap_or_else(|| font_col(font_size));
                         // Timer sets the widget offset
                         let y = (timer as f64 / crate::ecs::sys::floater::HP_SHOWTIME as f64
                             * number_speed)
@@ -2213,7 +2279,8 @@ impl Hud {
                                 } else {
                                     0
                                 };
-                            let font_col = font_col(font_size);
+                            let font_col =
+                                kind_col(floater.kind).unwrap_or_else(|| font_col(font_size));
                             // Timer sets the widget offset
                             let y = (floater.timer as f64
                                 / crate::ecs::sys::floater::HP_SHOWTIME as f64
@@ -2312,6 +2379,20 @@ impl Hud {
                 .font_id(self.fonts.cyri.conrod_id)
                 .font_size(self.fonts.cyri.scale(14))
                 .set(self.ids.ping, ui_widgets);
+            // Round trip time and jitter
+            let connection_stats_text = match global_state.connection_stats {
+                Some(stats) => format!(
+                    "RTT: {:.0}ms, Jitter: {:.1}ms",
+                    stats.rtt_ms, stats.jitter_ms
+                ),
+                None => "RTT: -".to_owned(),
+            };
+            Text::new(&connection_stats_text)
+                .color(TEXT_COLOR)
+                .down_from(self.ids.ping, V_PAD)
+                .font_id(self.fonts.cyri.conrod_id)
+                .font_size(self.fonts.cyri.scale(14))
+                .set(self.ids.connection_stats, ui_widgets);
             // Player's position
             let coordinates_text = match debug_info.coordinates {
                 Some(coordinates) => format!(
@@ -2322,7 +2403,7 @@ impl Hud {
             };
             Text::new(&coordinates_text)
                 .color(TEXT_COLOR)
-                .down_from(self.ids.ping, V_PAD)
+                .down_from(self.ids.connection_stats, V_PAD)
                 .font_id(self.fonts.cyri.conrod_id)
                 .font_size(self.fonts.cyri.scale(14))
                 .set(self.ids.coordinates, ui_widgets);
@@ -2500,8 +2581,13 @@ impl Hud {
 
             // Graphics backend
             Text::new(&format!(
-                "Graphics backend: {}",
+                "Graphics backend: {}{}",
                 global_state.window.renderer().graphics_backend(),
+                if global_state.battery_saver.active() {
+                    " (battery saver)"
+                } else {
+                    ""
+                },
             ))
             .color(TEXT_COLOR)
             .down_from(self.ids.num_particles, V_PAD)
@@ -2509,6 +2595,18 @@ impl Hud {
             .font_size(self.fonts.cyri.scale(14))
             .set(self.ids.graphics_backend, ui_widgets);
 
+            // Graphics adapter
+            let adapter_info = global_state.window.renderer().adapter_info();
+            Text::new(&format!(
+                "Graphics adapter: {} ({:?})",
+                adapter_info.name, adapter_info.device_type,
+            ))
+            .color(TEXT_COLOR)
+            .down_from(self.ids.graphics_backend, V_PAD)
+            .font_id(self.fonts.cyri.conrod_id)
+            .font_size(self.fonts.cyri.scale(14))
+            .set(self.ids.graphics_adapter, ui_widgets);
+
             let gpu_timings = global_state.window.renderer().timings();
             let mut timings_height = 0.0;
 
@@ -2549,7 +2647,7 @@ impl Hud {
 
             // Set debug box dimensions, only timings height is dynamic
             // TODO: Make the background box size fully dynamic
-            let debug_bg_size = [320.0, 370.0 + timings_height];
+            let debug_bg_size = [320.0, 390.0 + timings_height];
 
             Rectangle::fill(debug_bg_size)
                 .rgba(0.0, 0.0, 0.0, global_state.settings.chat.chat_opacity)
@@ -2658,6 +2756,22 @@ impl Hud {
         )
         .set(self.ids.popup, ui_widgets);
 
+        // Compass
+        Compass::new(
+            client,
+            &self.imgs,
+            &self.fonts,
+            camera.get_orientation(),
+            global_state.settings.interface.coordinates,
+        )
+        .set(self.ids.compass, ui_widgets);
+
+        // Fishing minigame
+        if let Some(fishing) = client.fishing() {
+            FishingMeter::new(&self.item_imgs, fishing, self.pulse)
+                .set(self.ids.fishing_meter, ui_widgets);
+        }
+
         // MiniMap
         for event in MiniMap::new(
             client,
@@ -2717,6 +2831,7 @@ impl Hud {
         let energies = ecs.read_storage::<comp::Energy>();
         let skillsets = ecs.read_storage::<comp::SkillSet>();
         let active_abilities = ecs.read_storage::<comp::ActiveAbilities>();
+        let cooldowns = ecs.read_storage::<comp::Cooldowns>();
         let character_states = ecs.read_storage::<comp::CharacterState>();
         let controllers = ecs.read_storage::<comp::Controller>();
         let bodies = ecs.read_storage::<comp::Body>();
@@ -2743,7 +2858,7 @@ impl Hud {
             Some(energy),
             Some(skillset),
             Some(body),
-            Some(_character_state),
+            Some(character_state),
             Some(_controller),
         ) = (
             healths.get(entity),
@@ -2754,6 +2869,12 @@ impl Hud {
             character_states.get(entity),
             controllers.get(entity).map(|c| &c.inputs),
         ) {
+            let combo_stage = match character_state {
+                comp::CharacterState::ComboMelee(c) if c.static_data.num_stages > 1 => {
+                    Some((c.stage, c.static_data.num_stages))
+                },
+                _ => None,
+            };
             Skillbar::new(
                 client,
                 global_state,
@@ -2766,6 +2887,7 @@ impl Hud {
                 energy,
                 skillset,
                 active_abilities.get(entity),
+                cooldowns.get(entity),
                 body,
                 //&character_state,
                 self.pulse,
@@ -2777,6 +2899,7 @@ impl Hud {
                 i18n,
                 &msm,
                 combo,
+                combo_stage,
             )
             .set(self.ids.skillbar, ui_widgets);
         }
@@ -2974,7 +3097,7 @@ impl Hud {
             for event in Chat::new(
                 &mut self.new_messages,
                 client,
-                global_state,
+                &global_state.settings.chat,
                 self.pulse,
                 &self.imgs,
                 &self.fonts,
@@ -3008,6 +3131,9 @@ impl Hud {
                         self.show.settings_tab = SettingsTab::Chat;
                         self.show.settings(true);
                     },
+                    chat::Event::PingPosition(pos) => {
+                        events.push(Event::MapMarkerEvent(MapMarkerChange::Update(pos)));
+                    },
                 }
             }
         }
@@ -3119,6 +3245,18 @@ impl Hud {
                         social::Event::SearchPlayers(search_key) => {
                             self.show.search_social_players(search_key)
                         },
+                        social::Event::ChangeTab(tab) => self.show.selected_social_tab(tab),
+                        social::Event::AddFriend(name) => events.push(Event::AddFriend(name)),
+                        social::Event::RemoveFriend(name) => events.push(Event::RemoveFriend(name)),
+                        social::Event::Whisper(name) => {
+                            let input = format!("/tell {} ", name);
+                            self.force_chat_cursor = Some(Index {
+                                line: 0,
+                                char: input.chars().count(),
+                            });
+                            self.force_chat_input = Some(input);
+                            self.to_focus = Some(Some(self.ids.chat));
+                        },
                     }
                 }
             }
@@ -3903,18 +4041,11 @@ impl Hud {
     }
 
     /// Checks if a TextEdit widget has the keyboard captured.
-    fn typing(&self) -> bool { Hud::is_captured::<widget::TextEdit>(&self.ui.ui) }
+    fn typing(&self) -> bool { self.ui.query().is_captured::<widget::TextEdit>() }
 
     /// Checks if a widget of type `W` has captured the keyboard
     fn is_captured<W: Widget>(ui: &conrod_core::Ui) -> bool {
-        if let Some(id) = ui.global_input().current.widget_capturing_keyboard {
-            ui.widget_graph()
-                .widget(id)
-                .filter(|c| c.type_id == std::any::TypeId::of::<<W as Widget>::State>())
-                .is_some()
-        } else {
-            false
-        }
+        WidgetQuery::new(ui).is_captured::<W>()
     }
 
     pub fn handle_event(
@@ -4210,7 +4341,7 @@ impl Hud {
     ) -> Vec<Event> {
         span!(_guard, "maintain", "Hud::maintain");
         // conrod eats tabs. Un-eat a tabstop so tab completion can work
-        if self.ui.ui.global_input().events().any(|event| {
+        if self.ui.layout.ui.global_input().events().any(|event| {
             use conrod_core::{event, input};
             matches!(
                 event,
@@ -4223,6 +4354,7 @@ impl Hud {
             )
         }) {
             self.ui
+                .layout
                 .ui
                 .handle_event(conrod_core::event::Input::Text("\t".to_string()));
         }
@@ -4292,7 +4424,7 @@ impl Hud {
 
     pub fn camera_clamp(&mut self, camera_clamp: bool) { self.show.camera_clamp = camera_clamp; }
 
-    pub fn handle_outcome(&mut self, outcome: &Outcome) {
+    pub fn handle_outcome(&mut self, outcome: &Outcome, player: Option<Uid>) {
         match outcome {
             Outcome::ExpChange { uid, exp, xp_pools } => {
                 self.floaters.exp_floaters.push(ExpFloater {
@@ -4308,12 +4440,15 @@ impl Hud {
                 skill_tree,
                 total_points,
                 ..
-            } => self.floaters.skill_point_displays.push(SkillPointGain {
-                owner: *uid,
-                skill_tree: *skill_tree,
-                total_points: *total_points,
-                timer: 5.0,
-            }),
+            } => self
+                .floaters
+                .skill_point_displays
+                .push_back(SkillPointGain {
+                    owner: *uid,
+                    skill_tree: *skill_tree,
+                    total_points: *total_points,
+                    timer: 5.0,
+                }),
             Outcome::ComboChange { uid, combo } => {
                 self.floaters.combo_floaters.push_front(ComboFloater {
                     owner: *uid,
@@ -4321,6 +4456,12 @@ impl Hud {
                     timer: comp::combo::COMBO_DECAY_START,
                 })
             },
+            Outcome::Damage { by, .. } if by.is_some() && *by == player => {
+                self.hit_flash.hit(false)
+            },
+            Outcome::CriticalHit { by, .. } if by.is_some() && *by == player => {
+                self.hit_flash.hit(true)
+            },
             Outcome::Block { uid, parry, .. } if *parry => {
                 self.floaters.block_floaters.push(BlockFloater {
                     owner: *uid,
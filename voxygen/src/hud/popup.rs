@@ -131,6 +131,32 @@ impl<'a> Widget for Popup<'a> {
                         s.infos.push_back(text.to_string());
                     });
                 },
+                Notification::Caught(name, amount) => {
+                    state.update(|s| {
+                        if s.infos.is_empty() {
+                            s.last_info_update = Instant::now();
+                        }
+                        let item = if *amount > 1 {
+                            format!("{} x{}", name, amount)
+                        } else {
+                            name.clone()
+                        };
+                        let text = self.i18n.get("hud.fishing.caught").replace("{item}", &item);
+                        s.infos.push_back(text);
+                    });
+                },
+                Notification::FriendOnline(name) => {
+                    state.update(|s| {
+                        if s.infos.is_empty() {
+                            s.last_info_update = Instant::now();
+                        }
+                        let text = self
+                            .i18n
+                            .get("hud.social.friend_online")
+                            .replace("{name}", name);
+                        s.infos.push_back(text);
+                    });
+                },
             }
         }
 
@@ -4,8 +4,8 @@ use crate::{
     game_input::GameInput,
     hud::{img_ids::Imgs, ERROR_COLOR, TEXT_BIND_CONFLICT_COLOR, TEXT_COLOR},
     session::settings_change::{Control as ControlChange, Control::*},
+    settings::ControlSettings,
     ui::fonts::Fonts,
-    GlobalState,
 };
 use conrod_core::{
     color,
@@ -14,6 +14,7 @@ use conrod_core::{
     widget_ids, Borderable, Colorable, Labelable, Positionable, Sizeable, Widget, WidgetCommon,
 };
 use i18n::Localization;
+use keyboard_keynames::key_layout::KeyLayout;
 use strum::IntoEnumIterator;
 
 widget_ids! {
@@ -31,7 +32,12 @@ widget_ids! {
 
 #[derive(WidgetCommon)]
 pub struct Controls<'a> {
-    global_state: &'a GlobalState,
+    controls: &'a ControlSettings,
+    key_layout: &'a Option<KeyLayout>,
+    /// The input whose new binding the window is waiting for
+    remapping: Option<GameInput>,
+    /// Whether clicking a binding remaps it instead of removing it
+    keybinding_mode: bool,
     imgs: &'a Imgs,
     fonts: &'a Fonts,
     localized_strings: &'a Localization,
@@ -40,13 +46,19 @@ pub struct Controls<'a> {
 }
 impl<'a> Controls<'a> {
     pub fn new(
-        global_state: &'a GlobalState,
+        controls: &'a ControlSettings,
+        key_layout: &'a Option<KeyLayout>,
+        remapping: Option<GameInput>,
+        keybinding_mode: bool,
         imgs: &'a Imgs,
         fonts: &'a Fonts,
         localized_strings: &'a Localization,
     ) -> Self {
         Self {
-            global_state,
+            controls,
+            key_layout,
+            remapping,
+            keybinding_mode,
             imgs,
             fonts,
             localized_strings,
@@ -77,7 +89,7 @@ impl<'a> Widget for Controls<'a> {
         let widget::UpdateArgs { state, ui, .. } = args;
 
         let mut events = Vec::new();
-        let key_layout = &self.global_state.window.key_layout;
+        let key_layout = self.key_layout;
 
         Rectangle::fill_with(args.rect.dim(), color::TRANSPARENT)
             .xy(args.rect.xy())
@@ -99,7 +111,7 @@ impl<'a> Widget for Controls<'a> {
         let mut keybindings_vec: Vec<GameInput> = GameInput::iter().collect();
         keybindings_vec.sort();
 
-        let controls = &self.global_state.settings.controls;
+        let controls = self.controls;
         if keybindings_vec.len() > state.ids.controls_texts.len()
             || keybindings_vec.len() > state.ids.controls_buttons.len()
         {
@@ -121,36 +133,35 @@ impl<'a> Widget for Controls<'a> {
                 .iter()
                 .zip(state.ids.controls_buttons.iter()),
         ) {
-            let (key_string, key_color) =
-                if self.global_state.window.remapping_keybindings == Some(game_input) {
-                    (
-                        self.localized_strings
-                            .get("hud.settings.awaitingkey")
-                            .to_owned(),
-                        TEXT_COLOR,
-                    )
-                } else if let Some(key) = controls.get_binding(game_input) {
-                    (
-                        format!(
-                            "{} {}",
-                            key.display_string(key_layout),
-                            key.display_shortened(key_layout)
-                                .map_or("".to_owned(), |short| format!("({})", short))
-                        ),
-                        if controls.has_conflicting_bindings(key) {
-                            TEXT_BIND_CONFLICT_COLOR
-                        } else {
-                            TEXT_COLOR
-                        },
-                    )
-                } else {
-                    (
-                        self.localized_strings
-                            .get("hud.settings.unbound")
-                            .to_owned(),
-                        ERROR_COLOR,
-                    )
-                };
+            let (key_string, key_color) = if self.remapping == Some(game_input) {
+                (
+                    self.localized_strings
+                        .get("hud.settings.awaitingkey")
+                        .to_owned(),
+                    TEXT_COLOR,
+                )
+            } else if let Some(key) = controls.get_binding(game_input) {
+                (
+                    format!(
+                        "{} {}",
+                        key.display_string(key_layout),
+                        key.display_shortened(key_layout)
+                            .map_or("".to_owned(), |short| format!("({})", short))
+                    ),
+                    if controls.has_conflicting_bindings(key) {
+                        TEXT_BIND_CONFLICT_COLOR
+                    } else {
+                        TEXT_COLOR
+                    },
+                )
+            } else {
+                (
+                    self.localized_strings
+                        .get("hud.settings.unbound")
+                        .to_owned(),
+                    ERROR_COLOR,
+                )
+            };
             let loc_key = self
                 .localized_strings
                 .get(game_input.get_localization_key());
@@ -179,7 +190,7 @@ impl<'a> Widget for Controls<'a> {
                 .set(button_id, ui)
                 .was_clicked()
             {
-                if self.global_state.window.keybinding_mode {
+                if self.keybinding_mode {
                     events.push(ChangeBinding(game_input));
                 } else {
                     events.push(RemoveBinding(game_input));
@@ -221,7 +232,7 @@ impl<'a> Widget for Controls<'a> {
             .unwrap_or(0.0);
 
         let toggle_widget = Button::new()
-            .label(if self.global_state.window.keybinding_mode {
+            .label(if self.keybinding_mode {
                 "remap"
             } else {
                 "clear"
@@ -251,3 +262,76 @@ impl<'a> Widget for Controls<'a> {
         events
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ui::headless::HeadlessUi, window::KeyMouse};
+    use conrod_core::widget::button;
+    use i18n::LocalizationHandle;
+    use vek::*;
+    use winit::event::VirtualKeyCode;
+
+    fn set_controls(
+        ui: &mut HeadlessUi,
+        id: widget::Id,
+        controls: &ControlSettings,
+        remapping: Option<GameInput>,
+        imgs: &Imgs,
+        fonts: &Fonts,
+        i18n: &Localization,
+    ) -> Vec<ControlChange> {
+        ui.frame(|ui| {
+            Controls::new(controls, &None, remapping, true, imgs, fonts, i18n)
+                .top_left_with_margins_on(ui.window, 0.0, 0.0)
+                .w_h(814.0, 834.0)
+                .set(id, ui)
+        })
+    }
+
+    #[test]
+    fn remapping_a_binding() {
+        let mut ui = HeadlessUi::new(Vec2::new(1920, 1080));
+        let i18n = LocalizationHandle::load_expect("en").read();
+        let imgs = Imgs::load(&mut ui).unwrap();
+        let fonts = Fonts::load(i18n.fonts(), &mut ui).unwrap();
+        let id = ui.new_id();
+        let mut controls = ControlSettings::default();
+        let awaiting_key = i18n.get("hud.settings.awaitingkey");
+
+        let input = GameInput::iter().min().unwrap();
+        assert!(set_controls(&mut ui, id, &controls, None, &imgs, &fonts, &i18n).is_empty());
+
+        // The binding button is in the same row as the name of the input
+        let query = ui.query();
+        let name = query
+            .rect(
+                query
+                    .find_text(i18n.get(input.get_localization_key()))
+                    .unwrap(),
+            )
+            .unwrap();
+        let button = query
+            .of_type::<Button<button::Flat>>()
+            .into_iter()
+            .find(|button| {
+                let rect = query.rect(*button).unwrap();
+                rect.w() == 150.0 && (rect.y() - name.y()).abs() < rect.h() / 2.0
+            })
+            .unwrap();
+        ui.click_widget(button);
+        let changes = set_controls(&mut ui, id, &controls, None, &imgs, &fonts, &i18n);
+        assert!(matches!(changes.as_slice(), [ChangeBinding(i)] if *i == input));
+
+        // The window is now waiting for the new key
+        set_controls(&mut ui, id, &controls, Some(input), &imgs, &fonts, &i18n);
+        let query = ui.query();
+        let awaiting = query.rect(query.find_text(awaiting_key).unwrap()).unwrap();
+        assert!(query.rect(button).unwrap().is_over(awaiting.xy()));
+
+        // Until the key was pressed and the binding changed
+        controls.modify_binding(input, KeyMouse::Key(VirtualKeyCode::F9));
+        set_controls(&mut ui, id, &controls, None, &imgs, &fonts, &i18n);
+        assert!(ui.query().find_text(awaiting_key).is_none());
+    }
+}
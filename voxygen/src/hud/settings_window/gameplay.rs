@@ -50,6 +50,8 @@ widget_ids! {
         stop_auto_walk_on_input_label,
         auto_camera_button,
         auto_camera_label,
+        trajectory_preview_button,
+        trajectory_preview_label,
     }
 }
 
@@ -523,6 +525,35 @@ impl<'a> Widget for Gameplay<'a> {
             .color(TEXT_COLOR)
             .set(state.ids.auto_camera_label, ui);
 
+        // Trajectory preview toggle
+        let trajectory_preview_toggle = ToggleButton::new(
+            self.global_state.settings.gameplay.trajectory_preview,
+            self.imgs.checkbox,
+            self.imgs.checkbox_checked,
+        )
+        .w_h(18.0, 18.0)
+        .down_from(state.ids.auto_camera_button, 8.0)
+        .hover_images(self.imgs.checkbox_mo, self.imgs.checkbox_checked_mo)
+        .press_images(self.imgs.checkbox_press, self.imgs.checkbox_checked)
+        .set(state.ids.trajectory_preview_button, ui);
+
+        if self.global_state.settings.gameplay.trajectory_preview != trajectory_preview_toggle {
+            events.push(ChangeTrajectoryPreview(
+                !self.global_state.settings.gameplay.trajectory_preview,
+            ));
+        }
+
+        Text::new(
+            self.localized_strings
+                .get("hud.settings.trajectory_preview"),
+        )
+        .right_from(state.ids.trajectory_preview_button, 10.0)
+        .font_size(self.fonts.cyri.scale(14))
+        .font_id(self.fonts.cyri.conrod_id)
+        .graphics_for(state.ids.trajectory_preview_button)
+        .color(TEXT_COLOR)
+        .set(state.ids.trajectory_preview_label, ui);
+
         // Reset the gameplay settings to the default settings
         if Button::image(self.imgs.button)
             .w_h(RESET_BUTTONS_WIDTH, RESET_BUTTONS_HEIGHT)
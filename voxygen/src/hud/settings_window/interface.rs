@@ -2,7 +2,8 @@ use super::{ScaleChange, RESET_BUTTONS_HEIGHT, RESET_BUTTONS_WIDTH};
 
 use crate::{
     hud::{
-        img_ids::Imgs, BarNumbers, BuffPosition, CrosshairType, ShortcutNumbers, Show, TEXT_COLOR,
+        img_ids::Imgs, BarNumbers, BuffPosition, CoordinatesDisplay, CrosshairType,
+        ShortcutNumbers, Show, TEXT_COLOR,
     },
     session::settings_change::{Interface as InterfaceChange, Interface::*},
     ui::{fonts::Fonts, ImageSlider, ScaleMode, ToggleButton},
@@ -38,10 +39,16 @@ widget_ids! {
         debug_button_label,
         hitboxes_button,
         hitboxes_button_label,
+        chunk_borders_button,
+        chunk_borders_button_label,
         chat_button,
         chat_button_label,
+        friend_toast_button,
+        friend_toast_button_label,
         hotkey_hints_button,
         hotkey_hints_button_label,
+        skill_point_presentation_button,
+        skill_point_presentation_label,
         ch_title,
         ch_transp_slider,
         ch_transp_value,
@@ -66,6 +73,13 @@ widget_ids! {
         show_bar_numbers_percentage_text,
         always_show_bars_button,
         always_show_bars_label,
+        coordinates_title,
+        coordinates_off_button,
+        coordinates_off_text,
+        coordinates_chunk_button,
+        coordinates_chunk_text,
+        coordinates_block_button,
+        coordinates_block_text,
         //
         show_shortcuts_button,
         show_shortcuts_text,
@@ -274,6 +288,33 @@ impl<'a> Widget for Interface<'a> {
             .color(TEXT_COLOR)
             .set(state.ids.hitboxes_button_label, ui);
 
+        // Chunk borders
+        let show_chunk_borders = ToggleButton::new(
+            self.global_state.settings.interface.toggle_chunk_borders,
+            self.imgs.checkbox,
+            self.imgs.checkbox_checked,
+        )
+        .w_h(18.0, 18.0)
+        .right_from(state.ids.hitboxes_button_label, 30.0)
+        .hover_images(self.imgs.checkbox_mo, self.imgs.checkbox_checked_mo)
+        .press_images(self.imgs.checkbox_press, self.imgs.checkbox_checked)
+        .set(state.ids.chunk_borders_button, ui);
+
+        if self.global_state.settings.interface.toggle_chunk_borders != show_chunk_borders {
+            events.push(ToggleChunkBorders(show_chunk_borders));
+        }
+
+        Text::new(
+            self.localized_strings
+                .get("hud.settings.show_chunk_borders"),
+        )
+        .right_from(state.ids.chunk_borders_button, 10.0)
+        .font_size(self.fonts.cyri.scale(14))
+        .font_id(self.fonts.cyri.conrod_id)
+        .graphics_for(state.ids.chunk_borders_button)
+        .color(TEXT_COLOR)
+        .set(state.ids.chunk_borders_button_label, ui);
+
         // Chat
         let show_chat = ToggleButton::new(
             self.global_state.settings.interface.toggle_chat,
@@ -298,6 +339,33 @@ impl<'a> Widget for Interface<'a> {
             .color(TEXT_COLOR)
             .set(state.ids.chat_button_label, ui);
 
+        // Friend Online Popups
+        let friend_online_toast = ToggleButton::new(
+            self.global_state.settings.interface.friend_online_toast,
+            self.imgs.checkbox,
+            self.imgs.checkbox_checked,
+        )
+        .w_h(18.0, 18.0)
+        .right_from(state.ids.chat_button_label, 30.0)
+        .hover_images(self.imgs.checkbox_mo, self.imgs.checkbox_checked_mo)
+        .press_images(self.imgs.checkbox_press, self.imgs.checkbox_checked)
+        .set(state.ids.friend_toast_button, ui);
+
+        if self.global_state.settings.interface.friend_online_toast != friend_online_toast {
+            events.push(ToggleFriendToast(friend_online_toast));
+        }
+
+        Text::new(
+            self.localized_strings
+                .get("hud.settings.friend_online_toast"),
+        )
+        .right_from(state.ids.friend_toast_button, 10.0)
+        .font_size(self.fonts.cyri.scale(14))
+        .font_id(self.fonts.cyri.conrod_id)
+        .graphics_for(state.ids.friend_toast_button)
+        .color(TEXT_COLOR)
+        .set(state.ids.friend_toast_button_label, ui);
+
         // Hotkey hints
         let show_hotkey_hints = ToggleButton::new(
             self.global_state.settings.interface.toggle_hotkey_hints,
@@ -322,6 +390,42 @@ impl<'a> Widget for Interface<'a> {
             .color(TEXT_COLOR)
             .set(state.ids.hotkey_hints_button_label, ui);
 
+        // Skill point banner
+        let skill_point_presentation = ToggleButton::new(
+            self.global_state
+                .settings
+                .interface
+                .skill_point_presentation,
+            self.imgs.checkbox,
+            self.imgs.checkbox_checked,
+        )
+        .w_h(18.0, 18.0)
+        .right_from(state.ids.hotkey_hints_button_label, 30.0)
+        .hover_images(self.imgs.checkbox_mo, self.imgs.checkbox_checked_mo)
+        .press_images(self.imgs.checkbox_press, self.imgs.checkbox_checked)
+        .set(state.ids.skill_point_presentation_button, ui);
+
+        if self
+            .global_state
+            .settings
+            .interface
+            .skill_point_presentation
+            != skill_point_presentation
+        {
+            events.push(ToggleSkillPointPresentation(skill_point_presentation));
+        }
+
+        Text::new(
+            self.localized_strings
+                .get("hud.settings.skill_point_presentation"),
+        )
+        .right_from(state.ids.skill_point_presentation_button, 10.0)
+        .font_size(self.fonts.cyri.scale(14))
+        .font_id(self.fonts.cyri.conrod_id)
+        .graphics_for(state.ids.skill_point_presentation_button)
+        .color(TEXT_COLOR)
+        .set(state.ids.skill_point_presentation_label, ui);
+
         // Ui Scale
         Text::new(self.localized_strings.get("hud.settings.ui_scale"))
             .down_from(state.ids.hotkey_hints_button, 20.0)
@@ -1062,6 +1166,70 @@ impl<'a> Widget for Interface<'a> {
             .color(TEXT_COLOR)
             .set(state.ids.always_show_bars_label, ui);
 
+        // Coordinates below the compass
+        Text::new(self.localized_strings.get("hud.settings.coordinates"))
+            .down_from(state.ids.always_show_bars_button, 20.0)
+            .font_size(self.fonts.cyri.scale(18))
+            .font_id(self.fonts.cyri.conrod_id)
+            .color(TEXT_COLOR)
+            .set(state.ids.coordinates_title, ui);
+
+        let coordinates = self.global_state.settings.interface.coordinates;
+        let options = [
+            (
+                CoordinatesDisplay::Off,
+                "hud.settings.none",
+                state.ids.coordinates_off_button,
+                state.ids.coordinates_off_text,
+            ),
+            (
+                CoordinatesDisplay::Chunk,
+                "hud.settings.coordinates_chunk",
+                state.ids.coordinates_chunk_button,
+                state.ids.coordinates_chunk_text,
+            ),
+            (
+                CoordinatesDisplay::Block,
+                "hud.settings.coordinates_block",
+                state.ids.coordinates_block_button,
+                state.ids.coordinates_block_text,
+            ),
+        ];
+        let mut above = state.ids.coordinates_title;
+        for (option, key, button, text) in options {
+            let selected = coordinates == option;
+            if Button::image(if selected {
+                self.imgs.check_checked
+            } else {
+                self.imgs.check
+            })
+            .w_h(12.0, 12.0)
+            .hover_image(if selected {
+                self.imgs.check_checked_mo
+            } else {
+                self.imgs.check_mo
+            })
+            .press_image(if selected {
+                self.imgs.check_checked
+            } else {
+                self.imgs.check_press
+            })
+            .down_from(above, 8.0)
+            .set(button, ui)
+            .was_clicked()
+            {
+                events.push(Coordinates(option));
+            }
+            Text::new(self.localized_strings.get(key))
+                .right_from(button, 10.0)
+                .font_size(self.fonts.cyri.scale(14))
+                .font_id(self.fonts.cyri.conrod_id)
+                .graphics_for(button)
+                .color(TEXT_COLOR)
+                .set(text, ui);
+            above = button;
+        }
+
         // Reset the interface settings to the default settings
         if Button::image(self.imgs.button)
             .w_h(RESET_BUTTONS_WIDTH, RESET_BUTTONS_HEIGHT)
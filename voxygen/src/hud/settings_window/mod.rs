@@ -31,7 +31,7 @@ widget_ids! {
         settings_title,
         settings_content_align,
 
-        tabs[],
+        tabs,
         interface,
         gameplay,
         controls,
@@ -150,7 +150,6 @@ impl<'a> Widget for SettingsWindow<'a> {
         let widget::UpdateArgs { state, ui, .. } = args;
 
         let mut events = Vec::new();
-        let tab_font_scale = 18;
 
         // Frame
         Image::new(self.imgs.settings_bg)
@@ -170,13 +169,6 @@ impl<'a> Widget for SettingsWindow<'a> {
             .top_right_with_margins_on(state.ids.frame, 46.0, 2.0)
             .set(state.ids.settings_content_align, ui);
 
-        // Tabs Content Alignment
-        Rectangle::fill_with([232.0, 814.0], color::TRANSPARENT)
-            .top_left_with_margins_on(state.ids.frame, 44.0, 2.0)
-            .scroll_kids()
-            .scroll_kids_vertically()
-            .set(state.ids.tabs_align, ui);
-
         // Icon
         Image::new(self.imgs.settings)
             .w_h(29.0 * 1.5, 25.0 * 1.5)
@@ -206,37 +198,17 @@ impl<'a> Widget for SettingsWindow<'a> {
         }
 
         // Tabs
-        if state.ids.tabs.len() < SettingsTab::iter().len() {
-            state.update(|s| {
-                s.ids
-                    .tabs
-                    .resize(SettingsTab::iter().len(), &mut ui.widget_id_generator())
-            });
-        }
-        for (i, settings_tab) in SettingsTab::iter().enumerate() {
-            let mut button = Button::image(if self.show.settings_tab == settings_tab {
-                self.imgs.selection
-            } else {
-                self.imgs.nothing
-            })
-            .w_h(230.0, 48.0)
-            .hover_image(self.imgs.selection_hover)
-            .press_image(self.imgs.selection_press)
-            .image_color(color::rgba(1.0, 0.82, 0.27, 1.0))
-            .label(self.localized_strings.get(settings_tab.name_key()))
-            .label_font_size(self.fonts.cyri.scale(tab_font_scale))
-            .label_font_id(self.fonts.cyri.conrod_id)
-            .label_color(TEXT_COLOR);
-
-            button = if i == 0 {
-                button.mid_top_with_margin_on(state.ids.tabs_align, 28.0)
-            } else {
-                button.down_from(state.ids.tabs[i - 1], 0.0)
-            };
-
-            if button.set(state.ids.tabs[i], ui).was_clicked() {
-                events.push(Event::ChangeTab(settings_tab));
-            }
+        if let Some(tab) = Tabs::new(
+            &self.show.settings_tab,
+            self.imgs,
+            self.fonts,
+            self.localized_strings,
+        )
+        .top_left_with_margins_on(state.ids.frame, 44.0, 2.0)
+        .w_h(232.0, 814.0)
+        .set(state.ids.tabs, ui)
+        {
+            events.push(Event::ChangeTab(tab));
         }
 
         // Content Area
@@ -283,10 +255,19 @@ impl<'a> Widget for SettingsWindow<'a> {
                 }
             },
             SettingsTab::Controls => {
-                for change in controls::Controls::new(global_state, imgs, fonts, localized_strings)
-                    .top_left_with_margins_on(state.ids.settings_content_align, 0.0, 0.0)
-                    .wh_of(state.ids.settings_content_align)
-                    .set(state.ids.controls, ui)
+                let window = &global_state.window;
+                for change in controls::Controls::new(
+                    &global_state.settings.controls,
+                    &window.key_layout,
+                    window.remapping_keybindings,
+                    window.keybinding_mode,
+                    imgs,
+                    fonts,
+                    localized_strings,
+                )
+                .top_left_with_margins_on(state.ids.settings_content_align, 0.0, 0.0)
+                .wh_of(state.ids.settings_content_align)
+                .set(state.ids.controls, ui)
                 {
                     events.push(Event::SettingsChange(change.into()));
                 }
@@ -324,3 +305,189 @@ impl<'a> Widget for SettingsWindow<'a> {
         events
     }
 }
+
+widget_ids! {
+    struct TabIds {
+        tabs_align,
+        tabs[],
+    }
+}
+
+/// List of the settings tabs, returns the tab that was clicked
+#[derive(WidgetCommon)]
+pub struct Tabs<'a> {
+    selected: &'a SettingsTab,
+    imgs: &'a Imgs,
+    fonts: &'a Fonts,
+    localized_strings: &'a Localization,
+    #[conrod(common_builder)]
+    common: widget::CommonBuilder,
+}
+
+impl<'a> Tabs<'a> {
+    pub fn new(
+        selected: &'a SettingsTab,
+        imgs: &'a Imgs,
+        fonts: &'a Fonts,
+        localized_strings: &'a Localization,
+    ) -> Self {
+        Self {
+            selected,
+            imgs,
+            fonts,
+            localized_strings,
+            common: widget::CommonBuilder::default(),
+        }
+    }
+}
+
+pub struct TabsState {
+    ids: TabIds,
+}
+
+impl<'a> Widget for Tabs<'a> {
+    type Event = Option<SettingsTab>;
+    type State = TabsState;
+    type Style = ();
+
+    fn init_state(&self, id_gen: widget::id::Generator) -> Self::State {
+        TabsState {
+            ids: TabIds::new(id_gen),
+        }
+    }
+
+    fn style(&self) -> Self::Style {}
+
+    fn update(self, args: widget::UpdateArgs<Self>) -> Self::Event {
+        common_base::prof_span!("Tabs::update");
+        let widget::UpdateArgs {
+            id,
+            state,
+            rect,
+            ui,
+            ..
+        } = args;
+
+        let tab_font_scale = 18;
+        let mut clicked = None;
+
+        // Tabs Content Alignment
+        Rectangle::fill_with(rect.dim(), color::TRANSPARENT)
+            .xy(rect.xy())
+            .graphics_for(id)
+            .scroll_kids()
+            .scroll_kids_vertically()
+            .set(state.ids.tabs_align, ui);
+
+        if state.ids.tabs.len() < SettingsTab::iter().len() {
+            state.update(|s| {
+                s.ids
+                    .tabs
+                    .resize(SettingsTab::iter().len(), &mut ui.widget_id_generator())
+            });
+        }
+        for (i, settings_tab) in SettingsTab::iter().enumerate() {
+            let mut button = Button::image(if *self.selected == settings_tab {
+                self.imgs.selection
+            } else {
+                self.imgs.nothing
+            })
+            .w_h(230.0, 48.0)
+            .hover_image(self.imgs.selection_hover)
+            .press_image(self.imgs.selection_press)
+            .image_color(color::rgba(1.0, 0.82, 0.27, 1.0))
+            .label(self.localized_strings.get(settings_tab.name_key()))
+            .label_font_size(self.fonts.cyri.scale(tab_font_scale))
+            .label_font_id(self.fonts.cyri.conrod_id)
+            .label_color(TEXT_COLOR);
+
+            button = if i == 0 {
+                button.mid_top_with_margin_on(state.ids.tabs_align, 28.0)
+            } else {
+                button.down_from(state.ids.tabs[i - 1], 0.0)
+            };
+
+            if button.set(state.ids.tabs[i], ui).was_clicked() {
+                clicked = Some(settings_tab);
+            }
+        }
+
+        clicked
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui::headless::HeadlessUi;
+    use conrod_core::widget::button;
+    use i18n::LocalizationHandle;
+    use vek::*;
+
+    fn set_tabs(
+        ui: &mut HeadlessUi,
+        id: widget::Id,
+        selected: &SettingsTab,
+        imgs: &Imgs,
+        fonts: &Fonts,
+        i18n: &Localization,
+    ) -> Option<SettingsTab> {
+        ui.frame(|ui| {
+            Tabs::new(selected, imgs, fonts, i18n)
+                .top_left_with_margins_on(ui.window, 44.0, 2.0)
+                .w_h(232.0, 814.0)
+                .set(id, ui)
+        })
+    }
+
+    #[test]
+    fn clicking_a_tab_switches_to_it() {
+        let mut ui = HeadlessUi::new(Vec2::new(1920, 1080));
+        let i18n = LocalizationHandle::load_expect("en").read();
+        let imgs = Imgs::load(&mut ui).unwrap();
+        let fonts = Fonts::load(i18n.fonts(), &mut ui).unwrap();
+        let id = ui.new_id();
+
+        let selected = SettingsTab::Interface;
+        assert_eq!(set_tabs(&mut ui, id, &selected, &imgs, &fonts, &i18n), None);
+        let tabs = ui.query().of_type::<Button<button::Image>>();
+        assert_eq!(tabs.len(), SettingsTab::iter().len());
+        for pair in tabs.windows(2) {
+            assert!(!ui.query().overlap(pair[0], pair[1]));
+        }
+
+        let controls = ui
+            .query()
+            .find_text(i18n.get(SettingsTab::Controls.name_key()))
+            .unwrap();
+        ui.click_widget(controls);
+        assert_eq!(
+            set_tabs(&mut ui, id, &selected, &imgs, &fonts, &i18n),
+            Some(SettingsTab::Controls)
+        );
+    }
+
+    #[test]
+    fn tabs_stay_clickable_after_resize() {
+        let mut ui = HeadlessUi::new(Vec2::new(1920, 1080));
+        let i18n = LocalizationHandle::load_expect("en").read();
+        let imgs = Imgs::load(&mut ui).unwrap();
+        let fonts = Fonts::load(i18n.fonts(), &mut ui).unwrap();
+        let id = ui.new_id();
+
+        let selected = SettingsTab::Interface;
+        set_tabs(&mut ui, id, &selected, &imgs, &fonts, &i18n);
+        ui.resize(Vec2::new(800, 600));
+        set_tabs(&mut ui, id, &selected, &imgs, &fonts, &i18n);
+
+        let sound = ui
+            .query()
+            .find_text(i18n.get(SettingsTab::Sound.name_key()))
+            .unwrap();
+        ui.click_widget(sound);
+        assert_eq!(
+            set_tabs(&mut ui, id, &selected, &imgs, &fonts, &i18n),
+            Some(SettingsTab::Sound)
+        );
+    }
+}
@@ -86,6 +86,9 @@ widget_ids! {
         fluid_mode_list,
         fullscreen_mode_text,
         fullscreen_mode_list,
+        adapter_text,
+        adapter_list,
+        adapter_restart_text,
         //
         resolution,
         resolution_label,
@@ -99,10 +102,17 @@ widget_ids! {
         //
         particles_button,
         particles_label,
+        battery_saver_button,
+        battery_saver_label,
+        battery_saver_auto_button,
+        battery_saver_auto_label,
+        battery_saver_active_text,
         lossy_terrain_compression_button,
         lossy_terrain_compression_label,
         weapon_trails_button,
         weapon_trails_label,
+        cave_ambient_button,
+        cave_ambient_label,
         //
         fullscreen_button,
         fullscreen_label,
@@ -1106,6 +1116,67 @@ impl<'a> Widget for Video<'a> {
             })));
         }
 
+        // Battery saver
+        Text::new(self.localized_strings.get("hud.settings.battery_saver"))
+            .font_size(self.fonts.cyri.scale(14))
+            .font_id(self.fonts.cyri.conrod_id)
+            .right_from(state.ids.gpu_profiler_label, 64.0)
+            .color(TEXT_COLOR)
+            .set(state.ids.battery_saver_label, ui);
+
+        let battery_saver = ToggleButton::new(
+            self.global_state.settings.graphics.battery_saver,
+            self.imgs.checkbox,
+            self.imgs.checkbox_checked,
+        )
+        .w_h(18.0, 18.0)
+        .right_from(state.ids.battery_saver_label, 10.0)
+        .hover_images(self.imgs.checkbox_mo, self.imgs.checkbox_checked_mo)
+        .press_images(self.imgs.checkbox_press, self.imgs.checkbox_checked)
+        .set(state.ids.battery_saver_button, ui);
+
+        if self.global_state.settings.graphics.battery_saver != battery_saver {
+            events.push(GraphicsChange::ToggleBatterySaver(battery_saver));
+        }
+
+        Text::new(
+            self.localized_strings
+                .get("hud.settings.battery_saver_auto"),
+        )
+        .font_size(self.fonts.cyri.scale(14))
+        .font_id(self.fonts.cyri.conrod_id)
+        .right_from(state.ids.battery_saver_label, 64.0)
+        .color(TEXT_COLOR)
+        .set(state.ids.battery_saver_auto_label, ui);
+
+        let battery_saver_auto = ToggleButton::new(
+            self.global_state.settings.graphics.battery_saver_auto,
+            self.imgs.checkbox,
+            self.imgs.checkbox_checked,
+        )
+        .w_h(18.0, 18.0)
+        .right_from(state.ids.battery_saver_auto_label, 10.0)
+        .hover_images(self.imgs.checkbox_mo, self.imgs.checkbox_checked_mo)
+        .press_images(self.imgs.checkbox_press, self.imgs.checkbox_checked)
+        .set(state.ids.battery_saver_auto_button, ui);
+
+        if self.global_state.settings.graphics.battery_saver_auto != battery_saver_auto {
+            events.push(GraphicsChange::ToggleBatterySaverAuto(battery_saver_auto));
+        }
+
+        // The overlay might also be enabled because the device runs on battery
+        if self.global_state.battery_saver.active() {
+            Text::new(
+                self.localized_strings
+                    .get("hud.settings.battery_saver_active"),
+            )
+            .font_size(self.fonts.cyri.scale(14))
+            .font_id(self.fonts.cyri.conrod_id)
+            .right_from(state.ids.battery_saver_auto_button, 10.0)
+            .color(TEXT_COLOR)
+            .set(state.ids.battery_saver_active_text, ui);
+        }
+
         // Particles
         Text::new(self.localized_strings.get("hud.settings.particles"))
             .font_size(self.fonts.cyri.scale(14))
@@ -1191,6 +1262,29 @@ impl<'a> Widget for Video<'a> {
             ));
         }
 
+        // Cave ambient light
+        Text::new(self.localized_strings.get("hud.settings.cave_ambient"))
+            .font_size(self.fonts.cyri.scale(14))
+            .font_id(self.fonts.cyri.conrod_id)
+            .right_from(state.ids.weapon_trails_label, 64.0)
+            .color(TEXT_COLOR)
+            .set(state.ids.cave_ambient_label, ui);
+
+        let cave_ambient = ToggleButton::new(
+            self.global_state.settings.graphics.cave_ambient,
+            self.imgs.checkbox,
+            self.imgs.checkbox_checked,
+        )
+        .w_h(18.0, 18.0)
+        .right_from(state.ids.cave_ambient_label, 10.0)
+        .hover_images(self.imgs.checkbox_mo, self.imgs.checkbox_checked_mo)
+        .press_images(self.imgs.checkbox_press, self.imgs.checkbox_checked)
+        .set(state.ids.cave_ambient_button, ui);
+
+        if self.global_state.settings.graphics.cave_ambient != cave_ambient {
+            events.push(GraphicsChange::ToggleCaveAmbient(cave_ambient));
+        }
+
         // Resolution
         let resolutions: Vec<[u16; 2]> = state
             .video_modes
@@ -1416,12 +1510,65 @@ impl<'a> Widget for Video<'a> {
             }));
         }
 
+        // Graphics Adapter
+        Text::new(self.localized_strings.get("hud.settings.graphics_adapter"))
+            .down_from(state.ids.fullscreen_mode_list, 8.0)
+            .font_size(self.fonts.cyri.scale(14))
+            .font_id(self.fonts.cyri.conrod_id)
+            .color(TEXT_COLOR)
+            .set(state.ids.adapter_text, ui);
+
+        let renderer = self.global_state.window.renderer();
+        let adapter_setting = self.global_state.settings.graphics.adapter.as_deref();
+        let adapter_names = renderer.adapter_names();
+        let mut adapter_label_list = vec![
+            self.localized_strings
+                .get("hud.settings.graphics_adapter.automatic")
+                .to_owned(),
+        ];
+        adapter_label_list.extend(adapter_names.iter().cloned());
+        // An adapter that is no longer present is still shown as selected
+        if let Some(setting) = adapter_setting {
+            if !adapter_names.iter().any(|name| name == setting) {
+                adapter_label_list.push(setting.to_owned());
+            }
+        }
+
+        let selected = adapter_setting.map_or(Some(0), |setting| {
+            adapter_label_list
+                .iter()
+                .skip(1)
+                .position(|name| name == setting)
+                .map(|i| i + 1)
+        });
+
+        if let Some(clicked) = DropDownList::new(&adapter_label_list, selected)
+            .w_h(400.0, 22.0)
+            .color(MENU_BG)
+            .label_color(TEXT_COLOR)
+            .label_font_id(self.fonts.cyri.conrod_id)
+            .down_from(state.ids.adapter_text, 8.0)
+            .set(state.ids.adapter_list, ui)
+        {
+            let adapter = (clicked > 0).then(|| adapter_label_list[clicked].clone());
+            events.push(GraphicsChange::ChangeAdapter(adapter));
+        }
+
+        if adapter_setting != renderer.adapter_preference() {
+            Text::new(self.localized_strings.get("hud.settings.restart_required"))
+                .right_from(state.ids.adapter_list, 10.0)
+                .font_size(self.fonts.cyri.scale(14))
+                .font_id(self.fonts.cyri.conrod_id)
+                .color(TEXT_COLOR)
+                .set(state.ids.adapter_restart_text, ui);
+        }
+
         // Save current screen size
         if Button::image(self.imgs.button)
             .w_h(RESET_BUTTONS_WIDTH, RESET_BUTTONS_HEIGHT)
             .hover_image(self.imgs.button_hover)
             .press_image(self.imgs.button_press)
-            .down_from(state.ids.fullscreen_mode_list, 12.0)
+            .down_from(state.ids.adapter_list, 12.0)
             .label(self.localized_strings.get("hud.settings.save_window_size"))
             .label_font_size(self.fonts.cyri.scale(14))
             .label_color(TEXT_COLOR)
@@ -1444,7 +1591,7 @@ impl<'a> Widget for Video<'a> {
             .w_h(RESET_BUTTONS_WIDTH, RESET_BUTTONS_HEIGHT)
             .hover_image(self.imgs.button_hover)
             .press_image(self.imgs.button_press)
-            .down_from(state.ids.fullscreen_mode_list, 12.0)
+            .down_from(state.ids.adapter_list, 12.0)
             .right_from(state.ids.save_window_size_button, 12.0)
             .label(self.localized_strings.get("hud.settings.reset_graphics"))
             .label_font_size(self.fonts.cyri.scale(14))
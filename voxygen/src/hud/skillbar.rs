@@ -23,7 +23,7 @@ use common::comp::{
     self,
     ability::AbilityInput,
     item::{ItemDesc, MaterialStatManifest},
-    Ability, ActiveAbilities, Body, Energy, Health, Inventory, SkillSet,
+    Ability, ActiveAbilities, Body, Cooldowns, Energy, Health, Inventory, SkillSet,
 };
 use conrod_core::{
     color,
@@ -82,6 +82,8 @@ widget_ids! {
         combo_align,
         combo_bg,
         combo,
+        // Combo Stage
+        combo_stage_pips[],
         // Slots
         m1_slot,
         m1_slot_bg,
@@ -89,42 +91,54 @@ widget_ids! {
         m1_text_bg,
         m1_slot_act,
         m1_content,
+        m1_cooldown,
         m2_slot,
         m2_slot_bg,
         m2_text,
         m2_text_bg,
         m2_slot_act,
         m2_content,
+        m2_cooldown,
         slot1,
         slot1_text,
         slot1_text_bg,
+        slot1_cooldown,
         slot2,
         slot2_text,
         slot2_text_bg,
+        slot2_cooldown,
         slot3,
         slot3_text,
         slot3_text_bg,
+        slot3_cooldown,
         slot4,
         slot4_text,
         slot4_text_bg,
+        slot4_cooldown,
         slot5,
         slot5_text,
         slot5_text_bg,
+        slot5_cooldown,
         slot6,
         slot6_text,
         slot6_text_bg,
+        slot6_cooldown,
         slot7,
         slot7_text,
         slot7_text_bg,
+        slot7_cooldown,
         slot8,
         slot8_text,
         slot8_text_bg,
+        slot8_cooldown,
         slot9,
         slot9_text,
         slot9_text_bg,
+        slot9_cooldown,
         slot10,
         slot10_text,
         slot10_text_bg,
+        slot10_cooldown,
     }
 }
 
@@ -137,6 +151,7 @@ struct SlotEntry {
     shortcut_position: PositionSpecifier,
     shortcut_position_bg: PositionSpecifier,
     shortcut_widget_ids: (widget::Id, widget::Id),
+    cooldown_widget_id: widget::Id,
 }
 
 fn slot_entries(state: &State, slot_offset: f64) -> [SlotEntry; 10] {
@@ -152,6 +167,7 @@ fn slot_entries(state: &State, slot_offset: f64) -> [SlotEntry; 10] {
             shortcut_position: BottomLeftWithMarginsOn(state.ids.slot1_text_bg, 1.0, 1.0),
             shortcut_position_bg: TopRightWithMarginsOn(state.ids.slot1, 3.0, 5.0),
             shortcut_widget_ids: (state.ids.slot1_text, state.ids.slot1_text_bg),
+            cooldown_widget_id: state.ids.slot1_cooldown,
         },
         SlotEntry {
             slot: hotbar::Slot::Two,
@@ -161,6 +177,7 @@ fn slot_entries(state: &State, slot_offset: f64) -> [SlotEntry; 10] {
             shortcut_position: BottomLeftWithMarginsOn(state.ids.slot2_text_bg, 1.0, 1.0),
             shortcut_position_bg: TopRightWithMarginsOn(state.ids.slot2, 3.0, 5.0),
             shortcut_widget_ids: (state.ids.slot2_text, state.ids.slot2_text_bg),
+            cooldown_widget_id: state.ids.slot2_cooldown,
         },
         SlotEntry {
             slot: hotbar::Slot::Three,
@@ -170,6 +187,7 @@ fn slot_entries(state: &State, slot_offset: f64) -> [SlotEntry; 10] {
             shortcut_position: BottomLeftWithMarginsOn(state.ids.slot3_text_bg, 1.0, 1.0),
             shortcut_position_bg: TopRightWithMarginsOn(state.ids.slot3, 3.0, 5.0),
             shortcut_widget_ids: (state.ids.slot3_text, state.ids.slot3_text_bg),
+            cooldown_widget_id: state.ids.slot3_cooldown,
         },
         SlotEntry {
             slot: hotbar::Slot::Four,
@@ -179,6 +197,7 @@ fn slot_entries(state: &State, slot_offset: f64) -> [SlotEntry; 10] {
             shortcut_position: BottomLeftWithMarginsOn(state.ids.slot4_text_bg, 1.0, 1.0),
             shortcut_position_bg: TopRightWithMarginsOn(state.ids.slot4, 3.0, 5.0),
             shortcut_widget_ids: (state.ids.slot4_text, state.ids.slot4_text_bg),
+            cooldown_widget_id: state.ids.slot4_cooldown,
         },
         SlotEntry {
             slot: hotbar::Slot::Five,
@@ -188,6 +207,7 @@ fn slot_entries(state: &State, slot_offset: f64) -> [SlotEntry; 10] {
             shortcut_position: BottomLeftWithMarginsOn(state.ids.slot5_text_bg, 1.0, 1.0),
             shortcut_position_bg: TopRightWithMarginsOn(state.ids.slot5, 3.0, 5.0),
             shortcut_widget_ids: (state.ids.slot5_text, state.ids.slot5_text_bg),
+            cooldown_widget_id: state.ids.slot5_cooldown,
         },
         // 6th - 10th slots
         SlotEntry {
@@ -198,6 +218,7 @@ fn slot_entries(state: &State, slot_offset: f64) -> [SlotEntry; 10] {
             shortcut_position: BottomLeftWithMarginsOn(state.ids.slot6_text_bg, 1.0, 1.0),
             shortcut_position_bg: TopRightWithMarginsOn(state.ids.slot6, 3.0, 5.0),
             shortcut_widget_ids: (state.ids.slot6_text, state.ids.slot6_text_bg),
+            cooldown_widget_id: state.ids.slot6_cooldown,
         },
         SlotEntry {
             slot: hotbar::Slot::Seven,
@@ -207,6 +228,7 @@ fn slot_entries(state: &State, slot_offset: f64) -> [SlotEntry; 10] {
             shortcut_position: BottomLeftWithMarginsOn(state.ids.slot7_text_bg, 1.0, 1.0),
             shortcut_position_bg: TopRightWithMarginsOn(state.ids.slot7, 3.0, 5.0),
             shortcut_widget_ids: (state.ids.slot7_text, state.ids.slot7_text_bg),
+            cooldown_widget_id: state.ids.slot7_cooldown,
         },
         SlotEntry {
             slot: hotbar::Slot::Eight,
@@ -216,6 +238,7 @@ fn slot_entries(state: &State, slot_offset: f64) -> [SlotEntry; 10] {
             shortcut_position: BottomLeftWithMarginsOn(state.ids.slot8_text_bg, 1.0, 1.0),
             shortcut_position_bg: TopRightWithMarginsOn(state.ids.slot8, 3.0, 5.0),
             shortcut_widget_ids: (state.ids.slot8_text, state.ids.slot8_text_bg),
+            cooldown_widget_id: state.ids.slot8_cooldown,
         },
         SlotEntry {
             slot: hotbar::Slot::Nine,
@@ -225,6 +248,7 @@ fn slot_entries(state: &State, slot_offset: f64) -> [SlotEntry; 10] {
             shortcut_position: BottomLeftWithMarginsOn(state.ids.slot9_text_bg, 1.0, 1.0),
             shortcut_position_bg: TopRightWithMarginsOn(state.ids.slot9, 3.0, 5.0),
             shortcut_widget_ids: (state.ids.slot9_text, state.ids.slot9_text_bg),
+            cooldown_widget_id: state.ids.slot9_cooldown,
         },
         SlotEntry {
             slot: hotbar::Slot::Ten,
@@ -234,6 +258,7 @@ fn slot_entries(state: &State, slot_offset: f64) -> [SlotEntry; 10] {
             shortcut_position: BottomLeftWithMarginsOn(state.ids.slot10_text_bg, 1.0, 1.0),
             shortcut_position_bg: TopRightWithMarginsOn(state.ids.slot10, 3.0, 5.0),
             shortcut_widget_ids: (state.ids.slot10_text, state.ids.slot10_text_bg),
+            cooldown_widget_id: state.ids.slot10_cooldown,
         },
     ]
 }
@@ -251,6 +276,7 @@ pub struct Skillbar<'a> {
     energy: &'a Energy,
     skillset: &'a SkillSet,
     active_abilities: Option<&'a ActiveAbilities>,
+    cooldowns: Option<&'a Cooldowns>,
     body: &'a Body,
     // character_state: &'a CharacterState,
     // controller: &'a ControllerInputs,
@@ -264,6 +290,8 @@ pub struct Skillbar<'a> {
     common: widget::CommonBuilder,
     msm: &'a MaterialStatManifest,
     combo: Option<ComboFloater>,
+    /// Current and total number of stages of the melee combo being performed
+    combo_stage: Option<(u32, u32)>,
 }
 
 impl<'a> Skillbar<'a> {
@@ -280,6 +308,7 @@ impl<'a> Skillbar<'a> {
         energy: &'a Energy,
         skillset: &'a SkillSet,
         active_abilities: Option<&'a ActiveAbilities>,
+        cooldowns: Option<&'a Cooldowns>,
         body: &'a Body,
         // character_state: &'a CharacterState,
         pulse: f32,
@@ -291,6 +320,7 @@ impl<'a> Skillbar<'a> {
         localized_strings: &'a Localization,
         msm: &'a MaterialStatManifest,
         combo: Option<ComboFloater>,
+        combo_stage: Option<(u32, u32)>,
     ) -> Self {
         Self {
             client,
@@ -304,6 +334,7 @@ impl<'a> Skillbar<'a> {
             energy,
             skillset,
             active_abilities,
+            cooldowns,
             body,
             common: widget::CommonBuilder::default(),
             // character_state,
@@ -316,6 +347,7 @@ impl<'a> Skillbar<'a> {
             localized_strings,
             msm,
             combo,
+            combo_stage,
         }
     }
 
@@ -599,23 +631,57 @@ impl<'a> Skillbar<'a> {
             })
         };
 
+        let slot_ability_id = |slot| {
+            let (hotbar, inventory, _, skill_set, active_abilities, _) = content_source;
+            hotbar.get(slot).and_then(|content| match content {
+                hotbar::SlotContents::Inventory(..) => None,
+                hotbar::SlotContents::Ability(i) => active_abilities.and_then(|a| {
+                    a.auxiliary_set(Some(inventory), Some(skill_set))
+                        .get(i)
+                        .and_then(|a| Ability::from(*a).ability_id(Some(inventory)))
+                }),
+            })
+        };
+
         // Helper
         let tooltip_text = |slot| {
-            let (hotbar, inventory, _, skill_set, active_abilities, _) = content_source;
+            let (hotbar, inventory, ..) = content_source;
             hotbar.get(slot).and_then(|content| match content {
                 hotbar::SlotContents::Inventory(i, _) => inventory
                     .get_by_hash(i)
                     .map(|item| (item.name(), item.description())),
-                hotbar::SlotContents::Ability(i) => active_abilities
-                    .and_then(|a| {
-                        a.auxiliary_set(Some(inventory), Some(skill_set))
-                            .get(i)
-                            .and_then(|a| Ability::from(*a).ability_id(Some(inventory)))
-                    })
-                    .map(util::ability_description),
+                hotbar::SlotContents::Ability(_) => {
+                    slot_ability_id(slot).map(util::ability_description)
+                },
             })
         };
 
+        // Darkens the part of a slot that corresponds to the remaining cooldown of its
+        // ability
+        let cooldowns = self.cooldowns;
+        let game_time = self.client.game_time();
+        let show_cooldown = |ability_id: Option<&str>,
+                             slot_id: widget::Id,
+                             cooldown_id: widget::Id,
+                             ui: &mut UiCell| {
+            let fraction =
+                ability_id
+                    .zip(cooldowns)
+                    .zip(game_time)
+                    .and_then(|((id, cooldowns), now)| {
+                        Some(cooldowns.get(id, now)?.remaining_fraction(now))
+                    });
+            if let (Some(fraction), Some([w, h])) = (fraction, ui.wh_of(slot_id)) {
+                Rectangle::fill_with(
+                    [w, h * f64::from(fraction)],
+                    Color::Rgba(0.0, 0.0, 0.0, 0.6),
+                )
+                .mid_bottom_of(slot_id)
+                .graphics_for(slot_id)
+                .set(cooldown_id, ui);
+            }
+        };
+
         slot_maker.empty_slot = self.imgs.skillbar_slot;
         slot_maker.selected_slot = self.imgs.skillbar_slot;
 
@@ -642,6 +708,12 @@ impl<'a> Skillbar<'a> {
             } else {
                 slot.set(entry.widget_id, ui);
             }
+            show_cooldown(
+                slot_ability_id(entry.slot),
+                entry.widget_id,
+                entry.cooldown_widget_id,
+                ui,
+            );
 
             // shortcuts
             if let ShortcutNumbers::On = shortcuts {
@@ -691,6 +763,12 @@ impl<'a> Skillbar<'a> {
         .w_h(36.0, 36.0)
         .middle_of(state.ids.m1_slot_bg)
         .set(state.ids.m1_content, ui);
+        show_cooldown(
+            primary_ability_id,
+            state.ids.m1_content,
+            state.ids.m1_cooldown,
+            ui,
+        );
         // Slot M2
         Image::new(self.imgs.skillbar_slot)
             .w_h(40.0, 40.0)
@@ -726,6 +804,12 @@ impl<'a> Skillbar<'a> {
             },
         )
         .set(state.ids.m2_content, ui);
+        show_cooldown(
+            secondary_ability_id,
+            state.ids.m2_content,
+            state.ids.m2_cooldown,
+            ui,
+        );
 
         // M1 and M2 icons
         Image::new(self.imgs.m1_ico)
@@ -777,6 +861,21 @@ impl<'a> Skillbar<'a> {
                 .set(state.ids.combo, ui);
         }
     }
+
+    fn show_combo_stage(&self, stage: u32, num_stages: u32, state: &State, ui: &mut UiCell) {
+        let offset = (num_stages as f64 - 1.0) / 2.0;
+        for (i, id) in state.ids.combo_stage_pips.iter().enumerate() {
+            let color = if i < stage as usize {
+                UI_HIGHLIGHT_0
+            } else {
+                Color::Rgba(0.0, 0.0, 0.0, 0.4)
+            };
+            Rectangle::fill_with([6.0, 6.0], color)
+                .up_from(state.ids.frame, 60.0)
+                .x_relative_to(state.ids.frame, (i as f64 - offset) * 10.0)
+                .set(*id, ui);
+        }
+    }
 }
 
 pub struct State {
@@ -824,5 +923,17 @@ impl<'a> Widget for Skillbar<'a> {
         if let Some(combo) = self.combo {
             self.show_combo_counter(combo, state, ui);
         }
+
+        // Combo Stage
+        if let Some((stage, num_stages)) = self.combo_stage {
+            if state.ids.combo_stage_pips.len() != num_stages as usize {
+                state.update(|s| {
+                    s.ids
+                        .combo_stage_pips
+                        .resize(num_stages as usize, &mut ui.widget_id_generator())
+                });
+            }
+            self.show_combo_stage(stage, num_stages, state, ui);
+        }
     }
 }
@@ -7,7 +7,7 @@ use client::{self, Client};
 use common::{comp::group, uid::Uid};
 use conrod_core::{
     color,
-    widget::{self, Button, Image, Rectangle, Scrollbar, Text, TextEdit},
+    widget::{self, button, Button, Image, Rectangle, Scrollbar, Text, TextEdit},
     widget_ids, Color, Colorable, Labelable, Positionable, Sizeable, Widget, WidgetCommon,
 };
 use i18n::Localization;
@@ -23,11 +23,17 @@ widget_ids! {
         bg,
         icon,
         scrollbar,
+        tab_players,
+        tab_friends,
         online_align,
         player_names[],
+        friend_names[],
         online_txt,
         online_no,
         invite_button,
+        add_friend_button,
+        remove_friend_button,
+        whisper_button,
         player_search_icon,
         player_search_input,
         player_search_input_bg,
@@ -40,6 +46,13 @@ pub struct State {
     // Holds the time when selection is made since this selection can be overridden
     // by selecting an entity in-game
     selected_uid: Option<(Uid, Instant)>,
+    selected_friend: Option<String>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SocialTab {
+    Online,
+    Friends,
 }
 
 #[derive(WidgetCommon)]
@@ -87,6 +100,37 @@ pub enum Event {
     Invite(Uid),
     Focus(widget::Id),
     SearchPlayers(Option<String>),
+    ChangeTab(SocialTab),
+    AddFriend(String),
+    RemoveFriend(String),
+    Whisper(String),
+}
+
+/// A button at the bottom of the window, greyed out while it can't be used
+fn action_button<'a>(
+    imgs: &Imgs,
+    fonts: &Fonts,
+    label: &'a str,
+    enabled: bool,
+) -> Button<'a, button::Image> {
+    let color = if enabled { TEXT_COLOR } else { TEXT_COLOR_3 };
+    Button::image(imgs.button)
+        .hover_image(if enabled {
+            imgs.button_hover
+        } else {
+            imgs.button
+        })
+        .press_image(if enabled {
+            imgs.button_press
+        } else {
+            imgs.button
+        })
+        .label(label)
+        .label_y(conrod_core::position::Relative::Scalar(3.0))
+        .label_color(color)
+        .image_color(color)
+        .label_font_size(fonts.cyri.scale(14))
+        .label_font_id(fonts.cyri.conrod_id)
 }
 
 impl<'a> Widget for Social<'a> {
@@ -98,6 +142,7 @@ impl<'a> Widget for Social<'a> {
         Self::State {
             ids: Ids::new(id_gen),
             selected_uid: None,
+            selected_friend: None,
         }
     }
 
@@ -166,6 +211,36 @@ impl<'a> Widget for Social<'a> {
             .color(TEXT_COLOR)
             .set(state.ids.title, ui);
 
+        // Tabs
+        let tab = self.show.social_tab;
+        for (tab_id, other_tab, label) in [
+            (
+                state.ids.tab_players,
+                SocialTab::Online,
+                "hud.social.players",
+            ),
+            (
+                state.ids.tab_friends,
+                SocialTab::Friends,
+                "hud.social.friends",
+            ),
+        ] {
+            let button = action_button(
+                self.imgs,
+                self.fonts,
+                self.localized_strings.get(label),
+                tab == other_tab,
+            )
+            .w_h(128.0, 22.0);
+            let button = match other_tab {
+                SocialTab::Online => button.top_left_with_margins_on(state.ids.frame, 76.0, 9.0),
+                SocialTab::Friends => button.right_from(state.ids.tab_players, 6.0),
+            };
+            if button.set(tab_id, ui).was_clicked() && tab != other_tab {
+                events.push(Event::ChangeTab(other_tab));
+            }
+        }
+
         let players = self
             .client
             .player_list()
@@ -174,8 +249,8 @@ impl<'a> Widget for Social<'a> {
         let player_count = players.clone().count();
 
         // Content Alignment
-        Rectangle::fill_with([270.0, 346.0], color::TRANSPARENT)
-            .mid_top_with_margin_on(state.ids.frame, 74.0)
+        Rectangle::fill_with([270.0, 318.0], color::TRANSPARENT)
+            .mid_top_with_margin_on(state.ids.frame, 102.0)
             .scroll_kids_vertically()
             .set(state.ids.online_align, ui);
         Scrollbar::y_axis(state.ids.online_align)
@@ -184,18 +259,20 @@ impl<'a> Widget for Social<'a> {
             .set(state.ids.scrollbar, ui);
 
         // Online Text
-        Text::new(self.localized_strings.get("hud.social.online"))
-            .bottom_left_with_margins_on(state.ids.frame, 18.0, 10.0)
-            .font_id(self.fonts.cyri.conrod_id)
-            .font_size(self.fonts.cyri.scale(14))
-            .color(TEXT_COLOR)
-            .set(state.ids.online_txt, ui);
-        Text::new(&player_count.to_string())
-            .right_from(state.ids.online_txt, 5.0)
-            .font_id(self.fonts.cyri.conrod_id)
-            .font_size(self.fonts.cyri.scale(14))
-            .color(TEXT_COLOR)
-            .set(state.ids.online_no, ui);
+        if tab == SocialTab::Online {
+            Text::new(self.localized_strings.get("hud.social.online"))
+                .bottom_left_with_margins_on(state.ids.frame, 18.0, 10.0)
+                .font_id(self.fonts.cyri.conrod_id)
+                .font_size(self.fonts.cyri.scale(14))
+                .color(TEXT_COLOR)
+                .set(state.ids.online_txt, ui);
+            Text::new(&player_count.to_string())
+                .right_from(state.ids.online_txt, 5.0)
+                .font_id(self.fonts.cyri.conrod_id)
+                .font_size(self.fonts.cyri.scale(14))
+                .color(TEXT_COLOR)
+                .set(state.ids.online_no, ui);
+        }
         // Adjust widget_id struct vec length to player count
         if state.ids.player_names.len() < player_count {
             state.update(|s| {
@@ -205,9 +282,11 @@ impl<'a> Widget for Social<'a> {
             })
         };
 
-        // Filter out yourself from the online list and perform search
+        // Filter out yourself from the online list and perform search, friends
+        // are listed in a tab of their own
         let my_uid = self.client.uid();
         let mut player_list = players
+            .filter(|_| tab == SocialTab::Online)
             .filter(|(uid, _)| Some(**uid) != my_uid)
             .filter(|(_, player)| {
                 self.show
@@ -306,6 +385,83 @@ impl<'a> Widget for Social<'a> {
             }
         }
 
+        // Friends, the online ones first
+        let mut friend_list = self
+            .client
+            .friends()
+            .iter()
+            .filter(|_| tab == SocialTab::Friends)
+            .filter(|(name, _)| {
+                self.show
+                    .social_search_key
+                    .as_ref()
+                    .map_or(true, |search_key| {
+                        name.to_lowercase().contains(&search_key.to_lowercase())
+                    })
+            })
+            .collect_vec();
+        friend_list.sort_by_key(|(_, friend)| !friend.online);
+        if state.ids.friend_names.len() < friend_list.len() {
+            state.update(|s| {
+                s.ids
+                    .friend_names
+                    .resize(friend_list.len(), &mut ui.widget_id_generator())
+            })
+        };
+        for (i, (name, friend)) in friend_list.into_iter().enumerate() {
+            let selected = state.selected_friend.as_ref() == Some(name);
+            let name_text = if friend.online {
+                name.clone()
+            } else {
+                format!(
+                    "{} [{}]",
+                    name,
+                    self.localized_strings.get("hud.social.offline")
+                )
+            };
+            let button = Button::image(if selected {
+                self.imgs.selection
+            } else {
+                self.imgs.nothing
+            })
+            .hover_image(if selected {
+                self.imgs.selection
+            } else {
+                self.imgs.selection_hover
+            })
+            .press_image(if selected {
+                self.imgs.selection
+            } else {
+                self.imgs.selection_press
+            })
+            .w_h(260.0, 20.0)
+            .image_color(color::rgba(1.0, 0.82, 0.27, 1.0));
+            let button = if i == 0 {
+                button.mid_top_with_margin_on(state.ids.online_align, 1.0)
+            } else {
+                button.down_from(state.ids.friend_names[i - 1], 1.0)
+            };
+            if button
+                .label(&name_text)
+                .label_font_size(self.fonts.cyri.scale(14))
+                .label_y(conrod_core::position::Relative::Scalar(1.0))
+                .label_font_id(self.fonts.cyri.conrod_id)
+                .label_color(if friend.online {
+                    TEXT_COLOR
+                } else {
+                    TEXT_COLOR_3
+                })
+                .set(state.ids.friend_names[i], ui)
+                .was_clicked()
+            {
+                state.update(|s| s.selected_friend = Some(name.clone()));
+            }
+        }
+        let selected_friend = state
+            .selected_friend
+            .as_ref()
+            .and_then(|name| Some((name.clone(), *self.client.friends().get(name)?)));
+
         // Invite Button
         let is_leader_or_not_in_group = self
             .client
@@ -324,26 +480,28 @@ impl<'a> Widget for Social<'a> {
         let group_not_full = current_members + current_invites < max_members;
         let selected_to_invite = (is_leader_or_not_in_group && group_not_full)
             .then(|| {
-                state
-                    .selected_uid
-                    .as_ref()
-                    .map(|(s, _)| *s)
-                    .filter(|selected| {
-                        self.client
-                            .player_list()
-                            .get(selected)
-                            .map_or(false, |selected_player| {
-                                selected_player.is_online && selected_player.character.is_some()
-                            })
-                    })
-                    .or_else(|| {
-                        self.selected_entity
-                            .and_then(|s| self.client.state().read_component_copied(s.0))
-                    })
-                    .filter(|selected| {
-                        // Prevent inviting entities already in the same group
-                        !self.client.group_members().contains_key(selected)
-                    })
+                match tab {
+                    SocialTab::Online => state.selected_uid.as_ref().map(|(s, _)| *s),
+                    SocialTab::Friends => {
+                        selected_friend.as_ref().and_then(|(_, friend)| friend.uid)
+                    },
+                }
+                .filter(|selected| {
+                    self.client
+                        .player_list()
+                        .get(selected)
+                        .map_or(false, |selected_player| {
+                            selected_player.is_online && selected_player.character.is_some()
+                        })
+                })
+                .or_else(|| {
+                    self.selected_entity
+                        .and_then(|s| self.client.state().read_component_copied(s.0))
+                })
+                .filter(|selected| {
+                    // Prevent inviting entities already in the same group
+                    !self.client.group_members().contains_key(selected)
+                })
             })
             .flatten();
 
@@ -404,6 +562,73 @@ impl<'a> Widget for Social<'a> {
             }
         }
 
+        match tab {
+            SocialTab::Online => {
+                // Add Friend Button
+                let to_befriend = state
+                    .selected_uid
+                    .and_then(|(uid, _)| self.client.player_list().get(&uid))
+                    .filter(|player| player.is_online)
+                    .map(|player| player.player_alias.clone())
+                    .filter(|alias| !self.client.friends().contains_key(alias));
+                if action_button(
+                    self.imgs,
+                    self.fonts,
+                    self.localized_strings.get("hud.social.add_friend"),
+                    to_befriend.is_some(),
+                )
+                .w_h(80.0, 26.0)
+                .left_from(state.ids.invite_button, 4.0)
+                .set(state.ids.add_friend_button, ui)
+                .was_clicked()
+                {
+                    if let Some(alias) = to_befriend {
+                        events.push(Event::AddFriend(alias));
+                    }
+                }
+            },
+            SocialTab::Friends => {
+                // Whisper Button
+                let online_friend = selected_friend
+                    .as_ref()
+                    .filter(|(_, friend)| friend.online)
+                    .map(|(name, _)| name);
+                if action_button(
+                    self.imgs,
+                    self.fonts,
+                    self.localized_strings.get("hud.social.whisper"),
+                    online_friend.is_some(),
+                )
+                .w_h(76.0, 26.0)
+                .left_from(state.ids.invite_button, 4.0)
+                .set(state.ids.whisper_button, ui)
+                .was_clicked()
+                {
+                    if let Some(name) = online_friend {
+                        events.push(Event::Whisper(name.clone()));
+                    }
+                }
+
+                // Remove Friend Button
+                if action_button(
+                    self.imgs,
+                    self.fonts,
+                    self.localized_strings.get("hud.social.remove_friend"),
+                    selected_friend.is_some(),
+                )
+                .w_h(76.0, 26.0)
+                .left_from(state.ids.whisper_button, 4.0)
+                .set(state.ids.remove_friend_button, ui)
+                .was_clicked()
+                {
+                    if let Some((name, _)) = selected_friend {
+                        events.push(Event::RemoveFriend(name));
+                        state.update(|s| s.selected_friend = None);
+                    }
+                }
+            },
+        }
+
         // Player Search
         if Button::image(self.imgs.search_btn)
             .top_left_with_margins_on(state.ids.frame, 54.0, 9.0)
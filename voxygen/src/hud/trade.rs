@@ -11,7 +11,7 @@ use client::Client;
 use common::{
     comp::{
         inventory::item::{ItemDesc, MaterialStatManifest, Quality},
-        Inventory, Stats,
+        Gravestone, Inventory, Stats,
     },
     trade::{PendingTrade, SitePrices, TradeAction, TradePhase},
 };
@@ -215,6 +215,17 @@ impl<'a> Trade<'a> {
                     .get(entity)
                     .map(|e| e.name.to_owned())
             })
+            .or_else(|| {
+                self.client
+                    .state()
+                    .read_storage::<Gravestone>()
+                    .get(entity)
+                    .map(|gravestone| {
+                        self.localized_strings
+                            .get("hud.trade.gravestone_of")
+                            .replace("{playername}", &gravestone.owner_name)
+                    })
+            })
             .unwrap_or_else(|| format!("Player {}", who));
 
         let offer_header = if ours {
@@ -12,6 +12,7 @@ use common::{
     effect::Effect,
     trade::{Good, SitePrices},
 };
+use common_net::msg::world_msg::SiteKind;
 use conrod_core::image;
 use i18n::Localization;
 use std::{borrow::Cow, fmt::Write};
@@ -129,6 +130,8 @@ pub fn stats_count(item: &dyn ItemDesc) -> usize {
                 armor.stats.energy_reward().is_some() as usize
                     + armor.stats.energy_max().is_some() as usize
                     + armor.stats.stealth().is_some() as usize
+                    + armor.stats.fire_resistance().is_some() as usize
+                    + armor.stats.poison_resistance().is_some() as usize
                     + armor.stats.crit_power().is_some() as usize
                     + armor.stats.poise_resilience().is_some() as usize
             }
@@ -267,6 +270,7 @@ fn tool_kind<'a>(tool: &Tool, i18n: &'a Localization) -> &'a str {
         ToolKind::Natural => i18n.get("common.weapons.natural"),
         ToolKind::Debug => i18n.get("common.tool.debug"),
         ToolKind::Farming => i18n.get("common.tool.farming"),
+        ToolKind::Fishing => i18n.get("common.tool.fishing"),
         ToolKind::Pick => i18n.get("common.tool.pick"),
         ToolKind::Empty => i18n.get("common.empty"),
     };
@@ -378,6 +382,23 @@ pub fn ability_image(imgs: &img_ids::Imgs, ability_id: &str) -> image::Id {
     }
 }
 
+/// Icon of a site on the map, kinds added by newer servers get a generic one
+pub fn site_icon(imgs: &img_ids::Imgs, kind: &SiteKind) -> image::Id {
+    match kind {
+        SiteKind::Town => imgs.mmap_site_town,
+        SiteKind::Castle => imgs.mmap_site_castle,
+        SiteKind::Cave => imgs.mmap_site_cave,
+        SiteKind::Tree => imgs.mmap_site_tree,
+        SiteKind::Gnarling => imgs.mmap_site_gnarling,
+        SiteKind::Dungeon { difficulty } => match difficulty {
+            4 => imgs.mmap_site_minotaur,
+            5 => imgs.mmap_site_mindflayer,
+            _ => imgs.mmap_site_dungeon,
+        },
+        SiteKind::Unknown => imgs.mmap_site_excl,
+    }
+}
+
 #[rustfmt::skip]
 pub fn ability_description(ability_id: &str) -> (&str, &str) {
     match ability_id {
@@ -17,6 +17,7 @@
 #[macro_use]
 pub mod ui;
 pub mod audio;
+pub mod battery_saver;
 pub mod controller;
 mod credits;
 mod ecs;
@@ -26,6 +27,7 @@ pub mod hud;
 pub mod key_state;
 pub mod menu;
 pub mod mesh;
+mod preload;
 pub mod profile;
 pub mod render;
 pub mod run;
@@ -42,6 +44,7 @@ use crate::singleplayer::Singleplayer;
 use crate::ui::egui::EguiState;
 use crate::{
     audio::AudioFrontend,
+    battery_saver::BatterySaver,
     profile::Profile,
     render::{Drawer, GlobalsBindGroup},
     settings::Settings,
@@ -49,11 +52,13 @@ use crate::{
 };
 use common::clock::Clock;
 use common_base::span;
+use common_net::msg::ConnectionStats;
 use i18n::LocalizationHandle;
 use std::path::PathBuf;
 
 use std::sync::Arc;
 use tokio::runtime::Runtime;
+use tracing::warn;
 
 /// A type used to store state that is shared between all play states.
 pub struct GlobalState {
@@ -78,8 +83,19 @@ pub struct GlobalState {
     // enter the game before confirmation of successful character load
     /// An error returned by Client that needs to be displayed by the UI
     pub client_error: Option<String>,
+    /// Set when the connection to the server was lost mid-session, the main
+    /// menu then tries to log in again using the settings' `ReconnectPolicy`
+    pub reconnect_pending: bool,
+    /// Set when the server kicked us and asked us not to come back on our own,
+    /// the next login is then tried only once instead of following the
+    /// `ReconnectPolicy`
+    pub reconnect_blocked: bool,
+    /// Round trip time and jitter of the connection to the server, kept up to
+    /// date while in game
+    pub connection_stats: Option<ConnectionStats>,
     // Used to clear the shadow textures when entering a PlayState that doesn't utilise shadows
     pub clear_shadows_next_frame: bool,
+    pub battery_saver: BatterySaver,
 }
 
 impl GlobalState {
@@ -93,6 +109,15 @@ impl GlobalState {
     pub fn maintain(&mut self, dt: std::time::Duration) {
         span!(_guard, "maintain", "GlobalState::maintain");
         self.audio.maintain(dt);
+        if self.battery_saver.maintain(&self.settings.graphics) {
+            let render_mode = self
+                .settings
+                .graphics
+                .effective_render_mode(self.battery_saver.active());
+            if let Err(err) = self.window.renderer_mut().set_render_mode(render_mode) {
+                warn!(?err, "Failed to apply the battery saver render mode");
+            }
+        }
         self.window.renderer().maintain()
     }
 
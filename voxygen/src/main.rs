@@ -11,6 +11,7 @@ static GLOBAL: common_base::tracy_client::ProfiledAllocator<std::alloc::System>
 use i18n::{self, LocalizationHandle};
 use veloren_voxygen::{
     audio::AudioFrontend,
+    battery_saver::BatterySaver,
     profile::Profile,
     run,
     scene::terrain::SpriteRenderContext,
@@ -122,12 +123,15 @@ fn main() {
             \n\
             Panic Payload: {:?}\n\
             PanicInfo: {}\n\
-            Game version: {} [{}]",
+            Game version: {} [{}]\n\
+            Graphics adapter: {}",
             logs_dir.join(&log_filename).display(),
             reason,
             panic_info,
             *common::util::GIT_HASH,
-            *common::util::GIT_DATE
+            *common::util::GIT_DATE,
+            veloren_voxygen::render::selected_adapter()
+                .unwrap_or_else(|| "not selected yet".to_owned()),
         );
 
         error!(
@@ -290,7 +294,11 @@ fn main() {
         i18n,
         clipboard,
         client_error: None,
+        reconnect_pending: false,
+        reconnect_blocked: false,
+        connection_stats: None,
         clear_shadows_next_frame: false,
+        battery_saver: BatterySaver::default(),
     };
 
     run::run(global_state, event_loop);
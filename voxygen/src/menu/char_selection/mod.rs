@@ -143,6 +143,20 @@ impl PlayState for CharSelectionState {
                             Rc::clone(&self.client),
                         )));
                     },
+                    ui::Event::AcceptRules => {
+                        self.client.borrow_mut().accept_rules();
+                    },
+                    ui::Event::DeclineRules => {
+                        // Leaving the character selection disconnects from the server
+                        global_state.info_message = Some(
+                            global_state
+                                .i18n
+                                .read()
+                                .get("char_selection.rules_declined")
+                                .to_owned(),
+                        );
+                        return PlayStateResult::Pop;
+                    },
                     ui::Event::ClearCharacterListError => {
                         self.char_selection_ui.error = None;
                     },
@@ -213,6 +227,18 @@ impl PlayState for CharSelectionState {
                                 );
                                 return PlayStateResult::Pop;
                             },
+                            client::Event::Kicked {
+                                reason,
+                                reconnect_allowed,
+                            } => {
+                                global_state.info_message = Some(format!(
+                                    "{}: {}",
+                                    localized_strings.get("main.login.kicked"),
+                                    reason
+                                ));
+                                global_state.reconnect_blocked = !reconnect_allowed;
+                                return PlayStateResult::Pop;
+                            },
                             client::Event::CharacterCreated(character_id) => {
                                 self.char_selection_ui.select_character(character_id);
                             },
@@ -224,8 +250,12 @@ impl PlayState for CharSelectionState {
                     }
                 },
                 Err(err) => {
-                    global_state.info_message =
-                        Some(localized_strings.get("common.connection_lost").to_owned());
+                    if err.is_connection_lost() {
+                        global_state.reconnect_pending = true;
+                    } else {
+                        global_state.info_message =
+                            Some(localized_strings.get("common.connection_lost").to_owned());
+                    }
                     error!(?err, "[char_selection] Failed to tick the client");
                     return PlayStateResult::Pop;
                 },
@@ -21,7 +21,9 @@ use crate::{
 };
 use client::{Client, ServerInfo};
 use common::{
-    character::{CharacterId, CharacterItem, MAX_CHARACTERS_PER_PLAYER, MAX_NAME_LENGTH},
+    character::{
+        self, CharacterId, CharacterItem, CreationError, MAX_CHARACTERS_PER_PLAYER, MAX_NAME_LENGTH,
+    },
     comp::{self, humanoid, inventory::slot::EquipSlot, Inventory, Item},
     LoadoutBuilder,
 };
@@ -141,6 +143,8 @@ pub enum Event {
     DeleteCharacter(CharacterId),
     ClearCharacterListError,
     SelectCharacter(Option<CharacterId>),
+    AcceptRules,
+    DeclineRules,
 }
 
 enum Mode {
@@ -148,6 +152,7 @@ enum Mode {
         info_content: Option<InfoContent>,
 
         characters_scroll: scrollable::State,
+        rules_scroll: scrollable::State,
         character_buttons: Vec<button::State>,
         new_character_button: button::State,
         logout_button: button::State,
@@ -181,6 +186,7 @@ impl Mode {
         Self::Select {
             info_content,
             characters_scroll: Default::default(),
+            rules_scroll: Default::default(),
             character_buttons: Vec::new(),
             new_character_button: Default::default(),
             logout_button: Default::default(),
@@ -259,6 +265,8 @@ enum InfoContent {
     EditingCharacter,
     DeletingCharacter,
     CharacterError(String),
+    /// The rules of the server, which have to be accepted first
+    Rules,
 }
 
 struct Controls {
@@ -298,6 +306,8 @@ enum Message {
     CancelDeletion,
     ConfirmDeletion,
     ClearCharacterListError,
+    AcceptRules,
+    DeclineRules,
     HairStyle(u8),
     HairColor(u8),
     Skin(u8),
@@ -393,6 +403,7 @@ impl Controls {
             Mode::Select {
                 ref mut info_content,
                 ref mut characters_scroll,
+                ref mut rules_scroll,
                 ref mut character_buttons,
                 ref mut new_character_button,
                 ref mut logout_button,
@@ -441,6 +452,13 @@ impl Controls {
                     *info_content = None;
                 }
 
+                if client.rules().is_some() {
+                    *info_content = Some(InfoContent::Rules);
+                } else if info_content == &Some(InfoContent::Rules) {
+                    // The character list is loaded once the rules are accepted
+                    *info_content = Some(InfoContent::LoadingCharacters);
+                }
+
                 let server = Container::new(
                     Column::with_children(vec![
                         Text::new(&client.server_info().name)
@@ -762,6 +780,51 @@ impl Controls {
                         .align_items(Align::Center)
                         .spacing(10)
                         .into(),
+                        InfoContent::Rules => Column::with_children(vec![
+                            Text::new(i18n.get("char_selection.server_rules"))
+                                .size(fonts.cyri.scale(24))
+                                .into(),
+                            Scrollable::new(rules_scroll)
+                                .push(
+                                    Column::with_children(rules_text(
+                                        client.rules().unwrap_or_default(),
+                                        fonts,
+                                    ))
+                                    .spacing(4),
+                                )
+                                .padding(6)
+                                .scrollbar_width(5)
+                                .scroller_width(5)
+                                .width(Length::Units(352))
+                                .height(Length::Units(360))
+                                .style(style::scrollable::Style {
+                                    track: None,
+                                    scroller: style::scrollable::Scroller::Color(UI_MAIN),
+                                })
+                                .into(),
+                            Row::with_children(vec![
+                                neat_button(
+                                    no_button,
+                                    i18n.get("char_selection.decline_rules"),
+                                    FILL_FRAC_ONE,
+                                    button_style,
+                                    Some(Message::DeclineRules),
+                                ),
+                                neat_button(
+                                    yes_button,
+                                    i18n.get("char_selection.accept_rules"),
+                                    FILL_FRAC_ONE,
+                                    button_style,
+                                    Some(Message::AcceptRules),
+                                ),
+                            ])
+                            .height(Length::Units(28))
+                            .spacing(30)
+                            .into(),
+                        ])
+                        .align_items(Align::Center)
+                        .spacing(10)
+                        .into(),
                     };
 
                     let over = Container::new(over_content)
@@ -1257,6 +1320,9 @@ impl Controls {
                     Message::CreateCharacter
                 };
 
+                // Checked again by the server
+                let validation = character::validate(name, &comp::Body::Humanoid(*body));
+
                 let name_input = BackgroundContainer::new(
                     Image::new(imgs.name_input)
                         .height(Length::Units(40))
@@ -1272,17 +1338,35 @@ impl Controls {
                 )
                 .padding(Padding::new().horizontal(7).top(5));
 
-                let bottom_center = Container::new(
-                    Row::with_children(vec![
-                        rand_name.into(),
-                        name_input.into(),
-                        Space::new(Length::Units(NAME_DICE_SIZE), Length::Units(NAME_DICE_SIZE))
-                            .into(),
+                let name_row = Row::with_children(vec![
+                    rand_name.into(),
+                    name_input.into(),
+                    Space::new(Length::Units(NAME_DICE_SIZE), Length::Units(NAME_DICE_SIZE)).into(),
+                ])
+                .align_items(Align::Center)
+                .spacing(5)
+                .padding(16);
+
+                // An empty name is explained by the tooltip of the create button
+                let name_error = match &validation {
+                    Err(CreationError::NameEmpty) | Ok(()) => None,
+                    Err(error) => Some(
+                        Text::new(creation_error_text(i18n, error))
+                            .size(fonts.cyri.scale(16))
+                            .color(iced::Color::from_rgb(1.0, 0.0, 0.0)),
+                    ),
+                };
+
+                let bottom_center = Container::new(match name_error {
+                    Some(name_error) => Column::with_children(vec![
+                        name_row.into(),
+                        name_error.into(),
+                        Space::new(Length::Shrink, Length::Units(10)).into(),
                     ])
                     .align_items(Align::Center)
-                    .spacing(5)
-                    .padding(16),
-                )
+                    .into(),
+                    None => Element::from(name_row),
+                })
                 .style(style::container::Style::color(Rgba::new(0, 0, 0, 100)));
 
                 let create = neat_button(
@@ -1294,17 +1378,17 @@ impl Controls {
                     }),
                     FILL_FRAC_ONE,
                     button_style,
-                    (!name.is_empty()).then_some(confirm_msg),
+                    validation.is_ok().then_some(confirm_msg),
                 );
 
-                let create: Element<Message> = if name.is_empty() {
+                let create: Element<Message> = if let Err(error) = validation {
+                    let info = if error == CreationError::NameEmpty {
+                        i18n.get("char_selection.create_info_name")
+                    } else {
+                        i18n.get("char_selection.create_info_invalid")
+                    };
                     create
-                        .with_tooltip(tooltip_manager, move || {
-                            tooltip::text(
-                                i18n.get("char_selection.create_info_name"),
-                                tooltip_style,
-                            )
-                        })
+                        .with_tooltip(tooltip_manager, move || tooltip::text(info, tooltip_style))
                         .into()
                 } else {
                     create
@@ -1388,8 +1472,12 @@ impl Controls {
                 events.push(Event::Logout);
             },
             Message::EnterWorld => {
-                if let (Mode::Select { .. }, Some(selected)) = (&self.mode, self.selected) {
-                    events.push(Event::Play(selected));
+                if let (Mode::Select { info_content, .. }, Some(selected)) =
+                    (&self.mode, self.selected)
+                {
+                    if info_content != &Some(InfoContent::Rules) {
+                        events.push(Event::Play(selected));
+                    }
                 }
             },
             Message::Select(id) => {
@@ -1433,6 +1521,9 @@ impl Controls {
                     ..
                 } = &self.mode
                 {
+                    if character::validate(name, &comp::Body::Humanoid(*body)).is_err() {
+                        return;
+                    }
                     events.push(Event::AddCharacter {
                         alias: name.clone(),
                         mainhand: mainhand.map(String::from),
@@ -1444,6 +1535,9 @@ impl Controls {
             },
             Message::ConfirmEdit(character_id) => {
                 if let Mode::CreateOrEdit { name, body, .. } = &self.mode {
+                    if character::validate(name, &comp::Body::Humanoid(*body)).is_err() {
+                        return;
+                    }
                     events.push(Event::EditCharacter {
                         alias: name.clone(),
                         character_id,
@@ -1531,6 +1625,8 @@ impl Controls {
                     }
                 }
             },
+            Message::AcceptRules => events.push(Event::AcceptRules),
+            Message::DeclineRules => events.push(Event::DeclineRules),
             Message::CancelDeletion => {
                 if let Mode::Select { info_content, .. } = &mut self.mode {
                     if let Some(InfoContent::Deletion(_)) = info_content {
@@ -1743,6 +1839,65 @@ impl CharSelectionUi {
     pub fn render<'a>(&'a self, drawer: &mut UiDrawer<'_, 'a>) { self.ui.render(drawer); }
 }
 
+/// Lays out rules written in a small subset of markdown: lines starting with
+/// `#` are headings, lines starting with `-` or `*` are list items and empty
+/// lines separate paragraphs
+fn rules_text<'a>(rules: &str, fonts: &Fonts) -> Vec<Element<'a, Message>> {
+    rules
+        .lines()
+        .map(|line| {
+            let line = line.trim();
+            if line.starts_with('#') {
+                let level = line.chars().take_while(|c| *c == '#').count();
+                Text::new(line.trim_start_matches('#').trim_start())
+                    .size(fonts.cyri.scale(if level == 1 { 22 } else { 20 }))
+                    .color(iced::Color::from_rgb8(255, 208, 69))
+                    .into()
+            } else if let Some(item) = line.strip_prefix("- ").or_else(|| line.strip_prefix("* ")) {
+                Row::with_children(vec![
+                    Space::new(Length::Units(2), Length::Shrink).into(),
+                    Text::new("•").size(fonts.cyri.scale(17)).into(),
+                    Text::new(item.trim_start())
+                        .size(fonts.cyri.scale(17))
+                        .width(Length::Fill)
+                        .into(),
+                ])
+                .spacing(6)
+                .into()
+            } else if line.is_empty() {
+                Space::new(Length::Fill, Length::Units(8)).into()
+            } else {
+                Text::new(line)
+                    .size(fonts.cyri.scale(17))
+                    .width(Length::Fill)
+                    .into()
+            }
+        })
+        .collect()
+}
+
+/// Localized explanation of why a character can't be created
+fn creation_error_text(i18n: &Localization, error: &CreationError) -> String {
+    match error {
+        CreationError::NameEmpty => i18n.get("char_selection.create_info_name").to_string(),
+        CreationError::NameTooLong(_) => i18n
+            .get("char_selection.name_too_long")
+            .replace("{max}", &MAX_NAME_LENGTH.to_string()),
+        CreationError::NameSurroundingSpaces => i18n
+            .get("char_selection.name_surrounding_spaces")
+            .to_string(),
+        CreationError::NameRepeatedSpaces => {
+            i18n.get("char_selection.name_repeated_spaces").to_string()
+        },
+        CreationError::NameInvalidCharacter(c) => i18n
+            .get("char_selection.name_invalid_character")
+            .replace("{character}", &c.to_string()),
+        CreationError::NotHumanoid | CreationError::BodyOutOfRange(_) => {
+            i18n.get("char_selection.invalid_body").to_string()
+        },
+    }
+}
+
 #[derive(Default)]
 struct Sliders {
     hair_style: slider::State,
@@ -1,15 +1,13 @@
+use crate::settings::ReconnectPolicy;
 use client::{
     addr::ConnectionArgs,
     error::{Error as ClientError, NetworkConnectError, NetworkError},
     Client, ServerInfo,
 };
 use crossbeam_channel::{unbounded, Receiver, Sender, TryRecvError};
-use std::{
-    sync::{
-        atomic::{AtomicBool, Ordering},
-        Arc,
-    },
-    time::Duration,
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
 };
 use tokio::runtime;
 use tracing::{trace, warn};
@@ -28,6 +26,12 @@ pub enum Error {
 #[allow(clippy::large_enum_variant)] // TODO: Pending review in #587
 pub enum Msg {
     IsAuthTrusted(String),
+    /// The server couldn't be reached, `attempt` out of `max_attempts` is
+    /// about to start
+    Retrying {
+        attempt: u32,
+        max_attempts: u32,
+    },
     Done(Result<Client, Error>),
 }
 
@@ -47,6 +51,7 @@ impl ClientInit {
         username: String,
         password: String,
         runtime: Arc<runtime::Runtime>,
+        reconnect: ReconnectPolicy,
     ) -> Self {
         let (tx, rx) = unbounded();
         let (trust_tx, trust_rx) = unbounded();
@@ -66,8 +71,9 @@ impl ClientInit {
 
             let mut last_err = None;
 
-            const FOUR_MINUTES_RETRIES: u64 = 48;
-            'tries: for _ in 0..FOUR_MINUTES_RETRIES {
+            // The first attempt isn't a retry
+            let max_attempts = reconnect.max_attempts.max(1);
+            'tries: for attempt in 0..max_attempts {
                 if cancel2.load(Ordering::Relaxed) {
                     break;
                 }
@@ -105,7 +111,14 @@ impl ClientInit {
                         break 'tries;
                     },
                 }
-                tokio::time::sleep(Duration::from_secs(5)).await;
+                if attempt + 1 < max_attempts {
+                    let delay = reconnect.delay(attempt, &mut rand::thread_rng());
+                    tokio::time::sleep(delay).await;
+                    let _ = tx.send(Msg::Retrying {
+                        attempt: attempt + 2,
+                        max_attempts,
+                    });
+                }
             }
 
             // Parsing/host name resolution successful but no connection succeeded
@@ -6,13 +6,14 @@ use super::char_selection::CharSelectionState;
 #[cfg(feature = "singleplayer")]
 use crate::singleplayer::Singleplayer;
 use crate::{
+    preload::Preload,
     render::{Drawer, GlobalsBindGroup},
-    settings::Settings,
+    settings::{ReconnectPolicy, Settings},
     window::Event,
     Direction, GlobalState, PlayState, PlayStateResult,
 };
 use client::{
-    addr::ConnectionArgs,
+    addr::{CertFingerprint, ConnectionArgs, NoiseKeys, NoisePublicKey},
     error::{InitProtocolError, NetworkConnectError, NetworkError},
     Client, ServerInfo,
 };
@@ -50,6 +51,11 @@ pub struct MainMenuState {
     main_menu_ui: MainMenuUi,
     init: InitState,
     scene: Scene,
+    /// Username, password and server of the last multiplayer login, used to
+    /// reconnect after the connection was lost
+    last_login: Option<(String, String, ConnectionArgs)>,
+    /// Assets loaded in the background while connecting, only done once
+    preload: Option<Preload>,
 }
 
 impl MainMenuState {
@@ -59,6 +65,8 @@ impl MainMenuState {
             main_menu_ui: MainMenuUi::new(global_state),
             init: InitState::None,
             scene: Scene::new(global_state.window.renderer_mut()),
+            last_login: None,
+            preload: None,
         }
     }
 }
@@ -105,6 +113,7 @@ impl PlayState for MainMenuState {
                             ConnectionArgs::Mpsc(14004),
                             &mut self.init,
                             &global_state.tokio_runtime,
+                            global_state.settings.networking.reconnect,
                             &global_state.i18n,
                         );
                     },
@@ -150,6 +159,29 @@ impl PlayState for MainMenuState {
                 }
             }
         }
+        // The connection was lost mid-session, log in again
+        if core::mem::take(&mut global_state.reconnect_pending) {
+            match self.last_login.clone() {
+                Some((username, password, connection_args)) => {
+                    self.main_menu_ui.reconnecting();
+                    attempt_login(
+                        &mut global_state.info_message,
+                        username,
+                        password,
+                        connection_args,
+                        &mut self.init,
+                        &global_state.tokio_runtime,
+                        global_state.settings.networking.reconnect,
+                        &global_state.i18n,
+                    );
+                },
+                None => {
+                    global_state.info_message =
+                        Some(localized_strings.get("common.connection_lost").to_owned());
+                },
+            }
+        }
+
         // Handle window events.
         for event in events {
             // Pass all events to the ui first.
@@ -163,6 +195,11 @@ impl PlayState for MainMenuState {
                 _ => {},
             }
         }
+        // Start loading the assets needed early in the session while connecting
+        if self.preload.is_none() && self.init.client().is_some() {
+            self.preload = Some(Preload::start());
+        }
+
         // Poll client creation.
         match self.init.client().and_then(|init| init.poll()) {
             Some(InitMsg::Done(Ok(mut client))) => {
@@ -184,6 +221,12 @@ impl PlayState for MainMenuState {
                         .replace("{init_fail_reason}", e.as_str()),
                 );
             },
+            Some(InitMsg::Retrying {
+                attempt,
+                max_attempts,
+            }) => {
+                self.main_menu_ui.connection_retrying(attempt, max_attempts);
+            },
             Some(InitMsg::IsAuthTrusted(auth_server)) => {
                 if global_state
                     .settings
@@ -238,14 +281,21 @@ impl PlayState for MainMenuState {
             }
         }
 
-        // Poll renderer pipeline creation
+        // Poll renderer pipeline creation and asset preloading
         if let InitState::Pipeline(..) = &self.init {
-            // If not complete go to char select screen
-            if global_state
-                .window
-                .renderer()
-                .pipeline_creation_status()
-                .is_none()
+            let preloaded = self.preload.as_ref().map_or(true, |preload| {
+                if !preload.is_finished() {
+                    self.main_menu_ui.preload_progress(preload.progress());
+                }
+                preload.is_finished()
+            });
+            // If both are complete go to char select screen
+            if preloaded
+                && global_state
+                    .window
+                    .renderer()
+                    .pipeline_creation_status()
+                    .is_none()
             {
                 // Always succeeds since we check above
                 if let InitState::Pipeline(client) =
@@ -278,11 +328,40 @@ impl PlayState for MainMenuState {
                     if !net_settings.servers.contains(&server_address) {
                         net_settings.servers.push(server_address.clone());
                     }
+                    let pinned_key = net_settings.pinned_server_keys.get(&server_address);
+                    let encryption = match pinned_key.map(|key| key.parse::<NoisePublicKey>()) {
+                        None => None,
+                        Some(Ok(key)) => Some(NoiseKeys::default().with_remote_key(key)),
+                        Some(Err(e)) => {
+                            global_state.info_message =
+                                Some(format!("Pinned key of {}: {}", server_address, e));
+                            continue;
+                        },
+                    };
+                    let use_tls = net_settings.tls_servers.contains(&server_address);
+                    let pinned_certificate = net_settings.pinned_certificates.get(&server_address);
+                    let fingerprint = match pinned_certificate
+                        .map(|cert| cert.parse::<CertFingerprint>())
+                    {
+                        None => None,
+                        Some(Ok(fingerprint)) => Some(fingerprint),
+                        Some(Err(e)) => {
+                            global_state.info_message =
+                                Some(format!("Pinned certificate of {}: {}", server_address, e));
+                            continue;
+                        },
+                    };
                     global_state
                         .settings
                         .save_to_file_warn(&global_state.config_dir);
 
-                    let connection_args = if use_quic {
+                    let connection_args = if use_tls || fingerprint.is_some() {
+                        ConnectionArgs::TcpTls {
+                            hostname: server_address,
+                            prefer_ipv6: false,
+                            fingerprint,
+                        }
+                    } else if use_quic {
                         ConnectionArgs::Quic {
                             hostname: server_address,
                             prefer_ipv6: false,
@@ -291,8 +370,17 @@ impl PlayState for MainMenuState {
                         ConnectionArgs::Tcp {
                             hostname: server_address,
                             prefer_ipv6: false,
+                            encryption,
                         }
                     };
+                    self.last_login =
+                        Some((username.clone(), password.clone(), connection_args.clone()));
+                    // Don't keep knocking on the door of a server which kicked us
+                    let reconnect = if core::mem::take(&mut global_state.reconnect_blocked) {
+                        ReconnectPolicy::no_retries()
+                    } else {
+                        global_state.settings.networking.reconnect
+                    };
                     attempt_login(
                         &mut global_state.info_message,
                         username,
@@ -300,6 +388,7 @@ impl PlayState for MainMenuState {
                         connection_args,
                         &mut self.init,
                         &global_state.tokio_runtime,
+                        reconnect,
                         &global_state.i18n,
                     );
                 },
@@ -330,6 +419,7 @@ impl PlayState for MainMenuState {
                 #[cfg(feature = "singleplayer")]
                 MainMenuEvent::StartSingleplayer => {
                     let singleplayer = Singleplayer::new(&global_state.tokio_runtime);
+                    self.last_login = None;
 
                     global_state.singleplayer = Some(singleplayer);
                 },
@@ -418,6 +508,16 @@ fn get_client_msg_error(
         }
     };
 
+    let incompatible_version =
+        |server: [u32; 3], minimum_client: [u32; 3], client: [u32; 3]| -> String {
+            let version = |v: [u32; 3]| format!("{}.{}.{}", v[0], v[1], v[2]);
+            localization
+                .get("main.login.incompatible_version")
+                .replace("{server_version}", &version(server))
+                .replace("{min_version}", &version(minimum_client))
+                .replace("{client_version}", &version(client))
+        };
+
     use client::Error;
     match error {
         InitError::ClientError {
@@ -444,6 +544,13 @@ fn get_client_msg_error(
                 format!("{}: {}", localization.get("main.login.banned"), reason)
             },
             Error::InvalidCharacter => localization.get("main.login.invalid_character").into(),
+            Error::NetworkErr(NetworkError::ConnectFailed(NetworkConnectError::Handshake(
+                InitProtocolError::VersionMismatch {
+                    server_version,
+                    min_client,
+                    client_version,
+                },
+            ))) => incompatible_version(server_version, min_client, client_version),
             Error::NetworkErr(NetworkError::ConnectFailed(NetworkConnectError::Handshake(
                 InitProtocolError::WrongVersion(_),
             ))) => net_error(
@@ -452,6 +559,12 @@ fn get_client_msg_error(
                     .to_owned(),
                 mismatched_server_info,
             ),
+            Error::NetworkErr(NetworkError::ConnectFailed(NetworkConnectError::Handshake(
+                e @ InitProtocolError::EncryptionMismatch { .. },
+            ))) => net_error(e.to_string(), mismatched_server_info),
+            Error::NetworkErr(NetworkError::ConnectFailed(NetworkConnectError::Handshake(
+                e @ InitProtocolError::UntrustedKey,
+            ))) => net_error(e.to_string(), mismatched_server_info),
             Error::NetworkErr(e) => net_error(e.to_string(), mismatched_server_info),
             Error::ParticipantErr(e) => net_error(e.to_string(), mismatched_server_info),
             Error::StreamErr(e) => net_error(e.to_string(), mismatched_server_info),
@@ -491,6 +604,7 @@ fn get_client_msg_error(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn attempt_login(
     info_message: &mut Option<String>,
     username: String,
@@ -498,6 +612,7 @@ fn attempt_login(
     connection_args: ConnectionArgs,
     init: &mut InitState,
     runtime: &Arc<runtime::Runtime>,
+    reconnect: ReconnectPolicy,
     localized_strings: &LocalizationHandle,
 ) {
     let localization = localized_strings.read();
@@ -529,6 +644,7 @@ fn attempt_login(
             username,
             password,
             Arc::clone(runtime),
+            reconnect,
         ));
     }
 }
@@ -100,8 +100,35 @@ impl Screen {
         let frame_id = self.loading_animation.frames[frame_index as usize];
 
         let children = match connection_state {
-            ConnectionState::InProgress => {
-                let tip = if show_tip {
+            ConnectionState::InProgress
+            | ConnectionState::Retrying { .. }
+            | ConnectionState::Preloading { .. } => {
+                let tip = if let ConnectionState::Preloading { progress } = connection_state {
+                    let preloading = i18n
+                        .get("main.connecting.preloading")
+                        .replace("{progress}", &format!("{:.0}", progress * 100.0));
+                    Container::new(Text::new(preloading).size(fonts.cyri.scale(25)))
+                        .width(Length::Fill)
+                        .height(Length::Fill)
+                        .center_x()
+                        .align_y(Align::End)
+                        .into()
+                } else if let ConnectionState::Retrying {
+                    attempt,
+                    max_attempts,
+                } = connection_state
+                {
+                    let retrying = i18n
+                        .get("main.connecting.retrying")
+                        .replace("{attempt}", &attempt.to_string())
+                        .replace("{max_attempts}", &max_attempts.to_string());
+                    Container::new(Text::new(retrying).size(fonts.cyri.scale(25)))
+                        .width(Length::Fill)
+                        .height(Length::Fill)
+                        .center_x()
+                        .align_y(Align::End)
+                        .into()
+                } else if show_tip {
                     let tip = &i18n.get_variation("loading.tips", self.tip_number);
                     let mut new_tip = String::with_capacity(tip.len());
                     let mut last_index = 0;
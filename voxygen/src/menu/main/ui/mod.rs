@@ -101,7 +101,19 @@ pub struct LoginInfo {
 
 enum ConnectionState {
     InProgress,
-    AuthTrustPrompt { auth_server: String, msg: String },
+    /// The server couldn't be reached, waiting for the next attempt
+    Retrying {
+        attempt: u32,
+        max_attempts: u32,
+    },
+    /// Connected, waiting for the assets needed early in the session to load
+    Preloading {
+        progress: f32,
+    },
+    AuthTrustPrompt {
+        auth_server: String,
+        msg: String,
+    },
 }
 
 enum Screen {
@@ -473,6 +485,27 @@ impl Controls {
         }
     }
 
+    fn connection_retrying(&mut self, attempt: u32, max_attempts: u32) {
+        if let Screen::Connecting {
+            connection_state, ..
+        } = &mut self.screen
+        {
+            *connection_state = ConnectionState::Retrying {
+                attempt,
+                max_attempts,
+            };
+        }
+    }
+
+    fn preload_progress(&mut self, progress: f32) {
+        if let Screen::Connecting {
+            connection_state, ..
+        } = &mut self.screen
+        {
+            *connection_state = ConnectionState::Preloading { progress };
+        }
+    }
+
     fn connection_error(&mut self, error: String) {
         if matches!(&self.screen, Screen::Connecting { .. })
             || matches!(&self.screen, Screen::Login { .. })
@@ -562,6 +595,21 @@ impl MainMenuUi {
 
     pub fn show_info(&mut self, msg: String) { self.controls.connection_error(msg); }
 
+    pub fn connection_retrying(&mut self, attempt: u32, max_attempts: u32) {
+        self.controls.connection_retrying(attempt, max_attempts);
+    }
+
+    pub fn preload_progress(&mut self, progress: f32) { self.controls.preload_progress(progress); }
+
+    /// Shows the connecting screen without a login attempt by the user, used
+    /// when reconnecting after the connection was lost
+    pub fn reconnecting(&mut self) {
+        self.controls.screen = Screen::Connecting {
+            screen: connecting::Screen::new(&mut self.ui),
+            connection_state: ConnectionState::InProgress,
+        };
+    }
+
     pub fn connected(&mut self) { self.controls.exit_connect_screen(); }
 
     pub fn cancel_connection(&mut self) { self.controls.exit_connect_screen(); }
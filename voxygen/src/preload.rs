@@ -0,0 +1,185 @@
+//! Loads assets that are needed early in a session ahead of time, while the
+//! loading screen is shown, so that they don't have to be loaded mid-frame.
+//! Assets that aren't in the preload manifest are still loaded lazily.
+//!
+//! How long preloading took is logged, as well as the frame time spikes during
+//! the start of the session, see [`StartupFrameSpikes`].
+
+use crate::audio::soundcache::OggSound;
+use common::assets::{self, AssetExt, DotVoxAsset, Image};
+use rayon::prelude::*;
+use serde::Deserialize;
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+use tracing::{info, warn};
+
+/// Assets of each chunk of work handed to the thread pool
+const CHUNK_SIZE: usize = 32;
+
+/// Asset specifiers to preload, grouped by the kind of asset
+#[derive(Deserialize)]
+struct PreloadManifest {
+    models: Vec<String>,
+    images: Vec<String>,
+    sounds: Vec<String>,
+}
+
+impl assets::Asset for PreloadManifest {
+    type Loader = assets::RonLoader;
+
+    const EXTENSION: &'static str = "ron";
+}
+
+#[derive(Default)]
+struct Progress {
+    loaded: AtomicUsize,
+    total: AtomicUsize,
+    finished: AtomicBool,
+}
+
+/// Marks preloading as finished once dropped, so the loading screen doesn't
+/// wait forever if the preload thread bails out or panics
+struct FinishOnDrop(Arc<Progress>);
+
+impl Drop for FinishOnDrop {
+    fn drop(&mut self) { self.0.finished.store(true, Ordering::Release); }
+}
+
+/// Handle to assets being preloaded in the background
+pub struct Preload {
+    progress: Arc<Progress>,
+}
+
+impl Preload {
+    /// Starts loading all the assets of the preload manifest on a thread pool
+    pub fn start() -> Self {
+        let progress = Arc::new(Progress::default());
+        let worker_progress = Arc::clone(&progress);
+        let spawned = std::thread::Builder::new()
+            .name("asset-preload".into())
+            .spawn(move || {
+                let worker_progress = FinishOnDrop(worker_progress);
+                let worker_progress = &worker_progress.0;
+                let start = Instant::now();
+                let manifest = match PreloadManifest::load("voxygen.preload_manifest") {
+                    Ok(manifest) => manifest.read(),
+                    Err(error) => {
+                        warn!(?error, "Could not load the preload manifest");
+                        return;
+                    },
+                };
+                let (models, images, sounds) = (
+                    resolve::<DotVoxAsset>(&manifest.models),
+                    resolve::<Image>(&manifest.images),
+                    resolve::<OggSound>(&manifest.sounds),
+                );
+                worker_progress.total.store(
+                    models.len() + images.len() + sounds.len(),
+                    Ordering::Relaxed,
+                );
+
+                load::<DotVoxAsset>(&models, worker_progress);
+                load::<Image>(&images, worker_progress);
+                load::<OggSound>(&sounds, worker_progress);
+                info!(
+                    assets = worker_progress.loaded.load(Ordering::Relaxed),
+                    elapsed = ?start.elapsed(),
+                    "Finished preloading assets"
+                );
+            });
+        if let Err(error) = spawned {
+            warn!(?error, "Could not spawn the asset preload thread");
+            progress.finished.store(true, Ordering::Release);
+        }
+
+        Self { progress }
+    }
+
+    /// Fraction of the assets that were loaded so far
+    pub fn progress(&self) -> f32 {
+        let total = self.progress.total.load(Ordering::Relaxed);
+        if total == 0 {
+            0.0
+        } else {
+            self.progress.loaded.load(Ordering::Relaxed) as f32 / total as f32
+        }
+    }
+
+    pub fn is_finished(&self) -> bool { self.progress.finished.load(Ordering::Acquire) }
+}
+
+/// Counts the frames that took unusually long during the start of a session,
+/// when assets that weren't preloaded are loaded mid-frame, and logs them once
+/// that's over. Compare runs with an emptied preload manifest to tell how much
+/// preloading helps.
+pub struct StartupFrameSpikes {
+    start: Instant,
+    frames: u32,
+    spikes: u32,
+    longest: Duration,
+    reported: bool,
+}
+
+impl Default for StartupFrameSpikes {
+    fn default() -> Self {
+        Self {
+            start: Instant::now(),
+            frames: 0,
+            spikes: 0,
+            longest: Duration::ZERO,
+            reported: false,
+        }
+    }
+}
+
+impl StartupFrameSpikes {
+    /// How long the start of a session is observed
+    const DURATION: Duration = Duration::from_secs(120);
+    /// Frames taking longer than this count as a spike
+    const SPIKE: Duration = Duration::from_millis(50);
+
+    pub fn record(&mut self, frame_time: Duration) {
+        if self.reported {
+            return;
+        }
+        if self.start.elapsed() > Self::DURATION {
+            info!(
+                frames = self.frames,
+                spikes = self.spikes,
+                longest = ?self.longest,
+                "Frame time spikes during the start of the session"
+            );
+            self.reported = true;
+            return;
+        }
+        self.frames += 1;
+        if frame_time > Self::SPIKE {
+            self.spikes += 1;
+        }
+        self.longest = self.longest.max(frame_time);
+    }
+}
+
+fn resolve<T: assets::DirLoadable>(specifiers: &[String]) -> Vec<String> {
+    let (ids, errors) = assets::bulk_ids::<T>(specifiers);
+    for error in errors {
+        warn!(?error, "Could not read preload directory");
+    }
+    ids
+}
+
+fn load<T: assets::Compound>(ids: &[String], progress: &Progress) {
+    ids.par_chunks(CHUNK_SIZE).for_each(|chunk| {
+        assets::load_bulk::<T>(chunk, |id, result| {
+            if let Err(error) = result {
+                warn!(?id, ?error, "Could not preload asset");
+            }
+            progress.loaded.fetch_add(1, Ordering::Relaxed);
+        })
+    });
+}
@@ -35,7 +35,9 @@ pub use self::{
             Instance as SpriteInstance, SpriteGlobalsBindGroup, SpriteVerts,
             Vertex as SpriteVertex, VERT_PAGE_SIZE as SPRITE_VERT_PAGE_SIZE,
         },
-        terrain::{Locals as TerrainLocals, TerrainLayout, Vertex as TerrainVertex},
+        terrain::{
+            CaveAmbientLight, Locals as TerrainLocals, TerrainLayout, Vertex as TerrainVertex,
+        },
         trail::Vertex as TrailVertex,
         ui::{
             create_quad as create_ui_quad,
@@ -46,12 +48,13 @@ pub use self::{
         GlobalModel, Globals, GlobalsBindGroup, GlobalsLayouts, Light, Shadow,
     },
     renderer::{
+        describe_adapter,
         drawer::{
             DebugDrawer, Drawer, FigureDrawer, FigureShadowDrawer, FirstPassDrawer, ParticleDrawer,
             PreparedUiDrawer, SecondPassDrawer, ShadowPassDrawer, SpriteDrawer, TerrainDrawer,
             TerrainShadowDrawer, ThirdPassDrawer, TrailDrawer, UiDrawer,
         },
-        ColLightInfo, Renderer,
+        selected_adapter, ColLightInfo, Renderer,
     },
     texture::Texture,
 };
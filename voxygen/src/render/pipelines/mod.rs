@@ -14,6 +14,7 @@ pub mod terrain;
 pub mod trail;
 pub mod ui;
 
+use self::terrain::CaveAmbientLight;
 use super::{Consts, Texture};
 use crate::scene::camera::CameraMode;
 use bytemuck::{Pod, Zeroable};
@@ -63,8 +64,8 @@ pub struct Globals {
     ambiance: f32,
     cam_mode: u32,
     sprite_render_distance: f32,
-    /// To keep 16-byte-aligned.
-    globals_dummy: f32,
+    /// Floor of the terrain light underground, see [`CaveAmbientLight`]
+    cave_ambient_light: f32,
 }
 
 #[repr(C)]
@@ -105,6 +106,7 @@ impl Globals {
         ambiance: f32,
         cam_mode: CameraMode,
         sprite_render_distance: f32,
+        cave_ambient_light: CaveAmbientLight,
     ) -> Self {
         Self {
             view_mat: view_mat.into_col_arrays(),
@@ -153,7 +155,7 @@ impl Globals {
             ambiance: ambiance.clamped(0.0, 1.0),
             cam_mode: cam_mode as u32,
             sprite_render_distance,
-            globals_dummy: 0.0,
+            cave_ambient_light: cave_ambient_light.0.clamped(0.0, CaveAmbientLight::MAX),
         }
     }
 
@@ -197,6 +199,7 @@ impl Default for Globals {
             1.0,
             CameraMode::ThirdPerson,
             250.0,
+            CaveAmbientLight::default(),
         )
     }
 }
@@ -164,6 +164,24 @@ impl Locals {
 
 pub type BoundLocals = Bound<Consts<Locals>>;
 
+/// Light level underground terrain never drops below, so that the shapes of
+/// caves without light sources can still be made out. Unlike the ambiance of
+/// the surface it grows the deeper the camera goes.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct CaveAmbientLight(pub f32);
+
+impl CaveAmbientLight {
+    /// Depth in blocks below the surface at which the light is the brightest
+    pub const FULL_DEPTH: f32 = 64.0;
+    pub const MAX: f32 = 0.15;
+
+    /// Light at `depth` blocks below the surface, 0 at the surface and
+    /// growing linearly up to [`Self::MAX`]
+    pub fn at_depth(depth: f32) -> Self {
+        Self((depth / Self::FULL_DEPTH).clamped(0.0, 1.0) * Self::MAX)
+    }
+}
+
 pub struct TerrainLayout {
     pub locals: wgpu::BindGroupLayout,
 }
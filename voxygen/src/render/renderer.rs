@@ -33,7 +33,8 @@ use common_base::span;
 use core::convert::TryFrom;
 #[cfg(feature = "egui-ui")]
 use egui_wgpu_backend::wgpu::TextureFormat;
-use std::sync::Arc;
+use lazy_static::lazy_static;
+use std::sync::{Arc, Mutex};
 use tracing::{error, info, warn};
 use vek::*;
 
@@ -46,6 +47,53 @@ pub type ColLightInfo = (Vec<[u8; 4]>, Vec2<u16>);
 const QUAD_INDEX_BUFFER_U16_START_VERT_LEN: u16 = 3000;
 const QUAD_INDEX_BUFFER_U32_START_VERT_LEN: u32 = 3000;
 
+lazy_static! {
+    /// Description of the graphics adapter in use, for crash reports
+    static ref SELECTED_ADAPTER: Mutex<Option<String>> = Mutex::new(None);
+}
+
+/// Description of the graphics adapter in use, `None` until a renderer was
+/// created
+pub fn selected_adapter() -> Option<String> {
+    // Called from the panic hook, so never block
+    SELECTED_ADAPTER
+        .try_lock()
+        .ok()
+        .and_then(|adapter| adapter.clone())
+}
+
+/// Name, type, backend and the PCI ids of the adapter, wgpu doesn't tell the
+/// driver version
+pub fn describe_adapter(info: &wgpu::AdapterInfo) -> String {
+    format!(
+        "{} ({:?}, {:?}, vendor {:#06x}, device {:#06x})",
+        info.name, info.device_type, info.backend, info.vendor, info.device
+    )
+}
+
+/// Picks one of the usable `adapters`: the first one with a name containing
+/// `preference`, otherwise the first discrete GPU since laptops with hybrid
+/// graphics tend to default to the integrated one. `None` leaves the choice to
+/// wgpu.
+fn choose_adapter(adapters: &[wgpu::AdapterInfo], preference: Option<&str>) -> Option<usize> {
+    if let Some(preference) = preference {
+        let lowercase = preference.to_lowercase();
+        match adapters
+            .iter()
+            .position(|info| info.name.to_lowercase().contains(&lowercase))
+        {
+            Some(i) => return Some(i),
+            None => warn!(
+                ?preference,
+                "The preferred graphics adapter was not found, falling back to the default one"
+            ),
+        }
+    }
+    adapters
+        .iter()
+        .position(|info| info.device_type == wgpu::DeviceType::DiscreteGpu)
+}
+
 /// A type that stores all the layouts associated with this renderer that never
 /// change when the RenderMode is modified.
 struct ImmutableLayouts {
@@ -178,14 +226,22 @@ pub struct Renderer {
 
     // To remember the backend info after initialization for debug purposes
     graphics_backend: String,
+    adapter_info: wgpu::AdapterInfo,
+    // Names of all the adapters found at initialization, they can only be switched with a
+    // restart
+    adapter_names: Vec<String>,
+    adapter_preference: Option<String>,
 }
 
 impl Renderer {
     /// Create a new `Renderer` from a variety of backend-specific components
     /// and the window targets.
+    /// `adapter_preference` picks the first graphics adapter with a name
+    /// containing it, the `WGPU_ADAPTER` environment variable overrides it.
     pub fn new(
         window: &winit::window::Window,
         mode: RenderMode,
+        adapter_preference: Option<&str>,
         runtime: &tokio::runtime::Runtime,
     ) -> Result<Self, RenderError> {
         let (pipeline_modes, mut other_modes) = mode.split();
@@ -241,6 +297,11 @@ impl Renderer {
             );
         }
 
+        let adapter_names = adapters
+            .iter()
+            .map(|(_, adapter)| adapter.get_info().name)
+            .collect::<Vec<_>>();
+
         let adapter = match std::env::var("WGPU_ADAPTER").ok() {
             Some(filter) if !filter.is_empty() => adapters.into_iter().find_map(|(i, adapter)| {
                 let info = adapter.get_info();
@@ -250,10 +311,21 @@ impl Renderer {
                 full_name.contains(&filter).then(|| adapter)
             }),
             Some(_) | None => {
-                runtime.block_on(instance.request_adapter(&wgpu::RequestAdapterOptionsBase {
-                    power_preference: wgpu::PowerPreference::HighPerformance,
-                    compatible_surface: Some(&surface),
-                }))
+                let usable = adapters
+                    .into_iter()
+                    .map(|(_, adapter)| adapter)
+                    .filter(|adapter| adapter.get_swap_chain_preferred_format(&surface).is_some())
+                    .collect::<Vec<_>>();
+                let infos = usable.iter().map(|a| a.get_info()).collect::<Vec<_>>();
+                match choose_adapter(&infos, adapter_preference) {
+                    Some(i) => usable.into_iter().nth(i),
+                    None => runtime.block_on(instance.request_adapter(
+                        &wgpu::RequestAdapterOptionsBase {
+                            power_preference: wgpu::PowerPreference::HighPerformance,
+                            compatible_surface: Some(&surface),
+                        },
+                    )),
+                }
             },
         }
         .ok_or(RenderError::CouldNotFindAdapter)?;
@@ -268,6 +340,10 @@ impl Renderer {
             "selected graphics device"
         );
         let graphics_backend = format!("{:?}", &info.backend);
+        if let Ok(mut selected) = SELECTED_ADAPTER.lock() {
+            *selected = Some(describe_adapter(&info));
+        }
+        let adapter_info = info.clone();
 
         let limits = wgpu::Limits {
             max_push_constant_size: 64,
@@ -529,12 +605,25 @@ impl Renderer {
             is_minimized: false,
 
             graphics_backend,
+            adapter_info,
+            adapter_names,
+            adapter_preference: adapter_preference.map(str::to_owned),
         })
     }
 
     /// Get the graphics backend being used
     pub fn graphics_backend(&self) -> &str { &self.graphics_backend }
 
+    /// Get the graphics adapter being used
+    pub fn adapter_info(&self) -> &wgpu::AdapterInfo { &self.adapter_info }
+
+    /// Names of the graphics adapters that were found at initialization
+    pub fn adapter_names(&self) -> &[String] { &self.adapter_names }
+
+    /// The adapter preference the renderer was created with, a different one
+    /// only applies after a restart
+    pub fn adapter_preference(&self) -> Option<&str> { self.adapter_preference.as_deref() }
+
     /// Check the status of the intial pipeline creation
     /// Returns `None` if complete
     /// Returns `Some((total, complete))` if in progress
@@ -1461,3 +1550,32 @@ fn create_quad_index_buffer_u32(device: &wgpu::Device, vert_length: usize) -> Bu
 
     Buffer::new(device, wgpu::BufferUsage::INDEX, &indices)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn adapter(name: &str, device_type: wgpu::DeviceType) -> wgpu::AdapterInfo {
+        wgpu::AdapterInfo {
+            name: name.to_owned(),
+            vendor: 0,
+            device: 0,
+            device_type,
+            backend: wgpu::Backend::Vulkan,
+        }
+    }
+
+    #[test]
+    fn adapter_selection() {
+        let adapters = [
+            adapter("Intel(R) UHD Graphics 620", wgpu::DeviceType::IntegratedGpu),
+            adapter("NVIDIA GeForce MX150", wgpu::DeviceType::DiscreteGpu),
+        ];
+        assert_eq!(choose_adapter(&adapters, None), Some(1));
+        assert_eq!(choose_adapter(&adapters, Some("intel")), Some(0));
+        // A preferred adapter that is gone falls back to the default choice
+        assert_eq!(choose_adapter(&adapters, Some("Radeon")), Some(1));
+        assert_eq!(choose_adapter(&adapters[..1], Some("Radeon")), None);
+        assert_eq!(choose_adapter(&[], None), None);
+    }
+}
@@ -235,8 +235,10 @@ fn handle_main_events_cleared(
         // Enforce an FPS cap for the non-game session play states to prevent them
         // running at hundreds/thousands of FPS resulting in high GPU usage for
         // effectively doing nothing.
-        let max_fps = get_fps(global_state.settings.graphics.max_fps);
-        let max_background_fps = get_fps(global_state.settings.graphics.max_background_fps);
+        let graphics = &global_state.settings.graphics;
+        let battery_saver = global_state.battery_saver.active();
+        let max_fps = get_fps(graphics.effective_max_fps(battery_saver));
+        let max_background_fps = get_fps(graphics.effective_max_background_fps(battery_saver));
         const TITLE_SCREEN_FPS_CAP: u32 = 60;
         let target_fps = if !global_state.window.focused {
             u32::min(max_background_fps, max_fps)
@@ -1,4 +1,4 @@
-use common::{terrain::TerrainGrid, vol::ReadVol};
+use common::{ray::RayCast, terrain::TerrainGrid, vol::ReadVol};
 use common_base::span;
 use core::{f32::consts::PI, fmt::Debug};
 use num::traits::{real::Real, FloatConst};
@@ -55,6 +55,8 @@ pub struct Camera {
     mode: CameraMode,
 
     last_time: Option<f64>,
+    /// Offset of the view, e.g. from camera shakes
+    shake: Vec2<f32>,
 
     dependents: Dependents,
     frustum: Frustum<f32>,
@@ -335,6 +337,7 @@ impl Camera {
             mode,
 
             last_time: None,
+            shake: Vec2::zero(),
 
             dependents: Dependents {
                 view_mat: Mat4::identity(),
@@ -401,16 +404,12 @@ impl Camera {
                 })
                 .chain([(self.focus - self.forward() * (self.dist + 0.5))])  // Padding to behind
                 .map(|pos| {
-                    match terrain
-                        .ray(self.focus, pos)
-                        .ignore_error()
-                        .max_iter(500)
+                    match RayCast::between(terrain, self.focus, pos)
                         .until(is_transparent)
                         .cast()
                     {
-                        (d, Ok(Some(_))) => f32::min(d, self.tgt_dist),
-                        (_, Ok(None)) => self.dist,
-                        (_, Err(_)) => self.dist,
+                        Some(hit) => f32::min(hit.dist, self.tgt_dist),
+                        None => self.dist,
                     }
                     .max(0.0)
                 })
@@ -441,16 +440,12 @@ impl Camera {
         let dist = {
             let (start, end) = (self.focus - self.forward() * self.dist, self.focus);
 
-            match terrain
-                .ray(start, end)
-                .ignore_error()
-                .max_iter(500)
+            match RayCast::between(terrain, start, end)
                 .until(|b| !is_transparent(b))
                 .cast()
             {
-                (d, Ok(Some(_))) => f32::min(self.dist - d - 0.03, self.dist),
-                (_, Ok(None)) => self.dist,
-                (_, Err(_)) => self.dist,
+                Some(hit) => f32::min(self.dist - hit.dist - 0.03, self.dist),
+                None => self.dist,
             }
             .max(0.0)
         };
@@ -462,7 +457,7 @@ impl Camera {
 
     fn compute_dependents_helper(&self, dist: f32) -> Dependents {
         let view_mat = Mat4::<f32>::identity()
-            * Mat4::translation_3d(-Vec3::unit_z() * dist)
+            * Mat4::translation_3d(self.shake.with_z(-dist))
             * Mat4::rotation_z(self.ori.z)
             * Mat4::rotation_x(self.ori.y)
             * Mat4::rotation_y(self.ori.x)
@@ -501,6 +496,10 @@ impl Camera {
 
     pub fn frustum(&self) -> &Frustum<f32> { &self.frustum }
 
+    /// Moves the view by `shake` blocks along the horizontal and vertical axes
+    /// of the screen, applied with the next `compute_dependents`
+    pub fn set_shake(&mut self, shake: Vec2<f32>) { self.shake = shake; }
+
     pub fn dependents(&self) -> Dependents { self.dependents }
 
     /// Rotate the camera about its focus by the given delta, limiting the input
@@ -1,7 +1,7 @@
 use crate::render::{
     Bound, Consts, DebugDrawer, DebugLocals, DebugVertex, Mesh, Model, Quad, Renderer, Tri,
 };
-use common::util::srgba_to_linear;
+use common::{terrain::TerrainChunkSize, util::srgba_to_linear, vol::RectVolSize};
 use hashbrown::{HashMap, HashSet};
 use tracing::warn;
 use vek::*;
@@ -19,6 +19,11 @@ pub enum DebugShape {
         radius: f32,
         height: f32,
     },
+    /// Planes standing between pairs of points, visible from both sides
+    VerticalPlanes {
+        segments: Vec<[Vec2<f32>; 2]>,
+        height: f32,
+    },
 }
 
 impl DebugShape {
@@ -140,6 +145,15 @@ impl DebugShape {
                 // 3) Draw second half-cylinder
                 draw_cylinder_sector(&mut mesh, p1, HALF_SECTORS, TOTAL);
             },
+            DebugShape::VerticalPlanes { segments, height } => {
+                let h = Vec3::new(0.0, 0.0, *height);
+                for [a, b] in segments {
+                    let a = Vec3::new(a.x, a.y, 0.0);
+                    let b = Vec3::new(b.x, b.y, 0.0);
+                    mesh.push_quad(quad(a, b, b + h, a + h));
+                    mesh.push_quad(quad(b, a, a + h, b + h));
+                }
+            },
         }
         mesh
     }
@@ -227,3 +241,195 @@ impl Debug {
 impl Default for Debug {
     fn default() -> Debug { Debug::new() }
 }
+
+/// Height of the chunk border planes, centered on the player
+const CHUNK_BORDER_HEIGHT: f32 = 128.0;
+const CHUNK_BORDER_COLORS: [[f32; 4]; 2] = [[1.0, 0.0, 1.0, 0.3], [0.0, 1.0, 1.0, 0.3]];
+
+/// Debug overlay drawing the borders of the chunks within the view distance,
+/// to find artifacts where the meshes of two chunks meet
+#[derive(Default)]
+pub struct ChunkBorderHighlight {
+    /// one shape for each color, the chunk the planes are centered on and the
+    /// view distance they were created for
+    shapes: Option<([DebugShapeId; 2], Vec2<i32>, u32)>,
+}
+
+impl ChunkBorderHighlight {
+    pub fn maintain(
+        &mut self,
+        debug: &mut Debug,
+        enabled: bool,
+        player_pos: Vec3<f32>,
+        view_distance: u32,
+    ) {
+        if !enabled {
+            self.clear(debug);
+            return;
+        }
+        let chunk_size = TerrainChunkSize::RECT_SIZE.map(|e| e as i32);
+        let center = player_pos
+            .xy()
+            .map2(chunk_size, |e, sz| (e.floor() as i32).div_euclid(sz));
+        if !matches!(self.shapes, Some((_, c, vd)) if c == center && vd == view_distance) {
+            self.clear(debug);
+            let [even, odd] = border_segments(view_distance as i32);
+            let height = CHUNK_BORDER_HEIGHT;
+            let ids = [
+                debug.add_shape(DebugShape::VerticalPlanes {
+                    segments: even,
+                    height,
+                }),
+                debug.add_shape(DebugShape::VerticalPlanes {
+                    segments: odd,
+                    height,
+                }),
+            ];
+            self.shapes = Some((ids, center, view_distance));
+        }
+        if let Some((ids, center, _)) = self.shapes {
+            let origin = center * chunk_size;
+            let pos = [
+                origin.x as f32,
+                origin.y as f32,
+                player_pos.z - CHUNK_BORDER_HEIGHT / 2.0,
+                0.0,
+            ];
+            for (id, color) in ids.iter().zip(CHUNK_BORDER_COLORS.iter()) {
+                debug.set_context(*id, pos, *color, [0.0, 0.0, 0.0, 1.0]);
+            }
+        }
+    }
+
+    fn clear(&mut self, debug: &mut Debug) {
+        if let Some((ids, _, _)) = self.shapes.take() {
+            ids.iter().for_each(|id| debug.remove_shape(*id));
+        }
+    }
+}
+
+/// The west and south borders of every chunk up to `range` chunks away from
+/// the origin chunk, split by the parity of `chunk_x + chunk_y` so they can be
+/// colored like a checkerboard
+fn border_segments(range: i32) -> [Vec<[Vec2<f32>; 2]>; 2] {
+    let size = TerrainChunkSize::RECT_SIZE.map(|e| e as f32);
+    let mut segments = [Vec::new(), Vec::new()];
+    for x in -range..=range {
+        for y in -range..=range {
+            let corner = Vec2::new(x as f32, y as f32) * size;
+            let parity = (x + y).rem_euclid(2) as usize;
+            segments[parity].push([corner, corner + Vec2::new(0.0, size.y)]);
+            segments[parity].push([corner, corner + Vec2::new(size.x, 0.0)]);
+        }
+    }
+    segments
+}
+
+/// How many ticks of flight the trajectory preview shows at most
+pub const TRAJECTORY_PREVIEW_TICKS: usize = 90;
+/// Part of the preview after which the dots start to fade out
+const TRAJECTORY_FADE_START: f32 = 0.6;
+const TRAJECTORY_DOT_RADIUS: f32 = 0.06;
+const TRAJECTORY_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 0.6];
+const TRAJECTORY_IMPACT_RADIUS: f32 = 0.4;
+const TRAJECTORY_IMPACT_COLOR: [f32; 4] = [1.0, 0.4, 0.1, 0.7];
+
+/// Arc a charged shot would fly along if it was released now, drawn as a dot
+/// for every tick of its flight and a disc on what it would hit
+#[derive(Default)]
+pub struct TrajectoryPreview {
+    dots: Vec<DebugShapeId>,
+    impact: Option<DebugShapeId>,
+}
+
+impl TrajectoryPreview {
+    /// `path` holds the positions of the projectile after each tick until it
+    /// hits something, `impact` the position and normal of what it hits. An
+    /// empty path hides the preview.
+    pub fn maintain(
+        &mut self,
+        debug: &mut Debug,
+        path: &[Vec3<f32>],
+        impact: Option<(Vec3<f32>, Vec3<f32>)>,
+    ) {
+        if self.dots.len() > path.len() {
+            self.dots
+                .drain(path.len()..)
+                .for_each(|id| debug.remove_shape(id));
+        }
+        while self.dots.len() < path.len() {
+            self.dots.push(debug.add_shape(DebugShape::Cylinder {
+                radius: TRAJECTORY_DOT_RADIUS,
+                height: TRAJECTORY_DOT_RADIUS * 2.0,
+            }));
+        }
+        for (i, (id, pos)) in self.dots.iter().zip(path).enumerate() {
+            let [r, g, b, a] = TRAJECTORY_COLOR;
+            debug.set_context(
+                *id,
+                [pos.x, pos.y, pos.z - TRAJECTORY_DOT_RADIUS, 0.0],
+                [r, g, b, a * trajectory_fade(i)],
+                [0.0, 0.0, 0.0, 1.0],
+            );
+        }
+
+        match impact {
+            Some((pos, normal)) if !path.is_empty() => {
+                let id = *self.impact.get_or_insert_with(|| {
+                    debug.add_shape(DebugShape::Cylinder {
+                        radius: TRAJECTORY_IMPACT_RADIUS,
+                        height: 0.02,
+                    })
+                });
+                // The disc lies flat on the face that was hit
+                let ori = Quaternion::rotation_from_to_3d(
+                    Vec3::unit_z(),
+                    normal.try_normalized().unwrap_or_else(Vec3::unit_z),
+                );
+                let [r, g, b, a] = TRAJECTORY_IMPACT_COLOR;
+                debug.set_context(
+                    id,
+                    [pos.x, pos.y, pos.z, 0.0],
+                    [r, g, b, a * trajectory_fade(path.len())],
+                    [ori.x, ori.y, ori.z, ori.w],
+                );
+            },
+            _ => {
+                if let Some(id) = self.impact.take() {
+                    debug.remove_shape(id);
+                }
+            },
+        }
+    }
+}
+
+/// Opacity of the dot after `tick` ticks of flight
+fn trajectory_fade(tick: usize) -> f32 {
+    let progress = tick as f32 / TRAJECTORY_PREVIEW_TICKS as f32;
+    (1.0 - (progress - TRAJECTORY_FADE_START) / (1.0 - TRAJECTORY_FADE_START)).clamp(0.0, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn border_checkerboard() {
+        let [even, odd] = border_segments(1);
+        // 9 chunks, with 2 borders each
+        assert_eq!(even.len(), 10);
+        assert_eq!(odd.len(), 8);
+        // the origin chunk is even, its neighbour to the east is odd
+        assert!(even.contains(&[Vec2::zero(), Vec2::new(0.0, 32.0)]));
+        assert!(odd.contains(&[Vec2::new(32.0, 0.0), Vec2::new(32.0, 32.0)]));
+    }
+
+    #[test]
+    fn trajectory_fades_out() {
+        assert_eq!(trajectory_fade(0), 1.0);
+        assert_eq!(trajectory_fade(TRAJECTORY_PREVIEW_TICKS / 2), 1.0);
+        let late = trajectory_fade(TRAJECTORY_PREVIEW_TICKS * 4 / 5);
+        assert!(late > 0.0 && late < 1.0);
+        assert_eq!(trajectory_fade(TRAJECTORY_PREVIEW_TICKS), 0.0);
+    }
+}
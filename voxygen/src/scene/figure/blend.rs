@@ -0,0 +1,368 @@
+use common::comp::CharacterState;
+use specs::Entity as EcsEntity;
+use std::cmp::Ordering;
+use vek::*;
+
+/// Seconds it takes by default to blend into a new base animation
+pub const DEFAULT_BLEND_DURATION: f32 = 0.2;
+/// Bounds of [`AnimationInfo::speed_modifier`], so that animations neither
+/// freeze nor flicker
+pub const MIN_SPEED_MODIFIER: f32 = 0.1;
+pub const MAX_SPEED_MODIFIER: f32 = 10.0;
+/// Fraction of its height up to which a figure has to be in water to swim,
+/// it wades through shallower water
+pub const SWIM_DEPTH: f32 = 0.5;
+/// Seconds it takes a dead figure to fall over
+pub const DEATH_DURATION: f64 = 0.8;
+
+/// Base animation of a figure, which the animations of its character state
+/// are applied on top of
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Animation {
+    Idle,
+    Run,
+    Jump,
+    Swim,
+    /// Treading water in place
+    SwimIdle,
+    /// Climbing up or down a wall
+    Climb,
+    /// Hanging on a wall
+    ClimbIdle,
+    Mount,
+    /// Falling over, plays once
+    Death,
+}
+
+impl Animation {
+    /// Seconds animations that play once take, `None` for looping ones
+    pub fn duration(self) -> Option<f64> {
+        match self {
+            Animation::Death => Some(DEATH_DURATION),
+            _ => None,
+        }
+    }
+}
+
+/// Base animation of a figure that is `moving`, `submerged` means the water at
+/// its position is deeper than [`SWIM_DEPTH`]. Figures that are `climbing`
+/// never run or jump, they hang on the wall.
+pub fn base_animation(
+    on_ground: bool,
+    moving: bool,
+    submerged: bool,
+    riding: bool,
+    climbing: bool,
+) -> Animation {
+    match (on_ground, moving, submerged, riding) {
+        (_, _, _, true) => Animation::Mount,
+        _ if climbing && moving => Animation::Climb,
+        _ if climbing => Animation::ClimbIdle,
+        (_, true, true, _) => Animation::Swim,
+        (_, false, true, _) => Animation::SwimIdle,
+        (true, false, false, _) => Animation::Idle,
+        (true, true, false, _) => Animation::Run,
+        (false, _, false, _) => Animation::Jump,
+    }
+}
+
+/// Moment of an animation that sounds and effects are synced to
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AnimationEvent {
+    Footstep,
+    AttackImpact,
+    SkillActivated,
+}
+
+/// An event that fired, as sent to the audio and particle systems
+#[derive(Copy, Clone, Debug)]
+pub struct AnimationEventInfo {
+    pub entity: EcsEntity,
+    pub event: AnimationEvent,
+    pub pos: Vec3<f32>,
+    /// Direction the figure faces
+    pub dir: Vec3<f32>,
+}
+
+/// Events of the animation of `character`, at offsets in animation time from
+/// the start of the character state
+pub fn state_events(character: &CharacterState) -> Vec<(f64, AnimationEvent)> {
+    match character {
+        CharacterState::BasicMelee(s) => vec![
+            (0.0, AnimationEvent::SkillActivated),
+            (
+                s.static_data.buildup_duration.as_secs_f64(),
+                AnimationEvent::AttackImpact,
+            ),
+        ],
+        CharacterState::Roll(s) => vec![(
+            (s.static_data.buildup_duration + s.static_data.movement_duration).as_secs_f64(),
+            AnimationEvent::Footstep,
+        )],
+        _ if character.is_attack() => vec![(0.0, AnimationEvent::SkillActivated)],
+        _ => Vec::new(),
+    }
+}
+
+/// Tracks the base animation of a figure, so that switching to another one
+/// blends the two instead of popping to the new pose
+#[derive(Clone, Debug)]
+pub struct AnimationInfo {
+    pub animation: Animation,
+    /// Previous animation and its elapsed time when the switch happened, `None`
+    /// once the blend is done
+    pub blend_from: Option<(Animation, f64)>,
+    /// Weight of `animation` against `blend_from`, goes from 0.0 to 1.0
+    pub blend_factor: f32,
+    /// Seconds a blend takes
+    pub blend_duration: f32,
+    /// Whether `animation` loops, otherwise it plays once and can't be
+    /// replaced before it is finished
+    pub looping: bool,
+    /// Seconds `animation` plays if it isn't `looping`
+    pub duration: f64,
+    /// Seconds since `animation` started
+    elapsed: f64,
+    /// Offsets in animation time within the current animation at which
+    /// events fire, sorted by offset
+    pub registered_events: Vec<(f64, AnimationEvent)>,
+    /// Number of `registered_events` that fired in this play-through
+    fired_events: usize,
+    /// How much faster than normal the animations play, kept within
+    /// `MIN_SPEED_MODIFIER..=MAX_SPEED_MODIFIER`
+    speed_modifier: f32,
+}
+
+impl Default for AnimationInfo {
+    fn default() -> Self {
+        Self {
+            animation: Animation::Idle,
+            blend_from: None,
+            blend_factor: 1.0,
+            blend_duration: DEFAULT_BLEND_DURATION,
+            looping: true,
+            duration: 0.0,
+            elapsed: 0.0,
+            registered_events: Vec::new(),
+            fired_events: 0,
+            speed_modifier: 1.0,
+        }
+    }
+}
+
+impl AnimationInfo {
+    /// Switches to `animation` at the elapsed animation time `time`, or
+    /// advances the current blend by `dt` seconds if it is already playing.
+    /// Animations that play once aren't replaced before they are finished.
+    pub fn update(&mut self, animation: Animation, time: f64, dt: f32) {
+        if animation != self.animation && self.is_finished() {
+            self.blend_from = Some((self.animation, time));
+            self.animation = animation;
+            self.blend_factor = 0.0;
+            self.looping = animation.duration().is_none();
+            self.duration = animation.duration().unwrap_or(0.0);
+            self.elapsed = 0.0;
+        } else {
+            self.elapsed += f64::from(dt);
+            if self.blend_from.is_some() {
+                self.blend_factor += dt / self.blend_duration;
+            }
+        }
+        if self.blend_factor >= 1.0 || self.blend_duration <= 0.0 {
+            self.blend_factor = 1.0;
+            self.blend_from = None;
+        }
+    }
+
+    /// Seconds since `animation` started
+    pub fn elapsed(&self) -> f64 { self.elapsed }
+
+    /// Whether `animation` played to its end, looping animations can always
+    /// be replaced and count as finished
+    pub fn is_finished(&self) -> bool { self.looping || self.elapsed >= self.duration }
+
+    pub fn speed_modifier(&self) -> f32 { self.speed_modifier }
+
+    /// Plays the animations `speed_modifier` times as fast, e.g. for hastened
+    /// or slowed figures
+    pub fn set_speed_modifier(&mut self, speed_modifier: f32) {
+        self.speed_modifier = if speed_modifier.is_nan() {
+            1.0
+        } else {
+            speed_modifier.clamp(MIN_SPEED_MODIFIER, MAX_SPEED_MODIFIER)
+        };
+    }
+
+    /// Starts a new play-through with `events`, none of them fired yet
+    pub fn register_events(&mut self, mut events: Vec<(f64, AnimationEvent)>) {
+        events.sort_by(|(a, _), (b, _)| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+        self.registered_events = events;
+        self.fired_events = 0;
+    }
+
+    /// Calls `fire` with the events reached at the elapsed animation time
+    /// `time` that didn't fire yet in this play-through
+    pub fn fire_events(&mut self, time: f64, mut fire: impl FnMut(AnimationEvent)) {
+        while let Some((offset, event)) = self.registered_events.get(self.fired_events) {
+            if *offset > time {
+                break;
+            }
+            fire(*event);
+            self.fired_events += 1;
+        }
+    }
+
+    /// Previous animation and the time to sample it at, it keeps playing
+    /// while it is blended out
+    pub fn blend_source(&self) -> Option<(Animation, f64)> {
+        self.blend_from.map(|(animation, time)| {
+            (
+                animation,
+                time + f64::from(self.blend_factor * self.blend_duration),
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blend_ends_after_duration() {
+        let mut info = AnimationInfo::default();
+        info.update(Animation::Idle, 1.0, 0.1);
+        assert_eq!(info.blend_from, None);
+
+        info.update(Animation::Run, 2.0, 0.1);
+        assert_eq!(info.blend_from, Some((Animation::Idle, 2.0)));
+        assert_eq!(info.blend_factor, 0.0);
+
+        info.update(Animation::Run, 2.1, 0.1);
+        assert!((info.blend_factor - 0.5).abs() < 1e-6);
+        let (animation, time) = info.blend_source().unwrap();
+        assert_eq!(animation, Animation::Idle);
+        assert!((time - 2.1).abs() < 1e-6);
+
+        info.update(Animation::Run, 2.2, 0.1);
+        assert_eq!(info.blend_from, None);
+        assert_eq!(info.blend_factor, 1.0);
+    }
+
+    #[test]
+    fn switching_back_restarts_blend() {
+        let mut info = AnimationInfo::default();
+        info.update(Animation::Run, 0.0, 0.1);
+        info.update(Animation::Run, 0.1, 0.1);
+        info.update(Animation::Idle, 0.2, 0.1);
+        assert_eq!(info.blend_from, Some((Animation::Run, 0.2)));
+        assert_eq!(info.blend_factor, 0.0);
+
+        let mut instant = AnimationInfo {
+            blend_duration: 0.0,
+            ..Default::default()
+        };
+        instant.update(Animation::Jump, 0.0, 0.1);
+        assert_eq!(instant.blend_from, None);
+    }
+
+    #[test]
+    fn deep_water_switches_to_swimming() {
+        assert_eq!(
+            base_animation(true, true, false, false, false),
+            Animation::Run
+        );
+        assert_eq!(
+            base_animation(true, true, true, false, false),
+            Animation::Swim
+        );
+        assert_eq!(
+            base_animation(false, false, true, false, false),
+            Animation::SwimIdle
+        );
+        assert_eq!(
+            base_animation(false, false, false, false, false),
+            Animation::Jump
+        );
+        assert_eq!(
+            base_animation(false, true, true, true, false),
+            Animation::Mount
+        );
+    }
+
+    #[test]
+    fn climbing_replaces_jump_and_run() {
+        assert_eq!(
+            base_animation(false, true, false, false, true),
+            Animation::Climb
+        );
+        assert_eq!(
+            base_animation(false, false, false, false, true),
+            Animation::ClimbIdle
+        );
+        assert_eq!(
+            base_animation(true, true, false, false, true),
+            Animation::Climb
+        );
+    }
+
+    #[test]
+    fn death_plays_to_the_end() {
+        let mut info = AnimationInfo::default();
+        info.update(Animation::Run, 0.0, 0.1);
+        assert!(info.is_finished());
+
+        info.update(Animation::Death, 0.1, 0.1);
+        assert!(!info.looping);
+        assert!(!info.is_finished());
+        // Movement doesn't interrupt the fall
+        info.update(Animation::Jump, 0.2, 0.4);
+        assert_eq!(info.animation, Animation::Death);
+        info.update(Animation::Death, 0.6, 0.4);
+        assert!(info.is_finished());
+
+        info.update(Animation::Idle, 1.0, 0.1);
+        assert_eq!(info.animation, Animation::Idle);
+        assert!(info.looping);
+    }
+
+    #[test]
+    fn speed_modifier_is_clamped() {
+        let mut info = AnimationInfo::default();
+        assert_eq!(info.speed_modifier(), 1.0);
+        info.set_speed_modifier(1.5);
+        assert_eq!(info.speed_modifier(), 1.5);
+        info.set_speed_modifier(0.0);
+        assert_eq!(info.speed_modifier(), MIN_SPEED_MODIFIER);
+        info.set_speed_modifier(f32::INFINITY);
+        assert_eq!(info.speed_modifier(), MAX_SPEED_MODIFIER);
+        info.set_speed_modifier(f32::NAN);
+        assert_eq!(info.speed_modifier(), 1.0);
+    }
+
+    #[test]
+    fn events_fire_once_per_play_through() {
+        let mut info = AnimationInfo::default();
+        info.register_events(vec![
+            (0.3, AnimationEvent::AttackImpact),
+            (0.0, AnimationEvent::SkillActivated),
+        ]);
+        let mut fired = Vec::new();
+        info.fire_events(0.1, |event| fired.push(event));
+        assert_eq!(fired, [AnimationEvent::SkillActivated]);
+
+        info.fire_events(0.2, |event| fired.push(event));
+        info.fire_events(0.5, |event| fired.push(event));
+        info.fire_events(0.6, |event| fired.push(event));
+        assert_eq!(fired, [
+            AnimationEvent::SkillActivated,
+            AnimationEvent::AttackImpact
+        ]);
+
+        // The next play-through fires them again
+        fired.clear();
+        info.register_events(vec![(0.0, AnimationEvent::SkillActivated)]);
+        info.fire_events(0.0, |event| fired.push(event));
+        assert_eq!(fired, [AnimationEvent::SkillActivated]);
+    }
+}
@@ -1,7 +1,9 @@
+mod blend;
 mod cache;
 pub mod load;
 mod volume;
 
+pub use blend::{AnimationEvent, AnimationEventInfo};
 pub use cache::FigureModelCache;
 pub use load::load_mesh; // TODO: Don't make this public.
 pub use volume::VolumeKey;
@@ -40,9 +42,9 @@ use common::{
     mounting::Rider,
     resources::{DeltaTime, Time},
     states::{equipping, idle, utils::StageSection, wielding},
-    terrain::TerrainChunk,
-    uid::UidAllocator,
-    vol::RectRasterableVol,
+    terrain::{TerrainChunk, TerrainGrid},
+    uid::{Uid, UidAllocator},
+    vol::{ReadVol, RectRasterableVol},
 };
 use common_base::span;
 use common_state::State;
@@ -63,6 +65,15 @@ const DAMAGE_FADE_COEFFICIENT: f64 = 15.0;
 const MOVING_THRESHOLD: f32 = 0.2;
 const MOVING_THRESHOLD_SQR: f32 = MOVING_THRESHOLD * MOVING_THRESHOLD;
 
+/// Whether the terrain at `pos` is under water up to [`blend::SWIM_DEPTH`] of
+/// the `height` of a figure standing there
+fn is_submerged(terrain: &TerrainGrid, pos: Vec3<f32>, height: f32) -> bool {
+    let probe = pos + Vec3::unit_z() * height * blend::SWIM_DEPTH;
+    terrain
+        .get(probe.map(|e| e.floor() as i32))
+        .map_or(false, |block| block.is_liquid())
+}
+
 /// camera data, figure LOD render distance.
 pub type CameraData<'a> = (&'a Camera, f32);
 
@@ -409,10 +420,16 @@ pub struct FigureMgr {
     volume_model_cache: FigureModelCache<VolumeKey>,
     arthropod_model_cache: FigureModelCache<ArthropodSkeleton>,
     states: FigureMgrStates,
+    /// Fired animation events are sent there, for the audio and particle
+    /// systems to pick them up
+    animation_events: crossbeam_channel::Sender<AnimationEventInfo>,
 }
 
 impl FigureMgr {
-    pub fn new(renderer: &mut Renderer) -> Self {
+    pub fn new(
+        renderer: &mut Renderer,
+        animation_events: crossbeam_channel::Sender<AnimationEventInfo>,
+    ) -> Self {
         Self {
             col_lights: FigureColLights::new(renderer),
             model_cache: FigureModelCache::new(),
@@ -434,6 +451,7 @@ impl FigureMgr {
             volume_model_cache: FigureModelCache::new(),
             arthropod_model_cache: FigureModelCache::new(),
             states: FigureMgrStates::default(),
+            animation_events,
         }
     }
 
@@ -659,6 +677,9 @@ impl FigureMgr {
         let uid_allocator = ecs.read_resource::<UidAllocator>();
 
         let bodies = ecs.read_storage::<Body>();
+        let terrain_grid = state.terrain();
+        // Not part of the join below, which can't take any more storages
+        let uids = ecs.read_storage::<Uid>();
 
         for (
             i,
@@ -922,112 +943,215 @@ impl FigureMgr {
 
                     if !character.same_variant(&last_character.0) {
                         state.state_time = 0.0;
+                        state
+                            .animation
+                            .register_events(blend::state_events(character));
                     }
+                    // The server tells every client how fast the animations play
+                    if let Some(uid) = uids.get(entity) {
+                        state
+                            .animation
+                            .set_speed_modifier(scene_data.client.animation_speed(*uid));
+                    }
+                    state
+                        .animation
+                        .fire_events(f64::from(state.state_time), |event| {
+                            let _ = self.animation_events.send(AnimationEventInfo {
+                                entity,
+                                event,
+                                pos: Vec3::from(pos.0),
+                                dir: Vec3::from(ori * anim::vek::Vec3::<f32>::unit_y()),
+                            });
+                        });
 
-                    let target_base = match (
-                        physics.on_ground.is_some(),
-                        rel_vel.magnitude_squared() > 0.01, // Moving
-                        physics.in_liquid().is_some(),      // In water
-                        is_rider.is_some(),
-                    ) {
-                        // Standing
-                        (true, false, false, false) => {
-                            anim::character::StandAnimation::update_skeleton(
-                                &CharacterSkeleton::new(holding_lantern),
-                                (
-                                    active_tool_kind,
-                                    second_tool_kind,
-                                    hands,
-                                    // TODO: Update to use the quaternion.
-                                    ori * anim::vek::Vec3::<f32>::unit_y(),
-                                    state.last_ori * anim::vek::Vec3::<f32>::unit_y(),
-                                    time,
-                                    rel_avg_vel,
-                                ),
-                                state.state_time,
-                                &mut state_animation_rate,
-                                skeleton_attr,
-                            )
-                        },
-                        // Running
-                        (true, true, false, false) => {
-                            anim::character::RunAnimation::update_skeleton(
-                                &CharacterSkeleton::new(holding_lantern),
-                                (
-                                    active_tool_kind,
-                                    second_tool_kind,
-                                    hands,
-                                    rel_vel,
-                                    // TODO: Update to use the quaternion.
-                                    ori * anim::vek::Vec3::<f32>::unit_y(),
-                                    state.last_ori * anim::vek::Vec3::<f32>::unit_y(),
-                                    time,
-                                    rel_avg_vel,
-                                    state.acc_vel,
-                                    wall_dir,
-                                ),
-                                state.state_time,
-                                &mut state_animation_rate,
-                                skeleton_attr,
-                            )
-                        },
-                        // In air
-                        (false, _, false, false) => {
-                            anim::character::JumpAnimation::update_skeleton(
-                                &CharacterSkeleton::new(holding_lantern),
-                                (
-                                    active_tool_kind,
-                                    second_tool_kind,
-                                    hands,
-                                    rel_vel,
-                                    // TODO: Update to use the quaternion.
-                                    ori * anim::vek::Vec3::<f32>::unit_y(),
-                                    state.last_ori * anim::vek::Vec3::<f32>::unit_y(),
-                                    time,
-                                ),
-                                state.state_time,
-                                &mut state_animation_rate,
-                                skeleton_attr,
-                            )
-                        },
-                        // Swim
-                        (_, _, true, false) => anim::character::SwimAnimation::update_skeleton(
-                            &CharacterSkeleton::new(holding_lantern),
-                            (
-                                active_tool_kind,
-                                second_tool_kind,
-                                hands,
-                                rel_vel,
-                                // TODO: Update to use the quaternion.
-                                ori * anim::vek::Vec3::<f32>::unit_y(),
-                                state.last_ori * anim::vek::Vec3::<f32>::unit_y(),
-                                time,
-                                rel_avg_vel,
-                            ),
-                            state.state_time,
-                            &mut state_animation_rate,
-                            skeleton_attr,
-                        ),
-                        // Mount
-                        (_, _, _, true) => anim::character::MountAnimation::update_skeleton(
-                            &CharacterSkeleton::new(holding_lantern),
-                            (
-                                active_tool_kind,
-                                second_tool_kind,
-                                hands,
-                                time,
-                                rel_vel,
-                                rel_avg_vel,
-                                // TODO: Update to use the quaternion.
-                                ori * anim::vek::Vec3::<f32>::unit_y(),
-                                state.last_ori * anim::vek::Vec3::<f32>::unit_y(),
-                            ),
-                            state.state_time,
-                            &mut state_animation_rate,
-                            skeleton_attr,
-                        ),
+                    let submerged = physics.in_liquid().is_some()
+                        && is_submerged(&terrain_grid, pos.0, Body::Humanoid(*body).height());
+                    let dead = health.map_or(false, |h| h.is_dead);
+                    let animation = if dead {
+                        blend::Animation::Death
+                    } else {
+                        blend::base_animation(
+                            physics.on_ground.is_some(),
+                            rel_vel.magnitude_squared() > 0.01,
+                            submerged,
+                            is_rider.is_some(),
+                            matches!(character, CharacterState::Climb(_)),
+                        )
                     };
+                    state
+                        .animation
+                        .update(animation, f64::from(state.state_time), dt);
+
+                    let last_ori = state.last_ori;
+                    let acc_vel = state.acc_vel;
+                    let base_skeleton =
+                        |animation: blend::Animation, anim_time: f32, rate: &mut f32| {
+                            match animation {
+                                blend::Animation::Idle => {
+                                    anim::character::StandAnimation::update_skeleton(
+                                        &CharacterSkeleton::new(holding_lantern),
+                                        (
+                                            active_tool_kind,
+                                            second_tool_kind,
+                                            hands,
+                                            // TODO: Update to use the quaternion.
+                                            ori * anim::vek::Vec3::<f32>::unit_y(),
+                                            last_ori * anim::vek::Vec3::<f32>::unit_y(),
+                                            time,
+                                            rel_avg_vel,
+                                        ),
+                                        anim_time,
+                                        rate,
+                                        skeleton_attr,
+                                    )
+                                },
+                                blend::Animation::Run => {
+                                    anim::character::RunAnimation::update_skeleton(
+                                        &CharacterSkeleton::new(holding_lantern),
+                                        (
+                                            active_tool_kind,
+                                            second_tool_kind,
+                                            hands,
+                                            rel_vel,
+                                            // TODO: Update to use the quaternion.
+                                            ori * anim::vek::Vec3::<f32>::unit_y(),
+                                            last_ori * anim::vek::Vec3::<f32>::unit_y(),
+                                            time,
+                                            rel_avg_vel,
+                                            acc_vel,
+                                            wall_dir,
+                                        ),
+                                        anim_time,
+                                        rate,
+                                        skeleton_attr,
+                                    )
+                                },
+                                blend::Animation::Jump => {
+                                    anim::character::JumpAnimation::update_skeleton(
+                                        &CharacterSkeleton::new(holding_lantern),
+                                        (
+                                            active_tool_kind,
+                                            second_tool_kind,
+                                            hands,
+                                            rel_vel,
+                                            // TODO: Update to use the quaternion.
+                                            ori * anim::vek::Vec3::<f32>::unit_y(),
+                                            last_ori * anim::vek::Vec3::<f32>::unit_y(),
+                                            time,
+                                        ),
+                                        anim_time,
+                                        rate,
+                                        skeleton_attr,
+                                    )
+                                },
+                                blend::Animation::Swim => {
+                                    anim::character::SwimAnimation::update_skeleton(
+                                        &CharacterSkeleton::new(holding_lantern),
+                                        (
+                                            active_tool_kind,
+                                            second_tool_kind,
+                                            hands,
+                                            rel_vel,
+                                            // TODO: Update to use the quaternion.
+                                            ori * anim::vek::Vec3::<f32>::unit_y(),
+                                            last_ori * anim::vek::Vec3::<f32>::unit_y(),
+                                            time,
+                                            rel_avg_vel,
+                                        ),
+                                        anim_time,
+                                        rate,
+                                        skeleton_attr,
+                                    )
+                                },
+                                blend::Animation::SwimIdle => {
+                                    anim::character::SwimIdleAnimation::update_skeleton(
+                                        &CharacterSkeleton::new(holding_lantern),
+                                        (active_tool_kind, second_tool_kind, hands, time),
+                                        anim_time,
+                                        rate,
+                                        skeleton_attr,
+                                    )
+                                },
+                                blend::Animation::Climb | blend::Animation::ClimbIdle => {
+                                    anim::character::ClimbAnimation::update_skeleton(
+                                        &CharacterSkeleton::new(holding_lantern),
+                                        (
+                                            active_tool_kind,
+                                            second_tool_kind,
+                                            // Hanging still holds the pose
+                                            if animation == blend::Animation::Climb {
+                                                rel_vel
+                                            } else {
+                                                anim::vek::Vec3::zero()
+                                            },
+                                            // TODO: Update to use the quaternion.
+                                            ori * anim::vek::Vec3::<f32>::unit_y(),
+                                            time,
+                                        ),
+                                        anim_time,
+                                        rate,
+                                        skeleton_attr,
+                                    )
+                                },
+                                blend::Animation::Death => {
+                                    anim::character::DeathAnimation::update_skeleton(
+                                        &CharacterSkeleton::new(holding_lantern),
+                                        (
+                                            active_tool_kind,
+                                            second_tool_kind,
+                                            hands,
+                                            blend::DEATH_DURATION as f32,
+                                        ),
+                                        anim_time,
+                                        rate,
+                                        skeleton_attr,
+                                    )
+                                },
+                                blend::Animation::Mount => {
+                                    anim::character::MountAnimation::update_skeleton(
+                                        &CharacterSkeleton::new(holding_lantern),
+                                        (
+                                            active_tool_kind,
+                                            second_tool_kind,
+                                            hands,
+                                            time,
+                                            rel_vel,
+                                            rel_avg_vel,
+                                            // TODO: Update to use the quaternion.
+                                            ori * anim::vek::Vec3::<f32>::unit_y(),
+                                            last_ori * anim::vek::Vec3::<f32>::unit_y(),
+                                        ),
+                                        anim_time,
+                                        rate,
+                                        skeleton_attr,
+                                    )
+                                },
+                            }
+                        };
+                    // Animations that play once start with the animation, not the character state
+                    let base_time = if state.animation.looping {
+                        state.state_time
+                    } else {
+                        state.animation.elapsed() as f32
+                    };
+                    let mut target_base = base_skeleton(
+                        state.animation.animation,
+                        base_time,
+                        &mut state_animation_rate,
+                    );
+                    // Blend out the previous base animation instead of popping to the new one
+                    if let Some((from, from_time)) = state.animation.blend_source() {
+                        // Only the rate of the new animation is used
+                        let from_base = base_skeleton(from, from_time as f32, &mut 1.0);
+                        target_base = anim::vek::Lerp::lerp(
+                            &from_base,
+                            &target_base,
+                            state.animation.blend_factor,
+                        );
+                    }
                     let target_bones = match &character {
+                        // The dead don't act anymore
+                        _ if dead => target_base,
                         CharacterState::Roll(s) => {
                             let stage_time = s.timer.as_secs_f32();
                             let wield_status = s.was_wielded;
@@ -1502,9 +1626,9 @@ impl FigureMgr {
                                         StageSection::Action => {
                                             stage_time / stage.base_swing_duration.as_secs_f32()
                                         },
-                                        StageSection::Recover => {
-                                            stage_time / stage.base_recover_duration.as_secs_f32()
-                                        },
+                                        StageSection::Recover => (stage_time
+                                            / stage.base_recover_duration.as_secs_f32())
+                                        .min(1.0),
                                         _ => 0.0,
                                     }
                                 } else {
@@ -1703,22 +1827,8 @@ impl FigureMgr {
                                 skeleton_attr,
                             )
                         },
-                        CharacterState::Climb { .. } => {
-                            anim::character::ClimbAnimation::update_skeleton(
-                                &target_base,
-                                (
-                                    active_tool_kind,
-                                    second_tool_kind,
-                                    rel_vel,
-                                    // TODO: Update to use the quaternion.
-                                    ori * anim::vek::Vec3::<f32>::unit_y(),
-                                    time,
-                                ),
-                                state.state_time,
-                                &mut state_animation_rate,
-                                skeleton_attr,
-                            )
-                        },
+                        // The base animation already climbs
+                        CharacterState::Climb { .. } => target_base,
                         CharacterState::Sit { .. } => {
                             anim::character::SitAnimation::update_skeleton(
                                 &target_base,
@@ -1763,12 +1873,13 @@ impl FigureMgr {
                     };
 
                     state.skeleton = anim::vek::Lerp::lerp(&state.skeleton, &target_bones, dt_lerp);
+                    let speed_modifier = state.animation.speed_modifier();
                     state.update(
                         renderer,
                         trail_mgr,
                         &mut update_buf,
                         &common_params,
-                        state_animation_rate,
+                        state_animation_rate * speed_modifier,
                         model,
                         body,
                     );
@@ -1894,9 +2005,9 @@ impl FigureMgr {
                                         StageSection::Action => {
                                             stage_time / stage.base_swing_duration.as_secs_f32()
                                         },
-                                        StageSection::Recover => {
-                                            stage_time / stage.base_recover_duration.as_secs_f32()
-                                        },
+                                        StageSection::Recover => (stage_time
+                                            / stage.base_recover_duration.as_secs_f32())
+                                        .min(1.0),
                                         _ => 0.0,
                                     }
                                 } else {
@@ -2175,9 +2286,9 @@ impl FigureMgr {
                                         StageSection::Action => {
                                             stage_time / stage.base_swing_duration.as_secs_f32()
                                         },
-                                        StageSection::Recover => {
-                                            stage_time / stage.base_recover_duration.as_secs_f32()
-                                        },
+                                        StageSection::Recover => (stage_time
+                                            / stage.base_recover_duration.as_secs_f32())
+                                        .min(1.0),
                                         _ => 0.0,
                                     }
                                 } else {
@@ -2533,9 +2644,9 @@ impl FigureMgr {
                                         StageSection::Action => {
                                             stage_time / stage.base_swing_duration.as_secs_f32()
                                         },
-                                        StageSection::Recover => {
-                                            stage_time / stage.base_recover_duration.as_secs_f32()
-                                        },
+                                        StageSection::Recover => (stage_time
+                                            / stage.base_recover_duration.as_secs_f32())
+                                        .min(1.0),
                                         _ => 0.0,
                                     }
                                 } else {
@@ -3206,9 +3317,9 @@ impl FigureMgr {
                                         StageSection::Action => {
                                             stage_time / stage.base_swing_duration.as_secs_f32()
                                         },
-                                        StageSection::Recover => {
-                                            stage_time / stage.base_recover_duration.as_secs_f32()
-                                        },
+                                        StageSection::Recover => (stage_time
+                                            / stage.base_recover_duration.as_secs_f32())
+                                        .min(1.0),
                                         _ => 0.0,
                                     }
                                 } else {
@@ -3548,9 +3659,9 @@ impl FigureMgr {
                                         StageSection::Action => {
                                             stage_time / stage.base_swing_duration.as_secs_f32()
                                         },
-                                        StageSection::Recover => {
-                                            stage_time / stage.base_recover_duration.as_secs_f32()
-                                        },
+                                        StageSection::Recover => (stage_time
+                                            / stage.base_recover_duration.as_secs_f32())
+                                        .min(1.0),
                                         _ => 0.0,
                                     }
                                 } else {
@@ -3727,9 +3838,9 @@ impl FigureMgr {
                                         StageSection::Action => {
                                             stage_time / stage.base_swing_duration.as_secs_f32()
                                         },
-                                        StageSection::Recover => {
-                                            stage_time / stage.base_recover_duration.as_secs_f32()
-                                        },
+                                        StageSection::Recover => (stage_time
+                                            / stage.base_recover_duration.as_secs_f32())
+                                        .min(1.0),
                                         _ => 0.0,
                                     }
                                 } else {
@@ -4063,9 +4174,9 @@ impl FigureMgr {
                                         StageSection::Action => {
                                             stage_time / stage.base_swing_duration.as_secs_f32()
                                         },
-                                        StageSection::Recover => {
-                                            stage_time / stage.base_recover_duration.as_secs_f32()
-                                        },
+                                        StageSection::Recover => (stage_time
+                                            / stage.base_recover_duration.as_secs_f32())
+                                        .min(1.0),
                                         _ => 0.0,
                                     }
                                 } else {
@@ -4693,9 +4804,9 @@ impl FigureMgr {
                                         StageSection::Action => {
                                             stage_time / stage.base_swing_duration.as_secs_f32()
                                         },
-                                        StageSection::Recover => {
-                                            stage_time / stage.base_recover_duration.as_secs_f32()
-                                        },
+                                        StageSection::Recover => (stage_time
+                                            / stage.base_recover_duration.as_secs_f32())
+                                        .min(1.0),
                                         _ => 0.0,
                                     }
                                 } else {
@@ -5042,9 +5153,9 @@ impl FigureMgr {
                                         StageSection::Action => {
                                             stage_time / stage.base_swing_duration.as_secs_f32()
                                         },
-                                        StageSection::Recover => {
-                                            stage_time / stage.base_recover_duration.as_secs_f32()
-                                        },
+                                        StageSection::Recover => (stage_time
+                                            / stage.base_recover_duration.as_secs_f32())
+                                        .min(1.0),
                                         _ => 0.0,
                                     }
                                 } else {
@@ -5522,6 +5633,17 @@ impl FigureMgr {
         visible_aabb
     }
 
+    /// Whether the dead `entity` is still shown, falling over or lying on the
+    /// ground
+    fn shows_death(&self, entity: EcsEntity) -> bool {
+        self.states
+            .character_states
+            .get(&entity)
+            .map_or(false, |state| {
+                state.animation.animation == blend::Animation::Death
+            })
+    }
+
     pub fn render_shadows<'a>(
         &'a self,
         drawer: &mut FigureShadowDrawer<'_, 'a>,
@@ -5544,8 +5666,10 @@ impl FigureMgr {
                 ecs.read_storage::<Collider>().maybe(),
             )
             .join()
-            // Don't render dead entities
-            .filter(|(_, _, _, _, health, _, _, _)| health.map_or(true, |h| !h.is_dead))
+            // Don't render dead entities, unless they lie where they fell
+            .filter(|(entity, _, _, _, health, _, _, _)| {
+                health.map_or(true, |h| !h.is_dead) || self.shows_death(*entity)
+            })
             .for_each(|(entity, pos, _, body, _, inventory, scale, collider)| {
                 if let Some((bound, model, _)) = self.get_model_for_render(
                     tick,
@@ -5593,8 +5717,10 @@ impl FigureMgr {
             ecs.read_storage::<Collider>().maybe(),
         )
             .join()
-        // Don't render dead entities
-        .filter(|(_, _, _, health, _, _, _)| health.map_or(true, |h| !h.is_dead))
+        // Don't render dead entities, unless they lie where they fell
+        .filter(|(entity, _, _, health, _, _, _)| {
+            health.map_or(true, |h| !h.is_dead) || self.shows_death(*entity)
+        })
         // Don't render player
         .filter(|(entity, _, _, _, _, _, _)| *entity != player_entity)
         {
@@ -5645,7 +5771,7 @@ impl FigureMgr {
         ) {
             let healths = state.read_storage::<Health>();
             let health = healths.get(player_entity);
-            if health.map_or(false, |h| h.is_dead) {
+            if health.map_or(false, |h| h.is_dead) && !self.shows_death(player_entity) {
                 return;
             }
 
@@ -6205,6 +6331,8 @@ pub struct FigureStateMeta {
     // For use if it is mounted by another figure
     mount_world_pos: anim::vek::Vec3<f32>,
     state_time: f32,
+    /// Base animation, only tracked for humanoids
+    animation: blend::AnimationInfo,
     last_ori: anim::vek::Quaternion<f32>,
     lpindex: u8,
     can_shadow_sun: bool,
@@ -6278,6 +6406,7 @@ impl<S: Skeleton> FigureState<S> {
                 mount_transform: offsets.mount_bone,
                 mount_world_pos: anim::vek::Vec3::zero(),
                 state_time: 0.0,
+                animation: blend::AnimationInfo::default(),
                 last_ori: Ori::default().into(),
                 lpindex: 0,
                 visible: false,
@@ -10,7 +10,7 @@ pub mod trail;
 
 pub use self::{
     camera::{Camera, CameraMode},
-    debug::{Debug, DebugShape, DebugShapeId},
+    debug::{ChunkBorderHighlight, Debug, DebugShape, DebugShapeId, TrajectoryPreview},
     figure::FigureMgr,
     lod::Lod,
     particle::ParticleMgr,
@@ -20,9 +20,9 @@ pub use self::{
 use crate::{
     audio::{ambient::AmbientMgr, music::MusicMgr, sfx::SfxMgr, AudioFrontend},
     render::{
-        create_skybox_mesh, CloudsLocals, Consts, Drawer, GlobalModel, Globals, GlobalsBindGroup,
-        Light, Model, PointLightMatrix, PostProcessLocals, Renderer, Shadow, ShadowLocals,
-        SkyboxVertex,
+        create_skybox_mesh, CaveAmbientLight, CloudsLocals, Consts, Drawer, GlobalModel, Globals,
+        GlobalsBindGroup, Light, Model, PointLightMatrix, PostProcessLocals, Renderer, Shadow,
+        ShadowLocals, SkyboxVertex,
     },
     settings::Settings,
     window::{AnalogGameInput, Event},
@@ -31,8 +31,12 @@ use client::Client;
 use common::{
     comp,
     outcome::Outcome,
+    ray::RayCast,
     resources::DeltaTime,
-    terrain::{BlockKind, TerrainChunk},
+    states::{charged_ranged, utils::StageSection},
+    terrain::{Block, BlockKind, TerrainChunk},
+    trajectory::{Trajectory, TICK_DT},
+    util::Dir,
     vol::ReadVol,
 };
 use common_base::{prof_span, span};
@@ -99,6 +103,7 @@ pub struct Scene {
     particle_mgr: ParticleMgr,
     trail_mgr: TrailMgr,
     figure_mgr: FigureMgr,
+    animation_events: crossbeam_channel::Receiver<figure::AnimationEventInfo>,
     pub sfx_mgr: SfxMgr,
     music_mgr: MusicMgr,
     ambient_mgr: AmbientMgr,
@@ -115,9 +120,12 @@ pub struct SceneData<'a> {
     pub gamma: f32,
     pub exposure: f32,
     pub ambiance: f32,
+    pub cave_ambient: bool,
     pub mouse_smoothing: bool,
     pub sprite_render_distance: f32,
     pub particles_enabled: bool,
+    /// Fraction of particles that get spawned, `1.0` spawns all of them
+    pub particle_density: f32,
     pub weapon_trails_enabled: bool,
     pub figure_lod_render_distance: f32,
     pub is_aiming: bool,
@@ -287,6 +295,7 @@ impl Scene {
         let globals_bind_group = renderer.bind_globals(&data, lod.get_data());
 
         let terrain = Terrain::new(renderer, &data, lod.get_data(), sprite_render_context);
+        let (animation_events_tx, animation_events_rx) = crossbeam_channel::unbounded();
 
         Self {
             data,
@@ -310,7 +319,8 @@ impl Scene {
             light_data: Vec::new(),
             particle_mgr: ParticleMgr::new(renderer),
             trail_mgr: TrailMgr::default(),
-            figure_mgr: FigureMgr::new(renderer),
+            figure_mgr: FigureMgr::new(renderer, animation_events_tx),
+            animation_events: animation_events_rx,
             sfx_mgr: SfxMgr::default(),
             music_mgr: MusicMgr::default(),
             ambient_mgr: AmbientMgr::default(),
@@ -645,6 +655,17 @@ impl Scene {
         let focus_pos = self.camera.get_focus_pos();
         let focus_off = focus_pos.map(|e| e.trunc());
 
+        // Caves get lighter the further the focus is below the surface
+        let cave_ambient_light = if scene_data.cave_ambient {
+            let terrain = scene_data.state.terrain();
+            terrain
+                .get_key(terrain.pos_key(focus_pos.map(|e| e.floor() as i32)))
+                .map(|chunk| CaveAmbientLight::at_depth(chunk.meta().alt() - focus_pos.z))
+                .unwrap_or_default()
+        } else {
+            CaveAmbientLight::default()
+        };
+
         // Update global constants.
         renderer.update_consts(&mut self.data.globals, &[Globals::new(
             view_mat,
@@ -676,6 +697,7 @@ impl Scene {
             scene_data.ambiance,
             self.camera.get_mode(),
             scene_data.sprite_render_distance as f32 - 20.0,
+            cave_ambient_light,
         )]);
         renderer.update_clouds_locals(CloudsLocals::new(proj_mat_inv, view_mat_inv));
         renderer.update_postprocess_locals(PostProcessLocals::new(proj_mat_inv, view_mat_inv));
@@ -1066,6 +1088,13 @@ impl Scene {
         self.music_mgr.maintain(audio, scene_data.state, client);
         self.ambient_mgr
             .maintain(audio, scene_data.state, client, &self.camera);
+
+        // Sounds and effects of the animation events that fired this frame
+        for event in self.animation_events.try_iter() {
+            self.sfx_mgr
+                .handle_animation_event(&event, audio, scene_data.state);
+            self.particle_mgr.handle_animation_event(&event, scene_data);
+        }
     }
 
     pub fn global_bind_group(&self) -> &GlobalsBindGroup { &self.globals_bind_group }
@@ -1226,4 +1255,99 @@ impl Scene {
             keep
         });
     }
+
+    pub fn maintain_debug_chunk_borders(
+        &mut self,
+        client: &Client,
+        settings: &Settings,
+        chunk_borders: &mut ChunkBorderHighlight,
+    ) {
+        chunk_borders.maintain(
+            &mut self.debug,
+            settings.interface.toggle_chunk_borders,
+            client.position().unwrap_or_default(),
+            client.view_distance().unwrap_or(1),
+        );
+    }
+
+    pub fn maintain_trajectory_preview(
+        &mut self,
+        client: &Client,
+        settings: &Settings,
+        look_dir: Dir,
+        preview: &mut TrajectoryPreview,
+    ) {
+        let (path, impact) = settings
+            .gameplay
+            .trajectory_preview
+            .then(|| predict_charged_shot(client, look_dir))
+            .flatten()
+            .unwrap_or_default();
+        preview.maintain(&mut self.debug, &path, impact);
+    }
+}
+
+/// Positions after each tick of the flight of the charged shot the player
+/// would release now, up to the block or entity it would hit, along with the
+/// position and normal where it hits
+fn predict_charged_shot(
+    client: &Client,
+    look_dir: Dir,
+) -> Option<(Vec<Vec3<f32>>, Option<(Vec3<f32>, Vec3<f32>)>)> {
+    let player = client.entity();
+    let ecs = client.state().ecs();
+    let (speed, body) = match ecs.read_storage::<comp::CharacterState>().get(player)? {
+        comp::CharacterState::ChargedRanged(
+            data @ charged_ranged::Data {
+                stage_section: StageSection::Charge,
+                exhausted: false,
+                ..
+            },
+        ) => (data.projectile_speed(), data.static_data.projectile_body),
+        _ => return None,
+    };
+
+    let positions = ecs.read_storage::<comp::Pos>();
+    let bodies = ecs.read_storage::<comp::Body>();
+    let scales = ecs.read_storage::<comp::Scale>();
+    let pos = positions.get(player)?.0;
+    let ori = ecs.read_storage::<comp::Ori>().get(player).copied()?;
+    let vel = ecs
+        .read_storage::<comp::Vel>()
+        .get(player)
+        .map_or(Vec3::zero(), |v| v.0);
+    // Launched the same way as in `charged_ranged`
+    let launch = pos + bodies.get(player)?.projectile_offsets(ori.look_vec());
+    let trajectory = Trajectory::new(launch, *look_dir * speed + vel, body);
+
+    let terrain = client.state().terrain();
+    let spatial_grid = ecs.read_resource::<common::CachedSpatialGrid>();
+    let aabb = |e| {
+        let (p, b) = (positions.get(e)?.0, bodies.get(e)?);
+        let scale = scales.get(e).map_or(1.0, |s| s.0);
+        let radius = b.max_radius() * scale;
+        Some(Aabb {
+            min: p - Vec3::new(radius, radius, 0.0),
+            max: p + Vec3::new(radius, radius, b.height() * scale),
+        })
+    };
+
+    let mut path = Vec::new();
+    let mut from = launch;
+    for to in trajectory
+        .path(TICK_DT)
+        .take(debug::TRAJECTORY_PREVIEW_TICKS)
+    {
+        if let Some(hit) = RayCast::between(&*terrain, from, to)
+            .until(Block::is_solid)
+            .spatial_grid(&spatial_grid.0, aabb)
+            .ignore_entity(player)
+            .cast()
+        {
+            return Some((path, Some((hit.pos, hit.normal))));
+        }
+        path.push(to);
+        from = to;
+    }
+    Some((path, None))
 }
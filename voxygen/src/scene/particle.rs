@@ -1,4 +1,8 @@
-use super::{terrain::BlocksOfInterest, SceneData, Terrain};
+use super::{
+    figure::{AnimationEvent, AnimationEventInfo},
+    terrain::BlocksOfInterest,
+    SceneData, Terrain,
+};
 use crate::{
     mesh::{greedy::GreedyMesh, segment::generate_mesh_base_vol_particle},
     render::{
@@ -255,6 +259,16 @@ impl ParticleMgr {
                     });
                 }
             },
+            Outcome::SkillPointGain { pos, .. } => {
+                self.particles.resize_with(self.particles.len() + 40, || {
+                    Particle::new(
+                        Duration::from_millis(800),
+                        time,
+                        ParticleMode::FireworkYellow,
+                        *pos + Vec3::unit_z(),
+                    )
+                });
+            },
             Outcome::GroundSlam { pos, .. } => {
                 self.particles.resize_with(self.particles.len() + 100, || {
                     Particle::new(
@@ -278,18 +292,53 @@ impl ParticleMgr {
                     )
                 });
             },
+            Outcome::Splash { pos, power } => {
+                self.particles.resize_with(
+                    self.particles.len() + (20.0 * power.max(0.1)) as usize,
+                    || {
+                        let dir =
+                            Vec3::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0), 2.0);
+                        Particle::new_directed(
+                            Duration::from_millis(400),
+                            time,
+                            ParticleMode::Water,
+                            *pos,
+                            *pos + dir * 0.4 * power.max(0.5),
+                        )
+                    },
+                );
+            },
             Outcome::ProjectileShot { .. }
             | Outcome::Beam { .. }
             | Outcome::ExpChange { .. }
-            | Outcome::SkillPointGain { .. }
             | Outcome::ComboChange { .. }
             | Outcome::Damage { .. }
+            | Outcome::CriticalHit { .. }
             | Outcome::PoiseChange { .. }
             | Outcome::Utterance { .. }
-            | Outcome::Glider { .. } => {},
+            | Outcome::Glider { .. }
+            | Outcome::FallDamage { .. } => {},
         }
     }
 
+    /// Sparks where a melee attack connects
+    pub fn handle_animation_event(&mut self, info: &AnimationEventInfo, scene_data: &SceneData) {
+        if !scene_data.particles_enabled || info.event != AnimationEvent::AttackImpact {
+            return;
+        }
+        let time = scene_data.state.get_time();
+        let mut rng = rand::thread_rng();
+        let impact_pos = info.pos + info.dir * 1.5 + Vec3::unit_z();
+        self.particles.resize_with(self.particles.len() + 10, || {
+            Particle::new(
+                Duration::from_millis(150),
+                time,
+                ParticleMode::Shrapnel,
+                impact_pos + Vec3::<f32>::zero().map(|_| rng.gen_range(-0.3..0.3)),
+            )
+        });
+    }
+
     pub fn maintain(
         &mut self,
         renderer: &mut Renderer,
@@ -307,6 +356,7 @@ impl ParticleMgr {
                 .retain(|p| p.alive_until > scene_data.state.get_time());
 
             // add new Particle
+            let old_len = self.particles.len();
             self.maintain_body_particles(scene_data);
             self.maintain_char_state_particles(scene_data);
             self.maintain_beam_particles(scene_data, lights);
@@ -314,6 +364,7 @@ impl ParticleMgr {
             self.maintain_shockwave_particles(scene_data);
             self.maintain_aura_particles(scene_data);
             self.maintain_buff_particles(scene_data);
+            thin_new_particles(&mut self.particles, old_len, scene_data.particle_density);
 
             self.upload_particles(renderer);
         } else {
@@ -1537,6 +1588,20 @@ fn default_cache(renderer: &mut Renderer) -> HashMap<&'static str, Model<Particl
     model_cache
 }
 
+/// Drops particles spawned after `old_len` so only `density` of them are
+/// kept, evenly spread over the new ones
+fn thin_new_particles(particles: &mut Vec<Particle>, old_len: usize, density: f32) {
+    if density >= 1.0 {
+        return;
+    }
+    let mut i = 0;
+    particles.retain(|_| {
+        i += 1;
+        let n = i.saturating_sub(old_len);
+        n == 0 || (n as f32 * density).floor() > ((n - 1) as f32 * density).floor()
+    });
+}
+
 /// Accumulates heartbeats to be consumed on the next tick.
 struct HeartbeatScheduler {
     /// Duration = Heartbeat Frequency/Intervals
@@ -1,9 +1,9 @@
 use crate::{
     mesh::{greedy::GreedyMesh, segment::generate_mesh_base_vol_terrain},
     render::{
-        create_skybox_mesh, BoneMeshes, Consts, FigureModel, FirstPassDrawer, GlobalModel, Globals,
-        GlobalsBindGroup, Light, LodData, Mesh, Model, PointLightMatrix, Renderer, Shadow,
-        ShadowLocals, SkyboxVertex, TerrainVertex,
+        create_skybox_mesh, BoneMeshes, CaveAmbientLight, Consts, FigureModel, FirstPassDrawer,
+        GlobalModel, Globals, GlobalsBindGroup, Light, LodData, Mesh, Model, PointLightMatrix,
+        Renderer, Shadow, ShadowLocals, SkyboxVertex, TerrainVertex,
     },
     scene::{
         camera::{self, Camera, CameraMode},
@@ -274,6 +274,7 @@ impl Scene {
             scene_data.ambiance,
             self.camera.get_mode(),
             250.0,
+            CaveAmbientLight::default(),
         )]);
 
         self.figure_model_cache
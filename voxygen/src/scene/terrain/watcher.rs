@@ -10,6 +10,7 @@ pub enum Interaction {
     Collect,
     Craft(CraftingTab),
     Mine,
+    Sleep,
 }
 
 #[derive(Default)]
@@ -144,6 +145,7 @@ impl BlocksOfInterest {
                         fires.push(pos);
                         interactables.push((pos, Interaction::Craft(CraftingTab::Dismantle)))
                     },
+                    Some(SpriteKind::Bed) => interactables.push((pos, Interaction::Sleep)),
                     _ => {},
                 },
             }
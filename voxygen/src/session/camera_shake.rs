@@ -0,0 +1,149 @@
+use common::{outcome::Outcome, uid::Uid};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use std::time::Duration;
+use vek::*;
+
+/// Shake per block of explosion radius, before falloff
+const EXPLOSION_INTENSITY: f32 = 0.06;
+/// Explosions shake the camera up to this many radii away
+const EXPLOSION_RANGE: f32 = 4.0;
+const EXPLOSION_DURATION: Duration = Duration::from_millis(600);
+/// Shake per point of fall damage
+const FALL_INTENSITY: f32 = 0.004;
+const FALL_DURATION: Duration = Duration::from_millis(350);
+/// Upper limit for the offset of a single shake, in blocks
+const MAX_INTENSITY: f32 = 0.6;
+
+/// A single shake of the camera, fading out over its duration
+#[derive(Clone, Debug)]
+pub struct CameraShake {
+    /// Maximum offset of the camera, in blocks
+    pub intensity: f32,
+    /// How many times per second the camera jumps to a new offset
+    pub frequency: f32,
+    pub remaining: Duration,
+    duration: Duration,
+    offset: Vec2<f32>,
+    until_next_offset: f32,
+}
+
+impl CameraShake {
+    pub fn new(intensity: f32, frequency: f32, duration: Duration) -> Self {
+        Self {
+            intensity: intensity.min(MAX_INTENSITY),
+            frequency,
+            remaining: duration,
+            duration,
+            offset: Vec2::zero(),
+            until_next_offset: 0.0,
+        }
+    }
+
+    /// Shake caused by an explosion of the given radius `dist` blocks away
+    /// from the player, `None` if it's too far away to be felt
+    pub fn explosion(radius: f32, dist: f32) -> Option<Self> {
+        let falloff = 1.0 - dist / (radius * EXPLOSION_RANGE).max(1.0);
+        (falloff > 0.0).then(|| {
+            Self::new(
+                radius * EXPLOSION_INTENSITY * falloff,
+                25.0,
+                EXPLOSION_DURATION,
+            )
+        })
+    }
+
+    /// Shake caused by the player landing hard enough to take damage
+    pub fn fall(damage: f32) -> Self { Self::new(damage * FALL_INTENSITY, 15.0, FALL_DURATION) }
+
+    /// Advances the shake by `dt` and returns the current offset of the
+    /// camera, which gets smaller as the shake runs out
+    fn update(&mut self, dt: Duration, rng: &mut impl Rng) -> Vec2<f32> {
+        self.remaining = self.remaining.saturating_sub(dt);
+        self.until_next_offset -= dt.as_secs_f32();
+        if self.until_next_offset <= 0.0 {
+            self.until_next_offset = 1.0 / self.frequency.max(f32::EPSILON);
+            self.offset = Vec2::new(rng.gen_range(-1.0..=1.0), rng.gen_range(-1.0..=1.0));
+        }
+        let envelope = self.remaining.as_secs_f32() / self.duration.as_secs_f32().max(f32::EPSILON);
+        self.offset * self.intensity * envelope
+    }
+}
+
+/// The camera shakes currently affecting the player
+pub struct CameraShakes {
+    shakes: Vec<CameraShake>,
+    rng: ChaCha8Rng,
+}
+
+impl Default for CameraShakes {
+    fn default() -> Self {
+        Self {
+            shakes: Vec::new(),
+            rng: ChaCha8Rng::seed_from_u64(0),
+        }
+    }
+}
+
+impl CameraShakes {
+    pub fn push(&mut self, shake: CameraShake) { self.shakes.push(shake); }
+
+    /// Starts shaking the camera if the outcome is felt by the player
+    pub fn handle_outcome(&mut self, outcome: &Outcome, player_pos: Vec3<f32>, player: Uid) {
+        match outcome {
+            Outcome::Explosion { pos, radius, .. } => {
+                if let Some(shake) = CameraShake::explosion(*radius, pos.distance(player_pos)) {
+                    self.push(shake);
+                }
+            },
+            Outcome::FallDamage { uid, damage, .. } if *uid == player => {
+                self.push(CameraShake::fall(*damage));
+            },
+            _ => {},
+        }
+    }
+
+    /// Advances all shakes by `dt`, returning the combined offset to apply to
+    /// the camera
+    pub fn offset(&mut self, dt: Duration) -> Vec2<f32> {
+        let rng = &mut self.rng;
+        let offset = self
+            .shakes
+            .iter_mut()
+            .map(|shake| shake.update(dt, rng))
+            .fold(Vec2::zero(), |a, b| a + b);
+        self.shakes.retain(|shake| !shake.remaining.is_zero());
+        offset
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn explosions_fall_off_with_distance() {
+        let near = CameraShake::explosion(5.0, 2.0).unwrap();
+        let far = CameraShake::explosion(5.0, 15.0).unwrap();
+        assert!(near.intensity > far.intensity);
+        assert!(CameraShake::explosion(5.0, 20.0).is_none());
+    }
+
+    #[test]
+    fn shakes_fade_out() {
+        let mut shakes = CameraShakes::default();
+        shakes.push(CameraShake::fall(100.0));
+        let dt = Duration::from_millis(50);
+        let mut last = f32::INFINITY;
+        for _ in 0..6 {
+            let offset = shakes.offset(dt);
+            assert!(offset.x.abs() <= 0.4 && offset.y.abs() <= 0.4);
+            // The envelope shrinks every frame
+            let envelope = shakes.shakes[0].remaining.as_secs_f32();
+            assert!(envelope < last);
+            last = envelope;
+        }
+        assert_eq!(shakes.offset(dt), Vec2::zero());
+        assert!(shakes.shakes.is_empty());
+    }
+}
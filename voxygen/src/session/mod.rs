@@ -1,7 +1,10 @@
+mod camera_shake;
 pub mod interactable;
 pub mod settings_change;
 mod target;
 
+pub use camera_shake::CameraShake;
+
 use std::{cell::RefCell, collections::HashSet, rc::Rc, result::Result, sync::Arc, time::Duration};
 
 #[cfg(not(target_os = "macos"))]
@@ -32,7 +35,7 @@ use common::{
 };
 use common_base::{prof_span, span};
 use common_net::{
-    msg::{server::InviteAnswer, PresenceKind},
+    msg::{server::InviteAnswer, Notification, PresenceKind, WorldEventKind},
     sync::WorldSyncExt,
 };
 
@@ -43,12 +46,17 @@ use crate::{
     hud::{DebugInfo, Event as HudEvent, Hud, HudInfo, LootMessage, PromptDialogSettings},
     key_state::KeyState,
     menu::char_selection::CharSelectionState,
+    preload::StartupFrameSpikes,
     render::{Drawer, GlobalsBindGroup},
-    scene::{camera, terrain::Interaction, CameraMode, DebugShapeId, Scene, SceneData},
+    scene::{
+        camera, terrain::Interaction, CameraMode, ChunkBorderHighlight, DebugShapeId, Scene,
+        SceneData, TrajectoryPreview,
+    },
     settings::Settings,
     window::{AnalogGameInput, Event},
     Direction, GlobalState, PlayState, PlayStateResult,
 };
+use camera_shake::CameraShakes;
 use hashbrown::HashMap;
 use interactable::{select_interactable, Interactable};
 use settings_change::Language::ChangeLanguage;
@@ -85,6 +93,10 @@ pub struct SessionState {
     #[cfg(not(target_os = "macos"))]
     mumble_link: SharedLink,
     hitboxes: HashMap<specs::Entity, DebugShapeId>,
+    chunk_borders: ChunkBorderHighlight,
+    trajectory_preview: TrajectoryPreview,
+    camera_shakes: CameraShakes,
+    startup_frame_spikes: StartupFrameSpikes,
 }
 
 /// Represents an active game session (i.e., the one being played).
@@ -146,6 +158,10 @@ impl SessionState {
             #[cfg(not(target_os = "macos"))]
             mumble_link,
             hitboxes: HashMap::new(),
+            chunk_borders: ChunkBorderHighlight::default(),
+            trajectory_preview: TrajectoryPreview::default(),
+            camera_shakes: CameraShakes::default(),
+            startup_frame_spikes: StartupFrameSpikes::default(),
         }
     }
 
@@ -167,6 +183,17 @@ impl SessionState {
         let mut client = self.client.borrow_mut();
         self.scene
             .maintain_debug_hitboxes(&client, &global_state.settings, &mut self.hitboxes);
+        self.scene.maintain_debug_chunk_borders(
+            &client,
+            &global_state.settings,
+            &mut self.chunk_borders,
+        );
+        self.scene.maintain_trajectory_preview(
+            &client,
+            &global_state.settings,
+            self.inputs.look_dir,
+            &mut self.trajectory_preview,
+        );
 
         #[cfg(not(target_os = "macos"))]
         {
@@ -203,6 +230,8 @@ impl SessionState {
                     let kind_str = match kind {
                         InviteKind::Group => "Group",
                         InviteKind::Trade => "Trade",
+                        InviteKind::Guild => "Guild",
+                        InviteKind::Friend => "Friend",
                     };
                     let target_name = match client.player_list().get(&target) {
                         Some(info) => info.player_alias.clone(),
@@ -285,16 +314,24 @@ impl SessionState {
                         message,
                     });
                 },
-                client::Event::Kicked(reason) => {
+                client::Event::Kicked {
+                    reason,
+                    reconnect_allowed,
+                } => {
                     global_state.info_message = Some(format!(
                         "{}: {}",
                         global_state.i18n.read().get("main.login.kicked"),
                         reason
                     ));
+                    global_state.reconnect_blocked = !reconnect_allowed;
                     return Ok(TickAction::Disconnect);
                 },
                 client::Event::Notification(n) => {
-                    self.hud.new_notification(n);
+                    if !matches!(n, Notification::FriendOnline(_))
+                        || global_state.settings.interface.friend_online_toast
+                    {
+                        self.hud.new_notification(n);
+                    }
                 },
                 client::Event::SetViewDistance(vd) => {
                     global_state.settings.graphics.view_distance = vd;
@@ -311,6 +348,29 @@ impl SessionState {
                 client::Event::MapMarker(event) => {
                     self.hud.show.update_map_markers(event);
                 },
+                client::Event::WorldEvent(WorldEventKind::BossPhaseChange {
+                    boss_uid,
+                    new_phase,
+                }) => {
+                    let name = client
+                        .state()
+                        .ecs()
+                        .entity_from_uid(boss_uid.into())
+                        .and_then(|entity| {
+                            let stats = client.state().read_storage::<Stats>();
+                            stats.get(entity).map(|stats| stats.name.clone())
+                        });
+                    // Bosses far away aren't synced, nobody there to tell anyway
+                    if let Some(name) = name {
+                        let msg = global_state
+                            .i18n
+                            .read()
+                            .get("hud.chat.boss_phase_change")
+                            .replace("{name}", &name)
+                            .replace("{phase}", &(new_phase + 1).to_string());
+                        self.hud.new_message(ChatType::Meta.chat_msg(msg));
+                    }
+                },
             }
         }
 
@@ -338,6 +398,7 @@ impl PlayState for SessionState {
 
     fn tick(&mut self, global_state: &mut GlobalState, events: Vec<Event>) -> PlayStateResult {
         span!(_guard, "tick", "<Session as PlayState>::tick");
+        self.startup_frame_spikes.record(global_state.clock.dt());
         // TODO: let mut client = self.client.borrow_mut();
 
         // TODO: can this be a method on the session or are there borrowcheck issues?
@@ -713,7 +774,13 @@ impl PlayState for SessionState {
                                 }
                             },
                             GameInput::Interact => {
-                                if state {
+                                if self.client.borrow().fishing().is_some() {
+                                    // Holding interact reels in the fish on the line
+                                    self.client.borrow_mut().reel_fishing_line(state);
+                                } else if state && self.client.borrow().is_fishing() {
+                                    // Hooks the fish if it just bit at the bobber
+                                    self.client.borrow_mut().reel_in();
+                                } else if state {
                                     if let Some(interactable) = self.interactable {
                                         let mut client = self.client.borrow_mut();
                                         match interactable {
@@ -731,6 +798,7 @@ impl PlayState for SessionState {
                                                         )
                                                     },
                                                     Interaction::Mine => {},
+                                                    Interaction::Sleep => client.sleep(pos),
                                                 }
                                             },
                                             Interactable::Entity(entity) => {
@@ -751,11 +819,29 @@ impl PlayState for SessionState {
                                                 {
                                                     // TODO: maybe start crafting instead?
                                                     client.toggle_sit();
+                                                } else if client
+                                                    .state()
+                                                    .ecs()
+                                                    .read_storage::<comp::Gravestone>()
+                                                    .contains(entity)
+                                                {
+                                                    // Gravestones are looted through the trade UI
+                                                    if let Some(uid) =
+                                                        client.state().ecs().uid_from_entity(entity)
+                                                    {
+                                                        client.send_invite(uid, InviteKind::Trade);
+                                                    }
                                                 } else {
                                                     client.npc_interact(entity);
                                                 }
                                             },
                                         }
+                                    } else {
+                                        let mut client = self.client.borrow_mut();
+                                        if client.is_wielding() == Some(true) {
+                                            // Casts the line if a fishing rod is wielded
+                                            client.start_fishing();
+                                        }
                                     }
                                 }
                             },
@@ -1026,13 +1112,17 @@ impl PlayState for SessionState {
                     Ok(TickAction::Continue) => {}, // Do nothing
                     Ok(TickAction::Disconnect) => return PlayStateResult::Pop, // Go to main menu
                     Err(err) => {
-                        global_state.info_message = Some(
-                            global_state
-                                .i18n
-                                .read()
-                                .get("common.connection_lost")
-                                .to_owned(),
-                        );
+                        if matches!(&err, Error::ClientError(e) if e.is_connection_lost()) {
+                            global_state.reconnect_pending = true;
+                        } else {
+                            global_state.info_message = Some(
+                                global_state
+                                    .i18n
+                                    .read()
+                                    .get("common.connection_lost")
+                                    .to_owned(),
+                            );
+                        }
                         error!("[session] Failed to tick the scene: {:?}", err);
 
                         return PlayStateResult::Pop;
@@ -1040,6 +1130,8 @@ impl PlayState for SessionState {
                 }
             }
 
+            global_state.connection_stats = self.client.borrow().connection_stats();
+
             // Recompute dependents just in case some input modified the camera
             self.scene
                 .camera_mut()
@@ -1426,6 +1518,12 @@ impl PlayState for SessionState {
                     HudEvent::InviteMember(uid) => {
                         self.client.borrow_mut().send_invite(uid, InviteKind::Group);
                     },
+                    HudEvent::AddFriend(name) => {
+                        self.client.borrow_mut().add_friend(name);
+                    },
+                    HudEvent::RemoveFriend(name) => {
+                        self.client.borrow_mut().remove_friend(name);
+                    },
                     HudEvent::AcceptInvite => {
                         self.client.borrow_mut().accept_invite();
                     },
@@ -1472,10 +1570,15 @@ impl PlayState for SessionState {
                     gamma: global_state.settings.graphics.gamma,
                     exposure: global_state.settings.graphics.exposure,
                     ambiance: global_state.settings.graphics.ambiance,
+                    cave_ambient: global_state.settings.graphics.cave_ambient,
                     mouse_smoothing: global_state.settings.gameplay.smooth_pan_enable,
                     sprite_render_distance: global_state.settings.graphics.sprite_render_distance
                         as f32,
                     particles_enabled: global_state.settings.graphics.particles_enabled,
+                    particle_density: global_state
+                        .settings
+                        .graphics
+                        .particle_density(global_state.battery_saver.active()),
                     weapon_trails_enabled: global_state.settings.graphics.weapon_trails_enabled,
                     figure_lod_render_distance: global_state
                         .settings
@@ -1487,6 +1590,10 @@ impl PlayState for SessionState {
 
                 // Runs if either in a multiplayer server or the singleplayer server is unpaused
                 if !global_state.paused() {
+                    let shake = self
+                        .camera_shakes
+                        .offset(global_state.clock.get_stable_dt());
+                    self.scene.camera_mut().set_shake(shake);
                     self.scene.maintain(
                         global_state.window.renderer_mut(),
                         &mut global_state.audio,
@@ -1495,10 +1602,25 @@ impl PlayState for SessionState {
                     );
 
                     // Process outcomes from client
+                    let player_pos = client
+                        .state()
+                        .read_storage::<Pos>()
+                        .get(client.entity())
+                        .map(|pos| pos.0);
                     for outcome in outcomes {
+                        // With a minimal presentation only the diary button shows new skill
+                        // points
+                        if matches!(outcome, Outcome::SkillPointGain { .. })
+                            && !global_state.settings.interface.skill_point_presentation
+                        {
+                            continue;
+                        }
+                        if let (Some(pos), Some(uid)) = (player_pos, client.uid()) {
+                            self.camera_shakes.handle_outcome(&outcome, pos, uid);
+                        }
                         self.scene
                             .handle_outcome(&outcome, &scene_data, &mut global_state.audio);
-                        self.hud.handle_outcome(&outcome);
+                        self.hud.handle_outcome(&outcome, client.uid());
                     }
                 }
             }
@@ -1544,10 +1666,13 @@ impl PlayState for SessionState {
             gamma: settings.graphics.gamma,
             exposure: settings.graphics.exposure,
             ambiance: settings.graphics.ambiance,
+            cave_ambient: settings.graphics.cave_ambient,
             mouse_smoothing: settings.gameplay.smooth_pan_enable,
             sprite_render_distance: settings.graphics.sprite_render_distance as f32,
             figure_lod_render_distance: settings.graphics.figure_lod_render_distance as f32,
             particles_enabled: settings.graphics.particles_enabled,
+            // Only used when spawning particles
+            particle_density: 1.0,
             weapon_trails_enabled: settings.graphics.weapon_trails_enabled,
             is_aiming: self.is_aiming,
         };
@@ -3,13 +3,13 @@ use crate::{
     controller::ControllerSettings,
     game_input::GameInput,
     hud::{
-        BarNumbers, BuffPosition, ChatTab, CrosshairType, Intro, PressBehavior, ScaleChange,
-        ShortcutNumbers, XpBar,
+        BarNumbers, BuffPosition, ChatTab, CoordinatesDisplay, CrosshairType, Intro, PressBehavior,
+        ScaleChange, ShortcutNumbers, XpBar,
     },
     render::RenderMode,
     settings::{
-        AudioSettings, ChatSettings, ControlSettings, Fps, GamepadSettings, GameplaySettings,
-        GraphicsSettings, InterfaceSettings,
+        battery_saver_render_mode, AudioSettings, ChatSettings, ControlSettings, Fps,
+        GamepadSettings, GameplaySettings, GraphicsSettings, InterfaceSettings,
     },
     window::FullScreenSettings,
     GlobalState,
@@ -63,6 +63,7 @@ pub enum Gameplay {
     ChangePlayerPhysicsBehavior { server_authoritative: bool },
     ChangeStopAutoWalkOnInput(bool),
     ChangeAutoCamera(bool),
+    ChangeTrajectoryPreview(bool),
 
     ResetGameplaySettings,
 }
@@ -87,7 +88,11 @@ pub enum Graphics {
     ToggleParticlesEnabled(bool),
     ToggleLossyTerrainCompression(bool),
     ToggleWeaponTrailsEnabled(bool),
+    ToggleCaveAmbient(bool),
+    ToggleBatterySaver(bool),
+    ToggleBatterySaverAuto(bool),
     AdjustWindowSize([u16; 2]),
+    ChangeAdapter(Option<String>),
 
     ResetGraphicsSettings,
 }
@@ -102,9 +107,12 @@ pub enum Interface {
     ToggleHelp(bool),
     ToggleDebug(bool),
     ToggleHitboxes(bool),
+    ToggleChunkBorders(bool),
     ToggleChat(bool),
     ToggleTips(bool),
+    ToggleFriendToast(bool),
     ToggleHotkeyHints(bool),
+    ToggleSkillPointPresentation(bool),
 
     CrosshairTransp(f32),
     CrosshairType(CrosshairType),
@@ -112,6 +120,7 @@ pub enum Interface {
     ToggleXpBar(XpBar),
     ToggleBarNumbers(BarNumbers),
     ToggleAlwaysShowBars(bool),
+    Coordinates(CoordinatesDisplay),
     ToggleShortcutNumbers(ShortcutNumbers),
     BuffPosition(BuffPosition),
 
@@ -317,6 +326,9 @@ impl SettingsChange {
                     Gameplay::ChangeAutoCamera(state) => {
                         settings.gameplay.auto_camera = state;
                     },
+                    Gameplay::ChangeTrajectoryPreview(state) => {
+                        settings.gameplay.trajectory_preview = state;
+                    },
                     Gameplay::ResetGameplaySettings => {
                         // Reset Gameplay Settings
                         settings.gameplay = GameplaySettings::default();
@@ -379,11 +391,16 @@ impl SettingsChange {
                         settings.graphics.ambiance = new_ambiance;
                     },
                     Graphics::ChangeRenderMode(new_render_mode) => {
+                        let render_mode = if global_state.battery_saver.active() {
+                            battery_saver_render_mode(&new_render_mode)
+                        } else {
+                            (&*new_render_mode).clone()
+                        };
                         // Do this first so if it crashes the setting isn't saved :)
                         global_state
                             .window
                             .renderer_mut()
-                            .set_render_mode((&*new_render_mode).clone())
+                            .set_render_mode(render_mode)
                             .unwrap();
                         settings.graphics.render_mode = *new_render_mode;
                     },
@@ -406,10 +423,24 @@ impl SettingsChange {
                     Graphics::ToggleWeaponTrailsEnabled(weapon_trails_enabled) => {
                         settings.graphics.weapon_trails_enabled = weapon_trails_enabled;
                     },
+                    Graphics::ToggleCaveAmbient(cave_ambient) => {
+                        settings.graphics.cave_ambient = cave_ambient;
+                    },
+                    // The render mode is updated by `GlobalState::maintain`
+                    Graphics::ToggleBatterySaver(battery_saver) => {
+                        settings.graphics.battery_saver = battery_saver;
+                    },
+                    Graphics::ToggleBatterySaverAuto(battery_saver_auto) => {
+                        settings.graphics.battery_saver_auto = battery_saver_auto;
+                    },
                     Graphics::AdjustWindowSize(new_size) => {
                         global_state.window.set_size(new_size.into());
                         settings.graphics.window_size = new_size;
                     },
+                    Graphics::ChangeAdapter(adapter) => {
+                        // Only applies after a restart
+                        settings.graphics.adapter = adapter;
+                    },
                     Graphics::ResetGraphicsSettings => {
                         settings.graphics = GraphicsSettings::default();
                         let graphics = &settings.graphics;
@@ -468,15 +499,24 @@ impl SettingsChange {
                     Interface::ToggleHitboxes(toggle_hitboxes) => {
                         settings.interface.toggle_hitboxes = toggle_hitboxes;
                     },
+                    Interface::ToggleChunkBorders(toggle_chunk_borders) => {
+                        settings.interface.toggle_chunk_borders = toggle_chunk_borders;
+                    },
                     Interface::ToggleChat(toggle_chat) => {
                         settings.interface.toggle_chat = toggle_chat;
                     },
                     Interface::ToggleTips(loading_tips) => {
                         settings.interface.loading_tips = loading_tips;
                     },
+                    Interface::ToggleFriendToast(friend_online_toast) => {
+                        settings.interface.friend_online_toast = friend_online_toast;
+                    },
                     Interface::ToggleHotkeyHints(toggle_hotkey_hints) => {
                         settings.interface.toggle_hotkey_hints = toggle_hotkey_hints;
                     },
+                    Interface::ToggleSkillPointPresentation(skill_point_presentation) => {
+                        settings.interface.skill_point_presentation = skill_point_presentation;
+                    },
                     Interface::CrosshairTransp(crosshair_opacity) => {
                         settings.interface.crosshair_opacity = crosshair_opacity;
                     },
@@ -495,6 +535,9 @@ impl SettingsChange {
                     Interface::ToggleAlwaysShowBars(always_show_bars) => {
                         settings.interface.always_show_bars = always_show_bars;
                     },
+                    Interface::Coordinates(coordinates) => {
+                        settings.interface.coordinates = coordinates;
+                    },
                     Interface::ToggleShortcutNumbers(shortcut_numbers) => {
                         settings.interface.shortcut_numbers = shortcut_numbers;
                     },
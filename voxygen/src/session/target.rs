@@ -1,4 +1,4 @@
-use specs::{Join, WorldExt};
+use specs::WorldExt;
 use vek::*;
 
 use client::{self, Client};
@@ -7,9 +7,9 @@ use common::{
     consts::MAX_PICKUP_RANGE,
     link::Is,
     mounting::Mount,
+    ray::{RayCast, RayTarget},
     terrain::Block,
     util::find_dist::{Cylinder, FindDist},
-    vol::ReadVol,
 };
 use common_base::span;
 
@@ -79,112 +79,91 @@ pub(super) fn targets_under_cursor(
     let terrain = client.state().terrain();
 
     let find_pos = |hit: fn(Block) -> bool| {
-        let cam_ray = terrain
-            .ray(cam_pos, cam_pos + cam_dir * 100.0)
+        let cam_dist = RayCast::new(&*terrain, cam_pos, cam_dir, 100.0)
             .until(|block| hit(*block))
-            .cast();
-        let cam_ray = (cam_ray.0, cam_ray.1.map(|x| x.copied()));
-        let cam_dist = cam_ray.0;
-
-        if matches!(
-            cam_ray.1,
-            Ok(Some(_)) if player_cylinder.min_distance(cam_pos + cam_dir * (cam_dist + 0.01)) <= MAX_PICKUP_RANGE
-        ) {
-            (
-                Some(cam_pos + cam_dir * (cam_dist + 0.01)),
-                Some(cam_pos + cam_dir * (cam_dist - 0.01)),
-                Some(cam_ray),
-            )
-        } else {
-            (None, None, None)
+            .cast()
+            .map(|hit| hit.dist);
+
+        match cam_dist {
+            Some(cam_dist)
+                if player_cylinder.min_distance(cam_pos + cam_dir * (cam_dist + 0.01))
+                    <= MAX_PICKUP_RANGE =>
+            {
+                (
+                    Some(cam_pos + cam_dir * (cam_dist + 0.01)),
+                    Some(cam_pos + cam_dir * (cam_dist - 0.01)),
+                    Some(cam_dist),
+                )
+            },
+            _ => (None, None, None),
         }
     };
 
-    let (collect_pos, _, collect_cam_ray) = find_pos(|b: Block| b.is_collectible());
-    let (mine_pos, _, mine_cam_ray) = is_mining
+    let (collect_pos, _, collect_cam_dist) = find_pos(|b: Block| b.is_collectible());
+    let (mine_pos, _, mine_cam_dist) = is_mining
         .then(|| find_pos(|b: Block| b.mine_tool().is_some()))
         .unwrap_or((None, None, None));
-    let (solid_pos, place_block_pos, solid_cam_ray) = find_pos(|b: Block| b.is_filled());
-
-    // See if ray hits entities
-    // Don't cast through blocks, (hence why use shortest_cam_dist from non-entity
-    // targets) Could check for intersection with entity from last frame to
-    // narrow this down
-    let cast_dist = solid_cam_ray
-        .as_ref()
-        .map(|(d, _)| d.min(MAX_TARGET_RANGE))
-        .unwrap_or(MAX_TARGET_RANGE);
-
-    // Need to raycast by distance to cam
-    // But also filter out by distance to the player (but this only needs to be done
-    // on final result)
-    let mut nearby = (
-        &ecs.entities(),
-        &positions,
-        scales.maybe(),
-        &ecs.read_storage::<comp::Body>(),
-        ecs.read_storage::<comp::Item>().maybe(),
-        !&ecs.read_storage::<Is<Mount>>(),
-    )
-        .join()
-        .filter(|(e, _, _, _, _, _)| *e != player_entity)
-        .filter_map(|(e, p, s, b, i, _)| {
-            const RADIUS_SCALE: f32 = 3.0;
-            // TODO: use collider radius instead of body radius?
-            let radius = s.map_or(1.0, |s| s.0) * b.max_radius() * RADIUS_SCALE;
-            // Move position up from the feet
-            let pos = Vec3::new(p.0.x, p.0.y, p.0.z + radius);
-            // Distance squared from camera to the entity
-            let dist_sqr = pos.distance_squared(cam_pos);
-            // We only care about interacting with entities that contain items,
-            // or are not inanimate (to trade with)
-            if i.is_some() || !matches!(b, comp::Body::Object(_)) {
-                Some((e, pos, radius, dist_sqr))
-            } else {
-                None
-            }
-        })
-        // Roughly filter out entities farther than ray distance
-        .filter(|(_, _, r, d_sqr)| *d_sqr <= cast_dist.powi(2) + 2.0 * cast_dist * r + r.powi(2))
-        // Ignore entities intersecting the camera
-        .filter(|(_, _, r, d_sqr)| *d_sqr > r.powi(2))
-        // Substract sphere radius from distance to the camera
-        .map(|(e, p, r, d_sqr)| (e, p, r, d_sqr.sqrt() - r))
-        .collect::<Vec<_>>();
-    // Sort by distance
-    nearby.sort_unstable_by(|a, b| a.3.partial_cmp(&b.3).unwrap());
-
-    let seg_ray = LineSegment3 {
-        start: cam_pos,
-        end: cam_pos + cam_dir * cast_dist,
+    let (solid_pos, place_block_pos, solid_cam_dist) = find_pos(|b: Block| b.is_filled());
+
+    let bodies = ecs.read_storage::<comp::Body>();
+    let items = ecs.read_storage::<comp::Item>();
+    let mounts = ecs.read_storage::<Is<Mount>>();
+    // Sphere around the entity that the cursor has to point at
+    let target_sphere = |e: specs::Entity| {
+        const RADIUS_SCALE: f32 = 3.0;
+        let (p, b) = (positions.get(e)?, bodies.get(e)?);
+        // We only care about interacting with entities that contain items,
+        // or are not inanimate (to trade with)
+        if mounts.contains(e) || (items.get(e).is_none() && matches!(b, comp::Body::Object(_))) {
+            return None;
+        }
+        // TODO: use collider radius instead of body radius?
+        let radius = scales.get(e).map_or(1.0, |s| s.0) * b.max_radius() * RADIUS_SCALE;
+        // Move position up from the feet
+        Some((Vec3::new(p.0.x, p.0.y, p.0.z + radius), radius))
     };
+
+    // See if ray hits entities, blocks in front of them hide them
     // TODO: fuzzy borders
-    let entity_target = nearby
-        .iter()
-        .map(|(e, p, r, _)| (e, *p, r))
-        // Find first one that intersects the ray segment
-        .find(|(_, p, r)| seg_ray.projected_point(*p).distance_squared(*p) < r.powi(2))
-        .and_then(|(e, p, _)| {
+    let spatial_grid = ecs.read_resource::<common::CachedSpatialGrid>();
+    let entity_target = RayCast::new(&*terrain, cam_pos, cam_dir, MAX_TARGET_RANGE)
+        .until(|block| block.is_filled())
+        .spatial_grid(&spatial_grid.0, |e| {
+            let (p, r) = target_sphere(e)?;
+            // Ignore entities intersecting the camera
+            (p.distance_squared(cam_pos) > r.powi(2)).then(|| Aabb {
+                min: p - r,
+                max: p + r,
+            })
+        })
+        .ignore_entity(player_entity)
+        .cast()
+        .and_then(|hit| match hit.target {
+            RayTarget::Entity(e) => Some((e, target_sphere(e)?.0)),
+            RayTarget::Block(_) => None,
+        })
+        .and_then(|(e, p)| {
             // Get the entity's cylinder
             let target_cylinder = Cylinder::from_components(
                 p,
-                scales.get(*e).copied(),
-                colliders.get(*e),
-                char_states.get(*e),
+                scales.get(e).copied(),
+                colliders.get(e),
+                char_states.get(e),
             );
 
             let dist_to_player = player_cylinder.min_distance(target_cylinder);
             if dist_to_player < MAX_TARGET_RANGE {
                 Some(Target {
-                    kind: Entity(*e),
+                    kind: Entity(e),
                     position: p,
                     distance: dist_to_player,
                 })
-            } else { None }
+            } else {
+                None
+            }
         });
 
-    let solid_ray_dist = solid_cam_ray.map(|r| r.0);
-    let terrain_target = if let (None, Some(distance)) = (entity_target, solid_ray_dist) {
+    let terrain_target = if let (None, Some(distance)) = (entity_target, solid_cam_dist) {
         solid_pos.map(|position| Target {
             kind: Terrain,
             distance,
@@ -194,7 +173,7 @@ pub(super) fn targets_under_cursor(
         None
     };
 
-    let build_target = if let (true, Some(distance)) = (can_build, solid_ray_dist) {
+    let build_target = if let (true, Some(distance)) = (can_build, solid_cam_dist) {
         place_block_pos
             .zip(solid_pos)
             .map(|(place_pos, position)| Target {
@@ -207,18 +186,20 @@ pub(super) fn targets_under_cursor(
     };
 
     let collect_target = collect_pos
-        .zip(collect_cam_ray)
-        .map(|(position, ray)| Target {
+        .zip(collect_cam_dist)
+        .map(|(position, distance)| Target {
             kind: Collectable,
-            distance: ray.0,
+            distance,
             position,
         });
 
-    let mine_target = mine_pos.zip(mine_cam_ray).map(|(position, ray)| Target {
-        kind: Mine,
-        distance: ray.0,
-        position,
-    });
+    let mine_target = mine_pos
+        .zip(mine_cam_dist)
+        .map(|(position, distance)| Target {
+            kind: Mine,
+            distance,
+            position,
+        });
 
     // Return multiple possible targets
     // GameInput events determine which target to use.
@@ -2,7 +2,7 @@ use crate::{game_input::GameInput, window::KeyMouse};
 use hashbrown::{HashMap, HashSet};
 use serde::{Deserialize, Serialize};
 use strum::IntoEnumIterator;
-use winit::event::{MouseButton, VirtualKeyCode};
+use winit::event::{ModifiersState, MouseButton, VirtualKeyCode};
 
 // ControlSetting-like struct used by Serde, to handle not serializing/building
 // post-deserializing the inverse_keybindings hashmap
@@ -106,6 +106,21 @@ impl ControlSettings {
         self.keybindings.insert(game_input, Some(key_mouse));
     }
 
+    /// Returns the binding a key press resolves to. The most specific binding
+    /// wins, so a bound chord (e.g. Ctrl + K) is preferred over the bare key.
+    pub fn resolve_key(&self, modifiers: ModifiersState, key: VirtualKeyCode) -> KeyMouse {
+        let chord = KeyMouse::with_modifiers(modifiers, key);
+        if self
+            .inverse_keybindings
+            .get(&chord)
+            .map_or(false, |game_inputs| !game_inputs.is_empty())
+        {
+            chord
+        } else {
+            KeyMouse::Key(key)
+        }
+    }
+
     /// Return true if this key is used for multiple GameInputs that aren't
     /// expected to be safe to have bound to the same key at the same time
     pub fn has_conflicting_bindings(&self, key_mouse: KeyMouse) -> bool {
@@ -206,3 +221,72 @@ impl Default for ControlSettings {
         new_settings
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chord_preferred_over_bare_key() {
+        let mut controls = ControlSettings::default();
+        let chord = KeyMouse::KeyChord(ModifiersState::CTRL, VirtualKeyCode::K);
+        controls.modify_binding(GameInput::ToggleDebug, chord);
+        assert_eq!(
+            controls.resolve_key(ModifiersState::CTRL, VirtualKeyCode::K),
+            chord
+        );
+        // bare key still reaches its own binding
+        assert_eq!(
+            controls.resolve_key(ModifiersState::empty(), VirtualKeyCode::K),
+            KeyMouse::Key(VirtualKeyCode::K)
+        );
+        // unbound chord falls back to the bare key
+        assert_eq!(
+            controls.resolve_key(ModifiersState::SHIFT, VirtualKeyCode::K),
+            KeyMouse::Key(VirtualKeyCode::K)
+        );
+    }
+
+    #[test]
+    fn modifier_keys_are_no_chords() {
+        let controls = ControlSettings::default();
+        assert_eq!(
+            controls.resolve_key(ModifiersState::SHIFT, VirtualKeyCode::LShift),
+            KeyMouse::Key(VirtualKeyCode::LShift)
+        );
+    }
+
+    #[test]
+    fn chord_and_bare_key_dont_conflict() {
+        let mut controls = ControlSettings::default();
+        controls.modify_binding(
+            GameInput::ToggleDebug,
+            KeyMouse::KeyChord(ModifiersState::CTRL, VirtualKeyCode::K),
+        );
+        assert!(!controls.has_conflicting_bindings(KeyMouse::Key(VirtualKeyCode::K)));
+        assert!(
+            !controls.has_conflicting_bindings(KeyMouse::KeyChord(
+                ModifiersState::CTRL,
+                VirtualKeyCode::K
+            ))
+        );
+    }
+
+    #[test]
+    fn old_settings_still_load() {
+        let controls: ControlSettings =
+            ron::de::from_str("(keybindings: {Sit: Some(Key(L)), Jump: None})").unwrap();
+        assert_eq!(
+            controls.get_binding(GameInput::Sit),
+            Some(KeyMouse::Key(VirtualKeyCode::L))
+        );
+        assert_eq!(controls.get_binding(GameInput::Jump), None);
+
+        let mut controls = ControlSettings::default();
+        let chord = KeyMouse::KeyChord(ModifiersState::CTRL, VirtualKeyCode::K);
+        controls.modify_binding(GameInput::ToggleDebug, chord);
+        let ron = ron::ser::to_string(&controls).unwrap();
+        let controls: ControlSettings = ron::de::from_str(&ron).unwrap();
+        assert_eq!(controls.get_binding(GameInput::ToggleDebug), Some(chord));
+    }
+}
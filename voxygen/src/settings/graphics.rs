@@ -1,4 +1,7 @@
-use crate::{render::RenderMode, window::FullScreenSettings};
+use crate::{
+    render::{RenderMode, ShadowMapMode, ShadowMode},
+    window::FullScreenSettings,
+};
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
@@ -24,6 +27,24 @@ impl fmt::Display for Fps {
     }
 }
 
+impl Fps {
+    /// Limits the fps to at most `max`
+    pub fn capped(self, max: u32) -> Self {
+        match self {
+            Fps::Max(x) => Fps::Max(x.min(max)),
+            Fps::Unlimited => Fps::Max(max),
+        }
+    }
+}
+
+// Limits applied on top of the user's settings while the battery saver is
+// active, the settings themselves are never changed
+const BATTERY_SAVER_MAX_FPS: u32 = 30;
+const BATTERY_SAVER_MAX_BACKGROUND_FPS: u32 = 5;
+const BATTERY_SAVER_UPSCALE_FACTOR: f32 = 0.75;
+const BATTERY_SAVER_SHADOW_MAP_RESOLUTION: f32 = 0.5;
+const BATTERY_SAVER_PARTICLE_DENSITY: f32 = 0.5;
+
 /// `GraphicsSettings` contains settings related to framerate and in-game
 /// visuals.
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -41,10 +62,20 @@ pub struct GraphicsSettings {
     pub gamma: f32,
     pub exposure: f32,
     pub ambiance: f32,
+    /// Keep caves dimly lit the deeper they are, see
+    /// [`crate::render::CaveAmbientLight`]
+    pub cave_ambient: bool,
     pub render_mode: RenderMode,
     pub window_size: [u16; 2],
     pub fullscreen: FullScreenSettings,
     pub lod_detail: u32,
+    /// Lower the framerate and a few visual settings to save power
+    pub battery_saver: bool,
+    /// Enable the battery saver whenever the device runs on battery
+    pub battery_saver_auto: bool,
+    /// Use the first graphics adapter with a name containing this, instead of
+    /// the first discrete GPU
+    pub adapter: Option<String>,
 }
 
 impl Default for GraphicsSettings {
@@ -62,10 +93,126 @@ impl Default for GraphicsSettings {
             gamma: 1.0,
             exposure: 1.0,
             ambiance: 0.5,
+            cave_ambient: true,
             render_mode: RenderMode::default(),
             window_size: [1280, 720],
             fullscreen: FullScreenSettings::default(),
             lod_detail: 250,
+            battery_saver: false,
+            battery_saver_auto: false,
+            adapter: None,
+        }
+    }
+}
+
+impl GraphicsSettings {
+    /// Whether the battery saver overlay applies, `on_battery` is what the
+    /// platform reported last
+    pub fn battery_saver_active(&self, on_battery: bool) -> bool {
+        self.battery_saver || (self.battery_saver_auto && on_battery)
+    }
+
+    pub fn effective_max_fps(&self, battery_saver: bool) -> Fps {
+        if battery_saver {
+            self.max_fps.capped(BATTERY_SAVER_MAX_FPS)
+        } else {
+            self.max_fps
+        }
+    }
+
+    pub fn effective_max_background_fps(&self, battery_saver: bool) -> Fps {
+        if battery_saver {
+            self.max_background_fps
+                .capped(BATTERY_SAVER_MAX_BACKGROUND_FPS)
+        } else {
+            self.max_background_fps
+        }
+    }
+
+    pub fn effective_render_mode(&self, battery_saver: bool) -> RenderMode {
+        if battery_saver {
+            battery_saver_render_mode(&self.render_mode)
+        } else {
+            self.render_mode.clone()
         }
     }
+
+    /// Fraction of particles that get spawned
+    pub fn particle_density(&self, battery_saver: bool) -> f32 {
+        if battery_saver {
+            BATTERY_SAVER_PARTICLE_DENSITY
+        } else {
+            1.0
+        }
+    }
+}
+
+/// `render_mode` with the battery saver limits applied, settings that are
+/// already cheaper are kept
+pub fn battery_saver_render_mode(render_mode: &RenderMode) -> RenderMode {
+    let mut render_mode = render_mode.clone();
+    render_mode.upscale_mode.factor = render_mode
+        .upscale_mode
+        .factor
+        .min(BATTERY_SAVER_UPSCALE_FACTOR);
+    if let ShadowMode::Map(ShadowMapMode { resolution }) = &mut render_mode.shadow {
+        *resolution = resolution.min(BATTERY_SAVER_SHADOW_MAP_RESOLUTION);
+    }
+    render_mode
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn battery_saver_auto_enable() {
+        let mut settings = GraphicsSettings::default();
+        assert!(!settings.battery_saver_active(false));
+        assert!(!settings.battery_saver_active(true));
+
+        settings.battery_saver_auto = true;
+        assert!(!settings.battery_saver_active(false));
+        assert!(settings.battery_saver_active(true));
+
+        settings.battery_saver = true;
+        assert!(settings.battery_saver_active(false));
+        settings.battery_saver_auto = false;
+        assert!(settings.battery_saver_active(false));
+    }
+
+    #[test]
+    fn battery_saver_overlay() {
+        let mut settings = GraphicsSettings {
+            max_fps: Fps::Unlimited,
+            max_background_fps: Fps::Max(3),
+            ..GraphicsSettings::default()
+        };
+        settings.render_mode.shadow = ShadowMode::Map(ShadowMapMode { resolution: 2.0 });
+        settings.render_mode.upscale_mode.factor = 0.5;
+        let user_settings = settings.clone();
+
+        assert_eq!(settings.effective_max_fps(true), Fps::Max(30));
+        // already below the cap
+        assert_eq!(settings.effective_max_background_fps(true), Fps::Max(3));
+        assert_eq!(settings.particle_density(true), 0.5);
+        let render_mode = settings.effective_render_mode(true);
+        assert_eq!(
+            render_mode.shadow,
+            ShadowMode::Map(ShadowMapMode { resolution: 0.5 })
+        );
+        assert_eq!(render_mode.upscale_mode.factor, 0.5);
+
+        // turning it off restores the user's settings exactly
+        assert_eq!(settings.effective_max_fps(false), user_settings.max_fps);
+        assert_eq!(
+            settings.effective_max_background_fps(false),
+            user_settings.max_background_fps
+        );
+        assert_eq!(settings.particle_density(false), 1.0);
+        assert_eq!(
+            settings.effective_render_mode(false),
+            user_settings.render_mode
+        );
+    }
 }
@@ -1,5 +1,7 @@
 use crate::{
-    hud::{BarNumbers, BuffPosition, CrosshairType, Intro, ShortcutNumbers, XpBar},
+    hud::{
+        BarNumbers, BuffPosition, CoordinatesDisplay, CrosshairType, Intro, ShortcutNumbers, XpBar,
+    },
     ui::ScaleMode,
 };
 
@@ -12,8 +14,12 @@ pub struct InterfaceSettings {
     pub toggle_debug: bool,
     pub toggle_egui_debug: bool,
     pub toggle_hitboxes: bool,
+    pub toggle_chunk_borders: bool,
     pub toggle_chat: bool,
     pub toggle_hotkey_hints: bool,
+    /// Show a banner, particles and a sound when a skill point is earned,
+    /// otherwise only the diary button pulses
+    pub skill_point_presentation: bool,
     pub sct: bool,
     pub sct_player_batch: bool,
     pub sct_damage_batch: bool,
@@ -28,6 +34,7 @@ pub struct InterfaceSettings {
     pub buff_position: BuffPosition,
     pub bar_numbers: BarNumbers,
     pub always_show_bars: bool,
+    pub coordinates: CoordinatesDisplay,
     pub ui_scale: ScaleMode,
     pub map_zoom: f64,
     pub map_show_topo_map: bool,
@@ -36,6 +43,7 @@ pub struct InterfaceSettings {
     pub map_show_dungeons: bool,
     pub map_show_castles: bool,
     pub loading_tips: bool,
+    pub friend_online_toast: bool,
     pub map_show_caves: bool,
     pub map_show_trees: bool,
     pub map_show_peaks: bool,
@@ -52,8 +60,10 @@ impl Default for InterfaceSettings {
             toggle_debug: false,
             toggle_egui_debug: false,
             toggle_hitboxes: false,
+            toggle_chunk_borders: false,
             toggle_chat: true,
             toggle_hotkey_hints: true,
+            skill_point_presentation: true,
             sct: true,
             sct_player_batch: false,
             sct_damage_batch: false,
@@ -68,6 +78,7 @@ impl Default for InterfaceSettings {
             buff_position: BuffPosition::Bar,
             bar_numbers: BarNumbers::Values,
             always_show_bars: false,
+            coordinates: CoordinatesDisplay::Off,
             ui_scale: ScaleMode::RelativeToWindow([1920.0, 1080.0].into()),
             map_zoom: 10.0,
             map_show_topo_map: true,
@@ -76,6 +87,7 @@ impl Default for InterfaceSettings {
             map_show_dungeons: true,
             map_show_castles: false,
             loading_tips: true,
+            friend_online_toast: true,
             map_show_caves: true,
             map_show_trees: false,
             map_show_peaks: false,
@@ -21,10 +21,10 @@ pub use chat::ChatSettings;
 pub use control::ControlSettings;
 pub use gamepad::GamepadSettings;
 pub use gameplay::GameplaySettings;
-pub use graphics::{get_fps, Fps, GraphicsSettings};
+pub use graphics::{battery_saver_render_mode, get_fps, Fps, GraphicsSettings};
 pub use interface::InterfaceSettings;
 pub use language::LanguageSettings;
-pub use networking::NetworkingSettings;
+pub use networking::{NetworkingSettings, ReconnectPolicy};
 
 /// `Settings` contains everything that can be configured in the settings.ron
 /// file.
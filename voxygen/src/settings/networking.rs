@@ -1,5 +1,7 @@
-use hashbrown::HashSet;
+use hashbrown::{HashMap, HashSet};
+use rand::Rng;
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
 
 /// `NetworkingSettings` stores server and networking settings.
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -10,6 +12,18 @@ pub struct NetworkingSettings {
     pub default_server: String,
     pub trusted_auth_servers: HashSet<String>,
     pub use_quic: bool,
+    pub reconnect: ReconnectPolicy,
+    /// Public keys server admins published, by server address. Channels to
+    /// these servers are encrypted and only established if the server owns
+    /// the key, so nobody in between can pose as it.
+    pub pinned_server_keys: HashMap<String, String>,
+    /// Server addresses to connect to over Tcp encrypted with Tls, the
+    /// certificate has to be signed by a root of the system
+    pub tls_servers: HashSet<String>,
+    /// Certificate fingerprints server admins published, by server address.
+    /// These servers are reached over Tls as well and may use a self-signed
+    /// certificate, as long as it has the pinned fingerprint.
+    pub pinned_certificates: HashMap<String, String>,
 }
 
 impl Default for NetworkingSettings {
@@ -23,6 +37,89 @@ impl Default for NetworkingSettings {
                 .map(|s| s.to_string())
                 .collect(),
             use_quic: false,
+            reconnect: ReconnectPolicy::default(),
+            pinned_server_keys: HashMap::new(),
+            tls_servers: HashSet::new(),
+            pinned_certificates: HashMap::new(),
+        }
+    }
+}
+
+/// How often and how fast we retry when the server can't be reached, both
+/// while logging in and after the connection was lost mid-session.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReconnectPolicy {
+    /// Delay before the first retry, in seconds
+    pub initial_delay: f32,
+    /// The delay doubles after every attempt until it reaches this, in seconds
+    pub max_delay: f32,
+    /// Fraction `0.0..=1.0` of the delay which is randomized, so that clients
+    /// don't all hit a restarting server at once
+    pub jitter: f32,
+    pub max_attempts: u32,
+}
+
+impl Default for ReconnectPolicy {
+    // Gives up after roughly four minutes
+    fn default() -> Self {
+        Self {
+            initial_delay: 1.0,
+            max_delay: 30.0,
+            jitter: 0.2,
+            max_attempts: 12,
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// Gives up after the first attempt
+    pub fn no_retries() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    /// Delay before retrying after `attempt` (starting at 0) failed
+    pub fn delay(&self, attempt: u32, rng: &mut impl Rng) -> Duration {
+        let base = (self.initial_delay.max(0.0) * 2f32.powi(attempt.min(31) as i32))
+            .min(self.max_delay.max(0.0));
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        let factor = if jitter > 0.0 {
+            1.0 - rng.gen_range(0.0..=jitter)
+        } else {
+            1.0
+        };
+        Duration::from_secs_f32(base * factor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delay_grows_exponentially_up_to_max() {
+        let policy = ReconnectPolicy {
+            jitter: 0.0,
+            ..ReconnectPolicy::default()
+        };
+        let mut rng = rand::thread_rng();
+        let delays = (0..8)
+            .map(|attempt| policy.delay(attempt, &mut rng).as_secs_f32())
+            .collect::<Vec<_>>();
+        assert_eq!(delays, vec![1.0, 2.0, 4.0, 8.0, 16.0, 30.0, 30.0, 30.0]);
+        assert_eq!(policy.delay(u32::MAX, &mut rng), Duration::from_secs(30));
+    }
+
+    #[test]
+    fn jitter_only_shortens_delay() {
+        let policy = ReconnectPolicy::default();
+        let mut rng = rand::thread_rng();
+        for _ in 0..100 {
+            let delay = policy.delay(2, &mut rng).as_secs_f32();
+            assert!((4.0 * 0.8..=4.0).contains(&delay), "{}", delay);
         }
     }
 }
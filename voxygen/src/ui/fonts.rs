@@ -7,7 +7,7 @@ pub struct Font {
 }
 
 impl Font {
-    fn new(font: &i18n::Font, ui: &mut crate::ui::Ui) -> Result<Self, assets::Error> {
+    fn new(font: &i18n::Font, ui: &mut impl crate::ui::UiResources) -> Result<Self, assets::Error> {
         let raw_font = RawFont::load(&font.asset_key)?.cloned();
 
         Ok(Self {
@@ -28,7 +28,7 @@ macro_rules! conrod_fonts {
             }
 
             impl Fonts {
-                pub fn load(fonts: &i18n::Fonts, ui: &mut crate::ui::Ui) -> Result<Self, assets::Error> {
+                pub fn load(fonts: &i18n::Fonts, ui: &mut impl crate::ui::UiResources) -> Result<Self, assets::Error> {
                     Ok(Self {
                         $( $name: Font::new(fonts.get(stringify!($name)).unwrap(), ui)?, )*
                     })
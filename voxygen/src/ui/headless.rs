@@ -0,0 +1,109 @@
+//! Runs the ui without a window or renderer, so that widgets can be tested by
+//! feeding them synthetic input and checking the resulting layout.
+
+use super::{ice::RawFont, img_ids::Rotations, Event, Graphic, UiLayout, UiResources, WidgetQuery};
+use conrod_core::{
+    event::Input,
+    image::{Id as ImageId, Map},
+    input::{Button, Key, Motion, MouseButton},
+    text::{self, font},
+    widget, Point, UiCell,
+};
+use vek::*;
+
+/// Ui for tests. Graphics aren't kept around, fonts are loaded so text is laid
+/// out like in the game. Positions passed to the input methods are in ui
+/// coordinates, i.e. relative to the center of the window with y pointing up,
+/// like the rects returned by the [`WidgetQuery`].
+pub struct HeadlessUi {
+    pub layout: UiLayout,
+    image_map: Map<()>,
+    physical_resolution: Vec2<u32>,
+}
+
+impl HeadlessUi {
+    pub fn new(physical_resolution: Vec2<u32>) -> Self {
+        Self {
+            layout: UiLayout::new(physical_resolution, 1.0),
+            image_map: Map::new(),
+            physical_resolution,
+        }
+    }
+
+    pub fn new_id(&mut self) -> widget::Id { self.layout.id_generator().next() }
+
+    /// Lays out the widgets set by `f`, input sent since the last frame is
+    /// handled by them
+    pub fn frame<R>(&mut self, f: impl FnOnce(&mut UiCell) -> R) -> R {
+        self.layout.maintain(self.physical_resolution);
+        let (mut ui, _, _) = self.layout.set_widgets();
+        f(&mut ui)
+    }
+
+    pub fn query(&self) -> WidgetQuery { self.layout.query() }
+
+    pub fn focus_widget(&mut self, id: Option<widget::Id>) { self.layout.focus_widget(id); }
+
+    /// Resizes the window, takes effect in the next frame
+    pub fn resize(&mut self, physical_resolution: Vec2<u32>) {
+        self.physical_resolution = physical_resolution;
+        self.layout
+            .handle_event(Event::new_resize(physical_resolution.map(f64::from)));
+    }
+
+    pub fn move_cursor(&mut self, pos: Point) {
+        let pos = Vec2::<f64>::from(pos) * self.layout.scale().scale_factor_logical();
+        self.layout
+            .handle_event(Event(Input::Motion(Motion::MouseCursor {
+                x: pos.x,
+                y: pos.y,
+            })));
+    }
+
+    pub fn click(&mut self, pos: Point) {
+        self.move_cursor(pos);
+        let button = Button::Mouse(MouseButton::Left);
+        self.layout.handle_event(Event(Input::Press(button)));
+        self.layout.handle_event(Event(Input::Release(button)));
+    }
+
+    /// Clicks the center of the visible part of the widget
+    pub fn click_widget(&mut self, id: widget::Id) {
+        let rect = self
+            .query()
+            .visible_rect(id)
+            .expect("Can't click a widget that isn't visible");
+        self.click(rect.xy());
+    }
+
+    pub fn press_key(&mut self, key: Key) {
+        let button = Button::Keyboard(key);
+        self.layout.handle_event(Event(Input::Press(button)));
+        self.layout.handle_event(Event(Input::Release(button)));
+    }
+
+    pub fn type_text(&mut self, text: &str) {
+        self.layout
+            .handle_event(Event(Input::Text(text.to_owned())));
+    }
+}
+
+impl UiResources for HeadlessUi {
+    fn add_graphic(&mut self, _: Graphic) -> ImageId { self.image_map.insert(()) }
+
+    fn add_graphic_with_rotations(&mut self, _: Graphic) -> Rotations {
+        Rotations {
+            none: self.image_map.insert(()),
+            cw90: self.image_map.insert(()),
+            cw180: self.image_map.insert(()),
+            cw270: self.image_map.insert(()),
+            source_north: self.image_map.insert(()),
+            target_north: self.image_map.insert(()),
+        }
+    }
+
+    fn new_font(&mut self, font: RawFont) -> font::Id {
+        let font = text::Font::from_bytes(font.0).unwrap();
+        self.layout.ui.fonts.insert(font)
+    }
+}
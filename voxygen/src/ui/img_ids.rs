@@ -154,7 +154,7 @@ macro_rules! image_ids {
             }
 
             impl $Ids {
-                pub fn load(ui: &mut crate::ui::Ui) -> Result<Self, common::assets::Error> {
+                pub fn load(ui: &mut impl crate::ui::UiResources) -> Result<Self, common::assets::Error> {
                     use crate::ui::img_ids::GraphicCreator;
                     Ok(Self {
                         $($( $name: ui.add_graphic(<$T as GraphicCreator>::new_graphic($specifier)?), )*)*
@@ -195,7 +195,7 @@ macro_rules! rotation_image_ids {
             }
 
             impl $Ids {
-                pub fn load(ui: &mut crate::ui::Ui) -> Result<Self, common::assets::Error> {
+                pub fn load(ui: &mut impl crate::ui::UiResources) -> Result<Self, common::assets::Error> {
                     use crate::ui::img_ids::GraphicCreator;
                     Ok(Self {
                         $($( $name: ui.add_graphic_with_rotations(<$T as GraphicCreator>::new_graphic($specifier)?), )*)*
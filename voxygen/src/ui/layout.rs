@@ -0,0 +1,201 @@
+use super::{
+    query::WidgetQuery,
+    scale::{Scale, ScaleMode},
+    widgets::{item_tooltip::ItemTooltipManager, tooltip::TooltipManager},
+    Event,
+};
+use conrod_core::{
+    event::Input,
+    graph::Graph,
+    input::{touch::Touch, Motion, Widget},
+    widget::{self, id::Generator},
+    UiBuilder, UiCell,
+};
+use std::time::Duration;
+use vek::*;
+
+/// Event handling and widget layout of the conrod ui, i.e. everything besides
+/// drawing. It doesn't touch the renderer, so the ui tests can drive it
+/// without a window.
+pub struct UiLayout {
+    pub ui: conrod_core::Ui,
+    // Whether the window was resized since the last maintain, for updating scaling
+    window_resized: bool,
+    // Scale factor changed
+    scale_factor_changed: Option<f64>,
+    // Scaling of the ui
+    scale: Scale,
+    // Tooltips
+    tooltip_manager: TooltipManager,
+    // Item tooltips manager
+    item_tooltip_manager: ItemTooltipManager,
+}
+
+impl UiLayout {
+    pub fn new(physical_resolution: Vec2<u32>, scale_factor: f64) -> Self {
+        let scale = Scale::new(
+            physical_resolution,
+            scale_factor,
+            ScaleMode::Absolute(1.0),
+            1.0,
+        );
+
+        let win_dims = scale.scaled_resolution().into_array();
+
+        let mut ui = UiBuilder::new(win_dims).build();
+        // NOTE: Since we redraw the actual frame each time whether or not the UI needs
+        // to be updated, there's no reason to set the redraw count higher than
+        // 1.
+        ui.set_num_redraw_frames(1);
+
+        let item_tooltip_manager = ItemTooltipManager::new(
+            Duration::from_millis(1),
+            Duration::from_millis(0),
+            scale.scale_factor_logical(),
+        );
+
+        let tooltip_manager = TooltipManager::new(
+            ui.widget_id_generator(),
+            Duration::from_millis(1),
+            Duration::from_millis(0),
+            scale.scale_factor_logical(),
+        );
+
+        Self {
+            ui,
+            window_resized: false,
+            scale_factor_changed: None,
+            scale,
+            tooltip_manager,
+            item_tooltip_manager,
+        }
+    }
+
+    // Set the scaling mode of the ui.
+    pub fn set_scaling_mode(&mut self, mode: ScaleMode) {
+        self.scale.set_scaling_mode(mode);
+        // Give conrod the new size.
+        let (w, h) = self.scale.scaled_resolution().into_tuple();
+        self.ui.handle_event(Input::Resize(w, h));
+    }
+
+    pub fn scale_factor_changed(&mut self, scale_factor: f64) {
+        self.scale_factor_changed = Some(scale_factor);
+    }
+
+    // Get a copy of Scale
+    pub fn scale(&self) -> Scale { self.scale }
+
+    pub fn id_generator(&mut self) -> Generator { self.ui.widget_id_generator() }
+
+    pub fn set_widgets(&mut self) -> (UiCell, &mut ItemTooltipManager, &mut TooltipManager) {
+        (
+            self.ui.set_widgets(),
+            &mut self.item_tooltip_manager,
+            &mut self.tooltip_manager,
+        )
+    }
+
+    pub fn set_item_widgets(&mut self) -> (UiCell, &mut ItemTooltipManager) {
+        (self.ui.set_widgets(), &mut self.item_tooltip_manager)
+    }
+
+    // Accepts Option so widget can be unfocused.
+    pub fn focus_widget(&mut self, id: Option<widget::Id>) {
+        self.ui.keyboard_capture(match id {
+            Some(id) => id,
+            None => self.ui.window,
+        });
+    }
+
+    // Get id of current widget capturing keyboard.
+    pub fn widget_capturing_keyboard(&self) -> Option<widget::Id> {
+        self.ui.global_input().current.widget_capturing_keyboard
+    }
+
+    // Get whether a widget besides the window is capturing the mouse.
+    pub fn no_widget_capturing_mouse(&self) -> bool {
+        self.ui
+            .global_input()
+            .current
+            .widget_capturing_mouse
+            .filter(|id| id != &self.ui.window)
+            .is_none()
+    }
+
+    // Get the widget graph.
+    pub fn widget_graph(&self) -> &Graph { self.ui.widget_graph() }
+
+    pub fn query(&self) -> WidgetQuery { WidgetQuery::new(&self.ui) }
+
+    pub fn handle_event(&mut self, event: Event) {
+        match event.0 {
+            Input::Resize(w, h) => {
+                if w > 0.0 && h > 0.0 {
+                    self.window_resized = true;
+                }
+            },
+            Input::Touch(touch) => self.ui.handle_event(Input::Touch(Touch {
+                xy: self.scale.scale_point(touch.xy.into()).into_array(),
+                ..touch
+            })),
+            Input::Motion(motion) => self.ui.handle_event(Input::Motion(match motion {
+                Motion::MouseCursor { x, y } => {
+                    let (x, y) = self.scale.scale_point(Vec2::new(x, y)).into_tuple();
+                    Motion::MouseCursor { x, y }
+                },
+                Motion::MouseRelative { x, y } => {
+                    let (x, y) = self.scale.scale_point(Vec2::new(x, y)).into_tuple();
+                    Motion::MouseRelative { x, y }
+                },
+                Motion::Scroll { x, y } => {
+                    let (x, y) = self.scale.scale_point(Vec2::new(x, y)).into_tuple();
+                    Motion::Scroll { x, y }
+                },
+                _ => motion,
+            })),
+            _ => self.ui.handle_event(event.0),
+        }
+    }
+
+    pub fn widget_input(&self, id: widget::Id) -> Widget { self.ui.widget_input(id) }
+
+    /// Updates the tooltips and applies pending resizes and scale factor
+    /// changes for a surface of `surface_resolution` physical pixels.
+    /// Returns whether the scaled size of the ui changed, in which case cached
+    /// graphics have to be regenerated.
+    pub fn maintain(&mut self, surface_resolution: Vec2<u32>) -> bool {
+        // Maintain tooltip manager
+        self.tooltip_manager
+            .maintain(self.ui.global_input(), self.scale.scale_factor_logical());
+
+        // Maintain tooltip manager
+        self.item_tooltip_manager
+            .maintain(self.ui.global_input(), self.scale.scale_factor_logical());
+
+        // Handle scale factor changing
+        let need_resize = if let Some(scale_factor) = self.scale_factor_changed.take() {
+            self.scale.scale_factor_changed(scale_factor)
+        } else {
+            false
+        };
+
+        // Handle window resizing.
+        if self.window_resized {
+            self.window_resized = false;
+            let (old_w, old_h) = self.scale.scaled_resolution().into_tuple();
+            self.scale.surface_resized(surface_resolution);
+            let (w, h) = self.scale.scaled_resolution().into_tuple();
+            self.ui.handle_event(Input::Resize(w, h));
+
+            // Avoid panic in graphic cache when minimizing.
+            // Avoid resetting cache if window size didn't change
+            // Somewhat inefficient for elements that won't change size after a window
+            // resize
+            (surface_resolution.x > 0 && surface_resolution.y > 0 && !(old_w == w && old_h == h))
+                || need_resize
+        } else {
+            need_resize
+        }
+    }
+}
@@ -1,6 +1,9 @@
 mod cache;
 mod event;
 mod graphic;
+#[cfg(test)] pub mod headless;
+mod layout;
+mod query;
 mod scale;
 mod widgets;
 #[macro_use]
@@ -14,6 +17,8 @@ pub mod keyed_jobs;
 pub use event::Event;
 pub use graphic::{Graphic, Id as GraphicId, Rotation, SampleStrat, Transform};
 pub use keyed_jobs::KeyedJobs;
+pub use layout::UiLayout;
+pub use query::WidgetQuery;
 pub use scale::{Scale, ScaleMode};
 pub use widgets::{
     image_frame::ImageFrame,
@@ -41,19 +46,17 @@ use cache::Cache;
 use common::{slowjob::SlowJobPool, util::srgba_to_linear};
 use common_base::span;
 use conrod_core::{
-    event::Input,
     graph::{self, Graph},
     image::{Id as ImageId, Map},
-    input::{touch::Touch, Motion, Widget},
+    input::Widget,
     render::{Primitive, PrimitiveKind},
     text::{self, font},
     widget::{self, id::Generator},
-    Rect, Scalar, UiBuilder, UiCell,
+    Rect, Scalar, UiCell,
 };
 use core::{convert::TryInto, f32, f64, ops::Range};
 use graphic::TexId;
 use hashbrown::hash_map::Entry;
-use std::time::Duration;
 use tracing::{error, warn};
 use vek::*;
 
@@ -103,8 +106,17 @@ impl DrawCommand {
     }
 }
 
+/// Registers the graphics and fonts widgets refer to. Implemented by the [`Ui`]
+/// and by the headless ui of the ui tests, so image and font ids can be loaded
+/// into both.
+pub trait UiResources {
+    fn add_graphic(&mut self, graphic: Graphic) -> ImageId;
+    fn add_graphic_with_rotations(&mut self, graphic: Graphic) -> img_ids::Rotations;
+    fn new_font(&mut self, font: ice::RawFont) -> font::Id;
+}
+
 pub struct Ui {
-    pub ui: conrod_core::Ui,
+    pub layout: UiLayout,
     image_map: Map<(graphic::Id, Rotation)>,
     cache: Cache,
     // Draw commands for the next render
@@ -118,20 +130,10 @@ pub struct Ui {
     interface_locals: UiBoundLocals,
     // Consts to specify positions of ingame elements (e.g. Nametags)
     ingame_locals: Vec<UiBoundLocals>,
-    // Whether the window was resized since the last maintain, for updating scaling
-    window_resized: bool,
-    // Scale factor changed
-    scale_factor_changed: Option<f64>,
     // Used to delay cache resizing until after current frame is drawn
     need_cache_resize: bool,
     // Whether a graphic was replaced with replaced_graphic since the last maintain call
     graphic_replaced: bool,
-    // Scaling of the ui
-    scale: Scale,
-    // Tooltips
-    tooltip_manager: TooltipManager,
-    // Item tooltips manager
-    item_tooltip_manager: ItemTooltipManager,
     // Scissor for the whole window
     window_scissor: Aabr<u16>,
 }
@@ -141,38 +143,11 @@ impl Ui {
         let scale_factor = window.scale_factor();
         let renderer = window.renderer_mut();
         let physical_resolution = renderer.resolution();
-        let scale = Scale::new(
-            physical_resolution,
-            scale_factor,
-            ScaleMode::Absolute(1.0),
-            1.0,
-        );
-
-        let win_dims = scale.scaled_resolution().into_array();
-
-        let mut ui = UiBuilder::new(win_dims).build();
-        // NOTE: Since we redraw the actual frame each time whether or not the UI needs
-        // to be updated, there's no reason to set the redraw count higher than
-        // 1.
-        ui.set_num_redraw_frames(1);
-
-        let item_tooltip_manager = ItemTooltipManager::new(
-            Duration::from_millis(1),
-            Duration::from_millis(0),
-            scale.scale_factor_logical(),
-        );
-
-        let tooltip_manager = TooltipManager::new(
-            ui.widget_id_generator(),
-            Duration::from_millis(1),
-            Duration::from_millis(0),
-            scale.scale_factor_logical(),
-        );
 
         let interface_locals = renderer.create_ui_bound_locals(&[UiLocals::default()]);
 
         Ok(Self {
-            ui,
+            layout: UiLayout::new(physical_resolution, scale_factor),
             image_map: Map::new(),
             cache: Cache::new(renderer)?,
             draw_commands: Vec::new(),
@@ -180,33 +155,25 @@ impl Ui {
             model: renderer.create_dynamic_model(100),
             interface_locals,
             ingame_locals: Vec::new(),
-            window_resized: false,
-            scale_factor_changed: None,
             need_cache_resize: false,
             graphic_replaced: false,
-            scale,
-            tooltip_manager,
-            item_tooltip_manager,
             window_scissor: default_scissor(physical_resolution),
         })
     }
 
     // Set the scaling mode of the ui.
     pub fn set_scaling_mode(&mut self, mode: ScaleMode) {
-        self.scale.set_scaling_mode(mode);
         // To clear the cache (it won't be resized in this case)
         self.need_cache_resize = true;
-        // Give conrod the new size.
-        let (w, h) = self.scale.scaled_resolution().into_tuple();
-        self.ui.handle_event(Input::Resize(w, h));
+        self.layout.set_scaling_mode(mode);
     }
 
     pub fn scale_factor_changed(&mut self, scale_factor: f64) {
-        self.scale_factor_changed = Some(scale_factor);
+        self.layout.scale_factor_changed(scale_factor);
     }
 
     // Get a copy of Scale
-    pub fn scale(&self) -> Scale { self.scale }
+    pub fn scale(&self) -> Scale { self.layout.scale() }
 
     pub fn add_graphic(&mut self, graphic: Graphic) -> ImageId {
         self.image_map
@@ -245,80 +212,38 @@ impl Ui {
 
     pub fn new_font(&mut self, font: crate::ui::ice::RawFont) -> font::Id {
         let font = text::Font::from_bytes(font.0).unwrap();
-        self.ui.fonts.insert(font)
+        self.layout.ui.fonts.insert(font)
     }
 
-    pub fn id_generator(&mut self) -> Generator { self.ui.widget_id_generator() }
+    pub fn id_generator(&mut self) -> Generator { self.layout.id_generator() }
 
     pub fn set_widgets(&mut self) -> (UiCell, &mut ItemTooltipManager, &mut TooltipManager) {
-        (
-            self.ui.set_widgets(),
-            &mut self.item_tooltip_manager,
-            &mut self.tooltip_manager,
-        )
+        self.layout.set_widgets()
     }
 
     pub fn set_item_widgets(&mut self) -> (UiCell, &mut ItemTooltipManager) {
-        (self.ui.set_widgets(), &mut self.item_tooltip_manager)
+        self.layout.set_item_widgets()
     }
 
     // Accepts Option so widget can be unfocused.
-    pub fn focus_widget(&mut self, id: Option<widget::Id>) {
-        self.ui.keyboard_capture(match id {
-            Some(id) => id,
-            None => self.ui.window,
-        });
-    }
+    pub fn focus_widget(&mut self, id: Option<widget::Id>) { self.layout.focus_widget(id); }
 
     // Get id of current widget capturing keyboard.
     pub fn widget_capturing_keyboard(&self) -> Option<widget::Id> {
-        self.ui.global_input().current.widget_capturing_keyboard
+        self.layout.widget_capturing_keyboard()
     }
 
     // Get whether a widget besides the window is capturing the mouse.
-    pub fn no_widget_capturing_mouse(&self) -> bool {
-        self.ui
-            .global_input()
-            .current
-            .widget_capturing_mouse
-            .filter(|id| id != &self.ui.window)
-            .is_none()
-    }
+    pub fn no_widget_capturing_mouse(&self) -> bool { self.layout.no_widget_capturing_mouse() }
 
     // Get the widget graph.
-    pub fn widget_graph(&self) -> &Graph { self.ui.widget_graph() }
+    pub fn widget_graph(&self) -> &Graph { self.layout.widget_graph() }
 
-    pub fn handle_event(&mut self, event: Event) {
-        match event.0 {
-            Input::Resize(w, h) => {
-                if w > 0.0 && h > 0.0 {
-                    self.window_resized = true;
-                }
-            },
-            Input::Touch(touch) => self.ui.handle_event(Input::Touch(Touch {
-                xy: self.scale.scale_point(touch.xy.into()).into_array(),
-                ..touch
-            })),
-            Input::Motion(motion) => self.ui.handle_event(Input::Motion(match motion {
-                Motion::MouseCursor { x, y } => {
-                    let (x, y) = self.scale.scale_point(Vec2::new(x, y)).into_tuple();
-                    Motion::MouseCursor { x, y }
-                },
-                Motion::MouseRelative { x, y } => {
-                    let (x, y) = self.scale.scale_point(Vec2::new(x, y)).into_tuple();
-                    Motion::MouseRelative { x, y }
-                },
-                Motion::Scroll { x, y } => {
-                    let (x, y) = self.scale.scale_point(Vec2::new(x, y)).into_tuple();
-                    Motion::Scroll { x, y }
-                },
-                _ => motion,
-            })),
-            _ => self.ui.handle_event(event.0),
-        }
-    }
+    pub fn query(&self) -> WidgetQuery { self.layout.query() }
+
+    pub fn handle_event(&mut self, event: Event) { self.layout.handle_event(event); }
 
-    pub fn widget_input(&self, id: widget::Id) -> Widget { self.ui.widget_input(id) }
+    pub fn widget_input(&self, id: widget::Id) -> Widget { self.layout.widget_input(id) }
 
     pub fn maintain(
         &mut self,
@@ -327,48 +252,15 @@ impl Ui {
         view_projection_mat: Option<Mat4<f32>>,
     ) {
         span!(_guard, "maintain", "Ui::maintain");
-        // Maintain tooltip manager
-        self.tooltip_manager
-            .maintain(self.ui.global_input(), self.scale.scale_factor_logical());
-
-        // Maintain tooltip manager
-        self.item_tooltip_manager
-            .maintain(self.ui.global_input(), self.scale.scale_factor_logical());
-
-        // Handle scale factor changing
-        let need_resize = if let Some(scale_factor) = self.scale_factor_changed.take() {
-            self.scale.scale_factor_changed(scale_factor)
-        } else {
-            false
-        };
-
         // Used to tell if we need to clear out the draw commands (which contain scissor
         // commands that can be invalidated by this change)
-        let physical_resolution_changed = renderer.resolution() != self.scale.physical_resolution();
-
-        // Handle window resizing.
-        let need_resize = if self.window_resized {
-            self.window_resized = false;
-            let surface_resolution = renderer.resolution();
-            let (old_w, old_h) = self.scale.scaled_resolution().into_tuple();
-            self.scale.surface_resized(surface_resolution);
-            let (w, h) = self.scale.scaled_resolution().into_tuple();
-            self.ui.handle_event(Input::Resize(w, h));
-            self.window_scissor = default_scissor(surface_resolution);
-
-            // Avoid panic in graphic cache when minimizing.
-            // Avoid resetting cache if window size didn't change
-            // Somewhat inefficient for elements that won't change size after a window
-            // resize
-            let res = renderer.resolution();
-            res.x > 0 && res.y > 0 && !(old_w == w && old_h == h)
-        } else {
-            false
-        } || need_resize;
+        let physical_resolution_changed =
+            renderer.resolution() != self.layout.scale().physical_resolution();
 
-        if need_resize {
+        if self.layout.maintain(renderer.resolution()) {
             self.need_cache_resize = true;
         }
+        self.window_scissor = default_scissor(self.layout.scale().physical_resolution());
 
         if self.need_cache_resize {
             // Resize graphic cache
@@ -420,10 +312,10 @@ impl Ui {
             // Also redraw if the physical resolution changed since we need to regenerate
             // the invalid scissor rect commands.
             self.graphic_replaced = false;
-            self.ui.draw()
+            self.layout.ui.draw()
         } else {
             // Otherwise, redraw only if widgets were actually updated.
-            match self.ui.draw_if_changed() {
+            match self.layout.ui.draw_if_changed() {
                 Some(primitives) => primitives,
                 None => return,
             }
@@ -438,8 +330,8 @@ impl Ui {
             )
         };
 
-        let ui = &self.ui;
-        let p_scale_factor = self.scale.scale_factor_physical();
+        let ui = &self.layout.ui;
+        let p_scale_factor = self.layout.scale().scale_factor_physical();
         // Functions for converting for conrod scalar coords to GL vertex coords (-1.0
         // to 1.0).
         let (ui_win_w, ui_win_h) = (ui.win_w, ui.win_h);
@@ -594,7 +486,7 @@ impl Ui {
                 text_cache.clear();
                 glyph_cache.clear();
                 glyph_cache.clear_queue();
-                self.ui.needs_redraw();
+                self.layout.ui.needs_redraw();
                 warn!("Could not recache queued glyphs, skipping frame.");
             } else {
                 // NOTE: If this is the first round after encountering a new glyph, we just
@@ -631,7 +523,7 @@ impl Ui {
                     // offending glyph or glyph set.  We then exit the loop and don't try to
                     // rerender the frame.
                     glyph_cache.clear_queue();
-                    self.ui.needs_redraw();
+                    self.layout.ui.needs_redraw();
                 } else {
                     // Successfully cached, so repeat the loop.
                     *retry = true;
@@ -689,7 +581,7 @@ impl Ui {
             // Check for a change in the scissor.
             let new_scissor = {
                 let (l, b, w, h) = scizzor.l_b_w_h();
-                let scale_factor = self.scale.scale_factor_physical();
+                let scale_factor = self.layout.scale().scale_factor_physical();
                 // Calculate minimum x and y coordinates while
                 // flipping y axis (from +up to +down) and
                 // moving origin to top-left corner (from middle).
@@ -1080,6 +972,16 @@ impl Ui {
     }
 }
 
+impl UiResources for Ui {
+    fn add_graphic(&mut self, graphic: Graphic) -> ImageId { Ui::add_graphic(self, graphic) }
+
+    fn add_graphic_with_rotations(&mut self, graphic: Graphic) -> img_ids::Rotations {
+        Ui::add_graphic_with_rotations(self, graphic)
+    }
+
+    fn new_font(&mut self, font: ice::RawFont) -> font::Id { Ui::new_font(self, font) }
+}
+
 fn default_scissor(physical_resolution: Vec2<u32>) -> Aabr<u16> {
     let (screen_w, screen_h) = physical_resolution.into_tuple();
     Aabr {
@@ -0,0 +1,101 @@
+use conrod_core::{widget, Rect, Widget};
+use std::{any::TypeId, cmp::Ordering};
+
+/// Read-only view of the widget tree as it was laid out in the last frame.
+/// Used by the hud to inspect the input state and by the ui tests to check
+/// positions and visibility of widgets.
+#[derive(Clone, Copy)]
+pub struct WidgetQuery<'a> {
+    ui: &'a conrod_core::Ui,
+}
+
+impl<'a> WidgetQuery<'a> {
+    pub fn new(ui: &'a conrod_core::Ui) -> Self { Self { ui } }
+
+    /// Whether the widget was set in the last frame
+    pub fn is_set(&self, id: widget::Id) -> bool { self.ui.updated_widgets().contains_key(&id) }
+
+    /// Area of the widget, `None` if it wasn't set in the last frame
+    pub fn rect(&self, id: widget::Id) -> Option<Rect> {
+        if self.is_set(id) {
+            self.ui.rect_of(id)
+        } else {
+            None
+        }
+    }
+
+    /// Part of the widget that isn't cropped away by its parents, `None` if it
+    /// is hidden entirely
+    pub fn visible_rect(&self, id: widget::Id) -> Option<Rect> {
+        if self.is_set(id) {
+            self.ui.visible_area(id)
+        } else {
+            None
+        }
+    }
+
+    pub fn is_visible(&self, id: widget::Id) -> bool { self.visible_rect(id).is_some() }
+
+    /// Whether the visible areas of both widgets overlap
+    pub fn overlap(&self, a: widget::Id, b: widget::Id) -> bool {
+        match (self.visible_rect(a), self.visible_rect(b)) {
+            (Some(a), Some(b)) => a.overlap(b).map_or(false, |o| o.w() > 0.0 && o.h() > 0.0),
+            _ => false,
+        }
+    }
+
+    /// All widgets of type `W` set in the last frame, in reading order (top to
+    /// bottom, then left to right)
+    pub fn of_type<W: Widget>(&self) -> Vec<widget::Id> {
+        let type_id = TypeId::of::<<W as Widget>::State>();
+        let graph = self.ui.widget_graph();
+        let mut ids = self
+            .ui
+            .updated_widgets()
+            .keys()
+            .copied()
+            .filter(|id| graph.widget(*id).map_or(false, |w| w.type_id == type_id))
+            .filter_map(|id| Some((id, self.ui.rect_of(id)?)))
+            .collect::<Vec<_>>();
+        ids.sort_by(|(_, a), (_, b)| {
+            b.y()
+                .partial_cmp(&a.y())
+                .unwrap_or(Ordering::Equal)
+                .then_with(|| a.x().partial_cmp(&b.x()).unwrap_or(Ordering::Equal))
+        });
+        ids.into_iter().map(|(id, _)| id).collect()
+    }
+
+    /// String shown by a `Text` widget
+    pub fn text(&self, id: widget::Id) -> Option<&'a str> {
+        self.ui
+            .widget_graph()
+            .widget(id)?
+            .unique_widget_state::<widget::Text>()
+            .map(|text| text.state.string.as_str())
+    }
+
+    /// First `Text` widget in reading order showing exactly `text`, this
+    /// includes the labels of buttons
+    pub fn find_text(&self, text: &str) -> Option<widget::Id> {
+        self.of_type::<widget::Text>()
+            .into_iter()
+            .find(|id| self.text(*id) == Some(text))
+    }
+
+    pub fn capturing_keyboard(&self) -> Option<widget::Id> {
+        self.ui.global_input().current.widget_capturing_keyboard
+    }
+
+    /// The widget the mouse is over, this is the one that receives clicks
+    pub fn under_mouse(&self) -> Option<widget::Id> {
+        self.ui.global_input().current.widget_under_mouse
+    }
+
+    /// Whether a widget of type `W` has captured the keyboard
+    pub fn is_captured<W: Widget>(&self) -> bool {
+        self.capturing_keyboard()
+            .and_then(|id| self.ui.widget_graph().widget(id))
+            .map_or(false, |c| c.type_id == TypeId::of::<<W as Widget>::State>())
+    }
+}
@@ -763,6 +763,8 @@ impl<'a> Widget for ItemTooltip<'a> {
                         let energy_reward = armor.energy_reward().map(|x| x * 100.0).unwrap_or(0.0);
                         let crit_power = armor.crit_power().unwrap_or(0.0);
                         let stealth = armor.stealth().unwrap_or(0.0);
+                        let fire_resistance = armor.fire_resistance().unwrap_or(0.0);
+                        let poison_resistance = armor.poison_resistance().unwrap_or(0.0);
 
                         widget::Text::new(&util::protec2string(protection))
                             .graphics_for(id)
@@ -926,6 +928,82 @@ impl<'a> Widget for ItemTooltip<'a> {
                             );
                         }
 
+                        // Fire Resistance
+                        if armor.stats.fire_resistance().is_some() {
+                            widget::Text::new(&format!(
+                                "{} : {:.0}%",
+                                i18n.get("common.stats.fire_resistance"),
+                                fire_resistance * 100.0
+                            ))
+                            .graphics_for(id)
+                            .parent(id)
+                            .with_style(self.style.desc)
+                            .color(text_color)
+                            .and(|t| {
+                                match armor.stats.poise_resilience().is_some() as usize
+                                    + armor.stats.energy_max().is_some() as usize
+                                    + armor.stats.energy_reward().is_some() as usize
+                                    + armor.stats.crit_power().is_some() as usize
+                                    + armor.stats.stealth().is_some() as usize
+                                {
+                                    0 => t
+                                        .x_align_to(
+                                            state.ids.item_frame,
+                                            conrod_core::position::Align::Start,
+                                        )
+                                        .down_from(state.ids.item_frame, V_PAD),
+                                    x => t.down_from(state.ids.stats[x - 1], V_PAD_STATS),
+                                }
+                            })
+                            .set(
+                                state.ids.stats[armor.stats.poise_resilience().is_some() as usize
+                                    + armor.stats.energy_max().is_some() as usize
+                                    + armor.stats.energy_reward().is_some() as usize
+                                    + armor.stats.crit_power().is_some() as usize
+                                    + armor.stats.stealth().is_some() as usize],
+                                ui,
+                            );
+                        }
+
+                        // Poison Resistance
+                        if armor.stats.poison_resistance().is_some() {
+                            widget::Text::new(&format!(
+                                "{} : {:.0}%",
+                                i18n.get("common.stats.poison_resistance"),
+                                poison_resistance * 100.0
+                            ))
+                            .graphics_for(id)
+                            .parent(id)
+                            .with_style(self.style.desc)
+                            .color(text_color)
+                            .and(|t| {
+                                match armor.stats.poise_resilience().is_some() as usize
+                                    + armor.stats.energy_max().is_some() as usize
+                                    + armor.stats.energy_reward().is_some() as usize
+                                    + armor.stats.crit_power().is_some() as usize
+                                    + armor.stats.stealth().is_some() as usize
+                                    + armor.stats.fire_resistance().is_some() as usize
+                                {
+                                    0 => t
+                                        .x_align_to(
+                                            state.ids.item_frame,
+                                            conrod_core::position::Align::Start,
+                                        )
+                                        .down_from(state.ids.item_frame, V_PAD),
+                                    x => t.down_from(state.ids.stats[x - 1], V_PAD_STATS),
+                                }
+                            })
+                            .set(
+                                state.ids.stats[armor.stats.poise_resilience().is_some() as usize
+                                    + armor.stats.energy_max().is_some() as usize
+                                    + armor.stats.energy_reward().is_some() as usize
+                                    + armor.stats.crit_power().is_some() as usize
+                                    + armor.stats.stealth().is_some() as usize
+                                    + armor.stats.fire_resistance().is_some() as usize],
+                                ui,
+                            );
+                        }
+
                         // Slots
                         if item.num_slots() > 0 {
                             widget::Text::new(&format!(
@@ -943,6 +1021,8 @@ impl<'a> Widget for ItemTooltip<'a> {
                                     + armor.stats.energy_reward().is_some() as usize
                                     + armor.stats.crit_power().is_some() as usize
                                     + armor.stats.stealth().is_some() as usize
+                                    + armor.stats.fire_resistance().is_some() as usize
+                                    + armor.stats.poison_resistance().is_some() as usize
                                 {
                                     0 => t
                                         .x_align_to(
@@ -958,7 +1038,9 @@ impl<'a> Widget for ItemTooltip<'a> {
                                     + armor.stats.energy_max().is_some() as usize
                                     + armor.stats.energy_reward().is_some() as usize
                                     + armor.stats.crit_power().is_some() as usize
-                                    + armor.stats.stealth().is_some() as usize],
+                                    + armor.stats.stealth().is_some() as usize
+                                    + armor.stats.fire_resistance().is_some() as usize
+                                    + armor.stats.poison_resistance().is_some() as usize],
                                 ui,
                             );
                         }
@@ -990,6 +1072,14 @@ impl<'a> Widget for ItemTooltip<'a> {
                         );
                         let stealth_diff =
                             util::option_comparison(&armor.stealth(), &equipped_armor.stealth());
+                        let fire_resistance_diff = util::option_comparison(
+                            &armor.fire_resistance(),
+                            &equipped_armor.fire_resistance(),
+                        );
+                        let poison_resistance_diff = util::option_comparison(
+                            &armor.poison_resistance(),
+                            &equipped_armor.poison_resistance(),
+                        );
 
                         if let Some(p_diff) = diff.protection() {
                             if p_diff != Protection::Normal(0.0) {
@@ -1075,6 +1165,45 @@ impl<'a> Widget for ItemTooltip<'a> {
                                 )
                             }
                         }
+
+                        if let Some(f_res_diff) = diff.fire_resistance() {
+                            if f_res_diff != 0.0_f32 {
+                                let text = format!(
+                                    "{} {:.0}%",
+                                    &fire_resistance_diff.0,
+                                    f_res_diff * 100.0
+                                );
+                                diff_text(
+                                    text,
+                                    fire_resistance_diff.1,
+                                    armor.stats.poise_resilience().is_some() as usize
+                                        + armor.stats.energy_max().is_some() as usize
+                                        + armor.stats.energy_reward().is_some() as usize
+                                        + armor.stats.crit_power().is_some() as usize
+                                        + armor.stats.stealth().is_some() as usize,
+                                )
+                            }
+                        }
+
+                        if let Some(p_res_diff) = diff.poison_resistance() {
+                            if p_res_diff != 0.0_f32 {
+                                let text = format!(
+                                    "{} {:.0}%",
+                                    &poison_resistance_diff.0,
+                                    p_res_diff * 100.0
+                                );
+                                diff_text(
+                                    text,
+                                    poison_resistance_diff.1,
+                                    armor.stats.poise_resilience().is_some() as usize
+                                        + armor.stats.energy_max().is_some() as usize
+                                        + armor.stats.energy_reward().is_some() as usize
+                                        + armor.stats.crit_power().is_some() as usize
+                                        + armor.stats.stealth().is_some() as usize
+                                        + armor.stats.fire_resistance().is_some() as usize,
+                                )
+                            }
+                        }
                     }
                 }
             },
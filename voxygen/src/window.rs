@@ -104,9 +104,32 @@ pub enum KeyMouse {
     Key(winit::event::VirtualKeyCode),
     Mouse(winit::event::MouseButton),
     ScanKey(winit::event::ScanCode),
+    /// A key pressed while holding modifiers (e.g. Ctrl + K)
+    KeyChord(winit::event::ModifiersState, winit::event::VirtualKeyCode),
 }
 
 impl KeyMouse {
+    /// Returns the binding for `key` pressed while holding `modifiers`.
+    /// Modifier keys themselves are never turned into a chord.
+    pub fn with_modifiers(
+        modifiers: winit::event::ModifiersState,
+        key: winit::event::VirtualKeyCode,
+    ) -> Self {
+        if modifiers.is_empty() || Self::is_modifier_key(key) {
+            KeyMouse::Key(key)
+        } else {
+            KeyMouse::KeyChord(modifiers, key)
+        }
+    }
+
+    pub fn is_modifier_key(key: winit::event::VirtualKeyCode) -> bool {
+        use winit::event::VirtualKeyCode::*;
+        matches!(
+            key,
+            LShift | RShift | LControl | RControl | LAlt | RAlt | LWin | RWin
+        )
+    }
+
     /// Returns key description (e.g Left Shift)
     pub fn display_string(&self, key_layout: &Option<KeyLayout>) -> String {
         use self::KeyMouse::*;
@@ -339,6 +362,22 @@ impl KeyMouse {
                     return format!("Unknown (0x{:X})", scancode);
                 }
             },
+            KeyChord(modifiers, key) => {
+                let mut chord = String::new();
+                for (held, name) in [
+                    (modifiers.ctrl(), "Ctrl"),
+                    (modifiers.alt(), "Alt"),
+                    (modifiers.shift(), "Shift"),
+                    (modifiers.logo(), "Super"),
+                ] {
+                    if held {
+                        chord.push_str(name);
+                        chord.push_str(" + ");
+                    }
+                }
+                chord.push_str(&Key(*key).display_string(key_layout));
+                return chord;
+            },
         };
 
         key_string.to_owned()
@@ -384,6 +423,9 @@ pub struct Window {
     scale_factor: f64,
     needs_refresh_resize: bool,
     keypress_map: HashMap<GameInput, winit::event::ElementState>,
+    // Binding each currently held key was resolved to when it got pressed, so that
+    // a chord is released properly even if its modifier was let go first
+    pressed_keys: HashMap<winit::event::VirtualKeyCode, KeyMouse>,
     pub remapping_keybindings: Option<GameInput>,
     //true for remapping keybinds, false for clearing keybinds
     pub keybinding_mode: bool,
@@ -426,7 +468,12 @@ impl Window {
 
         let window = win_builder.build(&event_loop).unwrap();
 
-        let renderer = Renderer::new(&window, settings.graphics.render_mode.clone(), runtime)?;
+        let renderer = Renderer::new(
+            &window,
+            settings.graphics.render_mode.clone(),
+            settings.graphics.adapter.as_deref(),
+            runtime,
+        )?;
 
         let keypress_map = HashMap::new();
 
@@ -487,6 +534,7 @@ impl Window {
             resized: false,
             needs_refresh_resize: false,
             keypress_map,
+            pressed_keys: HashMap::new(),
             remapping_keybindings: None,
             keybinding_mode: true,
             events: Vec::new(),
@@ -885,7 +933,26 @@ impl Window {
                 }
 
                 let input_key = match input.virtual_keycode {
-                    Some(key) => KeyMouse::Key(key),
+                    Some(key) if self.remapping_keybindings.is_some() => {
+                        // A modifier is only bound on its own if it gets released without
+                        // another key being pressed, otherwise it's part of a chord
+                        if KeyMouse::is_modifier_key(key)
+                            && input.state == winit::event::ElementState::Pressed
+                        {
+                            return;
+                        }
+                        KeyMouse::with_modifiers(self.modifiers, key)
+                    },
+                    Some(key) => match input.state {
+                        winit::event::ElementState::Pressed => {
+                            let key_mouse = controls.resolve_key(self.modifiers, key);
+                            self.pressed_keys.insert(key, key_mouse);
+                            key_mouse
+                        },
+                        winit::event::ElementState::Released => {
+                            self.pressed_keys.remove(&key).unwrap_or(KeyMouse::Key(key))
+                        },
+                    },
                     None => KeyMouse::ScanKey(input.scancode),
                 };
 
@@ -41,6 +41,7 @@ use crate::{
 use common::{
     assets::{self, AssetExt},
     calendar::Calendar,
+    comp::ThermalColumn,
     grid::Grid,
     lottery::Lottery,
     spiral::Spiral2d,
@@ -84,6 +85,10 @@ const DEFAULT_WORLD_CHUNKS_LG: MapSizeLg =
         panic!("Default world chunk size does not satisfy required invariants.");
     };
 
+/// Chance of a chunk with warm, open ground to have a thermal column rising
+/// from it.
+const THERMAL_CHANCE: f32 = 0.02;
+
 /// A structure that holds cached noise values and cumulative distribution
 /// functions for the input that led to those values.  See the definition of
 /// InverseCdf for a description of how to interpret the types of its fields.
@@ -1567,9 +1572,35 @@ impl WorldSim {
             horizons,
             sites: Vec::new(), // Will be substituted later
             pois: Vec::new(),  // Will be substituted later
+            thermals: self.get_thermals(),
         }
     }
 
+    /// Thermal columns rise from warm, dry and open land, they are stronger
+    /// over hotter ground.
+    pub fn get_thermals(&self) -> Vec<ThermalColumn> {
+        let rng = RandomField::new(self.seed);
+        self.chunks
+            .iter()
+            .enumerate()
+            .filter_map(|(posi, chunk)| {
+                let chunk_pos = uniform_idx_as_vec2(self.map_size_lg(), posi);
+                let warm_ground = chunk.temp > CONFIG.temperate_temp
+                    && chunk.humidity < CONFIG.forest_hum
+                    && chunk.tree_density < 0.5
+                    && !chunk.is_underwater();
+                (warm_ground && rng.chance(chunk_pos.with_z(0), THERMAL_CHANCE)).then(|| {
+                    let roll = rng.get_f32(chunk_pos.with_z(1));
+                    ThermalColumn {
+                        center: TerrainChunkSize::center_wpos(chunk_pos).map(|e| e as f32),
+                        radius: 24.0 + 24.0 * roll,
+                        updraft: 3.0 + 5.0 * (chunk.temp - CONFIG.temperate_temp).min(1.0),
+                    }
+                })
+            })
+            .collect()
+    }
+
     pub fn generate_cliffs(&mut self) {
         let mut rng = self.rng.clone();
 